@@ -0,0 +1,67 @@
+use anyhow::Result;
+use promptpro::{PromptVault, Variant, VariantDecider, VariantRequest, VersionSelector};
+use std::collections::HashSet;
+
+/// Stand-in for a LaunchDarkly/Unleash SDK client.
+///
+/// A real client would evaluate the flag remotely (or from its local cache)
+/// using targeting rules configured in the flag dashboard.
+struct FlagClient {
+    enabled_users: HashSet<String>,
+}
+
+impl FlagClient {
+    fn is_enabled(&self, _flag: &str, user_id: &str) -> bool {
+        self.enabled_users.contains(user_id)
+    }
+}
+
+/// Adapter that lets the flag service decide which prompt variant a user gets,
+/// while promptpro keeps serving the prompt content itself.
+struct FlagDecider {
+    client: FlagClient,
+}
+
+impl VariantDecider for FlagDecider {
+    fn decide(&self, request: &VariantRequest) -> Result<Variant> {
+        let flag = format!("prompt-{}-{}", request.key, request.candidate);
+        if self.client.is_enabled(&flag, request.request_id) {
+            Ok(Variant::Candidate)
+        } else {
+            Ok(Variant::Stable)
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let client = FlagClient {
+        enabled_users: HashSet::from(["beta-tester".to_string()]),
+    };
+    let vault = PromptVault::open(dir.path())?.with_variant_decider(FlagDecider { client });
+
+    vault.add("summarization", "Summarize the following text.")?;
+    vault.update(
+        "summarization",
+        "Summarize the following text in three bullet points.",
+        Some("Bullet point format".to_string()),
+    )?;
+    vault.tag("summarization", "prod", 1)?;
+    vault.tag("summarization", "next", 2)?;
+
+    for user in ["alice", "beta-tester"] {
+        let prompt = vault.get(
+            "summarization",
+            VersionSelector::Canary {
+                stable: "prod".to_string(),
+                candidate: "next".to_string(),
+                percent: 5,
+                request_id: user.to_string(),
+            },
+        )?;
+        println!("{}: {}", user, prompt);
+    }
+
+    println!("Served variants: {:?}", vault.canary_counts("summarization")?);
+    Ok(())
+}
@@ -21,7 +21,7 @@ async fn main() -> Result<()> {
     println!("Latest prompt: {}", latest);
 
     let stable = pm
-        .get_prompt("summarization", VersionSelector::Tag("stable"))
+        .get_prompt("summarization", VersionSelector::tag("stable"))
         .await?;
     println!("Stable prompt: {}", stable);
 
@@ -31,7 +31,7 @@ async fn main() -> Result<()> {
     println!("✅ Vault backup done");
 
     let dev_prompt = pm
-        .get_prompt("pc_operator_v2", VersionSelector::Tag("dev"))
+        .get_prompt("pc_operator_v2", VersionSelector::tag("dev"))
         .await?;
 
     println!("Dev prompt: {}", dev_prompt);
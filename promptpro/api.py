@@ -22,6 +22,8 @@ class VersionMeta:
         object_hash: The hash of the prompt content
         snapshot: Whether this version is stored as a snapshot (True) or diff (False)
         tags: List of tags associated with this version
+        author: Who stored the version, or None when unknown
+        validated: Whether the version passed the validators, or None when none ran
     """
 
     def __init__(self, rust_meta):
@@ -35,6 +37,8 @@ class VersionMeta:
         self.object_hash = rust_meta.object_hash
         self.snapshot = rust_meta.snapshot
         self.tags = rust_meta.tags
+        self.author = rust_meta.author
+        self.validated = rust_meta.validated
 
     def __repr__(self):
         return f"VersionMeta(key={self.key}, version={self.version}, tags={self.tags})"
@@ -173,6 +177,19 @@ class PromptVault:
         vault._rust_vault = rust_vault
         return vault
 
+    @staticmethod
+    def in_memory():
+        """
+        Create an empty vault in memory, e.g. for tests. It uses no vault
+        directory or HOME and is gone once it is garbage collected.
+
+        Returns:
+            A new PromptVault instance
+        """
+        vault = PromptVault.__new__(PromptVault)
+        vault._rust_vault = PyPromptVault.in_memory()
+        return vault
+
     @staticmethod
     def restore_or_default(input_path: str, password: Optional[str] = None):
         """
@@ -242,6 +259,18 @@ class DefaultPromptManager:
         manager._rust_manager = rust_manager
         return manager
 
+    @staticmethod
+    def in_memory():
+        """
+        Create a manager of an empty vault in memory, e.g. for tests.
+
+        Returns:
+            A new DefaultPromptManager instance
+        """
+        manager = DefaultPromptManager.__new__(DefaultPromptManager)
+        manager._rust_manager = PySyncPromptManager.in_memory()
+        return manager
+
     def add(self, key: str, content: str):
         """
         Add a new prompt.
@@ -0,0 +1,118 @@
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Where an adopted prompt came from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// Vault the prompt was copied from
+    pub source: String,
+    /// Latest source version at the time of adoption
+    pub source_version: u64,
+    /// Whether the full version history was copied
+    pub with_history: bool,
+    pub adopted_at: DateTime<Utc>,
+}
+
+fn provenance_key(key: &str) -> String {
+    format!("provenance:{}", key)
+}
+
+impl PromptVault {
+    /// Copy a prompt from another vault into this one, recording where it came from.
+    ///
+    /// With `with_history`, every version is copied with its original timestamps,
+    /// messages and tags; otherwise only the latest content is adopted as version 1.
+    /// `source_name` is recorded as the provenance of the copy.
+    pub fn adopt(
+        &self,
+        source: &PromptVault,
+        source_name: &str,
+        key: &str,
+        with_history: bool,
+    ) -> Result<Provenance> {
+        if self.get_latest_version_number(key)?.is_some() {
+            return Err(anyhow::anyhow!("Prompt with key '{}' already exists", key));
+        }
+        if source.is_sensitive(key)? {
+            return Err(anyhow::anyhow!(
+                "Prompt '{}' is password-protected; unprotect it before adopting",
+                key
+            ));
+        }
+
+        let history = source.history(key)?;
+        let latest = history
+            .last()
+            .ok_or_else(|| anyhow::anyhow!("Prompt with key '{}' does not exist", key))?
+            .clone();
+
+        if with_history {
+            self.copy_versions(source, key, key)?;
+        } else {
+            let content = source.get(key, VersionSelector::Version(latest.version))?;
+            self.add(key, &content)?;
+        }
+
+        let provenance = Provenance {
+            source: source_name.to_string(),
+            source_version: latest.version,
+            with_history,
+            adopted_at: Utc::now(),
+        };
+        self.set_provenance(key, &provenance)?;
+        Ok(provenance)
+    }
+
+    /// Record where a prompt came from, e.g. the package it was installed from
+    pub(crate) fn set_provenance(&self, key: &str, provenance: &Provenance) -> Result<()> {
+        self.db().insert(provenance_key(key).as_bytes(), bincode::serialize(provenance)?)?;
+        Ok(())
+    }
+
+    /// Provenance of a prompt adopted from another vault, if any
+    pub fn provenance(&self, key: &str) -> Result<Option<Provenance>> {
+        match self.db().get(provenance_key(key).as_bytes())? {
+            Some(bytes) => Ok(Some(bincode::deserialize(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_adopt() -> Result<()> {
+        let dir = tempdir()?;
+        let personal = PromptVault::open(dir.path().join("personal"))?;
+        let project = PromptVault::open(dir.path().join("project"))?;
+
+        personal.add("writer", "draft")?;
+        personal.update("writer", "polished", Some("tone".to_string()))?;
+        personal.tag("writer", "stable", 1)?;
+
+        // Latest content only
+        let provenance = project.adopt(&personal, "personal", "writer", false)?;
+        assert_eq!(provenance.source_version, 2);
+        assert_eq!(project.history("writer")?.len(), 1);
+        assert_eq!(project.get("writer", VersionSelector::Latest)?, "polished");
+        assert_eq!(project.provenance("writer")?, Some(provenance));
+        assert!(project.adopt(&personal, "personal", "writer", false).is_err());
+
+        // Full history, including messages and tags
+        let other = PromptVault::open(dir.path().join("other"))?;
+        other.adopt(&personal, "personal", "writer", true)?;
+        let history = other.history("writer")?;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].message.as_deref(), Some("tone"));
+        assert_eq!(other.get("writer", VersionSelector::tag("stable"))?, "draft");
+        assert_eq!(other.get("writer", VersionSelector::tag("dev"))?, "polished");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! Aliases: other names for a prompt, which `promptpro serve` resolves before looking a
+//! key up, so clients keep working after a prompt is renamed.
+//!
+//! An alias names an existing key. A prompt stored under the alias's own name takes
+//! precedence over it, and deleting the prompt removes the aliases naming it.
+
+use crate::storage::PromptVault;
+use anyhow::Result;
+
+const ALIAS_PREFIX: &str = "alias:";
+
+fn alias_key(alias: &str) -> String {
+    format!("{}{}", ALIAS_PREFIX, alias)
+}
+
+impl PromptVault {
+    /// Make `alias` another name for the existing prompt `key`, replacing what the alias
+    /// named before
+    pub fn set_alias(&self, alias: &str, key: &str) -> Result<()> {
+        if alias.is_empty() {
+            anyhow::bail!("An alias cannot be empty");
+        }
+        if self.get_latest_version_number(alias)?.is_some() {
+            anyhow::bail!("'{}' is a prompt key and cannot be used as an alias", alias);
+        }
+        if self.get_latest_version_number(key)?.is_none() {
+            return Err(self.unknown_key(key));
+        }
+        self.db().insert(alias_key(alias).as_bytes(), key.as_bytes())?;
+        Ok(())
+    }
+
+    /// Remove an alias; false when there was none
+    pub fn remove_alias(&self, alias: &str) -> Result<bool> {
+        Ok(self.db().remove(alias_key(alias).as_bytes())?.is_some())
+    }
+
+    /// Every alias with the key it names, sorted by alias
+    pub fn aliases(&self) -> Result<Vec<(String, String)>> {
+        let mut aliases = Vec::new();
+        for entry in self.db().scan_prefix(ALIAS_PREFIX.as_bytes()) {
+            let (alias, key) = entry?;
+            aliases.push((
+                String::from_utf8(alias[ALIAS_PREFIX.len()..].to_vec())?,
+                String::from_utf8(key.to_vec())?,
+            ));
+        }
+        Ok(aliases)
+    }
+
+    /// The key `key` names: itself when a prompt is stored under it, otherwise the key
+    /// of the alias by that name, if any
+    pub fn resolve_alias(&self, key: &str) -> Result<String> {
+        if self.get_latest_version_number(key)?.is_some() {
+            return Ok(key.to_string());
+        }
+        match self.db().get(alias_key(key).as_bytes())? {
+            Some(target) => Ok(String::from_utf8(target.to_vec())?),
+            None => Ok(key.to_string()),
+        }
+    }
+
+    /// Remove the aliases naming `key`
+    pub(crate) fn remove_aliases_to(&self, key: &str) -> Result<()> {
+        for (entry, target) in self.scan_entries(ALIAS_PREFIX.as_bytes())? {
+            if target == key.as_bytes() {
+                self.remove_entry(&entry)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_aliases() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("team/writer", "You write.")?;
+        vault.add("team/reviewer", "You review.")?;
+
+        assert!(vault.set_alias("writer", "missing").is_err());
+        assert!(vault.set_alias("team/reviewer", "team/writer").is_err());
+        vault.set_alias("writer", "team/writer")?;
+        vault.set_alias("reviewer", "team/reviewer")?;
+        assert_eq!(vault.resolve_alias("writer")?, "team/writer");
+        assert_eq!(vault.resolve_alias("team/reviewer")?, "team/reviewer");
+        assert_eq!(vault.resolve_alias("missing")?, "missing");
+
+        // A prompt stored under the alias's name takes precedence
+        vault.add("reviewer", "You review quickly.")?;
+        assert_eq!(vault.resolve_alias("reviewer")?, "reviewer");
+
+        vault.delete_prompt_key("team/writer")?;
+        assert_eq!(vault.resolve_alias("writer")?, "writer");
+        assert!(vault.remove_alias("reviewer")?);
+        assert!(!vault.remove_alias("reviewer")?);
+        assert!(vault.aliases()?.is_empty());
+
+        Ok(())
+    }
+}
@@ -1,31 +1,66 @@
 use anyhow::Result;
-use once_cell::sync::Lazy;
+use futures_util::Stream;
+use once_cell::sync::OnceCell;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::{PromptVault, VersionSelector};
+use crate::options::{InitError, ManagerOptions};
+use crate::{PromptVault, VersionMeta, VersionSelector, VersionedPrompt};
 
 /// Default global prompt manager (singleton)
 pub struct DefaultPromptManager {
     vault: Arc<RwLock<PromptVault>>,
+    options: ManagerOptions,
 }
 
-/// Static global instance of the default manager
-static DEFAULT_MANAGER: Lazy<DefaultPromptManager> = Lazy::new(|| {
-    let vault = PromptVault::open_default().expect("Failed to open PromptPro default vault");
-    DefaultPromptManager {
-        vault: Arc::new(RwLock::new(vault)),
-    }
-});
+/// Static global instance of the default manager, set on first successful access
+static DEFAULT_MANAGER: OnceCell<DefaultPromptManager> = OnceCell::new();
 
 impl DefaultPromptManager {
-    /// Get a reference to the global singleton
+    /// Get a reference to the global singleton, initializing it on first use.
+    ///
+    /// The singleton is configured from `configure()` if it was called, otherwise from
+    /// `PROMPTPRO_VAULT`, `PROMPTPRO_DEFAULT_TAG` and `PROMPTPRO_READONLY`. A failed
+    /// initialization is returned as an error and retried on the next call.
+    pub fn try_get() -> Result<&'static Self, InitError> {
+        DEFAULT_MANAGER.get_or_try_init(|| {
+            let options = ManagerOptions::for_singleton();
+            let vault = options.open_vault()?;
+            Ok(DefaultPromptManager {
+                vault: Arc::new(RwLock::new(vault)),
+                options,
+            })
+        })
+    }
+
+    /// Get a reference to the global singleton.
+    ///
+    /// Panics if the vault cannot be opened; use `try_get()` to handle that instead.
     pub fn get() -> &'static Self {
-        &DEFAULT_MANAGER
+        match Self::try_get() {
+            Ok(manager) => manager,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Configure the singleton; must be called before the first `get()`
+    pub fn configure(options: ManagerOptions) -> Result<()> {
+        if DEFAULT_MANAGER.get().is_some() {
+            return Err(anyhow::anyhow!(
+                "DefaultPromptManager is already initialized; call configure() before get()"
+            ));
+        }
+        ManagerOptions::configure(options)
+    }
+
+    /// Options the singleton was created with
+    pub fn options(&self) -> &ManagerOptions {
+        &self.options
     }
 
     /// Add a prompt (creates if missing)
     pub async fn add(&self, key: &str, content: &str) -> Result<()> {
+        self.options.check_writable()?;
         let vault = self.vault.write().await;
         vault.add(key, content)?;
         Ok(())
@@ -33,22 +68,56 @@ impl DefaultPromptManager {
 
     /// Update a prompt version
     pub async fn update(&self, key: &str, content: &str, message: Option<&str>) -> Result<()> {
+        self.options.check_writable()?;
         let vault = self.vault.write().await;
         vault.update(key, content, message.map(|s| s.to_string()))?;
         Ok(())
     }
 
+    /// Update a prompt only if `if_version`, when given, is still its latest version,
+    /// e.g. the version [`get_versioned`](Self::get_versioned) returned; fails with a
+    /// [`VersionConflict`](crate::VersionConflict) otherwise. Returns the new version.
+    pub async fn update_if(
+        &self,
+        key: &str,
+        content: &str,
+        message: Option<&str>,
+        if_version: Option<u64>,
+    ) -> Result<u64> {
+        self.options.check_writable()?;
+        let vault = self.vault.write().await;
+        vault.update_expecting(key, content, message.map(|s| s.to_string()), if_version)
+    }
+
     /// Tag a version (e.g. stable/release/dev)
     pub async fn tag(&self, key: &str, tag: &str, version: u64) -> Result<()> {
+        self.options.check_writable()?;
         let vault = self.vault.write().await;
         vault.tag(key, tag, version)?;
         Ok(())
     }
 
+    /// Remove a tag; returns the version it pointed to
+    pub async fn untag(&self, key: &str, tag: &str) -> Result<u64> {
+        self.options.check_writable()?;
+        let vault = self.vault.write().await;
+        vault.untag(key, tag)
+    }
+
     /// Retrieve a prompt by version/tag
-    pub async fn get_prompt(&self, key: &str, selector: VersionSelector<'_>) -> Result<String> {
+    pub async fn get_prompt(&self, key: &str, selector: VersionSelector) -> Result<String> {
         let vault = self.vault.read().await;
-        Ok(vault.get(key, selector)?)
+        vault.get(key, selector)
+    }
+
+    /// Retrieve a prompt with the version it resolved to
+    pub async fn get_versioned(
+        &self,
+        key: &str,
+        selector: VersionSelector,
+    ) -> Result<VersionedPrompt> {
+        let vault = self.vault.read().await;
+        vault.get_versioned(key, selector)
     }
 
     /// Retrieve latest prompt
@@ -56,6 +125,11 @@ impl DefaultPromptManager {
         self.get_prompt(key, VersionSelector::Latest).await
     }
 
+    /// Retrieve the prompt at the configured default tag (latest when none is set)
+    pub async fn get_default(&self, key: &str) -> Result<String> {
+        self.get_prompt(key, self.options.default_selector()).await
+    }
+
     /// List history of versions
     pub async fn history(&self, key: &str) -> Result<()> {
         let vault = self.vault.read().await;
@@ -70,10 +144,49 @@ impl DefaultPromptManager {
         Ok(())
     }
 
+    /// Versions of a prompt one at a time, oldest first, so long histories can be paged
+    /// through without loading them whole
+    pub async fn history_stream(
+        &self,
+        key: &str,
+    ) -> Result<impl Stream<Item = Result<VersionMeta>>> {
+        let versions = self.vault.read().await.history_iter(key)?;
+        Ok(futures_util::stream::iter(versions))
+    }
+
     /// Export (backup)
     pub async fn backup(&self, path: &str, password: Option<&str>) -> Result<()> {
         let vault = self.vault.read().await;
-        vault.dump(path, password.map(|p| p))?;
+        vault.dump(path, password)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_try_get_reports_init_error() -> Result<()> {
+        let dir = tempdir()?;
+        let not_a_dir = dir.path().join("vault");
+        std::fs::write(&not_a_dir, "")?;
+
+        DefaultPromptManager::configure(ManagerOptions {
+            vault_path: Some(not_a_dir),
+            ..Default::default()
+        })?;
+
+        // An unusable vault is an error rather than a panic, and is retried later
+        let err = DefaultPromptManager::try_get()
+            .err()
+            .expect("vault should not open");
+        assert!(err
+            .to_string()
+            .starts_with("Failed to initialize PromptPro manager"));
+        assert!(DefaultPromptManager::try_get().is_err());
+
         Ok(())
     }
 }
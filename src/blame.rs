@@ -0,0 +1,112 @@
+//! Which version introduced each line of a prompt, for `promptpro blame` and the TUI.
+
+use crate::storage::PromptVault;
+use crate::types::{VersionMeta, VersionSelector};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use similar::{DiffTag, TextDiff};
+
+/// A line of a prompt with the version that introduced it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    /// 1-based line number
+    pub number: usize,
+    /// Line content without its trailing newline
+    pub text: String,
+    pub version: u64,
+    pub message: Option<String>,
+    pub author: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl PromptVault {
+    /// For each line of the latest version, the version that introduced it
+    pub fn blame(&self, key: &str) -> Result<Vec<BlameLine>> {
+        self.blame_at(key, VersionSelector::Latest)
+    }
+
+    /// For each line of the selected version, the version that introduced it.
+    ///
+    /// Walks the version's ancestry from the first version on, diffing each version
+    /// against its parent: unchanged lines keep their origin, inserted and rewritten
+    /// lines are attributed to the version that wrote them.
+    pub fn blame_at(&self, key: &str, selector: VersionSelector) -> Result<Vec<BlameLine>> {
+        if self.is_sensitive(key)? {
+            return Err(anyhow::anyhow!("Prompt '{}' is password-protected", key));
+        }
+        let target = self.resolve_version(key, &selector)?;
+        let history = self.history(key)?;
+        let find = |version: u64| {
+            history
+                .iter()
+                .find(|meta| meta.version == version)
+                .ok_or_else(|| anyhow::anyhow!("Version {} of '{}' not found", version, key))
+        };
+
+        // Ancestry of the target, oldest first
+        let mut chain: Vec<&VersionMeta> = vec![find(target)?];
+        while let Some(parent) = chain[chain.len() - 1].parent {
+            chain.push(find(parent)?);
+        }
+        chain.reverse();
+
+        let mut content = String::new();
+        // Version that introduced each line of `content`
+        let mut origins: Vec<&VersionMeta> = Vec::new();
+        for meta in chain {
+            let next = self.get(key, VersionSelector::Version(meta.version))?;
+            let mut next_origins = Vec::new();
+            for op in TextDiff::from_lines(&content, &next).ops() {
+                let (tag, old, new) = op.as_tag_tuple();
+                match tag {
+                    DiffTag::Equal => next_origins.extend_from_slice(&origins[old]),
+                    _ => next_origins.extend(std::iter::repeat_n(meta, new.len())),
+                }
+            }
+            content = next;
+            origins = next_origins;
+        }
+
+        Ok(content
+            .lines()
+            .zip(origins)
+            .enumerate()
+            .map(|(index, (text, meta))| BlameLine {
+                number: index + 1,
+                text: text.to_string(),
+                version: meta.version,
+                message: meta.message.clone(),
+                author: meta.author.clone(),
+                timestamp: meta.timestamp,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_blame() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?.with_author(Some("alice".to_string()));
+        vault.add("writer", "You write.\nBe brief.\n")?;
+        vault.update("writer", "You write.\nBe brief.\nSign off.\n", Some("sign off".into()))?;
+        vault.update("writer", "You write posts.\nBe brief.\nSign off.\n", None)?;
+
+        let blame = vault.blame("writer")?;
+        let origins: Vec<(usize, u64)> = blame.iter().map(|l| (l.number, l.version)).collect();
+        assert_eq!(origins, [(1, 3), (2, 1), (3, 2)]);
+        assert_eq!(blame[2].message.as_deref(), Some("sign off"));
+        assert_eq!(blame[2].author.as_deref(), Some("alice"));
+        assert_eq!(blame[0].text, "You write posts.");
+
+        let blame = vault.blame_at("writer", VersionSelector::Version(2))?;
+        assert_eq!(blame.iter().map(|l| l.version).collect::<Vec<_>>(), [1, 1, 2]);
+        assert!(vault.blame("missing").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+
+/// Outcome of resolving a canary selector for one caller
+#[derive(Debug, Clone)]
+pub struct CanaryResolution {
+    /// Tag that was served (either the stable or the candidate tag)
+    pub tag: String,
+    /// Version the tag resolved to
+    pub version: u64,
+    /// Content of the served version
+    pub content: String,
+    /// Whether the caller was routed to the candidate
+    pub is_candidate: bool,
+}
+
+/// Which side of a canary rollout a caller is served
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Stable,
+    Candidate,
+}
+
+/// Everything a decider needs to route one caller
+#[derive(Debug, Clone, Copy)]
+pub struct VariantRequest<'a> {
+    pub key: &'a str,
+    pub stable: &'a str,
+    pub candidate: &'a str,
+    pub percent: u8,
+    pub request_id: &'a str,
+}
+
+/// Decides which variant a caller gets during a canary rollout.
+///
+/// Implement this to delegate rollout decisions to a feature-flag system
+/// (LaunchDarkly, Unleash, ...). The vault still resolves tags and serves
+/// the prompt content; the decider only picks the side.
+pub trait VariantDecider: Send + Sync {
+    fn decide(&self, request: &VariantRequest) -> Result<Variant>;
+}
+
+/// Default decider: deterministic hash bucketing of the request id
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HashBucketDecider;
+
+impl VariantDecider for HashBucketDecider {
+    fn decide(&self, request: &VariantRequest) -> Result<Variant> {
+        if canary_bucket(request.key, request.request_id) < request.percent.min(100) {
+            Ok(Variant::Candidate)
+        } else {
+            Ok(Variant::Stable)
+        }
+    }
+}
+
+/// Map a request id to a stable bucket in `0..100` for the given key
+pub(crate) fn canary_bucket(key: &str, request_id: &str) -> u8 {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(key.as_bytes());
+    hasher.update(&[0]);
+    hasher.update(request_id.as_bytes());
+    let hash = hasher.finalize();
+
+    let mut bucket_bytes = [0u8; 8];
+    bucket_bytes.copy_from_slice(&hash.as_bytes()[..8]);
+    (u64::from_le_bytes(bucket_bytes) % 100) as u8
+}
+
+impl PromptVault {
+    /// Resolve a canary rollout between two tags.
+    ///
+    /// The vault's [`VariantDecider`] picks the side; with the default decider,
+    /// callers whose `request_id` falls into the first `percent` buckets get the
+    /// `candidate` tag and everyone else gets `stable`. The same request id always
+    /// lands on the same side, and each served variant is counted in the vault.
+    pub fn resolve_canary(
+        &self,
+        key: &str,
+        stable: &str,
+        candidate: &str,
+        percent: u8,
+        request_id: &str,
+    ) -> Result<CanaryResolution> {
+        let (tag, is_candidate) = self.canary_tag(key, stable, candidate, percent, request_id)?;
+
+        let version = self
+            .get_version_by_tag(key, tag)?
+            .ok_or_else(|| anyhow::anyhow!("Tag '{}' not found for key '{}'", tag, key))?;
+        let content = self.get(key, VersionSelector::Version(version))?;
+
+        // Record which variant was served
+        let counter_key = format!("canary:{}:{}", key, tag);
+        self.db().update_and_fetch(counter_key.as_bytes(), |old| {
+            let count = old
+                .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+                .map(u64::from_le_bytes)
+                .unwrap_or(0);
+            Some((count + 1).to_le_bytes().to_vec())
+        })?;
+
+        Ok(CanaryResolution {
+            tag: tag.to_string(),
+            version,
+            content,
+            is_candidate,
+        })
+    }
+
+    /// Pick the tag a caller should be served, without recording anything
+    pub(crate) fn canary_tag<'a>(
+        &self,
+        key: &str,
+        stable: &'a str,
+        candidate: &'a str,
+        percent: u8,
+        request_id: &str,
+    ) -> Result<(&'a str, bool)> {
+        let request = VariantRequest {
+            key,
+            stable,
+            candidate,
+            percent,
+            request_id,
+        };
+        let is_candidate = self.variant_decider().decide(&request)? == Variant::Candidate;
+        let tag = if is_candidate { candidate } else { stable };
+        Ok((tag, is_candidate))
+    }
+
+    /// Get how many times each tag was served through canary resolution
+    pub fn canary_counts(&self, key: &str) -> Result<Vec<(String, u64)>> {
+        let prefix = format!("canary:{}:", key);
+        let mut counts = Vec::new();
+
+        for result in self.db().scan_prefix(prefix.as_bytes()) {
+            let (counter_key, value) = result?;
+            let counter_key = String::from_utf8(counter_key.to_vec())?;
+            let tag = counter_key[prefix.len()..].to_string();
+            let count_bytes: [u8; 8] = value
+                .as_ref()
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("Failed to read canary counter"))?;
+            counts.push((tag, u64::from_le_bytes(count_bytes)));
+        }
+
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_canary_routing() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+
+        vault.add("greet", "hello")?;
+        vault.update("greet", "hi", None)?;
+        vault.tag("greet", "prod", 1)?;
+        vault.tag("greet", "next", 2)?;
+
+        // 0% never routes to the candidate, 100% always does
+        let none = VersionSelector::Canary {
+            stable: "prod".to_string(),
+            candidate: "next".to_string(),
+            percent: 0,
+            request_id: "user-1".to_string(),
+        };
+        assert_eq!(vault.get("greet", none)?, "hello");
+        let all = vault.resolve_canary("greet", "prod", "next", 100, "user-1")?;
+        assert!(all.is_candidate);
+        assert_eq!(all.version, 2);
+
+        // The same request id always gets the same variant
+        let first = vault.resolve_canary("greet", "prod", "next", 50, "user-42")?;
+        let second = vault.resolve_canary("greet", "prod", "next", 50, "user-42")?;
+        assert_eq!(first.tag, second.tag);
+
+        let total: u64 = vault.canary_counts("greet")?.iter().map(|(_, c)| c).sum();
+        assert_eq!(total, 4);
+
+        Ok(())
+    }
+
+    struct AlwaysCandidate;
+
+    impl VariantDecider for AlwaysCandidate {
+        fn decide(&self, _request: &VariantRequest) -> Result<Variant> {
+            Ok(Variant::Candidate)
+        }
+    }
+
+    #[test]
+    fn test_custom_variant_decider() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?.with_variant_decider(AlwaysCandidate);
+
+        vault.add("greet", "hello")?;
+        vault.update("greet", "hi", None)?;
+        vault.tag("greet", "prod", 1)?;
+        vault.tag("greet", "next", 2)?;
+
+        // The decider overrides the percentage entirely
+        let resolution = vault.resolve_canary("greet", "prod", "next", 0, "user-1")?;
+        assert!(resolution.is_candidate);
+        assert_eq!(resolution.content, "hi");
+
+        Ok(())
+    }
+}
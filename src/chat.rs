@@ -0,0 +1,310 @@
+//! Chat prompts: an ordered list of messages tagged with their role, for chat models.
+//!
+//! A chat prompt is stored as the content of an ordinary version, a `# promptpro chat`
+//! line followed by the messages as YAML, so history, diffs and blame work line by line:
+//!
+//! ```yaml
+//! # promptpro chat
+//! - role: system
+//!   content: You write short posts.
+//! - role: user
+//!   content: Write about {{topic}}.
+//! ```
+//!
+//! [`ChatPrompt::to_openai`] and [`ChatPrompt::to_anthropic`] turn one into the message
+//! JSON of those APIs.
+
+use crate::render::render_template;
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// First line of the content of a chat prompt
+pub const CHAT_HEADER: &str = "# promptpro chat";
+
+/// Who a message of a chat prompt is from
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "system" => Ok(Role::System),
+            "user" => Ok(Role::User),
+            "assistant" => Ok(Role::Assistant),
+            _ => Err(anyhow::anyhow!(
+                "Unknown role '{}'; use system, user or assistant",
+                s
+            )),
+        }
+    }
+}
+
+/// One part of a chat prompt
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        ChatMessage {
+            role,
+            content: content.into(),
+        }
+    }
+
+    /// Parse `ROLE=TEXT`, e.g. `system=You write short posts.`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (role, content) = spec.split_once('=').ok_or_else(|| {
+            anyhow::anyhow!("Invalid message '{}'; expected ROLE=TEXT", spec)
+        })?;
+        Ok(ChatMessage::new(role.parse()?, content))
+    }
+}
+
+/// Role-tagged messages stored as one prompt
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct ChatPrompt {
+    pub messages: Vec<ChatMessage>,
+}
+
+impl ChatPrompt {
+    pub fn new(messages: Vec<ChatMessage>) -> Self {
+        ChatPrompt { messages }
+    }
+
+    /// Whether stored content is a chat prompt
+    pub fn is_chat(content: &str) -> bool {
+        content.lines().next().is_some_and(|line| line.trim_end() == CHAT_HEADER)
+    }
+
+    /// Parse the content of a chat prompt version
+    pub fn from_content(content: &str) -> Result<Self> {
+        if !Self::is_chat(content) {
+            return Err(anyhow::anyhow!("Not a chat prompt: missing '{}'", CHAT_HEADER));
+        }
+        Self::parse(content)
+    }
+
+    /// Parse messages given as YAML or JSON, with or without the chat header, e.g. an
+    /// OpenAI `messages` array
+    pub fn parse(text: &str) -> Result<Self> {
+        let messages: Vec<ChatMessage> = serde_yaml::from_str(text)
+            .map_err(|e| anyhow::anyhow!("Invalid chat messages: {}", e))?;
+        Ok(ChatPrompt { messages })
+    }
+
+    /// The content a chat prompt is stored as
+    pub fn to_content(&self) -> Result<String> {
+        Ok(format!("{}\n{}", CHAT_HEADER, serde_yaml::to_string(&self.messages)?))
+    }
+
+    /// The messages as text, each under a `[1] system` line
+    pub fn to_text(&self) -> String {
+        self.messages
+            .iter()
+            .enumerate()
+            .map(|(index, part)| format!("[{}] {}\n{}", index + 1, part.role, part.content))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Messages for the OpenAI chat completions API: `[{"role", "content"}, ...]`
+    pub fn to_openai(&self) -> serde_json::Value {
+        serde_json::Value::Array(
+            self.messages
+                .iter()
+                .map(|m| serde_json::json!({ "role": m.role, "content": m.content }))
+                .collect(),
+        )
+    }
+
+    /// Request fields for the Anthropic messages API, which takes the system prompt
+    /// apart from the messages: system messages are joined into `system`
+    pub fn to_anthropic(&self) -> serde_json::Value {
+        let system: Vec<&str> = self
+            .messages
+            .iter()
+            .filter(|m| m.role == Role::System)
+            .map(|m| m.content.as_str())
+            .collect();
+        let messages: Vec<serde_json::Value> = self
+            .messages
+            .iter()
+            .filter(|m| m.role != Role::System)
+            .map(|m| serde_json::json!({ "role": m.role, "content": m.content }))
+            .collect();
+        let mut request = serde_json::json!({ "messages": messages });
+        if !system.is_empty() {
+            request["system"] = serde_json::Value::String(system.join("\n\n"));
+        }
+        request
+    }
+}
+
+impl PromptVault {
+    /// Add a new chat prompt
+    pub fn add_chat(&self, key: &str, chat: &ChatPrompt) -> Result<()> {
+        self.add(key, &chat.to_content()?)
+    }
+
+    /// Store `chat` as the next version of `key`, returning its version number
+    pub fn update_chat(
+        &self,
+        key: &str,
+        chat: &ChatPrompt,
+        message: Option<String>,
+    ) -> Result<u64> {
+        self.update_expecting(key, &chat.to_content()?, message, None)
+    }
+
+    /// The selected version of `key` as a chat prompt, failing when it is not one
+    pub fn get_chat(&self, key: &str, selector: VersionSelector) -> Result<ChatPrompt> {
+        let prompt = self.get_versioned(key, selector)?;
+        ChatPrompt::from_content(&prompt.content)
+            .map_err(|e| anyhow::anyhow!("'{}' v{}: {}", key, prompt.version, e))
+    }
+
+    /// Message `index` (from 0) of the selected version of chat prompt `key`
+    pub fn chat_part(
+        &self,
+        key: &str,
+        selector: VersionSelector,
+        index: usize,
+    ) -> Result<ChatMessage> {
+        let chat = self.get_chat(key, selector)?;
+        let count = chat.messages.len();
+        chat.messages.into_iter().nth(index).ok_or_else(|| {
+            anyhow::anyhow!("'{}' has only {} messages", key, count)
+        })
+    }
+
+    /// Replace the content of message `index` (from 0) of the latest version of chat
+    /// prompt `key`, keeping the other messages; returns the new version number.
+    ///
+    /// Fails with a [`VersionConflict`](crate::VersionConflict) when another writer
+    /// updates the prompt in the meantime.
+    pub fn update_chat_part(
+        &self,
+        key: &str,
+        index: usize,
+        content: &str,
+        message: Option<String>,
+    ) -> Result<u64> {
+        let latest = self.get_versioned(key, VersionSelector::Latest)?;
+        let mut chat = ChatPrompt::from_content(&latest.content)
+            .map_err(|e| anyhow::anyhow!("'{}' v{}: {}", key, latest.version, e))?;
+        let count = chat.messages.len();
+        let part = chat.messages.get_mut(index).ok_or_else(|| {
+            anyhow::anyhow!("'{}' has only {} messages", key, count)
+        })?;
+        part.content = content.to_string();
+        self.update_expecting(key, &chat.to_content()?, message, Some(latest.version))
+    }
+
+    /// The selected version of chat prompt `key` with the includes and placeholders of
+    /// each message filled in, as in [`PromptVault::render`]
+    pub fn render_chat(
+        &self,
+        key: &str,
+        selector: VersionSelector,
+        vars: &HashMap<String, String>,
+        strict: bool,
+    ) -> Result<ChatPrompt> {
+        let prompt = self.get_versioned(key, selector)?;
+        let mut chat = ChatPrompt::from_content(&prompt.content)
+            .map_err(|e| anyhow::anyhow!("'{}' v{}: {}", key, prompt.version, e))?;
+        for part in &mut chat.messages {
+            let resolved = self.resolve_content(key, prompt.version, &part.content)?;
+            part.content = render_template(&resolved, vars, strict)?;
+        }
+        Ok(chat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_chat_prompts() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("preamble", "Be kind.")?;
+        let chat = ChatPrompt::new(vec![
+            ChatMessage::parse("system={{> preamble}}\nYou write short posts.")?,
+            ChatMessage::new(Role::User, "Write about {{topic}}."),
+            ChatMessage::new(Role::Assistant, "Sure:"),
+        ]);
+        vault.add_chat("writer", &chat)?;
+        let stored = vault.get("writer", VersionSelector::Latest)?;
+        assert!(stored.starts_with("# promptpro chat\n- role: system\n"));
+        assert_eq!(vault.get_chat("writer", VersionSelector::Latest)?, chat);
+        assert!(ChatMessage::parse("robot=Hi").is_err());
+        vault.add("plain", "You write.")?;
+        assert!(vault.get_chat("plain", VersionSelector::Latest).is_err());
+
+        // Editing one part keeps the others
+        vault.update_chat_part("writer", 1, "Write about {{topic}} in {{lang}}.", None)?;
+        let part = vault.chat_part("writer", VersionSelector::Latest, 1)?;
+        assert_eq!(part.content, "Write about {{topic}} in {{lang}}.");
+        assert_eq!(vault.chat_part("writer", VersionSelector::Version(1), 0)?, chat.messages[0]);
+        assert!(vault.update_chat_part("writer", 3, "Hi", None).is_err());
+
+        let vars = HashMap::from([
+            ("topic".to_string(), "tea".to_string()),
+            ("lang".to_string(), "French".to_string()),
+        ]);
+        let rendered = vault.render_chat("writer", VersionSelector::Latest, &vars, true)?;
+        assert_eq!(
+            rendered.to_openai(),
+            serde_json::json!([
+                { "role": "system", "content": "Be kind.\nYou write short posts." },
+                { "role": "user", "content": "Write about tea in French." },
+                { "role": "assistant", "content": "Sure:" },
+            ])
+        );
+        let anthropic = rendered.to_anthropic();
+        assert_eq!(anthropic["system"], "Be kind.\nYou write short posts.");
+        assert_eq!(anthropic["messages"].as_array().unwrap().len(), 2);
+        let missing = vault.render_chat("writer", VersionSelector::Latest, &HashMap::new(), true);
+        assert!(missing.is_err());
+
+        // OpenAI messages can be read back
+        let parsed = ChatPrompt::parse(&rendered.to_openai().to_string())?;
+        assert_eq!(parsed, rendered);
+
+        Ok(())
+    }
+}
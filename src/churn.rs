@@ -0,0 +1,149 @@
+//! Rate of change of prompts over a time window, for `promptpro churn`.
+
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+/// Prompts changed this many times in the window are reported as unstable
+pub const UNSTABLE_VERSIONS: u64 = 5;
+
+/// How much one prompt changed in the window, see [`PromptVault::churn`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChurnEntry {
+    pub key: String,
+    /// Versions written in the window
+    pub versions: u64,
+    /// Lines inserted and deleted by those versions, compared to their parents
+    pub lines_added: u64,
+    pub lines_removed: u64,
+    /// Author from the prompt metadata; versions do not record who wrote them
+    pub author: Option<String>,
+    pub last_change: DateTime<Utc>,
+}
+
+impl ChurnEntry {
+    pub fn lines_changed(&self) -> u64 {
+        self.lines_added + self.lines_removed
+    }
+
+    /// Changed often enough that it likely needs a design review rather than more tweaks
+    pub fn is_unstable(&self) -> bool {
+        self.versions >= UNSTABLE_VERSIONS
+    }
+}
+
+/// Parse a window such as `30d`, `2w`, `12h` or `45m`
+pub fn parse_window(window: &str) -> Result<Duration> {
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid window '{}', expected a number followed by m, h, d or w (e.g. 30d)",
+            window
+        )
+    };
+    let split = window.len().checked_sub(1).ok_or_else(invalid)?;
+    let (amount, unit) = window.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    let duration = match unit {
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => None,
+    };
+    duration.filter(|d| *d > Duration::zero()).ok_or_else(invalid)
+}
+
+impl PromptVault {
+    /// Keys changed since `since`, most versions first, then most lines changed.
+    ///
+    /// First versions count as versions but not as changed lines. Lines of
+    /// password-protected prompts are not counted, since their content is encrypted.
+    pub fn churn(&self, since: DateTime<Utc>) -> Result<Vec<ChurnEntry>> {
+        let mut entries = Vec::new();
+        for key in self.list_keys()? {
+            let recent: Vec<_> = self
+                .history(&key)?
+                .into_iter()
+                .filter(|meta| meta.timestamp >= since)
+                .collect();
+            let Some(last) = recent.iter().map(|meta| meta.timestamp).max() else {
+                continue;
+            };
+
+            let (mut lines_added, mut lines_removed) = (0, 0);
+            if !self.is_sensitive(&key)? {
+                for meta in &recent {
+                    let Some(parent) = meta.parent else {
+                        continue;
+                    };
+                    let old = self.get(&key, VersionSelector::Version(parent))?;
+                    let new = self.get(&key, VersionSelector::Version(meta.version))?;
+                    for change in TextDiff::from_lines(&old, &new).iter_all_changes() {
+                        match change.tag() {
+                            ChangeTag::Insert => lines_added += 1,
+                            ChangeTag::Delete => lines_removed += 1,
+                            ChangeTag::Equal => {}
+                        }
+                    }
+                }
+            }
+
+            entries.push(ChurnEntry {
+                author: self.prompt_meta(&key)?.author,
+                key,
+                versions: recent.len() as u64,
+                lines_added,
+                lines_removed,
+                last_change: last,
+            });
+        }
+
+        entries.sort_by(|a, b| {
+            b.versions
+                .cmp(&a.versions)
+                .then(b.lines_changed().cmp(&a.lines_changed()))
+                .then(a.key.cmp(&b.key))
+        });
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_churn() -> Result<()> {
+        assert_eq!(parse_window("30d")?, Duration::days(30));
+        assert_eq!(parse_window("2w")?, Duration::days(14));
+        assert!(parse_window("30").is_err());
+        assert!(parse_window("-1d").is_err());
+        assert!(parse_window("").is_err());
+
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        let start = Utc::now();
+        vault.add("stable", "You are stable.\n")?;
+        vault.add("planner", "Plan.\nStep by step.\n")?;
+        for step in 1..UNSTABLE_VERSIONS {
+            vault.update("planner", &format!("Plan {}.\nStep by step.\n", step), None)?;
+        }
+
+        let churn = vault.churn(start)?;
+        assert_eq!(churn[0].key, "planner");
+        assert_eq!(churn[0].versions, UNSTABLE_VERSIONS);
+        assert_eq!((churn[0].lines_added, churn[0].lines_removed), (4, 4));
+        assert!(churn[0].is_unstable());
+        assert_eq!(churn[1].lines_changed(), 0);
+        assert!(!churn[1].is_unstable());
+
+        // Nothing changed after the window starts
+        assert!(vault.churn(Utc::now())?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,2010 @@
+//! Command-line definition shared by the `promptpro` binary and `run_cli_from_args`
+
+use crate::commands;
+use crate::emit::EmitOptions;
+use crate::encryption::KdfParams;
+use crate::eval::Assertion;
+use crate::history::{parse_time, HistoryQuery};
+use crate::metadata::PromptMeta;
+use crate::password::PasswordOptions;
+use crate::profile::resolve_author;
+use crate::search::SearchOptions;
+use crate::validate::configured_validators;
+use crate::vault_lock::LockPolicy;
+use crate::webhook::{configured_webhooks, Webhook, WebhookSender};
+use anyhow::Result;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+
+/// Example invocations shown after each subcommand's `--help`, and by `examples`
+const EXAMPLES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "init",
+        &[
+            ("Create the personal default vault", "promptpro init"),
+            ("Create a project vault in ./.promptpro", "promptpro init --local"),
+            (
+                "Encrypt stored prompts, keeping the password in the OS keyring",
+                "promptpro init --encrypted --save-to-keyring",
+            ),
+        ],
+    ),
+    (
+        "add",
+        &[
+            ("Store a prompt (asks for its key)", "promptpro add \"You are a helpful assistant\""),
+            (
+                "Describe what it is for",
+                "promptpro add \"Summarize: {{text}}\" --description \"Article summaries\" --model gpt-4o --label team=news",
+            ),
+        ],
+    ),
+    (
+        "update",
+        &[
+            (
+                "Store a new version with a message",
+                "promptpro update assistant \"You are a concise assistant\" -m \"shorter answers\"",
+            ),
+            (
+                "Only if nobody stored a version since v3, e.g. from a script",
+                "promptpro update assistant \"Be brief\" --expected-parent 3",
+            ),
+        ],
+    ),
+    (
+        "get",
+        &[
+            ("Print the latest version", "promptpro get assistant"),
+            ("Print a version or a tag", "promptpro co assistant stable"),
+            ("Print the version that was current a week ago", "promptpro get assistant @-7d"),
+            ("Print the version current at noon UTC on May 1st", "promptpro get assistant @2024-05-01T12:00"),
+            ("Write it to a file", "promptpro get assistant 2 --output assistant.txt"),
+            ("Offer the closest key when mistyped", "promptpro get asistant --auto-correct"),
+            ("Fail unless the content was approved", "promptpro get assistant stable --expect 9f2c..."),
+            ("Read while the TUI holds the vault", "promptpro get assistant --snapshot"),
+        ],
+    ),
+    (
+        "render",
+        &[
+            ("Fill in placeholders", "promptpro render welcome --var customer_name=Ada"),
+            ("Fail on missing variables", "promptpro render welcome stable --var customer_name=Ada --strict"),
+        ],
+    ),
+    (
+        "emit",
+        &[
+            (
+                "Print an OpenAI chat completions request",
+                "promptpro emit support/agent --var customer=Ada",
+            ),
+            (
+                "Build an Anthropic messages request from the stable version",
+                "promptpro emit support/agent stable --format anthropic-messages --model claude-sonnet-4-5",
+            ),
+            ("Send a plain prompt as the system message", "promptpro emit writer --role system"),
+        ],
+    ),
+    (
+        "deps",
+        &[
+            ("Show the prompts a prompt includes, recursively", "promptpro deps support/agent"),
+            ("Show what the stable version includes", "promptpro deps support/agent stable"),
+            ("List the prompts including the shared preamble", "promptpro deps shared/preamble --reverse"),
+        ],
+    ),
+    (
+        "tokens",
+        &[
+            ("Count the tokens of the latest version", "promptpro tokens assistant"),
+            ("Count for another model's tokenizer", "promptpro tokens assistant stable --model gpt-4"),
+            ("Warn when over a budget", "promptpro tokens assistant --budget 4000"),
+        ],
+    ),
+    (
+        "list",
+        &[
+            ("List every prompt key", "promptpro ls"),
+            ("List the keys of a namespace", "promptpro ls team/agent/"),
+            ("List the most served prompts first", "promptpro ls --sort usage"),
+            ("Wait until the TUI closes the vault", "promptpro ls --wait"),
+        ],
+    ),
+    (
+        "history",
+        &[
+            ("Show versions, tags and messages", "promptpro hist assistant"),
+            ("The last 20 versions, then the 20 before", "promptpro hist assistant --limit 20 --offset 20"),
+            ("Versions of the last week whose message mentions a fix", "promptpro hist assistant --since 7d --message-contains fix"),
+            ("Versions tagged prod during May", "promptpro hist assistant --tag prod --since 2024-05-01 --until 2024-05-31"),
+        ],
+    ),
+    (
+        "diff",
+        &[("Compare a tag with the latest version", "promptpro diff assistant stable latest")],
+    ),
+    (
+        "compare",
+        &[
+            ("Render stable and v7 side by side", "promptpro compare assistant stable v7 --input case.json"),
+            (
+                "Also compare the answers of the configured model",
+                "promptpro compare assistant stable v7 --var question='Where is my order?' --run",
+            ),
+        ],
+    ),
+    (
+        "blame",
+        &[
+            ("See which version wrote each line", "promptpro blame assistant"),
+            ("Blame the stable version instead", "promptpro blame assistant stable"),
+        ],
+    ),
+    (
+        "comment",
+        &[
+            ("Leave review feedback on the latest version", "promptpro comment assistant 'Too formal'"),
+            ("Comment on the stable version", "promptpro comment assistant 'Ship it' --on stable"),
+            ("Reply to comment #2", "promptpro comment assistant 'Fixed in v4' --reply-to 2"),
+        ],
+    ),
+    (
+        "score",
+        &[
+            ("Record the accuracy your eval pipeline measured", "promptpro score record support v7 accuracy 0.91 --source eval-ci"),
+            ("List the scores of every version", "promptpro score list support"),
+            ("Get the version with the best accuracy", "promptpro get support best:accuracy"),
+        ],
+    ),
+    (
+        "chat",
+        &[
+            (
+                "Store a chat prompt",
+                "promptpro chat set support --part 'system=You are a support agent.' --part 'user={{question}}'",
+            ),
+            ("Import OpenAI messages", "promptpro chat set support --file messages.json"),
+            ("Rewrite the user message only", "promptpro chat set-part support 2 'Answer: {{question}}'"),
+            (
+                "Print messages for the OpenAI API",
+                "promptpro chat show support --format openai --var question='Where is my order?'",
+            ),
+            ("Print system and messages for the Anthropic API", "promptpro chat show support --format anthropic"),
+        ],
+    ),
+    (
+        "test",
+        &[
+            ("Test the latest version", "promptpro test support"),
+            ("Compare the stable version with the latest", "promptpro test support stable latest"),
+            ("Point the tests at a local OpenAI-compatible server", "promptpro config set llm.endpoint http://localhost:11434/v1"),
+        ],
+    ),
+    (
+        "test-case",
+        &[
+            (
+                "Check that refund questions mention the refund window",
+                "promptpro test-case add support refund --var question='Can I get a refund?' --matches '\\d+ days'",
+            ),
+            ("Forbid apologies", "promptpro test-case add support tone --var question=Hi --not-contains Sorry"),
+            ("List the test cases", "promptpro test-case list support"),
+        ],
+    ),
+    (
+        "experiment",
+        &[
+            (
+                "Serve v4 to 10% of users and the stable tag to the rest",
+                "promptpro experiment create assistant tone --variant stable=90 --variant v4=10",
+            ),
+            ("Print the prompt a user gets, counting it", "promptpro experiment sample assistant tone user-42"),
+            ("See how often each variant was served", "promptpro experiment list assistant"),
+        ],
+    ),
+    (
+        "churn",
+        &[
+            ("Find the prompts changed most in the last 30 days", "promptpro churn"),
+            ("Look at the last week only", "promptpro churn --since 1w"),
+        ],
+    ),
+    (
+        "search",
+        &[
+            ("Find prompts mentioning JSON", "promptpro search json"),
+            ("Regex over every version", "promptpro search '^You are' --regex --all-versions"),
+            (
+                "Matching lines with two lines of context",
+                "promptpro search --regex '(?i)respond in json' -C 2",
+            ),
+            (
+                "Stable agent prompts by alice mentioning a phrase",
+                "promptpro search 'tag:stable author:alice \"json output\" key:agents/*'",
+            ),
+        ],
+    ),
+    (
+        "tag",
+        &[
+            ("Tag version 1 as stable", "promptpro tag assistant stable 1"),
+            ("Tag the latest version as release", "promptpro tag assistant release"),
+            (
+                "Also post the change to a webhook",
+                "promptpro tag assistant release --webhook https://hooks.example.com/ppro",
+            ),
+        ],
+    ),
+    (
+        "untag",
+        &[("Remove the stable tag", "promptpro untag assistant stable")],
+    ),
+    (
+        "promote",
+        &[("Point stable at the latest version", "promptpro promote assistant stable")],
+    ),
+    (
+        "rollback",
+        &[("Restore the stable version as the new latest", "promptpro rollback assistant stable")],
+    ),
+    (
+        "cherry-pick",
+        &[(
+            "Carry the change made in v4 onto the latest version",
+            "promptpro cherry-pick assistant 4",
+        )],
+    ),
+    (
+        "copy",
+        &[
+            ("Start a new agent prompt from the latest planner prompt", "promptpro cp agent/planner agent/researcher"),
+            ("Fork with every version, tag and the metadata", "promptpro cp agent/planner agent/critic --history --metadata"),
+        ],
+    ),
+    (
+        "adopt",
+        &[(
+            "Copy a prompt with its history from the personal vault",
+            "promptpro adopt assistant --from default --history",
+        )],
+    ),
+    (
+        "pack",
+        &[
+            ("Share the stable versions of a namespace", "promptpro pack team/agents --out agents.ppk --selector stable"),
+        ],
+    ),
+    (
+        "install",
+        &[
+            ("Install a package under the namespace it was packed from", "promptpro install agents.ppk"),
+            ("Install into another namespace, updating prompts that differ", "promptpro install agents.ppk --into vendor/agents --on-conflict update"),
+        ],
+    ),
+    (
+        "codegen",
+        &[
+            ("Generate constants for every prompt key", "promptpro codegen rust --out src/prompt_keys.rs"),
+            ("Add structs of each prompt's template variables", "promptpro codegen rust --out src/prompt_keys.rs --render-helpers"),
+            ("Fail in CI when the module is out of date", "promptpro codegen rust --out src/prompt_keys.rs --check"),
+            ("Generate a key enum and render functions for Python", "promptpro codegen python --out prompts_keys.py"),
+        ],
+    ),
+    (
+        "serve",
+        &[
+            ("Serve the vault on localhost:8080", "promptpro serve"),
+            ("Tune for many reads", "promptpro serve --preload --cache-mb 512"),
+            (
+                "Check and prune the vault every 6 hours, keeping 20 versions per prompt",
+                "promptpro serve --sweep-every 6h --sweep-keep-last 20",
+            ),
+            ("Fetch the stable version over HTTP", "curl 'http://localhost:8080/prompts/assistant?selector=stable'"),
+        ],
+    ),
+    (
+        "docs",
+        &[
+            ("Set the vault README served at /docs", "promptpro docs readme VAULT.md"),
+            (
+                "Write notes on a prompt",
+                "promptpro docs note team/writer \"Keep posts under 800 words\"",
+            ),
+            ("Print the notes of a prompt", "promptpro docs show team/writer"),
+        ],
+    ),
+    (
+        "alias",
+        &[
+            ("Keep serving a renamed prompt under its old key", "promptpro alias set writer team/writer"),
+            ("List the aliases", "promptpro alias list"),
+        ],
+    ),
+    (
+        "push",
+        &[
+            ("Send new versions to a shared vault", "promptpro push /mnt/team/vault"),
+            ("Push to a vault on another machine", "promptpro push ssh://build-box/srv/promptpro"),
+            ("Push to an S3 bucket (s3 feature)", "promptpro push s3://team-prompts/prod"),
+        ],
+    ),
+    (
+        "pull",
+        &[("Fetch new versions from a running server", "promptpro pull http://prompts.internal:8080")],
+    ),
+    (
+        "git-sync",
+        &[
+            ("Mirror the vault into a Git repository, one commit per version", "promptpro git-sync --repo ../prompts-git"),
+            ("Keep the vault's side of prompts edited in both places", "promptpro git-sync --repo ../prompts-git --prefer vault"),
+        ],
+    ),
+    (
+        "profiles",
+        &[
+            ("List the configured profiles", "promptpro profiles"),
+            ("Push to the remote of the 'work' profile", "promptpro --profile work push"),
+        ],
+    ),
+    (
+        "config",
+        &[
+            ("Record your name on the versions you store", "promptpro config set user.name alice"),
+            ("Show the configured name", "promptpro config get user.name"),
+            (
+                "Record another author for one update",
+                "promptpro update writer 'New text' --author bob",
+            ),
+        ],
+    ),
+    (
+        "export",
+        &[
+            ("Export the vault for review", "promptpro export prompts.yaml"),
+            (
+                "Bundle the stable prompts for a web app",
+                "promptpro export --format web-bundle --tags stable --out prompts.json",
+            ),
+        ],
+    ),
+    (
+        "export-key",
+        &[
+            ("Document a prompt for an audit", "promptpro export-key assistant assistant.md"),
+            ("Print the document", "promptpro export-key assistant --format markdown"),
+        ],
+    ),
+    (
+        "export-templates",
+        &[
+            ("Write the stable prompts as Jinja2 templates", "promptpro export-templates --dir templates/ --selector stable"),
+            ("Handlebars templates of one namespace", "promptpro export-templates --dir views/prompts --syntax handlebars --prefix web/"),
+        ],
+    ),
+    (
+        "import",
+        &[
+            ("Import a reviewed export", "promptpro import prompts.yaml"),
+            ("Migrate the saved prompts of a LangChain project", "promptpro import --format langchain-hub prompts/"),
+            ("Import templates fetched from the PromptLayer API", "promptpro import --format promptlayer templates.json"),
+            ("Preview the hardcoded prompts of a crate", "promptpro import --from rust-src src/ --dry-run"),
+            ("Move the *_PROMPT constants of a Python package into the vault", "promptpro import --from python-src app/"),
+        ],
+    ),
+    (
+        "import-dir",
+        &[
+            ("Seed the vault from prompt files", "promptpro import-dir ./prompts/"),
+            ("Preview what would change", "promptpro import-dir ./prompts/ --dry-run"),
+        ],
+    ),
+    (
+        "watch",
+        &[("Version every save of the files in ./prompts/", "promptpro watch ./prompts/")],
+    ),
+    (
+        "lock",
+        &[
+            ("Pin the prompts a deployment uses", "promptpro lock --output prompts.lock"),
+            ("Check in CI that every pinned version is intact", "promptpro lock --check"),
+        ],
+    ),
+    (
+        "dump",
+        &[
+            ("Encrypted backup", "promptpro dump backup.bin --encrypt"),
+            (
+                "Make the password harder to brute-force (256 MiB per guess)",
+                "promptpro dump backup.bin --encrypt --kdf-memory 262144",
+            ),
+        ],
+    ),
+    (
+        "resume",
+        &[
+            ("Restore a backup", "promptpro resume backup.bin"),
+            ("Show which release wrote a dump", "promptpro resume backup.bin --inspect"),
+            (
+                "Restore into a fresh vault, e.g. in a container",
+                "promptpro resume backup.bin --target /srv/prompts/vault",
+            ),
+            (
+                "Replace the current vault with a backup",
+                "promptpro resume backup.bin --force",
+            ),
+            (
+                "Merge a backup, asking which side wins for keys changed on both",
+                "promptpro resume backup.bin --on-conflict interactive",
+            ),
+        ],
+    ),
+    (
+        "compression",
+        &[
+            ("Show the codec and the stored size", "promptpro compression"),
+            ("Compress a vault from an older release", "promptpro compression zstd"),
+        ],
+    ),
+    (
+        "stats",
+        &[
+            ("Sizes, most edited prompts, tags and growth of the vault", "promptpro stats"),
+            ("Stats of one prompt, or of every prompt of a namespace", "promptpro stats team/agent"),
+            ("Stats as JSON, for dashboards", "promptpro stats --json"),
+            ("Export usage counters and the audit trail", "promptpro stats export --out ./stats"),
+        ],
+    ),
+    (
+        "migrate",
+        &[
+            ("Check whether the vault needs migrating", "promptpro migrate --check"),
+            ("Migrate, keeping the backup next to the vault", "promptpro migrate"),
+            ("Migrate, verifying 200 sampled versions", "promptpro migrate --samples 200"),
+        ],
+    ),
+    (
+        "fsck",
+        &[
+            ("Check the vault after an unclean shutdown", "promptpro fsck"),
+            ("Repair dangling tags and entries", "promptpro fsck --repair"),
+        ],
+    ),
+    (
+        "gc",
+        &[
+            ("Remove orphaned entries only", "promptpro gc"),
+            ("Preview keeping the last 5 versions of each prompt", "promptpro gc --keep-last 5 --dry-run"),
+            (
+                "Keep tagged versions and the last 90 days, then shrink the vault on disk",
+                "promptpro gc --keep-newer-than 90d --compact",
+            ),
+        ],
+    ),
+    (
+        "delete",
+        &[
+            ("Delete a prompt and all its versions", "promptpro rm assistant"),
+            ("Delete a whole namespace", "promptpro rm team/agent --recursive"),
+        ],
+    ),
+];
+
+fn render_examples(examples: &[(&str, &str)]) -> String {
+    let mut out = String::from("Examples:");
+    for (description, line) in examples {
+        out.push_str(&format!("\n  # {}\n  {}", description, line));
+    }
+    out
+}
+
+/// The clap command, with the examples attached to each subcommand's help
+pub fn command() -> clap::Command {
+    EXAMPLES
+        .iter()
+        .fold(Cli::command(), |command, (name, examples)| {
+            command.mut_subcommand(*name, |sub| sub.after_help(render_examples(examples)))
+        })
+}
+
+/// Parse the given arguments
+pub fn try_parse_from(args: Vec<String>) -> Result<Cli, clap::Error> {
+    let matches = command().try_get_matches_from(args)?;
+    Cli::from_arg_matches(&matches)
+}
+
+/// Print the examples of one subcommand, or of all of them
+fn print_examples(command: Option<String>) -> Result<()> {
+    let selected: Vec<_> = EXAMPLES
+        .iter()
+        .filter(|(name, _)| command.as_deref().is_none_or(|c| c == *name))
+        .collect();
+    if selected.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No examples for '{}'",
+            command.unwrap_or_default()
+        ));
+    }
+
+    for (name, examples) in selected {
+        println!("{}", name);
+        for (description, line) in examples.iter() {
+            println!("  # {}\n  {}", description, line);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Cli {
+    /// Profile of ~/.promptpro/config.toml to use (default: $PROMPTPRO_PROFILE)
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    /// Refuse every network access: sync over HTTP or ssh, remote reads (also
+    /// PROMPTPRO_OFFLINE=1 or `offline = true` in ~/.promptpro/config.toml)
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Wait for another process holding the vault (such as an open TUI) to release it
+    #[arg(long, global = true, conflicts_with = "snapshot")]
+    pub wait: bool,
+    /// Read from a copy of the vault when another process holds it; commands that
+    /// write still fail
+    #[arg(long, global = true)]
+    pub snapshot: bool,
+    /// Also post the changes this command makes to URL as JSON (repeatable), besides
+    /// the [[webhooks]] of ~/.promptpro/config.toml
+    #[arg(long, global = true, value_name = "URL")]
+    pub webhook: Vec<String>,
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Initialize a new prompt vault
+    Init {
+        /// Path to the vault directory (default: ~/promptpro/default_vault)
+        #[arg(long)]
+        path: Option<String>,
+        /// Create a project vault in ./.promptpro, used from this directory and below
+        #[arg(long, conflicts_with = "path")]
+        local: bool,
+        /// Encrypt stored content with a password (asked for, or read from --password-file)
+        #[arg(long)]
+        encrypted: bool,
+        /// Read the vault password from a file
+        #[arg(long, requires = "encrypted")]
+        password_file: Option<String>,
+        /// Also store the vault password in the OS keyring
+        #[arg(long, requires = "encrypted")]
+        save_to_keyring: bool,
+    },
+    /// Add a new prompt
+    Add {
+        /// Content of the prompt
+        content: String,
+        #[command(flatten)]
+        meta: MetadataArgs,
+    },
+    /// Update an existing prompt
+    Update {
+        /// Key of the prompt to update
+        key: String,
+        /// New content of the prompt
+        content: String,
+        /// Optional message for the update
+        #[arg(short, long)]
+        message: Option<String>,
+        /// Fail instead of updating when this is no longer the latest version
+        #[arg(long)]
+        expected_parent: Option<u64>,
+        /// Read the passphrase of a protected prompt from a file
+        #[arg(long)]
+        passphrase_file: Option<String>,
+        #[command(flatten)]
+        meta: MetadataArgs,
+        /// Offer the closest existing key when this one is mistyped (asks first)
+        #[arg(long)]
+        auto_correct: bool,
+    },
+    /// Get a prompt by key and selector
+    #[command(visible_alias = "co")]
+    Get {
+        /// Key of the prompt
+        key: String,
+        /// Selector: latest, a version (3 or v3), a tag, or a time (@2024-05-01T12:00, @-7d)
+        selector: Option<String>,
+        /// Output to file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Read the passphrase of a protected prompt from a file
+        #[arg(long)]
+        passphrase_file: Option<String>,
+        /// Offer the closest existing key when this one is mistyped (asks first)
+        #[arg(long)]
+        auto_correct: bool,
+        /// Fail unless the content has this hash, as pinned by `promptpro lock`
+        #[arg(long, value_name = "HASH")]
+        expect: Option<String>,
+    },
+    /// Print a prompt with its {{name}} placeholders filled in
+    Render {
+        /// Key of the prompt
+        key: String,
+        /// Selector: latest, a version (3 or v3), a tag, or a time (@2024-05-01T12:00, @-7d)
+        selector: Option<String>,
+        /// Placeholder value as name=value (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE")]
+        vars: Vec<String>,
+        /// Fail if a placeholder has no value
+        #[arg(long)]
+        strict: bool,
+        /// List the placeholders instead of rendering
+        #[arg(long, conflicts_with_all = ["vars", "strict"])]
+        list_variables: bool,
+    },
+    /// Print a prompt, rendered, as the JSON body of an OpenAI or Anthropic request
+    Emit {
+        /// Key of the prompt
+        key: String,
+        /// Selector: latest, a version (3 or v3), a tag, or a time (@2024-05-01T12:00, @-7d)
+        selector: Option<String>,
+        /// Request format: openai-chat or anthropic-messages
+        #[arg(long, default_value = "openai-chat")]
+        format: String,
+        /// Placeholder value as name=value (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE")]
+        vars: Vec<String>,
+        /// Fail if a placeholder has no value
+        #[arg(long)]
+        strict: bool,
+        /// Model of the request (default: the model of the prompt's metadata)
+        #[arg(long)]
+        model: Option<String>,
+        /// Limit of generated tokens (Anthropic requests default to 1024)
+        #[arg(long)]
+        max_tokens: Option<u32>,
+        /// Role of the message for prompts that are not chat prompts: system, user or
+        /// assistant (default: user)
+        #[arg(long)]
+        role: Option<String>,
+        /// Print the JSON on one line
+        #[arg(long)]
+        compact: bool,
+    },
+    /// Show the prompts a prompt includes with {{> key@selector}}, or those including it
+    Deps {
+        /// Key of the prompt
+        key: String,
+        /// Selector: latest, a version (3 or v3), a tag, or a time (@2024-05-01T12:00, @-7d)
+        selector: Option<String>,
+        /// List the prompts whose latest version includes this one instead
+        #[arg(long, conflicts_with = "selector")]
+        reverse: bool,
+    },
+    /// Count the tokens of a version of a prompt
+    Tokens {
+        /// Key of the prompt
+        key: String,
+        /// Selector: latest, a version (3 or v3), a tag, or a time (@2024-05-01T12:00, @-7d)
+        selector: Option<String>,
+        /// Model whose tokenizer counts (default: `model` of [tokens] in the config file,
+        /// else gpt-4o)
+        #[arg(long)]
+        model: Option<String>,
+        /// Warn when there are more tokens than this (default: `budget` of [tokens] in the
+        /// config file)
+        #[arg(long)]
+        budget: Option<u64>,
+    },
+    /// Show a unified diff between two versions or tags of a prompt
+    Diff {
+        /// Key of the prompt
+        key: String,
+        /// Old side selector (latest, 3 or v3, a tag, @2024-05-01T12:00, @-7d)
+        old: String,
+        /// New side selector (latest, 3 or v3, a tag, @2024-05-01T12:00, @-7d)
+        new: String,
+    },
+    /// Show two versions of a prompt rendered side by side, optionally with the answers
+    /// of the configured model to each (needs the `eval` feature)
+    Compare {
+        /// Key of the prompt
+        key: String,
+        /// Old side selector (latest, 3 or v3, a tag, @2024-05-01T12:00, @-7d)
+        old: String,
+        /// New side selector (latest, 3 or v3, a tag, @2024-05-01T12:00, @-7d)
+        new: String,
+        /// JSON object of placeholder values, e.g. {"question": "..."} (`-` for stdin)
+        #[arg(long, value_name = "FILE")]
+        input: Option<String>,
+        /// Placeholder value as name=value (repeatable), over those of --input
+        #[arg(long = "var", value_name = "NAME=VALUE")]
+        vars: Vec<String>,
+        /// Send both versions to the model of the `[llm]` table and compare the answers
+        #[arg(long)]
+        run: bool,
+        /// Width of the output (default: the terminal's)
+        #[arg(long)]
+        width: Option<usize>,
+    },
+    /// Show which version introduced each line of a prompt
+    Blame {
+        /// Key of the prompt
+        key: String,
+        /// Selector: latest, a version (3 or v3), a tag, or a time (@2024-05-01T12:00, @-7d)
+        selector: Option<String>,
+    },
+    /// Comment on a version of a prompt, or reply to a comment; `history` lists them
+    Comment {
+        /// Key of the prompt
+        key: String,
+        /// Text of the comment
+        text: String,
+        /// Version to comment on: latest, a version (3 or v3), a tag, or a time
+        /// (default: latest)
+        #[arg(long, value_name = "SELECTOR", conflicts_with = "reply_to")]
+        on: Option<String>,
+        /// Reply to the comment with this number, on the version it is about
+        #[arg(long, value_name = "ID")]
+        reply_to: Option<u64>,
+        /// Who is commenting (default: PROMPTPRO_AUTHOR, the profile's author or
+        /// user.name of the config file)
+        #[arg(long)]
+        author: Option<String>,
+    },
+    /// Record and list evaluation scores of versions, e.g. accuracy
+    Score {
+        #[command(subcommand)]
+        action: ScoreCommands,
+    },
+    /// Chat prompts made of system, user and assistant messages
+    Chat {
+        #[command(subcommand)]
+        action: ChatCommands,
+    },
+    /// Run the test cases of a prompt against an LLM and report pass/fail per version
+    /// (needs the `eval` feature)
+    Test {
+        /// Key of the prompt
+        key: String,
+        /// Versions to test: versions (3 or v3), tags or latest (default: latest)
+        selectors: Vec<String>,
+        /// Print the answer to every test case
+        #[arg(long)]
+        show_output: bool,
+    },
+    /// Manage the test cases `promptpro test` runs
+    TestCase {
+        #[command(subcommand)]
+        action: TestCaseCommands,
+    },
+    /// A/B experiments splitting the traffic of a prompt between versions or tags
+    Experiment {
+        #[command(subcommand)]
+        action: ExperimentCommands,
+    },
+    /// Rank prompts by how much they changed recently
+    Churn {
+        /// Window to look back over, e.g. 30d, 2w or 12h
+        #[arg(long, default_value = "30d")]
+        since: String,
+    },
+    /// Search prompt keys, contents, messages and tags
+    Search {
+        /// Words or "quoted phrases" (patterns with --regex) that must all match, plus
+        /// `tag:`, `author:`, `model:`, `label:` and `key:` (with `*`) selectors
+        query: String,
+        /// Treat the query as a regular expression
+        #[arg(long)]
+        regex: bool,
+        /// Match case exactly
+        #[arg(long)]
+        case_sensitive: bool,
+        /// Search every version instead of only the latest
+        #[arg(long)]
+        all_versions: bool,
+        /// With --regex, lines of context to show around each matching line
+        #[arg(short = 'C', long, value_name = "LINES", default_value_t = 0)]
+        context: usize,
+    },
+    /// Copy a prompt to a new key, e.g. to fork it as the start of another prompt
+    #[command(visible_alias = "cp")]
+    Copy {
+        /// Key of the prompt to copy
+        src: String,
+        /// New key of the copy
+        dst: String,
+        /// Copy every version with its messages and tags, not just the latest content
+        #[arg(long)]
+        history: bool,
+        /// Copy the description, author, model, temperature and labels too
+        #[arg(long)]
+        metadata: bool,
+    },
+    /// Copy a prompt from another vault into this one, recording its provenance
+    Adopt {
+        /// Key of the prompt to copy
+        key: String,
+        /// Vault directory to copy from (`default` for the personal default vault)
+        #[arg(long)]
+        from: String,
+        /// Vault directory to copy into (defaults to the current vault)
+        #[arg(long)]
+        to: Option<String>,
+        /// Copy every version, not just the latest content
+        #[arg(long)]
+        history: bool,
+    },
+    /// Pack one version of each prompt of a namespace, with its tags and metadata, into a
+    /// package file to share
+    Pack {
+        /// Namespace to pack, e.g. `team/agents`
+        namespace: String,
+        /// Package file to write
+        #[arg(long)]
+        out: String,
+        /// Version to pack of each prompt (latest, a version number or a tag); prompts
+        /// without it are left out
+        #[arg(long, default_value = "latest")]
+        selector: String,
+    },
+    /// Install the prompts of a package file into this vault
+    Install {
+        /// Package file written by `promptpro pack`
+        path: String,
+        /// Namespace to install into (defaults to the one the package was packed from)
+        #[arg(long)]
+        into: Option<String>,
+        /// When a prompt exists with other content: `fail` (default, installs nothing),
+        /// `skip` it or `update` it with a new version
+        #[arg(long, default_value = "fail")]
+        on_conflict: String,
+    },
+    /// List prompt keys
+    #[command(visible_alias = "ls")]
+    List {
+        /// Only list keys starting with this prefix, e.g. `team/agent/`
+        prefix: Option<String>,
+        /// Order keys by `name`, `modified` (newest first), `usage` (most served first)
+        /// or `tokens` (largest first)
+        #[arg(long, default_value = "name")]
+        sort: String,
+    },
+    /// Show history of a prompt
+    #[command(visible_alias = "hist")]
+    History {
+        /// Key of the prompt
+        key: String,
+        /// Show previews of a password-protected prompt (asks for its passphrase)
+        #[arg(long)]
+        unlock: bool,
+        /// Versions written since a date (2024-05-01), an RFC 3339 time or a window (7d)
+        #[arg(long)]
+        since: Option<String>,
+        /// Versions written until a date (inclusive), an RFC 3339 time or a window
+        #[arg(long)]
+        until: Option<String>,
+        /// Versions carrying this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Versions whose message contains this text, ignoring case
+        #[arg(long)]
+        message_contains: Option<String>,
+        /// Show at most this many versions, the newest ones
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Skip this many of the newest matching versions, to page back
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+    },
+    /// Tag a specific version of a prompt
+    Tag {
+        /// Key of the prompt
+        key: String,
+        /// Tag name
+        tag: String,
+        /// Version number (optional, defaults to latest)
+        version: Option<u64>,
+        /// Offer the closest existing key when this one is mistyped (asks first)
+        #[arg(long)]
+        auto_correct: bool,
+    },
+    /// Remove a tag from a prompt
+    Untag {
+        /// Key of the prompt
+        key: String,
+        /// Tag name to remove
+        tag: String,
+    },
+    /// Promote a tag to the latest version
+    Promote {
+        /// Key of the prompt
+        key: String,
+        /// Tag name to promote
+        tag: String,
+    },
+    /// Restore an earlier version or tag as the new latest version
+    Rollback {
+        /// Key of the prompt
+        key: String,
+        /// Version or tag to restore
+        selector: String,
+    },
+    /// Apply the change a version made (against its parent) to the latest version,
+    /// merging it with the changes made since
+    CherryPick {
+        /// Key of the prompt
+        key: String,
+        /// Version or tag whose change to carry forward
+        selector: String,
+    },
+    /// Open TUI editor
+    Tui {
+        /// Password-protected prompt to show instead of redacting (repeatable)
+        #[arg(long)]
+        unlock: Vec<String>,
+        /// Disable editing, tagging, adding and deleting (also PROMPTPRO_TUI_READ_ONLY=1)
+        #[arg(long)]
+        read_only: bool,
+    },
+    /// Edit a prompt in TUI mode
+    Edit {
+        /// Key of the prompt to edit
+        key: String,
+        /// Unlock the prompt if it is password-protected (asks for its passphrase)
+        #[arg(long)]
+        unlock: bool,
+    },
+    /// Dump the vault to a binary file
+    Dump {
+        /// Output file path for the dump
+        output: String,
+        /// Password to encrypt the dump (visible in shell history; prefer the options below)
+        #[arg(long)]
+        password: Option<String>,
+        /// Read the encryption password from a file
+        #[arg(long)]
+        password_file: Option<String>,
+        /// Encrypt using PROMPTPRO_PASSWORD, the OS keyring, or an interactive prompt
+        #[arg(long)]
+        encrypt: bool,
+        /// Memory in KiB for deriving the encryption key with Argon2id
+        #[arg(long)]
+        kdf_memory: Option<u32>,
+        /// Argon2id iterations for deriving the encryption key
+        #[arg(long)]
+        kdf_iterations: Option<u32>,
+    },
+    /// Vault statistics
+    #[command(args_conflicts_with_subcommands = true)]
+    Stats {
+        #[command(subcommand)]
+        action: Option<StatsCommands>,
+        /// Only this prompt, or the prompts of this namespace
+        key: Option<String>,
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write the vault README and notes on prompts, served at /docs by `serve`
+    Docs {
+        #[command(subcommand)]
+        action: DocsCommands,
+    },
+    /// Give prompts other names, which `serve` resolves before looking a key up
+    Alias {
+        #[command(subcommand)]
+        action: AliasCommands,
+    },
+    /// Generate code naming the vault's prompt keys, so stale references fail to compile
+    Codegen {
+        #[command(subcommand)]
+        action: CodegenCommands,
+    },
+    /// Serve the vault over HTTP
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        /// Keep the latest versions and tags of every prompt in memory
+        #[arg(long)]
+        preload: bool,
+        /// Page cache size in MiB (sled's default when unset)
+        #[arg(long, value_name = "MIB")]
+        cache_mb: Option<u64>,
+        /// Check, repair and garbage-collect the vault in the background at about
+        /// this interval, e.g. 6h
+        #[arg(long, value_name = "WINDOW")]
+        sweep_every: Option<String>,
+        /// Versions of each prompt kept by background sweeps
+        #[arg(long, requires = "sweep_every")]
+        sweep_keep_last: Option<usize>,
+        /// Versions written within this window are kept by background sweeps, e.g. 90d
+        #[arg(long, value_name = "WINDOW", requires = "sweep_every")]
+        sweep_keep_newer_than: Option<String>,
+    },
+    /// Send the versions and tags a remote vault is missing
+    Push {
+        /// Vault path, `promptpro serve` URL (http://host:port), ssh://host/path or
+        /// s3://bucket/prefix (default: the remote of the profile)
+        remote: Option<String>,
+    },
+    /// Fetch the versions and tags this vault is missing from a remote vault
+    Pull {
+        /// Vault path, `promptpro serve` URL (http://host:port), ssh://host/path or
+        /// s3://bucket/prefix (default: the remote of the profile)
+        remote: Option<String>,
+    },
+    /// List the profiles of ~/.promptpro/config.toml, marking the selected one
+    Profiles,
+    /// Read and change settings of ~/.promptpro/config.toml, such as user.name
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+    /// Mirror every prompt as a `<key>.txt` file in a Git repository, one commit per
+    /// version, and bring commits to those files back as new versions (requires the
+    /// `git` feature)
+    GitSync {
+        /// Repository to sync with; created if it does not exist
+        #[arg(long)]
+        repo: String,
+        /// Side whose content wins for prompts changed in both since the last sync:
+        /// `vault` or `git` (by default such prompts fail the sync)
+        #[arg(long)]
+        prefer: Option<String>,
+    },
+    /// Answer `push` and `pull` over ssh (used internally)
+    #[command(name = "sync-remote", hide = true)]
+    SyncRemote {
+        #[command(subcommand)]
+        action: SyncRemoteCommands,
+    },
+    /// Export every prompt with its history and tags as JSON, YAML or Markdown
+    Export {
+        /// Output file (defaults to stdout)
+        output: Option<String>,
+        /// Output file, as an alternative to the positional argument
+        #[arg(long, value_name = "FILE", conflicts_with = "output")]
+        out: Option<String>,
+        /// Output format (json, yaml, markdown or web-bundle; guessed from the file
+        /// extension, except web-bundle)
+        #[arg(long)]
+        format: Option<String>,
+        /// With web-bundle, bundle the first of these tags each prompt has, skipping
+        /// prompts with none (default: the latest versions)
+        #[arg(long, value_delimiter = ',')]
+        tags: Vec<String>,
+        /// With web-bundle, fill in a placeholder as name=value (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE")]
+        vars: Vec<String>,
+    },
+    /// Export one prompt as a Markdown document for design docs and audits (stable
+    /// content, metadata and a changelog with per-version diffs), or as JSON or YAML
+    ExportKey {
+        /// Key of the prompt to export
+        key: String,
+        /// Output file (defaults to stdout)
+        output: Option<String>,
+        /// Output format (markdown, json or yaml; guessed from the file extension)
+        #[arg(long)]
+        format: Option<String>,
+    },
+    /// Write the selected version of each prompt as a Jinja2 or Handlebars template file
+    /// with a frontmatter header (key, version, tags, variables)
+    ExportTemplates {
+        /// Directory to write `<key>.j2` or `<key>.hbs` files to
+        #[arg(long)]
+        dir: String,
+        /// Template syntax: jinja2 or handlebars
+        #[arg(long, default_value = "jinja2")]
+        syntax: String,
+        /// Version to write of each prompt (latest, a version number or a tag); prompts
+        /// without it are skipped
+        #[arg(long, default_value = "latest")]
+        selector: String,
+        /// Only keys starting with this prefix
+        #[arg(long, default_value = "")]
+        prefix: String,
+    },
+    /// Import prompts from a JSON, YAML or Markdown export, LangChain, PromptLayer or
+    /// plain YAML prompt definitions, or the `*_PROMPT` string constants of Rust or
+    /// Python source code
+    Import {
+        /// File to import, a directory of LangChain prompts, or the source directory or
+        /// file with --from
+        input: String,
+        /// Input format: json, yaml or markdown (guessed from the file extension), or
+        /// langchain-hub or promptlayer. YAML that is not a promptpro export is read as
+        /// a mapping of keys to prompts
+        #[arg(long, conflicts_with = "from")]
+        format: Option<String>,
+        /// Scan source code instead: rust-src or python-src
+        #[arg(long)]
+        from: Option<String>,
+        /// With --from, list the constants found and their proposed keys without
+        /// importing them
+        #[arg(long, requires = "from")]
+        dry_run: bool,
+    },
+    /// Create or update one prompt per .txt/.md file of a directory, keyed by its
+    /// relative path without the extension; unchanged files are skipped
+    ImportDir {
+        /// Directory of prompt files
+        dir: String,
+        /// Show what would change without writing to the vault
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Import a directory of prompt files, then keep watching it: every saved .txt/.md
+    /// file becomes a new version with a generated message
+    Watch {
+        /// Directory of prompt files
+        dir: String,
+    },
+    /// Pin the latest version of every prompt, with its content hash, in a lockfile
+    Lock {
+        /// Lockfile to write
+        #[arg(long, default_value = "prompts.lock")]
+        output: String,
+        /// Check the vault against the lockfile instead of writing it
+        #[arg(long)]
+        check: bool,
+    },
+    /// Restore/Resume the vault from a binary file
+    Resume {
+        /// Input file path to restore from
+        input: String,
+        /// Password to decrypt the dump (visible in shell history; prefer the options below)
+        #[arg(long)]
+        password: Option<String>,
+        /// Read the decryption password from a file
+        #[arg(long)]
+        password_file: Option<String>,
+        /// Only print the dump's header (format, encryption, writing release)
+        #[arg(long)]
+        inspect: bool,
+        /// Restore into the vault at this path instead of merging into the current vault
+        #[arg(long)]
+        target: Option<String>,
+        /// Replace the target vault if it already holds prompts
+        #[arg(long)]
+        force: bool,
+        /// When merging into the current vault, settle keys both sides changed with
+        /// `keep-local` (default), `keep-remote`, `keep-both` or `interactive`
+        #[arg(long)]
+        on_conflict: Option<String>,
+    },
+    /// Show or change how stored content and diffs are compressed; changing it
+    /// rewrites what is already stored
+    Compression {
+        /// New codec: zstd, lz4 or none
+        codec: Option<String>,
+    },
+    /// Upgrade a vault written by an older release to the current storage layout,
+    /// after dumping it to a backup
+    Migrate {
+        /// Where to write the pre-migration dump (default: next to the vault)
+        #[arg(long)]
+        backup: Option<String>,
+        /// Number of randomly chosen versions compared before and after
+        #[arg(long, default_value_t = 50)]
+        samples: usize,
+        /// Only report the vault's layout and whether it needs migrating
+        #[arg(long)]
+        check: bool,
+    },
+    /// Check that every version reads back with its content hash and that tags, parents,
+    /// the key index and shared content agree with the stored versions
+    Fsck {
+        /// Remove dangling tags and entries, and fix the key index and reference counts
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Remove old versions outside a retention policy and entries no version uses;
+    /// the latest version of each prompt is always kept
+    Gc {
+        /// Keep this many of the latest versions of each prompt
+        #[arg(long)]
+        keep_last: Option<usize>,
+        /// Keep versions written within this window, e.g. 90d
+        #[arg(long)]
+        keep_newer_than: Option<String>,
+        /// Remove tagged versions too when they fall outside the policy
+        #[arg(long)]
+        drop_tagged: bool,
+        /// Rewrite the vault afterwards so the freed space is returned to the disk
+        #[arg(long)]
+        compact: bool,
+        /// List the versions that would be removed without changing the vault
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Encrypt a prompt with its own passphrase
+    Protect {
+        /// Key of the prompt to protect
+        key: String,
+        /// Also store the passphrase in the OS keyring
+        #[arg(long)]
+        save_to_keyring: bool,
+    },
+    /// Remove passphrase protection from a prompt
+    Unprotect {
+        /// Key of the prompt to unprotect
+        key: String,
+        /// Read the passphrase from a file
+        #[arg(long)]
+        passphrase_file: Option<String>,
+    },
+    /// Manage dump passwords stored in the OS keyring
+    Password {
+        #[command(subcommand)]
+        action: PasswordCommands,
+    },
+    /// Delete a prompt by key
+    #[command(visible_alias = "rm")]
+    Delete {
+        /// Key of the prompt to delete, or a namespace with --recursive
+        key: String,
+        /// Delete every prompt inside the namespace `key`
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Print copy-pasteable example workflows
+    Examples {
+        /// Only show examples of this subcommand
+        command: Option<String>,
+    },
+}
+
+/// Prompt metadata flags of `add` and `update`
+#[derive(Args, Debug, Default)]
+pub struct MetadataArgs {
+    /// What the prompt is for
+    #[arg(long)]
+    description: Option<String>,
+    /// Author of the prompt, also recorded on the new version
+    #[arg(long)]
+    author: Option<String>,
+    /// Model the prompt is written for
+    #[arg(long)]
+    model: Option<String>,
+    /// Suggested sampling temperature
+    #[arg(long)]
+    temperature: Option<f32>,
+    /// Label as NAME=VALUE, repeatable; an empty value removes the label
+    #[arg(long = "label", value_name = "NAME=VALUE")]
+    labels: Vec<String>,
+}
+
+impl MetadataArgs {
+    fn into_meta(self) -> Result<PromptMeta> {
+        if let Some(temperature) = self.temperature {
+            if !temperature.is_finite() || temperature < 0.0 {
+                return Err(anyhow::anyhow!("Invalid --temperature {}", temperature));
+            }
+        }
+        let labels = self
+            .labels
+            .iter()
+            .map(|label| match label.split_once('=') {
+                Some((name, value)) => Ok((name.to_string(), value.to_string())),
+                None => Err(anyhow::anyhow!("Invalid --label '{}', expected name=value", label)),
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(PromptMeta {
+            description: self.description,
+            author: self.author,
+            model: self.model,
+            temperature: self.temperature,
+            labels,
+        })
+    }
+}
+
+#[derive(Subcommand)]
+pub enum PasswordCommands {
+    /// Store a password in the OS keyring (prompted, never echoed)
+    Set {
+        /// Keyring entry name
+        #[arg(long, default_value = "default")]
+        entry: String,
+    },
+    /// Remove a password from the OS keyring
+    Clear {
+        /// Keyring entry name
+        #[arg(long, default_value = "default")]
+        entry: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DocsCommands {
+    /// Print the vault README, or the notes of a prompt
+    Show {
+        /// Prompt whose notes to print
+        key: Option<String>,
+    },
+    /// Set the vault README from a file (`-` reads stdin)
+    Readme {
+        file: Option<String>,
+        /// Remove the README
+        #[arg(long, conflicts_with = "file")]
+        clear: bool,
+    },
+    /// Set the notes of a prompt
+    Note {
+        key: String,
+        /// Text of the notes
+        text: Option<String>,
+        /// Read the notes from a file (`-` reads stdin)
+        #[arg(long, conflicts_with = "text")]
+        file: Option<String>,
+        /// Remove the notes
+        #[arg(long, conflicts_with_all = ["text", "file"])]
+        clear: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AliasCommands {
+    /// Make an alias name an existing prompt, replacing what it named before
+    Set {
+        alias: String,
+        key: String,
+    },
+    /// Remove an alias
+    Remove {
+        alias: String,
+    },
+    /// List the aliases with the prompts they name
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum CodegenCommands {
+    /// A Rust module with a `pub const` per prompt key, namespaces as nested modules
+    Rust {
+        /// File to write (stdout when omitted)
+        #[arg(long)]
+        out: Option<String>,
+        /// Only keys starting with this prefix
+        #[arg(long, default_value = "")]
+        prefix: String,
+        /// Also generate a struct of the template variables of each prompt
+        #[arg(long)]
+        render_helpers: bool,
+        /// Fail if the file differs from what would be generated, instead of writing it
+        #[arg(long, requires = "out")]
+        check: bool,
+    },
+    /// A Python module with an enum of the prompt keys and a render function per prompt
+    Python {
+        /// File to write (stdout when omitted)
+        #[arg(long)]
+        out: Option<String>,
+        /// Only keys starting with this prefix
+        #[arg(long, default_value = "")]
+        prefix: String,
+        /// Fail if the file differs from what would be generated, instead of writing it
+        #[arg(long, requires = "out")]
+        check: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum StatsCommands {
+    /// Export usage counters, metrics and audit events
+    Export {
+        /// Output format (csv or parquet)
+        #[arg(long, default_value = "csv")]
+        format: String,
+        /// Output directory
+        #[arg(long)]
+        out: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ScoreCommands {
+    /// Record the score of a version for a metric, replacing the one recorded before
+    Record {
+        key: String,
+        /// Version scored: a version (3 or v3), a tag or latest
+        selector: String,
+        /// Name of the metric, e.g. accuracy
+        metric: String,
+        value: f64,
+        /// What measured the score, e.g. eval-ci
+        #[arg(long, default_value = "cli")]
+        source: String,
+    },
+    /// List the scores of a prompt, or of one version
+    List {
+        key: String,
+        /// Only the scores of this version
+        selector: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ChatCommands {
+    /// Store the messages of a chat prompt, adding the prompt if it does not exist
+    Set {
+        key: String,
+        /// Message as ROLE=TEXT, where ROLE is system, user or assistant; repeat for
+        /// each message, in order
+        #[arg(long = "part", value_name = "ROLE=TEXT", required_unless_present = "file")]
+        parts: Vec<String>,
+        /// Read the messages from a YAML or JSON list of {role, content} instead, e.g.
+        /// OpenAI messages (`-` for stdin)
+        #[arg(long, conflicts_with = "parts")]
+        file: Option<String>,
+        /// Optional message for the update
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+    /// Print the messages of a chat prompt with their placeholders filled in
+    Show {
+        key: String,
+        /// Selector: latest, a version (3 or v3), a tag, or a time (@2024-05-01T12:00, @-7d)
+        selector: Option<String>,
+        /// Output format: text, openai (messages JSON) or anthropic (system and messages)
+        #[arg(long, default_value = "text")]
+        format: String,
+        /// Placeholder value as name=value (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE")]
+        vars: Vec<String>,
+        /// Fail if a placeholder has no value
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Replace the text of one message of the latest version, keeping the others
+    SetPart {
+        key: String,
+        /// Number of the message, from 1 as `chat show` lists them
+        index: usize,
+        text: String,
+        /// Optional message for the update
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TestCaseCommands {
+    /// Add a test case, replacing the one of the same name
+    Add {
+        key: String,
+        /// Name of the test case
+        name: String,
+        /// Placeholder value as name=value (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE")]
+        vars: Vec<String>,
+        /// The answer must contain this text (repeatable)
+        #[arg(long, value_name = "TEXT")]
+        contains: Vec<String>,
+        /// The answer must not contain this text (repeatable)
+        #[arg(long, value_name = "TEXT")]
+        not_contains: Vec<String>,
+        /// The answer must match this regular expression (repeatable)
+        #[arg(long, value_name = "REGEX")]
+        matches: Vec<String>,
+    },
+    /// List the test cases of a prompt
+    List { key: String },
+    /// Remove a test case
+    Remove { key: String, name: String },
+}
+
+#[derive(Subcommand)]
+pub enum ExperimentCommands {
+    /// Start an experiment serving each variant to its share of users
+    Create {
+        key: String,
+        /// Name of the experiment
+        name: String,
+        /// Variant as SELECTOR=WEIGHT, where SELECTOR is a version (3 or v3), a tag or
+        /// latest; repeat for each variant
+        #[arg(long = "variant", value_name = "SELECTOR=WEIGHT", required = true)]
+        variants: Vec<String>,
+    },
+    /// List experiments with how often each variant was served
+    List {
+        /// Only the experiments on this prompt
+        key: Option<String>,
+    },
+    /// Print the variant of the prompt a user is served, and count it
+    Sample {
+        key: String,
+        name: String,
+        /// Id of the user, which always gets the same variant
+        user_id: String,
+    },
+    /// Stop an experiment
+    Delete { key: String, name: String },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Print a setting
+    Get { name: String },
+    /// Change a setting, e.g. `user.name`, the author recorded on new versions
+    Set { name: String, value: String },
+    /// Remove a setting
+    Unset { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum SyncRemoteCommands {
+    /// Print the syncable prompts of a vault as JSON
+    Fetch { vault: String },
+    /// Apply a sync patch read as JSON from stdin
+    Apply { vault: String },
+}
+
+/// Run a parsed command
+pub async fn dispatch(cli: Cli) -> Result<()> {
+    crate::profile::select_profile(cli.profile.as_deref())?;
+    if cli.offline {
+        crate::network::go_offline();
+    }
+    let lock_policy = if cli.wait {
+        LockPolicy::Wait(None)
+    } else if cli.snapshot {
+        LockPolicy::Snapshot
+    } else {
+        LockPolicy::Fail
+    };
+    let mut webhooks = configured_webhooks().unwrap_or_else(|e| {
+        eprintln!("⚠️ Webhooks of the config file disabled: {:#}", e);
+        Vec::new()
+    });
+    webhooks.extend(cli.webhook.into_iter().map(Webhook::new));
+    let explicit_author = match &cli.command {
+        Commands::Add { meta, .. } | Commands::Update { meta, .. } => meta.author.clone(),
+        Commands::Comment { author, .. } => author.clone(),
+        _ => None,
+    };
+    // A config file that cannot be read was reported with the webhooks above
+    let author = resolve_author(explicit_author.as_deref()).unwrap_or_default();
+    let validators = configured_validators().unwrap_or_default();
+    let cache_capacity = match &cli.command {
+        Commands::Serve { cache_mb, .. } => cache_mb.map(|cache_mb| cache_mb * 1024 * 1024),
+        _ => None,
+    };
+    let ctx = commands::Context::new()
+        .with_lock_policy(lock_policy)
+        .with_webhooks(WebhookSender::new(webhooks))
+        .with_author(author)
+        .with_validators(validators)
+        .with_cache_capacity(cache_capacity);
+    let result = match cli.command {
+        Commands::Init {
+            path,
+            local,
+            encrypted,
+            password_file,
+            save_to_keyring,
+        } => commands::init(path, local, encrypted, password_file, save_to_keyring).await,
+        Commands::Add { content, meta } => commands::add(&ctx, content, meta.into_meta()?).await,
+        Commands::Update {
+            key,
+            content,
+            message,
+            expected_parent,
+            passphrase_file,
+            meta,
+            auto_correct,
+        } => {
+            let passphrase = PasswordOptions::new(None, passphrase_file);
+            let meta = meta.into_meta()?;
+            commands::update(
+                &ctx,
+                key,
+                content,
+                message,
+                expected_parent,
+                passphrase,
+                meta,
+                auto_correct,
+            )
+            .await
+        }
+        Commands::Get {
+            key,
+            selector,
+            output,
+            passphrase_file,
+            auto_correct,
+            expect,
+        } => {
+            let passphrase = PasswordOptions::new(None, passphrase_file);
+            commands::get(&ctx, key, selector, output, passphrase, auto_correct, expect).await
+        }
+        Commands::Render {
+            key,
+            selector,
+            vars,
+            strict,
+            list_variables,
+        } => commands::render(&ctx, key, selector, vars, strict, list_variables).await,
+        Commands::Emit {
+            key,
+            selector,
+            format,
+            vars,
+            strict,
+            model,
+            max_tokens,
+            role,
+            compact,
+        } => {
+            let options = EmitOptions {
+                strict,
+                model,
+                max_tokens,
+                role: role.map(|role| role.parse()).transpose()?,
+            };
+            commands::emit(&ctx, key, selector, format, vars, options, compact).await
+        }
+        Commands::Deps {
+            key,
+            selector,
+            reverse,
+        } => commands::deps(&ctx, key, selector, reverse).await,
+        Commands::Tokens {
+            key,
+            selector,
+            model,
+            budget,
+        } => commands::tokens(&ctx, key, selector, model, budget).await,
+        Commands::Diff { key, old, new } => commands::diff(&ctx, key, old, new).await,
+        Commands::Compare {
+            key,
+            old,
+            new,
+            input,
+            vars,
+            run,
+            width,
+        } => commands::compare(&ctx, key, old, new, input, vars, run, width).await,
+        Commands::Blame { key, selector } => commands::blame(&ctx, key, selector).await,
+        Commands::Comment {
+            key,
+            text,
+            on,
+            reply_to,
+            author: _,
+        } => commands::comment(&ctx, key, text, on, reply_to).await,
+        Commands::Churn { since } => commands::churn(&ctx, since).await,
+        Commands::Search {
+            query,
+            regex,
+            case_sensitive,
+            all_versions,
+            context,
+        } => {
+            let options = SearchOptions {
+                regex,
+                case_sensitive,
+                all_versions,
+            };
+            commands::search(&ctx, query, options, context).await
+        }
+        Commands::Copy {
+            src,
+            dst,
+            history,
+            metadata,
+        } => commands::copy(&ctx, src, dst, history, metadata).await,
+        Commands::Adopt {
+            key,
+            from,
+            to,
+            history,
+        } => commands::adopt(&ctx, key, from, to, history).await,
+        Commands::Pack {
+            namespace,
+            out,
+            selector,
+        } => commands::pack(&ctx, namespace, out, selector).await,
+        Commands::Install {
+            path,
+            into,
+            on_conflict,
+        } => commands::install(&ctx, path, into, on_conflict.parse()?).await,
+        Commands::History {
+            key,
+            unlock,
+            since,
+            until,
+            tag,
+            message_contains,
+            limit,
+            offset,
+        } => {
+            let now = chrono::Utc::now();
+            let query = HistoryQuery {
+                since: since.map(|since| parse_time(&since, now, false)).transpose()?,
+                until: until.map(|until| parse_time(&until, now, true)).transpose()?,
+                tag,
+                message_contains,
+                limit,
+                offset,
+            };
+            commands::history(&ctx, key, unlock, query).await
+        }
+        Commands::Tag {
+            key,
+            tag,
+            version,
+            auto_correct,
+        } => commands::tag(&ctx, key, tag, version, auto_correct).await,
+        Commands::Untag { key, tag } => commands::untag(&ctx, key, tag).await,
+        Commands::Promote { key, tag } => commands::promote(&ctx, key, tag).await,
+        Commands::Rollback { key, selector } => commands::rollback(&ctx, key, selector).await,
+        Commands::CherryPick { key, selector } => {
+            commands::cherry_pick(&ctx, key, selector).await
+        }
+        Commands::Tui { unlock, read_only } => commands::tui(&ctx, unlock, read_only).await,
+        Commands::Edit { key, unlock } => commands::edit(&ctx, key, unlock).await,
+        Commands::Dump {
+            output,
+            password,
+            password_file,
+            encrypt,
+            kdf_memory,
+            kdf_iterations,
+        } => {
+            let password = PasswordOptions::new(password, password_file);
+            let defaults = KdfParams::default();
+            let kdf = (kdf_memory.is_some() || kdf_iterations.is_some()).then(|| KdfParams {
+                memory_kib: kdf_memory.unwrap_or(defaults.memory_kib),
+                iterations: kdf_iterations.unwrap_or(defaults.iterations),
+                ..defaults
+            });
+            commands::dump(&ctx, output, password, encrypt, kdf).await
+        }
+        Commands::Stats { action, key, json } => match action {
+            Some(StatsCommands::Export { format, out }) => {
+                commands::stats_export(&ctx, format, out).await
+            }
+            None => commands::stats(&ctx, key, json).await,
+        },
+        Commands::Docs { action } => match action {
+            DocsCommands::Show { key } => commands::docs_show(&ctx, key).await,
+            DocsCommands::Readme { file, clear } => commands::docs_readme(&ctx, file, clear).await,
+            DocsCommands::Note {
+                key,
+                text,
+                file,
+                clear,
+            } => commands::docs_note(&ctx, key, text, file, clear).await,
+        },
+        Commands::Alias { action } => match action {
+            AliasCommands::Set { alias, key } => commands::alias_set(&ctx, alias, key).await,
+            AliasCommands::Remove { alias } => commands::alias_remove(&ctx, alias).await,
+            AliasCommands::List => commands::alias_list(&ctx).await,
+        },
+        Commands::Codegen { action } => match action {
+            CodegenCommands::Rust {
+                out,
+                prefix,
+                render_helpers,
+                check,
+            } => commands::codegen_rust(&ctx, out, prefix, render_helpers, check).await,
+            CodegenCommands::Python { out, prefix, check } => {
+                commands::codegen_python(&ctx, out, prefix, check).await
+            }
+        },
+        Commands::Serve {
+            host,
+            port,
+            preload,
+            sweep_every,
+            sweep_keep_last,
+            sweep_keep_newer_than,
+            // Passed to the context above
+            cache_mb: _,
+        } => {
+            let sweep =
+                commands::sweep_options(sweep_every, sweep_keep_last, sweep_keep_newer_than)?;
+            commands::serve(&ctx, host, port, preload, sweep).await
+        }
+        Commands::Push { remote } => commands::push(&ctx, remote).await,
+        Commands::Pull { remote } => commands::pull(&ctx, remote).await,
+        Commands::Profiles => commands::profiles().await,
+        Commands::Score { action } => match action {
+            ScoreCommands::Record {
+                key,
+                selector,
+                metric,
+                value,
+                source,
+            } => commands::score_record(&ctx, key, selector, metric, value, source).await,
+            ScoreCommands::List { key, selector } => {
+                commands::score_list(&ctx, key, selector).await
+            }
+        },
+        Commands::Chat { action } => match action {
+            ChatCommands::Set {
+                key,
+                parts,
+                file,
+                message,
+            } => commands::chat_set(&ctx, key, parts, file, message).await,
+            ChatCommands::Show {
+                key,
+                selector,
+                format,
+                vars,
+                strict,
+            } => commands::chat_show(&ctx, key, selector, format, vars, strict).await,
+            ChatCommands::SetPart {
+                key,
+                index,
+                text,
+                message,
+            } => commands::chat_set_part(&ctx, key, index, text, message).await,
+        },
+        Commands::Test {
+            key,
+            selectors,
+            show_output,
+        } => commands::test(&ctx, key, selectors, show_output).await,
+        Commands::TestCase { action } => match action {
+            TestCaseCommands::Add {
+                key,
+                name,
+                vars,
+                contains,
+                not_contains,
+                matches,
+            } => {
+                let assertions = contains
+                    .into_iter()
+                    .map(Assertion::Contains)
+                    .chain(not_contains.into_iter().map(Assertion::NotContains))
+                    .chain(matches.into_iter().map(Assertion::Matches))
+                    .collect();
+                commands::test_case_add(&ctx, key, name, vars, assertions).await
+            }
+            TestCaseCommands::List { key } => commands::test_case_list(&ctx, key).await,
+            TestCaseCommands::Remove { key, name } => {
+                commands::test_case_remove(&ctx, key, name).await
+            }
+        },
+        Commands::Experiment { action } => match action {
+            ExperimentCommands::Create {
+                key,
+                name,
+                variants,
+            } => commands::experiment_create(&ctx, key, name, variants).await,
+            ExperimentCommands::List { key } => commands::experiment_list(&ctx, key).await,
+            ExperimentCommands::Sample { key, name, user_id } => {
+                commands::experiment_sample(&ctx, key, name, user_id).await
+            }
+            ExperimentCommands::Delete { key, name } => {
+                commands::experiment_delete(&ctx, key, name).await
+            }
+        },
+        Commands::Config { action } => match action {
+            ConfigCommands::Get { name } => commands::config_get(name).await,
+            ConfigCommands::Set { name, value } => commands::config_set(name, value).await,
+            ConfigCommands::Unset { name } => commands::config_unset(name).await,
+        },
+        Commands::GitSync { repo, prefer } => commands::git_sync(&ctx, repo, prefer).await,
+        Commands::SyncRemote { action } => match action {
+            SyncRemoteCommands::Fetch { vault } => commands::sync_remote_fetch(vault).await,
+            SyncRemoteCommands::Apply { vault } => commands::sync_remote_apply(vault).await,
+        },
+        Commands::Export {
+            output,
+            out,
+            format,
+            tags,
+            vars,
+        } => commands::export(&ctx, output.or(out), format, tags, vars).await,
+        Commands::ExportKey {
+            key,
+            output,
+            format,
+        } => commands::export_key(&ctx, key, output, format).await,
+        Commands::ExportTemplates {
+            dir,
+            syntax,
+            selector,
+            prefix,
+        } => commands::export_templates(&ctx, dir, syntax.parse()?, selector, prefix).await,
+        Commands::Import {
+            input,
+            from: Some(from),
+            dry_run,
+            ..
+        } => commands::import_source(&ctx, input, from, dry_run).await,
+        Commands::Import { input, format, .. } => commands::import(&ctx, input, format).await,
+        Commands::ImportDir { dir, dry_run } => commands::import_dir(&ctx, dir, dry_run).await,
+        Commands::Watch { dir } => commands::watch(&ctx, dir).await,
+        Commands::Lock { output, check } => commands::lock(&ctx, output, check).await,
+        Commands::Resume {
+            input,
+            inspect: true,
+            ..
+        } => commands::inspect_dump(input).await,
+        Commands::Resume {
+            input,
+            password,
+            password_file,
+            inspect: false,
+            target,
+            force,
+            on_conflict,
+        } => {
+            let password = PasswordOptions::new(password, password_file);
+            commands::resume(&ctx, input, password, target, force, on_conflict).await
+        }
+        Commands::Compression { codec } => commands::compression(&ctx, codec).await,
+        Commands::Migrate {
+            backup,
+            samples,
+            check,
+        } => commands::migrate(&ctx, backup, samples, check).await,
+        Commands::Fsck { repair } => commands::fsck(&ctx, repair).await,
+        Commands::Gc {
+            keep_last,
+            keep_newer_than,
+            drop_tagged,
+            compact,
+            dry_run,
+        } => {
+            commands::gc(&ctx, keep_last, keep_newer_than, drop_tagged, compact, dry_run).await
+        }
+        Commands::Protect {
+            key,
+            save_to_keyring,
+        } => commands::protect(&ctx, key, save_to_keyring).await,
+        Commands::Unprotect { key, passphrase_file } => {
+            commands::unprotect(&ctx, key, PasswordOptions::new(None, passphrase_file)).await
+        }
+        Commands::Password { action } => match action {
+            PasswordCommands::Set { entry } => commands::password_set(entry).await,
+            PasswordCommands::Clear { entry } => commands::password_clear(entry).await,
+        },
+        Commands::Delete { key, recursive } => commands::delete(&ctx, key, recursive).await,
+        Commands::List { prefix, sort } => commands::list(&ctx, prefix, sort).await,
+        Commands::Examples { command } => print_examples(command),
+    };
+    ctx.flush_webhooks().await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition() {
+        command().debug_assert();
+    }
+
+    #[test]
+    fn test_examples_name_real_subcommands() {
+        let command = command();
+        for (name, _) in EXAMPLES {
+            assert!(command.find_subcommand(name).is_some(), "unknown subcommand {}", name);
+        }
+    }
+
+    #[test]
+    fn test_aliases() {
+        let cli = try_parse_from(vec!["promptpro".into(), "rm".into(), "writer".into()]).unwrap();
+        assert!(matches!(cli.command, Commands::Delete { key, .. } if key == "writer"));
+    }
+}
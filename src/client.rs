@@ -0,0 +1,261 @@
+//! Client for a vault served by `promptpro serve`, with an on-disk cache that keeps
+//! prompts available while the server is unreachable.
+
+use crate::network;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use reqwest::header::IF_NONE_MATCH;
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long a cached prompt is served without asking the server
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// A prompt read from a remote vault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemotePrompt {
+    pub key: String,
+    /// Version the selector resolved to
+    pub version: u64,
+    pub content: String,
+    /// Served from the cache because the server could not be reached
+    pub stale: bool,
+}
+
+/// The server's answer to `GET /prompts/{key}`
+#[derive(Deserialize)]
+struct PromptResponse {
+    key: String,
+    version: u64,
+    content: String,
+}
+
+/// A cached prompt, one JSON file per server, key and selector
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    version: u64,
+    content: String,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Reads prompts from a `promptpro serve` instance.
+///
+/// Prompts are cached on disk: within the TTL the cached copy is served without a
+/// request, afterwards it is revalidated with the last known version (a `304` keeps
+/// it), and when the server is down or failing it is served with `stale` set.
+#[derive(Debug, Clone)]
+pub struct RemotePromptVault {
+    base_url: String,
+    http: reqwest::Client,
+    cache_dir: Option<PathBuf>,
+    ttl: Duration,
+}
+
+impl RemotePromptVault {
+    /// Client for a server such as `http://prompts.internal:8080`, caching in
+    /// `~/.promptpro/remote_cache`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let cache_dir = std::env::var("HOME")
+            .ok()
+            .map(|home| PathBuf::from(home).join(".promptpro").join("remote_cache"));
+
+        RemotePromptVault {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            cache_dir,
+            ttl: DEFAULT_CACHE_TTL,
+        }
+    }
+
+    /// Cache prompts in `dir` instead of the default directory
+    pub fn with_cache_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.cache_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Never cache prompts on disk; every read goes to the server
+    pub fn without_cache(mut self) -> Self {
+        self.cache_dir = None;
+        self
+    }
+
+    /// Serve cached prompts without asking the server for `ttl`; zero revalidates on
+    /// every read
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Read a prompt; `selector` is a version number, `latest` or a tag name, and the
+    /// latest version when `None`
+    pub async fn get(&self, key: &str, selector: Option<&str>) -> Result<RemotePrompt> {
+        let cache_path = self.cache_path(key, selector);
+        let cached = cache_path.as_deref().and_then(read_cache);
+
+        if let Some(entry) = &cached {
+            let age = (Utc::now() - entry.fetched_at).to_std().unwrap_or_default();
+            if age < self.ttl {
+                return Ok(entry.to_prompt(false));
+            }
+        }
+
+        let mut request = self.http.get(self.prompt_url(key, selector)?);
+        if let Some(entry) = &cached {
+            request = request.header(IF_NONE_MATCH, format!("\"{}\"", entry.version));
+        }
+
+        let action = format!("Reading '{}' from {}", key, self.base_url);
+        let response = match network::send(&action, request).await {
+            Ok(response) if response.status().is_server_error() => {
+                Err(anyhow::anyhow!("Server answered {}", response.status()))
+            }
+            Ok(response) => Ok(response),
+            Err(e) => Err(e),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                let Some(entry) = cached else {
+                    return Err(e);
+                };
+                eprintln!(
+                    "⚠️ Serving cached '{}' v{} from {}: {}",
+                    key,
+                    entry.version,
+                    entry.fetched_at.format("%Y-%m-%d %H:%M:%S"),
+                    e
+                );
+                return Ok(entry.to_prompt(true));
+            }
+        };
+
+        let entry = match (response.status(), cached) {
+            (StatusCode::NOT_MODIFIED, Some(mut entry)) => {
+                entry.fetched_at = Utc::now();
+                entry
+            }
+            (status, _) if status.is_success() => {
+                let prompt: PromptResponse = response.json().await?;
+                CacheEntry {
+                    key: prompt.key,
+                    version: prompt.version,
+                    content: prompt.content,
+                    fetched_at: Utc::now(),
+                }
+            }
+            (status, _) => {
+                let message = response
+                    .json::<serde_json::Value>()
+                    .await
+                    .ok()
+                    .and_then(|body| body["error"].as_str().map(str::to_string))
+                    .unwrap_or_default();
+                return Err(anyhow::anyhow!("Server answered {}: {}", status, message));
+            }
+        };
+
+        if let Some(path) = &cache_path {
+            // A failing cache must not fail the read
+            let _ = write_cache(path, &entry);
+        }
+        Ok(entry.to_prompt(false))
+    }
+
+    /// `{base}/prompts/{key}?selector=...`, with the key as one encoded path segment
+    fn prompt_url(&self, key: &str, selector: Option<&str>) -> Result<Url> {
+        let mut url = Url::parse(&self.base_url)?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid server URL '{}'", self.base_url))?
+            .pop_if_empty()
+            .push("prompts")
+            .push(key);
+        if let Some(selector) = selector {
+            url.query_pairs_mut().append_pair("selector", selector);
+        }
+        Ok(url)
+    }
+
+    fn cache_path(&self, key: &str, selector: Option<&str>) -> Option<PathBuf> {
+        let dir = self.cache_dir.as_ref()?;
+        let id = format!("{}\0{}\0{}", self.base_url, key, selector.unwrap_or("latest"));
+        Some(dir.join(format!("{}.json", blake3::hash(id.as_bytes()))))
+    }
+}
+
+impl CacheEntry {
+    fn to_prompt(&self, stale: bool) -> RemotePrompt {
+        RemotePrompt {
+            key: self.key.clone(),
+            version: self.version,
+            content: self.content.clone(),
+            stale,
+        }
+    }
+}
+
+fn read_cache(path: &Path) -> Option<CacheEntry> {
+    let bytes = std::fs::read(path).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+fn write_cache(path: &Path, entry: &CacheEntry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Write then rename, so concurrent readers never see a partial file
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec(entry)?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::PromptVault;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_remote_vault_cache() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        vault.add("team/writer", "v1")?;
+        vault.tag("team/writer", "stable", 1)?;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let router = crate::server::router(vault.clone());
+        let server = tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let cache_dir = dir.path().join("cache");
+        let client = RemotePromptVault::new(&base_url)
+            .with_cache_dir(&cache_dir)
+            .with_ttl(Duration::ZERO);
+
+        let prompt = client.get("team/writer", None).await?;
+        assert_eq!((prompt.version, prompt.content.as_str()), (1, "v1"));
+
+        vault.update("team/writer", "v2", None)?;
+        assert_eq!(client.get("team/writer", None).await?.content, "v2");
+        assert_eq!(client.get("team/writer", Some("stable")).await?.content, "v1");
+        // Revalidated with a 304
+        assert_eq!(client.get("team/writer", None).await?.version, 2);
+        assert!(client.get("missing", None).await.is_err());
+
+        // A fresh client (no pooled connections) against a stopped server
+        server.abort();
+        let _ = server.await;
+        let offline = RemotePromptVault::new(&base_url)
+            .with_cache_dir(&cache_dir)
+            .with_ttl(Duration::ZERO);
+        let prompt = offline.get("team/writer", None).await?;
+        assert!(prompt.stale);
+        assert_eq!(prompt.content, "v2");
+        assert!(offline.get("team/reviewer", None).await.is_err());
+
+        Ok(())
+    }
+}
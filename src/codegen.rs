@@ -0,0 +1,456 @@
+//! Source code generated from the vault, for `promptpro codegen`.
+//!
+//! The Rust module has a `pub const` per prompt key, with namespaces as nested modules
+//! (`team/writer` → `team::WRITER`), so that code referring to a key removed from the
+//! vault stops compiling once the module is regenerated. Render helpers are structs
+//! with one field per `{{placeholder}}` of the latest version.
+//!
+//! The Python module has a `PromptKey` enum of the keys (`team/writer` →
+//! `PromptKey.TEAM_WRITER`) and a `render_team_writer(vault, *, topic)` function per
+//! prompt, whose keyword arguments are the placeholders of the latest version.
+
+use crate::namespace::NAMESPACE_SEPARATOR;
+use crate::render::template_variables;
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Words that cannot name a module
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+    "move", "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait",
+    "true", "type", "unsafe", "use", "where", "while",
+];
+
+/// Names a Python parameter cannot have; `vault` is the helpers' own parameter
+const PYTHON_RESERVED: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+    "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is",
+    "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "vault", "while",
+    "with", "yield",
+];
+
+/// Start of the Python module, up to the key enum
+const PYTHON_HEADER: &str = r#""""Prompt keys of the vault, generated by `promptpro codegen python`.
+
+Do not edit; regenerate after adding or removing prompts.
+"""
+
+import re
+from enum import Enum
+
+_PLACEHOLDER = re.compile(r"\{\{\s*([A-Za-z_][A-Za-z0-9_.-]*)\s*\}\}")
+"#;
+
+/// What the render functions call; placeholders without a value are left as they are,
+/// like `render` does
+const PYTHON_RENDER: &str = r#"
+
+def _render(vault, key, variables):
+    template = vault.get_latest(key.value)
+    return _PLACEHOLDER.sub(lambda m: variables.get(m.group(1), m.group(0)), template)
+"#;
+
+/// What `codegen rust` generates
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RustCodegen {
+    /// Only keys starting with this prefix
+    pub prefix: String,
+    /// Also generate a struct of the template variables of each prompt that has some
+    pub render_helpers: bool,
+}
+
+/// What `codegen python` generates
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PythonCodegen {
+    /// Only keys starting with this prefix
+    pub prefix: String,
+}
+
+/// Words of a key segment or variable name, split at anything but letters and digits
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            words.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+            previous_lower = false;
+            continue;
+        }
+        // camelCase boundaries start a word too
+        if c.is_ascii_uppercase() && previous_lower {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        current.push(c.to_ascii_lowercase());
+    }
+    words.extend((!current.is_empty()).then_some(current));
+    words
+}
+
+/// Make `ident` a valid identifier: never empty, never starting with a digit
+fn identifier(ident: String) -> String {
+    match ident.chars().next() {
+        None => "_".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("_{}", ident),
+        Some(_) => ident,
+    }
+}
+
+fn const_name(segment: &str) -> String {
+    identifier(words(segment).join("_").to_uppercase())
+}
+
+fn snake_name(name: &str) -> String {
+    let name = identifier(words(name).join("_"));
+    if RUST_KEYWORDS.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+fn python_name(name: &str) -> String {
+    let name = identifier(words(name).join("_"));
+    if PYTHON_RESERVED.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+/// A Python string literal; JSON escapes are valid Python ones
+fn python_str(value: &str) -> Result<String> {
+    Ok(serde_json::to_string(value)?)
+}
+
+fn camel_name(segment: &str) -> String {
+    let camel: String = words(segment)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect();
+    identifier(camel)
+}
+
+/// A prompt of the generated module
+struct Entry {
+    key: String,
+    /// Template variables, when render helpers are generated
+    variables: Vec<String>,
+}
+
+/// Keys of one module and its nested modules
+#[derive(Default)]
+struct Module {
+    entries: BTreeMap<String, Entry>,
+    children: BTreeMap<String, Module>,
+}
+
+impl Module {
+    fn insert(&mut self, segments: &[&str], entry: Entry) -> Result<()> {
+        match segments {
+            [] => Ok(()),
+            [last] => {
+                let name = const_name(last);
+                if let Some(other) = self.entries.get(&name) {
+                    return Err(anyhow::anyhow!(
+                        "Keys '{}' and '{}' would both be named {}; rename one of them",
+                        other.key,
+                        entry.key,
+                        name
+                    ));
+                }
+                self.entries.insert(name, entry);
+                Ok(())
+            }
+            [namespace, rest @ ..] => self
+                .children
+                .entry(snake_name(namespace))
+                .or_default()
+                .insert(rest, entry),
+        }
+    }
+
+    fn write(&self, out: &mut String, depth: usize) -> Result<()> {
+        let indent = "    ".repeat(depth);
+        let mut first = true;
+        for (name, entry) in &self.entries {
+            if !first {
+                out.push('\n');
+            }
+            first = false;
+            writeln!(out, "{}/// `{}`", indent, entry.key)?;
+            writeln!(out, "{}pub const {}: &str = {:?};", indent, name, entry.key)?;
+            if !entry.variables.is_empty() {
+                out.push('\n');
+                write_vars(out, &indent, name, entry)?;
+            }
+        }
+        for (name, child) in &self.children {
+            if !first {
+                out.push('\n');
+            }
+            first = false;
+            writeln!(out, "{}pub mod {} {{", indent, name)?;
+            child.write(out, depth + 1)?;
+            writeln!(out, "{}}}", indent)?;
+        }
+        Ok(())
+    }
+}
+
+/// A struct of the variables of `entry`, convertible to the map `render` takes
+fn write_vars(out: &mut String, indent: &str, name: &str, entry: &Entry) -> Result<()> {
+    let struct_name = format!("{}Vars", camel_name(name));
+    let mut fields = BTreeMap::new();
+    for variable in &entry.variables {
+        let field = snake_name(variable);
+        if let Some(other) = fields.insert(field.clone(), variable) {
+            return Err(anyhow::anyhow!(
+                "Variables '{}' and '{}' of '{}' would both be named {}",
+                other,
+                variable,
+                entry.key,
+                field
+            ));
+        }
+    }
+
+    writeln!(out, "{}/// Template variables of `{}`", indent, entry.key)?;
+    writeln!(out, "{}#[derive(Debug, Clone, Default)]", indent)?;
+    writeln!(out, "{}pub struct {}<'a> {{", indent, struct_name)?;
+    for variable in &entry.variables {
+        writeln!(out, "{}    /// `{{{{{}}}}}`", indent, variable)?;
+        writeln!(out, "{}    pub {}: &'a str,", indent, snake_name(variable))?;
+    }
+    writeln!(out, "{}}}", indent)?;
+    out.push('\n');
+    writeln!(out, "{}impl {}<'_> {{", indent, struct_name)?;
+    writeln!(out, "{}    /// The variables by placeholder name", indent)?;
+    writeln!(
+        out,
+        "{}    pub fn to_vars(&self) -> std::collections::HashMap<String, String> {{",
+        indent
+    )?;
+    writeln!(out, "{}        [", indent)?;
+    for variable in &entry.variables {
+        writeln!(
+            out,
+            "{}            ({:?}, self.{}),",
+            indent,
+            variable,
+            snake_name(variable)
+        )?;
+    }
+    writeln!(out, "{}        ]", indent)?;
+    writeln!(out, "{}        .into_iter()", indent)?;
+    writeln!(
+        out,
+        "{}        .map(|(name, value)| (name.to_string(), value.to_string()))",
+        indent
+    )?;
+    writeln!(out, "{}        .collect()", indent)?;
+    writeln!(out, "{}    }}", indent)?;
+    writeln!(out, "{}}}", indent)?;
+    Ok(())
+}
+
+/// `render_<name>`, rendering the latest version of `key` with its variables
+fn write_python_helper(
+    out: &mut String,
+    name: &str,
+    key: &str,
+    variables: &[String],
+) -> Result<()> {
+    let mut params = BTreeMap::new();
+    for variable in variables {
+        let param = python_name(variable);
+        if let Some(other) = params.insert(param.clone(), variable) {
+            return Err(anyhow::anyhow!(
+                "Variables '{}' and '{}' of '{}' would both be named {}",
+                other,
+                variable,
+                key,
+                param
+            ));
+        }
+    }
+
+    out.push_str("\n\n");
+    write!(out, "def render_{}(vault", name.to_lowercase())?;
+    if !variables.is_empty() {
+        out.push_str(", *");
+        for variable in variables {
+            write!(out, ", {}: str", python_name(variable))?;
+        }
+    }
+    out.push_str(") -> str:\n");
+    writeln!(out, "    \"\"\"Render `{}` with its template variables\"\"\"", key)?;
+    if variables.is_empty() {
+        out.push_str("    variables = {}\n");
+    } else {
+        out.push_str("    variables = {\n");
+        for variable in variables {
+            writeln!(out, "        {}: {},", python_str(variable)?, python_name(variable))?;
+        }
+        out.push_str("    }\n");
+    }
+    writeln!(out, "    return _render(vault, PromptKey.{}, variables)", name)?;
+    Ok(())
+}
+
+impl PromptVault {
+    /// A Rust module naming the keys of the vault (see the [module docs](self))
+    pub fn codegen_rust(&self, options: &RustCodegen) -> Result<String> {
+        let keys = self.list_keys_with_prefix(&options.prefix)?;
+        let mut root = Module::default();
+        for key in &keys {
+            let variables = if options.render_helpers && !self.is_sensitive(key)? {
+                template_variables(&self.get(key, VersionSelector::Latest)?)
+            } else {
+                Vec::new()
+            };
+            let segments: Vec<&str> = key.split(NAMESPACE_SEPARATOR).collect();
+            let entry = Entry {
+                key: key.clone(),
+                variables,
+            };
+            root.insert(&segments, entry)?;
+        }
+
+        let mut out = String::new();
+        out.push_str("//! Prompt keys of the vault, generated by `promptpro codegen rust`.\n");
+        out.push_str("//! Do not edit; regenerate after adding or removing prompts.\n\n");
+        out.push_str("#![allow(dead_code)]\n\n");
+        // A function, which no key constant or namespace module can clash with
+        out.push_str("/// Every key, in order\n");
+        out.push_str("pub const fn all() -> &'static [&'static str] {\n    &[\n");
+        for key in &keys {
+            writeln!(out, "        {:?},", key)?;
+        }
+        out.push_str("    ]\n}\n");
+        if !root.entries.is_empty() || !root.children.is_empty() {
+            out.push('\n');
+        }
+        root.write(&mut out, 0)?;
+        Ok(out)
+    }
+
+    /// A Python module naming the keys of the vault, with a render function per prompt
+    /// (see the [module docs](self))
+    pub fn codegen_python(&self, options: &PythonCodegen) -> Result<String> {
+        let keys = self.list_keys_with_prefix(&options.prefix)?;
+        let mut names: BTreeMap<String, &str> = BTreeMap::new();
+        for key in &keys {
+            let name = const_name(key);
+            if let Some(other) = names.insert(name.clone(), key) {
+                return Err(anyhow::anyhow!(
+                    "Keys '{}' and '{}' would both be named {}; rename one of them",
+                    other,
+                    key,
+                    name
+                ));
+            }
+        }
+
+        let mut out = PYTHON_HEADER.to_string();
+        out.push_str("\n\nclass PromptKey(str, Enum):\n    \"\"\"Every key, in order\"\"\"\n");
+        if !keys.is_empty() {
+            out.push('\n');
+        }
+        for key in &keys {
+            writeln!(out, "    {} = {}", const_name(key), python_str(key)?)?;
+        }
+        out.push_str(PYTHON_RENDER);
+        for key in &keys {
+            if self.is_sensitive(key)? {
+                continue;
+            }
+            let variables = template_variables(&self.get(key, VersionSelector::Latest)?);
+            write_python_helper(&mut out, &const_name(key), key, &variables)?;
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_codegen_rust() -> Result<()> {
+        assert_eq!(words("userName-2.id"), ["user", "name", "2", "id"]);
+        assert_eq!(snake_name("type"), "type_");
+        assert_eq!(const_name("3d-render"), "_3D_RENDER");
+
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("team/writer", "Write about {{topic}} for {{ audience.name }}.")?;
+        vault.add("team/type/review", "Review it.")?;
+        vault.add("summary", "Summarize.")?;
+
+        let plain = vault.codegen_rust(&RustCodegen::default())?;
+        assert!(plain.contains("    &[\n        \"summary\",\n"));
+        assert!(plain.contains("pub const SUMMARY: &str = \"summary\";"));
+        assert!(plain.contains(
+            "pub mod team {\n    /// `team/writer`\n    pub const WRITER: &str = \"team/writer\";"
+        ));
+        assert!(plain.contains("    pub mod type_ {\n"));
+        assert!(!plain.contains("Vars"));
+
+        let options = RustCodegen {
+            prefix: "team/".to_string(),
+            render_helpers: true,
+        };
+        let helpers = vault.codegen_rust(&options)?;
+        assert!(!helpers.contains("SUMMARY"));
+        assert!(helpers.contains("    pub struct WriterVars<'a> {"));
+        assert!(helpers.contains("        pub audience_name: &'a str,"));
+        assert!(helpers.contains("                (\"audience.name\", self.audience_name),"));
+
+        vault.add("team/Writer", "Duplicate.")?;
+        assert!(vault.codegen_rust(&RustCodegen::default()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_codegen_python() -> Result<()> {
+        assert_eq!(python_name("lambda"), "lambda_");
+        assert_eq!(python_name("vault"), "vault_");
+
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("team/writer", "Write about {{topic}} for {{ audience.name }}.")?;
+        vault.add("summary", "Summarize.")?;
+
+        let options = PythonCodegen {
+            prefix: String::new(),
+        };
+        let code = vault.codegen_python(&options)?;
+        assert!(code.contains("    SUMMARY = \"summary\"\n    TEAM_WRITER = \"team/writer\"\n"));
+        assert!(code.contains("def render_summary(vault) -> str:\n"));
+        assert!(code.contains("    variables = {}\n    return _render(vault, PromptKey.SUMMARY"));
+        assert!(code.contains(
+            "def render_team_writer(vault, *, topic: str, audience_name: str) -> str:\n"
+        ));
+        assert!(code.contains("        \"audience.name\": audience_name,\n"));
+        assert!(code.contains("    return _render(vault, PromptKey.TEAM_WRITER, variables)\n"));
+
+        vault.add("team-writer", "Duplicate.")?;
+        assert!(vault.codegen_python(&PythonCodegen::default()).is_err());
+
+        Ok(())
+    }
+}
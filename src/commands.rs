@@ -1,29 +1,222 @@
-use crate::storage::PromptVault;
+use crate::password::{
+    delete_from_keyring, prompt_keyring_entry, prompt_new_password, store_in_keyring,
+    vault_keyring_entry, PasswordOptions,
+};
+use crate::chat::{ChatMessage, ChatPrompt};
+use crate::churn::{parse_window, UNSTABLE_VERSIONS};
+use crate::codegen::{PythonCodegen, RustCodegen};
+use crate::comments::{comment_threads, Comment};
+use crate::compare::parse_input;
+use crate::compose::Dependency;
+use crate::compress::Compression;
+use crate::copy::CopyOptions;
+use crate::diff::{side_by_side, RowKind, SideBySideRow};
+use crate::dump::RestorePolicy;
+use crate::dump_merge::{ConflictPolicy, MergeReport};
+use crate::emit::{EmitOptions, RequestFormat};
+use crate::encryption::KdfParams;
+use crate::eval::{Assertion, LlmConfig, TestCase};
+use crate::experiment::ExperimentVariant;
+use crate::export::ExportFormat;
+use crate::gc::RetentionPolicy;
+use crate::history::HistoryQuery;
+use crate::import_dir::FileChange;
+use crate::import_external::{is_vault_export, read_external, ExternalFormat};
+use crate::import_source::{scan_source, SourceLanguage};
+use crate::metadata::PromptMeta;
+use crate::package::{InstallPolicy, Package};
+use crate::migrate::LAYOUT_VERSION;
+use crate::profile::{active_profile, ProfileConfig};
+use crate::protect::{UnlockedKeys, REDACTED};
+use crate::remote::{Remote, SyncPatch, SyncReport};
+use crate::search::{SearchOptions, SearchQuery};
+use crate::settings::{get_setting, set_setting, unset_setting};
+use crate::sort::KeyOrder;
+use crate::template_export::TemplateSyntax;
+use crate::stats::StatsFormat;
+use crate::storage::{PromptVault, VersionConflict};
+use crate::sweep::SweepOptions;
+use crate::tokens::TokenConfig;
+use crate::tui::TuiOptions;
 use crate::types::VersionSelector;
+use crate::validate::{ValidationError, Validator};
+use crate::vault_lock::LockPolicy;
+use crate::web_bundle::WebBundleOptions;
+use crate::webhook::WebhookSender;
 use anyhow::Result;
-use std::io::{self, Write};
+use std::collections::HashMap;
+use once_cell::sync::OnceCell;
+use std::io::{self, IsTerminal, Read, Write};
+
+/// Vault access for the command of one CLI invocation.
+///
+/// The vault is opened lazily, at most once, so commands can validate their arguments
+/// first. Read-only commands never create a missing vault.
+#[derive(Default)]
+pub struct Context {
+    vault: OnceCell<PromptVault>,
+    lock_policy: LockPolicy,
+    webhooks: WebhookSender,
+    author: Option<String>,
+    validators: Vec<Validator>,
+    cache_capacity: Option<u64>,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What to do when another process holds the vault (`--wait`, `--snapshot`)
+    pub fn with_lock_policy(mut self, lock_policy: LockPolicy) -> Self {
+        self.lock_policy = lock_policy;
+        self
+    }
+
+    /// Post the changes made to the vaults of this command to `webhooks`
+    pub fn with_webhooks(mut self, webhooks: WebhookSender) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
+
+    /// Record `author` on the versions this command stores
+    pub fn with_author(mut self, author: Option<String>) -> Self {
+        self.author = author;
+        self
+    }
+
+    /// Check the versions this command stores with `validators`
+    pub fn with_validators(mut self, validators: Vec<Validator>) -> Self {
+        self.validators = validators;
+        self
+    }
+
+    /// Open the vault with a page cache of `cache_capacity` bytes (`serve --cache-mb`)
+    pub fn with_cache_capacity(mut self, cache_capacity: Option<u64>) -> Self {
+        self.cache_capacity = cache_capacity;
+        self
+    }
+
+    /// Wait until the webhooks have received the changes made so far
+    pub async fn flush_webhooks(&self) {
+        self.webhooks.flush().await
+    }
+
+    /// The current vault for reading; fails instead of creating it when it is missing
+    fn vault(&self) -> Result<&PromptVault> {
+        self.vault.get_or_try_init(|| {
+            let path = crate::utils::current_vault_path()?;
+            if !path.exists() {
+                return Err(anyhow::anyhow!(
+                    "No vault at {:?}; run `ppro init` first",
+                    path
+                ));
+            }
+            self.open(&path, self.lock_policy)
+        })
+    }
+
+    /// The current vault for writing, created if it does not exist yet
+    fn writable_vault(&self) -> Result<&PromptVault> {
+        self.vault.get_or_try_init(|| {
+            let path = crate::utils::current_vault_path()?;
+            std::fs::create_dir_all(&path)?;
+            self.open(&path, self.write_lock_policy())
+        })
+    }
+
+    /// Open a vault recording the author and validating new versions, whose changes go
+    /// to the webhooks
+    fn open(&self, path: &std::path::Path, lock_policy: LockPolicy) -> Result<PromptVault> {
+        let vault = open_vault(path, lock_policy, self.cache_capacity)?
+            .with_author(self.author.clone());
+        for validator in &self.validators {
+            vault.add_validator(validator.clone());
+        }
+        self.webhooks.attach(&vault);
+        Ok(vault)
+    }
+
+    /// The lock policy for opening a vault to write to; writes to a snapshot would be
+    /// lost, so writers never fall back to one
+    fn write_lock_policy(&self) -> LockPolicy {
+        match self.lock_policy {
+            LockPolicy::Snapshot => LockPolicy::Fail,
+            policy => policy,
+        }
+    }
+}
+
+/// Open the vault at `path`, unlocking it if it is encrypted with the password from
+/// PROMPTPRO_PASSWORD, the vault's keyring entry, or an interactive prompt
+fn open_vault(
+    path: &std::path::Path,
+    lock_policy: LockPolicy,
+    cache_capacity: Option<u64>,
+) -> Result<PromptVault> {
+    let vault = PromptVault::open_with_policy_and_cache(path, lock_policy, cache_capacity)?;
+    if vault.is_encrypted()? {
+        let password = PasswordOptions::default()
+            .with_keyring_entry(vault_keyring_entry(path))
+            .resolve_or_prompt("Vault password: ")?;
+        vault.unlock(&password)?;
+    }
+    Ok(vault)
+}
 
 /// Initialize a new prompt vault
-pub async fn init(path: Option<String>) -> Result<()> {
+pub async fn init(
+    path: Option<String>,
+    local: bool,
+    encrypted: bool,
+    password_file: Option<String>,
+    save_to_keyring: bool,
+) -> Result<()> {
     let vault_path = match path {
         Some(p) => std::path::PathBuf::from(p),
-        None => {
-            let home_dir = std::env::var("HOME")?;
-            std::path::PathBuf::from(home_dir).join(".promptpro").join("default_vault")
+        None if local => crate::utils::project_vault_path(&std::env::current_dir()?),
+        None => match crate::utils::vault_path_from_env() {
+            Some(path) => path,
+            None => match crate::utils::profile_vault_path()? {
+                Some(path) => path,
+                None => crate::utils::default_vault_path()?,
+            },
         },
     };
 
     std::fs::create_dir_all(&vault_path)?;
-    let _vault = PromptVault::open(&vault_path)?;
-    
-    println!("Initialized prompt vault at: {:?}", vault_path);
+    let vault = PromptVault::open(&vault_path)?;
+    if !encrypted {
+        println!("Initialized prompt vault at: {:?}", vault_path);
+        return Ok(());
+    }
+
+    if vault.is_encrypted()? {
+        return Err(anyhow::anyhow!("The vault at {:?} is already encrypted", vault_path));
+    }
+    let password = match password_file {
+        Some(file) => PasswordOptions::new(None, Some(file)).resolve()?.unwrap_or_default(),
+        None => prompt_new_password()?,
+    };
+    let rewritten = vault.encrypt_at_rest(&password)?;
+
+    println!("Initialized encrypted prompt vault at: {:?}", vault_path);
+    if rewritten > 0 {
+        println!("    encrypted {} stored blob(s)", rewritten);
+    }
+    if save_to_keyring {
+        let entry = vault_keyring_entry(&vault_path);
+        store_in_keyring(&entry, &password)?;
+        println!("    password stored in the OS keyring as '{}'", entry);
+    }
     Ok(())
 }
 
 /// Add a new prompt
-pub async fn add(content: String) -> Result<()> {
-    let vault = PromptVault::open_default()?;
-
+pub async fn add(ctx: &Context, content: String, mut meta: PromptMeta) -> Result<()> {
+    if meta.author.is_none() {
+        meta.author = active_profile()?.and_then(|profile| profile.author.clone());
+    }
     print!("Enter key name: ");
     io::stdout().flush()?;
     
@@ -31,28 +224,62 @@ pub async fn add(content: String) -> Result<()> {
     io::stdin().read_line(&mut key)?;
     key = key.trim().to_string();
 
+    let vault = ctx.writable_vault()?;
     vault.add(&key, &content)?;
+    if !meta.is_empty() {
+        vault.update_prompt_meta(&key, meta)?;
+    }
     
     println!("[+] Stored prompt under key: {}", key);
     println!("    version: 1 (snapshot)");
+    warn_over_budget(&key, &content);
 
     Ok(())
 }
 
 /// Update an existing prompt
-pub async fn update(key: String, content: String, message: Option<String>) -> Result<()> {
-    let vault = PromptVault::open_default()?;
-    
-    match vault.update(&key, &content, message) {
+#[allow(clippy::too_many_arguments)]
+pub async fn update(
+    ctx: &Context,
+    key: String,
+    content: String,
+    message: Option<String>,
+    expected_parent: Option<u64>,
+    passphrase: PasswordOptions,
+    meta: PromptMeta,
+    auto_correct: bool,
+) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    let key = correct_key(vault, key, auto_correct)?;
+
+    let result = if vault.is_sensitive(&key)? {
+        let passphrase = resolve_passphrase(&key, passphrase)?;
+        if let Some(latest) = vault.get_latest_version_number(&key)? {
+            vault.check_parent(&key, expected_parent, latest)?;
+        }
+        vault.update_protected(&key, &content, message, &passphrase)
+    } else {
+        vault
+            .update_expecting(&key, &content, message, expected_parent)
+            .map(|_| ())
+    };
+
+    match result {
         Ok(()) => {
+            if !meta.is_empty() {
+                vault.update_prompt_meta(&key, meta)?;
+            }
             println!("[+] Updated prompt: {}", key);
             
             // Get the new latest version
-            if let Ok(Some(version)) = get_latest_version_number(&vault, &key) {
+            if let Ok(Some(version)) = get_latest_version_number(vault, &key) {
                 println!("    version: {} (updated)", version);
                 println!("    'dev' tag automatically updated to latest version");
             }
+            warn_over_budget(&key, &content);
         },
+        // Scripts relying on --expected-parent or validators need a failing exit code
+        Err(e) if e.is::<VersionConflict>() || e.is::<ValidationError>() => return Err(e),
         Err(e) => {
             eprintln!("Error updating prompt: {}", e);
         }
@@ -61,27 +288,125 @@ pub async fn update(key: String, content: String, message: Option<String>) -> Re
     Ok(())
 }
 
+/// Print the include tree of a prompt version, or the prompts including the prompt
+pub async fn deps(
+    ctx: &Context,
+    key: String,
+    selector: Option<String>,
+    reverse: bool,
+) -> Result<()> {
+    let vault = ctx.vault()?;
+    if reverse {
+        let dependents = vault.dependents(&key)?;
+        if dependents.is_empty() {
+            println!("No prompt includes '{}'", key);
+        }
+        for dependent in dependents {
+            println!("{}", dependent);
+        }
+        return Ok(());
+    }
+
+    let selector = VersionSelector::parse(selector.as_deref().unwrap_or("latest"))?;
+    let tree = vault.dependencies(&key, selector)?;
+    println!("{} v{}", tree.key, tree.selector);
+    print_dependencies(&tree.includes, "");
+    Ok(())
+}
+
+/// Print included prompts as the branches of a tree
+fn print_dependencies(dependencies: &[Dependency], prefix: &str) {
+    for (index, dependency) in dependencies.iter().enumerate() {
+        let last = index + 1 == dependencies.len();
+        let state = match dependency.version {
+            None => " (missing)".to_string(),
+            Some(version) if dependency.cycle => format!(" (v{}, cycle)", version),
+            Some(version) => format!(" (v{})", version),
+        };
+        println!(
+            "{}{}{}@{}{}",
+            prefix,
+            if last { "└── " } else { "├── " },
+            dependency.key,
+            dependency.selector,
+            state
+        );
+        let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        print_dependencies(&dependency.includes, &prefix);
+    }
+}
+
+/// Warn when a new version of `key` has more tokens than the configured budget
+fn warn_over_budget(key: &str, content: &str) {
+    match TokenConfig::load().and_then(|config| config.budget_warning(content)) {
+        Ok(Some(warning)) => eprintln!("⚠️ '{}' has {}", key, warning),
+        Ok(None) => {}
+        Err(e) => eprintln!("⚠️ Tokens of '{}' not counted: {}", key, e),
+    }
+}
+
+/// Print the number of tokens of a version of a prompt
+pub async fn tokens(
+    ctx: &Context,
+    key: String,
+    selector: Option<String>,
+    model: Option<String>,
+    budget: Option<u64>,
+) -> Result<()> {
+    let mut config = TokenConfig::load()?;
+    config.model = model.or(config.model);
+    config.budget = budget.or(config.budget);
+    let vault = ctx.vault()?;
+    let selector = VersionSelector::parse(selector.as_deref().unwrap_or("latest"))?;
+    let version = vault.resolve_version(&key, &selector)?;
+
+    let count = vault.token_count(&key, VersionSelector::Version(version), config.model())?;
+    println!("{} v{}: {}", key, version, count);
+    if !count.exact {
+        eprintln!("   (estimated; build promptpro with the `tiktoken` feature for exact counts)");
+    }
+    if let Some(budget) = config.budget.filter(|budget| count.tokens > *budget) {
+        eprintln!("⚠️ Over the budget of {} tokens", budget);
+    }
+    Ok(())
+}
+
 /// Get a prompt by key and selector
-pub async fn get(key: String, selector: Option<String>, output: Option<String>) -> Result<()> {
-    let vault = PromptVault::open_default()?;
+pub async fn get(
+    ctx: &Context,
+    key: String,
+    selector: Option<String>,
+    output: Option<String>,
+    passphrase: PasswordOptions,
+    auto_correct: bool,
+    expect: Option<String>,
+) -> Result<()> {
+    let vault = ctx.vault()?;
+    let key = correct_key(vault, key, auto_correct)?;
     
-    let sel = match selector {
-        Some(s) => {
-            // Try to parse as version number first
-            if let Ok(version) = s.parse::<u64>() {
-                VersionSelector::Version(version)
-            } else if s == "latest" {
-                VersionSelector::Latest
-            } else {
-                // Assume it's a tag - use a temporary string and make it static for this use case
-                // This is a simplified implementation, in a real one we'd handle lifetimes differently
-                VersionSelector::Tag(Box::leak(s.into_boxed_str()))
-            }
-        },
+    let mut sel = match &selector {
+        Some(s) => VersionSelector::parse(s)?,
         None => VersionSelector::Latest,
     };
+    // Check the hash of the version that is printed, not of a newer one
+    let pinned = match &expect {
+        Some(hash) => {
+            let version = vault.resolve_version(&key, &sel)?;
+            sel = VersionSelector::Version(version);
+            Some((version, hash))
+        }
+        None => None,
+    };
 
-    let content = vault.get(&key, sel)?;
+    let content = if vault.is_sensitive(&key)? {
+        let passphrase = resolve_passphrase(&key, passphrase)?;
+        vault.get_protected(&key, sel, &passphrase)?
+    } else {
+        vault.get(&key, sel)?
+    };
+    if let Some((version, hash)) = pinned {
+        crate::lock::check_content_hash(&key, version, &content, hash)?;
+    }
     
     match output {
         Some(file_path) => {
@@ -96,197 +421,2331 @@ pub async fn get(key: String, selector: Option<String>, output: Option<String>)
     Ok(())
 }
 
-/// Show history of a prompt
-pub async fn history(key: String) -> Result<()> {
-    let vault = PromptVault::open_default()?;
-    
-    let versions = vault.history(&key)?;
-    
-    if versions.is_empty() {
-        println!("No versions found for key: {}", key);
+/// Print a prompt with its placeholders filled in, or list its placeholders
+pub async fn render(
+    ctx: &Context,
+    key: String,
+    selector: Option<String>,
+    vars: Vec<String>,
+    strict: bool,
+    list_variables: bool,
+) -> Result<()> {
+    let sel = match &selector {
+        Some(s) => VersionSelector::parse(s)?,
+        None => VersionSelector::Latest,
+    };
+    let vars = parse_vars(&vars)?;
+    let vault = ctx.vault()?;
+
+    if list_variables {
+        for name in vault.variables(&key, sel)? {
+            println!("{}", name);
+        }
         return Ok(());
     }
 
-    println!("History for key: {}", key);
-    println!("{:<5} {:<20} {:<15} {:<30} {}", "Ver", "Timestamp", "Tags", "Message", "Content Preview");
-    println!("{}", "-".repeat(120));
+    println!("{}", vault.render(&key, sel, &vars, strict)?);
 
-    for version in versions {
-        let timestamp = version.timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
-        let tags_str = version.tags.join(",");
-        let message = version.message.unwrap_or_default();
-        
-        // Get content for preview
-        let content_preview = match vault.get(&key, VersionSelector::Version(version.version)) {
-            Ok(content) => {
-                // Take first 40 characters or until first newline
-                let preview = content.lines().next().unwrap_or(&content);
-                let preview = if preview.len() > 40 {
-                    &preview[..40]
-                } else {
-                    preview
-                };
-                format!("{}", preview)
-            },
-            Err(_) => "Content unavailable".to_string(),
-        };
-        
-        println!(
-            "{:<5} {:<20} {:<15} {:<30} {}", 
-            version.version, 
-            timestamp, 
-            tags_str, 
-            message,
-            content_preview
-        );
+    Ok(())
+}
+
+/// Print a prompt rendered into the JSON body of an API request
+pub async fn emit(
+    ctx: &Context,
+    key: String,
+    selector: Option<String>,
+    format: String,
+    vars: Vec<String>,
+    options: EmitOptions,
+    compact: bool,
+) -> Result<()> {
+    let selector = VersionSelector::parse(selector.as_deref().unwrap_or("latest"))?;
+    let format: RequestFormat = format.parse()?;
+    let vars = parse_vars(&vars)?;
+    let body = ctx.vault()?.request_body(&key, selector, format, &vars, &options)?;
+    if compact {
+        println!("{}", serde_json::to_string(&body)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&body)?);
+    }
+    Ok(())
+}
+
+/// `--var name=value` arguments as placeholder values
+fn parse_vars(vars: &[String]) -> Result<HashMap<String, String>> {
+    vars.iter()
+        .map(|var| match var.split_once('=') {
+            Some((name, value)) => Ok((name.to_string(), value.to_string())),
+            None => Err(anyhow::anyhow!("Invalid --var '{}', expected name=value", var)),
+        })
+        .collect()
+}
+
+/// Show a colored unified diff between two versions of a prompt
+pub async fn diff(ctx: &Context, key: String, old: String, new: String) -> Result<()> {
+    use crossterm::style::Stylize;
+    use std::io::IsTerminal;
+
+    let vault = ctx.vault()?;
+    let diff = vault.diff(&key, VersionSelector::parse(&old)?, VersionSelector::parse(&new)?)?;
+
+    if diff.is_empty() {
+        println!("No differences between {} and {}", old, new);
+        return Ok(());
+    }
+
+    let color = io::stdout().is_terminal();
+    for line in diff.to_unified().lines() {
+        if !color {
+            println!("{}", line);
+        } else if line.starts_with("---") || line.starts_with("+++") {
+            println!("{}", line.bold());
+        } else if line.starts_with("@@") {
+            println!("{}", line.cyan());
+        } else if line.starts_with('-') {
+            println!("{}", line.red());
+        } else if line.starts_with('+') {
+            println!("{}", line.green());
+        } else {
+            println!("{}", line);
+        }
     }
 
     Ok(())
 }
 
-/// Tag a specific version of a prompt
-pub async fn tag(key: String, tag: String, version: Option<u64>) -> Result<()> {
-    let vault = PromptVault::open_default()?;
-    
-    let version_to_tag = match version {
-        Some(v) => v,
-        None => {
-            // Use latest version if no version specified
-            match get_latest_version_number(&vault, &key)? {
-                Some(v) => v,
-                None => return Err(anyhow::anyhow!("No versions found for key '{}'", key)),
+/// Print two versions of a prompt, rendered with the same values, side by side, and
+/// with `run` the answers of the configured model to each
+#[allow(clippy::too_many_arguments)]
+pub async fn compare(
+    ctx: &Context,
+    key: String,
+    old: String,
+    new: String,
+    input: Option<String>,
+    vars: Vec<String>,
+    run: bool,
+    width: Option<usize>,
+) -> Result<()> {
+    let mut values = match input {
+        Some(path) => parse_input(&read_text_arg(&path)?)?,
+        None => HashMap::new(),
+    };
+    values.extend(parse_vars(&vars)?);
+    let vault = ctx.vault()?;
+    let comparison =
+        vault.compare(&key, VersionSelector::parse(&old)?, VersionSelector::parse(&new)?, &values)?;
+
+    let width = width
+        .or_else(|| crossterm::terminal::size().ok().map(|(columns, _)| columns as usize))
+        .unwrap_or(120);
+    let color = io::stdout().is_terminal();
+    let (old_version, new_version) = (comparison.old_version, comparison.new_version);
+    let headers = (format!("{} v{}", key, old_version), format!("{} v{}", key, new_version));
+    print_columns(headers, &comparison.prompt_rows(), width, color);
+
+    if run {
+        let config = LlmConfig::load()?;
+        let old_answer = vault.respond(&key, old_version, &values, &config).await?;
+        let new_answer = vault.respond(&key, new_version, &values, &config).await?;
+        println!();
+        let headers = (
+            format!("Answer to v{}", old_version),
+            format!("Answer to v{}", new_version),
+        );
+        print_columns(headers, &side_by_side(&old_answer, &new_answer), width, color);
+    }
+    Ok(())
+}
+
+/// Print rows in two columns of a `width` wide terminal, wrapping long lines
+fn print_columns(headers: (String, String), rows: &[SideBySideRow], width: usize, color: bool) {
+    use crossterm::style::Stylize;
+
+    let column = (width.saturating_sub(3) / 2).max(20);
+    let pad = |text: &str| {
+        let used = unicode_width::UnicodeWidthStr::width(text);
+        format!("{}{}", text, " ".repeat(column.saturating_sub(used)))
+    };
+    println!("{} │ {}", pad(&headers.0), headers.1);
+    println!("{}┼{}", "─".repeat(column + 1), "─".repeat(column + 1));
+    for row in rows {
+        let old = wrap(row.old.as_deref().unwrap_or(""), column);
+        let new = wrap(row.new.as_deref().unwrap_or(""), column);
+        for line in 0..old.len().max(new.len()) {
+            let left = pad(old.get(line).map_or("", String::as_str));
+            let right = new.get(line).map_or("", String::as_str);
+            match (color, row.kind) {
+                (false, _) | (true, RowKind::Equal) => println!("{} │ {}", left, right),
+                (true, RowKind::Changed) => println!("{} │ {}", left.red(), right.green()),
+                (true, RowKind::Delete) => println!("{} │ {}", left.red(), right),
+                (true, RowKind::Insert) => println!("{} │ {}", left, right.green()),
             }
         }
+    }
+}
+
+/// Split `text` into pieces at most `width` columns wide
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    let mut used = 0;
+    for c in text.chars() {
+        let c_width = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        if used + c_width > width {
+            lines.push(String::new());
+            used = 0;
+        }
+        lines.last_mut().expect("lines is never empty").push(c);
+        used += c_width;
+    }
+    lines
+}
+
+/// Show which version introduced each line of a prompt
+pub async fn blame(ctx: &Context, key: String, selector: Option<String>) -> Result<()> {
+    use crossterm::style::Stylize;
+
+    let vault = ctx.vault()?;
+    let selector = match selector.as_deref() {
+        Some(selector) => VersionSelector::parse(selector)?,
+        None => VersionSelector::Latest,
     };
+    let lines = vault.blame_at(&key, selector)?;
 
-    vault.tag(&key, &tag, version_to_tag)?;
-    println!("Tagged version {} of '{}' as '{}'", version_to_tag, key, tag);
+    let color = io::stdout().is_terminal();
+    let width = lines.len().to_string().len();
+    for line in &lines {
+        let message: String = line.message.as_deref().unwrap_or("").chars().take(24).collect();
+        let author: String = line.author.as_deref().unwrap_or("-").chars().take(12).collect();
+        let origin = format!(
+            "{:<5} {} {:<12} {:<24}",
+            format!("v{}", line.version),
+            line.timestamp.format("%Y-%m-%d"),
+            author,
+            message
+        );
+        let origin = if color {
+            origin.dark_grey().to_string()
+        } else {
+            origin
+        };
+        println!("{}  {:>width$}  {}", origin, line.number, line.text, width = width);
+    }
 
     Ok(())
 }
 
-/// Promote a tag to the latest version
-pub async fn promote(key: String, tag: String) -> Result<()> {
-    let vault = PromptVault::open_default()?;
-    
-    vault.promote(&key, &tag)?;
-    println!("Promoted tag '{}' of '{}' to latest version", tag, key);
-
+/// Comment on a version of a prompt as the author of this command, or reply to a comment
+pub async fn comment(
+    ctx: &Context,
+    key: String,
+    text: String,
+    on: Option<String>,
+    reply_to: Option<u64>,
+) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    let author = vault.author().map(str::to_string).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Who is commenting? Pass --author or run `promptpro config set user.name NAME`"
+        )
+    })?;
+    let comment = match reply_to {
+        Some(id) => vault.reply(&key, id, &author, &text)?,
+        None => {
+            let selector = match on.as_deref() {
+                Some(selector) => VersionSelector::parse(selector)?,
+                None => VersionSelector::Latest,
+            };
+            let version = vault.resolve_version(&key, &selector)?;
+            vault.comment(&key, version, &author, &text)?
+        }
+    };
+    println!("[+] Comment #{} on {} v{}", comment.id, key, comment.version);
     Ok(())
 }
 
-/// Open TUI editor
-pub async fn tui() -> Result<()> {
-    println!("Opening TUI editor...");
-    crate::tui::run().await
+/// Record the score of a version for a metric
+pub async fn score_record(
+    ctx: &Context,
+    key: String,
+    selector: String,
+    metric: String,
+    value: f64,
+    source: String,
+) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    let version = vault.resolve_version(&key, &VersionSelector::parse(&selector)?)?;
+    vault.record_score(&key, version, &metric, value, &source)?;
+    println!("[+] Recorded {} = {} for {} v{}", metric, value, key, version);
+    Ok(())
 }
 
-/// Edit a prompt in TUI mode
-pub async fn edit(key: String) -> Result<()> {
-    println!("Opening TUI editor for key: {}", key);
-    crate::tui::run_with_key(key).await
+/// List the scores of a prompt, one line per version and metric
+pub async fn score_list(ctx: &Context, key: String, selector: Option<String>) -> Result<()> {
+    let vault = ctx.vault()?;
+    let version = match selector {
+        Some(selector) => Some(vault.resolve_version(&key, &VersionSelector::parse(&selector)?)?),
+        None => None,
+    };
+    let scores = vault.scores(&key, version)?;
+    if scores.is_empty() {
+        println!("No scores recorded for {}", key);
+        return Ok(());
+    }
+    println!("{:<5} {:<20} {:>12}  {:<15} Recorded", "Ver", "Metric", "Value", "Source");
+    for score in scores {
+        println!(
+            "{:<5} {:<20} {:>12}  {:<15} {}",
+            score.version,
+            score.metric,
+            score.value,
+            score.source,
+            score.recorded_at.format("%Y-%m-%d %H:%M")
+        );
+    }
+    Ok(())
 }
 
-/// Dump the vault to a binary file
-pub async fn dump(output: String, password: Option<String>) -> Result<()> {
-    let vault = PromptVault::open_default()?;
-    let password_ref = password.as_deref();
-    
-    match vault.dump(&output, password_ref) {
-        Ok(()) => {
-            println!("Vault dumped successfully to: {}", output);
-            if password.is_some() {
-                println!("Vault is encrypted with provided password");
-            } else {
-                println!("Vault is unencrypted");
-            }
-        },
-        Err(e) => {
-            eprintln!("Error dumping vault: {}", e);
-        }
+/// Store the messages of a chat prompt, adding it when the key is new
+pub async fn chat_set(
+    ctx: &Context,
+    key: String,
+    parts: Vec<String>,
+    file: Option<String>,
+    message: Option<String>,
+) -> Result<()> {
+    let chat = match file {
+        Some(path) => ChatPrompt::parse(&read_text_arg(&path)?)?,
+        None => ChatPrompt::new(
+            parts
+                .iter()
+                .map(|spec| ChatMessage::parse(spec))
+                .collect::<Result<Vec<_>>>()?,
+        ),
+    };
+    let vault = ctx.writable_vault()?;
+    if vault.get_latest_version_number(&key)?.is_none() {
+        vault.add_chat(&key, &chat)?;
+        println!("[+] Stored chat prompt under key: {}", key);
+        println!("    version: 1 (snapshot), {} messages", chat.messages.len());
+    } else {
+        let version = vault.update_chat(&key, &chat, message)?;
+        println!("[+] Updated chat prompt: {}", key);
+        println!("    version: {}, {} messages", version, chat.messages.len());
     }
-    
+    warn_over_budget(&key, &chat.to_content()?);
     Ok(())
 }
 
-/// Delete a prompt key and all its versions
-pub async fn delete(key: String) -> Result<()> {
-    let vault = PromptVault::open_default()?;
-    
-    match vault.delete_prompt_key(&key) {
-        Ok(()) => {
-            println!("[+] Deleted prompt: {}", key);
-        },
-        Err(e) => {
-            eprintln!("Error deleting prompt: {}", e);
-            std::process::exit(1);
+/// Print the messages of a chat prompt as text or as OpenAI or Anthropic JSON
+pub async fn chat_show(
+    ctx: &Context,
+    key: String,
+    selector: Option<String>,
+    format: String,
+    vars: Vec<String>,
+    strict: bool,
+) -> Result<()> {
+    let selector = VersionSelector::parse(selector.as_deref().unwrap_or("latest"))?;
+    let vars = parse_vars(&vars)?;
+    let chat = ctx.vault()?.render_chat(&key, selector, &vars, strict)?;
+    match format.as_str() {
+        "text" => println!("{}", chat.to_text()),
+        "openai" => println!("{}", serde_json::to_string_pretty(&chat.to_openai())?),
+        "anthropic" => println!("{}", serde_json::to_string_pretty(&chat.to_anthropic())?),
+        other => {
+            return Err(anyhow::anyhow!(
+                "Unknown format '{}'; use text, openai or anthropic",
+                other
+            ))
         }
     }
+    Ok(())
+}
 
+/// Replace the text of message `index` (from 1) of a chat prompt
+pub async fn chat_set_part(
+    ctx: &Context,
+    key: String,
+    index: usize,
+    text: String,
+    message: Option<String>,
+) -> Result<()> {
+    if index == 0 {
+        return Err(anyhow::anyhow!("Messages are numbered from 1"));
+    }
+    let vault = ctx.writable_vault()?;
+    let version = vault.update_chat_part(&key, index - 1, &text, message)?;
+    println!("[+] Updated message {} of {}", index, key);
+    println!("    version: {}", version);
+    warn_over_budget(&key, &vault.get(&key, VersionSelector::Version(version))?);
     Ok(())
 }
 
-/// Restore/Resume the vault from a binary file
-pub async fn resume(input: String, password: Option<String>) -> Result<()> {
-    use std::fs;
+/// Run the test cases of a prompt against the configured LLM, failing when one fails
+pub async fn test(
+    ctx: &Context,
+    key: String,
+    selectors: Vec<String>,
+    show_output: bool,
+) -> Result<()> {
+    let selectors = match selectors.is_empty() {
+        true => vec![VersionSelector::Latest],
+        false => selectors
+            .iter()
+            .map(|selector| VersionSelector::parse(selector))
+            .collect::<Result<Vec<_>>>()?,
+    };
+    let config = LlmConfig::load()?;
+    let reports = ctx.vault()?.run_tests(&key, &selectors, &config).await?;
 
-    
-    let password_ref = password.as_deref();
-    
-    // Create a temporary vault from the dump file
-    match PromptVault::restore(&input, password_ref) {
-        Ok(restored_vault) => {
-            // Get the default vault path
-            let default_dir = std::env::var("HOME")?;
-            let default_vault_path = std::path::PathBuf::from(default_dir).join(".promptpro").join("default_vault");
-            
-            // Ensure the parent directory exists
-            if let Some(parent) = default_vault_path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            
-            // Close the restored vault to ensure files are flushed
-            restored_vault.db().flush()?;
-            
-            // Since sled creates multiple files, we'll copy the content differently
-            // Open the default vault and copy entries from the restored vault
-            let target_vault = PromptVault::open(&default_vault_path)?;
-            
-            // Clear the target vault first to avoid conflicts
-            // For sled, we'll just copy entries over which will overwrite
-            // Copy all entries from the restored vault to the target vault
-            for result in restored_vault.db().iter() {
-                let (key, value) = result?;
-                target_vault.db().insert(key, value)?;
-            }
-            
-            // Flush the target vault to ensure data is written
-            target_vault.db().flush()?;
-            
-            println!("Vault restored successfully from: {}", input);
-            if password.is_some() {
-                println!("Vault was encrypted with provided password");
-            } else {
-                println!("Vault was unencrypted");
+    let mut failed = 0;
+    for report in &reports {
+        let total = report.results.len();
+        println!("{} v{}: {}/{} passed", key, report.version, report.passed(), total);
+        for result in &report.results {
+            let mark = if result.passed() { "✓" } else { "✗" };
+            println!("  {} {}", mark, result.case);
+            for failure in &result.failures {
+                println!("      {}", failure);
             }
-            
-            // Count number of entries in the target vault as validation
-            let mut count = 0;
-            for result in target_vault.db().iter() {
-                if result.is_ok() {
-                    count += 1;
+            if show_output {
+                for line in result.output.lines() {
+                    println!("      │ {}", line);
                 }
             }
-            println!("Restored {} entries to the default vault", count);
-        },
-        Err(e) => {
-            eprintln!("Error resuming vault: {}", e);
         }
+        failed += report.results.len() - report.passed();
     }
-    
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} test case runs failed", failed));
+    }
+    Ok(())
+}
+
+/// Store a test case of a prompt
+pub async fn test_case_add(
+    ctx: &Context,
+    key: String,
+    name: String,
+    vars: Vec<String>,
+    assertions: Vec<Assertion>,
+) -> Result<()> {
+    let case = TestCase {
+        key,
+        name,
+        vars: parse_vars(&vars)?.into_iter().collect(),
+        assertions,
+    };
+    ctx.writable_vault()?.add_test_case(&case)?;
+    println!(
+        "[+] Test case '{}' of {} with {} assertions",
+        case.name,
+        case.key,
+        case.assertions.len()
+    );
+    Ok(())
+}
+
+/// List the test cases of a prompt with their values and assertions
+pub async fn test_case_list(ctx: &Context, key: String) -> Result<()> {
+    let cases = ctx.vault()?.test_cases(&key)?;
+    if cases.is_empty() {
+        println!("No test cases for {}", key);
+        return Ok(());
+    }
+    for case in cases {
+        println!("{}", case.name);
+        for (name, value) in &case.vars {
+            println!("  {} = {}", name, value);
+        }
+        for assertion in &case.assertions {
+            println!("  the answer must {}", assertion);
+        }
+    }
+    Ok(())
+}
+
+/// Remove a test case
+pub async fn test_case_remove(ctx: &Context, key: String, name: String) -> Result<()> {
+    if !ctx.writable_vault()?.delete_test_case(&key, &name)? {
+        return Err(anyhow::anyhow!("Test case '{}' of '{}' not found", name, key));
+    }
+    println!("[-] Removed test case '{}' of {}", name, key);
+    Ok(())
+}
+
+/// Start an A/B experiment on a prompt
+pub async fn experiment_create(
+    ctx: &Context,
+    key: String,
+    name: String,
+    variants: Vec<String>,
+) -> Result<()> {
+    let variants = variants
+        .iter()
+        .map(|spec| ExperimentVariant::parse(spec))
+        .collect::<Result<Vec<_>>>()?;
+    let vault = ctx.writable_vault()?;
+    let experiment = vault.create_experiment(&key, &name, variants)?;
+    println!(
+        "[+] Experiment '{}' on {} started with {} variants",
+        name,
+        key,
+        experiment.variants.len()
+    );
     Ok(())
 }
 
+/// List experiments with the traffic share and serve count of each variant
+pub async fn experiment_list(ctx: &Context, key: Option<String>) -> Result<()> {
+    let vault = ctx.vault()?;
+    let experiments = vault.experiments(key.as_deref())?;
+    if experiments.is_empty() {
+        println!("No experiments");
+        return Ok(());
+    }
+    for experiment in experiments {
+        println!(
+            "{} / {} (started {})",
+            experiment.key,
+            experiment.name,
+            experiment.created_at.format("%Y-%m-%d %H:%M")
+        );
+        let total = experiment.total_weight().max(1) as f64;
+        for variant in &experiment.variants {
+            println!(
+                "  {:<16} weight {:<5} {:>5.1}%   served {}",
+                variant.selector,
+                variant.weight,
+                variant.weight as f64 * 100.0 / total,
+                variant.served
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print the variant a user is served; which one goes to stderr
+pub async fn experiment_sample(
+    ctx: &Context,
+    key: String,
+    name: String,
+    user_id: String,
+) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    let sample = vault.sample(&key, &name, &user_id)?;
+    eprintln!("Serving {} ({} v{}) to {}", sample.variant, key, sample.version, user_id);
+    println!("{}", sample.content);
+    Ok(())
+}
+
+/// Stop an experiment
+pub async fn experiment_delete(ctx: &Context, key: String, name: String) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    if !vault.delete_experiment(&key, &name)? {
+        return Err(anyhow::anyhow!("Experiment '{}' on '{}' not found", name, key));
+    }
+    println!("[-] Experiment '{}' on {} stopped", name, key);
+    Ok(())
+}
+
+/// Print the comments on one version as indented threads
+fn print_comments(comments: &[Comment]) {
+    for (depth, comment) in comment_threads(comments) {
+        let marker = if comment.reply_to.is_some() { "↳ " } else { "" };
+        println!(
+            "      {}{}#{} {} ({}): {}",
+            "  ".repeat(depth),
+            marker,
+            comment.id,
+            comment.author,
+            comment.timestamp.format("%Y-%m-%d %H:%M"),
+            comment.text.replace('\n', " ")
+        );
+    }
+}
+
+/// Rank prompts by versions and lines changed in the window, flagging unstable ones
+pub async fn churn(ctx: &Context, since: String) -> Result<()> {
+    let window = parse_window(&since)?;
+    let vault = ctx.vault()?;
+    let churn = vault.churn(chrono::Utc::now() - window)?;
+
+    if churn.is_empty() {
+        println!("No prompts changed in the last {}", since);
+        return Ok(());
+    }
+
+    println!(
+        "{:<2} {:<30} {:>8} {:>8} {:>8}  {:<16} Last change",
+        "", "Key", "Versions", "Added", "Removed", "Author"
+    );
+    println!("{}", "-".repeat(100));
+    for entry in &churn {
+        println!(
+            "{:<2} {:<30} {:>8} {:>8} {:>8}  {:<16} {}",
+            if entry.is_unstable() { "!" } else { "" },
+            entry.key,
+            entry.versions,
+            format!("+{}", entry.lines_added),
+            format!("-{}", entry.lines_removed),
+            entry.author.as_deref().unwrap_or("-"),
+            entry.last_change.format("%Y-%m-%d %H:%M")
+        );
+    }
+
+    let unstable = churn.iter().filter(|entry| entry.is_unstable()).count();
+    if unstable > 0 {
+        println!(
+            "\n! {} prompt(s) changed {}+ times in the last {}: consider a design review \
+             rather than more tweaks",
+            unstable, UNSTABLE_VERSIONS, since
+        );
+    }
+    Ok(())
+}
+
+/// Search prompt keys, contents, messages and tags, with `tag:`/`author:`/`key:` selectors.
+/// With `--regex`, matching content lines are printed like ripgrep, as they are found.
+pub async fn search(
+    ctx: &Context,
+    query: String,
+    options: SearchOptions,
+    context: usize,
+) -> Result<()> {
+    let parsed: SearchQuery = query.parse()?;
+    let vault = ctx.vault()?;
+
+    if options.regex {
+        return search_lines(vault, &parsed, &options, context, &query);
+    }
+
+    let hits = vault.search_query(&parsed, &options)?;
+    if hits.is_empty() {
+        println!("No matches for '{}'", query);
+        return Ok(());
+    }
+
+    println!("{:<30} {:<5} {:<8} Match", "Key", "Ver", "Field");
+    println!("{}", "-".repeat(100));
+    for hit in &hits {
+        println!(
+            "{:<30} {:<5} {:<8} {}",
+            hit.key,
+            hit.version,
+            hit.field.to_string(),
+            hit.snippet
+        );
+    }
+    println!("{} match(es)", hits.len());
+
+    Ok(())
+}
+
+/// `key@vN` headings, then `N:line` for matches and `N-line` for context, with `--`
+/// between runs of the same version
+fn search_lines(
+    vault: &PromptVault,
+    query: &SearchQuery,
+    options: &SearchOptions,
+    context: usize,
+    raw_query: &str,
+) -> Result<()> {
+    let mut current: Option<(String, u64)> = None;
+    let mut matches = 0;
+
+    vault.search_lines(query, options, context, |run| {
+        let heading = (run.key.clone(), run.version);
+        if current.as_ref() == Some(&heading) {
+            println!("--");
+        } else {
+            if current.is_some() {
+                println!();
+            }
+            println!("{}@v{}", run.key, run.version);
+            current = Some(heading);
+        }
+        for line in &run.lines {
+            let separator = if line.matched { ':' } else { '-' };
+            println!("{}{}{}", line.number, separator, line.text);
+        }
+        matches += run.lines.iter().filter(|line| line.matched).count();
+        Ok(())
+    })?;
+
+    if matches == 0 {
+        println!("No matches for '{}'", raw_query);
+    } else {
+        println!("\n{} matching line(s)", matches);
+    }
+    Ok(())
+}
+
+/// Copy a prompt to a new key of the current vault
+pub async fn copy(
+    ctx: &Context,
+    src: String,
+    dst: String,
+    history: bool,
+    metadata: bool,
+) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    let options = CopyOptions {
+        with_history: history,
+        with_metadata: metadata,
+    };
+
+    let version = vault.copy_key(&src, &dst, &options)?;
+    println!("[+] Copied '{}' to '{}'", src, dst);
+    if history {
+        println!("    copied full history up to v{}", version);
+    } else {
+        println!("    copied the latest content as version 1");
+    }
+
+    Ok(())
+}
+
+/// Copy a prompt from another vault, e.g. from the personal default vault into a project
+pub async fn adopt(
+    ctx: &Context,
+    key: String,
+    from: String,
+    to: Option<String>,
+    history: bool,
+) -> Result<()> {
+    let source_path = vault_path_arg(&from)?;
+    let source = open_vault(&source_path, ctx.lock_policy, None)?;
+    let target = match &to {
+        Some(to) => ctx.open(&vault_path_arg(to)?, ctx.write_lock_policy())?,
+        None => ctx.writable_vault()?.clone(),
+    };
+
+    let provenance = target.adopt(&source, &source_path.to_string_lossy(), &key, history)?;
+    println!("[+] Adopted '{}' from {}", key, provenance.source);
+    if history {
+        println!("    copied full history up to v{}", provenance.source_version);
+    } else {
+        println!("    copied v{} as version 1", provenance.source_version);
+    }
+
+    Ok(())
+}
+
+/// Pack the selected version of each prompt of a namespace into a package file
+pub async fn pack(ctx: &Context, namespace: String, out: String, selector: String) -> Result<()> {
+    let (package, skipped) = ctx.vault()?.pack(&namespace, &VersionSelector::parse(&selector)?)?;
+    if package.prompts.is_empty() {
+        return Err(anyhow::anyhow!("No prompt of '{}' has version '{}'", namespace, selector));
+    }
+    package.write(&out)?;
+
+    println!("[+] Packed {} prompts of '{}' into {}", package.prompts.len(), namespace, out);
+    for prompt in &package.prompts {
+        if prompt.tags.is_empty() {
+            println!("    {} v{}", prompt.name, prompt.version);
+        } else {
+            println!("    {} v{} [{}]", prompt.name, prompt.version, prompt.tags.join(", "));
+        }
+    }
+    if !skipped.is_empty() {
+        println!("    left out (no such version, or password-protected): {}", skipped.join(", "));
+    }
+
+    Ok(())
+}
+
+/// Install the prompts of a package file
+pub async fn install(
+    ctx: &Context,
+    path: String,
+    into: Option<String>,
+    policy: InstallPolicy,
+) -> Result<()> {
+    let package = Package::read(&path)?;
+    let report = ctx.writable_vault()?.install(&package, into.as_deref(), policy)?;
+
+    let namespace = into.as_deref().unwrap_or(&package.namespace);
+    println!("[+] Installed package '{}' into '{}'", package.namespace, namespace);
+    for (label, keys) in [
+        ("installed", &report.installed),
+        ("updated", &report.updated),
+        ("unchanged", &report.unchanged),
+        ("skipped", &report.skipped),
+    ] {
+        if !keys.is_empty() {
+            println!("    {}: {}", label, keys.join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+/// List prompt keys, optionally under a prefix, with their latest version
+pub async fn list(ctx: &Context, prefix: Option<String>, sort: String) -> Result<()> {
+    let order: KeyOrder = sort.parse()?;
+    let vault = ctx.vault()?;
+
+    let keys = vault.list_keys_sorted(prefix.as_deref().unwrap_or_default(), order)?;
+    if keys.is_empty() {
+        match prefix {
+            Some(prefix) => println!("No prompts starting with '{}'", prefix),
+            None => println!("No prompts in the vault"),
+        }
+        return Ok(());
+    }
+
+    for key in keys {
+        match get_latest_version_number(vault, &key)? {
+            Some(version) => println!("{:<40} v{}", key, version),
+            None => println!("{}", key),
+        }
+    }
+
+    Ok(())
+}
+
+/// Show history of a prompt
+pub async fn history(ctx: &Context, key: String, unlock: bool, query: HistoryQuery) -> Result<()> {
+    let vault = ctx.vault()?;
+    let unlocked = if unlock {
+        unlock_keys(vault, std::slice::from_ref(&key))?
+    } else {
+        UnlockedKeys::new()
+    };
+    
+    let all = vault.history(&key)?;
+    
+    if all.is_empty() {
+        println!("No versions found for key: {}", key);
+        return Ok(());
+    }
+    let versions = vault.history_filtered(&key, &query)?;
+    if versions.is_empty() {
+        println!("No versions of {} match the filters", key);
+        return Ok(());
+    }
+
+    println!("History for key: {}", key);
+    for line in vault.prompt_meta(&key)?.summary_lines() {
+        println!("{}", line);
+    }
+    if let Some(provenance) = vault.provenance(&key)? {
+        println!(
+            "Adopted from {} (v{}) on {}",
+            provenance.source,
+            provenance.source_version,
+            provenance.adopted_at.format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+    println!(
+        "{:<5} {:<20} {:<12} {:<15} {:<30} Content Preview",
+        "Ver", "Timestamp", "Author", "Tags", "Message"
+    );
+    println!("{}", "-".repeat(133));
+
+    let comments = vault.key_comments(&key)?;
+    let scores = vault.scores(&key, None)?;
+    let shown = versions.len();
+    for version in versions {
+        let timestamp = version.timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
+        let tags_str = version.tags.join(",");
+        let message = version.message.unwrap_or_default();
+        let author = version.author.unwrap_or_else(|| "-".to_string());
+        // Versions stored despite failing a warn-only validator
+        let message = match version.validated {
+            Some(false) => format!("[!] {}", message),
+            _ => message,
+        };
+        
+        // Get content for preview
+        let content_preview = match vault.display_content(&key, version.version, &unlocked) {
+            Ok(content) if content == REDACTED => content,
+            Ok(content) => {
+                // Take first 40 characters or until first newline
+                let preview = content.lines().next().unwrap_or(&content);
+                let preview = if preview.len() > 40 {
+                    &preview[..40]
+                } else {
+                    preview
+                };
+                preview.to_string()
+            },
+            Err(_) => "Content unavailable".to_string(),
+        };
+        
+        println!(
+            "{:<5} {:<20} {:<12} {:<15} {:<30} {}", 
+            version.version, 
+            timestamp, 
+            author,
+            tags_str, 
+            message,
+            content_preview
+        );
+        let on_version: Vec<Comment> = comments
+            .iter()
+            .filter(|comment| comment.version == version.version)
+            .cloned()
+            .collect();
+        print_comments(&on_version);
+        let version_scores: Vec<String> = scores
+            .iter()
+            .filter(|score| score.version == version.version)
+            .map(|score| format!("{}={}", score.metric, score.value))
+            .collect();
+        if !version_scores.is_empty() {
+            println!("      scores: {}", version_scores.join(", "));
+        }
+    }
+    if shown < all.len() {
+        println!("Showing {} of {} versions", shown, all.len());
+    }
+
+    Ok(())
+}
+
+/// Tag a specific version of a prompt
+pub async fn tag(
+    ctx: &Context,
+    key: String,
+    tag: String,
+    version: Option<u64>,
+    auto_correct: bool,
+) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    let key = correct_key(vault, key, auto_correct)?;
+    
+    let version_to_tag = match version {
+        Some(v) => v,
+        None => {
+            // Use latest version if no version specified
+            match get_latest_version_number(vault, &key)? {
+                Some(v) => v,
+                None => return Err(vault.unknown_key(&key)),
+            }
+        }
+    };
+
+    vault.tag(&key, &tag, version_to_tag)?;
+    println!("Tagged version {} of '{}' as '{}'", version_to_tag, key, tag);
+
+    Ok(())
+}
+
+/// Remove a tag from a prompt
+pub async fn untag(ctx: &Context, key: String, tag: String) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+
+    let version = vault.untag(&key, &tag)?;
+    println!("Removed tag '{}' from version {} of '{}'", tag, version, key);
+
+    Ok(())
+}
+
+/// Promote a tag to the latest version
+pub async fn promote(ctx: &Context, key: String, tag: String) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    
+    vault.promote(&key, &tag)?;
+    println!("Promoted tag '{}' of '{}' to latest version", tag, key);
+
+    Ok(())
+}
+
+/// Restore an earlier version as the new latest version
+pub async fn rollback(ctx: &Context, key: String, selector: String) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+
+    let version = vault.rollback(&key, VersionSelector::parse(&selector)?)?;
+    println!("[+] Rolled back '{}' to {}", key, selector);
+    println!("    version: {} (reverted)", version);
+    println!("    'dev' tag automatically updated to latest version");
+
+    Ok(())
+}
+
+/// Carry the change of a version forward onto the latest version
+pub async fn cherry_pick(ctx: &Context, key: String, selector: String) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+
+    let version = vault.cherry_pick(&key, VersionSelector::parse(&selector)?)?;
+    println!("[+] Cherry-picked {} of '{}'", selector, key);
+    println!("    version: {} (cherry-picked)", version);
+    println!("    'dev' tag automatically updated to latest version");
+
+    Ok(())
+}
+
+/// Open TUI editor
+pub async fn tui(ctx: &Context, unlock: Vec<String>, read_only: bool) -> Result<()> {
+    let read_only = read_only || TuiOptions::read_only_from_env();
+    if !read_only && io::stdin().is_terminal() && !first_run(ctx)? {
+        return Ok(());
+    }
+    let vault = if read_only {
+        ctx.vault()?
+    } else {
+        ctx.writable_vault()?
+    };
+    let options = TuiOptions {
+        unlocked: unlock_keys(vault, &unlock)?,
+        read_only,
+        tokens: TokenConfig::load()?,
+    };
+    println!("Opening TUI editor...");
+    crate::tui::run(vault.clone(), options).await
+}
+
+/// Offer to create or populate the vault when it is missing or empty, so the TUI
+/// does not open to a blank screen; `false` when the user chose to quit
+fn first_run(ctx: &Context) -> Result<bool> {
+    let path = crate::utils::current_vault_path()?;
+    let missing = !path.exists();
+    if !missing && !ctx.writable_vault()?.list_keys()?.is_empty() {
+        return Ok(true);
+    }
+
+    if missing {
+        println!("No prompt vault at {:?} yet.", path);
+    } else {
+        println!("The prompt vault at {:?} is empty.", path);
+    }
+    println!("  1) Start with an empty vault");
+    println!("  2) Import a directory of .txt/.md prompt files");
+    println!("  3) Restore a dump made with `ppro dump`");
+    println!("  q) Quit");
+
+    loop {
+        match read_line("Choose [1]: ")?.as_str() {
+            "" | "1" => {
+                ctx.writable_vault()?;
+                println!("Press 'a' in the Keys panel to add your first prompt.");
+                return Ok(true);
+            }
+            "2" => {
+                let dir = read_line("Directory: ")?;
+                match ctx.writable_vault()?.import_dir(&dir) {
+                    Ok(report) => {
+                        println!("[+] Imported {} prompt(s) from {}", report.created.len(), dir);
+                        return Ok(true);
+                    }
+                    Err(e) => eprintln!("Error importing {}: {}", dir, e),
+                }
+            }
+            "3" => {
+                let input = read_line("Dump file: ")?;
+                let vault = ctx.writable_vault()?;
+                match restore_dump(vault, &input, PasswordOptions::default()) {
+                    Ok(count) => {
+                        println!("[+] Restored {} entries from {}", count, input);
+                        return Ok(true);
+                    }
+                    Err(e) => eprintln!("Error restoring {}: {}", input, e),
+                }
+            }
+            "q" | "Q" => return Ok(false),
+            other => println!("Unknown choice '{}'", other),
+        }
+    }
+}
+
+/// With `--auto-correct`, offer the closest existing key in place of one without
+/// versions. Only asks on a terminal; otherwise the key is kept and fails with the
+/// suggestions in its error.
+fn correct_key(vault: &PromptVault, key: String, auto_correct: bool) -> Result<String> {
+    if !auto_correct || !io::stdin().is_terminal() {
+        return Ok(key);
+    }
+    if vault.get_latest_version_number(&key)?.is_some() {
+        return Ok(key);
+    }
+    let Some(suggestion) = vault.suggest_keys(&key)?.into_iter().next() else {
+        return Ok(key);
+    };
+
+    // The prompt goes to stderr so that stdout keeps only the command's output
+    eprint!("No prompt '{}'; use '{}' instead? [Y/n] ", key, suggestion);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    match answer.trim() {
+        "" | "y" | "Y" | "yes" => Ok(suggestion),
+        _ => Ok(key),
+    }
+}
+
+/// Print `prompt` and read a trimmed line from stdin
+fn read_line(prompt: &str) -> Result<String> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+/// Edit a prompt in TUI mode
+pub async fn edit(ctx: &Context, key: String, unlock: bool) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    let unlocked = if unlock {
+        unlock_keys(vault, std::slice::from_ref(&key))?
+    } else {
+        UnlockedKeys::new()
+    };
+    let options = TuiOptions {
+        unlocked,
+        tokens: TokenConfig::load()?,
+        ..Default::default()
+    };
+    println!("Opening TUI editor for key: {}", key);
+    crate::tui::run_with_key(vault.clone(), key, options).await
+}
+
+/// Dump the vault to a binary file
+pub async fn dump(
+    ctx: &Context,
+    output: String,
+    password: PasswordOptions,
+    encrypt: bool,
+    kdf: Option<KdfParams>,
+) -> Result<()> {
+    let encrypt = encrypt || password.is_explicit();
+    if let Some(kdf) = &kdf {
+        if !encrypt {
+            return Err(anyhow::anyhow!("--kdf-memory and --kdf-iterations need --encrypt"));
+        }
+        kdf.validate()?;
+    }
+
+    let vault = ctx.vault()?;
+    let password = if encrypt {
+        match password.resolve()? {
+            Some(password) => Some(password),
+            None => Some(prompt_new_password()?),
+        }
+    } else {
+        None
+    };
+    let password_ref = password.as_deref();
+    
+    match vault.dump_with_kdf(&output, password_ref, kdf.unwrap_or_default()) {
+        Ok(()) => {
+            println!("Vault dumped successfully to: {}", output);
+            if password.is_some() {
+                println!("Vault is encrypted with provided password");
+            } else {
+                println!("Vault is unencrypted");
+            }
+        },
+        Err(e) => {
+            eprintln!("Error dumping vault: {}", e);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Store a dump password in the OS keyring
+pub async fn password_set(entry: String) -> Result<()> {
+    let password = prompt_new_password()?;
+    store_in_keyring(&entry, &password)?;
+    println!("Stored password '{}' in the OS keyring", entry);
+    Ok(())
+}
+
+/// Remove a dump password from the OS keyring
+pub async fn password_clear(entry: String) -> Result<()> {
+    delete_from_keyring(&entry)?;
+    println!("Removed password '{}' from the OS keyring", entry);
+    Ok(())
+}
+
+/// Protect a prompt with its own passphrase
+pub async fn protect(ctx: &Context, key: String, save_to_keyring: bool) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    let passphrase = prompt_new_password()?;
+
+    vault.protect(&key, &passphrase)?;
+    println!("[+] Protected prompt: {}", key);
+
+    if save_to_keyring {
+        let entry = prompt_keyring_entry(&key);
+        store_in_keyring(&entry, &passphrase)?;
+        println!("    passphrase stored in the OS keyring as '{}'", entry);
+    }
+
+    Ok(())
+}
+
+/// Remove passphrase protection from a prompt
+pub async fn unprotect(ctx: &Context, key: String, passphrase: PasswordOptions) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    let passphrase = resolve_passphrase(&key, passphrase)?;
+
+    vault.unprotect(&key, &passphrase)?;
+    println!("[-] Removed protection from prompt: {}", key);
+
+    Ok(())
+}
+
+/// Generate a Rust module naming the prompt keys
+pub async fn codegen_rust(
+    ctx: &Context,
+    out: Option<String>,
+    prefix: String,
+    render_helpers: bool,
+    check: bool,
+) -> Result<()> {
+    let options = RustCodegen {
+        prefix,
+        render_helpers,
+    };
+    let code = ctx.vault()?.codegen_rust(&options)?;
+    write_codegen(&code, out, check, "rust")
+}
+
+/// Generate a Python module naming the prompt keys, with a render function per prompt
+pub async fn codegen_python(
+    ctx: &Context,
+    out: Option<String>,
+    prefix: String,
+    check: bool,
+) -> Result<()> {
+    let code = ctx.vault()?.codegen_python(&PythonCodegen { prefix })?;
+    write_codegen(&code, out, check, "python")
+}
+
+/// Print generated code, write it to `out`, or with `check` compare it with `out`
+fn write_codegen(code: &str, out: Option<String>, check: bool, language: &str) -> Result<()> {
+    let Some(out) = out else {
+        print!("{}", code);
+        return Ok(());
+    };
+    if check {
+        let current = std::fs::read_to_string(&out).unwrap_or_default();
+        if current != code {
+            return Err(anyhow::anyhow!(
+                "{} is out of date; run `promptpro codegen {} --out {}`",
+                out,
+                language,
+                out
+            ));
+        }
+        println!("{} is up to date", out);
+        return Ok(());
+    }
+    std::fs::write(&out, code)?;
+    println!("[+] Wrote {}", out);
+
+    Ok(())
+}
+
+/// Print the vault README, or the notes of a prompt
+pub async fn docs_show(ctx: &Context, key: Option<String>) -> Result<()> {
+    let vault = ctx.vault()?;
+    match key {
+        Some(key) => match vault.notes(&key)? {
+            Some(notes) => println!("{}", notes),
+            None => println!("No notes on '{}'; add some with `ppro docs note`", key),
+        },
+        None => match vault.readme()? {
+            Some(readme) => println!("{}", readme),
+            None => println!("The vault has no README; set one with `ppro docs readme`"),
+        },
+    }
+    Ok(())
+}
+
+/// Set or remove the vault README
+pub async fn docs_readme(ctx: &Context, file: Option<String>, clear: bool) -> Result<()> {
+    let readme = match (file, clear) {
+        (_, true) => None,
+        (Some(file), false) => Some(read_text_arg(&file)?),
+        (None, false) => {
+            return Err(anyhow::anyhow!("Give a README file (`-` for stdin) or --clear"))
+        }
+    };
+    let vault = ctx.writable_vault()?;
+
+    vault.set_readme(readme.as_deref())?;
+    match readme {
+        Some(_) => println!("[+] Vault README updated"),
+        None => println!("[-] Vault README removed"),
+    }
+    Ok(())
+}
+
+/// Set or remove the notes of a prompt
+pub async fn docs_note(
+    ctx: &Context,
+    key: String,
+    text: Option<String>,
+    file: Option<String>,
+    clear: bool,
+) -> Result<()> {
+    let notes = match (text, file, clear) {
+        (_, _, true) => None,
+        (Some(text), None, false) => Some(text),
+        (None, Some(file), false) => Some(read_text_arg(&file)?),
+        _ => return Err(anyhow::anyhow!("Give the notes as text, with --file, or --clear")),
+    };
+    let vault = ctx.writable_vault()?;
+
+    vault.set_notes(&key, notes.as_deref())?;
+    match notes {
+        Some(_) => println!("[+] Notes on '{}' updated", key),
+        None => println!("[-] Notes on '{}' removed", key),
+    }
+    Ok(())
+}
+
+/// Make an alias name an existing prompt
+pub async fn alias_set(ctx: &Context, alias: String, key: String) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    vault.set_alias(&alias, &key)?;
+    println!("[+] '{}' now names '{}'", alias, key);
+    Ok(())
+}
+
+/// Remove an alias
+pub async fn alias_remove(ctx: &Context, alias: String) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+    if !vault.remove_alias(&alias)? {
+        return Err(anyhow::anyhow!("No alias '{}'", alias));
+    }
+    println!("[-] Removed alias '{}'", alias);
+    Ok(())
+}
+
+/// Print every alias with the prompt it names
+pub async fn alias_list(ctx: &Context) -> Result<()> {
+    let aliases = ctx.vault()?.aliases()?;
+    if aliases.is_empty() {
+        println!("No aliases; add one with `ppro alias set`");
+    }
+    for (alias, key) in aliases {
+        println!("{} -> {}", alias, key);
+    }
+    Ok(())
+}
+
+/// Read a file argument, where `-` is stdin
+fn read_text_arg(path: &str) -> Result<String> {
+    if path == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        return Ok(text);
+    }
+    std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path, e))
+}
+
+/// Print the size, activity and tags of the vault, or of one key or namespace
+pub async fn stats(ctx: &Context, key: Option<String>, json: bool) -> Result<()> {
+    let vault = ctx.vault()?;
+    let stats = match &key {
+        Some(key) => vault.key_stats(key)?,
+        None => vault.stats()?,
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    let date = |time: Option<chrono::DateTime<chrono::Utc>>| match time {
+        Some(time) => time.format("%Y-%m-%d %H:%M").to_string(),
+        None => "-".to_string(),
+    };
+    println!(
+        "{} prompt(s), {} version(s), {} stored bytes, last write {}",
+        stats.keys,
+        stats.versions,
+        stats.bytes,
+        date(stats.last_write)
+    );
+
+    if key.is_none() && stats.namespaces.len() > 1 {
+        println!("\nNamespaces:");
+        for (namespace, ns) in &stats.namespaces {
+            let namespace = if namespace.is_empty() { "(none)" } else { namespace };
+            println!(
+                "  {:<24} {:>5} prompt(s) {:>6} version(s) {:>10} bytes",
+                namespace, ns.keys, ns.versions, ns.bytes
+            );
+        }
+    }
+
+    if stats.prompts.len() > 1 {
+        println!("\nMost edited:");
+        for prompt in stats.most_edited(10) {
+            println!("  {:<32} {:>6} version(s)", prompt.key, prompt.versions);
+        }
+    }
+
+    if !stats.tags.is_empty() {
+        println!("\nTags:");
+        for (tag, keys) in &stats.tags {
+            println!("  {:<16} {} prompt(s)", tag, keys);
+        }
+    }
+
+    if !stats.growth.is_empty() {
+        println!("\nGrowth:");
+        for month in &stats.growth {
+            println!(
+                "  {}  +{} version(s), +{} prompt(s)",
+                month.month, month.versions, month.new_keys
+            );
+        }
+    }
+
+    println!("\nPrompts:");
+    let mut prompts: Vec<_> = stats.prompts.iter().collect();
+    prompts.sort_by_key(|prompt| std::cmp::Reverse(prompt.last_write));
+    for prompt in prompts {
+        let line = format!(
+            "  {:<32} {:>6} version(s) {:>10} bytes  modified {}  {}",
+            prompt.key,
+            prompt.versions,
+            prompt.bytes,
+            date(prompt.last_write),
+            prompt.tags.join(",")
+        );
+        println!("{}", line.trim_end());
+    }
+
+    Ok(())
+}
+
+/// Export usage counters, metrics and audit events
+pub async fn stats_export(ctx: &Context, format: String, out: String) -> Result<()> {
+    let format: StatsFormat = format.parse()?;
+    let vault = ctx.vault()?;
+
+    for path in vault.export_stats(&out, format)? {
+        println!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Serve the vault over HTTP until Ctrl-C
+pub async fn serve(
+    ctx: &Context,
+    host: String,
+    port: u16,
+    preload: bool,
+    sweep: Option<SweepOptions>,
+) -> Result<()> {
+    let addr: std::net::SocketAddr = format!("{}:{}", host, port)
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid address '{}:{}': {}", host, port, e))?;
+    let path = crate::utils::current_vault_path()?;
+    let vault = ctx.writable_vault()?.clone();
+    if preload {
+        vault.preload()?;
+    }
+
+    if let Some(sweep) = sweep {
+        println!("Sweeping the vault about every {:?}", sweep.every);
+        crate::sweep::spawn_sweeps(vault.clone(), sweep);
+    }
+
+    println!("Serving {:?} on http://{}", path, addr);
+    crate::server::serve(vault, addr).await
+}
+
+/// Background sweep settings of `serve`, if sweeps are on
+pub fn sweep_options(
+    every: Option<String>,
+    keep_last: Option<usize>,
+    keep_newer_than: Option<String>,
+) -> Result<Option<SweepOptions>> {
+    let Some(every) = every else {
+        return Ok(None);
+    };
+    if keep_last == Some(0) {
+        return Err(anyhow::anyhow!("--sweep-keep-last must be at least 1"));
+    }
+    let every = parse_window(&every)?
+        .to_std()
+        .map_err(|_| anyhow::anyhow!("--sweep-every must be positive"))?;
+    Ok(Some(SweepOptions {
+        keep_last,
+        keep_within: keep_newer_than.as_deref().map(parse_window).transpose()?,
+        ..SweepOptions::new(every)
+    }))
+}
+
+/// Send the versions and tags a remote vault is missing
+pub async fn push(ctx: &Context, remote: Option<String>) -> Result<()> {
+    let remote = profile_remote(remote)?;
+    let report = ctx.vault()?.push(&remote).await?;
+    print_sync_report(&report)
+}
+
+/// Fetch the versions and tags this vault is missing from a remote vault
+pub async fn pull(ctx: &Context, remote: Option<String>) -> Result<()> {
+    let remote = profile_remote(remote)?;
+    let report = ctx.writable_vault()?.pull(&remote).await?;
+    print_sync_report(&report)
+}
+
+/// The given remote, else the remote of the selected profile
+fn profile_remote(remote: Option<String>) -> Result<Remote> {
+    let profile = active_profile()?;
+    let remote = remote
+        .or_else(|| profile.and_then(|profile| profile.remote.clone()))
+        .ok_or_else(|| match profile {
+            Some(profile) => anyhow::anyhow!(
+                "No remote given, and profile '{}' sets none",
+                profile.name
+            ),
+            None => anyhow::anyhow!("No remote given; pass one or select a profile that sets one"),
+        })?;
+    remote.parse()
+}
+
+/// List the profiles of the config file, marking the selected one
+pub async fn profiles() -> Result<()> {
+    let path = ProfileConfig::default_path()?;
+    let config = ProfileConfig::load(&path)?;
+    if config.profile.is_empty() {
+        println!("No profiles in {}", path.display());
+        return Ok(());
+    }
+    let active = active_profile()?.map(|profile| profile.name.as_str());
+    for (name, profile) in &config.profile {
+        let marker = if Some(name.as_str()) == active { "*" } else { " " };
+        println!("{} {}", marker, name);
+        if let Some(vault) = profile.vault_path()? {
+            println!("    vault:  {}", vault.display());
+        }
+        if let Some(remote) = &profile.remote {
+            println!("    remote: {}", remote);
+        }
+        if let Some(author) = &profile.author {
+            println!("    author: {}", author);
+        }
+    }
+    Ok(())
+}
+
+/// Print a setting of the config file
+pub async fn config_get(name: String) -> Result<()> {
+    match get_setting(&ProfileConfig::default_path()?, &name)? {
+        Some(value) => println!("{}", value),
+        None => println!("{} is not set", name),
+    }
+    Ok(())
+}
+
+/// Change a setting of the config file
+pub async fn config_set(name: String, value: String) -> Result<()> {
+    let path = ProfileConfig::default_path()?;
+    set_setting(&path, &name, &value)?;
+    println!("[+] Set {} in {}", name, path.display());
+    Ok(())
+}
+
+/// Remove a setting from the config file
+pub async fn config_unset(name: String) -> Result<()> {
+    let path = ProfileConfig::default_path()?;
+    if unset_setting(&path, &name)? {
+        println!("[-] Removed {} from {}", name, path.display());
+    } else {
+        println!("{} is not set", name);
+    }
+    Ok(())
+}
+
+fn print_sync_report(report: &SyncReport) -> Result<()> {
+    if report.is_up_to_date() {
+        println!("Already up to date");
+        return Ok(());
+    }
+    for (key, count) in &report.updated {
+        println!("[+] {}: {} new version(s)", key, count);
+    }
+    for key in &report.retagged {
+        println!("[~] {}: tags updated", key);
+    }
+    for key in &report.conflicts {
+        eprintln!("[!] {}: both vaults added different versions", key);
+    }
+    if !report.conflicts.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} prompt(s) were not synced because of conflicts",
+            report.conflicts.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Reconcile the vault with a Git repository mirroring it
+#[cfg(feature = "git")]
+pub async fn git_sync(ctx: &Context, repo: String, prefer: Option<String>) -> Result<()> {
+    let prefer = prefer.map(|side| side.parse()).transpose()?;
+    let report = ctx.writable_vault()?.git_sync(&repo, prefer)?;
+
+    println!("[+] Synced with {}", repo);
+    for (label, keys) in [
+        ("from git", &report.from_git),
+        ("committed", &report.committed),
+        ("removed", &report.removed),
+        ("removed in git, kept in the vault", &report.removed_in_git),
+        ("password-protected, skipped", &report.protected),
+    ] {
+        if !keys.is_empty() {
+            println!("    {}: {}", label, keys.join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "git"))]
+pub async fn git_sync(_ctx: &Context, _repo: String, _prefer: Option<String>) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "git-sync requires promptpro to be built with the `git` feature"
+    ))
+}
+
+/// Print the syncable prompts of a vault for `push`/`pull` over ssh
+pub async fn sync_remote_fetch(vault: String) -> Result<()> {
+    let export = open_vault(vault.as_ref(), LockPolicy::Fail, None)?.export_prompts()?;
+    serde_json::to_writer(io::stdout().lock(), &export)?;
+    Ok(())
+}
+
+/// Apply a patch sent by `push` over ssh
+pub async fn sync_remote_apply(vault: String) -> Result<()> {
+    let patch: SyncPatch = serde_json::from_reader(io::stdin().lock())?;
+    open_vault(vault.as_ref(), LockPolicy::Fail, None)?.apply_sync_patch(&patch)
+}
+
+/// Export the vault in a human-readable format
+pub async fn export(
+    ctx: &Context,
+    output: Option<String>,
+    format: Option<String>,
+    tags: Vec<String>,
+    vars: Vec<String>,
+) -> Result<()> {
+    if format.as_deref() == Some("web-bundle") {
+        let options = WebBundleOptions {
+            tags,
+            vars: parse_vars(&vars)?,
+        };
+        return export_web_bundle(ctx, output, &options);
+    }
+    if !tags.is_empty() || !vars.is_empty() {
+        return Err(anyhow::anyhow!("--tags and --var need --format web-bundle"));
+    }
+    let format = export_format(format, output.as_deref())?.unwrap_or(ExportFormat::Json);
+    let vault = ctx.vault()?;
+
+    let count = match &output {
+        Some(path) => vault.export_to(io::BufWriter::new(std::fs::File::create(path)?), format)?,
+        None => vault.export_to(io::stdout().lock(), format)?,
+    };
+
+    // Keep stdout clean for the export itself
+    eprintln!("Exported {} prompt(s)", count);
+    for key in vault.list_keys()? {
+        if vault.is_sensitive(&key)? {
+            eprintln!("    skipped password-protected prompt: {}", key);
+        }
+    }
+
+    Ok(())
+}
+
+/// Export the prompts `options` selects as a minified JSON bundle for web apps
+fn export_web_bundle(
+    ctx: &Context,
+    output: Option<String>,
+    options: &WebBundleOptions,
+) -> Result<()> {
+    let vault = ctx.vault()?;
+    let count = match &output {
+        Some(path) => {
+            vault.export_web_bundle(io::BufWriter::new(std::fs::File::create(path)?), options)?
+        }
+        None => vault.export_web_bundle(io::stdout().lock(), options)?,
+    };
+    eprintln!("Bundled {} prompt(s)", count);
+    Ok(())
+}
+
+/// Export one prompt as a Markdown document, or as JSON or YAML
+pub async fn export_key(
+    ctx: &Context,
+    key: String,
+    output: Option<String>,
+    format: Option<String>,
+) -> Result<()> {
+    let format = export_format(format, output.as_deref())?.unwrap_or(ExportFormat::Markdown);
+    let vault = ctx.vault()?;
+
+    match &output {
+        Some(path) => {
+            let file = io::BufWriter::new(std::fs::File::create(path)?);
+            vault.export_key_to(file, &key, format)?;
+            println!("[+] Exported '{}' to {}", key, path);
+        }
+        None => vault.export_key_to(io::stdout().lock(), &key, format)?,
+    }
+
+    Ok(())
+}
+
+/// Write prompts as template files for other template engines
+pub async fn export_templates(
+    ctx: &Context,
+    dir: String,
+    syntax: TemplateSyntax,
+    selector: String,
+    prefix: String,
+) -> Result<()> {
+    let selector = VersionSelector::parse(&selector)?;
+    let written = ctx.vault()?.export_templates(&dir, syntax, &selector, &prefix)?;
+    for path in &written {
+        println!("Wrote {}", path.display());
+    }
+    println!("[+] Exported {} template(s) to {}", written.len(), dir);
+
+    Ok(())
+}
+
+/// Import prompts from a human-readable export
+pub async fn import(ctx: &Context, input: String, format: Option<String>) -> Result<()> {
+    if let Some(external) = external_format(format.as_deref(), &input)? {
+        return import_external(ctx, input, external).await;
+    }
+    let format = export_format(format, Some(&input))?.ok_or_else(|| {
+        anyhow::anyhow!("Cannot guess the format of '{}'; pass --format", input)
+    })?;
+    let vault = ctx.writable_vault()?;
+
+    let count = vault.import_from(io::BufReader::new(std::fs::File::open(&input)?), format)?;
+    println!("[+] Imported {} prompt(s) from {}", count, input);
+
+    Ok(())
+}
+
+/// The format of prompts defined for another tool, or `None` for a promptpro export
+fn external_format(format: Option<&str>, input: &str) -> Result<Option<ExternalFormat>> {
+    let external = match format {
+        Some(format) => format.parse().ok(),
+        None => (ExportFormat::from_path(input) == Some(ExportFormat::Yaml))
+            .then_some(ExternalFormat::Yaml),
+    };
+    if external == Some(ExternalFormat::Yaml) && is_vault_export(&std::fs::read_to_string(input)?) {
+        return Ok(None);
+    }
+    Ok(external)
+}
+
+/// Import prompts defined for another tool
+async fn import_external(ctx: &Context, input: String, format: ExternalFormat) -> Result<()> {
+    let prompts = read_external(&input, format)?;
+    let report = ctx.writable_vault()?.import_external(&prompts, &input)?;
+    for key in &report.protected {
+        println!("Skipped password-protected {}", key);
+    }
+    println!(
+        "[+] Imported {} prompt(s) from {}: {} created, {} updated, {} unchanged",
+        prompts.len(),
+        input,
+        report.created.len(),
+        report.updated.len(),
+        report.unchanged.len()
+    );
+
+    Ok(())
+}
+
+/// Import the prompt constants of source code
+pub async fn import_source(
+    ctx: &Context,
+    input: String,
+    from: String,
+    dry_run: bool,
+) -> Result<()> {
+    let language: SourceLanguage = from.parse()?;
+    let constants = scan_source(&input, language)?;
+    if constants.is_empty() {
+        println!("No prompt constants found in {}", input);
+        return Ok(());
+    }
+
+    let vault = if dry_run {
+        ctx.vault()?
+    } else {
+        ctx.writable_vault()?
+    };
+    for constant in &constants {
+        println!(
+            "{}:{} {} -> {}",
+            constant.file.display(),
+            constant.line,
+            constant.name,
+            constant.key
+        );
+    }
+    let report = vault.import_constants(&constants, !dry_run)?;
+    for key in &report.protected {
+        println!("Skipped password-protected {}", key);
+    }
+    let (created, updated) = if dry_run {
+        ("would be created", "would be updated")
+    } else {
+        ("created", "updated")
+    };
+    println!(
+        "{} {}, {} {}, {} unchanged",
+        report.created.len(),
+        created,
+        report.updated.len(),
+        updated,
+        report.unchanged.len()
+    );
+
+    Ok(())
+}
+
+/// Import a directory of prompt files
+pub async fn import_dir(ctx: &Context, dir: String, dry_run: bool) -> Result<()> {
+    let vault = if dry_run {
+        ctx.vault()?
+    } else {
+        ctx.writable_vault()?
+    };
+
+    let report = if dry_run {
+        vault.plan_import_dir(&dir)?
+    } else {
+        vault.import_dir(&dir)?
+    };
+    let (created, updated) = if dry_run {
+        ("Would create", "Would update")
+    } else {
+        ("[+] Created", "[+] Updated")
+    };
+    for key in &report.created {
+        println!("{} {}", created, key);
+    }
+    for key in &report.updated {
+        println!("{} {}", updated, key);
+    }
+    for key in &report.protected {
+        println!("Skipped password-protected {}", key);
+    }
+    println!(
+        "{} created, {} updated, {} unchanged",
+        report.created.len(),
+        report.updated.len(),
+        report.unchanged.len()
+    );
+
+    Ok(())
+}
+
+/// Import a directory of prompt files and version every file saved afterwards
+pub async fn watch(ctx: &Context, dir: String) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+
+    // Start watching first so that saves during the import are not missed
+    let watcher = vault.watch_dir(&dir)?;
+    let report = vault.import_dir(&dir)?;
+    println!(
+        "{} created, {} updated, {} unchanged",
+        report.created.len(),
+        report.updated.len(),
+        report.unchanged.len()
+    );
+    println!("Watching {} for changes (Ctrl+C to stop)", dir);
+
+    loop {
+        for watched in watcher.wait(vault, std::time::Duration::from_secs(3600))? {
+            match watched.change {
+                FileChange::Created => {
+                    println!("[+] Created {} from {}", watched.key, watched.file)
+                }
+                FileChange::Updated => {
+                    let version = get_latest_version_number(vault, &watched.key)?.unwrap_or(0);
+                    println!("[+] Updated {} to v{} from {}", watched.key, version, watched.file);
+                }
+                FileChange::Protected => {
+                    println!("Skipped password-protected {}", watched.key)
+                }
+                FileChange::Unchanged => {}
+            }
+        }
+    }
+}
+
+/// Explicit `--format`, or the format implied by the file extension
+fn export_format(format: Option<String>, path: Option<&str>) -> Result<Option<ExportFormat>> {
+    match format {
+        Some(format) => Ok(Some(format.parse()?)),
+        None => Ok(path.and_then(ExportFormat::from_path)),
+    }
+}
+
+/// Delete a prompt key and all its versions, or every prompt in a namespace
+pub async fn delete(ctx: &Context, key: String, recursive: bool) -> Result<()> {
+    let vault = ctx.writable_vault()?;
+
+    if recursive {
+        let deleted = vault.delete_namespace(&key)?;
+        if deleted.is_empty() {
+            return Err(anyhow::anyhow!("No prompts in namespace '{}'", key));
+        }
+        for key in &deleted {
+            println!("[+] Deleted prompt: {}", key);
+        }
+        return Ok(());
+    }
+    
+    match vault.delete_prompt_key(&key) {
+        Ok(()) => {
+            println!("[+] Deleted prompt: {}", key);
+        },
+        Err(e) => {
+            eprintln!("Error deleting prompt: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// Restore/Resume the vault from a binary file
+pub async fn resume(
+    ctx: &Context,
+    input: String,
+    password: PasswordOptions,
+    target: Option<String>,
+    force: bool,
+    on_conflict: Option<String>,
+) -> Result<()> {
+    let merging = target.is_none() && !force;
+    // `None` asks for each conflict
+    let policy = match on_conflict.as_deref() {
+        Some(_) if !merging => {
+            return Err(anyhow::anyhow!(
+                "--on-conflict only applies when merging into the current vault"
+            ))
+        }
+        None => Some(ConflictPolicy::Local),
+        Some("interactive") if !io::stdin().is_terminal() => {
+            return Err(anyhow::anyhow!("--on-conflict interactive needs a terminal"))
+        }
+        Some("interactive") => None,
+        Some(policy) => Some(policy.parse()?),
+    };
+
+    let encrypted = PromptVault::is_encrypted_dump(&input).unwrap_or(false);
+    let result = if !merging {
+        restore_dump_to(&input, target, password, force)
+    } else {
+        match ctx.writable_vault() {
+            Ok(vault) if !vault.list_keys()?.is_empty() => {
+                match merge_dump_into(vault, &input, password, policy) {
+                    Ok(report) => print_merge_report(&input, &report),
+                    Err(e) => eprintln!("Error resuming vault: {}", e),
+                }
+                return Ok(());
+            }
+            // A vault without prompts takes the dump whole
+            Ok(vault) => restore_dump(vault, &input, password)
+                .and_then(|count| Ok((count, crate::utils::current_vault_path()?))),
+            Err(e) => Err(e),
+        }
+    };
+
+    match result {
+        Ok((count, path)) => {
+            println!("Vault restored successfully from: {}", input);
+            if encrypted {
+                println!("Vault was encrypted with provided password");
+            } else {
+                println!("Vault was unencrypted");
+            }
+            println!("Restored {} entries to {:?}", count, path);
+        }
+        Err(e) => {
+            eprintln!("Error resuming vault: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Merge a dump into `vault` key by key, settling conflicts with `policy`, or by asking
+/// without one
+fn merge_dump_into(
+    vault: &PromptVault,
+    input: &str,
+    password: PasswordOptions,
+    policy: Option<ConflictPolicy>,
+) -> Result<MergeReport> {
+    let password = if PromptVault::is_encrypted_dump(input).unwrap_or(false) {
+        Some(password.resolve_or_prompt("Dump password: ")?)
+    } else {
+        None
+    };
+
+    vault.merge_dump_with(input, password.as_deref(), |conflict| {
+        if let Some(policy) = policy {
+            return Ok(policy);
+        }
+        println!(
+            "'{}' changed on both sides after v{}: local v{} ({}), dump v{} ({})",
+            conflict.key,
+            conflict.shared,
+            conflict.local_latest,
+            conflict.local_modified.format("%Y-%m-%d %H:%M"),
+            conflict.dump_latest,
+            conflict.dump_modified.format("%Y-%m-%d %H:%M"),
+        );
+        loop {
+            match read_line("Keep [l]ocal, [r]emote or [b]oth? ")?.as_str() {
+                "l" | "local" => return Ok(ConflictPolicy::Local),
+                "r" | "remote" => return Ok(ConflictPolicy::Remote),
+                "b" | "both" => return Ok(ConflictPolicy::Both),
+                other => println!("Unknown choice '{}'", other),
+            }
+        }
+    })
+}
+
+fn print_merge_report(input: &str, report: &MergeReport) {
+    println!(
+        "Merged {}: {} added, {} updated, {} conflicting",
+        input,
+        report.added.len(),
+        report.updated.len(),
+        report.conflicts.len()
+    );
+    for key in &report.added {
+        println!("  + {}", key);
+    }
+    for (key, versions) in &report.updated {
+        println!("  ↑ {} (+{} versions)", key, versions);
+    }
+    for (key, policy) in &report.conflicts {
+        println!("  ! {} ({})", key, policy);
+    }
+    for key in &report.skipped {
+        println!("  - {} (password-protected, not merged)", key);
+    }
+}
+
+/// Restore a dump into the vault at `target` (default: the current vault), which must
+/// not hold prompts unless `force` overwrites it
+fn restore_dump_to(
+    input: &str,
+    target: Option<String>,
+    password: PasswordOptions,
+    force: bool,
+) -> Result<(usize, std::path::PathBuf)> {
+    let path = match target {
+        Some(target) => std::path::PathBuf::from(target),
+        None => crate::utils::current_vault_path()?,
+    };
+    let password = if PromptVault::is_encrypted_dump(input).unwrap_or(false) {
+        Some(password.resolve_or_prompt("Dump password: ")?)
+    } else {
+        None
+    };
+    let policy = if force {
+        RestorePolicy::Overwrite
+    } else {
+        RestorePolicy::Refuse
+    };
+
+    let vault = PromptVault::restore_to(input, &path, password.as_deref(), policy)?;
+    Ok((vault.db().len(), path))
+}
+
+/// Write a lockfile pinning the latest version of every prompt, or check the vault
+/// against one
+pub async fn lock(ctx: &Context, output: String, check: bool) -> Result<()> {
+    let vault = ctx.vault()?;
+
+    if check {
+        let lockfile = vault.verify_lock(&output)?;
+        println!("[+] {} pinned prompt(s) match {}", lockfile.prompts.len(), output);
+        return Ok(());
+    }
+
+    let lockfile = vault.lock()?;
+    lockfile.write(&output)?;
+    println!("[+] Pinned {} prompt(s) in {}", lockfile.prompts.len(), output);
+
+    Ok(())
+}
+
+/// Print the header of a dump file
+pub async fn inspect_dump(input: String) -> Result<()> {
+    let header = PromptVault::inspect_dump(&input)?;
+
+    if header.is_supported() {
+        println!("Format:      v{}", header.format);
+    } else {
+        println!("Format:      v{} (newer than this promptpro reads)", header.format);
+    }
+    println!("Encrypted:   {}", if header.encrypted { "yes" } else { "no" });
+    println!(
+        "Written by:  {}",
+        header
+            .written_by
+            .map(|version| format!("promptpro {}", version))
+            .unwrap_or_else(|| "unknown".to_string())
+    );
+    if let Some(created_at) = header.created_at {
+        println!("Created:     {}", created_at.format("%Y-%m-%d %H:%M:%S"));
+    }
+    if let Some(entries) = header.entries {
+        println!("Entries:     {}", entries);
+    }
+
+    Ok(())
+}
+
+/// Show the vault's compression, or change it and rewrite the stored content
+pub async fn compression(ctx: &Context, codec: Option<String>) -> Result<()> {
+    let codec: Option<Compression> = codec.map(|codec| codec.parse()).transpose()?;
+
+    let Some(codec) = codec else {
+        let vault = ctx.vault()?;
+        println!("Compression:  {}", vault.compression()?);
+        println!("Stored bytes: {}", vault.stats()?.bytes);
+        return Ok(());
+    };
+
+    let vault = ctx.writable_vault()?;
+    vault.set_compression(codec)?;
+    let report = vault.recompress()?;
+    vault.db().flush()?;
+    println!("[+] Vault now uses {} compression", codec);
+    println!(
+        "    {} blob(s) rewritten: {} -> {} bytes",
+        report.blobs, report.bytes_before, report.bytes_after
+    );
+
+    Ok(())
+}
+
+/// Migrate the vault to the current storage layout
+pub async fn migrate(
+    ctx: &Context,
+    backup: Option<String>,
+    samples: usize,
+    check: bool,
+) -> Result<()> {
+    let vault = ctx.vault()?;
+    let from = vault.layout_version()?;
+    if from >= LAYOUT_VERSION {
+        println!("Vault uses storage layout {}; nothing to migrate", from);
+        return Ok(());
+    }
+    if check {
+        println!(
+            "Vault uses storage layout {}; run `promptpro migrate` to upgrade it to {}",
+            from, LAYOUT_VERSION
+        );
+        return Ok(());
+    }
+
+    let vault_path = crate::utils::current_vault_path()?;
+    let backup = match backup {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            let stamp = chrono::Local::now().format("%Y%m%d%H%M%S");
+            let mut path = vault_path.clone().into_os_string();
+            path.push(format!(".pre-migrate-{}.bin", stamp));
+            std::path::PathBuf::from(path)
+        }
+    };
+    let rollback = format!(
+        "To roll back, move {} aside and run `promptpro resume {}`",
+        vault_path.display(),
+        backup.display()
+    );
+
+    println!("Backing up the vault to {}", backup.display());
+    let result = vault.migrate(&backup, samples, |done, total, key| {
+        eprint!("\r\x1b[2K[{}/{}] {}", done, total, key);
+        let _ = io::stderr().flush();
+    });
+    eprintln!();
+
+    match result {
+        Ok(report) => {
+            println!(
+                "[+] Migrated the vault from layout {} to {}",
+                report.from, report.to
+            );
+            println!(
+                "{} version(s) repacked, {} sampled version(s) verified",
+                report.repacked, report.verified
+            );
+            println!("{}", rollback);
+            Ok(())
+        }
+        Err(e) if backup.exists() => Err(anyhow::anyhow!("{}\n{}", e, rollback)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Check the integrity of the vault, optionally repairing it; fails while issues remain
+pub async fn fsck(ctx: &Context, repair: bool) -> Result<()> {
+    let vault = if repair {
+        ctx.writable_vault()?
+    } else {
+        ctx.vault()?
+    };
+    let report = vault.verify(repair)?;
+
+    for issue in &report.issues {
+        let repaired = report.repaired && issue.repairable();
+        println!("{} {}", if repaired { "[repaired]" } else { "[!]" }, issue);
+    }
+    let outstanding = report.outstanding().count();
+    println!(
+        "{} version(s) checked, {} issue(s) found, {} outstanding",
+        report.versions,
+        report.issues.len(),
+        outstanding
+    );
+    if outstanding > 0 {
+        let hint = if !repair && report.issues.iter().any(|issue| issue.repairable()) {
+            "; run `promptpro fsck --repair` to fix the repairable ones"
+        } else {
+            ""
+        };
+        return Err(anyhow::anyhow!("The vault has {} issue(s){}", outstanding, hint));
+    }
+
+    Ok(())
+}
+
+/// Remove versions outside a retention policy and orphaned entries, then optionally
+/// rewrite the vault to shrink it on disk
+pub async fn gc(
+    ctx: &Context,
+    keep_last: Option<usize>,
+    keep_newer_than: Option<String>,
+    drop_tagged: bool,
+    compact: bool,
+    dry_run: bool,
+) -> Result<()> {
+    if keep_last == Some(0) {
+        return Err(anyhow::anyhow!("--keep-last must be at least 1"));
+    }
+    let policy = RetentionPolicy {
+        keep_last,
+        keep_tagged: !drop_tagged,
+        keep_newer_than: match keep_newer_than {
+            Some(window) => Some(chrono::Utc::now() - parse_window(&window)?),
+            None => None,
+        },
+    };
+    if drop_tagged && !policy.prunes_versions() {
+        return Err(anyhow::anyhow!(
+            "--drop-tagged needs --keep-last or --keep-newer-than"
+        ));
+    }
+    if compact && dry_run {
+        return Err(anyhow::anyhow!("--compact cannot be combined with --dry-run"));
+    }
+
+    // Opened here rather than through the context, so it can be closed for compaction
+    let vault_path = crate::utils::current_vault_path()?;
+    if !vault_path.exists() {
+        return Err(anyhow::anyhow!("No vault at {:?}; run `ppro init` first", vault_path));
+    }
+    let vault = ctx.open(&vault_path, ctx.write_lock_policy())?;
+
+    if dry_run {
+        let pruned = vault.gc_preview(&policy)?;
+        for (key, versions) in &pruned {
+            let versions: Vec<String> = versions.iter().map(|v| format!("v{}", v)).collect();
+            println!("{}: {}", key, versions.join(", "));
+        }
+        let total: usize = pruned.values().map(Vec::len).sum();
+        println!("{} version(s) of {} prompt(s) would be removed", total, pruned.len());
+        return Ok(());
+    }
+
+    let report = vault.gc(&policy)?;
+    println!(
+        "[+] Removed {} version(s) of {} prompt(s) and {} orphaned entr{}",
+        report.pruned_versions(),
+        report.pruned.len(),
+        report.orphans,
+        if report.orphans == 1 { "y" } else { "ies" }
+    );
+    if report.rebased > 0 {
+        println!("    {} kept version(s) rewritten as snapshots", report.rebased);
+    }
+    for key in &report.skipped {
+        println!("    Skipped protected prompt '{}'", key);
+    }
+
+    if compact {
+        drop(vault);
+        let (before, after) = PromptVault::compact(&vault_path)?;
+        println!("[+] Compacted the vault: {} -> {} bytes on disk", before, after);
+    }
+
+    Ok(())
+}
+
+/// Copy the entries of a dump into `target`; returns the number of entries the
+/// target holds afterwards
+fn restore_dump(target: &PromptVault, input: &str, password: PasswordOptions) -> Result<usize> {
+    // Only ask for a password when the dump is actually encrypted
+    let password = if PromptVault::is_encrypted_dump(input).unwrap_or(false) {
+        Some(password.resolve_or_prompt("Dump password: ")?)
+    } else {
+        None
+    };
+
+    target.copy_dump(input, password.as_deref())?;
+    Ok(target.db().iter().filter(|result| result.is_ok()).count())
+}
+
+/// Resolve the passphrase of a protected prompt, checking the prompt's keyring entry
+fn resolve_passphrase(key: &str, passphrase: PasswordOptions) -> Result<String> {
+    passphrase
+        .with_keyring_entry(prompt_keyring_entry(key))
+        .resolve_or_prompt(&format!("Passphrase for '{}': ", key))
+}
+
+/// Resolve a vault argument, where `default` means the personal default vault
+fn vault_path_arg(vault: &str) -> Result<std::path::PathBuf> {
+    if vault == "default" {
+        crate::utils::default_vault_path()
+    } else {
+        Ok(std::path::PathBuf::from(vault))
+    }
+}
+
+/// Unlock sensitive prompts for display, resolving each passphrase
+fn unlock_keys(vault: &PromptVault, keys: &[String]) -> Result<UnlockedKeys> {
+    let mut unlocked = UnlockedKeys::new();
+    for key in keys {
+        let passphrase = resolve_passphrase(key, PasswordOptions::default())?;
+        unlocked.unlock(vault, key, &passphrase)?;
+    }
+    Ok(unlocked)
+}
+
 /// Helper function to get the latest version number for a key
 fn get_latest_version_number(vault: &PromptVault, key: &str) -> Result<Option<u64>> {
     let mut versions = Vec::new();
@@ -0,0 +1,217 @@
+//! Review comments on prompt versions, so feedback stays with the version it is about.
+//!
+//! A comment can reply to an earlier comment on the same version, which makes threads.
+//! `promptpro history` lists the comments under each version and the TUI shows them in
+//! a popup (`c` in the Versions panel). Comments are numbered per prompt from 1.
+
+use crate::storage::PromptVault;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A comment on a version of a prompt
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    /// Number of the comment among those on the prompt
+    pub id: u64,
+    pub key: String,
+    pub version: u64,
+    /// Comment this one replies to
+    pub reply_to: Option<u64>,
+    pub author: String,
+    pub text: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+fn comment_key(key: &str, version: u64, id: u64) -> String {
+    format!("comment:{}:{}:{}", key, version, id)
+}
+
+fn comments_prefix(key: &str) -> String {
+    format!("comment:{}:", key)
+}
+
+/// Number of the last comment on a prompt
+fn last_comment_key(key: &str) -> String {
+    format!("comment_id:{}", key)
+}
+
+impl PromptVault {
+    /// Comment on version `version` of `key` as `author`
+    pub fn comment(&self, key: &str, version: u64, author: &str, text: &str) -> Result<Comment> {
+        self.store_comment(key, version, None, author, text)
+    }
+
+    /// Reply to comment `reply_to` of `key` as `author`, on the version it is about
+    pub fn reply(&self, key: &str, reply_to: u64, author: &str, text: &str) -> Result<Comment> {
+        let parent = self
+            .key_comments(key)?
+            .into_iter()
+            .find(|comment| comment.id == reply_to)
+            .ok_or_else(|| anyhow::anyhow!("Comment #{} on '{}' not found", reply_to, key))?;
+        self.store_comment(key, parent.version, Some(reply_to), author, text)
+    }
+
+    fn store_comment(
+        &self,
+        key: &str,
+        version: u64,
+        reply_to: Option<u64>,
+        author: &str,
+        text: &str,
+    ) -> Result<Comment> {
+        let (author, text) = (author.trim(), text.trim());
+        if author.is_empty() {
+            return Err(anyhow::anyhow!("Comments need an author"));
+        }
+        if text.is_empty() {
+            return Err(anyhow::anyhow!("Comments cannot be empty"));
+        }
+        if self.get_version_meta(key, version)?.is_none() {
+            if self.get_latest_version_number(key)?.is_none() {
+                return Err(self.unknown_key(key));
+            }
+            return Err(anyhow::anyhow!("Version {} of '{}' not found", version, key));
+        }
+
+        let id = self.next_comment_id(key)?;
+        let comment = Comment {
+            id,
+            key: key.to_string(),
+            version,
+            reply_to,
+            author: author.to_string(),
+            text: text.to_string(),
+            timestamp: Utc::now(),
+        };
+        self.db().insert(
+            comment_key(key, version, id).as_bytes(),
+            bincode::serialize(&comment)?,
+        )?;
+        Ok(comment)
+    }
+
+    /// Claim the number of a new comment on `key`
+    fn next_comment_id(&self, key: &str) -> Result<u64> {
+        let last = self
+            .db()
+            .update_and_fetch(last_comment_key(key).as_bytes(), |old| {
+                let last = old
+                    .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+                    .map(u64::from_le_bytes)
+                    .unwrap_or(0);
+                Some((last + 1).to_le_bytes().to_vec())
+            })?
+            .ok_or_else(|| anyhow::anyhow!("Failed to number the comment"))?;
+        Ok(u64::from_le_bytes(last.as_ref().try_into()?))
+    }
+
+    /// Comments on version `version` of `key`, oldest first
+    pub fn comments(&self, key: &str, version: u64) -> Result<Vec<Comment>> {
+        let mut comments = self.key_comments(key)?;
+        comments.retain(|comment| comment.version == version);
+        Ok(comments)
+    }
+
+    /// Comments on every version of `key`, oldest first
+    pub fn key_comments(&self, key: &str) -> Result<Vec<Comment>> {
+        let mut comments = Vec::new();
+        for (_, value) in self.scan_entries(comments_prefix(key).as_bytes())? {
+            let comment: Comment = bincode::deserialize(&value)?;
+            // The prefix also matches the comments of keys starting with `key:`
+            if comment.key == key {
+                comments.push(comment);
+            }
+        }
+        comments.sort_by_key(|comment| comment.id);
+        Ok(comments)
+    }
+
+    /// Remove the comments on a prompt that is being deleted
+    pub(crate) fn remove_comments(&self, key: &str) -> Result<()> {
+        for (entry, value) in self.scan_entries(comments_prefix(key).as_bytes())? {
+            let comment: Comment = bincode::deserialize(&value)?;
+            if comment.key == key {
+                self.remove_entry(&entry)?;
+            }
+        }
+        self.remove_entry(last_comment_key(key).as_bytes())
+    }
+}
+
+/// `comments` in thread order, each followed by its replies, with how deeply each
+/// is nested. Replies to comments that are not in `comments` start a thread.
+pub fn comment_threads(comments: &[Comment]) -> Vec<(usize, &Comment)> {
+    fn add_thread<'a>(
+        comments: &'a [Comment],
+        comment: &'a Comment,
+        depth: usize,
+        out: &mut Vec<(usize, &'a Comment)>,
+    ) {
+        out.push((depth, comment));
+        for reply in comments.iter().filter(|reply| reply.reply_to == Some(comment.id)) {
+            add_thread(comments, reply, depth + 1, out);
+        }
+    }
+
+    let mut out = Vec::with_capacity(comments.len());
+    let roots = comments.iter().filter(|comment| {
+        comment
+            .reply_to
+            .is_none_or(|parent| !comments.iter().any(|other| other.id == parent))
+    });
+    for root in roots {
+        add_thread(comments, root, 0, &mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_comments() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "You write.")?;
+        vault.update("writer", "You write posts.", None)?;
+        vault.add("writer:draft", "Draft.")?;
+
+        let first = vault.comment("writer", 2, "alice", "Too vague?")?;
+        vault.comment("writer", 1, "carol", "Fine for now")?;
+        let reply = vault.reply("writer", first.id, "bob", "Agreed, name the audience")?;
+        vault.reply("writer", reply.id, "alice", "Done in v3")?;
+        vault.comment("writer", 2, "carol", "Ship it")?;
+        vault.comment("writer:draft", 1, "dave", "Unrelated")?;
+        assert_eq!((reply.id, reply.version), (3, 2));
+
+        assert!(vault.comment("writer", 9, "alice", "Nope").is_err());
+        assert!(vault.comment("missing", 1, "alice", "Nope").is_err());
+        assert!(vault.comment("writer", 1, "alice", "  ").is_err());
+        assert!(vault.reply("writer", 42, "alice", "Nope").is_err());
+
+        let comments = vault.comments("writer", 2)?;
+        let thread: Vec<(usize, &str)> = comment_threads(&comments)
+            .into_iter()
+            .map(|(depth, comment)| (depth, comment.text.as_str()))
+            .collect();
+        assert_eq!(
+            thread,
+            [
+                (0, "Too vague?"),
+                (1, "Agreed, name the audience"),
+                (2, "Done in v3"),
+                (0, "Ship it")
+            ]
+        );
+        assert_eq!(vault.key_comments("writer")?.len(), 5);
+
+        vault.delete_prompt_key("writer")?;
+        assert!(vault.key_comments("writer")?.is_empty());
+        assert_eq!(vault.key_comments("writer:draft")?.len(), 1);
+
+        Ok(())
+    }
+}
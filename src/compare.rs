@@ -0,0 +1,107 @@
+//! Two versions of a prompt rendered with the same values, for `promptpro compare`, to
+//! decide whether a candidate version should replace the stable one.
+
+use crate::chat::ChatPrompt;
+use crate::diff::{side_by_side, SideBySideRow};
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Two rendered versions of a prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    pub key: String,
+    pub old_version: u64,
+    pub new_version: u64,
+    pub old_prompt: String,
+    pub new_prompt: String,
+}
+
+impl Comparison {
+    /// The lines of both prompts side by side
+    pub fn prompt_rows(&self) -> Vec<SideBySideRow> {
+        side_by_side(&self.old_prompt, &self.new_prompt)
+    }
+}
+
+/// Values for the placeholders of a prompt from a JSON object, e.g. `{"name": "Ada"}`;
+/// values that are not strings are used as JSON
+pub fn parse_input(json: &str) -> Result<HashMap<String, String>> {
+    let input: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json)
+        .map_err(|e| anyhow::anyhow!("Input must be a JSON object of values: {}", e))?;
+    Ok(input
+        .into_iter()
+        .map(|(name, value)| match value {
+            serde_json::Value::String(value) => (name, value),
+            value => (name, value.to_string()),
+        })
+        .collect())
+}
+
+impl PromptVault {
+    /// Render versions `old` and `new` of `key` with `vars`; chat prompts are rendered
+    /// as their messages
+    pub fn compare(
+        &self,
+        key: &str,
+        old: VersionSelector,
+        new: VersionSelector,
+        vars: &HashMap<String, String>,
+    ) -> Result<Comparison> {
+        let old_version = self.resolve_version(key, &old)?;
+        let new_version = self.resolve_version(key, &new)?;
+        Ok(Comparison {
+            key: key.to_string(),
+            old_version,
+            new_version,
+            old_prompt: self.render_for_comparison(key, old_version, vars)?,
+            new_prompt: self.render_for_comparison(key, new_version, vars)?,
+        })
+    }
+
+    fn render_for_comparison(
+        &self,
+        key: &str,
+        version: u64,
+        vars: &HashMap<String, String>,
+    ) -> Result<String> {
+        let selector = VersionSelector::Version(version);
+        if ChatPrompt::is_chat(&self.get(key, selector.clone())?) {
+            return Ok(self.render_chat(key, selector, vars, false)?.to_text());
+        }
+        self.render(key, selector, vars, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::RowKind;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_compare() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("support", "Hello {{name}}.\nBe polite.\nBye.\n")?;
+        vault.update("support", "Hello {{name}}.\nBe brief.\nCite sources.\nBye.\n", None)?;
+        vault.tag("support", "stable", 1)?;
+
+        let vars = parse_input(r#"{"name": "Ada", "age": 36}"#)?;
+        assert_eq!(vars["age"], "36");
+        assert!(parse_input("[1, 2]").is_err());
+        let stable = VersionSelector::tag("stable");
+        let comparison = vault.compare("support", stable, VersionSelector::Latest, &vars)?;
+        assert_eq!((comparison.old_version, comparison.new_version), (1, 2));
+
+        let rows = comparison.prompt_rows();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0].old.as_deref(), Some("Hello Ada."));
+        assert_eq!((rows[1].kind, rows[1].new.as_deref()), (RowKind::Changed, Some("Be brief.")));
+        assert_eq!((rows[2].kind, rows[2].old.as_deref()), (RowKind::Insert, None));
+        assert_eq!(rows[3].kind, RowKind::Equal);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+//! Prompts including other prompts with `{{> key}}` or `{{> key@selector}}`, so shared
+//! text such as a preamble is stored once.
+//!
+//! Includes are resolved when a prompt is rendered ([`PromptVault::resolve`]): each is
+//! replaced by the content of the selected version of the included prompt, itself
+//! resolved, so a tag moving changes every prompt including it. `get` returns the
+//! content as stored.
+
+use crate::storage::PromptVault;
+use crate::types::{VersionSelector, VersionedPrompt};
+use anyhow::{Context as _, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// A `{{> key}}` or `{{> key@selector}}` include
+static INCLUDE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{>\s*([^\s@}]+)(?:@([^\s}]+))?\s*\}\}").unwrap());
+
+/// How deeply includes may nest
+const MAX_DEPTH: usize = 32;
+
+/// A prompt included by another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Include {
+    pub key: String,
+    /// Version selector, `latest` when none is given
+    pub selector: String,
+}
+
+/// The includes of a prompt, in order of appearance
+pub fn includes(content: &str) -> Vec<Include> {
+    INCLUDE
+        .captures_iter(content)
+        .map(|captures| Include {
+            key: captures[1].to_string(),
+            selector: captures.get(2).map_or("latest", |m| m.as_str()).to_string(),
+        })
+        .collect()
+}
+
+/// A prompt with the prompts it includes, for `promptpro deps`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub key: String,
+    pub selector: String,
+    /// Version the selector resolves to, or none when it does not resolve
+    pub version: Option<u64>,
+    /// Whether the prompt includes itself through this one; its includes are left out
+    pub cycle: bool,
+    pub includes: Vec<Dependency>,
+}
+
+/// `a@3 -> b@1 -> a@3`
+fn describe_chain(chain: &[(String, u64)], key: &str, version: u64) -> String {
+    chain
+        .iter()
+        .map(|(key, version)| format!("{}@{}", key, version))
+        .chain([format!("{}@{}", key, version)])
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+impl PromptVault {
+    /// Get a prompt version with its `{{> key@selector}}` includes replaced by the
+    /// content of the included prompts, failing on missing prompts and cycles
+    pub fn resolve(&self, key: &str, selector: VersionSelector) -> Result<String> {
+        let prompt = self.get_versioned(key, selector)?;
+        self.expand(key, prompt.version, prompt.content, &mut Vec::new())
+    }
+
+    /// Resolve the includes of part of version `version` of `key`, e.g. one message of a
+    /// chat prompt
+    pub(crate) fn resolve_content(&self, key: &str, version: u64, content: &str) -> Result<String> {
+        self.expand(key, version, content.to_string(), &mut Vec::new())
+    }
+
+    fn expand(
+        &self,
+        key: &str,
+        version: u64,
+        content: String,
+        chain: &mut Vec<(String, u64)>,
+    ) -> Result<String> {
+        if chain.iter().any(|(k, v)| k == key && *v == version) {
+            return Err(anyhow::anyhow!(
+                "Include cycle: {}",
+                describe_chain(chain, key, version)
+            ));
+        }
+        if chain.len() >= MAX_DEPTH {
+            return Err(anyhow::anyhow!(
+                "Includes nested deeper than {}: {}",
+                MAX_DEPTH,
+                describe_chain(chain, key, version)
+            ));
+        }
+        if !INCLUDE.is_match(&content) {
+            return Ok(content);
+        }
+
+        chain.push((key.to_string(), version));
+        let mut resolved = String::with_capacity(content.len());
+        let mut rest = 0;
+        for captures in INCLUDE.captures_iter(&content) {
+            let whole = captures.get(0).expect("group 0 always matches");
+            resolved.push_str(&content[rest..whole.start()]);
+            rest = whole.end();
+
+            let included = &captures[1];
+            let selector = captures.get(2).map_or("latest", |m| m.as_str());
+            let prompt = VersionSelector::parse(selector)
+                .and_then(|selector| self.get_versioned(included, selector))
+                .with_context(|| {
+                    format!("Cannot include {}@{} in '{}' v{}", included, selector, key, version)
+                })?;
+            resolved.push_str(&self.expand(included, prompt.version, prompt.content, chain)?);
+        }
+        resolved.push_str(&content[rest..]);
+        chain.pop();
+        Ok(resolved)
+    }
+
+    /// The prompts a prompt version includes, directly and through other includes
+    pub fn dependencies(&self, key: &str, selector: VersionSelector) -> Result<Dependency> {
+        let prompt = self.get_versioned(key, selector)?;
+        let selector = prompt.version.to_string();
+        Ok(self.dependency(key, selector, Some(prompt), &mut Vec::new()))
+    }
+
+    fn dependency(
+        &self,
+        key: &str,
+        selector: String,
+        prompt: Option<VersionedPrompt>,
+        chain: &mut Vec<(String, u64)>,
+    ) -> Dependency {
+        let mut dependency = Dependency {
+            key: key.to_string(),
+            selector,
+            version: prompt.as_ref().map(|prompt| prompt.version),
+            cycle: false,
+            includes: Vec::new(),
+        };
+        let Some(prompt) = prompt else {
+            return dependency;
+        };
+        if chain.iter().any(|(k, v)| k == key && *v == prompt.version) {
+            dependency.cycle = true;
+            return dependency;
+        }
+
+        chain.push((key.to_string(), prompt.version));
+        for include in includes(&prompt.content) {
+            let included = VersionSelector::parse(&include.selector)
+                .and_then(|selector| self.get_versioned(&include.key, selector))
+                .ok();
+            let child = self.dependency(&include.key, include.selector, included, chain);
+            dependency.includes.push(child);
+        }
+        chain.pop();
+        dependency
+    }
+
+    /// Keys whose latest version includes `key` directly
+    pub fn dependents(&self, key: &str) -> Result<Vec<String>> {
+        let mut dependents = Vec::new();
+        for other in self.list_keys()? {
+            let content = match self.get(&other, VersionSelector::Latest) {
+                Ok(content) => content,
+                // Locked prompts cannot be read without their passphrase
+                Err(_) => continue,
+            };
+            if includes(&content).iter().any(|include| include.key == key) {
+                dependents.push(other);
+            }
+        }
+        Ok(dependents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_includes() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("preamble", "Be kind.")?;
+        vault.update("preamble", "Be kind to {{name}}.", None)?;
+        vault.tag("preamble", "stable", 1)?;
+        vault.add("legal", "{{> preamble}} No advice.")?;
+        vault.add("writer", "{{>preamble@stable}}\n{{> legal }}\nWrite.")?;
+
+        assert_eq!(
+            vault.resolve("writer", VersionSelector::Latest)?,
+            "Be kind.\nBe kind to {{name}}. No advice.\nWrite."
+        );
+        let vars = HashMap::from([("name".to_string(), "Ada".to_string())]);
+        let rendered = vault.render("legal", VersionSelector::Latest, &vars, true)?;
+        assert_eq!(rendered, "Be kind to Ada. No advice.");
+        assert_eq!(vault.variables("writer", VersionSelector::Latest)?, ["name"]);
+
+        let tree = vault.dependencies("writer", VersionSelector::Latest)?;
+        let children: Vec<(&str, &str, Option<u64>)> = tree
+            .includes
+            .iter()
+            .map(|d| (d.key.as_str(), d.selector.as_str(), d.version))
+            .collect();
+        assert_eq!(children, [("preamble", "stable", Some(1)), ("legal", "latest", Some(1))]);
+        assert_eq!(tree.includes[1].includes[0].version, Some(2));
+        assert_eq!(vault.dependents("preamble")?, ["legal", "writer"]);
+
+        // Cycles and missing prompts fail to resolve, but still show as dependencies
+        vault.update("preamble", "{{> writer}}", None)?;
+        let error = vault.resolve("writer", VersionSelector::Latest).unwrap_err();
+        assert!(format!("{:#}", error).contains("writer@1 -> legal@1 -> preamble@3 -> writer@1"));
+        let tree = vault.dependencies("writer", VersionSelector::Latest)?;
+        let preamble = &tree.includes[1].includes[0];
+        assert_eq!((preamble.version, preamble.includes[0].cycle), (Some(3), true));
+        vault.add("broken", "{{> missing@stable}}")?;
+        assert!(vault.resolve("broken", VersionSelector::Latest).is_err());
+        let tree = vault.dependencies("broken", VersionSelector::Latest)?;
+        assert_eq!(tree.includes[0].version, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+//! Transparent compression of stored content and diffs, for `promptpro compression`.
+//!
+//! Compressed blobs start with [`MAGIC`] and a codec byte, so a vault can hold blobs
+//! written with any codec, or none: reading never depends on the current setting.
+//! Blobs without the prefix are stored as they are, as older releases wrote them.
+
+use crate::encryption::is_encrypted_blob;
+use crate::storage::PromptVault;
+use anyhow::Result;
+use std::borrow::Cow;
+
+/// Vault-level codec for newly written blobs
+const COMPRESSION_KEY: &[u8] = b"meta:compression";
+
+const MAGIC: &[u8] = b"\0PPZ";
+
+/// Blobs shorter than this are not worth compressing
+const MIN_COMPRESSED_LEN: usize = 1024;
+
+const ZSTD_LEVEL: i32 = 3;
+
+/// Codec used for newly stored content and diffs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Stored as is, as vaults from older releases are
+    None,
+    #[default]
+    Zstd,
+    /// Faster than zstd, compressing less
+    Lz4,
+}
+
+impl Compression {
+    fn codec_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Zstd => 1,
+            Compression::Lz4 => 2,
+        }
+    }
+
+    fn from_codec_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Zstd),
+            2 => Ok(Compression::Lz4),
+            other => Err(anyhow::anyhow!("Unknown compression codec {}", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Compression::None => "none",
+            Compression::Zstd => "zstd",
+            Compression::Lz4 => "lz4",
+        })
+    }
+}
+
+impl std::str::FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Compression::None),
+            "zstd" => Ok(Compression::Zstd),
+            "lz4" => Ok(Compression::Lz4),
+            other => Err(anyhow::anyhow!(
+                "Unknown compression '{}', expected 'zstd', 'lz4' or 'none'",
+                other
+            )),
+        }
+    }
+}
+
+/// Encode a blob for storage with `compression`. Small blobs, and blobs that do not
+/// shrink, are stored as they are.
+pub(crate) fn compress(data: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    let compressed = match compression {
+        _ if data.len() < MIN_COMPRESSED_LEN => None,
+        Compression::None => None,
+        Compression::Zstd => Some(zstd::bulk::compress(data, ZSTD_LEVEL)?),
+        Compression::Lz4 => Some(lz4_flex::compress_prepend_size(data)),
+    };
+
+    match compressed {
+        Some(compressed) if compressed.len() + MAGIC.len() + 1 < data.len() => {
+            let mut blob = Vec::with_capacity(MAGIC.len() + 1 + compressed.len());
+            blob.extend_from_slice(MAGIC);
+            blob.push(compression.codec_byte());
+            blob.extend_from_slice(&compressed);
+            Ok(blob)
+        }
+        // Uncompressed data that happens to start like a compressed or encrypted blob
+        // is framed too
+        _ if data.starts_with(MAGIC) || is_encrypted_blob(data) => {
+            let mut blob = MAGIC.to_vec();
+            blob.push(Compression::None.codec_byte());
+            blob.extend_from_slice(data);
+            Ok(blob)
+        }
+        _ => Ok(data.to_vec()),
+    }
+}
+
+/// Decode a stored blob, whichever codec wrote it
+pub(crate) fn decompress(blob: &[u8]) -> Result<Cow<'_, [u8]>> {
+    let Some(framed) = blob.strip_prefix(MAGIC) else {
+        return Ok(Cow::Borrowed(blob));
+    };
+    let (&codec, data) = framed
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("Truncated compressed blob"))?;
+
+    match Compression::from_codec_byte(codec)? {
+        Compression::None => Ok(Cow::Borrowed(data)),
+        Compression::Zstd => Ok(Cow::Owned(zstd::stream::decode_all(data)?)),
+        Compression::Lz4 => Ok(Cow::Owned(
+            lz4_flex::decompress_size_prepended(data)
+                .map_err(|e| anyhow::anyhow!("Corrupted lz4 blob: {}", e))?,
+        )),
+    }
+}
+
+/// What [`PromptVault::recompress`] did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecompressReport {
+    /// Blobs rewritten with the current codec
+    pub blobs: usize,
+    /// Stored bytes of the blobs before and after
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+impl PromptVault {
+    /// Codec for newly stored content and diffs. Vaults from older releases, which
+    /// have no setting, store them uncompressed.
+    pub fn compression(&self) -> Result<Compression> {
+        match self.db().get(COMPRESSION_KEY)? {
+            Some(value) => std::str::from_utf8(&value)?.parse(),
+            None => Ok(Compression::None),
+        }
+    }
+
+    /// Set the codec for newly stored content and diffs; [`PromptVault::recompress`]
+    /// rewrites what is already stored
+    pub fn set_compression(&self, compression: Compression) -> Result<()> {
+        self.db()
+            .insert(COMPRESSION_KEY, compression.to_string().as_bytes())?;
+        Ok(())
+    }
+
+    /// Rewrite every stored content and diff with the current codec, e.g. after
+    /// [`PromptVault::set_compression`] on a vault written by an older release.
+    /// Encrypted content of password-protected prompts is left as is.
+    pub fn recompress(&self) -> Result<RecompressReport> {
+        let compression = self.compression()?;
+        self.rewrite_blobs(|entry, blob| {
+            let compressed = self.decrypt_blob(entry, blob)?;
+            let rewritten = compress(&decompress(&compressed)?, compression)?;
+            if rewritten[..] == compressed[..] {
+                return Ok(None);
+            }
+            self.encrypt_blob(entry, rewritten).map(Some)
+        })
+    }
+
+    /// Replace every stored content and diff for which `rewrite` returns a new blob,
+    /// skipping the encrypted content of password-protected prompts
+    pub(crate) fn rewrite_blobs(
+        &self,
+        mut rewrite: impl FnMut(&[u8], &[u8]) -> Result<Option<Vec<u8>>>,
+    ) -> Result<RecompressReport> {
+        let mut report = RecompressReport::default();
+
+        for prefix in ["object:", "content:", "diff:"] {
+            for entry in self.db().scan_prefix(prefix.as_bytes()) {
+                let (entry_key, blob) = entry?;
+                if prefix == "content:" && self.is_sensitive(prompt_key(&entry_key, prefix))? {
+                    continue;
+                }
+                let Some(rewritten) = rewrite(&entry_key, &blob)? else {
+                    continue;
+                };
+                report.blobs += 1;
+                report.bytes_before += blob.len() as u64;
+                report.bytes_after += rewritten.len() as u64;
+                // Only replace the blob if it was not rewritten in the meantime
+                let _ = self
+                    .db()
+                    .compare_and_swap(&entry_key, Some(&blob), Some(rewritten))?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Encode a blob with the vault's codec
+    pub(crate) fn compress_blob(&self, data: &[u8]) -> Result<Vec<u8>> {
+        compress(data, self.compression()?)
+    }
+}
+
+/// `{key}` of a `{prefix}{key}:{version}` entry
+fn prompt_key<'a>(entry_key: &'a [u8], prefix: &str) -> &'a str {
+    let entry_key = std::str::from_utf8(entry_key).unwrap_or_default();
+    let rest = entry_key.strip_prefix(prefix).unwrap_or(entry_key);
+    rest.rsplit_once(':').map_or(rest, |(key, _)| key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_compression() -> Result<()> {
+        let few_shot = "Q: what is 2 + 2?\nA: 4\n".repeat(200);
+        for compression in [Compression::None, Compression::Zstd, Compression::Lz4] {
+            let blob = compress(few_shot.as_bytes(), compression)?;
+            assert_eq!(blob.len() < few_shot.len() / 4, compression != Compression::None);
+            assert_eq!(&decompress(&blob)?[..], few_shot.as_bytes());
+        }
+        // Short content is stored as is; content that looks framed round-trips too
+        assert_eq!(compress(b"short", Compression::Zstd)?, b"short");
+        assert_eq!(&decompress(&compress(b"\0PPZ\x01x", Compression::None)?)?[..], b"\0PPZ\x01x");
+
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        assert_eq!(vault.compression()?, Compression::Zstd);
+
+        // A vault from an older release: no setting, uncompressed blobs
+        vault.set_compression(Compression::None)?;
+        vault.add("few-shot", &few_shot)?;
+        let rewritten = few_shot.replace("2 + 2", "3 + 3");
+        vault.update("few-shot", &rewritten, None)?;
+        let stored = vault.stats()?.bytes;
+
+        vault.set_compression(Compression::Zstd)?;
+        let report = vault.recompress()?;
+        assert_eq!(report.blobs, 2);
+        assert!(report.bytes_after < report.bytes_before / 4);
+        assert!(vault.stats()?.bytes < stored / 4);
+        assert_eq!(vault.get("few-shot", VersionSelector::Version(1))?, few_shot);
+        assert_eq!(vault.get("few-shot", VersionSelector::Latest)?, rewritten);
+        assert_eq!(vault.recompress()?.blobs, 0);
+
+        Ok(())
+    }
+}
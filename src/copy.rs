@@ -0,0 +1,127 @@
+//! Copying a prompt to a new key of the same vault, for `promptpro cp`.
+
+use crate::storage::PromptVault;
+use crate::types::{VersionMeta, VersionSelector};
+use anyhow::Result;
+
+/// What [`PromptVault::copy_key`] copies besides the latest content
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CopyOptions {
+    /// Copy every version with its timestamp, message and tags, instead of only the
+    /// latest content as version 1
+    pub with_history: bool,
+    /// Copy the description, author, model, temperature and labels
+    pub with_metadata: bool,
+}
+
+impl PromptVault {
+    /// Copy the prompt `src` to the new key `dst`, e.g. to fork an agent prompt as the
+    /// starting point for another one. Returns the latest version of the copy.
+    pub fn copy_key(&self, src: &str, dst: &str, options: &CopyOptions) -> Result<u64> {
+        if self.get_latest_version_number(dst)?.is_some() {
+            return Err(anyhow::anyhow!("Prompt with key '{}' already exists", dst));
+        }
+        if self.is_sensitive(src)? {
+            return Err(anyhow::anyhow!(
+                "Prompt '{}' is password-protected; unprotect it before copying",
+                src
+            ));
+        }
+        let latest = self
+            .get_latest_version_number(src)?
+            .ok_or_else(|| self.unknown_key(src))?;
+
+        let version = if options.with_history {
+            self.copy_versions(self, src, dst)?;
+            latest
+        } else {
+            let content = self.get(src, VersionSelector::Version(latest))?;
+            let message = format!("Copied from {} v{}", src, latest);
+            let mut meta = VersionMeta::new(dst.to_string(), 1, &content, None, Some(message));
+            meta.author = self.author().map(str::to_string);
+            self.store_version_after(&mut meta, &content, None)?;
+            1
+        };
+
+        if options.with_metadata {
+            let meta = self.prompt_meta(src)?;
+            if !meta.is_empty() {
+                self.set_prompt_meta(dst, &meta)?;
+            }
+        }
+        Ok(version)
+    }
+
+    /// Copy every version of `key` in `source` to `target_key` in this vault, with
+    /// timestamps, messages and tags
+    pub(crate) fn copy_versions(
+        &self,
+        source: &PromptVault,
+        key: &str,
+        target_key: &str,
+    ) -> Result<()> {
+        let history = source.history(key)?;
+        let mut parent_content: Option<String> = None;
+        for meta in &history {
+            let content = source.get(key, VersionSelector::Version(meta.version))?;
+            let mut copy = meta.clone();
+            copy.key = target_key.to_string();
+            copy.tags.clear();
+            self.store_version_after(&mut copy, &content, parent_content.as_deref())?;
+            parent_content = Some(content);
+        }
+        for meta in &history {
+            for tag in &meta.tags {
+                self.tag(target_key, tag, meta.version)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::PromptMeta;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_copy_key() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("agent/planner", "Plan the task.")?;
+        vault.update("agent/planner", "Plan the task step by step.", Some("steps".into()))?;
+        vault.tag("agent/planner", "prod", 1)?;
+        let description = PromptMeta {
+            description: Some("Plans tasks".to_string()),
+            ..PromptMeta::default()
+        };
+        vault.set_prompt_meta("agent/planner", &description)?;
+
+        // Latest content only, as version 1
+        let latest_only = CopyOptions::default();
+        assert_eq!(vault.copy_key("agent/planner", "agent/researcher", &latest_only)?, 1);
+        let history = vault.history("agent/researcher")?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].message.as_deref(), Some("Copied from agent/planner v2"));
+        assert!(vault.prompt_meta("agent/researcher")?.is_empty());
+        assert_eq!(vault.list_keys()?, ["agent/planner", "agent/researcher"]);
+
+        // Full history with tags and metadata
+        let options = CopyOptions {
+            with_history: true,
+            with_metadata: true,
+        };
+        assert_eq!(vault.copy_key("agent/planner", "agent/critic", &options)?, 2);
+        let history = vault.history("agent/critic")?;
+        assert_eq!(history[1].message.as_deref(), Some("steps"));
+        assert_eq!(vault.get("agent/critic", VersionSelector::tag("prod"))?, "Plan the task.");
+        assert_eq!(vault.prompt_meta("agent/critic")?, description);
+        assert!(vault.verify(false)?.is_clean());
+
+        assert!(vault.copy_key("agent/planner", "agent/critic", &options).is_err());
+        assert!(vault.copy_key("agent/missing", "agent/other", &options).is_err());
+
+        Ok(())
+    }
+}
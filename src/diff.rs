@@ -0,0 +1,248 @@
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use similar::{ChangeTag, DiffTag, TextDiff};
+
+/// Lines of unchanged context shown around each hunk
+const CONTEXT_LINES: usize = 3;
+
+/// Kind of change a diff line represents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// A single line of a diff hunk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffChange {
+    pub kind: ChangeKind,
+    /// Line content without its trailing newline
+    pub text: String,
+    /// Whether the line is the last line and has no trailing newline
+    pub missing_newline: bool,
+}
+
+/// A hunk of changed lines with surrounding context (line numbers are 1-based)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub changes: Vec<DiffChange>,
+}
+
+impl DiffHunk {
+    /// The `@@ -a,b +c,d @@` header of the hunk
+    pub fn header(&self) -> String {
+        format!(
+            "@@ -{} +{} @@",
+            hunk_range(self.old_start, self.old_len),
+            hunk_range(self.new_start, self.new_len)
+        )
+    }
+}
+
+/// Differences between two versions of a prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptDiff {
+    pub key: String,
+    pub old_version: u64,
+    pub new_version: u64,
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl PromptDiff {
+    pub fn is_empty(&self) -> bool {
+        self.hunks.is_empty()
+    }
+
+    /// Render as a plain unified diff
+    pub fn to_unified(&self) -> String {
+        let mut out = format!(
+            "--- {}@{}\n+++ {}@{}\n",
+            self.key, self.old_version, self.key, self.new_version
+        );
+        for hunk in &self.hunks {
+            out.push_str(&hunk.header());
+            out.push('\n');
+            for change in &hunk.changes {
+                let sign = match change.kind {
+                    ChangeKind::Equal => ' ',
+                    ChangeKind::Delete => '-',
+                    ChangeKind::Insert => '+',
+                };
+                out.push(sign);
+                out.push_str(&change.text);
+                out.push('\n');
+                if change.missing_newline {
+                    out.push_str("\\ No newline at end of file\n");
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Format a hunk range the way unified diffs do: `start` alone for one line,
+/// and an empty range starting at the line before it
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        1 => start.to_string(),
+        0 => format!("{},0", start.saturating_sub(1)),
+        _ => format!("{},{}", start, len),
+    }
+}
+
+/// Hunks of the lines changed from `old` to `new`
+pub(crate) fn line_hunks(old: &str, new: &str) -> Vec<DiffHunk> {
+    let text_diff = TextDiff::configure()
+        .newline_terminated(true)
+        .diff_lines(old, new);
+
+    text_diff
+        .grouped_ops(CONTEXT_LINES)
+        .iter()
+        .filter(|ops| !ops.is_empty())
+        .map(|ops| {
+            let (first, last) = (&ops[0], &ops[ops.len() - 1]);
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+
+            let changes = ops
+                .iter()
+                .flat_map(|op| text_diff.iter_changes(op))
+                .map(|change| DiffChange {
+                    kind: match change.tag() {
+                        ChangeTag::Equal => ChangeKind::Equal,
+                        ChangeTag::Delete => ChangeKind::Delete,
+                        ChangeTag::Insert => ChangeKind::Insert,
+                    },
+                    text: change.value().trim_end_matches('\n').to_string(),
+                    missing_newline: change.missing_newline(),
+                })
+                .collect();
+
+            DiffHunk {
+                old_start: old_range.start + 1,
+                old_len: old_range.len(),
+                new_start: new_range.start + 1,
+                new_len: new_range.len(),
+                changes,
+            }
+        })
+        .collect()
+}
+
+/// How a row of a side-by-side diff changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowKind {
+    Equal,
+    /// The old line was replaced by the new one
+    Changed,
+    Delete,
+    Insert,
+}
+
+/// A line of the old text next to the line of the new text it corresponds to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SideBySideRow {
+    pub kind: RowKind,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Every line of `old` and `new` in rows, pairing replaced lines with their replacements
+pub fn side_by_side(old: &str, new: &str) -> Vec<SideBySideRow> {
+    let text_diff = TextDiff::configure()
+        .newline_terminated(true)
+        .diff_lines(old, new);
+    let line = |lines: &[&str], index: usize| {
+        lines.get(index).map(|line| line.trim_end_matches('\n').to_string())
+    };
+    let (old_lines, new_lines) = (text_diff.old_slices(), text_diff.new_slices());
+
+    let mut rows = Vec::new();
+    for op in text_diff.ops() {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        for offset in 0..old_range.len().max(new_range.len()) {
+            let old = (offset < old_range.len()).then(|| line(old_lines, old_range.start + offset));
+            let new = (offset < new_range.len()).then(|| line(new_lines, new_range.start + offset));
+            let (old, new) = (old.flatten(), new.flatten());
+            let kind = match (tag, &old, &new) {
+                (DiffTag::Equal, _, _) => RowKind::Equal,
+                (_, Some(_), Some(_)) => RowKind::Changed,
+                (_, Some(_), None) => RowKind::Delete,
+                _ => RowKind::Insert,
+            };
+            rows.push(SideBySideRow { kind, old, new });
+        }
+    }
+    rows
+}
+
+impl PromptVault {
+    /// Diff two versions of a prompt, e.g. `Tag("stable")` against `Latest`
+    pub fn diff(
+        &self,
+        key: &str,
+        old: VersionSelector,
+        new: VersionSelector,
+    ) -> Result<PromptDiff> {
+        let old_version = self.resolve_version(key, &old)?;
+        let new_version = self.resolve_version(key, &new)?;
+        let old_content = self.get(key, VersionSelector::Version(old_version))?;
+        let new_content = self.get(key, VersionSelector::Version(new_version))?;
+
+        Ok(PromptDiff {
+            key: key.to_string(),
+            old_version,
+            new_version,
+            hunks: line_hunks(&old_content, &new_content),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_diff_versions() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+
+        vault.add("greet", "Hello\nBe polite\nBye\n")?;
+        vault.update("greet", "Hello\nBe concise\nBye\n", None)?;
+        vault.tag("greet", "stable", 1)?;
+
+        let diff = vault.diff("greet", VersionSelector::tag("stable"), VersionSelector::Latest)?;
+        assert_eq!((diff.old_version, diff.new_version), (1, 2));
+        assert_eq!(diff.hunks.len(), 1);
+
+        let hunk = &diff.hunks[0];
+        assert_eq!(hunk.header(), "@@ -1,3 +1,3 @@");
+        let kinds: Vec<_> = hunk.changes.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ChangeKind::Equal,
+                ChangeKind::Delete,
+                ChangeKind::Insert,
+                ChangeKind::Equal
+            ]
+        );
+        assert_eq!(hunk.changes[2].text, "Be concise");
+        assert!(diff.to_unified().contains("-Be polite\n+Be concise\n"));
+
+        // Identical versions have no hunks
+        assert!(vault
+            .diff("greet", VersionSelector::Version(2), VersionSelector::Latest)?
+            .is_empty());
+
+        Ok(())
+    }
+}
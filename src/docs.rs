@@ -0,0 +1,206 @@
+//! Documentation kept in the vault: a vault-level README and notes per key, served at
+//! `/docs` by `promptpro serve` so the vault doubles as a prompt catalog.
+
+use crate::storage::PromptVault;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Vault-level description document
+const README_KEY: &[u8] = b"meta:readme";
+
+fn notes_key(key: &str) -> String {
+    format!("notes:{}", key)
+}
+
+/// The catalog served at `/docs`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VaultDocs {
+    pub readme: Option<String>,
+    pub prompts: Vec<KeyDocs>,
+}
+
+/// What the catalog says about one prompt
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KeyDocs {
+    pub key: String,
+    /// Description from the prompt metadata
+    pub description: Option<String>,
+    pub notes: Option<String>,
+    pub latest_version: u64,
+    /// Tags, with the versions they point at
+    pub tags: Vec<(String, u64)>,
+}
+
+impl PromptVault {
+    /// The vault-level README, if one was set
+    pub fn readme(&self) -> Result<Option<String>> {
+        match self.db().get(README_KEY)? {
+            Some(bytes) => Ok(Some(String::from_utf8(bytes.to_vec())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Set the vault-level README; `None` removes it
+    pub fn set_readme(&self, readme: Option<&str>) -> Result<()> {
+        match readme {
+            Some(readme) => self.db().insert(README_KEY, readme.as_bytes())?,
+            None => self.db().remove(README_KEY)?,
+        };
+        Ok(())
+    }
+
+    /// Notes on a prompt, if any were written
+    pub fn notes(&self, key: &str) -> Result<Option<String>> {
+        match self.db().get(notes_key(key).as_bytes())? {
+            Some(bytes) => Ok(Some(String::from_utf8(bytes.to_vec())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Set the notes of an existing prompt; `None` removes them
+    pub fn set_notes(&self, key: &str, notes: Option<&str>) -> Result<()> {
+        match notes {
+            Some(notes) => {
+                if self.get_latest_version_number(key)?.is_none() {
+                    return Err(self.unknown_key(key));
+                }
+                self.db().insert(notes_key(key).as_bytes(), notes.as_bytes())?;
+            }
+            None => {
+                self.remove_entry(notes_key(key).as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The README and, for every prompt, its description, notes and tags
+    pub fn docs(&self) -> Result<VaultDocs> {
+        let mut prompts = Vec::new();
+        for key in self.list_keys()? {
+            let history = self.history(&key)?;
+            let Some(latest) = history.last() else {
+                continue;
+            };
+            let tags = history
+                .iter()
+                .flat_map(|meta| meta.tags.iter().map(|tag| (tag.clone(), meta.version)))
+                .collect();
+            prompts.push(KeyDocs {
+                description: self.prompt_meta(&key)?.description,
+                notes: self.notes(&key)?,
+                latest_version: latest.version,
+                tags,
+                key,
+            });
+        }
+        Ok(VaultDocs {
+            readme: self.readme()?,
+            prompts,
+        })
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl VaultDocs {
+    /// A standalone HTML page: the README, then a section per prompt. Text is shown as
+    /// written, with its line breaks.
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Prompt catalog</title>\n<style>\n\
+             body { font-family: sans-serif; max-width: 60em; margin: 2em auto; }\n\
+             .text { white-space: pre-wrap; }\n\
+             .tag { background: #eee; border-radius: 3px; padding: 0 4px; }\n\
+             </style>\n</head>\n<body>\n<h1>Prompt catalog</h1>\n",
+        );
+        if let Some(readme) = &self.readme {
+            html.push_str(&format!("<div class=\"text\">{}</div>\n", escape_html(readme)));
+        }
+
+        html.push_str("<h2>Prompts</h2>\n<ul>\n");
+        for prompt in &self.prompts {
+            let key = escape_html(&prompt.key);
+            html.push_str(&format!("<li><a href=\"#{0}\">{0}</a></li>\n", key));
+        }
+        html.push_str("</ul>\n");
+
+        for prompt in &self.prompts {
+            let key = escape_html(&prompt.key);
+            html.push_str(&format!(
+                "<h3 id=\"{0}\">{0} <small>v{1}</small></h3>\n",
+                key, prompt.latest_version
+            ));
+            if !prompt.tags.is_empty() {
+                let tags: Vec<String> = prompt
+                    .tags
+                    .iter()
+                    .map(|(tag, version)| {
+                        format!("<span class=\"tag\">{}: v{}</span>", escape_html(tag), version)
+                    })
+                    .collect();
+                html.push_str(&format!("<p>{}</p>\n", tags.join(" ")));
+            }
+            if let Some(description) = &prompt.description {
+                html.push_str(&format!("<p><em>{}</em></p>\n", escape_html(description)));
+            }
+            if let Some(notes) = &prompt.notes {
+                html.push_str(&format!("<div class=\"text\">{}</div>\n", escape_html(notes)));
+            }
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::PromptMeta;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_vault_docs() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("team/writer", "You write.")?;
+        vault.add("team/reviewer", "You review.")?;
+        vault.tag("team/writer", "stable", 1)?;
+        vault.update_prompt_meta(
+            "team/writer",
+            PromptMeta {
+                description: Some("Drafts blog posts".to_string()),
+                ..PromptMeta::default()
+            },
+        )?;
+
+        assert!(vault.set_notes("missing", Some("Nothing here")).is_err());
+        vault.set_readme(Some("# Team prompts\nAsk #prompts before changing <prod>."))?;
+        vault.set_notes("team/writer", Some("Keep posts under 800 words."))?;
+
+        let docs = vault.docs()?;
+        assert_eq!(docs.prompts.len(), 2);
+        let writer = &docs.prompts[1];
+        assert_eq!(writer.key, "team/writer");
+        assert_eq!(writer.description.as_deref(), Some("Drafts blog posts"));
+        assert_eq!(writer.notes.as_deref(), Some("Keep posts under 800 words."));
+        assert!(writer.tags.contains(&("stable".to_string(), 1)));
+
+        let html = docs.to_html();
+        assert!(html.contains("changing &lt;prod&gt;."));
+        assert!(html.contains("<h3 id=\"team/writer\">"));
+
+        vault.delete_prompt_key("team/writer")?;
+        assert_eq!(vault.notes("team/writer")?, None);
+        vault.set_readme(None)?;
+        assert_eq!(vault.readme()?, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,922 @@
+//! File format of vault dumps (`promptpro dump` / `promptpro resume`).
+//!
+//! Dumps start with `PPRODUMP`, the format version (u32 LE), the length of a JSON
+//! header (u32 LE) and the header itself. That prefix stays the same in later formats,
+//! so any release can tell that a dump is newer than it reads. In format 2 the vault
+//! entries follow as chunks, each a length (u32 LE) and a bincode list of up to
+//! [`CHUNK_ENTRIES`] entries, encrypted on its own when the header says so; a zero
+//! length ends the dump. Chunks are encoded and decoded in parallel, a batch at a time,
+//! so neither side holds the whole vault in memory, and a restore records each chunk it
+//! inserts so that it can resume at a chunk boundary after an interruption.
+//!
+//! Encrypted dumps use a key derived with Argon2id, whose parameters and salt are in
+//! the header. The header carries a keyed hash of the fields a restore depends on,
+//! each encrypted chunk starts with a cipher version byte and is bound to its index,
+//! and a trailer after the end marker holds the number of chunks, so a tampered,
+//! reordered or truncated dump fails with a clear error.
+//!
+//! Format 1 files, written by older releases, start with `VAULT_RAW` or `VAULT_ENC`,
+//! have no header and hold one bincode list of all entries, encrypted with a key
+//! hashed from the password with blake3.
+
+use crate::dump_merge::{ConflictPolicy, MergeReport};
+use crate::encryption::KdfParams;
+use crate::storage::PromptVault;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io::{ErrorKind, Read, Write};
+use std::path::Path;
+
+/// Dump format written by this release
+pub const DUMP_FORMAT_VERSION: u32 = 2;
+
+/// Entries per chunk of a dump
+pub(crate) const CHUNK_ENTRIES: usize = 4096;
+
+/// Chunks encoded or decoded at once; bounds the memory a dump or restore needs
+const CHUNKS_PER_BATCH: usize = 32;
+
+/// A vault entry: a sled key and its value
+pub(crate) type Entry = (Vec<u8>, Vec<u8>);
+
+/// Progress of an interrupted restore (see [`PromptVault::restore_interrupted`])
+const RESTORE_PROGRESS_KEY: &[u8] = b"meta:restore_progress";
+
+const DUMP_MAGIC: &[u8] = b"PPRODUMP";
+const LEGACY_RAW_MAGIC: &[u8] = b"VAULT_RAW";
+const LEGACY_ENC_MAGIC: &[u8] = b"VAULT_ENC";
+
+/// Version byte of the encrypted chunks of a dump: AES-256-GCM
+const CHUNK_CIPHER: u8 = 1;
+
+const NONCE_LEN: usize = 12;
+
+/// Index the trailer of an encrypted dump is bound to, after every chunk
+const TRAILER_INDEX: u64 = u64::MAX;
+
+/// Key derivation of an encrypted dump
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DumpKdf {
+    /// Always `argon2id`
+    pub algorithm: String,
+    /// Hex-encoded random salt
+    pub salt: String,
+    pub params: KdfParams,
+}
+
+impl DumpKdf {
+    fn new(params: KdfParams) -> Self {
+        DumpKdf {
+            algorithm: "argon2id".to_string(),
+            salt: hex::encode(rand::random::<[u8; 16]>()),
+            params,
+        }
+    }
+}
+
+/// What a dump file says about itself
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct DumpHeader {
+    /// Format version; 1 for dumps written by older releases
+    #[serde(skip)]
+    pub format: u32,
+    pub encrypted: bool,
+    /// Random identifier of the dump, which an interrupted restore resumes by
+    pub id: Option<String>,
+    /// Release that wrote the dump; unknown for format 1
+    pub written_by: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Number of vault entries; unknown for format 1
+    pub entries: Option<usize>,
+    /// Key derivation of an encrypted dump; format 2 and later
+    pub kdf: Option<DumpKdf>,
+    /// Keyed hash authenticating the header of an encrypted dump; format 2 and later
+    pub mac: Option<String>,
+}
+
+impl DumpHeader {
+    /// Header of a dump written now by this release
+    pub(crate) fn current(encrypted: bool, entries: usize) -> Self {
+        DumpHeader {
+            format: DUMP_FORMAT_VERSION,
+            encrypted,
+            id: Some(hex::encode(rand::random::<[u8; 16]>())),
+            written_by: Some(env!("CARGO_PKG_VERSION").to_string()),
+            created_at: Some(Utc::now()),
+            entries: Some(entries),
+            kdf: None,
+            mac: None,
+        }
+    }
+
+    /// Whether this release can restore the dump
+    pub fn is_supported(&self) -> bool {
+        self.format <= DUMP_FORMAT_VERSION
+    }
+
+    /// The prefix and header a dump starts with
+    pub(crate) fn encode(&self) -> Result<Vec<u8>> {
+        let header = serde_json::to_vec(self)?;
+        let mut data = Vec::with_capacity(DUMP_MAGIC.len() + 8 + header.len());
+        data.extend_from_slice(DUMP_MAGIC);
+        data.extend_from_slice(&self.format.to_le_bytes());
+        data.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        data.extend_from_slice(&header);
+        Ok(data)
+    }
+
+    /// Read the header at the start of a dump, leaving `input` at the entries
+    pub(crate) fn read_from<R: Read>(input: &mut R) -> Result<DumpHeader> {
+        let mut magic = vec![0u8; DUMP_MAGIC.len()];
+        read_header_bytes(input, &mut magic)?;
+        if magic != DUMP_MAGIC {
+            // The legacy magics are one byte longer
+            let mut last = [0u8; 1];
+            read_header_bytes(input, &mut last)?;
+            magic.push(last[0]);
+            for (legacy, encrypted) in [(LEGACY_RAW_MAGIC, false), (LEGACY_ENC_MAGIC, true)] {
+                if magic == legacy {
+                    return Ok(DumpHeader {
+                        format: 1,
+                        encrypted,
+                        ..Default::default()
+                    });
+                }
+            }
+            return Err(anyhow::anyhow!(
+                "Not a promptpro dump: the file starts with {:?}",
+                String::from_utf8_lossy(&magic)
+            ));
+        }
+
+        let mut word = [0u8; 4];
+        read_header_bytes(input, &mut word)?;
+        let format = u32::from_le_bytes(word);
+        read_header_bytes(input, &mut word)?;
+        let mut header = vec![0u8; u32::from_le_bytes(word) as usize];
+        read_header_bytes(input, &mut header)?;
+
+        let mut header: DumpHeader = serde_json::from_slice(&header).map_err(|e| {
+            anyhow::anyhow!("Invalid dump: unreadable format v{} header: {}", format, e)
+        })?;
+        header.format = format;
+        Ok(header)
+    }
+
+    /// Fail with a request to upgrade when this release cannot restore the dump
+    pub(crate) fn check_supported(&self) -> Result<()> {
+        if self.is_supported() {
+            return Ok(());
+        }
+        Err(anyhow::anyhow!(
+            "The dump was written in a newer format by a later release of promptpro; \
+             upgrade promptpro to restore it"
+        ))
+    }
+}
+
+fn read_header_bytes<R: Read>(input: &mut R, buf: &mut [u8]) -> Result<()> {
+    input.read_exact(buf).map_err(|e| match e.kind() {
+        ErrorKind::UnexpectedEof => anyhow::anyhow!("Invalid dump: the header is truncated"),
+        _ => e.into(),
+    })
+}
+
+fn write_chunk<W: Write>(out: &mut W, chunk: &[u8]) -> Result<()> {
+    out.write_all(&(chunk.len() as u32).to_le_bytes())?;
+    out.write_all(chunk)?;
+    Ok(())
+}
+
+/// The next chunk of a dump; `None` at the end marker
+fn read_chunk<R: Read>(input: &mut R) -> Result<Option<Vec<u8>>> {
+    let truncated = |e: std::io::Error| match e.kind() {
+        ErrorKind::UnexpectedEof => anyhow::anyhow!("Invalid dump: the file is truncated"),
+        _ => e.into(),
+    };
+    let mut length = [0u8; 4];
+    input.read_exact(&mut length).map_err(truncated)?;
+    let length = u32::from_le_bytes(length) as usize;
+    if length == 0 {
+        return Ok(None);
+    }
+    let mut chunk = vec![0u8; length];
+    input.read_exact(&mut chunk).map_err(truncated)?;
+    Ok(Some(chunk))
+}
+
+/// Encryption of a dump, keyed from its password and header
+struct DumpCipher {
+    aes: Aes256Gcm,
+    mac_key: [u8; 32],
+}
+
+impl DumpCipher {
+    fn new(password: &str, kdf: &DumpKdf) -> Result<Self> {
+        if kdf.algorithm != "argon2id" {
+            return Err(anyhow::anyhow!(
+                "Unsupported dump key derivation '{}'",
+                kdf.algorithm
+            ));
+        }
+        let salt = hex::decode(&kdf.salt)
+            .map_err(|_| anyhow::anyhow!("Invalid dump: malformed key derivation salt"))?;
+        let mut keys = [0u8; 64];
+        kdf.params.derive(password, &salt, &mut keys)?;
+        let (aes_key, mac_key) = keys.split_at(32);
+        Ok(DumpCipher {
+            aes: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(aes_key)),
+            mac_key: mac_key.try_into()?,
+        })
+    }
+
+    /// Keyed hash of the header fields a restore depends on
+    fn header_mac(&self, header: &DumpHeader) -> Result<String> {
+        let fields = bincode::serialize(&(
+            header.format,
+            header.encrypted,
+            &header.id,
+            header.entries,
+            &header.kdf,
+        ))?;
+        Ok(blake3::keyed_hash(&self.mac_key, &fields).to_hex().to_string())
+    }
+
+    /// Associated data binding a chunk to the dump and its position in it
+    fn chunk_aad(header_mac: &str, index: u64) -> Vec<u8> {
+        let mut aad = header_mac.as_bytes().to_vec();
+        aad.extend_from_slice(&index.to_le_bytes());
+        aad
+    }
+
+    fn seal(&self, header_mac: &str, index: u64, data: &[u8]) -> Result<Vec<u8>> {
+        let nonce = rand::random::<[u8; NONCE_LEN]>();
+        let aad = Self::chunk_aad(header_mac, index);
+        let ciphertext = self
+            .aes
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: data, aad: &aad })
+            .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
+
+        let mut chunk = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        chunk.push(CHUNK_CIPHER);
+        chunk.extend_from_slice(&nonce);
+        chunk.extend_from_slice(&ciphertext);
+        Ok(chunk)
+    }
+
+    fn open(&self, header_mac: &str, index: u64, chunk: &[u8]) -> Result<Vec<u8>> {
+        let sealed = match chunk.split_first() {
+            Some((&CHUNK_CIPHER, sealed)) if sealed.len() >= NONCE_LEN => sealed,
+            Some((&CHUNK_CIPHER, _)) | None => {
+                return Err(anyhow::anyhow!("Corrupted dump: a chunk is truncated"))
+            }
+            Some((&version, _)) => {
+                return Err(anyhow::anyhow!("Unsupported dump cipher version {}", version))
+            }
+        };
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let aad = Self::chunk_aad(header_mac, index);
+        self.aes
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "Corrupted dump: chunk {} failed authentication; the dump was tampered \
+                     with, reordered or damaged",
+                    index
+                )
+            })
+    }
+}
+
+/// The cipher of an encrypted dump, once its header is authenticated
+fn header_cipher(header: &DumpHeader, password: &str) -> Result<(DumpCipher, String)> {
+    let kdf = header
+        .kdf
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Invalid dump: no key derivation in the header"))?;
+    let cipher = DumpCipher::new(password, kdf)?;
+    let mac = cipher.header_mac(header)?;
+    if header.mac.as_deref() != Some(mac.as_str()) {
+        return Err(anyhow::anyhow!(
+            "Wrong password, or the dump header was tampered with"
+        ));
+    }
+    Ok((cipher, mac))
+}
+
+/// Read the entries of a dump whose header was just read from `input`, handing each
+/// chunk (with its index) to `insert` in order, after skipping the first `skip` chunks.
+/// Returns the number of entries handed over.
+///
+/// Chunks that were read completely are inserted before a truncated or corrupted
+/// chunk fails the read, so a restore gets as far as the dump allows.
+fn read_entries<R, F>(
+    mut input: R,
+    header: &DumpHeader,
+    password: Option<&str>,
+    skip: usize,
+    mut insert: F,
+) -> Result<usize>
+where
+    R: Read,
+    F: FnMut(usize, Vec<Entry>) -> Result<()>,
+{
+    let password = match (header.encrypted, password) {
+        (true, None) => return Err(anyhow::anyhow!("Vault encrypted but no password provided")),
+        (true, password) => password,
+        (false, _) => None,
+    };
+    let corrupted = || anyhow::anyhow!("Corrupted dump: the entries could not be decoded");
+
+    if header.format == 1 {
+        let mut payload = Vec::new();
+        input.read_to_end(&mut payload)?;
+        if let Some(password) = password {
+            payload = PromptVault::decrypt_data(&payload, password)?;
+        }
+        let entries: Vec<Entry> = bincode::deserialize(&payload).map_err(|_| corrupted())?;
+        let count = entries.len();
+        insert(0, entries)?;
+        return Ok(count);
+    }
+
+    let cipher = match password {
+        Some(password) => Some(header_cipher(header, password)?),
+        None => None,
+    };
+    let decode = |index: usize, data: &[u8]| -> Result<Vec<Entry>> {
+        let raw = match &cipher {
+            Some((cipher, mac)) => Cow::Owned(cipher.open(mac, index as u64, data)?),
+            None => Cow::Borrowed(data),
+        };
+        bincode::deserialize(&raw).map_err(|_| corrupted())
+    };
+    // Dumps end with the number of chunks, so chunks cut off at a chunk boundary are
+    // noticed too
+    let finish = |input: &mut R, chunks: usize| -> Result<()> {
+        let trailer = read_chunk(input)?
+            .ok_or_else(|| anyhow::anyhow!("Invalid dump: the file is truncated"))?;
+        let trailer = match &cipher {
+            Some((cipher, mac)) => cipher.open(mac, TRAILER_INDEX, &trailer)?,
+            None => trailer,
+        };
+        let written = trailer
+            .as_slice()
+            .try_into()
+            .map(u64::from_le_bytes)
+            .map_err(|_| anyhow::anyhow!("Corrupted dump: unreadable trailer"))?;
+        if written != chunks as u64 {
+            return Err(anyhow::anyhow!(
+                "Corrupted dump: {} of {} chunks found; the dump was truncated or tampered with",
+                chunks,
+                written
+            ));
+        }
+        Ok(())
+    };
+
+    for skipped in 0..skip {
+        if read_chunk(&mut input)?.is_none() {
+            finish(&mut input, skipped)?;
+            return Ok(0);
+        }
+    }
+
+    let mut count = 0;
+    let mut index = skip;
+    loop {
+        let mut chunks = Vec::with_capacity(CHUNKS_PER_BATCH);
+        let mut end = None;
+        while chunks.len() < CHUNKS_PER_BATCH {
+            match read_chunk(&mut input) {
+                Ok(Some(chunk)) => chunks.push(chunk),
+                Ok(None) => {
+                    end = Some(Ok(()));
+                    break;
+                }
+                Err(e) => {
+                    end = Some(Err(e));
+                    break;
+                }
+            }
+        }
+
+        let decoded: Vec<Result<Vec<Entry>>> = chunks
+            .par_iter()
+            .enumerate()
+            .map(|(offset, chunk)| decode(index + offset, chunk))
+            .collect();
+        for entries in decoded {
+            let entries = entries?;
+            count += entries.len();
+            insert(index, entries)?;
+            index += 1;
+        }
+        match end {
+            Some(Ok(())) => {
+                finish(&mut input, index)?;
+                return Ok(count);
+            }
+            Some(Err(e)) => return Err(e),
+            None => {}
+        }
+    }
+}
+
+/// What [`PromptVault::restore_to`] does when the target already holds prompts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestorePolicy {
+    /// Keep the existing vault and skip the restore, as [`PromptVault::restore`] does
+    Skip,
+    /// Fail without changing anything
+    Refuse,
+    /// Replace the existing vault with the dump
+    Overwrite,
+    /// Merge the dump into the existing vault key by key, keeping the local versions of
+    /// keys both changed, as [`PromptVault::merge_dump`] does
+    Merge,
+}
+
+/// Where an overwriting restore is written before it replaces the target
+fn staging_path(target: &Path) -> std::path::PathBuf {
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".restoring");
+    target.with_file_name(name)
+}
+
+/// How far an interrupted restore got, kept in the vault being restored
+#[derive(Serialize, Deserialize)]
+struct RestoreProgress {
+    dump_id: String,
+    /// Chunks inserted so far
+    chunks: usize,
+}
+
+impl PromptVault {
+    /// Read the header of a dump file without restoring it; works for dumps written by
+    /// newer releases too
+    pub fn inspect_dump(input_path: &str) -> Result<DumpHeader> {
+        let mut input = std::io::BufReader::new(std::fs::File::open(input_path)?);
+        DumpHeader::read_from(&mut input)
+    }
+
+    /// Restore a dump into the vault at `target`, creating it if needed. What happens
+    /// when the target already holds prompts depends on `policy`; an interrupted restore
+    /// into the target is resumed whatever the policy.
+    pub fn restore_to<P: AsRef<Path>>(
+        input_path: &str,
+        target: P,
+        password: Option<&str>,
+        policy: RestorePolicy,
+    ) -> Result<Self> {
+        let target = target.as_ref();
+        if !Path::new(input_path).exists() {
+            return Err(anyhow::anyhow!("Vault file not found: {}", input_path));
+        }
+
+        let existing = if target.exists() {
+            Some(Self::open(target)?)
+        } else {
+            None
+        };
+        if let Some(vault) = &existing {
+            if !vault.restore_interrupted()? && !vault.list_keys()?.is_empty() {
+                match policy {
+                    RestorePolicy::Skip => {
+                        println!(
+                            "✅ A vault already exists at {} — skipping restore.",
+                            target.display()
+                        );
+                        return Ok(vault.clone());
+                    }
+                    RestorePolicy::Refuse => {
+                        return Err(anyhow::anyhow!(
+                            "{} already holds a vault; overwrite or merge it instead",
+                            target.display()
+                        ))
+                    }
+                    RestorePolicy::Merge => {
+                        vault.merge_dump(input_path, password)?;
+                        return Ok(vault.clone());
+                    }
+                    RestorePolicy::Overwrite => {
+                        drop(existing);
+                        return Self::overwrite_with_dump(input_path, target, password);
+                    }
+                }
+            }
+        }
+
+        let mut input = std::io::BufReader::new(std::fs::File::open(input_path)?);
+        let header = DumpHeader::read_from(&mut input)?;
+        header.check_supported()?;
+        if header.encrypted && password.is_none() {
+            return Err(anyhow::anyhow!("Vault encrypted but no password provided"));
+        }
+
+        let created = existing.is_none();
+        let vault = match existing {
+            Some(vault) if vault.restore_interrupted()? => {
+                println!("🔄 Resuming the interrupted restore into {}", target.display());
+                vault
+            }
+            existing => {
+                let vault = match existing {
+                    Some(vault) => vault,
+                    None => {
+                        std::fs::create_dir_all(target)?;
+                        Self::open(target)?
+                    }
+                };
+                // Dumps from older versions carry no layout marker either; their
+                // entries decide
+                vault.db().remove(crate::migrate::LAYOUT_VERSION_KEY)?;
+                vault
+            }
+        };
+
+        if let Err(e) = vault.restore_entries(input, &header, password) {
+            if vault.restore_interrupted()? {
+                return Err(e.context(format!(
+                    "Restore of {} interrupted; restore the same dump again to resume",
+                    input_path
+                )));
+            }
+            if created {
+                // Nothing to resume (an older dump, or a wrong password); leave no
+                // half-restored vault behind
+                drop(vault);
+                let _ = std::fs::remove_dir_all(target);
+            }
+            return Err(e);
+        }
+        // Dumps from older versions carry no key index
+        vault.rebuild_key_index()?;
+        vault.db().flush()?;
+
+        println!("✅ Restored vault → {}", target.display());
+        Ok(vault)
+    }
+
+    /// Restore a dump next to `target`, then replace `target` with it, so a dump that
+    /// fails to restore leaves the existing vault untouched
+    fn overwrite_with_dump(
+        input_path: &str,
+        target: &Path,
+        password: Option<&str>,
+    ) -> Result<Self> {
+        let staging = staging_path(target);
+        // A staging vault left by an interrupted overwrite is resumed; any other is stale
+        if staging.exists() && !Self::open(&staging)?.restore_interrupted()? {
+            std::fs::remove_dir_all(&staging)?;
+        }
+        drop(Self::restore_to(input_path, &staging, password, RestorePolicy::Refuse)?);
+
+        std::fs::remove_dir_all(target)?;
+        std::fs::rename(&staging, target)?;
+        Self::open(target)
+    }
+
+    /// Merge a dump into this vault key by key, keeping the local versions of keys both
+    /// sides changed; see [`merge_dump_with`](Self::merge_dump_with)
+    pub fn merge_dump(&self, input_path: &str, password: Option<&str>) -> Result<MergeReport> {
+        self.merge_dump_with(input_path, password, |_| Ok(ConflictPolicy::Local))
+    }
+
+    /// Add the entries of a dump to a vault without prompts, replacing entries with the
+    /// same keys; versions of the same key would overwrite each other, so a vault
+    /// holding prompts is refused. Reference counts of shared content are added up.
+    /// Returns the number of entries read.
+    pub(crate) fn copy_dump(&self, input_path: &str, password: Option<&str>) -> Result<usize> {
+        if !self.list_keys()?.is_empty() {
+            return Err(anyhow::anyhow!(
+                "The vault already holds prompts; merge the dump into it instead"
+            ));
+        }
+        let mut input = std::io::BufReader::new(std::fs::File::open(input_path)?);
+        let header = DumpHeader::read_from(&mut input)?;
+        header.check_supported()?;
+
+        let count = read_entries(input, &header, password, 0, |_, entries| {
+            let mut batch = sled::Batch::default();
+            for (key, value) in entries {
+                let value = match key.strip_prefix(b"objref:".as_slice()) {
+                    Some(_) => {
+                        let count = |bytes: &[u8]| bytes.try_into().map_or(0, u64::from_le_bytes);
+                        let existing = self.db().get(&key)?.map_or(0, |bytes| count(&bytes));
+                        (existing + count(&value)).to_le_bytes().to_vec()
+                    }
+                    None => value,
+                };
+                if let Some(entry) = key.strip_prefix(b"version:".as_slice()) {
+                    let entry = String::from_utf8_lossy(entry);
+                    if let Some((prompt, _)) = entry.rsplit_once(':') {
+                        self.index_cache().forget(prompt);
+                    }
+                }
+                batch.insert(key, value);
+            }
+            self.db().apply_batch(batch)?;
+            Ok(())
+        })?;
+
+        self.rebuild_key_index()?;
+        self.db().flush()?;
+        Ok(count)
+    }
+
+    /// Restore a dump into a vault in memory, e.g. to compare it with this one
+    pub(crate) fn stage_dump(input_path: &str, password: Option<&str>) -> Result<Self> {
+        let mut input = std::io::BufReader::new(std::fs::File::open(input_path)?);
+        let header = DumpHeader::read_from(&mut input)?;
+        header.check_supported()?;
+
+        let staged = Self::open_in_memory()?;
+        staged.db().remove(crate::migrate::LAYOUT_VERSION_KEY)?;
+        staged.restore_entries(input, &header, password)?;
+        staged.rebuild_key_index()?;
+        Ok(staged)
+    }
+
+    /// Whether a restore into this vault was interrupted; restoring the same dump again
+    /// continues it
+    pub fn restore_interrupted(&self) -> Result<bool> {
+        Ok(self.db().contains_key(RESTORE_PROGRESS_KEY)?)
+    }
+
+    /// Insert the entries of a dump whose header was just read from `input`.
+    ///
+    /// Each chunk of a dump is inserted in one batch with a record of how far
+    /// the restore got, so after an interruption restoring the same dump again skips
+    /// the chunks already inserted. Returns the number of entries inserted.
+    pub(crate) fn restore_entries<R: Read>(
+        &self,
+        input: R,
+        header: &DumpHeader,
+        password: Option<&str>,
+    ) -> Result<usize> {
+        let progress: Option<RestoreProgress> = match self.db().get(RESTORE_PROGRESS_KEY)? {
+            Some(bytes) => Some(bincode::deserialize(&bytes)?),
+            None => None,
+        };
+        let skip = match (progress, &header.id) {
+            (None, _) => 0,
+            (Some(progress), Some(id)) if progress.dump_id == *id => progress.chunks,
+            (Some(_), _) => {
+                return Err(anyhow::anyhow!(
+                    "The vault holds an interrupted restore of another dump"
+                ))
+            }
+        };
+
+        let inserted = read_entries(input, header, password, skip, |index, entries| {
+            let mut batch = sled::Batch::default();
+            for (key, value) in entries {
+                batch.insert(key, value);
+            }
+            if let Some(id) = &header.id {
+                let progress = RestoreProgress {
+                    dump_id: id.clone(),
+                    chunks: index + 1,
+                };
+                batch.insert(RESTORE_PROGRESS_KEY, bincode::serialize(&progress)?);
+            }
+            self.db().apply_batch(batch)?;
+            Ok(())
+        })?;
+
+        self.db().remove(RESTORE_PROGRESS_KEY)?;
+        Ok(inserted)
+    }
+
+    /// Write the whole vault as a dump to `out`, encrypted with a key derived from
+    /// `password` with `kdf`
+    pub(crate) fn write_dump<W: Write>(
+        &self,
+        mut out: W,
+        password: Option<&str>,
+        kdf: KdfParams,
+    ) -> Result<()> {
+        let mut header = DumpHeader::current(password.is_some(), self.db().len());
+        let cipher = match password {
+            Some(password) => {
+                let dump_kdf = DumpKdf::new(kdf);
+                let cipher = DumpCipher::new(password, &dump_kdf)?;
+                header.kdf = Some(dump_kdf);
+                let mac = cipher.header_mac(&header)?;
+                header.mac = Some(mac.clone());
+                Some((cipher, mac))
+            }
+            None => None,
+        };
+        out.write_all(&header.encode()?)?;
+
+        let mut index = 0;
+        let mut entries = self.db().iter();
+        loop {
+            let mut batch: Vec<Vec<Entry>> = Vec::with_capacity(CHUNKS_PER_BATCH);
+            while batch.len() < CHUNKS_PER_BATCH {
+                let chunk = entries
+                    .by_ref()
+                    .take(CHUNK_ENTRIES)
+                    .map(|result| {
+                        let (key, value) = result?;
+                        Ok((key.to_vec(), value.to_vec()))
+                    })
+                    .collect::<Result<Vec<Entry>>>()?;
+                if chunk.is_empty() {
+                    break;
+                }
+                batch.push(chunk);
+            }
+            if batch.is_empty() {
+                break;
+            }
+
+            let encoded = batch
+                .par_iter()
+                .enumerate()
+                .map(|(offset, chunk)| {
+                    let data = bincode::serialize(chunk)?;
+                    match &cipher {
+                        Some((cipher, mac)) => cipher.seal(mac, (index + offset) as u64, &data),
+                        None => Ok(data),
+                    }
+                })
+                .collect::<Result<Vec<_>>>()?;
+            for chunk in &encoded {
+                write_chunk(&mut out, chunk)?;
+            }
+            index += encoded.len();
+        }
+
+        write_chunk(&mut out, &[])?;
+        let trailer = (index as u64).to_le_bytes();
+        match &cipher {
+            Some((cipher, mac)) => {
+                write_chunk(&mut out, &cipher.seal(mac, TRAILER_INDEX, &trailer)?)?
+            }
+            None => write_chunk(&mut out, &trailer)?,
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_header() -> Result<()> {
+        let header = DumpHeader::current(true, 3);
+        let mut data = header.encode()?;
+        data.extend_from_slice(b"entries");
+        let mut input = &data[..];
+        assert_eq!(DumpHeader::read_from(&mut input)?, header);
+        assert_eq!(input, b"entries");
+
+        let legacy = DumpHeader::read_from(&mut &b"VAULT_RAWentries"[..])?;
+        assert_eq!((legacy.format, legacy.encrypted), (1, false));
+        assert!(legacy.is_supported());
+
+        // A newer format with fields this release does not know about
+        let json = br#"{"encrypted":false,"written_by":"0.5.2","codec":"zstd"}"#;
+        let mut data = b"PPRODUMP".to_vec();
+        data.extend_from_slice(&5u32.to_le_bytes());
+        data.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        data.extend_from_slice(json);
+        let future = DumpHeader::read_from(&mut &data[..])?;
+        assert_eq!((future.format, future.written_by.as_deref()), (5, Some("0.5.2")));
+        let error = future.check_supported().unwrap_err().to_string();
+        assert!(error.contains("upgrade promptpro"));
+
+        assert!(DumpHeader::read_from(&mut &b"{\"prompts\": []}"[..]).is_err());
+        assert!(DumpHeader::read_from(&mut &data[..14]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunked_dump() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        for i in 0..CHUNK_ENTRIES + 10 {
+            vault.db().insert(format!("entry:{:05}", i), format!("value {}", i).as_bytes())?;
+        }
+
+        let mut data = Vec::new();
+        vault.write_dump(&mut data, Some("secret"), KdfParams::default())?;
+        let mut input = &data[..];
+        let header = DumpHeader::read_from(&mut input)?;
+        assert_eq!(header.entries, Some(vault.db().len()));
+
+        let mut chunks = Vec::new();
+        let count = read_entries(input, &header, Some("secret"), 0, |index, entries| {
+            assert_eq!(index, chunks.len());
+            chunks.push(entries);
+            Ok(())
+        })?;
+        assert_eq!(count, vault.db().len());
+        assert_eq!(chunks.len(), 2);
+        let restored: Vec<Entry> = chunks.into_iter().flatten().collect();
+        assert!(restored.contains(&(b"entry:04100".to_vec(), b"value 4100".to_vec())));
+        assert!(read_entries(input, &header, None, 0, |_, _| Ok(())).is_err());
+
+        // A wrong password, or a header that does not match its keyed hash
+        let error = read_entries(input, &header, Some("wrong"), 0, |_, _| Ok(())).unwrap_err();
+        assert!(error.to_string().starts_with("Wrong password"));
+        let tampered = DumpHeader {
+            entries: Some(1),
+            ..header.clone()
+        };
+        assert!(read_entries(input, &tampered, Some("secret"), 0, |_, _| Ok(())).is_err());
+
+        // Chunks that are reordered, or dropped along with the end of the dump
+        let mut rest = input;
+        let (first, second) = (read_chunk(&mut rest)?.unwrap(), read_chunk(&mut rest)?.unwrap());
+        assert!(read_chunk(&mut rest)?.is_none());
+        let mut reordered = Vec::new();
+        for chunk in [&second, &first, &Vec::new()] {
+            write_chunk(&mut reordered, chunk)?;
+        }
+        reordered.extend_from_slice(rest);
+        let error = read_entries(&reordered[..], &header, Some("secret"), 0, |_, _| Ok(()));
+        assert!(error.unwrap_err().to_string().contains("chunk 0 failed authentication"));
+        let mut dropped = Vec::new();
+        write_chunk(&mut dropped, &first)?;
+        write_chunk(&mut dropped, &[])?;
+        dropped.extend_from_slice(rest);
+        let error = read_entries(&dropped[..], &header, Some("secret"), 0, |_, _| Ok(()));
+        assert!(error.unwrap_err().to_string().contains("1 of 2 chunks found"));
+
+        // Interrupted in the second chunk, then resumed from it
+        let target = PromptVault::open(dir.path().join("target"))?;
+        let truncated = &input[..input.len() - 64];
+        assert!(target.restore_entries(truncated, &header, Some("secret")).is_err());
+        assert!(target.restore_interrupted()?);
+        assert!(target.db().contains_key("entry:04095")?);
+        let rest = target.restore_entries(input, &header, Some("secret"))?;
+        assert_eq!(rest, vault.db().len() - CHUNK_ENTRIES);
+        assert!(!target.restore_interrupted()?);
+        assert_eq!(target.db().len(), vault.db().len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_to() -> Result<()> {
+        use crate::types::VersionSelector;
+
+        let dir = tempfile::tempdir()?;
+        let source = PromptVault::open(dir.path().join("source"))?;
+        source.add("writer", "You write.")?;
+        source.add("critic", "Dump critic.")?;
+        source.update("critic", "Dump critic, v2.", None)?;
+        let dump = dir.path().join("backup.bin");
+        let dump = dump.to_str().unwrap();
+        source.dump(dump, None)?;
+
+        let target = dir.path().join("target");
+        let occupied = PromptVault::open(&target)?;
+        occupied.add("planner", "Plan.")?;
+        // Diverged from the dump's 'critic' from its first version on
+        occupied.add("critic", "Local critic.")?;
+        occupied.update("critic", "Local critic, v2.", None)?;
+        occupied.update("critic", "Local critic, v3.", None)?;
+        drop(occupied);
+        let Err(error) = PromptVault::restore_to(dump, &target, None, RestorePolicy::Refuse) else {
+            panic!("restored over an existing vault");
+        };
+        assert!(error.to_string().contains("already holds a vault"));
+
+        let skipped = PromptVault::restore_to(dump, &target, None, RestorePolicy::Skip)?;
+        assert_eq!(skipped.list_keys()?, ["critic", "planner"]);
+        drop(skipped);
+
+        let merged = PromptVault::restore_to(dump, &target, None, RestorePolicy::Merge)?;
+        assert_eq!(merged.list_keys()?, ["critic", "planner", "writer"]);
+        assert_eq!(merged.get("writer", VersionSelector::Latest)?, "You write.");
+        // The diverged key keeps its local versions, and every one of them reads back
+        let critic: Vec<String> = (1..=3)
+            .map(|version| merged.get("critic", VersionSelector::Version(version)))
+            .collect::<Result<_>>()?;
+        assert_eq!(critic, ["Local critic.", "Local critic, v2.", "Local critic, v3."]);
+        assert!(merged.verify(false)?.is_clean());
+        drop(merged);
+
+        let overwritten = PromptVault::restore_to(dump, &target, None, RestorePolicy::Overwrite)?;
+        assert_eq!(overwritten.list_keys()?, ["critic", "writer"]);
+        assert!(!staging_path(&target).exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+//! Merging a dump into a vault that already holds prompts, key by key, for
+//! `promptpro resume`.
+//!
+//! The dump is restored in memory first. Versions the vault and the dump share (same
+//! number, timestamp and content) are left alone; a key whose local history is a
+//! prefix of the dump's gets the missing versions, one whose dump history is a prefix
+//! of the local one is kept, and a key where both sides have versions the other lacks
+//! is a conflict, settled with a [`ConflictPolicy`].
+
+use crate::export::VersionExport;
+use crate::remote::SyncPatch;
+use crate::storage::PromptVault;
+use crate::types::{calculate_hash, VersionMeta};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// How to settle a key both the vault and a dump changed since they last agreed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the local versions and ignore the dump's
+    Local,
+    /// Replace the local versions after the shared ones with the dump's
+    Remote,
+    /// Keep the local versions and append the dump's after them, renumbered
+    Both,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "keep-local" => Ok(ConflictPolicy::Local),
+            "keep-remote" => Ok(ConflictPolicy::Remote),
+            "keep-both" | "keep-both-renumber" => Ok(ConflictPolicy::Both),
+            other => Err(anyhow::anyhow!(
+                "Unknown conflict policy '{}', expected 'keep-local', 'keep-remote' or \
+                 'keep-both'",
+                other
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ConflictPolicy::Local => "kept local",
+            ConflictPolicy::Remote => "kept dump",
+            ConflictPolicy::Both => "kept both",
+        })
+    }
+}
+
+/// A key both the vault and a dump changed, as handed to the resolver of
+/// [`PromptVault::merge_dump_with`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyConflict {
+    pub key: String,
+    /// Versions both sides share
+    pub shared: u64,
+    pub local_latest: u64,
+    pub local_modified: DateTime<Utc>,
+    pub dump_latest: u64,
+    pub dump_modified: DateTime<Utc>,
+}
+
+/// What merging a dump did
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Keys the vault did not have
+    pub added: Vec<String>,
+    /// Keys whose local history the dump extends, with the number of versions added
+    pub updated: Vec<(String, usize)>,
+    /// Keys both sides changed, with how each was settled
+    pub conflicts: Vec<(String, ConflictPolicy)>,
+    /// Password-protected prompts, on either side, which are not merged
+    pub skipped: Vec<String>,
+}
+
+/// Whether a local version is the same version as one of the dump
+fn same_version(local: &VersionMeta, dump: &VersionExport) -> bool {
+    local.version == dump.version
+        && local.timestamp == dump.timestamp
+        && local.object_hash == calculate_hash(&dump.content)
+}
+
+/// Tags of `versions`, by name
+fn tags_of(versions: &[VersionExport]) -> BTreeMap<String, u64> {
+    versions
+        .iter()
+        .flat_map(|version| version.tags.iter().map(move |tag| (tag.clone(), version.version)))
+        .collect()
+}
+
+impl PromptVault {
+    /// Merge a dump into this vault key by key, calling `resolve` for each key both
+    /// sides changed since they last agreed. New keys get the dump's metadata too.
+    pub fn merge_dump_with<F>(
+        &self,
+        input_path: &str,
+        password: Option<&str>,
+        mut resolve: F,
+    ) -> Result<MergeReport>
+    where
+        F: FnMut(&KeyConflict) -> Result<ConflictPolicy>,
+    {
+        let staged = Self::stage_dump(input_path, password)?;
+        if staged.is_encrypted()? {
+            return Err(anyhow::anyhow!(
+                "The dump holds an encrypted vault, which can only be restored whole; \
+                 restore it with --target instead"
+            ));
+        }
+
+        let mut report = MergeReport::default();
+        let mut patch = SyncPatch::default();
+        // Metadata and notes of new keys, set once they have versions
+        let mut metas = Vec::new();
+        let mut notes = Vec::new();
+        for key in staged.list_keys()? {
+            if staged.is_sensitive(&key)? || self.is_sensitive(&key)? {
+                report.skipped.push(key);
+            }
+        }
+
+        for prompt in staged.export_prompts()?.prompts {
+            let key = prompt.key.as_str();
+            if self.is_sensitive(key)? {
+                continue;
+            }
+            let local = self.history(key)?;
+            let shared = local
+                .iter()
+                .zip(&prompt.versions)
+                .take_while(|(local, dump)| same_version(local, dump))
+                .count();
+
+            if local.is_empty() {
+                let meta = staged.prompt_meta(key)?;
+                if !meta.is_empty() {
+                    metas.push((key.to_string(), meta));
+                }
+                if let Some(text) = staged.notes(key)? {
+                    notes.push((key.to_string(), text));
+                }
+                report.added.push(key.to_string());
+            } else if shared == prompt.versions.len() {
+                // The vault already has everything the dump has
+                continue;
+            } else if shared == local.len() {
+                report
+                    .updated
+                    .push((key.to_string(), prompt.versions.len() - shared));
+            } else {
+                let local_last = &local[local.len() - 1];
+                let dump_last = &prompt.versions[prompt.versions.len() - 1];
+                let conflict = KeyConflict {
+                    key: key.to_string(),
+                    shared: shared as u64,
+                    local_latest: local_last.version,
+                    local_modified: local_last.timestamp,
+                    dump_latest: dump_last.version,
+                    dump_modified: dump_last.timestamp,
+                };
+                let policy = resolve(&conflict)?;
+                report.conflicts.push((key.to_string(), policy));
+                match policy {
+                    ConflictPolicy::Local => continue,
+                    ConflictPolicy::Remote => self.drop_versions_after(key, shared as u64)?,
+                    ConflictPolicy::Both => {
+                        // Local tags stay where they are; only 'dev' moves to the new latest
+                        let base = local_last.version;
+                        let versions: Vec<VersionExport> = (base + 1..)
+                            .zip(&prompt.versions[shared..])
+                            .map(|(number, version)| VersionExport {
+                                version: number,
+                                tags: Vec::new(),
+                                ..version.clone()
+                            })
+                            .collect();
+                        let latest = base + versions.len() as u64;
+                        let dev = BTreeMap::from([("dev".to_string(), latest)]);
+                        patch.push(key, base, versions, dev);
+                        continue;
+                    }
+                }
+            }
+
+            let missing = &prompt.versions[shared..];
+            patch.push(key, shared as u64, missing.to_vec(), tags_of(missing));
+        }
+
+        self.apply_sync_patch(&patch)?;
+        for (key, meta) in metas {
+            self.set_prompt_meta(&key, &meta)?;
+        }
+        for (key, text) in notes {
+            self.set_notes(&key, Some(&text))?;
+        }
+        self.db().flush()?;
+        Ok(report)
+    }
+
+    /// Remove the versions of `key` after `keep`, with their content and tags
+    fn drop_versions_after(&self, key: &str, keep: u64) -> Result<()> {
+        for version in self.history(key)?.into_iter().filter(|v| v.version > keep) {
+            self.remove_version(&version)?;
+        }
+        self.index_cache().forget(key);
+        self.unindex_versions(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_merge_dump_with() -> Result<()> {
+        let dir = tempdir()?;
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+
+        let backup = PromptVault::open(path("backup"))?;
+        for key in ["critic", "planner", "reviewer", "writer"] {
+            backup.add(key, "v1")?;
+        }
+        backup.dump(&path("early.vault"), None)?;
+        let local = PromptVault::open(path("local"))?;
+        local.merge_dump(&path("early.vault"), None)?;
+
+        for key in ["planner", "reviewer", "writer"] {
+            backup.update(key, "dump v2", None)?;
+        }
+        backup.tag("reviewer", "prod", 2)?;
+        backup.add("new", "from the dump")?;
+        let meta = crate::metadata::PromptMeta {
+            author: Some("alice".to_string()),
+            ..Default::default()
+        };
+        backup.set_prompt_meta("new", &meta)?;
+        backup.dump(&path("backup.vault"), None)?;
+        for key in ["critic", "planner", "writer"] {
+            local.update(key, "local v2", None)?;
+        }
+
+        let mut asked = Vec::new();
+        let report = local.merge_dump_with(&path("backup.vault"), None, |conflict| {
+            asked.push(conflict.key.clone());
+            assert_eq!((conflict.shared, conflict.local_latest), (1, 2));
+            Ok(match conflict.key.as_str() {
+                "writer" => ConflictPolicy::Local,
+                _ => ConflictPolicy::Remote,
+            })
+        })?;
+        assert_eq!(asked, ["planner", "writer"]);
+        assert_eq!(report.added, ["new"]);
+        assert_eq!(report.updated, [("reviewer".to_string(), 1)]);
+        assert_eq!(local.get("new", VersionSelector::Latest)?, "from the dump");
+        assert_eq!(local.prompt_meta("new")?, meta);
+        assert_eq!(local.get("reviewer", VersionSelector::tag("prod"))?, "dump v2");
+        assert_eq!(local.get("planner", VersionSelector::tag("dev"))?, "dump v2");
+        assert_eq!(local.history("planner")?.len(), 2);
+        assert_eq!(local.get("writer", VersionSelector::Latest)?, "local v2");
+        // Only changed locally
+        assert_eq!(local.get("critic", VersionSelector::Latest)?, "local v2");
+
+        // Keeping both appends the dump's versions after the local ones
+        let report =
+            local.merge_dump_with(&path("backup.vault"), None, |_| Ok(ConflictPolicy::Both))?;
+        assert_eq!(report.conflicts, [("writer".to_string(), ConflictPolicy::Both)]);
+        assert_eq!(local.get("writer", VersionSelector::Version(2))?, "local v2");
+        assert_eq!(local.get("writer", VersionSelector::tag("dev"))?, "dump v2");
+        assert_eq!(local.history("writer")?.len(), 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! Prompts baked into a binary at compile time, for services that cannot reach the
+//! vault at runtime.
+//!
+//! A build script calls [`embed_prompts`] with specs such as `writer@stable`, which
+//! writes the content and version of each into `OUT_DIR`. The crate then reads them with
+//! [`prompt!`](crate::prompt) and [`prompt_version!`](crate::prompt_version), given the
+//! same spec: `const WRITER: &str = promptpro::prompt!("writer@stable");`. A spec
+//! without a selector takes the version pinned in the lockfile, when one is given.
+
+use crate::lock::{check_content_hash, Lockfile};
+use crate::storage::PromptVault;
+use crate::types::{key_file_path, VersionSelector};
+use crate::utils::current_vault_path;
+use crate::vault_lock::LockPolicy;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Directory of `OUT_DIR` the embedded prompts are written to
+pub const EMBED_DIR: &str = "promptpro";
+
+/// Where [`embed_prompts`] reads prompts from and writes them to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmbedOptions {
+    /// Vault to read, by default the one `promptpro` itself would use
+    pub vault: Option<PathBuf>,
+    /// Lockfile pinning the version of specs without a selector, whose content is
+    /// checked against the locked hash
+    pub lockfile: Option<PathBuf>,
+    /// Directory to write to instead of `$OUT_DIR/promptpro`
+    pub out_dir: Option<PathBuf>,
+}
+
+/// A prompt written by [`embed_prompts`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddedPrompt {
+    /// Spec the prompt was embedded for, e.g. `writer@stable`
+    pub spec: String,
+    pub key: String,
+    pub version: u64,
+    /// `object_hash` of the content
+    pub hash: String,
+}
+
+/// Embed the prompts of `specs` (`key` or `key@selector`) for [`prompt!`](crate::prompt)
+/// and [`prompt_version!`](crate::prompt_version). Meant to be called from `build.rs`,
+/// whose build is rerun when the vault or the lockfile change.
+pub fn embed_prompts(specs: &[&str], options: &EmbedOptions) -> Result<Vec<EmbeddedPrompt>> {
+    let out_dir = match &options.out_dir {
+        Some(out_dir) => out_dir.clone(),
+        None => std::env::var_os("OUT_DIR")
+            .map(|out_dir| PathBuf::from(out_dir).join(EMBED_DIR))
+            .ok_or_else(|| {
+                anyhow::anyhow!("OUT_DIR is not set; call embed_prompts from a build script")
+            })?,
+    };
+    let vault_path = match &options.vault {
+        Some(vault) => vault.clone(),
+        None => current_vault_path()?,
+    };
+    println!("cargo:rerun-if-changed={}", vault_path.display());
+    println!("cargo:rerun-if-env-changed=PROMPTPRO_VAULT");
+    let lockfile = match &options.lockfile {
+        Some(path) => {
+            println!("cargo:rerun-if-changed={}", path.display());
+            Some(Lockfile::read(path)?)
+        }
+        None => None,
+    };
+    let vault = PromptVault::open_with_policy(&vault_path, LockPolicy::Snapshot)?;
+
+    let mut embedded = Vec::new();
+    for spec in specs {
+        let (content, prompt) = embed_one(&vault, spec, lockfile.as_ref())
+            .map_err(|e| anyhow::anyhow!("Cannot embed '{}': {}", spec, e))?;
+        write_embedded(&out_dir, spec, &content, prompt.version)?;
+        embedded.push(prompt);
+    }
+    Ok(embedded)
+}
+
+/// The content of a spec, checked against the lockfile when it pins the version
+fn embed_one(
+    vault: &PromptVault,
+    spec: &str,
+    lockfile: Option<&Lockfile>,
+) -> Result<(String, EmbeddedPrompt)> {
+    // The spec names the embedded files, which must stay inside the output directory
+    key_file_path(spec, "txt")?;
+    let (key, selector, locked_hash) = match (spec.split_once('@'), lockfile) {
+        (Some((key, selector)), _) => (key, VersionSelector::parse(selector)?, None),
+        (None, Some(lockfile)) => {
+            let locked = lockfile
+                .prompts
+                .get(spec)
+                .ok_or_else(|| anyhow::anyhow!("'{}' is not in the lockfile", spec))?;
+            (spec, VersionSelector::Version(locked.version), Some(&locked.hash))
+        }
+        (None, None) => (spec, VersionSelector::Latest, None),
+    };
+    let prompt = vault.get_versioned(key, selector)?;
+    let meta = vault
+        .get_version_meta(key, prompt.version)?
+        .ok_or_else(|| anyhow::anyhow!("Version {} of '{}' not found", prompt.version, key))?;
+    if let Some(hash) = locked_hash {
+        check_content_hash(key, prompt.version, &prompt.content, hash)?;
+    }
+    let embedded = EmbeddedPrompt {
+        spec: spec.to_string(),
+        key: key.to_string(),
+        version: prompt.version,
+        hash: meta.object_hash,
+    };
+    Ok((prompt.content, embedded))
+}
+
+/// `<spec>.txt` with the content and `<spec>.version` with the version as a `u64`
+/// literal, the files the macros include
+fn write_embedded(out_dir: &Path, spec: &str, content: &str, version: u64) -> Result<()> {
+    let content_path = out_dir.join(format!("{}.txt", spec));
+    if let Some(parent) = content_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&content_path, content)?;
+    std::fs::write(out_dir.join(format!("{}.version", spec)), format!("{}u64", version))?;
+    Ok(())
+}
+
+/// The content embedded for a spec by [`embed_prompts`](crate::embed_prompts) in the
+/// build script, as a `&'static str`
+#[macro_export]
+macro_rules! prompt {
+    ($spec:literal) => {
+        include_str!(concat!(env!("OUT_DIR"), "/promptpro/", $spec, ".txt"))
+    };
+}
+
+/// The version embedded for a spec by [`embed_prompts`](crate::embed_prompts), as a `u64`
+#[macro_export]
+macro_rules! prompt_version {
+    ($spec:literal) => {
+        include!(concat!(env!("OUT_DIR"), "/promptpro/", $spec, ".version"))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_embed_prompts() -> Result<()> {
+        let dir = tempdir()?;
+        let vault_path = dir.path().join("vault");
+        let lockfile = dir.path().join("prompts.lock");
+        {
+            let vault = PromptVault::open(&vault_path)?;
+            vault.add("agents/writer", "You write.")?;
+            vault.tag("agents/writer", "stable", 1)?;
+            vault.lock()?.write(&lockfile)?;
+            vault.update("agents/writer", "You write posts.", None)?;
+        }
+        let out_dir = dir.path().join("out");
+        let options = EmbedOptions {
+            vault: Some(vault_path),
+            lockfile: Some(lockfile),
+            out_dir: Some(out_dir.clone()),
+        };
+
+        let embedded = embed_prompts(&["agents/writer", "agents/writer@latest"], &options)?;
+        let versions: Vec<u64> = embedded.iter().map(|prompt| prompt.version).collect();
+        assert_eq!(versions, [1, 2]);
+        let read = |name: &str| std::fs::read_to_string(out_dir.join(name));
+        assert_eq!(read("agents/writer.txt")?, "You write.");
+        assert_eq!(read("agents/writer.version")?, "1u64");
+        assert_eq!(read("agents/writer@latest.txt")?, "You write posts.");
+        assert!(embed_prompts(&["agents/planner"], &options).is_err());
+        assert!(embed_prompts(&["../secrets@latest"], &options).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+//! Prompts rendered into the JSON body of a chat API request, for `promptpro emit`, so
+//! services can send a stored prompt without glue code of their own.
+//!
+//! Chat prompts keep their messages; any other prompt is sent as a single message. The
+//! model and temperature come from the prompt's metadata unless given.
+
+use crate::chat::{ChatMessage, ChatPrompt, Role};
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// `max_tokens` of Anthropic requests when none is given, as the API requires one
+pub const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// API whose request body [`PromptVault::request_body`] builds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestFormat {
+    /// OpenAI chat completions: `{"model", "messages", ...}`
+    OpenAiChat,
+    /// Anthropic messages: `{"model", "max_tokens", "system", "messages", ...}`
+    AnthropicMessages,
+}
+
+impl std::str::FromStr for RequestFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "openai-chat" | "openai" => Ok(RequestFormat::OpenAiChat),
+            "anthropic-messages" | "anthropic" => Ok(RequestFormat::AnthropicMessages),
+            other => Err(anyhow::anyhow!(
+                "Unknown request format '{}', expected 'openai-chat' or 'anthropic-messages'",
+                other
+            )),
+        }
+    }
+}
+
+/// How [`PromptVault::request_body`] fills in a request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmitOptions {
+    /// Fail if a placeholder has no value
+    pub strict: bool,
+    /// Model of the request, instead of the one of the prompt's metadata
+    pub model: Option<String>,
+    /// Limit of generated tokens; Anthropic requests get [`DEFAULT_MAX_TOKENS`] without
+    pub max_tokens: Option<u32>,
+    /// Role of the message a prompt that is not a chat prompt becomes, user by default
+    pub role: Option<Role>,
+}
+
+impl PromptVault {
+    /// Render the selected version of `key` with `vars` into a request body for `format`
+    pub fn request_body(
+        &self,
+        key: &str,
+        selector: VersionSelector,
+        format: RequestFormat,
+        vars: &HashMap<String, String>,
+        options: &EmitOptions,
+    ) -> Result<serde_json::Value> {
+        let version = VersionSelector::Version(self.resolve_version(key, &selector)?);
+        let chat = if ChatPrompt::is_chat(&self.get(key, version.clone())?) {
+            self.render_chat(key, version, vars, options.strict)?
+        } else {
+            let content = self.render(key, version, vars, options.strict)?;
+            let role = options.role.unwrap_or(Role::User);
+            ChatPrompt::new(vec![ChatMessage::new(role, content)])
+        };
+
+        let meta = self.prompt_meta(key)?;
+        let mut body = match format {
+            RequestFormat::OpenAiChat => serde_json::json!({ "messages": chat.to_openai() }),
+            RequestFormat::AnthropicMessages => {
+                let mut body = chat.to_anthropic();
+                let max_tokens = options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+                body["max_tokens"] = max_tokens.into();
+                body
+            }
+        };
+        if let Some(model) = options.model.clone().or(meta.model) {
+            body["model"] = model.into();
+        }
+        if let Some(temperature) = meta.temperature {
+            body["temperature"] = (temperature as f64).into();
+        }
+        if let (RequestFormat::OpenAiChat, Some(max_tokens)) = (format, options.max_tokens) {
+            body["max_completion_tokens"] = max_tokens.into();
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::PromptMeta;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_request_body() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("summarize", "Summarize {{text}}.")?;
+        vault.update_prompt_meta(
+            "summarize",
+            PromptMeta {
+                model: Some("gpt-4o-mini".to_string()),
+                ..PromptMeta::default()
+            },
+        )?;
+        let vars = HashMap::from([("text".to_string(), "the news".to_string())]);
+        let options = EmitOptions::default();
+
+        let body = vault.request_body(
+            "summarize",
+            VersionSelector::Latest,
+            RequestFormat::OpenAiChat,
+            &vars,
+            &options,
+        )?;
+        assert_eq!(
+            body,
+            json!({
+                "model": "gpt-4o-mini",
+                "messages": [{ "role": "user", "content": "Summarize the news." }],
+            })
+        );
+
+        vault.add_chat(
+            "support",
+            &ChatPrompt::new(vec![
+                ChatMessage::new(Role::System, "You help."),
+                ChatMessage::new(Role::User, "{{question}}"),
+            ]),
+        )?;
+        let options = EmitOptions {
+            model: Some("claude-sonnet-4-5".to_string()),
+            ..EmitOptions::default()
+        };
+        let format = "anthropic-messages".parse()?;
+        let body = vault.request_body("support", VersionSelector::Latest, format, &vars, &options)?;
+        assert_eq!(
+            body,
+            json!({
+                "model": "claude-sonnet-4-5",
+                "max_tokens": DEFAULT_MAX_TOKENS,
+                "system": "You help.",
+                "messages": [{ "role": "user", "content": "{{question}}" }],
+            })
+        );
+
+        let strict = EmitOptions {
+            strict: true,
+            ..EmitOptions::default()
+        };
+        let latest = VersionSelector::Latest;
+        assert!(vault.request_body("support", latest, format, &vars, &strict).is_err());
+        assert!("openai-responses".parse::<RequestFormat>().is_err());
+
+        Ok(())
+    }
+}
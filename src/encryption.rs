@@ -0,0 +1,289 @@
+//! Encryption at rest of stored content and diffs, for `promptpro init --encrypted`.
+//!
+//! The vault key is derived from a password with Argon2id; the salt and parameters are
+//! stored under `meta:encryption` with a check value to recognise a wrong password.
+//! Encrypted blobs start with [`MAGIC`], after compression, so a vault converted from
+//! plaintext stays readable while it is rewritten. Each blob is authenticated together
+//! with the entry it is stored under, so blobs cannot be swapped between prompts.
+//!
+//! Keys, versions, tags and messages are not encrypted, nor is the `object_hash` of
+//! each version: an unkeyed BLAKE3 hash of its content, which lets anyone holding the
+//! vault confirm a guess of a prompt's exact content.
+
+use crate::storage::PromptVault;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Key derivation settings of an encrypted vault
+const ENCRYPTION_KEY: &[u8] = b"meta:encryption";
+
+pub(crate) const MAGIC: &[u8] = b"\0PPA";
+
+const NONCE_LEN: usize = 12;
+
+/// Plaintext of the check value, to tell a wrong password from corrupted content
+const PASSWORD_CHECK: &[u8] = b"promptpro vault";
+
+/// Argon2id cost parameters, stored next to the salt so that derived keys can be
+/// recomputed whatever the defaults of later releases
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    /// Memory in KiB
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        let params = Params::default();
+        KdfParams {
+            memory_kib: params.m_cost(),
+            iterations: params.t_cost(),
+            parallelism: params.p_cost(),
+        }
+    }
+}
+
+impl KdfParams {
+    /// Fail on parameters Argon2id does not accept
+    pub fn validate(&self) -> Result<()> {
+        self.argon2(32).map(|_| ())
+    }
+
+    fn argon2(&self, output_len: usize) -> Result<Argon2<'static>> {
+        let params = Params::new(
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+            Some(output_len),
+        )
+        .map_err(|e| anyhow::anyhow!("Invalid key derivation parameters: {}", e))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    /// Derive `output.len()` bytes of key material from a password
+    pub(crate) fn derive(&self, password: &str, salt: &[u8], output: &mut [u8]) -> Result<()> {
+        self.argon2(output.len())?
+            .hash_password_into(password.as_bytes(), salt, output)
+            .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyDerivation {
+    salt: [u8; 16],
+    params: KdfParams,
+    check: Vec<u8>,
+}
+
+impl KeyDerivation {
+    fn derive(&self, password: &str) -> Result<Aes256Gcm> {
+        let mut key = [0u8; 32];
+        self.params.derive(password, &self.salt, &mut key)?;
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+    }
+}
+
+/// Whether a stored blob is encrypted
+pub(crate) fn is_encrypted_blob(blob: &[u8]) -> bool {
+    blob.starts_with(MAGIC)
+}
+
+/// Encrypt `data` to be stored under the entry `entry`
+fn seal(cipher: &Aes256Gcm, entry: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let payload = Payload {
+        msg: data,
+        aad: entry,
+    };
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
+
+    let mut blob = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    blob.extend_from_slice(MAGIC);
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+/// Decrypt a blob stored under the entry `entry`
+fn open(cipher: &Aes256Gcm, entry: &[u8], blob: &[u8]) -> Result<Vec<u8>> {
+    let sealed = blob
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| anyhow::anyhow!("Not an encrypted blob"))?;
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow::anyhow!("Truncated encrypted blob"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let payload = Payload {
+        msg: ciphertext,
+        aad: entry,
+    };
+    cipher.decrypt(Nonce::from_slice(nonce), payload).map_err(|_| {
+        anyhow::anyhow!("Cannot decrypt stored content: wrong vault key or moved entry")
+    })
+}
+
+fn locked() -> anyhow::Error {
+    anyhow::anyhow!("The vault is encrypted; unlock it with its password first")
+}
+
+impl PromptVault {
+    /// Whether the vault stores its content encrypted
+    pub fn is_encrypted(&self) -> Result<bool> {
+        Ok(self.db().contains_key(ENCRYPTION_KEY)?)
+    }
+
+    /// Encrypt the vault with a key derived from `password`, rewriting the content and
+    /// diffs already stored. Content of password-protected prompts is already encrypted
+    /// with its own passphrase and is left as is. Returns the number of blobs rewritten.
+    pub fn encrypt_at_rest(&self, password: &str) -> Result<usize> {
+        if self.is_encrypted()? {
+            return Err(anyhow::anyhow!("The vault is already encrypted"));
+        }
+        if password.is_empty() {
+            return Err(anyhow::anyhow!("Password cannot be empty"));
+        }
+
+        let mut derivation = KeyDerivation {
+            salt: [0u8; 16],
+            params: KdfParams::default(),
+            check: Vec::new(),
+        };
+        rand::thread_rng().fill_bytes(&mut derivation.salt);
+        let cipher = derivation.derive(password)?;
+        derivation.check = seal(&cipher, ENCRYPTION_KEY, PASSWORD_CHECK)?;
+
+        // Settings first, blobs after: an interruption leaves a readable, mixed vault
+        self.db()
+            .insert(ENCRYPTION_KEY, bincode::serialize(&derivation)?)?;
+        let _ = self.vault_cipher().set(cipher);
+
+        let report = self.rewrite_blobs(|entry, blob| {
+            if is_encrypted_blob(blob) {
+                return Ok(None);
+            }
+            self.encrypt_blob(entry, blob.to_vec()).map(Some)
+        })?;
+        Ok(report.blobs)
+    }
+
+    /// Unlock an encrypted vault for reading and writing content; a no-op for vaults
+    /// that are not encrypted
+    pub fn unlock(&self, password: &str) -> Result<()> {
+        let Some(settings) = self.db().get(ENCRYPTION_KEY)? else {
+            return Ok(());
+        };
+        let derivation: KeyDerivation = bincode::deserialize(&settings)?;
+        let cipher = derivation.derive(password)?;
+        let check = open(&cipher, ENCRYPTION_KEY, &derivation.check).ok();
+        if check.as_deref() != Some(PASSWORD_CHECK) {
+            return Err(anyhow::anyhow!("Wrong vault password"));
+        }
+        let _ = self.vault_cipher().set(cipher);
+        Ok(())
+    }
+
+    /// Fail if the vault is encrypted and not unlocked, so content cannot be stored
+    pub(crate) fn ensure_unlocked(&self) -> Result<()> {
+        if self.vault_cipher().get().is_none() && self.is_encrypted()? {
+            return Err(locked());
+        }
+        Ok(())
+    }
+
+    /// Encrypt a (compressed) blob to be stored under `entry` if the vault is encrypted
+    pub(crate) fn encrypt_blob(&self, entry: &[u8], blob: Vec<u8>) -> Result<Vec<u8>> {
+        self.ensure_unlocked()?;
+        match self.vault_cipher().get() {
+            Some(cipher) => seal(cipher, entry, &blob),
+            None => Ok(blob),
+        }
+    }
+
+    /// Decrypt a blob stored under `entry` if it is encrypted
+    pub(crate) fn decrypt_blob<'a>(&self, entry: &[u8], blob: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+        if !is_encrypted_blob(blob) {
+            return Ok(Cow::Borrowed(blob));
+        }
+        let cipher = self.vault_cipher().get().ok_or_else(locked)?;
+        Ok(Cow::Owned(open(cipher, entry, blob)?))
+    }
+
+    /// Encode content or a diff to be stored under `entry`: compressed, then encrypted
+    pub(crate) fn encode_blob(&self, entry: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt_blob(entry, self.compress_blob(data)?)
+    }
+
+    /// Decode a blob stored under `entry`, written by [`PromptVault::encode_blob`] or an
+    /// older release
+    pub(crate) fn decode_blob(&self, entry: &[u8], blob: &[u8]) -> Result<Vec<u8>> {
+        let compressed = self.decrypt_blob(entry, blob)?;
+        Ok(crate::compress::decompress(&compressed)?.into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_encryption_at_rest() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("vault");
+        let few_shot = "Q: what is the capital of France?\nA: Paris\n".repeat(50);
+        {
+            let vault = PromptVault::open(&path)?;
+            vault.add("existing", "Written before encryption.")?;
+            assert_eq!(vault.encrypt_at_rest("hunter2")?, 1);
+            vault.add("few-shot", &few_shot)?;
+            vault.update("few-shot", &few_shot.replace("France", "Italy"), None)?;
+            assert!(vault.encrypt_at_rest("again").is_err());
+        }
+
+        // Nothing readable is left in the stored blobs
+        let vault = PromptVault::open(&path)?;
+        assert!(vault.is_encrypted()?);
+        for prefix in ["object:", "diff:"] {
+            for entry in vault.db().scan_prefix(prefix) {
+                assert!(entry?.1.starts_with(MAGIC));
+            }
+        }
+
+        // Content needs the password; metadata does not
+        assert!(vault.get("existing", VersionSelector::Latest).is_err());
+        assert!(vault.add("new", "Locked.").is_err());
+        assert_eq!(vault.history("few-shot")?.len(), 2);
+        assert!(vault.unlock("wrong").is_err());
+
+        vault.unlock("hunter2")?;
+        assert_eq!(vault.get("existing", VersionSelector::Latest)?, "Written before encryption.");
+        assert_eq!(vault.get("few-shot", VersionSelector::Version(1))?, few_shot);
+        assert!(vault.get("few-shot", VersionSelector::Latest)?.contains("Italy"));
+
+        // Blobs are bound to their entry, so swapping them is detected
+        let objects: Vec<(sled::IVec, sled::IVec)> =
+            vault.db().scan_prefix("object:").collect::<sled::Result<_>>()?;
+        vault.db().insert(&objects[0].0, objects[1].1.clone())?;
+        vault.db().insert(&objects[1].0, objects[0].1.clone())?;
+        assert!(vault.get("existing", VersionSelector::Latest).is_err());
+        for (entry, blob) in &objects {
+            vault.db().insert(entry, blob)?;
+        }
+        assert_eq!(vault.get("existing", VersionSelector::Latest)?, "Written before encryption.");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+//! Test cases run against an LLM, for `promptpro test`: each case gives placeholder
+//! values and assertions on the answer, and versions of the prompt pass or fail them.
+//!
+//! Requests go to an OpenAI-compatible chat completions API set in the `[llm]` table of
+//! `~/.promptpro/config.toml`; sending them needs the `eval` feature.
+//!
+//! ```toml
+//! [llm]
+//! endpoint = "https://api.openai.com/v1"   # the default; e.g. http://localhost:11434/v1
+//! model = "gpt-4o-mini"                    # for prompts without a model in their metadata
+//! api_key_env = "OPENAI_API_KEY"           # the default
+//! ```
+
+use crate::emit::{EmitOptions, RequestFormat};
+use crate::profile::ProfileConfig;
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::{Context as _, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Base URL of the API when none is configured
+pub const DEFAULT_ENDPOINT: &str = "https://api.openai.com/v1";
+/// Environment variable holding the API key when none is configured
+pub const DEFAULT_API_KEY_ENV: &str = "OPENAI_API_KEY";
+
+/// The `[llm]` table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LlmConfig {
+    /// Base URL of an OpenAI-compatible API, [`DEFAULT_ENDPOINT`] when unset
+    pub endpoint: Option<String>,
+    /// Model of prompts whose metadata names none
+    pub model: Option<String>,
+    /// Environment variable holding the API key, [`DEFAULT_API_KEY_ENV`] when unset
+    pub api_key_env: Option<String>,
+}
+
+impl LlmConfig {
+    /// The `[llm]` table of the config file
+    pub fn load() -> Result<Self> {
+        Ok(ProfileConfig::load(&ProfileConfig::default_path()?)?.llm)
+    }
+
+    pub fn endpoint(&self) -> &str {
+        self.endpoint.as_deref().unwrap_or(DEFAULT_ENDPOINT)
+    }
+
+    pub fn api_key_env(&self) -> &str {
+        self.api_key_env.as_deref().unwrap_or(DEFAULT_API_KEY_ENV)
+    }
+}
+
+/// What the answer to a test case must satisfy
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Assertion {
+    Contains(String),
+    NotContains(String),
+    /// Matches a regular expression
+    Matches(String),
+}
+
+impl Assertion {
+    /// Why `output` fails the assertion, if it does
+    pub fn check(&self, output: &str) -> Result<Option<String>> {
+        let passed = match self {
+            Assertion::Contains(text) => output.contains(text.as_str()),
+            Assertion::NotContains(text) => !output.contains(text.as_str()),
+            Assertion::Matches(pattern) => Regex::new(pattern)?.is_match(output),
+        };
+        Ok((!passed).then(|| format!("expected the answer to {}", self)))
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assertion::Contains(text) => write!(f, "contain {:?}", text),
+            Assertion::NotContains(text) => write!(f, "not contain {:?}", text),
+            Assertion::Matches(pattern) => write!(f, "match /{}/", pattern),
+        }
+    }
+}
+
+/// Placeholder values for a prompt and what the answer must satisfy
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TestCase {
+    pub key: String,
+    pub name: String,
+    pub vars: BTreeMap<String, String>,
+    pub assertions: Vec<Assertion>,
+}
+
+/// The outcome of one test case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseResult {
+    pub case: String,
+    pub output: String,
+    /// Why the answer failed, one entry per failed assertion
+    pub failures: Vec<String>,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// The outcomes of the test cases of a prompt for one version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionReport {
+    pub version: u64,
+    pub results: Vec<CaseResult>,
+}
+
+impl VersionReport {
+    /// Number of test cases that passed
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|result| result.passed()).count()
+    }
+}
+
+/// Check the answer to `case` against its assertions
+pub fn check_output(case: &TestCase, output: String) -> Result<CaseResult> {
+    let mut failures = Vec::new();
+    for assertion in &case.assertions {
+        failures.extend(assertion.check(&output)?);
+    }
+    Ok(CaseResult {
+        case: case.name.clone(),
+        output,
+        failures,
+    })
+}
+
+fn test_case_key(key: &str, name: &str) -> String {
+    format!("test_case:{}:{}", key, name)
+}
+
+impl PromptVault {
+    /// Store a test case of a prompt, replacing the one of the same name
+    pub fn add_test_case(&self, case: &TestCase) -> Result<()> {
+        let name = &case.name;
+        if name.is_empty()
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid test case name '{}'; use letters, digits, '-', '_' and '.'",
+                name
+            ));
+        }
+        if self.get_latest_version_number(&case.key)?.is_none() {
+            return Err(self.unknown_key(&case.key));
+        }
+        if case.assertions.is_empty() {
+            return Err(anyhow::anyhow!("Test case '{}' has no assertions", name));
+        }
+        for assertion in &case.assertions {
+            if let Assertion::Matches(pattern) = assertion {
+                Regex::new(pattern)
+                    .map_err(|e| anyhow::anyhow!("Invalid regex '{}': {}", pattern, e))?;
+            }
+        }
+        self.db()
+            .insert(test_case_key(&case.key, name).as_bytes(), bincode::serialize(case)?)?;
+        Ok(())
+    }
+
+    /// The test cases of `key`, ordered by name
+    pub fn test_cases(&self, key: &str) -> Result<Vec<TestCase>> {
+        let mut cases = Vec::new();
+        for (_, value) in self.scan_entries(format!("test_case:{}:", key).as_bytes())? {
+            let case: TestCase = bincode::deserialize(&value)?;
+            // The prefix also matches the cases of keys starting with `key:`
+            if case.key == key {
+                cases.push(case);
+            }
+        }
+        Ok(cases)
+    }
+
+    /// Remove test case `name` of `key`; returns whether it existed
+    pub fn delete_test_case(&self, key: &str, name: &str) -> Result<bool> {
+        Ok(self.db().remove(test_case_key(key, name).as_bytes())?.is_some())
+    }
+
+    /// Remove the test cases of a prompt that is being deleted
+    pub(crate) fn remove_test_cases(&self, key: &str) -> Result<()> {
+        for case in self.test_cases(key)? {
+            self.remove_entry(test_case_key(key, &case.name).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// The chat completions request `case` sends for version `version` of its prompt
+    pub fn test_request(
+        &self,
+        case: &TestCase,
+        version: u64,
+        config: &LlmConfig,
+    ) -> Result<serde_json::Value> {
+        let vars: HashMap<String, String> = case.vars.clone().into_iter().collect();
+        self.llm_request(&case.key, version, &vars, config)
+    }
+
+    /// Chat completions request for version `version` of `key` rendered with `vars`,
+    /// failing on placeholders without a value
+    fn llm_request(
+        &self,
+        key: &str,
+        version: u64,
+        vars: &HashMap<String, String>,
+        config: &LlmConfig,
+    ) -> Result<serde_json::Value> {
+        let options = EmitOptions {
+            strict: true,
+            ..EmitOptions::default()
+        };
+        let selector = VersionSelector::Version(version);
+        let mut body = self.request_body(key, selector, RequestFormat::OpenAiChat, vars, &options)?;
+        if body.get("model").is_none() {
+            let model = config.model.clone().ok_or_else(|| {
+                anyhow::anyhow!("No model for '{}'; set one in its metadata or as llm.model", key)
+            })?;
+            body["model"] = model.into();
+        }
+        Ok(body)
+    }
+
+    /// The answer of the model of `config` to version `version` of `key` rendered with
+    /// `vars`
+    pub async fn respond(
+        &self,
+        key: &str,
+        version: u64,
+        vars: &HashMap<String, String>,
+        config: &LlmConfig,
+    ) -> Result<String> {
+        let body = self.llm_request(key, version, vars, config)?;
+        complete(config, &body).await.with_context(|| format!("Answer to {} v{}", key, version))
+    }
+
+    /// Run the test cases of `key` against the selected versions, in order, sending
+    /// each request to the API of `config`
+    pub async fn run_tests(
+        &self,
+        key: &str,
+        selectors: &[VersionSelector],
+        config: &LlmConfig,
+    ) -> Result<Vec<VersionReport>> {
+        let cases = self.test_cases(key)?;
+        if cases.is_empty() {
+            return Err(anyhow::anyhow!("'{}' has no test cases", key));
+        }
+        let mut reports: Vec<VersionReport> = Vec::new();
+        for selector in selectors {
+            let version = self.resolve_version(key, selector)?;
+            if reports.iter().any(|report| report.version == version) {
+                continue;
+            }
+            let mut results = Vec::new();
+            for case in &cases {
+                let body = self.test_request(case, version, config)?;
+                let output = complete(config, &body)
+                    .await
+                    .with_context(|| format!("Test case '{}' of {} v{}", case.name, key, version))?;
+                results.push(check_output(case, output)?);
+            }
+            reports.push(VersionReport { version, results });
+        }
+        Ok(reports)
+    }
+}
+
+/// Send a chat completions request, returning the answer
+#[cfg(feature = "eval")]
+async fn complete(config: &LlmConfig, body: &serde_json::Value) -> Result<String> {
+    let url = format!("{}/chat/completions", config.endpoint().trim_end_matches('/'));
+    let mut request = reqwest::Client::new().post(&url).json(body);
+    if let Ok(api_key) = std::env::var(config.api_key_env()) {
+        request = request.bearer_auth(api_key);
+    }
+    let response = crate::network::send(&format!("Testing prompts with {}", url), request).await?;
+    let status = response.status();
+    let text = response.text().await?;
+    if !status.is_success() {
+        return Err(anyhow::anyhow!("{} answered {}: {}", url, status, text.trim()));
+    }
+    let answer: serde_json::Value = serde_json::from_str(&text)?;
+    answer["choices"][0]["message"]["content"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("No answer in the response of {}", url))
+}
+
+#[cfg(not(feature = "eval"))]
+async fn complete(_config: &LlmConfig, _body: &serde_json::Value) -> Result<String> {
+    Err(anyhow::anyhow!(
+        "Running tests requires promptpro to be built with the `eval` feature"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::PromptMeta;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_test_cases() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("support", "Answer {{question}} politely.")?;
+        let case = TestCase {
+            key: "support".to_string(),
+            name: "refund".to_string(),
+            vars: BTreeMap::from([("question".to_string(), "Can I get a refund?".to_string())]),
+            assertions: vec![
+                Assertion::Contains("refund".to_string()),
+                Assertion::NotContains("sorry".to_string()),
+                Assertion::Matches(r"\d+ days".to_string()),
+            ],
+        };
+        vault.add_test_case(&case)?;
+        let bad_regex = TestCase {
+            assertions: vec![Assertion::Matches("(".to_string())],
+            ..case.clone()
+        };
+        assert!(vault.add_test_case(&bad_regex).is_err());
+        let bad_name = TestCase {
+            name: "a b".to_string(),
+            ..case.clone()
+        };
+        assert!(vault.add_test_case(&bad_name).is_err());
+        assert_eq!(vault.test_cases("support")?.len(), 1);
+
+        // Requests take the model of the metadata, else of the config
+        let config = LlmConfig {
+            model: Some("gpt-4o-mini".to_string()),
+            ..LlmConfig::default()
+        };
+        let body = vault.test_request(&case, 1, &config)?;
+        assert_eq!(body["model"], "gpt-4o-mini");
+        assert_eq!(body["messages"][0]["content"], "Answer Can I get a refund? politely.");
+        assert!(vault.test_request(&case, 1, &LlmConfig::default()).is_err());
+        let meta = PromptMeta {
+            model: Some("llama3".to_string()),
+            ..PromptMeta::default()
+        };
+        vault.update_prompt_meta("support", meta)?;
+        assert_eq!(vault.test_request(&case, 1, &config)?["model"], "llama3");
+
+        let answer = "Refunds take 14 days; your refund is on its way.";
+        let passed = check_output(&case, answer.to_string())?;
+        assert!(passed.passed());
+        let failed = check_output(&case, "Sorry, no refund.".into())?;
+        assert_eq!(failed.failures, [r"expected the answer to match /\d+ days/"]);
+
+        vault.delete_prompt_key("support")?;
+        assert!(vault.test_cases("support")?.is_empty());
+
+        Ok(())
+    }
+}
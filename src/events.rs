@@ -0,0 +1,166 @@
+//! Events emitted when changes to a vault are committed, for [`PromptVault::on_event`].
+//!
+//! Events are worked out from the writes of each commit, so every way of changing a
+//! vault emits them (the CLI, the TUI, `serve`, sync, transactions), only once the
+//! change is stored, and a transaction that fails emits none.
+
+use crate::storage::PromptVault;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// A committed change to a vault
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum VaultEvent {
+    /// A new key with its version 1
+    PromptAdded { key: String },
+    /// A version after the first
+    VersionCreated { key: String, version: u64 },
+    /// A tag now points at `version`, including `dev` following a new version
+    Tagged { key: String, tag: String, version: u64 },
+    Untagged { key: String, tag: String },
+    /// The key was deleted with all of its versions
+    Deleted { key: String },
+}
+
+impl VaultEvent {
+    pub fn key(&self) -> &str {
+        match self {
+            VaultEvent::PromptAdded { key }
+            | VaultEvent::VersionCreated { key, .. }
+            | VaultEvent::Tagged { key, .. }
+            | VaultEvent::Untagged { key, .. }
+            | VaultEvent::Deleted { key } => key,
+        }
+    }
+
+    /// `prompt_added`, `version_created`, `tagged`, `untagged` or `deleted`
+    pub fn name(&self) -> &'static str {
+        match self {
+            VaultEvent::PromptAdded { .. } => "prompt_added",
+            VaultEvent::VersionCreated { .. } => "version_created",
+            VaultEvent::Tagged { .. } => "tagged",
+            VaultEvent::Untagged { .. } => "untagged",
+            VaultEvent::Deleted { .. } => "deleted",
+        }
+    }
+}
+
+impl fmt::Display for VaultEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VaultEvent::PromptAdded { key } => write!(f, "Prompt '{}' was added", key),
+            VaultEvent::VersionCreated { key, version } => {
+                write!(f, "Prompt '{}' has a new version v{}", key, version)
+            }
+            VaultEvent::Tagged { key, tag, version } => {
+                write!(f, "Prompt '{}' v{} was tagged '{}'", key, version, tag)
+            }
+            VaultEvent::Untagged { key, tag } => {
+                write!(f, "Tag '{}' was removed from prompt '{}'", tag, key)
+            }
+            VaultEvent::Deleted { key } => write!(f, "Prompt '{}' was deleted", key),
+        }
+    }
+}
+
+type Listener = Arc<dyn Fn(&VaultEvent) + Send + Sync>;
+
+/// Listeners of a vault, shared by its clones
+#[derive(Default)]
+pub(crate) struct EventBus {
+    listeners: RwLock<Vec<Listener>>,
+}
+
+impl EventBus {
+    pub(crate) fn emit(&self, events: &[VaultEvent]) {
+        if events.is_empty() {
+            return;
+        }
+        let listeners = self.listeners.read().unwrap().clone();
+        for event in events {
+            for listener in &listeners {
+                listener(event);
+            }
+        }
+    }
+}
+
+impl PromptVault {
+    /// Call `listener` with every change committed to this vault or one of its clones
+    /// from now on, e.g. to notify a chat when a prompt is tagged `release`.
+    ///
+    /// Listeners run on the thread that made the change, after it is stored; keep
+    /// them quick and hand slow work such as network calls to another task.
+    pub fn on_event<F>(&self, listener: F)
+    where
+        F: Fn(&VaultEvent) + Send + Sync + 'static,
+    {
+        self.event_bus().listeners.write().unwrap().push(Arc::new(listener));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_vault_events() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        vault.clone().on_event(move |event| seen.lock().unwrap().push(event.clone()));
+        let take = || std::mem::take(&mut *events.lock().unwrap());
+
+        vault.add("writer", "v1")?;
+        assert_eq!(take(), [VaultEvent::PromptAdded { key: "writer".into() }]);
+
+        vault.update("writer", "v2", None)?;
+        vault.tag("writer", "release", 2)?;
+        vault.tag("writer", "release", 2)?;
+        vault.untag("writer", "release")?;
+        let tagged = |tag: &str| VaultEvent::Tagged {
+            key: "writer".into(),
+            tag: tag.into(),
+            version: 2,
+        };
+        assert_eq!(
+            take(),
+            [
+                VaultEvent::VersionCreated {
+                    key: "writer".into(),
+                    version: 2
+                },
+                tagged("dev"),
+                tagged("release"),
+                VaultEvent::Untagged {
+                    key: "writer".into(),
+                    tag: "release".into()
+                },
+            ]
+        );
+        assert_eq!(tagged("release").to_string(), "Prompt 'writer' v2 was tagged 'release'");
+
+        // A failed transaction emits nothing
+        let failed = vault.transaction(|tx| {
+            tx.add("planner", "plan")?;
+            tx.tag("planner", "release", 7)
+        });
+        assert!(failed.is_err());
+        assert!(take().is_empty());
+
+        vault.delete_prompt_key("writer")?;
+        assert_eq!(take(), [VaultEvent::Deleted { key: "writer".into() }]);
+
+        let json = serde_json::to_value(tagged("release"))?;
+        assert_eq!(json["event"], "tagged");
+        assert_eq!(json["version"], 2);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+//! A/B experiments splitting the traffic of a prompt between versions or tags by weight.
+//!
+//! Callers are assigned a variant by hashing the key, the experiment name and their id,
+//! so a user keeps seeing the same variant while the experiment runs.
+//! [`PromptVault::sample`] counts how often each variant was served.
+
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One side of an experiment
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExperimentVariant {
+    /// Version or tag served: `3`, `v3`, `latest` or a tag name
+    pub selector: String,
+    /// Share of the traffic, relative to the weights of the other variants
+    pub weight: u32,
+    /// How often the variant was served
+    pub served: u64,
+}
+
+impl ExperimentVariant {
+    pub fn new(selector: impl Into<String>, weight: u32) -> Self {
+        ExperimentVariant {
+            selector: selector.into(),
+            weight,
+            served: 0,
+        }
+    }
+
+    /// Parse `SELECTOR=WEIGHT`, e.g. `stable=90`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (selector, weight) = spec.rsplit_once('=').ok_or_else(|| {
+            anyhow::anyhow!("Invalid variant '{}'; expected SELECTOR=WEIGHT", spec)
+        })?;
+        let weight = weight.trim().parse().map_err(|_| {
+            anyhow::anyhow!("Invalid weight '{}' for variant '{}'", weight, selector)
+        })?;
+        Ok(ExperimentVariant::new(selector.trim(), weight))
+    }
+}
+
+/// An experiment on a prompt
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Experiment {
+    pub key: String,
+    pub name: String,
+    pub variants: Vec<ExperimentVariant>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Experiment {
+    /// Sum of the weights of the variants
+    pub fn total_weight(&self) -> u64 {
+        self.variants.iter().map(|variant| variant.weight as u64).sum()
+    }
+
+    /// Index of the variant `user_id` is served
+    pub fn pick(&self, user_id: &str) -> usize {
+        let mut hasher = blake3::Hasher::new();
+        for part in [&self.key, &self.name] {
+            hasher.update(part.as_bytes());
+            hasher.update(&[0]);
+        }
+        hasher.update(user_id.as_bytes());
+        let mut bucket_bytes = [0u8; 8];
+        bucket_bytes.copy_from_slice(&hasher.finalize().as_bytes()[..8]);
+
+        let mut bucket = u64::from_le_bytes(bucket_bytes) % self.total_weight().max(1);
+        for (index, variant) in self.variants.iter().enumerate() {
+            if bucket < variant.weight as u64 {
+                return index;
+            }
+            bucket -= variant.weight as u64;
+        }
+        0
+    }
+}
+
+/// What one caller was served by [`PromptVault::sample`]
+#[derive(Debug, Clone)]
+pub struct ExperimentSample {
+    pub experiment: String,
+    /// Selector of the variant served
+    pub variant: String,
+    /// Version the variant resolved to
+    pub version: u64,
+    /// Content of the served version
+    pub content: String,
+}
+
+fn experiment_key(key: &str, name: &str) -> String {
+    format!("experiment:{}:{}", key, name)
+}
+
+impl PromptVault {
+    /// Start experiment `name` on `key`, serving each variant to its share of callers
+    pub fn create_experiment(
+        &self,
+        key: &str,
+        name: &str,
+        variants: Vec<ExperimentVariant>,
+    ) -> Result<Experiment> {
+        if name.is_empty()
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid experiment name '{}'; use letters, digits, '-', '_' and '.'",
+                name
+            ));
+        }
+        if self.get_latest_version_number(key)?.is_none() {
+            return Err(self.unknown_key(key));
+        }
+        if self.experiment(key, name)?.is_some() {
+            return Err(anyhow::anyhow!("Experiment '{}' on '{}' already exists", name, key));
+        }
+        if variants.len() < 2 {
+            return Err(anyhow::anyhow!("Experiments need at least two variants"));
+        }
+        for (index, variant) in variants.iter().enumerate() {
+            if variants[..index].iter().any(|other| other.selector == variant.selector) {
+                return Err(anyhow::anyhow!("Variant '{}' is given twice", variant.selector));
+            }
+            self.resolve_variant(key, &variant.selector)?;
+        }
+
+        let experiment = Experiment {
+            key: key.to_string(),
+            name: name.to_string(),
+            variants: variants
+                .into_iter()
+                .map(|variant| ExperimentVariant { served: 0, ..variant })
+                .collect(),
+            created_at: Utc::now(),
+        };
+        if experiment.total_weight() == 0 {
+            return Err(anyhow::anyhow!("At least one variant needs a weight above 0"));
+        }
+        self.db().insert(
+            experiment_key(key, name).as_bytes(),
+            bincode::serialize(&experiment)?,
+        )?;
+        Ok(experiment)
+    }
+
+    /// Version a variant of an experiment on `key` currently resolves to
+    fn resolve_variant(&self, key: &str, selector: &str) -> Result<u64> {
+        let version = match VersionSelector::parse(selector)? {
+            selector @ (VersionSelector::Latest | VersionSelector::Tag(_)) => {
+                self.resolve_version(key, &selector)?
+            }
+            VersionSelector::Version(version) => version,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Variant '{}' must be a version, a tag or latest",
+                    selector
+                ))
+            }
+        };
+        if self.get_version_meta(key, version)?.is_none() {
+            return Err(anyhow::anyhow!("Version {} of '{}' not found", version, key));
+        }
+        Ok(version)
+    }
+
+    /// Experiment `name` on `key`, if it exists
+    pub fn experiment(&self, key: &str, name: &str) -> Result<Option<Experiment>> {
+        match self.db().get(experiment_key(key, name).as_bytes())? {
+            Some(value) => Ok(Some(bincode::deserialize(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Experiments on `key`, or on every prompt, ordered by key and name
+    pub fn experiments(&self, key: Option<&str>) -> Result<Vec<Experiment>> {
+        let prefix = match key {
+            Some(key) => format!("experiment:{}:", key),
+            None => "experiment:".to_string(),
+        };
+        let mut experiments = Vec::new();
+        for (_, value) in self.scan_entries(prefix.as_bytes())? {
+            let experiment: Experiment = bincode::deserialize(&value)?;
+            // The prefix also matches the experiments of keys starting with `key:`
+            if key.is_none_or(|key| experiment.key == key) {
+                experiments.push(experiment);
+            }
+        }
+        Ok(experiments)
+    }
+
+    /// Stop experiment `name` on `key`; returns whether it existed
+    pub fn delete_experiment(&self, key: &str, name: &str) -> Result<bool> {
+        Ok(self.db().remove(experiment_key(key, name).as_bytes())?.is_some())
+    }
+
+    /// Serve `user_id` its variant of experiment `experiment` on `key`.
+    ///
+    /// The same user id always gets the same variant as long as the variants and
+    /// their weights stay the same; each served variant is counted in the experiment.
+    pub fn sample(&self, key: &str, experiment: &str, user_id: &str) -> Result<ExperimentSample> {
+        let found = self.experiment(key, experiment)?.ok_or_else(|| {
+            anyhow::anyhow!("Experiment '{}' on '{}' not found", experiment, key)
+        })?;
+        let index = found.pick(user_id);
+        let variant = found.variants[index].selector.clone();
+        let version = self.resolve_variant(key, &variant)?;
+        let content = self.get(key, VersionSelector::Version(version))?;
+
+        // Record which variant was served
+        self.db()
+            .update_and_fetch(experiment_key(key, experiment).as_bytes(), |old| {
+                let old = old?;
+                let Ok(mut stored) = bincode::deserialize::<Experiment>(old) else {
+                    return Some(old.to_vec());
+                };
+                if let Some(served) = stored.variants.get_mut(index) {
+                    served.served += 1;
+                }
+                bincode::serialize(&stored).ok().or_else(|| Some(old.to_vec()))
+            })?;
+
+        Ok(ExperimentSample {
+            experiment: experiment.to_string(),
+            variant,
+            version,
+            content,
+        })
+    }
+
+    /// Remove the experiments on a prompt that is being deleted
+    pub(crate) fn remove_experiments(&self, key: &str) -> Result<()> {
+        for (entry, value) in self.scan_entries(format!("experiment:{}:", key).as_bytes())? {
+            let experiment: Experiment = bincode::deserialize(&value)?;
+            if experiment.key == key {
+                self.remove_entry(&entry)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_experiments() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "You write.")?;
+        vault.update("writer", "You write posts.", None)?;
+        vault.tag("writer", "stable", 1)?;
+
+        let variants = vec![ExperimentVariant::parse("stable=3")?, ExperimentVariant::new("v2", 1)];
+        vault.create_experiment("writer", "tone", variants.clone())?;
+        assert!(vault.create_experiment("writer", "tone", variants.clone()).is_err());
+        assert!(vault.create_experiment("writer", "bad name", variants.clone()).is_err());
+        assert!(vault.create_experiment("missing", "tone", variants.clone()).is_err());
+        let unknown = vec![ExperimentVariant::new("stable", 1), ExperimentVariant::new("v9", 1)];
+        assert!(vault.create_experiment("writer", "other", unknown).is_err());
+        assert!(ExperimentVariant::parse("stable").is_err());
+
+        // Users keep their variant, and the weights split the traffic
+        let first = vault.sample("writer", "tone", "user-7")?;
+        let again = vault.sample("writer", "tone", "user-7")?;
+        assert_eq!((first.variant, first.version), (again.variant, again.version));
+        for user in 0..398 {
+            vault.sample("writer", "tone", &format!("user-{}", user))?;
+        }
+        let experiment = vault.experiment("writer", "tone")?.unwrap();
+        let served: Vec<u64> = experiment.variants.iter().map(|v| v.served).collect();
+        assert_eq!(served.iter().sum::<u64>(), 400);
+        assert!(served[0] > 250 && served[1] > 60, "{:?}", served);
+
+        vault.add("writer:draft", "Draft.")?;
+        let variants = vec![ExperimentVariant::new("latest", 1), ExperimentVariant::new("1", 1)];
+        vault.create_experiment("writer:draft", "tone", variants)?;
+        assert_eq!(vault.experiments(Some("writer"))?.len(), 1);
+        assert_eq!(vault.experiments(None)?.len(), 2);
+        vault.delete_prompt_key("writer")?;
+        assert_eq!(vault.experiments(None)?.len(), 1);
+        assert!(vault.delete_experiment("writer:draft", "tone")?);
+        assert!(vault.sample("writer:draft", "tone", "user-1").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,535 @@
+use crate::storage::PromptVault;
+use crate::types::{VersionMeta, VersionSelector};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Human-readable format for `export` and `import`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Yaml,
+    Markdown,
+}
+
+impl ExportFormat {
+    /// Guess the format from a file extension (`.json`, `.yaml`/`.yml`, `.md`)
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(ExportFormat::Json),
+            "yaml" | "yml" => Some(ExportFormat::Yaml),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(ExportFormat::Json),
+            "yaml" | "yml" => Ok(ExportFormat::Yaml),
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            other => Err(anyhow::anyhow!(
+                "Unknown export format '{}', expected 'json', 'yaml' or 'markdown'",
+                other
+            )),
+        }
+    }
+}
+
+/// Every exported prompt of a vault
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VaultExport {
+    pub(crate) prompts: Vec<PromptExport>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PromptExport {
+    pub(crate) key: String,
+    pub(crate) versions: Vec<VersionExport>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VersionExport {
+    pub(crate) version: u64,
+    pub(crate) timestamp: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) validated: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags: Vec<String>,
+    pub(crate) content: String,
+}
+
+impl PromptVault {
+    /// Write every prompt with its full history and tags in a human-readable format.
+    ///
+    /// Password-protected prompts are skipped so their content never leaves the vault
+    /// in plaintext. Returns the number of exported prompts.
+    pub fn export_to<W: Write>(&self, mut writer: W, format: ExportFormat) -> Result<usize> {
+        let export = self.export_prompts()?;
+
+        match format {
+            ExportFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, &export)?;
+                writeln!(writer)?;
+            }
+            ExportFormat::Yaml => serde_yaml::to_writer(&mut writer, &export)?,
+            ExportFormat::Markdown => writer.write_all(to_markdown(&export)?.as_bytes())?,
+        }
+        writer.flush()?;
+
+        Ok(export.prompts.len())
+    }
+
+    /// Write one prompt in a human-readable format.
+    ///
+    /// JSON and YAML hold the prompt's full history and tags as [`export_to`] writes
+    /// them. Markdown is a document for design docs and audits: the stable content (or
+    /// the latest, without a `stable` tag), a metadata table, and a changelog appendix
+    /// with each version's diff against its parent. Password-protected prompts cannot
+    /// be exported.
+    ///
+    /// [`export_to`]: PromptVault::export_to
+    pub fn export_key_to<W: Write>(
+        &self,
+        mut writer: W,
+        key: &str,
+        format: ExportFormat,
+    ) -> Result<()> {
+        if self.is_sensitive(key)? {
+            return Err(anyhow::anyhow!("Prompt '{}' is password-protected", key));
+        }
+        let history = self.history(key)?;
+        if history.is_empty() {
+            return Err(anyhow::anyhow!("Prompt with key '{}' does not exist", key));
+        }
+
+        match format {
+            ExportFormat::Json | ExportFormat::Yaml => {
+                let prompt = self
+                    .export_prompts()?
+                    .prompts
+                    .into_iter()
+                    .find(|prompt| prompt.key == key)
+                    .ok_or_else(|| anyhow::anyhow!("Prompt with key '{}' does not exist", key))?;
+                if format == ExportFormat::Json {
+                    serde_json::to_writer_pretty(&mut writer, &prompt)?;
+                    writeln!(writer)?;
+                } else {
+                    serde_yaml::to_writer(&mut writer, &prompt)?;
+                }
+            }
+            ExportFormat::Markdown => {
+                writer.write_all(self.key_document(key, &history)?.as_bytes())?
+            }
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// The Markdown document [`PromptVault::export_key_to`] writes
+    fn key_document(&self, key: &str, history: &[VersionMeta]) -> Result<String> {
+        let latest = &history[history.len() - 1];
+        let shown = match self.get_version_by_tag(key, "stable")? {
+            Some(version) => (version, format!("Stable version (v{})", version)),
+            None => (
+                latest.version,
+                format!("Latest version (v{})", latest.version),
+            ),
+        };
+        let content = self.get(key, VersionSelector::Version(shown.0))?;
+        let fence = fence_for(&content);
+        let mut out = format!(
+            "# {}\n\n## {}\n\n{}\n{}\n{}\n",
+            key, shown.1, fence, content, fence
+        );
+
+        let mut rows = vec![
+            ("Versions".to_string(), history.len().to_string()),
+            (
+                "Latest".to_string(),
+                format!(
+                    "v{} ({})",
+                    latest.version,
+                    latest.timestamp.format("%Y-%m-%d %H:%M UTC")
+                ),
+            ),
+        ];
+        let tags: Vec<String> = history
+            .iter()
+            .flat_map(|meta| {
+                meta.tags
+                    .iter()
+                    .map(move |tag| format!("{} → v{}", tag, meta.version))
+            })
+            .collect();
+        if !tags.is_empty() {
+            rows.push(("Tags".to_string(), tags.join(", ")));
+        }
+        let meta = self.prompt_meta(key)?;
+        if let Some(description) = meta.description {
+            rows.push(("Description".to_string(), description));
+        }
+        if let Some(author) = meta.author {
+            rows.push(("Author".to_string(), author));
+        }
+        if let Some(model) = meta.model {
+            rows.push(("Model".to_string(), model));
+        }
+        if let Some(temperature) = meta.temperature {
+            rows.push(("Temperature".to_string(), temperature.to_string()));
+        }
+        for (name, value) in meta.labels {
+            rows.push((format!("Label `{}`", name), value));
+        }
+        if let Some(provenance) = self.provenance(key)? {
+            rows.push((
+                "Adopted from".to_string(),
+                format!("{} v{}", provenance.source, provenance.source_version),
+            ));
+        }
+
+        out.push_str("\n## Metadata\n\n| Field | Value |\n| --- | --- |\n");
+        for (field, value) in rows {
+            out.push_str(&format!(
+                "| {} | {} |\n",
+                table_cell(&field),
+                table_cell(&value)
+            ));
+        }
+
+        out.push_str("\n## Appendix: Changelog\n");
+        for meta in history.iter().rev() {
+            out.push_str(&format!(
+                "\n### v{} — {}\n\n",
+                meta.version,
+                meta.timestamp.format("%Y-%m-%d %H:%M UTC")
+            ));
+            if let Some(message) = &meta.message {
+                out.push_str(&format!("{}\n\n", message));
+            }
+            if !meta.tags.is_empty() {
+                out.push_str(&format!("Tags: {}\n\n", meta.tags.join(", ")));
+            }
+            let (language, body) = match meta.parent {
+                Some(parent) => {
+                    let diff = self.diff(
+                        key,
+                        VersionSelector::Version(parent),
+                        VersionSelector::Version(meta.version),
+                    )?;
+                    ("diff", diff.to_unified())
+                }
+                None => {
+                    let content = self.get(key, VersionSelector::Version(meta.version))?;
+                    ("", format!("{}\n", content.trim_end_matches('\n')))
+                }
+            };
+            let fence = fence_for(&body);
+            out.push_str(&format!("{}{}\n{}{}\n", fence, language, body, fence));
+        }
+
+        Ok(out)
+    }
+
+    /// Every prompt that is not password-protected, with its full history and tags
+    pub(crate) fn export_prompts(&self) -> Result<VaultExport> {
+        let mut export = VaultExport {
+            prompts: Vec::new(),
+        };
+
+        for key in self.list_keys()? {
+            if self.is_sensitive(&key)? {
+                continue;
+            }
+            let versions = self
+                .history(&key)?
+                .into_iter()
+                .map(|meta| -> Result<VersionExport> {
+                    Ok(VersionExport {
+                        content: self.get(&key, VersionSelector::Version(meta.version))?,
+                        version: meta.version,
+                        timestamp: meta.timestamp,
+                        message: meta.message,
+                        author: meta.author,
+                        validated: meta.validated,
+                        tags: meta.tags,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            export.prompts.push(PromptExport { key, versions });
+        }
+
+        Ok(export)
+    }
+
+    /// Import prompts written by [`PromptVault::export_to`], keeping their version
+    /// numbers, timestamps, messages and tags.
+    ///
+    /// Fails without importing anything if one of the keys already exists. Returns the
+    /// number of imported prompts.
+    pub fn import_from<R: Read>(&self, mut reader: R, format: ExportFormat) -> Result<usize> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+
+        let export: VaultExport = match format {
+            ExportFormat::Json => serde_json::from_str(&input)?,
+            ExportFormat::Yaml => serde_yaml::from_str(&input)?,
+            ExportFormat::Markdown => from_markdown(&input)?,
+        };
+
+        for prompt in &export.prompts {
+            if self.get_latest_version_number(&prompt.key)?.is_some() {
+                return Err(anyhow::anyhow!("Prompt with key '{}' already exists", prompt.key));
+            }
+            for (expected, version) in (1..).zip(&prompt.versions) {
+                if version.version != expected {
+                    return Err(anyhow::anyhow!(
+                        "Prompt '{}' has version {} where version {} was expected",
+                        prompt.key,
+                        version.version,
+                        expected
+                    ));
+                }
+            }
+        }
+
+        for prompt in &export.prompts {
+            let mut parent_content: Option<&str> = None;
+            for version in &prompt.versions {
+                let parent = version.version.checked_sub(1).filter(|v| *v > 0);
+                let mut meta = VersionMeta::new(
+                    prompt.key.clone(),
+                    version.version,
+                    &version.content,
+                    parent,
+                    version.message.clone(),
+                );
+                meta.timestamp = version.timestamp;
+                meta.author = version.author.clone();
+                meta.validated = version.validated;
+                self.store_version_after(&mut meta, &version.content, parent_content)?;
+                parent_content = Some(&version.content);
+            }
+            for version in &prompt.versions {
+                for tag in &version.tags {
+                    self.tag(&prompt.key, tag, version.version)?;
+                }
+            }
+        }
+
+        Ok(export.prompts.len())
+    }
+}
+
+const MARKDOWN_TITLE: &str = "# PromptPro vault export";
+
+/// Render an export as Markdown: one `##` section per prompt, one `###` section per
+/// version, with the content in a fenced block longer than any backtick run inside it
+fn to_markdown(export: &VaultExport) -> Result<String> {
+    let mut out = format!("{}\n", MARKDOWN_TITLE);
+
+    for prompt in &export.prompts {
+        out.push_str(&format!("\n## {}\n", prompt.key));
+        for version in &prompt.versions {
+            out.push_str(&format!("\n### v{}\n\n", version.version));
+            out.push_str(&format!("- timestamp: {}\n", version.timestamp.to_rfc3339()));
+            if let Some(message) = &version.message {
+                out.push_str(&format!("- message: {}\n", serde_json::to_string(message)?));
+            }
+            if let Some(author) = &version.author {
+                out.push_str(&format!("- author: {}\n", serde_json::to_string(author)?));
+            }
+            if let Some(validated) = version.validated {
+                out.push_str(&format!("- validated: {}\n", validated));
+            }
+            if !version.tags.is_empty() {
+                out.push_str(&format!("- tags: {}\n", version.tags.join(", ")));
+            }
+
+            let fence = fence_for(&version.content);
+            out.push_str(&format!("\n{}\n{}\n{}\n", fence, version.content, fence));
+        }
+    }
+
+    Ok(out)
+}
+
+/// A code fence longer than any backtick run in `content`
+fn fence_for(content: &str) -> String {
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest_run.max(2) + 1)
+}
+
+/// A Markdown table cell
+fn table_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Parse Markdown written by `to_markdown`
+fn from_markdown(input: &str) -> Result<VaultExport> {
+    let mut export = VaultExport {
+        prompts: Vec::new(),
+    };
+    // Split on '\n' only, so content keeps any '\r' characters
+    let mut lines = input.split('\n');
+
+    while let Some(line) = lines.next() {
+        if let Some(key) = line.strip_prefix("## ") {
+            export.prompts.push(PromptExport {
+                key: key.trim().to_string(),
+                versions: Vec::new(),
+            });
+        } else if let Some(number) = line.strip_prefix("### v") {
+            let prompt = export
+                .prompts
+                .last_mut()
+                .ok_or_else(|| anyhow::anyhow!("Version '{}' outside of a prompt section", line))?;
+            prompt.versions.push(VersionExport {
+                version: number.trim().parse()?,
+                timestamp: Utc::now(),
+                message: None,
+                author: None,
+                validated: None,
+                tags: Vec::new(),
+                content: String::new(),
+            });
+        } else if line.starts_with("```") {
+            let version = last_version(&mut export, line)?;
+            let mut content = Vec::new();
+            loop {
+                match lines.next() {
+                    Some(inner) if inner == line => break,
+                    Some(inner) => content.push(inner),
+                    None => return Err(anyhow::anyhow!("Unterminated content block")),
+                }
+            }
+            version.content = content.join("\n");
+        } else if let Some(timestamp) = line.strip_prefix("- timestamp: ") {
+            last_version(&mut export, line)?.timestamp =
+                DateTime::parse_from_rfc3339(timestamp.trim())?.with_timezone(&Utc);
+        } else if let Some(message) = line.strip_prefix("- message: ") {
+            last_version(&mut export, line)?.message = Some(serde_json::from_str(message)?);
+        } else if let Some(author) = line.strip_prefix("- author: ") {
+            last_version(&mut export, line)?.author = Some(serde_json::from_str(author)?);
+        } else if let Some(validated) = line.strip_prefix("- validated: ") {
+            last_version(&mut export, line)?.validated = Some(validated.trim().parse()?);
+        } else if let Some(tags) = line.strip_prefix("- tags: ") {
+            last_version(&mut export, line)?.tags = tags
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+    }
+
+    Ok(export)
+}
+
+fn last_version<'a>(export: &'a mut VaultExport, line: &str) -> Result<&'a mut VersionExport> {
+    export
+        .prompts
+        .last_mut()
+        .and_then(|prompt| prompt.versions.last_mut())
+        .ok_or_else(|| anyhow::anyhow!("'{}' outside of a version section", line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::PromptMeta;
+    use crate::validate::Validator;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_export_import_round_trip() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path().join("source"))?.with_author(Some("ana".into()));
+
+        vault.add("writer", "draft\n```rust\nfn main() {}\n```\n")?;
+        vault.add_validator(Validator::new("any", |_| Ok(())));
+        vault.update("writer", "polished", Some("tone \"fix\"".to_string()))?;
+        vault.tag("writer", "stable", 1)?;
+        vault.add("secret", "hidden")?;
+        vault.protect("secret", "pass")?;
+
+        for (name, format) in [
+            ("json", ExportFormat::Json),
+            ("yaml", ExportFormat::Yaml),
+            ("markdown", ExportFormat::Markdown),
+        ] {
+            let mut buffer = Vec::new();
+            assert_eq!(vault.export_to(&mut buffer, format)?, 1);
+
+            let copy = PromptVault::open(dir.path().join(name))?;
+            assert_eq!(copy.import_from(buffer.as_slice(), format)?, 1);
+            assert_eq!(copy.list_keys()?, ["writer"]);
+            assert_eq!(
+                copy.get("writer", VersionSelector::tag("stable"))?,
+                vault.get("writer", VersionSelector::Version(1))?
+            );
+            assert_eq!(copy.get("writer", VersionSelector::tag("dev"))?, "polished");
+
+            let history = copy.history("writer")?;
+            let original = vault.history("writer")?;
+            assert_eq!(history[1].message, original[1].message);
+            assert_eq!(history[1].author.as_deref(), Some("ana"));
+            assert_eq!((history[0].validated, history[1].validated), (None, Some(true)));
+            assert_eq!(history[0].timestamp, original[0].timestamp);
+
+            // Existing keys are never overwritten
+            assert!(copy.import_from(buffer.as_slice(), format).is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_key_document() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "You write.\nBe brief.\n")?;
+        vault.update("writer", "You write posts.\nBe brief.\n", Some("posts".into()))?;
+        vault.update("writer", "You write anything.\nBe brief.\n", None)?;
+        vault.tag("writer", "stable", 2)?;
+        vault.update_prompt_meta(
+            "writer",
+            PromptMeta {
+                author: Some("ana | ops".to_string()),
+                ..PromptMeta::default()
+            },
+        )?;
+
+        let mut buffer = Vec::new();
+        vault.export_key_to(&mut buffer, "writer", ExportFormat::Markdown)?;
+        let document = String::from_utf8(buffer)?;
+        let stable = "# writer\n\n## Stable version (v2)\n\n```\nYou write posts.\nBe brief.\n\n```";
+        assert!(document.starts_with(stable));
+        assert!(document.contains("| Author | ana \\| ops |"));
+        assert!(document.contains("| Tags | stable → v2, dev → v3 |"));
+        let changelog = &document[document.find("## Appendix: Changelog").unwrap()..];
+        assert!(changelog.find("### v3").unwrap() < changelog.find("### v2").unwrap());
+        assert!(changelog.contains("posts\n\nTags: stable\n\n```diff\n--- writer@1\n+++ writer@2\n"));
+        assert!(changelog.contains("-You write.\n+You write posts.\n"));
+
+        let mut buffer = Vec::new();
+        vault.export_key_to(&mut buffer, "writer", ExportFormat::Json)?;
+        let prompt: PromptExport = serde_json::from_slice(&buffer)?;
+        assert_eq!(prompt.versions.len(), 3);
+        assert!(vault.export_key_to(Vec::new(), "missing", ExportFormat::Markdown).is_err());
+
+        Ok(())
+    }
+}
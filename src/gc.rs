@@ -0,0 +1,458 @@
+//! Pruning old versions and orphaned entries, for `promptpro gc`.
+//!
+//! Versions outside a [`RetentionPolicy`] are removed with their content, diff and
+//! tags. A kept version stored as a diff against a removed parent is rewritten as a
+//! snapshot in the same transaction, so every kept version still reads back the same
+//! content, even after a crash. Reference counts of shared content are then recounted
+//! from the versions left, and content, diffs, objects and per-key records no version
+//! uses are removed.
+
+use crate::comments::Comment;
+use crate::dump::RestorePolicy;
+use crate::eval::TestCase;
+use crate::experiment::Experiment;
+use crate::score::Score;
+use crate::storage::PromptVault;
+use crate::types::{VersionMeta, VersionSelector};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+/// Which versions `gc` keeps. The latest version of a key is always kept, and without
+/// `keep_last` or `keep_newer_than` no version is removed at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Keep this many of the latest versions of each key
+    pub keep_last: Option<usize>,
+    /// Keep versions that have a tag
+    pub keep_tagged: bool,
+    /// Keep versions written at or after this time
+    pub keep_newer_than: Option<DateTime<Utc>>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            keep_last: None,
+            keep_tagged: true,
+            keep_newer_than: None,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Whether the policy removes versions, rather than only orphaned entries
+    pub fn prunes_versions(&self) -> bool {
+        self.keep_last.is_some() || self.keep_newer_than.is_some()
+    }
+
+    /// Whether to keep `meta`, the `from_latest`-th version counting back from the
+    /// latest (0 for the latest)
+    fn keeps(&self, meta: &VersionMeta, from_latest: usize) -> bool {
+        from_latest == 0
+            || !self.prunes_versions()
+            || (self.keep_tagged && !meta.tags.is_empty())
+            || self.keep_last.is_some_and(|n| from_latest < n)
+            || self.keep_newer_than.is_some_and(|time| meta.timestamp >= time)
+    }
+}
+
+/// What `gc` removed
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GcReport {
+    /// Removed versions, by key
+    pub pruned: BTreeMap<String, Vec<u64>>,
+    /// Kept versions rewritten as snapshots because their parent was removed
+    pub rebased: usize,
+    /// Content, diff, object and per-key entries no version used
+    pub orphans: usize,
+    /// Password-protected prompts, whose versions are never removed
+    pub skipped: Vec<String>,
+}
+
+impl GcReport {
+    pub fn pruned_versions(&self) -> usize {
+        self.pruned.values().map(Vec::len).sum()
+    }
+}
+
+/// Records kept per key next to its versions, which `delete` removes after them: canary
+/// counters, the password-protection marker, provenance, metadata, notes and the
+/// comment counter
+const KEY_RECORD_PREFIXES: &[&str] =
+    &["canary:", "sensitive:", "provenance:", "prompt_meta:", "notes:", "comment_id:"];
+
+/// Entries no version uses, and reference counts that disagree with the versions
+#[derive(Debug, Default)]
+pub(crate) struct Orphans {
+    /// Content, diff, per-key record, object and reference count entries
+    pub entries: Vec<String>,
+    /// Hashes of objects with the number of versions actually referencing them
+    pub refcounts: Vec<(String, u64)>,
+}
+
+/// Key and version of a `content:` or `diff:` entry
+fn entry_version(entry: &[u8], prefix: &str) -> Option<(String, u64)> {
+    let entry = std::str::from_utf8(entry).ok()?.strip_prefix(prefix)?;
+    let (key, version) = entry.rsplit_once(':')?;
+    Some((key.to_string(), version.parse().ok()?))
+}
+
+impl PromptVault {
+    /// Versions `policy` would remove, by key, without changing anything
+    pub fn gc_preview(&self, policy: &RetentionPolicy) -> Result<BTreeMap<String, Vec<u64>>> {
+        let mut pruned = BTreeMap::new();
+        if !policy.prunes_versions() {
+            return Ok(pruned);
+        }
+        for key in self.list_keys()? {
+            if self.is_sensitive(&key)? {
+                continue;
+            }
+            let history = self.history(&key)?;
+            let versions: Vec<u64> = history
+                .iter()
+                .rev()
+                .enumerate()
+                .filter(|(from_latest, meta)| !policy.keeps(meta, *from_latest))
+                .map(|(_, meta)| meta.version)
+                .rev()
+                .collect();
+            if !versions.is_empty() {
+                pruned.insert(key, versions);
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Remove the versions outside `policy`, then every orphaned entry (see the
+    /// [module docs](self))
+    pub fn gc(&self, policy: &RetentionPolicy) -> Result<GcReport> {
+        let mut report = GcReport::default();
+        for key in self.list_keys()? {
+            if policy.prunes_versions() && self.is_sensitive(&key)? {
+                report.skipped.push(key);
+            }
+        }
+
+        report.pruned = self.gc_preview(policy)?;
+        for (key, versions) in &report.pruned {
+            report.rebased += self.prune_versions(key, versions)?;
+        }
+        let orphans = self.find_orphans()?;
+        report.orphans = orphans.entries.len();
+        self.remove_orphans(&orphans)?;
+        self.db().flush()?;
+        Ok(report)
+    }
+
+    /// Remove `versions` of `key`, rewriting kept versions that depend on them as
+    /// snapshots, in one transaction. Returns the number of versions rewritten.
+    fn prune_versions(&self, key: &str, versions: &[u64]) -> Result<usize> {
+        let removed: BTreeSet<u64> = versions.iter().copied().collect();
+        let rebased = self.atomically(|vault| {
+            // Versions are read through the transaction, so it runs again when another
+            // writer changes one of them
+            let mut history = Vec::new();
+            for meta in vault.history(key)? {
+                history.extend(vault.get_version_meta(key, meta.version)?);
+            }
+
+            // Read the content of orphaned diffs while their parents still exist
+            let mut rebase = Vec::new();
+            for meta in &history {
+                let orphaned = meta.parent.is_some_and(|parent| removed.contains(&parent));
+                if !removed.contains(&meta.version) && orphaned {
+                    let content = vault.get(key, VersionSelector::Version(meta.version))?;
+                    rebase.push((meta.clone(), content));
+                }
+            }
+
+            for (mut meta, content) in rebase.iter().cloned() {
+                meta.parent = None;
+                meta.snapshot = true;
+                vault.share_snapshot(&meta, &content)?;
+                vault.remove_entry(format!("diff:{}:{}", key, meta.version).as_bytes())?;
+                vault.update_version_meta(&meta)?;
+            }
+            for meta in history.iter().filter(|meta| removed.contains(&meta.version)) {
+                vault.remove_version(meta)?;
+            }
+            vault.unindex_versions(key)?;
+            Ok(rebase.len())
+        })?;
+        self.index_cache().forget(key);
+        Ok(rebased)
+    }
+
+    /// Remove one version with its content, diff and tags
+    pub(crate) fn remove_version(&self, meta: &VersionMeta) -> Result<()> {
+        let (key, version) = (&meta.key, meta.version);
+        self.remove_entry(format!("version:{}:{}", key, version).as_bytes())?;
+        if self.references_object(meta)? {
+            self.release_object(&meta.object_hash)?;
+        }
+        self.remove_entry(format!("content:{}:{}", key, version).as_bytes())?;
+        self.remove_entry(format!("diff:{}:{}", key, version).as_bytes())?;
+        for tag in &meta.tags {
+            self.remove_entry(format!("tag:{}:{}", key, tag).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Content and diffs of missing versions, records of keys without versions, objects
+    /// no version references, and reference counts that disagree with the versions.
+    /// An interrupted delete leaves such entries behind.
+    pub(crate) fn find_orphans(&self) -> Result<Orphans> {
+        let mut orphans = Orphans::default();
+        for prefix in ["content:", "diff:"] {
+            for entry in self.db().scan_prefix(prefix.as_bytes()).keys() {
+                let entry = entry?;
+                // Entries without a valid version belong to no version either
+                let used = match entry_version(&entry, prefix) {
+                    Some((key, version)) => {
+                        let version = format!("version:{}:{}", key, version);
+                        self.read_checked_entry(version.as_bytes())?.is_some()
+                    }
+                    None => false,
+                };
+                if !used {
+                    orphans.entries.push(String::from_utf8_lossy(&entry).into_owned());
+                }
+            }
+        }
+
+        // Comments go with the version they are about
+        for entry in self.db().scan_prefix(b"comment:") {
+            let (entry, value) = entry?;
+            let used = match bincode::deserialize::<Comment>(&value) {
+                Ok(comment) => self.get_version_meta(&comment.key, comment.version)?.is_some(),
+                Err(_) => false,
+            };
+            if !used {
+                orphans.entries.push(String::from_utf8_lossy(&entry).into_owned());
+            }
+        }
+
+        // Experiments are stored by key and name
+        for entry in self.db().scan_prefix(b"experiment:") {
+            let (entry, value) = entry?;
+            let used = match bincode::deserialize::<Experiment>(&value) {
+                Ok(experiment) => self.get_latest_version_number(&experiment.key)?.is_some(),
+                Err(_) => false,
+            };
+            if !used {
+                orphans.entries.push(String::from_utf8_lossy(&entry).into_owned());
+            }
+        }
+
+        // Scores go with the version they are about
+        for entry in self.db().scan_prefix(b"score:") {
+            let (entry, value) = entry?;
+            let used = match bincode::deserialize::<Score>(&value) {
+                Ok(score) => self.get_version_meta(&score.key, score.version)?.is_some(),
+                Err(_) => false,
+            };
+            if !used {
+                orphans.entries.push(String::from_utf8_lossy(&entry).into_owned());
+            }
+        }
+
+        // Test cases, like experiments, are stored by key and name
+        for entry in self.db().scan_prefix(b"test_case:") {
+            let (entry, value) = entry?;
+            let used = match bincode::deserialize::<TestCase>(&value) {
+                Ok(case) => self.get_latest_version_number(&case.key)?.is_some(),
+                Err(_) => false,
+            };
+            if !used {
+                orphans.entries.push(String::from_utf8_lossy(&entry).into_owned());
+            }
+        }
+
+        // Aliases are stored by their own name and hold the key they name
+        for entry in self.db().scan_prefix(b"alias:") {
+            let (entry, key) = entry?;
+            let used = match std::str::from_utf8(&key) {
+                Ok(key) => self.get_latest_version_number(key)?.is_some(),
+                Err(_) => false,
+            };
+            if !used {
+                orphans.entries.push(String::from_utf8_lossy(&entry).into_owned());
+            }
+        }
+
+        for prefix in KEY_RECORD_PREFIXES {
+            for entry in self.db().scan_prefix(prefix.as_bytes()).keys() {
+                let entry = String::from_utf8_lossy(&entry?).into_owned();
+                let key = &entry[prefix.len()..];
+                // Canary counters are stored per key and tag
+                let key = match *prefix {
+                    "canary:" => key.rsplit_once(':').map_or(key, |(key, _)| key),
+                    _ => key,
+                };
+                if self.get_latest_version_number(key)?.is_none() {
+                    orphans.entries.push(entry);
+                }
+            }
+        }
+
+        // Objects and counts are read before the versions: a version stored in between
+        // changes a count read here, which makes a transaction recounting them run again
+        let mut stored = Vec::new();
+        for prefix in ["object:", "objref:"] {
+            for entry in self.db().scan_prefix(prefix.as_bytes()).keys() {
+                let entry = entry?;
+                if let Some(value) = self.read_entry(&entry)? {
+                    stored.push((prefix, String::from_utf8_lossy(&entry).into_owned(), value));
+                }
+            }
+        }
+        let mut references: HashMap<String, u64> = HashMap::new();
+        for entry in self.db().scan_prefix(b"version:").keys() {
+            let Some(value) = self.read_checked_entry(&entry?)? else {
+                continue;
+            };
+            let meta = VersionMeta::decode(&value)?;
+            if self.references_object(&meta)? {
+                *references.entry(meta.object_hash).or_default() += 1;
+            }
+        }
+        for (prefix, entry, value) in stored {
+            let hash = &entry[prefix.len()..];
+            match references.get(hash) {
+                None => orphans.entries.push(entry),
+                Some(&count) if prefix == "objref:" => {
+                    let stored = value.as_ref().try_into().map(u64::from_le_bytes).ok();
+                    if stored != Some(count) {
+                        orphans.refcounts.push((hash.to_string(), count));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(orphans)
+    }
+
+    /// Remove the entries of `orphans` and correct its reference counts. The vault is
+    /// searched again in one transaction with the removals, and only what is still
+    /// orphaned is changed, so a version stored since `orphans` was found keeps its
+    /// content and its reference.
+    pub(crate) fn remove_orphans(&self, orphans: &Orphans) -> Result<()> {
+        let entries: HashSet<&String> = orphans.entries.iter().collect();
+        self.atomically(|vault| {
+            let current = vault.find_orphans()?;
+            for entry in current.entries.iter().filter(|entry| entries.contains(entry)) {
+                vault.remove_entry(entry.as_bytes())?;
+            }
+            for refcount in &current.refcounts {
+                if orphans.refcounts.contains(refcount) {
+                    let (hash, count) = refcount;
+                    vault.write_entry(format!("objref:{}", hash).as_bytes(), &count.to_le_bytes())?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Rewrite the vault at `path` into a fresh directory, so sled gives back the
+    /// space of removed entries. Returns the size on disk before and after.
+    pub fn compact<P: AsRef<Path>>(path: P) -> Result<(u64, u64)> {
+        let path = path.as_ref();
+        let parent = path.parent().unwrap_or_else(|| Path::new("."));
+        let dump = tempfile::NamedTempFile::new_in(parent)?;
+        let dump_path = dump.path().to_string_lossy().into_owned();
+
+        let before = {
+            let vault = Self::open(path)?;
+            // Entries are copied as stored, so an encrypted vault stays encrypted
+            vault.dump(&dump_path, None)?;
+            vault.db().size_on_disk()?
+        };
+        let vault = Self::restore_to(&dump_path, path, None, RestorePolicy::Overwrite)?;
+        Ok((before, vault.db().size_on_disk()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::calculate_hash;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_gc() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        vault.add("writer", "draft 0\n")?;
+        for step in 1..15 {
+            vault.update("writer", &format!("draft {}\n", step), None)?;
+        }
+        vault.tag("writer", "stable", 3)?;
+        vault.comment("writer", 5, "alice", "Goes with v5")?;
+        vault.comment("writer", 13, "bob", "Stays with v13")?;
+        vault.add("planner", "plan\n")?;
+        let expected: Vec<String> = (12..=15)
+            .map(|v| vault.get("writer", VersionSelector::Version(v)))
+            .collect::<Result<_>>()?;
+
+        // Without a retention rule only orphans go
+        vault.db().insert("diff:ghost:3", b"stale".as_slice())?;
+        let report = vault.gc(&RetentionPolicy::default())?;
+        assert_eq!((report.pruned_versions(), report.orphans), (0, 1));
+
+        let policy = RetentionPolicy {
+            keep_last: Some(4),
+            ..RetentionPolicy::default()
+        };
+        assert_eq!(vault.gc_preview(&policy)?["writer"].len(), 10);
+        let report = vault.gc(&policy)?;
+        assert_eq!(report.pruned_versions(), 10);
+        assert!(!report.pruned.contains_key("planner"));
+        assert_eq!(report.rebased, 2);
+
+        let kept: Vec<u64> = vault.history("writer")?.iter().map(|m| m.version).collect();
+        assert_eq!(kept, [3, 12, 13, 14, 15]);
+        let comments = vault.key_comments("writer")?;
+        assert_eq!(comments.iter().map(|c| c.version).collect::<Vec<_>>(), [13]);
+        assert_eq!(vault.get("writer", VersionSelector::tag("stable"))?, "draft 2\n");
+        for (version, content) in (12..=15).zip(&expected) {
+            assert_eq!(&vault.get("writer", VersionSelector::Version(version))?, content);
+        }
+        vault.update("writer", "draft 15\n", None)?;
+        assert_eq!(vault.get("writer", VersionSelector::Latest)?, "draft 15\n");
+        assert_eq!(vault.gc(&RetentionPolicy::default())?.orphans, 0);
+
+        // Dropping tagged versions too
+        let policy = RetentionPolicy {
+            keep_last: Some(1),
+            keep_tagged: false,
+            keep_newer_than: None,
+        };
+        vault.gc(&policy)?;
+        assert_eq!(vault.history("writer")?.len(), 1);
+        assert!(vault.get("writer", VersionSelector::tag("stable")).is_err());
+
+        let (before, after) = {
+            drop(vault);
+            PromptVault::compact(dir.path().join("vault"))?
+        };
+        assert!(before > 0 && after > 0);
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        assert_eq!(vault.get("planner", VersionSelector::Latest)?, "plan\n");
+
+        // A wrong count is left alone when a version stored since it was found changes it
+        let refcount = format!("objref:{}", calculate_hash("plan\n"));
+        vault.db().insert(refcount.as_bytes(), &5u64.to_le_bytes())?;
+        let orphans = vault.find_orphans()?;
+        vault.add("copy", "plan\n")?;
+        vault.remove_orphans(&orphans)?;
+        vault.delete_prompt_key("planner")?;
+        assert_eq!(vault.get("copy", VersionSelector::Latest)?, "plan\n");
+        assert_eq!(vault.gc(&RetentionPolicy::default())?.orphans, 0);
+
+        Ok(())
+    }
+}
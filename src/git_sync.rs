@@ -0,0 +1,424 @@
+//! A Git repository mirroring the vault, for `promptpro git-sync`: every prompt is a
+//! `<key>.txt` file and every version a commit, so history can be reviewed with Git
+//! tooling while prompts are still written with promptpro.
+//!
+//! Syncing compares both sides with what they held at the previous sync, kept in the
+//! vault per repository. Prompts changed in the vault get one commit per new version,
+//! with the version's author, time and message; files changed in commits get a new
+//! version. A prompt changed on both sides is a conflict unless a side is preferred.
+//! Deleting a prompt removes its file, while a file removed in Git keeps its prompt.
+
+use crate::import_dir::FileChange;
+use crate::storage::PromptVault;
+use crate::types::{key_file_path, VersionSelector};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use git2::{ObjectType, Oid, Repository, Signature, Status, Tree, TreeWalkMode, TreeWalkResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Extension of the prompt files of the repository
+pub const GIT_PROMPT_EXTENSION: &str = "txt";
+
+/// Email of the commits promptpro writes; the name is the author of the version
+const COMMIT_EMAIL: &str = "promptpro@localhost";
+
+/// Which side wins for prompts changed both in the vault and in the repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncSide {
+    Vault,
+    Git,
+}
+
+impl std::str::FromStr for SyncSide {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "vault" => Ok(SyncSide::Vault),
+            "git" => Ok(SyncSide::Git),
+            other => Err(anyhow::anyhow!("Unknown side '{}', expected 'vault' or 'git'", other)),
+        }
+    }
+}
+
+/// What a sync did
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GitSyncReport {
+    /// Keys that got a new version from the repository
+    pub from_git: Vec<String>,
+    /// Versions committed to the repository, as `key v3`
+    pub committed: Vec<String>,
+    /// Keys of deleted prompts whose file was removed
+    pub removed: Vec<String>,
+    /// Keys whose file was removed in the repository; the prompts are kept
+    pub removed_in_git: Vec<String>,
+    /// Password-protected keys, which are never written to or read from the repository
+    pub protected: Vec<String>,
+}
+
+/// What both sides held at the previous sync
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct SyncState {
+    commit: Option<String>,
+    /// Latest version of each prompt
+    versions: BTreeMap<String, u64>,
+}
+
+/// A change to write to the repository
+enum Commit {
+    Version {
+        key: String,
+        version: u64,
+        timestamp: DateTime<Utc>,
+    },
+    Delete(String),
+}
+
+fn state_key(workdir: &Path) -> String {
+    format!("git_sync:{}", workdir.display())
+}
+
+/// File of `key` in the repository; fails for keys that would leave the work tree
+fn file_path(key: &str) -> Result<PathBuf> {
+    key_file_path(key, GIT_PROMPT_EXTENSION)
+}
+
+/// The prompt files of a tree by key, skipping hidden files and directories
+fn tree_prompts(repo: &Repository, tree: Option<&Tree>) -> Result<BTreeMap<String, String>> {
+    let mut prompts = BTreeMap::new();
+    let Some(tree) = tree else {
+        return Ok(prompts);
+    };
+    let suffix = format!(".{}", GIT_PROMPT_EXTENSION);
+    tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        let name = entry.name().unwrap_or(".");
+        if name.starts_with('.') {
+            return TreeWalkResult::Skip;
+        }
+        if entry.kind() != Some(ObjectType::Blob) {
+            return TreeWalkResult::Ok;
+        }
+        let Some(stem) = name.strip_suffix(suffix.as_str()) else {
+            return TreeWalkResult::Ok;
+        };
+        if let Ok(blob) = repo.find_blob(entry.id()) {
+            if let Ok(content) = std::str::from_utf8(blob.content()) {
+                prompts.insert(format!("{}{}", dir, stem), content.to_string());
+            }
+        }
+        TreeWalkResult::Ok
+    })?;
+    Ok(prompts)
+}
+
+/// Fail if the repository has staged changes, or uncommitted changes to `paths`, which
+/// committing would take along or overwrite
+fn check_clean(repo: &Repository, paths: &[PathBuf]) -> Result<()> {
+    let staged = Status::INDEX_NEW
+        | Status::INDEX_MODIFIED
+        | Status::INDEX_DELETED
+        | Status::INDEX_RENAMED
+        | Status::INDEX_TYPECHANGE;
+    for entry in repo.statuses(None)?.iter() {
+        if entry.status().intersects(staged) {
+            return Err(anyhow::anyhow!(
+                "The repository has staged changes ({}); commit or unstage them first",
+                entry.path().unwrap_or_default()
+            ));
+        }
+    }
+    for path in paths {
+        let status = repo.status_file(path).unwrap_or(Status::CURRENT);
+        if !status.is_empty() && status != Status::IGNORED {
+            return Err(anyhow::anyhow!(
+                "{} has uncommitted changes; commit or discard them first",
+                path.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl PromptVault {
+    /// Reconcile the vault with the Git repository at `path`, creating the repository
+    /// if needed. Fails without changing either side when a prompt changed on both
+    /// sides since the previous sync and `prefer` is `None`.
+    pub fn git_sync<P: AsRef<Path>>(
+        &self,
+        path: P,
+        prefer: Option<SyncSide>,
+    ) -> Result<GitSyncReport> {
+        let path = path.as_ref();
+        let repo = match Repository::open(path) {
+            Ok(repo) => repo,
+            Err(_) => {
+                std::fs::create_dir_all(path)?;
+                Repository::init(path)?
+            }
+        };
+        let workdir = repo
+            .workdir()
+            .ok_or_else(|| anyhow::anyhow!("{} is a bare repository", path.display()))?
+            .canonicalize()?;
+        let state: SyncState = match self.db().get(state_key(&workdir).as_bytes())? {
+            Some(bytes) => bincode::deserialize(&bytes)?,
+            None => SyncState::default(),
+        };
+
+        let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let head_tree = head.as_ref().map(|commit| commit.tree()).transpose()?;
+        // A commit missing after a history rewrite makes every file count as changed
+        let base_tree = state
+            .commit
+            .as_deref()
+            .and_then(|id| Oid::from_str(id).ok())
+            .and_then(|oid| repo.find_commit(oid).ok())
+            .map(|commit| commit.tree())
+            .transpose()?;
+        let git_now = tree_prompts(&repo, head_tree.as_ref())?;
+        let git_base = tree_prompts(&repo, base_tree.as_ref())?;
+
+        let mut latest = BTreeMap::new();
+        for key in self.list_keys()? {
+            if let Some(version) = self.get_latest_version_number(&key)? {
+                latest.insert(key, version);
+            }
+        }
+
+        let mut report = GitSyncReport::default();
+        let mut from_git = Vec::new();
+        let mut to_git = Vec::new();
+        let mut conflicts = Vec::new();
+        let keys: BTreeSet<&String> = git_now
+            .keys()
+            .chain(git_base.keys())
+            .chain(latest.keys())
+            .chain(state.versions.keys())
+            .collect();
+        for key in keys {
+            let git_changed = git_now.get(key) != git_base.get(key);
+            let vault_changed = latest.get(key) != state.versions.get(key);
+            if latest.contains_key(key) && self.is_sensitive(key)? {
+                if git_changed {
+                    report.protected.push(key.clone());
+                }
+                continue;
+            }
+            let side = match (git_changed, vault_changed) {
+                (false, false) => continue,
+                (true, false) => SyncSide::Git,
+                (false, true) => SyncSide::Vault,
+                (true, true) => {
+                    let content = if latest.contains_key(key) {
+                        Some(self.get(key, VersionSelector::Latest)?)
+                    } else {
+                        None
+                    };
+                    if content.as_ref() == git_now.get(key) {
+                        continue;
+                    }
+                    match prefer {
+                        Some(side) => side,
+                        None => {
+                            conflicts.push(key.as_str());
+                            continue;
+                        }
+                    }
+                }
+            };
+            match (side, git_now.get(key)) {
+                (SyncSide::Git, Some(content)) => from_git.push((key.clone(), content)),
+                (SyncSide::Git, None) => report.removed_in_git.push(key.clone()),
+                (SyncSide::Vault, _) => to_git.push(key.clone()),
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Changed in both the vault and the repository since the last sync: {}; \
+                 pass --prefer vault or --prefer git",
+                conflicts.join(", ")
+            ));
+        }
+
+        let mut commits = Vec::new();
+        for key in &to_git {
+            let Some(&version) = latest.get(key) else {
+                commits.push(Commit::Delete(key.clone()));
+                continue;
+            };
+            // Without a previous sync, a file already in the repository only gets the
+            // latest version rather than the whole history
+            let synced = match state.versions.get(key) {
+                Some(&synced) => synced,
+                None if git_now.contains_key(key) => version - 1,
+                None => 0,
+            };
+            for meta in self.history(key)? {
+                if meta.version > synced {
+                    commits.push(Commit::Version {
+                        key: key.clone(),
+                        version: meta.version,
+                        timestamp: meta.timestamp,
+                    });
+                }
+            }
+        }
+        commits.sort_by_key(|commit| match commit {
+            Commit::Version { timestamp, .. } => Some(*timestamp),
+            Commit::Delete(_) => None,
+        });
+        // Before changing either side, so an unsafe key leaves both as they were
+        let paths: Vec<PathBuf> = to_git.iter().map(|key| file_path(key)).collect::<Result<_>>()?;
+        check_clean(&repo, &paths)?;
+
+        if let Some(head) = &head {
+            let summary = head.summary().unwrap_or_default().to_string();
+            let id = head.id().to_string();
+            for (key, content) in from_git {
+                let message = |_: &str, _: &str| format!("git {}: {}", &id[..7], summary);
+                match self.sync_prompt(&key, content, true, message)? {
+                    FileChange::Created | FileChange::Updated => report.from_git.push(key),
+                    FileChange::Protected => report.protected.push(key),
+                    FileChange::Unchanged => {}
+                }
+            }
+        }
+
+        let mut index = repo.index()?;
+        for commit in commits {
+            let (relative, message, signature) = match &commit {
+                Commit::Version {
+                    key,
+                    version,
+                    timestamp,
+                } => {
+                    let meta = self.get_version_meta(key, *version)?.ok_or_else(|| {
+                        anyhow::anyhow!("Version {} of '{}' not found", version, key)
+                    })?;
+                    let relative = file_path(key)?;
+                    let file = workdir.join(&relative);
+                    if let Some(parent) = file.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::write(&file, self.get(key, VersionSelector::Version(*version))?)?;
+                    index.add_path(&relative)?;
+                    let mut message = format!("{} v{}", key, version);
+                    if let Some(text) = &meta.message {
+                        message.push_str(&format!("\n\n{}", text));
+                    }
+                    let name = meta.author.as_deref().unwrap_or("promptpro");
+                    let time = git2::Time::new(timestamp.timestamp(), 0);
+                    report.committed.push(format!("{} v{}", key, version));
+                    (relative, message, Signature::new(name, COMMIT_EMAIL, &time)?)
+                }
+                Commit::Delete(key) => {
+                    let relative = file_path(key)?;
+                    let file = workdir.join(&relative);
+                    if file.exists() {
+                        std::fs::remove_file(&file)?;
+                    }
+                    index.remove_path(&relative)?;
+                    report.removed.push(key.clone());
+                    let signature = Signature::now("promptpro", COMMIT_EMAIL)?;
+                    (relative, format!("Delete {}", key), signature)
+                }
+            };
+            index.write()?;
+            let tree = repo.find_tree(index.write_tree()?)?;
+            let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+            if parent.as_ref().is_some_and(|parent| parent.tree_id() == tree.id()) {
+                // Nothing changed, e.g. a deleted prompt whose file was never committed
+                continue;
+            }
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &parents)
+                .map_err(|e| anyhow::anyhow!("Cannot commit {}: {}", relative.display(), e))?;
+        }
+
+        let mut synced = SyncState {
+            commit: repo.head().ok().and_then(|head| head.target()).map(|id| id.to_string()),
+            versions: BTreeMap::new(),
+        };
+        for key in self.list_keys()? {
+            if let Some(version) = self.get_latest_version_number(&key)? {
+                synced.versions.insert(key, version);
+            }
+        }
+        self.db()
+            .insert(state_key(&workdir).as_bytes(), bincode::serialize(&synced)?)?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Commit `content` as the file of `key`, as someone editing the repository would
+    fn commit_file(repo: &Repository, key: &str, content: &str) -> Result<()> {
+        let workdir = repo.workdir().unwrap();
+        let relative = file_path(key)?;
+        std::fs::create_dir_all(workdir.join(&relative).parent().unwrap())?;
+        std::fs::write(workdir.join(&relative), content)?;
+        let mut index = repo.index()?;
+        index.add_path(&relative)?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let parent = repo.head()?.peel_to_commit()?;
+        let signature = Signature::now("ada", "ada@example.com")?;
+        repo.commit(Some("HEAD"), &signature, &signature, "Edit in git", &tree, &[&parent])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_git_sync() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        let path = dir.path().join("repo");
+        vault.add("agents/writer", "Write.")?;
+        vault.update("agents/writer", "Write well.", Some("Better".to_string()))?;
+        vault.add("greeting", "Hi.")?;
+
+        let report = vault.git_sync(&path, None)?;
+        assert_eq!(report.committed, ["agents/writer v1", "agents/writer v2", "greeting v1"]);
+        let repo = Repository::open(&path)?;
+        let head = repo.head()?.peel_to_commit()?;
+        assert_eq!(head.message(), Some("greeting v1"));
+        assert_eq!(head.parent(0)?.message(), Some("agents/writer v2\n\nBetter"));
+        assert_eq!(std::fs::read_to_string(path.join("agents/writer.txt"))?, "Write well.");
+        assert_eq!(vault.git_sync(&path, None)?, GitSyncReport::default());
+
+        // Both directions at once
+        commit_file(&repo, "greeting", "Hello.")?;
+        commit_file(&repo, "farewell", "Bye.")?;
+        vault.update("agents/writer", "Write very well.", None)?;
+        let report = vault.git_sync(&path, None)?;
+        assert_eq!(report.from_git, ["farewell", "greeting"]);
+        assert_eq!(report.committed, ["agents/writer v3"]);
+        assert_eq!(vault.get("greeting", VersionSelector::Latest)?, "Hello.");
+
+        // Conflicts need a side
+        commit_file(&repo, "greeting", "Hello there.")?;
+        vault.update("greeting", "Hey.", None)?;
+        assert!(vault.git_sync(&path, None).is_err());
+        let report = vault.git_sync(&path, Some(SyncSide::Vault))?;
+        assert_eq!(report.committed, ["greeting v3"]);
+        assert_eq!(std::fs::read_to_string(path.join("greeting.txt"))?, "Hey.");
+
+        vault.delete_prompt_key("farewell")?;
+        assert_eq!(vault.git_sync(&path, None)?.removed, ["farewell"]);
+        assert!(!path.join("farewell.txt").exists());
+
+        // Keys that would leave the work tree fail the sync before anything is written
+        vault.add("../escape", "Out of reach.")?;
+        let error = vault.git_sync(&path, None).unwrap_err().to_string();
+        assert!(error.contains("cannot be written as a file"));
+        assert!(!dir.path().join("escape.txt").exists());
+        assert_eq!(vault.get("greeting", VersionSelector::Latest)?, "Hey.");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+//! Filtered and paginated history, for `promptpro history --since/--limit/...`, read
+//! one version at a time so long histories are never loaded whole.
+
+use crate::churn::parse_window;
+use crate::storage::PromptVault;
+use crate::types::VersionMeta;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Which versions [`PromptVault::history_filtered`] returns; every field is optional
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct HistoryQuery {
+    /// Versions written at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Versions written at or before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Versions carrying this tag
+    pub tag: Option<String>,
+    /// Versions whose message contains this text, ignoring case
+    pub message_contains: Option<String>,
+    /// At most this many versions, the newest matching ones
+    pub limit: Option<usize>,
+    /// Skip this many of the newest matching versions first, to page through older ones
+    pub offset: usize,
+}
+
+impl HistoryQuery {
+    /// Whether `meta` passes every filter; `limit` and `offset` are not applied
+    pub fn matches(&self, meta: &VersionMeta) -> bool {
+        self.since.is_none_or(|since| meta.timestamp >= since)
+            && self.until.is_none_or(|until| meta.timestamp <= until)
+            && self.tag.as_ref().is_none_or(|tag| meta.tags.contains(tag))
+            && self.message_contains.as_ref().is_none_or(|text| {
+                let message = meta.message.as_deref().unwrap_or_default();
+                message.to_lowercase().contains(&text.to_lowercase())
+            })
+    }
+}
+
+/// Parse a point in time given as RFC 3339, as `YYYY-MM-DDTHH:MM[:SS]` or a `YYYY-MM-DD`
+/// date in UTC, or as a window back from `now` such as `7d`. A date means its start, or
+/// its end with `end_of_day`.
+pub fn parse_time(value: &str, now: DateTime<Utc>, end_of_day: bool) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(time.and_utc());
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let time = if end_of_day {
+            date.and_hms_nano_opt(23, 59, 59, 999_999_999)
+        } else {
+            date.and_hms_opt(0, 0, 0)
+        };
+        return Ok(time.expect("valid time of day").and_utc());
+    }
+    match parse_window(value) {
+        Ok(window) => Ok(now - window),
+        Err(_) => Err(anyhow::anyhow!(
+            "Invalid time '{}', expected a date (2024-05-01), a time (2024-05-01T12:00) or a \
+             window (7d)",
+            value
+        )),
+    }
+}
+
+/// The versions of a key, oldest first or newest first with `rev()`, each read when it
+/// is reached; see [`PromptVault::history_iter`]
+pub struct HistoryIter {
+    vault: PromptVault,
+    key: String,
+    /// Next version from the front
+    front: u64,
+    /// Next version from the back
+    back: u64,
+}
+
+impl HistoryIter {
+    /// Metadata of `version`, or `None` when `gc` pruned it
+    fn read(&self, version: u64) -> Option<Result<VersionMeta>> {
+        self.vault.get_version_meta(&self.key, version).transpose()
+    }
+}
+
+impl Iterator for HistoryIter {
+    type Item = Result<VersionMeta>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.front <= self.back {
+            let version = self.front;
+            self.front += 1;
+            if let Some(meta) = self.read(version) {
+                return Some(meta);
+            }
+        }
+        None
+    }
+}
+
+impl DoubleEndedIterator for HistoryIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.front <= self.back {
+            let version = self.back;
+            self.back -= 1;
+            if let Some(meta) = self.read(version) {
+                return Some(meta);
+            }
+        }
+        None
+    }
+}
+
+impl PromptVault {
+    /// The versions of `key` one at a time, oldest first (`rev()` for newest first),
+    /// e.g. to page through a key with thousands of versions without loading them all
+    /// as [`history`](Self::history) does. Versions stored after the call are not
+    /// included.
+    pub fn history_iter(&self, key: &str) -> Result<HistoryIter> {
+        let latest = self.get_latest_version_number(key)?;
+        Ok(HistoryIter {
+            vault: self.clone(),
+            key: key.to_string(),
+            front: 1,
+            // An empty range when the key has no versions
+            back: latest.unwrap_or(0),
+        })
+    }
+
+    /// The versions of `key` matching `query`, oldest first. Only the versions up to
+    /// the requested page are read.
+    pub fn history_filtered(&self, key: &str, query: &HistoryQuery) -> Result<Vec<VersionMeta>> {
+        let mut versions = Vec::new();
+        // Pages count back from the newest version
+        let mut skipped = 0;
+        for meta in self.history_iter(key)?.rev() {
+            let meta = meta?;
+            if !query.matches(&meta) {
+                continue;
+            }
+            if skipped < query.offset {
+                skipped += 1;
+                continue;
+            }
+            versions.push(meta);
+            if query.limit.is_some_and(|limit| versions.len() >= limit) {
+                break;
+            }
+        }
+        versions.reverse();
+        Ok(versions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_history_filtered() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "v1")?;
+        for step in 2..=6 {
+            let message = (step % 2 == 0).then(|| format!("Auto-save {}", step));
+            vault.update("writer", &format!("v{}", step), message)?;
+        }
+        vault.tag("writer", "prod", 3)?;
+
+        let versions = |query: &HistoryQuery| -> Result<Vec<u64>> {
+            let history = vault.history_filtered("writer", query)?;
+            Ok(history.iter().map(|meta| meta.version).collect())
+        };
+        assert_eq!(versions(&HistoryQuery::default())?, [1, 2, 3, 4, 5, 6]);
+        let newest: Vec<u64> = vault
+            .history_iter("writer")?
+            .rev()
+            .take(2)
+            .map(|meta| meta.map(|meta| meta.version))
+            .collect::<Result<_>>()?;
+        assert_eq!(newest, [6, 5]);
+        assert_eq!(vault.history_iter("missing")?.count(), 0);
+        let page = HistoryQuery {
+            limit: Some(2),
+            ..HistoryQuery::default()
+        };
+        assert_eq!(versions(&page)?, [5, 6]);
+        assert_eq!(versions(&HistoryQuery { offset: 2, ..page })?, [3, 4]);
+        let saves = HistoryQuery {
+            message_contains: Some("auto-SAVE".to_string()),
+            ..HistoryQuery::default()
+        };
+        assert_eq!(versions(&saves)?, [2, 4, 6]);
+        let tagged = HistoryQuery {
+            tag: Some("prod".to_string()),
+            ..HistoryQuery::default()
+        };
+        assert_eq!(versions(&tagged)?, [3]);
+
+        let third = vault.get_version_meta("writer", 3)?.unwrap().timestamp;
+        let window = HistoryQuery {
+            since: Some(third),
+            until: Some(vault.get_version_meta("writer", 4)?.unwrap().timestamp),
+            ..HistoryQuery::default()
+        };
+        assert_eq!(versions(&window)?, [3, 4]);
+
+        let now = Utc::now();
+        assert_eq!(parse_time("7d", now, false)?, now - chrono::Duration::days(7));
+        let day = parse_time("2024-05-01", now, true)?;
+        assert_eq!(day.to_rfc3339(), "2024-05-01T23:59:59.999999999+00:00");
+        assert!(parse_time("yesterday", now, false).is_err());
+
+        Ok(())
+    }
+}
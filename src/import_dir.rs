@@ -0,0 +1,210 @@
+//! Prompts kept as files: every `.txt`/`.md` file of a directory becomes a prompt,
+//! keyed by its path relative to the directory (`agents/planner.md` → `agents/planner`).
+
+use crate::namespace::NAMESPACE_SEPARATOR;
+use crate::storage::PromptVault;
+use crate::types::{VersionMeta, VersionSelector};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Extensions of the files read as prompts
+pub const PROMPT_FILE_EXTENSIONS: &[&str] = &["txt", "md"];
+
+/// What importing a directory did, by key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirImportReport {
+    pub created: Vec<String>,
+    /// Keys that got a new version
+    pub updated: Vec<String>,
+    /// Keys whose latest version already had the file's content
+    pub unchanged: Vec<String>,
+    /// Password-protected keys, which are never overwritten from files
+    pub protected: Vec<String>,
+}
+
+/// What syncing one prompt file did to its prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+    Created,
+    /// A new version was added
+    Updated,
+    /// The latest version already had the file's content
+    Unchanged,
+    /// The prompt is password-protected and was left alone
+    Protected,
+}
+
+/// Prompt files under `dir`, sorted, skipping hidden files and directories
+pub(crate) fn prompt_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_prompt_file(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+pub(crate) fn is_prompt_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| PROMPT_FILE_EXTENSIONS.contains(&ext))
+}
+
+/// Key of a prompt file: its path relative to `dir`, without the extension
+pub(crate) fn key_for_file(dir: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(dir).ok()?.with_extension("");
+    let segments: Option<Vec<&str>> = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect();
+    let key = segments?.join(&NAMESPACE_SEPARATOR.to_string());
+    (!key.is_empty()).then_some(key)
+}
+
+impl PromptVault {
+    /// Create or update one prompt per `.txt`/`.md` file under `dir`.
+    ///
+    /// Files whose content matches the latest version (by `object_hash`) are left
+    /// alone, so importing the same directory twice adds no versions.
+    pub fn import_dir<P: AsRef<Path>>(&self, dir: P) -> Result<DirImportReport> {
+        self.sync_dir(dir.as_ref(), true)
+    }
+
+    /// What [`PromptVault::import_dir`] would do, without writing anything
+    pub fn plan_import_dir<P: AsRef<Path>>(&self, dir: P) -> Result<DirImportReport> {
+        self.sync_dir(dir.as_ref(), false)
+    }
+
+    fn sync_dir(&self, dir: &Path, apply: bool) -> Result<DirImportReport> {
+        if !dir.is_dir() {
+            return Err(anyhow::anyhow!("{:?} is not a directory", dir));
+        }
+
+        let mut report = DirImportReport::default();
+        for file in prompt_files(dir)? {
+            let relative = file.strip_prefix(dir)?.display().to_string();
+            let message = |_: &str, _: &str| format!("imported from {}", relative);
+            let Some((key, change)) = self.sync_file(dir, &file, apply, message)? else {
+                continue;
+            };
+            match change {
+                FileChange::Created => report.created.push(key),
+                FileChange::Updated => report.updated.push(key),
+                FileChange::Unchanged => report.unchanged.push(key),
+                FileChange::Protected => report.protected.push(key),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Bring the prompt of one file under `dir` in line with the file; `message` gets
+    /// the latest and the new content and names the version an update creates.
+    /// Returns the key and what changed, or `None` when the file has no key.
+    pub(crate) fn sync_file<F>(
+        &self,
+        dir: &Path,
+        file: &Path,
+        apply: bool,
+        message: F,
+    ) -> Result<Option<(String, FileChange)>>
+    where
+        F: FnOnce(&str, &str) -> String,
+    {
+        let Some(key) = key_for_file(dir, file) else {
+            return Ok(None);
+        };
+        let content = std::fs::read_to_string(file)?;
+        let change = self.sync_prompt(&key, &content, apply, message)?;
+        Ok(Some((key, change)))
+    }
+
+    /// Bring the prompt `key` in line with `content`, as [`PromptVault::sync_file`]
+    /// does for a file
+    pub(crate) fn sync_prompt<F>(
+        &self,
+        key: &str,
+        content: &str,
+        apply: bool,
+        message: F,
+    ) -> Result<FileChange>
+    where
+        F: FnOnce(&str, &str) -> String,
+    {
+        Ok(match self.history(key)?.last() {
+            None => {
+                if apply {
+                    self.add(key, content)?;
+                }
+                FileChange::Created
+            }
+            Some(_) if self.is_sensitive(key)? => FileChange::Protected,
+            Some(latest) if latest.object_hash == content_hash(content) => FileChange::Unchanged,
+            Some(latest) => {
+                if apply {
+                    let previous = self.get(key, VersionSelector::Version(latest.version))?;
+                    self.update(key, content, Some(message(&previous, content)))?;
+                }
+                FileChange::Updated
+            }
+        })
+    }
+}
+
+/// The `object_hash` a version with `content` gets
+pub(crate) fn content_hash(content: &str) -> String {
+    VersionMeta::new(String::new(), 0, content, None, None).object_hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_import_dir() -> Result<()> {
+        let dir = tempdir()?;
+        let prompts = dir.path().join("prompts");
+        std::fs::create_dir_all(prompts.join("agents"))?;
+        std::fs::create_dir_all(prompts.join(".git"))?;
+        std::fs::write(prompts.join("agents/planner.md"), "Plan the work")?;
+        std::fs::write(prompts.join("writer.txt"), "Write it")?;
+        std::fs::write(prompts.join("notes.json"), "{}")?;
+        std::fs::write(prompts.join(".git/HEAD.txt"), "ignored")?;
+
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        let report = vault.import_dir(&prompts)?;
+        assert_eq!(report.created, ["agents/planner", "writer"]);
+        assert_eq!(vault.get("agents/planner", VersionSelector::Latest)?, "Plan the work");
+
+        std::fs::write(prompts.join("writer.txt"), "Write it well")?;
+        assert_eq!(vault.plan_import_dir(&prompts)?.updated, ["writer"]);
+        assert_eq!(vault.history("writer")?.len(), 1);
+        let report = vault.import_dir(&prompts)?;
+        assert_eq!(report.updated, ["writer"]);
+        assert_eq!(report.unchanged, ["agents/planner"]);
+        let history = vault.history("writer")?;
+        assert_eq!(history[1].message.as_deref(), Some("imported from writer.txt"));
+
+        assert!(vault.import_dir(prompts.join("writer.txt")).is_err());
+
+        Ok(())
+    }
+}
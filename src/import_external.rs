@@ -0,0 +1,512 @@
+//! Prompts defined for other tools: LangChain prompt templates (as saved by
+//! `prompt.save()` or pulled from the LangChain Hub), PromptLayer registry templates,
+//! and a plain YAML file of prompts, mapped to vault keys with their metadata.
+//!
+//! Templates are converted to `{{name}}` placeholders and message lists become
+//! [chat prompts](crate::ChatPrompt).
+
+use crate::chat::{ChatMessage, ChatPrompt, Role};
+use crate::export::VaultExport;
+use crate::import_dir::{key_for_file, DirImportReport, FileChange};
+use crate::metadata::PromptMeta;
+use crate::render::template_variables;
+use crate::storage::PromptVault;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Extensions of the LangChain prompt files read from a directory
+const LANGCHAIN_FILE_EXTENSIONS: &[&str] = &["json", "yaml", "yml"];
+
+/// An escaped brace or a `{name}` field of a Python format string
+static F_STRING_FIELD: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{|\}\}|\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+
+/// Format of prompt definitions written by another tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExternalFormat {
+    /// A LangChain `PromptTemplate` or `ChatPromptTemplate` file, or a directory of them
+    LangChainHub,
+    /// A PromptLayer prompt template, or a list of them, as its REST API returns them
+    PromptLayer,
+    /// A YAML mapping of keys to a template or messages, with optional metadata
+    Yaml,
+}
+
+impl std::str::FromStr for ExternalFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "langchain-hub" | "langchain" => Ok(ExternalFormat::LangChainHub),
+            "promptlayer" => Ok(ExternalFormat::PromptLayer),
+            "yaml" | "yml" => Ok(ExternalFormat::Yaml),
+            other => Err(anyhow::anyhow!(
+                "Unknown prompt format '{}', expected 'langchain-hub', 'promptlayer' or 'yaml'",
+                other
+            )),
+        }
+    }
+}
+
+/// A prompt read from another tool's definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalPrompt {
+    pub key: String,
+    /// Content as stored in the vault: the template, or a chat prompt
+    pub content: String,
+    pub meta: PromptMeta,
+}
+
+/// Placeholder syntax of a template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TemplateFormat {
+    /// Python `{name}` fields with `{{` and `}}` for braces, LangChain's default
+    FString,
+    /// `{{name}}`, as promptpro writes placeholders
+    Mustache,
+}
+
+impl TemplateFormat {
+    fn parse(format: Option<&str>) -> Result<Self> {
+        match format.unwrap_or("f-string") {
+            "f-string" => Ok(TemplateFormat::FString),
+            // Plain `{{ name }}` Jinja expressions are promptpro placeholders
+            "mustache" | "jinja2" => Ok(TemplateFormat::Mustache),
+            other => Err(anyhow::anyhow!("Unknown template format '{}'", other)),
+        }
+    }
+
+    fn convert(self, template: &str) -> String {
+        match self {
+            TemplateFormat::FString => F_STRING_FIELD
+                .replace_all(template, |captures: &Captures| match &captures[0] {
+                    "{{" => "{".to_string(),
+                    "}}" => "}".to_string(),
+                    _ => format!("{{{{{}}}}}", &captures[1]),
+                })
+                .into_owned(),
+            TemplateFormat::Mustache => template.to_string(),
+        }
+    }
+}
+
+/// Read the prompts of `path` written in `format`. LangChain prompts are keyed by
+/// their file name, or their path relative to `path` when it is a directory.
+pub fn read_external<P: AsRef<Path>>(
+    path: P,
+    format: ExternalFormat,
+) -> Result<Vec<ExternalPrompt>> {
+    let path = path.as_ref();
+    if format == ExternalFormat::LangChainHub && path.is_dir() {
+        let mut prompts = Vec::new();
+        for file in langchain_files(path)? {
+            let key = key_for_file(path, &file)
+                .ok_or_else(|| anyhow::anyhow!("No key for {}", file.display()))?;
+            prompts.push(read_langchain_file(&file, key)?);
+        }
+        return Ok(prompts);
+    }
+
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+    let in_file = |e: anyhow::Error| anyhow::anyhow!("{}: {}", path.display(), e);
+    match format {
+        ExternalFormat::LangChainHub => {
+            let key = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| anyhow::anyhow!("No key for {}", path.display()))?;
+            Ok(vec![read_langchain_file(path, key.to_string())?])
+        }
+        ExternalFormat::PromptLayer => parse_promptlayer(&text).map_err(in_file),
+        ExternalFormat::Yaml => parse_yaml(&text).map_err(in_file),
+    }
+}
+
+/// Whether YAML is a promptpro export, which `import` reads with its full history
+pub fn is_vault_export(text: &str) -> bool {
+    serde_yaml::from_str::<VaultExport>(text).is_ok()
+}
+
+/// LangChain prompt files under `dir`, sorted, skipping hidden files and directories
+fn langchain_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or(".");
+            if name.starts_with('.') {
+                continue;
+            }
+            let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+            if path.is_dir() {
+                pending.push(path);
+            } else if LANGCHAIN_FILE_EXTENSIONS.contains(&extension) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn read_langchain_file(path: &Path, key: String) -> Result<ExternalPrompt> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+    // YAML is a superset of JSON, so both kinds of saved prompts parse
+    let value: Value = serde_yaml::from_str(&text)
+        .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    let (content, meta) =
+        parse_langchain(&value).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    Ok(ExternalPrompt { key, content, meta })
+}
+
+fn str_field<'a>(value: &'a Value, field: &str) -> Option<&'a str> {
+    value.get(field).and_then(Value::as_str)
+}
+
+/// The content and metadata of a LangChain prompt, either in the `{"_type": "prompt"}`
+/// form of `prompt.save()` or in the `{"lc": 1, "type": "constructor"}` form of the Hub
+fn parse_langchain(value: &Value) -> Result<(String, PromptMeta)> {
+    if let Some(kind) = str_field(value, "_type") {
+        if kind != "prompt" {
+            return Err(anyhow::anyhow!("Unsupported LangChain prompt type '{}'", kind));
+        }
+        let template = str_field(value, "template")
+            .ok_or_else(|| anyhow::anyhow!("Prompt has no 'template'"))?;
+        let format = TemplateFormat::parse(str_field(value, "template_format"))?;
+        return Ok((format.convert(template), PromptMeta::default()));
+    }
+
+    let kwargs = &value["kwargs"];
+    let mut meta = PromptMeta::default();
+    if let Some(metadata) = kwargs.get("metadata").and_then(Value::as_object) {
+        for (name, value) in metadata {
+            if let Some(value) = value.as_str() {
+                meta.labels.insert(name.clone(), value.to_string());
+            }
+        }
+    }
+    let content = match langchain_class(value)? {
+        "PromptTemplate" => langchain_template(kwargs)?,
+        "ChatPromptTemplate" => {
+            let messages = kwargs["messages"]
+                .as_array()
+                .ok_or_else(|| anyhow::anyhow!("ChatPromptTemplate has no 'messages'"))?;
+            let messages = messages.iter().map(langchain_message).collect::<Result<_>>()?;
+            ChatPrompt::new(messages).to_content()?
+        }
+        other => return Err(anyhow::anyhow!("Unsupported LangChain class '{}'", other)),
+    };
+    Ok((content, meta))
+}
+
+/// Class name of a serialized LangChain object, the last part of its `id`
+fn langchain_class(value: &Value) -> Result<&str> {
+    value["id"]
+        .as_array()
+        .and_then(|id| id.last())
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("Not a LangChain prompt: no '_type' or 'id'"))
+}
+
+/// The converted template of the `kwargs` of a `PromptTemplate`
+fn langchain_template(kwargs: &Value) -> Result<String> {
+    let template = str_field(kwargs, "template")
+        .ok_or_else(|| anyhow::anyhow!("PromptTemplate has no 'template'"))?;
+    let format = TemplateFormat::parse(str_field(kwargs, "template_format"))?;
+    Ok(format.convert(template))
+}
+
+fn langchain_message(value: &Value) -> Result<ChatMessage> {
+    let class = langchain_class(value)?;
+    let kwargs = &value["kwargs"];
+    let role = match class {
+        "SystemMessagePromptTemplate" | "SystemMessage" => Role::System,
+        "HumanMessagePromptTemplate" | "HumanMessage" => Role::User,
+        "AIMessagePromptTemplate" | "AIMessage" => Role::Assistant,
+        "ChatMessagePromptTemplate" | "ChatMessage" => str_field(kwargs, "role")
+            .ok_or_else(|| anyhow::anyhow!("{} has no 'role'", class))?
+            .parse()?,
+        other => {
+            return Err(anyhow::anyhow!(
+                "Unsupported message class '{}' in ChatPromptTemplate",
+                other
+            ))
+        }
+    };
+    let content = match str_field(kwargs, "content") {
+        // A fixed message
+        Some(content) => content.to_string(),
+        None => langchain_template(&kwargs["prompt"]["kwargs"])?,
+    };
+    Ok(ChatMessage::new(role, content))
+}
+
+/// PromptLayer templates: one, a list, or an `{"items": [...]}` page of them
+fn parse_promptlayer(text: &str) -> Result<Vec<ExternalPrompt>> {
+    let value: Value = serde_json::from_str(text)?;
+    let templates = match &value {
+        Value::Array(templates) => templates.clone(),
+        Value::Object(object) => match object.get("items").and_then(Value::as_array) {
+            Some(templates) => templates.clone(),
+            None => vec![value],
+        },
+        _ => return Err(anyhow::anyhow!("Expected a PromptLayer template or a list of them")),
+    };
+    templates.iter().map(promptlayer_prompt).collect()
+}
+
+fn promptlayer_prompt(value: &Value) -> Result<ExternalPrompt> {
+    let key = str_field(value, "prompt_name")
+        .ok_or_else(|| anyhow::anyhow!("PromptLayer template has no 'prompt_name'"))?;
+    let in_prompt = |e: anyhow::Error| anyhow::anyhow!("'{}': {}", key, e);
+    let template = &value["prompt_template"];
+    let content = match str_field(template, "type") {
+        Some("chat") => {
+            let messages = template["messages"]
+                .as_array()
+                .ok_or_else(|| anyhow::anyhow!("'{}' has no 'messages'", key))?;
+            let messages = messages.iter().map(promptlayer_message).collect::<Result<_>>();
+            ChatPrompt::new(messages.map_err(in_prompt)?).to_content()?
+        }
+        Some("completion") => promptlayer_text(template).map_err(in_prompt)?,
+        other => {
+            return Err(anyhow::anyhow!(
+                "'{}' has template type {:?}, expected 'chat' or 'completion'",
+                key,
+                other.unwrap_or_default()
+            ))
+        }
+    };
+
+    let mut meta = PromptMeta::default();
+    let model = &value["metadata"]["model"];
+    meta.model = str_field(model, "name").map(str::to_string);
+    meta.temperature = model["parameters"]["temperature"].as_f64().map(|t| t as f32);
+    if let Some(provider) = str_field(model, "provider") {
+        meta.labels.insert("provider".to_string(), provider.to_string());
+    }
+    Ok(ExternalPrompt {
+        key: key.to_string(),
+        content,
+        meta,
+    })
+}
+
+fn promptlayer_message(value: &Value) -> Result<ChatMessage> {
+    let role = match str_field(value, "role").unwrap_or_default() {
+        "developer" => Role::System,
+        role => role.parse()?,
+    };
+    Ok(ChatMessage::new(role, promptlayer_text(value)?))
+}
+
+/// The converted text of a PromptLayer message or completion template, whose content
+/// is a list of parts of which only text parts are kept
+fn promptlayer_text(value: &Value) -> Result<String> {
+    let text = match &value["content"] {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter(|part| str_field(part, "type") == Some("text"))
+            .filter_map(|part| str_field(part, "text"))
+            .collect(),
+        _ => return Err(anyhow::anyhow!("Template has no 'content'")),
+    };
+    Ok(TemplateFormat::parse(str_field(value, "template_format"))?.convert(&text))
+}
+
+/// One prompt of a plain YAML file: a template, or a mapping with a template or
+/// messages and metadata
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum YamlPrompt {
+    Template(String),
+    Definition(Box<YamlDefinition>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct YamlDefinition {
+    template: Option<String>,
+    messages: Option<Vec<ChatMessage>>,
+    /// `mustache` (default), `jinja2` or `f-string`
+    template_format: Option<String>,
+    /// Variables the template must use
+    #[serde(default)]
+    input_variables: Vec<String>,
+    description: Option<String>,
+    author: Option<String>,
+    model: Option<String>,
+    temperature: Option<f32>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+fn parse_yaml(text: &str) -> Result<Vec<ExternalPrompt>> {
+    let prompts: BTreeMap<String, YamlPrompt> = serde_yaml::from_str(text)?;
+    let mut imported = Vec::new();
+    for (key, prompt) in prompts {
+        let definition = match prompt {
+            YamlPrompt::Template(template) => {
+                imported.push(ExternalPrompt {
+                    key,
+                    content: template,
+                    meta: PromptMeta::default(),
+                });
+                continue;
+            }
+            YamlPrompt::Definition(definition) => definition,
+        };
+        let format = definition.template_format.as_deref().unwrap_or("mustache");
+        let format = TemplateFormat::parse(Some(format))?;
+        let content = match (definition.template, definition.messages) {
+            (Some(template), None) => format.convert(&template),
+            (None, Some(mut messages)) => {
+                for message in &mut messages {
+                    message.content = format.convert(&message.content);
+                }
+                ChatPrompt::new(messages).to_content()?
+            }
+            _ => return Err(anyhow::anyhow!("'{}' needs either 'template' or 'messages'", key)),
+        };
+        let used = template_variables(&content);
+        if let Some(missing) = definition.input_variables.iter().find(|v| !used.contains(v)) {
+            return Err(anyhow::anyhow!(
+                "'{}' declares the input variable '{}' but never uses it",
+                key,
+                missing
+            ));
+        }
+        let meta = PromptMeta {
+            description: definition.description,
+            author: definition.author,
+            model: definition.model,
+            temperature: definition.temperature,
+            labels: definition.labels,
+        };
+        imported.push(ExternalPrompt { key, content, meta });
+    }
+    Ok(imported)
+}
+
+impl PromptVault {
+    /// Create or update a prompt for each of `prompts`, setting the metadata they
+    /// carry; prompts whose latest version has the same content are left alone
+    pub fn import_external(
+        &self,
+        prompts: &[ExternalPrompt],
+        source: &str,
+    ) -> Result<DirImportReport> {
+        let mut report = DirImportReport::default();
+        for prompt in prompts {
+            let key = prompt.key.clone();
+            let message = |_: &str, _: &str| format!("imported from {}", source);
+            match self.sync_prompt(&prompt.key, &prompt.content, true, message)? {
+                FileChange::Created => report.created.push(key),
+                FileChange::Updated => report.updated.push(key),
+                FileChange::Unchanged => {
+                    report.unchanged.push(key);
+                    continue;
+                }
+                FileChange::Protected => {
+                    report.protected.push(key);
+                    continue;
+                }
+            }
+            if !prompt.meta.is_empty() {
+                self.update_prompt_meta(&prompt.key, prompt.meta.clone())?;
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_import_external() -> Result<()> {
+        let dir = tempdir()?;
+        let chains = dir.path().join("chains");
+        std::fs::create_dir_all(chains.join("agents"))?;
+        std::fs::write(
+            chains.join("summary.json"),
+            r#"{"_type": "prompt", "input_variables": ["text"],
+                "template": "Summarize {text} as {{json}}.", "template_format": "f-string"}"#,
+        )?;
+        let system = r#"{"lc": 1, "type": "constructor",
+            "id": ["langchain", "prompts", "chat", "SystemMessagePromptTemplate"],
+            "kwargs": {"prompt": {"lc": 1, "type": "constructor",
+                "id": ["langchain", "prompts", "prompt", "PromptTemplate"],
+                "kwargs": {"input_variables": ["tone"], "template": "Be {tone}."}}}}"#;
+        let human = r#"{"lc": 1, "type": "constructor",
+            "id": ["langchain_core", "messages", "HumanMessage"],
+            "kwargs": {"content": "Hi"}}"#;
+        std::fs::write(
+            chains.join("agents/support.json"),
+            format!(
+                r#"{{"lc": 1, "type": "constructor",
+                    "id": ["langchain", "prompts", "chat", "ChatPromptTemplate"],
+                    "kwargs": {{"metadata": {{"lc_hub_owner": "acme"}},
+                    "messages": [{}, {}]}}}}"#,
+                system, human
+            ),
+        )?;
+        let langchain = read_external(&chains, ExternalFormat::LangChainHub)?;
+        let keys: Vec<&str> = langchain.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(keys, ["agents/support", "summary"]);
+        assert_eq!(langchain[1].content, "Summarize {{text}} as {json}.");
+        let chat = ChatPrompt::from_content(&langchain[0].content)?;
+        assert_eq!(chat.messages[0], ChatMessage::new(Role::System, "Be {{tone}}."));
+        assert_eq!(chat.messages[1], ChatMessage::new(Role::User, "Hi"));
+        assert_eq!(langchain[0].meta.labels["lc_hub_owner"], "acme");
+
+        let promptlayer = dir.path().join("promptlayer.json");
+        std::fs::write(
+            &promptlayer,
+            r#"{"items": [{"prompt_name": "greeter",
+                "prompt_template": {"type": "chat", "messages": [{"role": "system",
+                    "content": [{"type": "text", "text": "Greet {name}."}]}]},
+                "metadata": {"model": {"provider": "openai", "name": "gpt-4o",
+                    "parameters": {"temperature": 0.5}}}}]}"#,
+        )?;
+        let greeter = &read_external(&promptlayer, ExternalFormat::PromptLayer)?[0];
+        assert_eq!(greeter.meta.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(greeter.meta.temperature, Some(0.5));
+        assert!(greeter.content.ends_with("content: Greet {{name}}.\n"));
+
+        let yaml = dir.path().join("prompts.yaml");
+        std::fs::write(
+            &yaml,
+            "short: Answer briefly.\ntranslate:\n  template: Translate {{text}}.\n  \
+             input_variables: [text]\n  description: Translations\n",
+        )?;
+        let plain = read_external(&yaml, ExternalFormat::Yaml)?;
+        assert_eq!(plain[1].meta.description.as_deref(), Some("Translations"));
+        assert!(!is_vault_export(&std::fs::read_to_string(&yaml)?));
+        std::fs::write(&yaml, "bad:\n  template: Hi\n  input_variables: [name]\n")?;
+        assert!(read_external(&yaml, ExternalFormat::Yaml).is_err());
+
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        let report = vault.import_external(&langchain, "chains")?;
+        assert_eq!(report.created.len(), 2);
+        assert_eq!(vault.import_external(&langchain, "chains")?.unchanged.len(), 2);
+        vault.import_external(&plain, "prompts.yaml")?;
+        let summary = vault.prompt_meta("translate")?;
+        assert_eq!(summary.description.as_deref(), Some("Translations"));
+        assert_eq!(vault.get("short", VersionSelector::Latest)?, "Answer briefly.");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+//! Prompts hardcoded in source files: string constants named like `SYSTEM_PROMPT` in
+//! Rust or Python code are found and proposed as prompts, keyed by their names
+//! (`CODE_REVIEW_PROMPT` → `code_review`).
+
+use crate::import_dir::{DirImportReport, FileChange};
+use crate::namespace::NAMESPACE_SEPARATOR;
+use crate::storage::PromptVault;
+use anyhow::Result;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// Shorter constants are taken for labels or names rather than prompts
+pub const MIN_PROMPT_CONSTANT_CHARS: usize = 32;
+
+/// Directories of build output and dependencies, never scanned
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "__pycache__", "venv"];
+
+/// Language of the source files to scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceLanguage {
+    Rust,
+    Python,
+}
+
+impl std::str::FromStr for SourceLanguage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "rust-src" | "rust" => Ok(SourceLanguage::Rust),
+            "python-src" | "python" => Ok(SourceLanguage::Python),
+            other => Err(anyhow::anyhow!(
+                "Unknown source '{}', expected 'rust-src' or 'python-src'",
+                other
+            )),
+        }
+    }
+}
+
+impl SourceLanguage {
+    fn extension(self) -> &'static str {
+        match self {
+            SourceLanguage::Rust => "rs",
+            SourceLanguage::Python => "py",
+        }
+    }
+
+    /// Start of a string constant definition, up to the literal, capturing the name
+    fn definition(self) -> Regex {
+        let pattern = match self {
+            // `pub(crate) const NAME: &'static str = `
+            SourceLanguage::Rust => concat!(
+                r"(?m)^[ \t]*(?:pub(?:\([^)]*\))?[ \t]+)?(?:const|static)[ \t]+",
+                r"([A-Z][A-Z0-9_]*)\s*:\s*&\s*(?:'static\s+)?str\s*=\s*"
+            ),
+            // `NAME: Final[str] = `
+            SourceLanguage::Python => concat!(
+                r"(?m)^[ \t]*([A-Z][A-Z0-9_]*)[ \t]*",
+                r"(?::[ \t]*(?:str|Final(?:\[str\])?)[ \t]*)?=[ \t]*"
+            ),
+        };
+        Regex::new(pattern).expect("valid definition pattern")
+    }
+
+    /// Value of the string literal `source` starts with
+    fn literal(self, source: &str) -> Option<String> {
+        match self {
+            SourceLanguage::Rust => rust_literal(source),
+            SourceLanguage::Python => python_literal(source),
+        }
+    }
+}
+
+/// A prompt found in a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptConstant {
+    /// Proposed key
+    pub key: String,
+    /// Name of the constant
+    pub name: String,
+    pub file: PathBuf,
+    /// Line of the definition, starting at 1
+    pub line: usize,
+    pub content: String,
+}
+
+/// Whether `name` reads as a prompt: `PROMPT` is one of its words
+fn is_prompt_name(name: &str) -> bool {
+    name.split('_').any(|word| word == "PROMPT")
+}
+
+/// Key proposed for a constant: its name without `PROMPT`, in lower case, or the
+/// file's name for a constant named just `PROMPT`
+fn key_for_constant(name: &str, file: &Path) -> String {
+    let words: Vec<String> = name
+        .split('_')
+        .filter(|word| !word.is_empty() && *word != "PROMPT")
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() {
+        return file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "prompt".to_string());
+    }
+    words.join("_")
+}
+
+/// Resolve the escape after a backslash, `rest` being the text after it. Returns the
+/// characters it stands for (none for a line continuation) and the length consumed.
+fn unescape(rest: &str, python: bool) -> Option<(Option<char>, usize)> {
+    let next = rest.chars().next()?;
+    let simple = match next {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        '\\' | '"' | '\'' => Some(next),
+        _ => None,
+    };
+    if let Some(c) = simple {
+        return Some((Some(c), 1));
+    }
+    match next {
+        '\n' if python => Some((None, 1)),
+        // Rust also skips the indentation of the next line
+        '\n' => {
+            let skipped = rest[1..].len() - rest[1..].trim_start().len();
+            Some((None, 1 + skipped))
+        }
+        'u' if rest[1..].starts_with('{') => {
+            let end = rest.find('}')?;
+            let code = u32::from_str_radix(&rest[2..end], 16).ok()?;
+            Some((Some(char::from_u32(code)?), end + 1))
+        }
+        'x' => {
+            let code = u8::from_str_radix(rest.get(1..3)?, 16).ok()?;
+            Some((Some(code as char), 3))
+        }
+        // Unknown escapes are kept as written in Python
+        _ if python => Some((Some('\\'), 0)),
+        _ => None,
+    }
+}
+
+/// Read an escaped literal up to `quote`, returning its value
+fn read_escaped(body: &str, quote: &str, python: bool) -> Option<String> {
+    let mut value = String::new();
+    let mut rest = body;
+    loop {
+        if rest.starts_with(quote) {
+            return Some(value);
+        }
+        let c = rest.chars().next()?;
+        rest = &rest[c.len_utf8()..];
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let (escaped, consumed) = unescape(rest, python)?;
+        value.extend(escaped);
+        rest = &rest[consumed..];
+    }
+}
+
+/// Value of a Rust string literal (`"..."` or `r#"..."#`)
+fn rust_literal(source: &str) -> Option<String> {
+    if let Some(raw) = source.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let body = raw[hashes..].strip_prefix('"')?;
+        let end = format!("\"{}", "#".repeat(hashes));
+        return Some(body[..body.find(&end)?].to_string());
+    }
+    read_escaped(source.strip_prefix('"')?, "\"", false)
+}
+
+/// Value of a Python string literal, triple-quoted or not; f-strings and bytes are not
+/// constants and are skipped
+fn python_literal(source: &str) -> Option<String> {
+    let prefix = source.len() - source.trim_start_matches(char::is_alphabetic).len();
+    let (flags, body) = source.split_at(prefix);
+    let raw = match flags.to_lowercase().as_str() {
+        "" | "u" => false,
+        "r" => true,
+        _ => return None,
+    };
+    let quote = ["\"\"\"", "'''", "\"", "'"]
+        .into_iter()
+        .find(|quote| body.starts_with(quote))?;
+    let body = &body[quote.len()..];
+    if raw {
+        return Some(body[..body.find(quote)?].to_string());
+    }
+    read_escaped(body, quote, true)
+}
+
+/// Source files of `language` under `dir`, sorted, skipping hidden files and build
+/// or dependency directories
+fn source_files(dir: &Path, language: SourceLanguage) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+            if name.starts_with('.') || SKIPPED_DIRS.contains(&name) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some(language.extension())
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Find the prompt constants of the `language` files under `path` (a directory or a
+/// single file). Constants proposing the same key are told apart by the path of their
+/// file, e.g. `agents/planner/system`.
+pub fn scan_source<P: AsRef<Path>>(
+    path: P,
+    language: SourceLanguage,
+) -> Result<Vec<PromptConstant>> {
+    let path = path.as_ref();
+    let (root, files) = if path.is_dir() {
+        (path, source_files(path, language)?)
+    } else if path.is_file() {
+        (path.parent().unwrap_or(path), vec![path.to_path_buf()])
+    } else {
+        return Err(anyhow::anyhow!("{:?} does not exist", path));
+    };
+
+    let definition = language.definition();
+    let mut constants = Vec::new();
+    for file in files {
+        let source = std::fs::read_to_string(&file)?;
+        for captures in definition.captures_iter(&source) {
+            let (whole, name) = (captures.get(0).unwrap(), &captures[1]);
+            if !is_prompt_name(name) {
+                continue;
+            }
+            let Some(content) = language.literal(&source[whole.end()..]) else {
+                continue;
+            };
+            if content.trim().chars().count() < MIN_PROMPT_CONSTANT_CHARS {
+                continue;
+            }
+            constants.push(PromptConstant {
+                key: key_for_constant(name, &file),
+                name: name.to_string(),
+                line: source[..whole.start()].matches('\n').count() + 1,
+                file: file.clone(),
+                content,
+            });
+        }
+    }
+
+    let mut duplicated = std::collections::HashSet::new();
+    for (index, constant) in constants.iter().enumerate() {
+        if constants[..index].iter().any(|other| other.key == constant.key) {
+            duplicated.insert(constant.key.clone());
+        }
+    }
+    for constant in &mut constants {
+        if duplicated.contains(&constant.key) {
+            let module = crate::import_dir::key_for_file(root, &constant.file)
+                .unwrap_or_else(|| key_for_constant("PROMPT", &constant.file));
+            constant.key = format!("{}{}{}", module, NAMESPACE_SEPARATOR, constant.key);
+        }
+    }
+    Ok(constants)
+}
+
+impl PromptVault {
+    /// Import prompt constants found by [`scan_source`], like
+    /// [`PromptVault::import_dir`] imports files: unchanged prompts get no version and
+    /// password-protected ones are never overwritten. With `apply` false nothing is
+    /// written.
+    pub fn import_constants(
+        &self,
+        constants: &[PromptConstant],
+        apply: bool,
+    ) -> Result<DirImportReport> {
+        let mut report = DirImportReport::default();
+        for constant in constants {
+            let message = |_: &str, _: &str| {
+                format!(
+                    "imported {} from {}:{}",
+                    constant.name,
+                    constant.file.display(),
+                    constant.line
+                )
+            };
+            let key = constant.key.clone();
+            match self.sync_prompt(&constant.key, &constant.content, apply, message)? {
+                FileChange::Created => report.created.push(key),
+                FileChange::Updated => report.updated.push(key),
+                FileChange::Unchanged => report.unchanged.push(key),
+                FileChange::Protected => report.protected.push(key),
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_scan_source() -> Result<()> {
+        let dir = tempdir()?;
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("agents"))?;
+        std::fs::create_dir_all(src.join("target"))?;
+        std::fs::write(
+            src.join("agents/planner.rs"),
+            "pub const SYSTEM_PROMPT: &str = \"You plan the work.\\nList the steps \\\n    \
+             in order.\";\nconst PROMPT_NAME: &str = \"planner\";\n",
+        )?;
+        std::fs::write(
+            src.join("lib.rs"),
+            "static CODE_REVIEW_PROMPT: &'static str = r#\"Review this \"diff\" for bugs \
+             and style.\"#;\nconst SYSTEM_PROMPT: &str = \"You are a helpful writing \
+             assistant.\";\n",
+        )?;
+        std::fs::write(src.join("target/gen.rs"), "const X_PROMPT: &str = \"ignored\";")?;
+        std::fs::write(
+            src.join("chains.py"),
+            "SUMMARY_PROMPT = \"\"\"Summarize the text below\nin three bullet points.\"\"\"\n\
+             GREETING_PROMPT = f\"Hello {name}, how can I help you today?\"\n",
+        )?;
+
+        let rust = scan_source(&src, SourceLanguage::Rust)?;
+        let found: Vec<(&str, &str, usize)> = rust
+            .iter()
+            .map(|c| (c.key.as_str(), c.name.as_str(), c.line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("agents/planner/system", "SYSTEM_PROMPT", 1),
+                ("code_review", "CODE_REVIEW_PROMPT", 1),
+                ("lib/system", "SYSTEM_PROMPT", 2),
+            ]
+        );
+        assert_eq!(rust[0].content, "You plan the work.\nList the steps in order.");
+        assert_eq!(rust[1].content, "Review this \"diff\" for bugs and style.");
+
+        let python = scan_source(src.join("chains.py"), SourceLanguage::Python)?;
+        assert_eq!(python.len(), 1);
+        assert_eq!(python[0].key, "summary");
+        assert_eq!(python[0].content, "Summarize the text below\nin three bullet points.");
+
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        assert!(vault.import_constants(&rust, false)?.created.len() == 3);
+        assert!(vault.list_keys()?.is_empty());
+        let report = vault.import_constants(&rust, true)?;
+        assert_eq!(report.created, ["agents/planner/system", "code_review", "lib/system"]);
+        assert_eq!(vault.get("code_review", VersionSelector::Latest)?, rust[1].content);
+        assert_eq!(vault.import_constants(&rust, true)?.unchanged.len(), 3);
+
+        Ok(())
+    }
+}
@@ -4,155 +4,198 @@
 //! and diff capabilities. It can be used as a standalone CLI tool or as a library
 //! integrated into other Rust projects.
 
+mod adopt;
+mod alias;
 pub mod api;
+mod blame;
+mod canary;
+mod chat;
+mod churn;
+mod cli;
+mod client;
+mod codegen;
 mod commands;
+mod comments;
+mod compare;
+mod compose;
+mod compress;
+mod copy;
+mod diff;
+mod docs;
+mod dump;
+mod dump_merge;
+mod embed;
+mod emit;
+mod encryption;
+mod eval;
+mod events;
+mod experiment;
+mod export;
+mod gc;
+mod history;
+mod import_dir;
+mod import_external;
+mod import_source;
+mod lock;
+mod merge;
+mod metadata;
+mod migrate;
+mod namespace;
+mod network;
+mod objects;
+mod options;
+mod package;
+mod password;
+mod preload;
+mod profile;
+mod protect;
+mod remote;
+mod render;
+mod score;
+mod search;
+pub mod server;
+mod settings;
+mod sort;
+mod stats;
 mod storage;
+mod suggest;
+mod sweep;
+mod template_export;
+mod tokens;
+mod transaction;
 mod tui;
 mod types;
 mod utils;
-
+mod validate;
+mod vault_lock;
+mod verify;
+mod watch;
+mod web_bundle;
+mod webhook;
+
+#[cfg(feature = "git")]
+mod git_sync;
+#[cfg(feature = "s3")]
+mod s3;
 #[cfg(feature = "python")]
 mod sync_api;
 #[cfg(feature = "python")]
 mod python_bindings;
 
-pub use storage::PromptVault;
-pub use types::{VersionMeta, VersionSelector};
-pub use utils::default_vault_path;
-
+pub use adopt::Provenance;
+pub use blame::BlameLine;
+pub use canary::{CanaryResolution, HashBucketDecider, Variant, VariantDecider, VariantRequest};
+pub use chat::{ChatMessage, ChatPrompt, Role, CHAT_HEADER};
+pub use churn::{ChurnEntry, UNSTABLE_VERSIONS};
+pub use client::{RemotePrompt, RemotePromptVault};
+pub use codegen::{PythonCodegen, RustCodegen};
+pub use comments::{comment_threads, Comment};
+pub use compare::{parse_input, Comparison};
+pub use compose::{includes, Dependency, Include};
+pub use compress::{Compression, RecompressReport};
+pub use copy::CopyOptions;
+pub use diff::{side_by_side, ChangeKind, DiffChange, DiffHunk, PromptDiff, RowKind, SideBySideRow};
+pub use docs::{KeyDocs, VaultDocs};
+pub use dump::{DumpHeader, DumpKdf, RestorePolicy, DUMP_FORMAT_VERSION};
+pub use dump_merge::{ConflictPolicy, KeyConflict, MergeReport};
+pub use embed::{embed_prompts, EmbedOptions, EmbeddedPrompt, EMBED_DIR};
+pub use emit::{EmitOptions, RequestFormat, DEFAULT_MAX_TOKENS};
+pub use encryption::KdfParams;
+pub use eval::{
+    check_output, Assertion, CaseResult, LlmConfig, TestCase, VersionReport, DEFAULT_API_KEY_ENV,
+    DEFAULT_ENDPOINT,
+};
+pub use events::VaultEvent;
+pub use experiment::{Experiment, ExperimentSample, ExperimentVariant};
+pub use export::ExportFormat;
+pub use gc::{GcReport, RetentionPolicy};
+pub use history::{parse_time, HistoryIter, HistoryQuery};
+pub use import_dir::{DirImportReport, FileChange, PROMPT_FILE_EXTENSIONS};
+pub use import_external::{is_vault_export, read_external, ExternalFormat, ExternalPrompt};
+pub use import_source::{scan_source, PromptConstant, SourceLanguage, MIN_PROMPT_CONSTANT_CHARS};
+pub use lock::{HashMismatch, LockedPrompt, Lockfile};
+pub use metadata::PromptMeta;
+pub use migrate::{MigrationReport, LAYOUT_VERSION};
+pub use namespace::{namespaces_of, NAMESPACE_SEPARATOR};
+pub use network::{go_offline, is_offline, OfflineError, OFFLINE_ENV};
+pub use options::{InitError, ManagerOptions, VAULT_ENV};
+pub use package::{
+    InstallPolicy, InstallReport, Package, PackagedPrompt, PACKAGE_FORMAT_VERSION,
+};
+pub use profile::{
+    active_profile, resolve_author, select_profile, Profile, ProfileConfig, UserConfig, AUTHOR_ENV,
+    PROFILE_ENV,
+};
+pub use protect::{UnlockedKeys, REDACTED};
+pub use remote::{Remote, SyncReport};
+pub use render::{render_template, template_variables};
+pub use score::Score;
+pub use search::{LineMatch, MatchLine, SearchField, SearchHit, SearchOptions, SearchQuery};
+pub use settings::{get_setting, set_setting, unset_setting, SETTINGS};
+pub use sort::{estimate_tokens, KeyOrder};
+pub use stats::{GrowthStats, KeyStats, NamespaceStats, StatsFormat, VaultStats};
+pub use storage::{PromptVault, VersionConflict};
+pub use sweep::{spawn_sweeps, SweepOptions, SweepReport};
+pub use template_export::TemplateSyntax;
+pub use tokens::{count_tokens, TokenConfig, TokenCount, DEFAULT_MODEL};
+pub use transaction::Transaction;
+pub use types::{VersionMeta, VersionSelector, VersionedPrompt};
+pub use utils::{current_vault_path, default_vault_path, find_project_vault};
+pub use validate::{
+    configured_validators, ValidationError, ValidationRequest, Validator, ValidatorConfig,
+};
+pub use vault_lock::{LockPolicy, VaultInUse};
+pub use verify::{Issue, VerifyReport};
+pub use watch::{DirWatcher, WatchedChange};
+pub use web_bundle::{WebBundle, WebBundleOptions};
+pub use webhook::{configured_webhooks, Webhook, WebhookSender, EVENT_NAMES};
+
+#[cfg(feature = "git")]
+pub use git_sync::{GitSyncReport, SyncSide, GIT_PROMPT_EXTENSION};
+#[cfg(feature = "s3")]
+pub use s3::{Credentials, S3Remote, DEFAULT_REGION};
 #[cfg(feature = "python")]
 pub use sync_api::SyncPromptManager;
 
-// Function to run CLI from arguments
+/// Run the CLI with the given arguments, blocking until the command finishes.
+///
+/// Safe to call from inside a Tokio runtime (e.g. an embedding application's event loop):
+/// a multi-threaded runtime is reused via `block_in_place`, any other runtime hands the
+/// command to a dedicated thread with its own runtime.
 pub fn run_cli_from_args(args: Vec<String>) -> anyhow::Result<()> {
-    use clap::Parser;
-    
-    // Define the CLI struct here to avoid duplicate definitions
-    #[derive(Parser)]
-    #[command(author, version, about, long_about = None)]
-    struct Cli {
-        #[command(subcommand)]
-        command: Commands,
-    }
+    use tokio::runtime::{Handle, Runtime, RuntimeFlavor};
 
-    #[derive(clap::Subcommand)]
-    enum Commands {
-        /// Initialize a new prompt vault
-        Init {
-            /// Path to the vault directory (default: ~/promptpro/default_vault)
-            #[arg(long)]
-            path: Option<String>,
-        },
-        /// Add a new prompt
-        Add {
-            /// Content of the prompt
-            content: String,
-        },
-        /// Update an existing prompt
-        Update {
-            /// Key of the prompt to update
-            key: String,
-            /// New content of the prompt
-            content: String,
-            /// Optional message for the update
-            #[arg(short, long)]
-            message: Option<String>,
-        },
-        /// Get a prompt by key and selector
-        Get {
-            /// Key of the prompt
-            key: String,
-            /// Selector (version, tag, latest)
-            selector: Option<String>,
-            /// Output to file instead of stdout
-            #[arg(short, long)]
-            output: Option<String>,
-        },
-        /// Show history of a prompt
-        History {
-            /// Key of the prompt
-            key: String,
-        },
-        /// Tag a specific version of a prompt
-        Tag {
-            /// Key of the prompt
-            key: String,
-            /// Tag name
-            tag: String,
-            /// Version number (optional, defaults to latest)
-            version: Option<u64>,
-        },
-        /// Promote a tag to the latest version
-        Promote {
-            /// Key of the prompt
-            key: String,
-            /// Tag name to promote
-            tag: String,
-        },
-        /// Open TUI editor
-        Tui,
-        /// Edit a prompt in TUI mode
-        Edit {
-            /// Key of the prompt to edit
-            key: String,
-        },
-        /// Dump the vault to a binary file
-        Dump {
-            /// Output file path for the dump
-            output: String,
-            /// Password to encrypt the dump (optional)
-            #[arg(long)]
-            password: Option<String>,
-        },
-        /// Restore/Resume the vault from a binary file
-        Resume {
-            /// Input file path to restore from
-            input: String,
-            /// Password to decrypt the dump (optional)
-            #[arg(long)]
-            password: Option<String>,
-        },
-        /// Delete a prompt by key
-        Delete {
-            /// Key of the prompt to delete
-            key: String,
-        },
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| handle.block_on(run_cli_from_args_async(args)))
+        }
+        Ok(_) => std::thread::scope(|scope| {
+            scope
+                .spawn(|| Runtime::new()?.block_on(run_cli_from_args_async(args)))
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        }),
+        Err(_) => Runtime::new()?.block_on(run_cli_from_args_async(args)),
     }
-    
+}
+
+/// Run the CLI with the given arguments on the caller's async runtime
+pub async fn run_cli_from_args_async(args: Vec<String>) -> anyhow::Result<()> {
     // Skip the first argument since it's typically the program name
     let cli_args = if !args.is_empty() {
         args
     } else {
         vec!["promptpro".to_string()] // Default to showing help if no args
     };
-    
-    // Parse the arguments using clap
-    let cli = Cli::try_parse_from(cli_args)?;
-    
-    // Execute the command based on the parsed arguments
-    tokio::runtime::Runtime::new()?.block_on(async {
-        match cli.command {
-            Commands::Init { path } => commands::init(path).await,
-            Commands::Add { content } => commands::add(content).await,
-            Commands::Update { key, content, message } => commands::update(key, content, message).await,
-            Commands::Get { key, selector, output } => commands::get(key, selector, output).await,
-            Commands::History { key } => commands::history(key).await,
-            Commands::Tag { key, tag, version } => commands::tag(key, tag, version).await,
-            Commands::Promote { key, tag } => commands::promote(key, tag).await,
-            Commands::Tui => commands::tui().await,
-            Commands::Edit { key } => commands::edit(key).await,
-            Commands::Dump { output, password } => commands::dump(output, password).await,
-            Commands::Resume { input, password } => commands::resume(input, password).await,
-            Commands::Delete { key } => commands::delete(key).await,
-        }
-    })
-}
 
-use anyhow::Result;
+    let cli = cli::try_parse_from(cli_args)?;
+    cli::dispatch(cli).await
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Result;
 
     #[tokio::test]
     async fn test_library_api() -> Result<()> {
@@ -175,7 +218,7 @@ mod tests {
 
         // Test tagging
         vault.tag("greet", "stable", 1)?; // Tag version 1 as stable
-        let text = vault.get("greet", VersionSelector::Tag("stable"))?;
+        let text = vault.get("greet", VersionSelector::tag("stable"))?;
         assert_eq!(text, "hello world");
 
         // Test history
@@ -184,4 +227,16 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_run_cli_inside_current_thread_runtime() {
+        let err = run_cli_from_args(vec!["promptpro".into(), "no-such-command".into()]);
+        assert!(err.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_run_cli_inside_multi_thread_runtime() {
+        let err = run_cli_from_args(vec!["promptpro".into(), "no-such-command".into()]);
+        assert!(err.is_err());
+    }
 }
@@ -0,0 +1,189 @@
+//! Pinning the exact prompt versions a deployment uses, for `promptpro lock`, and the
+//! content hashes a read is checked against.
+
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Content read for a prompt differs from the hash it was pinned to
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("'{key}' v{version} does not match the expected content hash {expected} (found {actual})")]
+pub struct HashMismatch {
+    pub key: String,
+    pub version: u64,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Fail with [`HashMismatch`] unless `content` has the `object_hash` `expected`
+pub(crate) fn check_content_hash(
+    key: &str,
+    version: u64,
+    content: &str,
+    expected: &str,
+) -> Result<()> {
+    let actual = crate::import_dir::content_hash(content);
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(HashMismatch {
+            key: key.to_string(),
+            version,
+            expected: expected.trim().to_string(),
+            actual,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// A manifest pinning each prompt to a version and its content hash, like Cargo.lock
+/// pins crates
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lockfile {
+    pub prompts: BTreeMap<String, LockedPrompt>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LockedPrompt {
+    pub version: u64,
+    /// `object_hash` of the version's content
+    pub hash: String,
+}
+
+impl Lockfile {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read lockfile {}: {}", path.display(), e))?;
+        serde_json::from_slice(&data)
+            .map_err(|e| anyhow::anyhow!("Invalid lockfile {}: {}", path.display(), e))
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut data = serde_json::to_string_pretty(self)?;
+        data.push('\n');
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    fn entry(&self, key: &str) -> Result<&LockedPrompt> {
+        self.prompts
+            .get(key)
+            .ok_or_else(|| anyhow::anyhow!("Prompt '{}' is not in the lockfile", key))
+    }
+}
+
+impl PromptVault {
+    /// Pin the latest version of every prompt
+    pub fn lock(&self) -> Result<Lockfile> {
+        let mut lockfile = Lockfile::default();
+        for key in self.list_keys()? {
+            let Some(meta) = self.history(&key)?.pop() else {
+                continue;
+            };
+            lockfile.prompts.insert(
+                key,
+                LockedPrompt {
+                    version: meta.version,
+                    hash: meta.object_hash,
+                },
+            );
+        }
+        Ok(lockfile)
+    }
+
+    /// Check that every version pinned in the lockfile at `path` still exists with
+    /// the locked content. Fails listing every prompt that does not match.
+    pub fn verify_lock<P: AsRef<Path>>(&self, path: P) -> Result<Lockfile> {
+        let lockfile = Lockfile::read(path)?;
+        let mut problems = Vec::new();
+        for (key, locked) in &lockfile.prompts {
+            match self.get_version_meta(key, locked.version)? {
+                None => problems.push(format!("'{}' v{} no longer exists", key, locked.version)),
+                Some(meta) if meta.object_hash != locked.hash => problems.push(format!(
+                    "'{}' v{} does not match the locked content",
+                    key, locked.version
+                )),
+                Some(_) => {}
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(anyhow::anyhow!(
+                "The vault does not match the lockfile:\n  {}",
+                problems.join("\n  ")
+            ));
+        }
+        Ok(lockfile)
+    }
+
+    /// Get prompt content only if it has the content hash `expected_hash` (the
+    /// `object_hash` of the version, as `promptpro lock` records it), so a deployment
+    /// serves exactly the bytes approved in review. Fails with [`HashMismatch`]
+    /// otherwise.
+    pub fn get_verified(
+        &self,
+        key: &str,
+        selector: VersionSelector,
+        expected_hash: &str,
+    ) -> Result<String> {
+        let prompt = self.get_versioned(key, selector)?;
+        check_content_hash(key, prompt.version, &prompt.content, expected_hash)?;
+        Ok(prompt.content)
+    }
+
+    /// Content of the version of `key` pinned in the lockfile at `lockfile`, checked
+    /// against the locked hash
+    pub fn get_locked<P: AsRef<Path>>(&self, key: &str, lockfile: P) -> Result<String> {
+        let locked = Lockfile::read(lockfile)?.entry(key)?.clone();
+        let content = self.get(key, VersionSelector::Version(locked.version))?;
+        if crate::import_dir::content_hash(&content) != locked.hash {
+            return Err(anyhow::anyhow!(
+                "'{}' v{} does not match the locked content",
+                key,
+                locked.version
+            ));
+        }
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        vault.add("writer", "You write.")?;
+        vault.update("writer", "You write posts.", None)?;
+        vault.add("planner", "Plan.")?;
+
+        let path = dir.path().join("prompts.lock");
+        vault.lock()?.write(&path)?;
+        assert_eq!(vault.verify_lock(&path)?.prompts["writer"].version, 2);
+
+        vault.update("writer", "You write anything.", None)?;
+        assert_eq!(vault.get_locked("writer", &path)?, "You write posts.");
+        assert!(vault.get_locked("missing", &path).is_err());
+
+        let locked = Lockfile::read(&path)?.prompts["writer"].clone();
+        let pinned = vault.get_verified("writer", VersionSelector::Version(2), &locked.hash)?;
+        assert_eq!(pinned, "You write posts.");
+        let error = vault
+            .get_verified("writer", VersionSelector::Latest, &locked.hash)
+            .unwrap_err();
+        let mismatch = error.downcast_ref::<HashMismatch>().unwrap();
+        assert_eq!((mismatch.version, &mismatch.expected), (3, &locked.hash));
+
+        vault.delete_prompt_key("planner")?;
+        let error = vault.verify_lock(&path).unwrap_err().to_string();
+        assert!(error.contains("'planner' v1 no longer exists"));
+
+        Ok(())
+    }
+}
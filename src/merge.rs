@@ -0,0 +1,186 @@
+//! Carrying the change of one version forward onto the latest version, for
+//! `promptpro cherry-pick`.
+
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use similar::{DiffTag, TextDiff};
+use std::ops::Range;
+
+/// A change to a run of base lines
+struct Edit<'a> {
+    /// Base lines replaced; empty for an insertion
+    old: Range<usize>,
+    lines: Vec<&'a str>,
+}
+
+impl Edit<'_> {
+    fn conflicts_with(&self, other: &Edit) -> bool {
+        let (a, b) = (&self.old, &other.old);
+        if a.is_empty() && b.is_empty() {
+            return a.start == b.start;
+        }
+        if a.is_empty() {
+            return b.start < a.start && a.start < b.end;
+        }
+        if b.is_empty() {
+            return a.start < b.start && b.start < a.end;
+        }
+        a.start < b.end && b.start < a.end
+    }
+}
+
+/// The line edits turning `base` into `changed`
+fn edits<'a>(base: &str, changed: &'a str) -> Vec<Edit<'a>> {
+    let lines: Vec<&str> = changed.split_inclusive('\n').collect();
+    TextDiff::from_lines(base, changed)
+        .ops()
+        .iter()
+        .map(|op| op.as_tag_tuple())
+        .filter(|(tag, _, _)| *tag != DiffTag::Equal)
+        .map(|(_, old, new)| Edit {
+            old,
+            lines: lines[new].to_vec(),
+        })
+        .collect()
+}
+
+/// Three-way merge of two descendants of `base`, line by line: applies the changes
+/// `theirs` made to `base` onto `ours`. Changes both sides made identically are taken
+/// once. Fails with the 1-based base line of the first change `ours` also touched.
+pub(crate) fn merge_lines(base: &str, ours: &str, theirs: &str) -> Result<String, usize> {
+    let our_edits = edits(base, ours);
+    let mut merged: Vec<&str> = ours.split_inclusive('\n').collect();
+
+    // Apply from the bottom up so that earlier positions stay valid
+    for edit in edits(base, theirs).iter().rev() {
+        let mut already_applied = false;
+        for ours in our_edits.iter().filter(|ours| ours.conflicts_with(edit)) {
+            if ours.old == edit.old && ours.lines == edit.lines {
+                already_applied = true;
+            } else {
+                return Err(edit.old.start + 1);
+            }
+        }
+        if already_applied {
+            continue;
+        }
+
+        // Shift the base position by what `ours` inserted or removed above it
+        let shift = |position: usize| -> usize {
+            let delta: isize = our_edits
+                .iter()
+                .filter(|ours| ours.old.end <= position)
+                .map(|ours| ours.lines.len() as isize - ours.old.len() as isize)
+                .sum();
+            (position as isize + delta) as usize
+        };
+        let start = shift(edit.old.start);
+        merged.splice(start..start + edit.old.len(), edit.lines.iter().copied());
+    }
+
+    Ok(merged.concat())
+}
+
+impl PromptVault {
+    /// Carry the change a version made (against its parent) forward onto the latest
+    /// version, e.g. a fix made on an old tagged version.
+    ///
+    /// The change is three-way merged line by line and stored as a new latest version;
+    /// returns its number. Fails without writing when the latest version changed the
+    /// same lines differently, or already contains the change.
+    pub fn cherry_pick(&self, key: &str, selector: VersionSelector) -> Result<u64> {
+        let source = self.resolve_version(key, &selector)?;
+        let meta = self
+            .history(key)?
+            .into_iter()
+            .find(|meta| meta.version == source)
+            .ok_or_else(|| anyhow::anyhow!("Version {} of '{}' not found", source, key))?;
+        let parent = meta.parent.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Version {} of '{}' has no parent to take a change from",
+                source,
+                key
+            )
+        })?;
+        let latest = self
+            .get_latest_version_number(key)?
+            .ok_or_else(|| anyhow::anyhow!("No versions found for key '{}'", key))?;
+
+        let base = self.get(key, VersionSelector::Version(parent))?;
+        let theirs = self.get(key, VersionSelector::Version(source))?;
+        let ours = self.get(key, VersionSelector::Version(latest))?;
+
+        let merged = merge_lines(&base, &ours, &theirs).map_err(|line| {
+            anyhow::anyhow!(
+                "Cherry-picking v{} onto v{} of '{}' conflicts at line {} of v{}; \
+                 carry the change over by hand",
+                source,
+                latest,
+                key,
+                line,
+                parent
+            )
+        })?;
+        if merged == ours {
+            return Err(anyhow::anyhow!(
+                "Version {} of '{}' already contains the change made in v{}",
+                latest,
+                key,
+                source
+            ));
+        }
+
+        self.update(key, &merged, Some(format!("cherry-picked v{}", source)))?;
+        Ok(latest + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_merge_lines() {
+        let base = "a\nb\nc\nd\n";
+        // Both sides change different lines, and insert at different places
+        assert_eq!(
+            merge_lines(base, "a\nB\nc\nd\ne\n", "x\na\nb\nc\nD\n"),
+            Ok("x\na\nB\nc\nD\ne\n".to_string())
+        );
+        // The same change on both sides
+        let both = "a\nB\nc\nd\n";
+        assert_eq!(merge_lines(base, both, both), Ok(both.to_string()));
+        // Different changes to the same line
+        assert_eq!(merge_lines(base, "a\nB\nc\nd\n", "a\nb2\nc\nd\n"), Err(2));
+    }
+
+    #[test]
+    fn test_cherry_pick() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "You write.\nBe brief.\nSign off.\n")?;
+        vault.tag("writer", "stable", 1)?;
+        // A fix that got lost when v1 was restored
+        vault.update("writer", "You write.\nBe brief.\nSign off politely.\n", None)?;
+        vault.rollback("writer", VersionSelector::tag("stable"))?;
+        vault.update("writer", "You write blog posts.\nBe brief.\nSign off.\n", None)?;
+
+        assert_eq!(vault.cherry_pick("writer", VersionSelector::Version(2))?, 5);
+        assert_eq!(
+            vault.get("writer", VersionSelector::Latest)?,
+            "You write blog posts.\nBe brief.\nSign off politely.\n"
+        );
+        assert_eq!(vault.history("writer")?[4].message.as_deref(), Some("cherry-picked v2"));
+
+        assert!(vault.cherry_pick("writer", VersionSelector::Version(2)).is_err());
+        assert!(vault.cherry_pick("writer", VersionSelector::tag("stable")).is_err());
+        vault.update("writer", "You write blog posts.\nBe brief.\nSign off warmly.\n", None)?;
+        vault.rollback("writer", VersionSelector::Version(4))?;
+        let error = vault.cherry_pick("writer", VersionSelector::Version(6)).unwrap_err();
+        assert!(error.to_string().contains("conflicts at line 3 of v5"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use crate::storage::PromptVault;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Descriptive metadata of a prompt, shared by all its versions
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PromptMeta {
+    /// What the prompt is for
+    pub description: Option<String>,
+    pub author: Option<String>,
+    /// Model the prompt is written for, e.g. `gpt-4o`
+    pub model: Option<String>,
+    /// Suggested sampling temperature
+    pub temperature: Option<f32>,
+    /// Arbitrary `name = value` labels
+    pub labels: BTreeMap<String, String>,
+}
+
+impl PromptMeta {
+    pub fn is_empty(&self) -> bool {
+        *self == PromptMeta::default()
+    }
+
+    /// Apply the fields set in `changes`; a label with an empty value is removed
+    pub fn merge(&mut self, changes: PromptMeta) {
+        if changes.description.is_some() {
+            self.description = changes.description;
+        }
+        if changes.author.is_some() {
+            self.author = changes.author;
+        }
+        if changes.model.is_some() {
+            self.model = changes.model;
+        }
+        if changes.temperature.is_some() {
+            self.temperature = changes.temperature;
+        }
+        for (name, value) in changes.labels {
+            if value.is_empty() {
+                self.labels.remove(&name);
+            } else {
+                self.labels.insert(name, value);
+            }
+        }
+    }
+
+    /// One `Field: value` line per set field, for history and the TUI
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(description) = &self.description {
+            lines.push(format!("Description: {}", description));
+        }
+        if let Some(author) = &self.author {
+            lines.push(format!("Author: {}", author));
+        }
+        match (&self.model, self.temperature) {
+            (Some(model), Some(temperature)) => {
+                lines.push(format!("Model: {} (temperature {})", model, temperature))
+            }
+            (Some(model), None) => lines.push(format!("Model: {}", model)),
+            (None, Some(temperature)) => lines.push(format!("Temperature: {}", temperature)),
+            (None, None) => {}
+        }
+        if !self.labels.is_empty() {
+            let labels: Vec<String> = self
+                .labels
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            lines.push(format!("Labels: {}", labels.join(", ")));
+        }
+        lines
+    }
+}
+
+fn prompt_meta_key(key: &str) -> String {
+    format!("prompt_meta:{}", key)
+}
+
+impl PromptVault {
+    /// Metadata of a prompt; empty when none was set
+    pub fn prompt_meta(&self, key: &str) -> Result<PromptMeta> {
+        match self.db().get(prompt_meta_key(key).as_bytes())? {
+            Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+            None => Ok(PromptMeta::default()),
+        }
+    }
+
+    /// Replace the metadata of an existing prompt
+    pub fn set_prompt_meta(&self, key: &str, meta: &PromptMeta) -> Result<()> {
+        if self.get_latest_version_number(key)?.is_none() {
+            return Err(anyhow::anyhow!("Prompt with key '{}' does not exist", key));
+        }
+        self.db()
+            .insert(prompt_meta_key(key).as_bytes(), bincode::serialize(meta)?)?;
+        Ok(())
+    }
+
+    /// Merge `changes` into the metadata of an existing prompt (see [`PromptMeta::merge`])
+    pub fn update_prompt_meta(&self, key: &str, changes: PromptMeta) -> Result<PromptMeta> {
+        let mut meta = self.prompt_meta(key)?;
+        meta.merge(changes);
+        self.set_prompt_meta(key, &meta)?;
+        Ok(meta)
+    }
+
+    /// Remove the metadata of a prompt
+    pub(crate) fn remove_prompt_meta(&self, key: &str) -> Result<()> {
+        self.remove_entry(prompt_meta_key(key).as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_prompt_meta() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "v1")?;
+        assert!(vault.prompt_meta("writer")?.is_empty());
+
+        let mut changes = PromptMeta {
+            description: Some("Blog post writer".to_string()),
+            model: Some("gpt-4o".to_string()),
+            temperature: Some(0.7),
+            ..Default::default()
+        };
+        changes.labels.insert("team".to_string(), "content".to_string());
+        vault.update_prompt_meta("writer", changes)?;
+
+        let mut changes = PromptMeta {
+            author: Some("ada".to_string()),
+            ..Default::default()
+        };
+        changes.labels.insert("team".to_string(), String::new());
+        let meta = vault.update_prompt_meta("writer", changes)?;
+        assert_eq!(meta.description.as_deref(), Some("Blog post writer"));
+        assert_eq!(meta.author.as_deref(), Some("ada"));
+        assert!(meta.labels.is_empty());
+        assert_eq!(vault.prompt_meta("writer")?, meta);
+        assert_eq!(meta.summary_lines()[2], "Model: gpt-4o (temperature 0.7)");
+
+        assert!(vault.set_prompt_meta("missing", &meta).is_err());
+        vault.delete_prompt_key("writer")?;
+        assert!(vault.prompt_meta("writer")?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+//! Upgrading vaults written by older releases to the current storage layout, run by
+//! `promptpro migrate`.
+//!
+//! Layouts: `1` keeps every version as a full snapshot and has the `keys:` index
+//! (backfilled whenever an older vault is opened); `2` stores versions as diffs against
+//! their parent, with a snapshot every [`SNAPSHOT_INTERVAL`] versions; `3` stores the
+//! content of snapshots once per distinct content, shared by the versions referencing
+//! it (see [`crate::objects`]).
+
+use crate::storage::{apply_diff, make_diff, PromptVault, SNAPSHOT_INTERVAL};
+use crate::types::{VersionMeta, VersionSelector};
+use anyhow::Result;
+use rand::seq::SliceRandom;
+use std::path::{Path, PathBuf};
+
+/// Storage layout written by this release
+pub const LAYOUT_VERSION: u32 = 3;
+
+/// Layout of the vault, once it has been recorded
+pub(crate) const LAYOUT_VERSION_KEY: &[u8] = b"meta:layout_version";
+
+/// What a migration did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub from: u32,
+    pub to: u32,
+    /// Versions rewritten as diffs or as references to shared content
+    pub repacked: usize,
+    /// Sampled versions whose content was compared before and after
+    pub verified: usize,
+    /// Dump written before anything was changed; `None` when there was nothing to do
+    pub backup: Option<PathBuf>,
+}
+
+impl PromptVault {
+    /// Storage layout of the vault (see the [module docs](self))
+    pub fn layout_version(&self) -> Result<u32> {
+        match self.db().get(LAYOUT_VERSION_KEY)? {
+            Some(bytes) => {
+                let bytes: [u8; 4] = bytes
+                    .as_ref()
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("Invalid layout version entry"))?;
+                Ok(u32::from_le_bytes(bytes))
+            }
+            // Opening the vault already backfilled the key index
+            None => Ok(1),
+        }
+    }
+
+    /// Record that a new, empty vault uses the current layout
+    pub(crate) fn mark_layout_current(&self) -> Result<()> {
+        self.db().insert(LAYOUT_VERSION_KEY, &LAYOUT_VERSION.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Upgrade the vault to [`LAYOUT_VERSION`].
+    ///
+    /// The vault is first dumped to `backup`. Afterwards `samples` randomly chosen
+    /// versions are compared with their content before the migration, and every
+    /// prompt must still have all its versions; on a mismatch the migration fails and
+    /// the backup should be restored. `progress` is called with the number of keys
+    /// done, the number of keys and the current key.
+    pub fn migrate<F>(
+        &self,
+        backup: &Path,
+        samples: usize,
+        mut progress: F,
+    ) -> Result<MigrationReport>
+    where
+        F: FnMut(usize, usize, &str),
+    {
+        let from = self.layout_version()?;
+        if from >= LAYOUT_VERSION {
+            return Ok(MigrationReport {
+                from,
+                to: from,
+                repacked: 0,
+                verified: 0,
+                backup: None,
+            });
+        }
+
+        let backup_str = backup
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("Invalid backup path {:?}", backup))?;
+        self.dump(backup_str, None)?;
+
+        let keys = self.list_keys()?;
+        let mut version_counts = Vec::with_capacity(keys.len());
+        let mut readable = Vec::new();
+        for key in &keys {
+            let history = self.history(key)?;
+            version_counts.push(history.len());
+            if !self.is_sensitive(key)? {
+                readable.extend(history.iter().map(|meta| (key.clone(), meta.version)));
+            }
+        }
+        let sampled: Vec<(String, u64, String)> = readable
+            .choose_multiple(&mut rand::thread_rng(), samples)
+            .map(|(key, version)| -> Result<_> {
+                let content = self.get(key, VersionSelector::Version(*version))?;
+                Ok((key.clone(), *version, content))
+            })
+            .collect::<Result<_>>()?;
+
+        let mut repacked = 0;
+        for (done, key) in keys.iter().enumerate() {
+            progress(done, keys.len(), key);
+            repacked += self.repack_key(key)?;
+        }
+        progress(keys.len(), keys.len(), "");
+
+        for (key, count) in keys.iter().zip(version_counts) {
+            if self.history(key)?.len() != count {
+                return Err(anyhow::anyhow!(
+                    "Verification failed: '{}' lost versions during the migration",
+                    key
+                ));
+            }
+        }
+        for (key, version, before) in &sampled {
+            if self.get(key, VersionSelector::Version(*version))? != *before {
+                return Err(anyhow::anyhow!(
+                    "Verification failed: '{}' v{} changed during the migration",
+                    key,
+                    version
+                ));
+            }
+        }
+
+        self.mark_layout_current()?;
+        self.db().flush()?;
+
+        Ok(MigrationReport {
+            from,
+            to: LAYOUT_VERSION,
+            repacked,
+            verified: sampled.len(),
+            backup: Some(backup.to_path_buf()),
+        })
+    }
+
+    /// Rewrite the full snapshots of a prompt that the current layout stores as diffs,
+    /// and move the others into the shared content store; returns how many were
+    /// rewritten
+    fn repack_key(&self, key: &str) -> Result<usize> {
+        // Protected prompts are encrypted per version and stay snapshots
+        if self.is_sensitive(key)? {
+            return Ok(0);
+        }
+
+        let history = self.history(key)?;
+        let contents = history
+            .iter()
+            .map(|meta| self.get(key, VersionSelector::Version(meta.version)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut repacked = 0;
+        for (meta, content) in history.iter().zip(&contents) {
+            // Diffs and snapshots referencing shared content are already current
+            if !meta.snapshot || self.references_object(meta)? {
+                continue;
+            }
+
+            let parent_content = meta
+                .parent
+                .filter(|_| !(meta.version - 1).is_multiple_of(SNAPSHOT_INTERVAL))
+                .and_then(|parent| history.iter().position(|m| m.version == parent))
+                .map(|index| &contents[index]);
+            let diff = match parent_content {
+                Some(parent) => {
+                    let diff = make_diff(parent, content);
+                    // Keep the snapshot rather than store a diff that does not round-trip
+                    (apply_diff(parent, &diff)? == *content).then_some(diff)
+                }
+                None => None,
+            };
+            let Some(diff) = diff else {
+                if self.share_snapshot(meta, content)? {
+                    repacked += 1;
+                }
+                continue;
+            };
+
+            // Diff first, content last: an interruption leaves a readable version
+            let diff_key = format!("diff:{}:{}", key, meta.version);
+            let blob = self.encode_blob(diff_key.as_bytes(), diff.as_bytes())?;
+            self.db().insert(diff_key.as_bytes(), blob)?;
+            self.update_version_meta(&VersionMeta {
+                snapshot: false,
+                ..meta.clone()
+            })?;
+            let content_key = format!("content:{}:{}", key, meta.version);
+            self.db().remove(content_key.as_bytes())?;
+            repacked += 1;
+        }
+
+        Ok(repacked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_migrate() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        assert_eq!(vault.layout_version()?, LAYOUT_VERSION);
+
+        // A layout 1 prompt: every version a full snapshot
+        let contents = ["a\n", "a\nb\n", "a\nb\nc\n"];
+        for (version, content) in (1..).zip(contents) {
+            let parent = (version > 1).then(|| version - 1);
+            let meta = VersionMeta::new("legacy".to_string(), version, content, parent, None);
+            let version_key = format!("version:legacy:{}", version);
+            vault.db().insert(version_key.as_bytes(), bincode::serialize(&meta)?)?;
+            let content_key = format!("content:legacy:{}", version);
+            vault.db().insert(content_key.as_bytes(), content.as_bytes())?;
+        }
+        vault.index_key("legacy")?;
+        vault.db().remove(LAYOUT_VERSION_KEY)?;
+        assert_eq!(vault.layout_version()?, 1);
+
+        let backup = dir.path().join("backup.bin");
+        let mut calls = 0;
+        let report = vault.migrate(&backup, 10, |_, _, _| calls += 1)?;
+        assert_eq!((report.from, report.to, report.repacked), (1, LAYOUT_VERSION, 3));
+        assert_eq!(report.verified, 3);
+        assert!(backup.exists());
+        assert_eq!(calls, 2);
+
+        assert_eq!(vault.layout_version()?, LAYOUT_VERSION);
+        assert!(vault.db().get(b"content:legacy:2")?.is_none());
+        // The first version's snapshot is now shared content
+        assert!(vault.db().get(b"content:legacy:1")?.is_none());
+        assert!(vault.history("legacy")?[0].snapshot);
+        assert!(!vault.history("legacy")?[2].snapshot);
+        for (version, content) in (1..).zip(contents) {
+            assert_eq!(vault.get("legacy", VersionSelector::Version(version))?, content);
+        }
+
+        // Already current: nothing to do, no backup
+        let report = vault.migrate(&dir.path().join("again.bin"), 10, |_, _, _| {})?;
+        assert_eq!((report.repacked, report.backup), (0, None));
+
+        Ok(())
+    }
+}
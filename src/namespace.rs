@@ -0,0 +1,216 @@
+use crate::storage::PromptVault;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Separator between the segments of a hierarchical key such as `team/agent/system`
+pub const NAMESPACE_SEPARATOR: char = '/';
+
+/// Prefix of the key index: one `keys:{key}` entry per prompt key
+const KEY_INDEX_PREFIX: &str = "keys:";
+
+/// Marker set once the key index covers every stored key
+const KEY_INDEX_BUILT: &[u8] = b"meta:key_index";
+
+/// Prefix of the latest-version index: one `latest:{key}` entry per prompt key
+const LATEST_INDEX_PREFIX: &str = "latest:";
+
+/// Marker set once the latest-version index covers every stored key
+const LATEST_INDEX_BUILT: &[u8] = b"meta:latest_index";
+
+fn index_entry(key: &str) -> String {
+    format!("{}{}", KEY_INDEX_PREFIX, key)
+}
+
+fn latest_entry(key: &str) -> String {
+    format!("{}{}", LATEST_INDEX_PREFIX, key)
+}
+
+/// Namespaces containing a key, outermost first: `a/b/c` is in `a` and `a/b`
+pub fn namespaces_of(key: &str) -> impl Iterator<Item = &str> {
+    key.match_indices(NAMESPACE_SEPARATOR)
+        .map(move |(position, _)| &key[..position])
+}
+
+impl PromptVault {
+    /// Record a key in the key index
+    pub(crate) fn index_key(&self, key: &str) -> Result<()> {
+        self.write_entry(index_entry(key).as_bytes(), b"")
+    }
+
+    /// Remove a key from the key index
+    pub(crate) fn unindex_key(&self, key: &str) -> Result<()> {
+        self.remove_entry(index_entry(key).as_bytes())
+    }
+
+    /// Latest version of a key as recorded in the latest-version index
+    pub(crate) fn indexed_latest(&self, key: &str) -> Result<Option<u64>> {
+        let Some(bytes) = self.read_entry(latest_entry(key).as_bytes())? else {
+            return Ok(None);
+        };
+        let bytes: [u8; 8] = bytes
+            .as_ref()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid latest version entry of '{}'", key))?;
+        Ok(Some(u64::from_le_bytes(bytes)))
+    }
+
+    /// Record a stored version in the latest-version index
+    pub(crate) fn index_version(&self, key: &str, version: u64) -> Result<()> {
+        if self.indexed_latest(key)?.is_some_and(|latest| latest >= version) {
+            return Ok(());
+        }
+        self.write_entry(latest_entry(key).as_bytes(), &version.to_le_bytes())
+    }
+
+    /// Drop a key from the latest-version index, e.g. after removing versions; it is
+    /// recorded again from the stored versions when there are any left
+    pub(crate) fn unindex_versions(&self, key: &str) -> Result<()> {
+        self.remove_entry(latest_entry(key).as_bytes())?;
+        if let Some(latest) = self.get_latest_version_number(key)? {
+            self.index_version(key, latest)?;
+        }
+        Ok(())
+    }
+
+    /// Build the key and latest-version indexes for vaults written before they existed
+    pub(crate) fn ensure_key_index(&self) -> Result<()> {
+        if !self.db().contains_key(KEY_INDEX_BUILT)?
+            || !self.db().contains_key(LATEST_INDEX_BUILT)?
+        {
+            self.rebuild_key_index()?;
+        }
+        Ok(())
+    }
+
+    /// Rebuild the key and latest-version indexes from the stored versions
+    pub(crate) fn rebuild_key_index(&self) -> Result<()> {
+        // Extract the key and version from every "version:{key}:{version}" entry
+        let mut latest: BTreeMap<String, u64> = BTreeMap::new();
+        for result in self.db().scan_prefix(b"version:") {
+            let (entry_key, _) = result?;
+            let entry_key = String::from_utf8(entry_key.to_vec())?;
+            if let Some((key, version)) = entry_key["version:".len()..].rsplit_once(':') {
+                let version: u64 = version.parse()?;
+                let entry = latest.entry(key.to_string()).or_default();
+                *entry = (*entry).max(version);
+            }
+        }
+        let mut batch = sled::Batch::default();
+        for entry in self.db().scan_prefix(LATEST_INDEX_PREFIX.as_bytes()).keys() {
+            batch.remove(entry?);
+        }
+        for (key, version) in &latest {
+            batch.insert(index_entry(key).as_bytes(), b"");
+            batch.insert(latest_entry(key).as_bytes(), &version.to_le_bytes());
+        }
+        batch.insert(KEY_INDEX_BUILT, b"");
+        batch.insert(LATEST_INDEX_BUILT, b"");
+        self.db().apply_batch(batch)?;
+        Ok(())
+    }
+
+    /// List the keys starting with `prefix`, sorted alphabetically, without scanning
+    /// the rest of the vault
+    pub fn list_keys_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for result in self.db().scan_prefix(index_entry(prefix).as_bytes()) {
+            let (entry_key, _) = result?;
+            let entry_key = String::from_utf8(entry_key.to_vec())?;
+            keys.push(entry_key[KEY_INDEX_PREFIX.len()..].to_string());
+        }
+        Ok(keys)
+    }
+
+    /// List the keys inside a namespace and its sub-namespaces, e.g. every key under
+    /// `team/agent/` for `team/agent`
+    pub fn list_namespace(&self, namespace: &str) -> Result<Vec<String>> {
+        let namespace = namespace.trim_end_matches(NAMESPACE_SEPARATOR);
+        self.list_keys_with_prefix(&format!("{}{}", namespace, NAMESPACE_SEPARATOR))
+    }
+
+    /// Delete every prompt inside a namespace and its sub-namespaces.
+    ///
+    /// Returns the deleted keys.
+    pub fn delete_namespace(&self, namespace: &str) -> Result<Vec<String>> {
+        let keys = self.list_namespace(namespace)?;
+        for key in &keys {
+            self.delete_prompt_key(key)?;
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_namespaces() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+
+        vault.add("team/agent/system", "system")?;
+        vault.add("team/agent/tools", "tools")?;
+        vault.add("team/writer", "writer")?;
+        vault.add("teams", "other")?;
+
+        assert_eq!(
+            vault.list_namespace("team/")?,
+            ["team/agent/system", "team/agent/tools", "team/writer"]
+        );
+        assert_eq!(vault.list_keys_with_prefix("team")?.len(), 4);
+        assert_eq!(
+            namespaces_of("team/agent/system").collect::<Vec<_>>(),
+            ["team", "team/agent"]
+        );
+
+        assert_eq!(vault.delete_namespace("team/agent")?.len(), 2);
+        assert_eq!(vault.list_keys()?, ["team/writer", "teams"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_index_backfill() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("team/writer", "writer")?;
+
+        // Simulate a vault written before the key index existed
+        vault.unindex_key("team/writer")?;
+        vault.db().remove(KEY_INDEX_BUILT)?;
+
+        vault.ensure_key_index()?;
+        assert_eq!(vault.list_namespace("team")?, ["team/writer"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_latest_index() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("team/writer", "v1")?;
+        vault.update("team/writer", "v2", None)?;
+        vault.add("team/planner", "plan")?;
+        assert_eq!(vault.indexed_latest("team/writer")?, Some(2));
+
+        // Simulate a vault written before the index existed; reopening backfills it
+        vault.db().remove(latest_entry("team/writer"))?;
+        vault.db().remove(LATEST_INDEX_BUILT)?;
+        drop(vault);
+        let vault = PromptVault::open(dir.path())?;
+        assert_eq!(vault.indexed_latest("team/writer")?, Some(2));
+        assert_eq!(vault.get_latest_version_number("team/writer")?, Some(2));
+
+        vault.update("team/writer", "v3", None)?;
+        assert_eq!(vault.indexed_latest("team/writer")?, Some(3));
+        vault.delete_prompt_key("team/planner")?;
+        assert_eq!(vault.indexed_latest("team/planner")?, None);
+        assert_eq!(vault.get_latest_version_number("team/planner")?, None);
+        assert!(vault.verify(false)?.is_clean());
+
+        Ok(())
+    }
+}
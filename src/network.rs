@@ -0,0 +1,103 @@
+//! The one gate every outgoing network access goes through, for offline mode.
+//!
+//! Sync over HTTP and ssh and the client of `promptpro serve` send their requests and
+//! start `ssh` only through [`send`] and [`ssh`], so offline mode (`--offline`,
+//! `PROMPTPRO_OFFLINE=1` or `offline = true` in `~/.promptpro/config.toml`) disables
+//! all of them in one place. `promptpro serve` only answers incoming requests and
+//! stays available.
+
+use crate::profile::ProfileConfig;
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Disable network access when set to `1` or `true`
+pub const OFFLINE_ENV: &str = "PROMPTPRO_OFFLINE";
+
+/// Set by [`go_offline`], e.g. for `--offline`
+static FORCED: AtomicBool = AtomicBool::new(false);
+/// Offline mode of the environment and config file, read on first use
+static CONFIGURED: OnceCell<bool> = OnceCell::new();
+
+/// Network access refused in offline mode
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{action} needs network access, which offline mode disables")]
+pub struct OfflineError {
+    /// What was refused, e.g. `Pushing to http://prompts.internal:8080`
+    pub action: String,
+}
+
+/// Refuse all network access in this process from now on
+pub fn go_offline() {
+    FORCED.store(true, Ordering::SeqCst);
+}
+
+/// Whether network access is disabled, by [`go_offline`], `PROMPTPRO_OFFLINE` or the
+/// config file
+pub fn is_offline() -> bool {
+    FORCED.load(Ordering::SeqCst) || *CONFIGURED.get_or_init(configured_offline)
+}
+
+fn configured_offline() -> bool {
+    let from_env = std::env::var(OFFLINE_ENV)
+        .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    // An unreadable config file fails the commands that select a profile; offline
+    // mode is only turned on by a config that says so
+    from_env
+        || ProfileConfig::default_path()
+            .and_then(|path| ProfileConfig::load(&path))
+            .is_ok_and(|config| config.offline)
+}
+
+/// Fail with [`OfflineError`] when `offline` is set
+fn allow(offline: bool, action: &str) -> Result<()> {
+    if offline {
+        return Err(OfflineError {
+            action: action.to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Send an HTTP request, unless offline
+pub(crate) async fn send(
+    action: &str,
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response> {
+    allow(is_offline(), action)?;
+    Ok(request.send().await?)
+}
+
+/// `ssh host command`, unless offline
+pub(crate) fn ssh(action: &str, host: &str, command: String) -> Result<tokio::process::Command> {
+    allow(is_offline(), action)?;
+    let mut ssh = tokio::process::Command::new("ssh");
+    // `--` keeps a host starting with `-` from being read as an option
+    ssh.arg("--").arg(host).arg(command);
+    Ok(ssh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_gate() -> Result<()> {
+        allow(false, "Pulling from http://prompts.internal:8080")?;
+        let error = allow(true, "Pulling from http://prompts.internal:8080").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Pulling from http://prompts.internal:8080 needs network access, which offline \
+             mode disables"
+        );
+        assert!(error.is::<OfflineError>());
+
+        let config = ProfileConfig::parse("offline = true\n[profile.work]\nauthor = \"alice\"")?;
+        assert!(config.offline);
+        assert!(!ProfileConfig::parse("")?.offline);
+
+        Ok(())
+    }
+}
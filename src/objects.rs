@@ -0,0 +1,140 @@
+//! Content-addressed storage of snapshot content.
+//!
+//! A snapshot version does not store its content itself: the content is stored once
+//! under its `object_hash` (`object:{hash}`), with the number of versions referencing
+//! it (`objref:{hash}`), so identical versions across keys and rollbacks share one
+//! copy. The object is removed when the last version referencing it is deleted.
+//!
+//! Versions of password-protected prompts, and snapshots written by older layouts,
+//! keep their own `content:{key}:{version}` entry instead.
+
+use crate::storage::PromptVault;
+use crate::types::{calculate_hash, VersionMeta};
+use anyhow::Result;
+use sled::IVec;
+
+fn object_key(hash: &str) -> String {
+    format!("object:{}", hash)
+}
+
+fn refcount_key(hash: &str) -> String {
+    format!("objref:{}", hash)
+}
+
+fn own_content_key(meta: &VersionMeta) -> String {
+    format!("content:{}:{}", meta.key, meta.version)
+}
+
+fn parse_count(bytes: Option<IVec>) -> u64 {
+    bytes
+        .and_then(|bytes| bytes.as_ref().try_into().ok())
+        .map(u64::from_le_bytes)
+        .unwrap_or(0)
+}
+
+impl PromptVault {
+    /// Whether some version already references an object with this hash
+    pub(crate) fn has_object(&self, hash: &str) -> Result<bool> {
+        Ok(self.read_entry(object_key(hash).as_bytes())?.is_some())
+    }
+
+    /// Add a reference to the object holding `content`, storing it if it is new
+    pub(crate) fn put_object(&self, hash: &str, content: &str) -> Result<()> {
+        let (object, refcount) = (object_key(hash), refcount_key(hash));
+        let blob = self.encode_blob(object.as_bytes(), content.as_bytes())?;
+        self.atomically(|vault| {
+            let count = parse_count(vault.read_entry(refcount.as_bytes())?);
+            if count == 0 {
+                vault.write_entry(object.as_bytes(), blob.as_slice())?;
+            }
+            vault.write_entry(refcount.as_bytes(), &(count + 1).to_le_bytes())
+        })
+    }
+
+    /// Drop a reference to an object, removing it with its last reference
+    pub(crate) fn release_object(&self, hash: &str) -> Result<()> {
+        let (object, refcount) = (object_key(hash), refcount_key(hash));
+        self.atomically(|vault| match parse_count(vault.read_entry(refcount.as_bytes())?) {
+            0 | 1 => {
+                vault.remove_entry(object.as_bytes())?;
+                vault.remove_entry(refcount.as_bytes())
+            }
+            count => vault.write_entry(refcount.as_bytes(), &(count - 1).to_le_bytes()),
+        })
+    }
+
+    /// Stored (possibly compressed or encrypted) content of a snapshot version with the
+    /// entry holding it: its own entry if it has one, otherwise the object it references
+    pub(crate) fn stored_snapshot(&self, meta: &VersionMeta) -> Result<Option<(String, IVec)>> {
+        let own = own_content_key(meta);
+        if let Some(content) = self.read_entry(own.as_bytes())? {
+            return Ok(Some((own, content)));
+        }
+        let object = object_key(&meta.object_hash);
+        Ok(self.read_entry(object.as_bytes())?.map(|content| (object, content)))
+    }
+
+    /// Whether a snapshot version references an object rather than storing its own
+    /// content
+    pub(crate) fn references_object(&self, meta: &VersionMeta) -> Result<bool> {
+        Ok(meta.snapshot && self.read_entry(own_content_key(meta).as_bytes())?.is_none())
+    }
+
+    /// Store the plaintext content of a snapshot version as a reference to its object,
+    /// replacing any entry of its own. Content that does not match the version's hash
+    /// keeps an entry of its own; returns whether the version references an object.
+    pub(crate) fn share_snapshot(&self, meta: &VersionMeta, content: &str) -> Result<bool> {
+        if calculate_hash(content) != meta.object_hash {
+            let own = own_content_key(meta);
+            let blob = self.encode_blob(own.as_bytes(), content.as_bytes())?;
+            self.write_entry(own.as_bytes(), blob)?;
+            return Ok(false);
+        }
+        // Reference first, entry last: an interruption leaves a readable version
+        self.put_object(&meta.object_hash, content)?;
+        self.remove_entry(own_content_key(meta).as_bytes())?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    fn objects(vault: &PromptVault) -> usize {
+        vault.db().scan_prefix(b"object:").count()
+    }
+
+    #[test]
+    fn test_shared_objects() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        let boilerplate = "You are a helpful assistant.\n";
+        vault.add("writer", boilerplate)?;
+        vault.add("planner", boilerplate)?;
+        assert_eq!(objects(&vault), 1);
+
+        // Going back to stored content references it instead of storing a diff
+        vault.update("writer", "You write.\n", None)?;
+        vault.rollback("writer", VersionSelector::Version(1))?;
+        assert!(vault.history("writer")?[2].snapshot);
+        assert_eq!(objects(&vault), 1);
+
+        vault.delete_prompt_key("planner")?;
+        assert_eq!(vault.get("writer", VersionSelector::Latest)?, boilerplate);
+
+        // Protected versions are encrypted per key and leave the store
+        vault.protect("writer", "secret")?;
+        assert_eq!(objects(&vault), 0);
+        vault.unprotect("writer", "secret")?;
+        assert_eq!(objects(&vault), 2);
+        assert_eq!(vault.get("writer", VersionSelector::Version(2))?, "You write.\n");
+
+        vault.delete_prompt_key("writer")?;
+        assert_eq!(vault.db().scan_prefix(b"obj").count(), 0);
+
+        Ok(())
+    }
+}
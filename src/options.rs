@@ -0,0 +1,153 @@
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use std::path::PathBuf;
+
+use crate::{PromptVault, VersionSelector};
+
+/// Vault directory used by the manager singletons (and the CLI)
+pub use crate::utils::VAULT_ENV;
+/// Tag served by `get_default` instead of the latest version
+pub const DEFAULT_TAG_ENV: &str = "PROMPTPRO_DEFAULT_TAG";
+/// Refuse writes through the manager singletons when set to `1` or `true`
+pub const READONLY_ENV: &str = "PROMPTPRO_READONLY";
+
+/// Options set through `configure()` before the first use of a manager singleton
+static CONFIGURED: OnceCell<ManagerOptions> = OnceCell::new();
+
+/// A manager singleton could not open its vault (missing HOME, locked vault, ...).
+///
+/// Initialization is retried on the next access, so the error is not sticky.
+#[derive(Debug, thiserror::Error)]
+#[error("Failed to initialize PromptPro manager: {0:#}")]
+pub struct InitError(anyhow::Error);
+
+impl InitError {
+    /// The underlying cause
+    pub fn cause(&self) -> &anyhow::Error {
+        &self.0
+    }
+}
+
+impl From<anyhow::Error> for InitError {
+    fn from(error: anyhow::Error) -> Self {
+        InitError(error)
+    }
+}
+
+/// Configuration for `DefaultPromptManager` and `SyncPromptManager`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManagerOptions {
+    /// Vault directory; the default vault when unset
+    pub vault_path: Option<PathBuf>,
+    /// Tag served by `get_default`; the latest version when unset
+    pub default_tag: Option<String>,
+    /// Reject add/update/tag/delete calls
+    pub read_only: bool,
+    /// Page cache size in bytes; sled's default when unset
+    pub cache_capacity: Option<u64>,
+    /// Keep the latest versions and tags in memory (see `PromptVault::preload`)
+    pub preload: bool,
+    /// Use an empty vault in memory instead of `vault_path`, e.g. for tests
+    pub in_memory: bool,
+}
+
+impl ManagerOptions {
+    /// Read options from `PROMPTPRO_VAULT`, `PROMPTPRO_DEFAULT_TAG` and `PROMPTPRO_READONLY`
+    pub fn from_env() -> Self {
+        let non_empty = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        ManagerOptions {
+            vault_path: crate::utils::vault_path_from_env(),
+            default_tag: non_empty(DEFAULT_TAG_ENV),
+            read_only: non_empty(READONLY_ENV)
+                .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            ..Default::default()
+        }
+    }
+
+    /// Options for a singleton's first use: the configured ones, else the environment
+    pub(crate) fn for_singleton() -> Self {
+        CONFIGURED.get().cloned().unwrap_or_else(Self::from_env)
+    }
+
+    /// Store options for the singletons; fails if options were already configured
+    pub(crate) fn configure(options: ManagerOptions) -> Result<()> {
+        CONFIGURED
+            .set(options)
+            .map_err(|_| anyhow::anyhow!("PromptPro manager options are already configured"))
+    }
+
+    /// Open the configured vault
+    pub(crate) fn open_vault(&self) -> Result<PromptVault> {
+        let vault = if self.in_memory {
+            PromptVault::open_in_memory()?
+        } else {
+            let path = match &self.vault_path {
+                Some(path) => path.clone(),
+                None => crate::utils::default_vault_path()?,
+            };
+            std::fs::create_dir_all(&path)?;
+            match self.cache_capacity {
+                Some(capacity) => PromptVault::open_with_cache_capacity(&path, capacity)?,
+                None => PromptVault::open(&path)?,
+            }
+        };
+        if self.preload {
+            vault.preload()?;
+        }
+        Ok(vault)
+    }
+
+    /// Error out of write operations when read-only
+    pub(crate) fn check_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(anyhow::anyhow!("PromptPro manager is read-only"));
+        }
+        Ok(())
+    }
+
+    /// Selector used by `get_default`
+    pub(crate) fn default_selector(&self) -> VersionSelector {
+        match &self.default_tag {
+            Some(tag) => VersionSelector::tag(tag),
+            None => VersionSelector::Latest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_manager_options() -> Result<()> {
+        let dir = tempdir()?;
+        let options = ManagerOptions {
+            vault_path: Some(dir.path().join("vault")),
+            default_tag: Some("prod".to_string()),
+            read_only: true,
+            preload: true,
+            ..Default::default()
+        };
+
+        let vault = options.open_vault()?;
+        assert!(vault.is_preloaded());
+        vault.add("greet", "hello")?;
+        vault.tag("greet", "prod", 1)?;
+        assert_eq!(vault.get("greet", options.default_selector())?, "hello");
+        assert!(options.check_writable().is_err());
+        assert!(ManagerOptions::default().check_writable().is_ok());
+
+        let in_memory = ManagerOptions {
+            in_memory: true,
+            ..Default::default()
+        };
+        let scratch = in_memory.open_vault()?;
+        scratch.add("greet", "hi")?;
+        assert!(in_memory.open_vault()?.list_keys()?.is_empty());
+
+        Ok(())
+    }
+}
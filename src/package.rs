@@ -0,0 +1,302 @@
+//! Prompt packages: a curated set of prompts of a namespace, one pinned version each,
+//! written to a `.ppk` file by `promptpro pack` and added to another vault by
+//! `promptpro install`.
+//!
+//! A package is JSON holding, for each prompt, its key relative to the namespace, the
+//! content of the packed version, the tags of that version and the prompt's metadata.
+//! Installed prompts record the package as their [`Provenance`].
+
+use crate::adopt::Provenance;
+use crate::metadata::PromptMeta;
+use crate::namespace::NAMESPACE_SEPARATOR;
+use crate::storage::PromptVault;
+use crate::types::{calculate_hash, VersionSelector};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Version of the package format written by [`Package::write`]
+pub const PACKAGE_FORMAT_VERSION: u32 = 1;
+
+/// Tag every latest version carries, which installing sets on its own
+const DEV_TAG: &str = "dev";
+
+/// A set of prompts packed from a namespace
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Package {
+    pub format: u32,
+    /// Namespace the prompts were packed from, and are installed into by default
+    pub namespace: String,
+    pub created_at: DateTime<Utc>,
+    pub prompts: Vec<PackagedPrompt>,
+}
+
+/// One prompt of a package
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PackagedPrompt {
+    /// Key inside the namespace, e.g. `writer` for `team/writer`
+    pub name: String,
+    /// Version the content was packed from
+    pub version: u64,
+    /// Tags of that version
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "PromptMeta::is_empty")]
+    pub meta: PromptMeta,
+    pub content: String,
+    /// Hash of the content, checked when installing
+    pub hash: String,
+}
+
+impl Package {
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| anyhow::anyhow!("Cannot write {}: {}", path.display(), e))
+    }
+
+    /// Read a package, checking its format and the hashes of its prompts
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        let package: Package = serde_json::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("{} is no prompt package: {}", path.display(), e))?;
+        if package.format > PACKAGE_FORMAT_VERSION {
+            return Err(anyhow::anyhow!(
+                "{} has package format {}; this promptpro reads up to {}",
+                path.display(),
+                package.format,
+                PACKAGE_FORMAT_VERSION
+            ));
+        }
+        for prompt in &package.prompts {
+            if prompt.name.is_empty() || calculate_hash(&prompt.content) != prompt.hash {
+                return Err(anyhow::anyhow!(
+                    "{} is corrupt: prompt '{}' does not match its hash",
+                    path.display(),
+                    prompt.name
+                ));
+            }
+        }
+        Ok(package)
+    }
+}
+
+/// What to do when a prompt of a package already exists in the vault with other content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InstallPolicy {
+    /// Install nothing
+    #[default]
+    Fail,
+    /// Keep the existing prompt
+    Skip,
+    /// Add the packaged content as a new version of the existing prompt
+    Update,
+}
+
+impl std::str::FromStr for InstallPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fail" => Ok(InstallPolicy::Fail),
+            "skip" => Ok(InstallPolicy::Skip),
+            "update" => Ok(InstallPolicy::Update),
+            other => Err(anyhow::anyhow!(
+                "Unknown conflict policy '{}', expected 'fail', 'skip' or 'update'",
+                other
+            )),
+        }
+    }
+}
+
+/// What installing a package did, by key
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InstallReport {
+    /// Keys the vault did not have
+    pub installed: Vec<String>,
+    /// Existing keys that got the packaged content as a new version
+    pub updated: Vec<String>,
+    /// Existing keys whose latest version already has the packaged content
+    pub unchanged: Vec<String>,
+    /// Existing keys with other content, kept as they are
+    pub skipped: Vec<String>,
+}
+
+/// `namespace/name`, or `name` for the root namespace
+fn full_key(namespace: &str, name: &str) -> String {
+    match namespace.trim_end_matches(NAMESPACE_SEPARATOR) {
+        "" => name.to_string(),
+        namespace => format!("{}{}{}", namespace, NAMESPACE_SEPARATOR, name),
+    }
+}
+
+impl PromptVault {
+    /// Pack the prompts of `namespace`, taking the version `selector` selects of each,
+    /// e.g. a `stable` tag. Prompts the selector does not resolve for are left out, as
+    /// are password-protected ones; both are returned as skipped keys.
+    pub fn pack(
+        &self,
+        namespace: &str,
+        selector: &VersionSelector,
+    ) -> Result<(Package, Vec<String>)> {
+        let namespace = namespace.trim_end_matches(NAMESPACE_SEPARATOR);
+        let prefix = full_key(namespace, "");
+        let keys = match namespace {
+            "" => self.list_keys()?,
+            namespace => self.list_namespace(namespace)?,
+        };
+        if keys.is_empty() {
+            return Err(anyhow::anyhow!("No prompts in namespace '{}'", namespace));
+        }
+
+        let mut prompts = Vec::new();
+        let mut skipped = Vec::new();
+        for key in keys {
+            let version = match self.resolve_version(&key, selector) {
+                Ok(version) if !self.is_sensitive(&key)? => version,
+                _ => {
+                    skipped.push(key);
+                    continue;
+                }
+            };
+            let Some(meta) = self.get_version_meta(&key, version)? else {
+                skipped.push(key);
+                continue;
+            };
+            let content = self.get(&key, VersionSelector::Version(version))?;
+            prompts.push(PackagedPrompt {
+                name: key[prefix.len()..].to_string(),
+                version,
+                tags: meta.tags.into_iter().filter(|tag| tag != DEV_TAG).collect(),
+                meta: self.prompt_meta(&key)?,
+                hash: calculate_hash(&content),
+                content,
+            });
+        }
+        let package = Package {
+            format: PACKAGE_FORMAT_VERSION,
+            namespace: namespace.to_string(),
+            created_at: Utc::now(),
+            prompts,
+        };
+        Ok((package, skipped))
+    }
+
+    /// Install the prompts of a package into `namespace`, by default the one they were
+    /// packed from, tagging each like the packed version.
+    ///
+    /// With [`InstallPolicy::Fail`] nothing is installed when a prompt exists with
+    /// other content; prompts whose latest version has the packaged content are left
+    /// alone whatever the policy.
+    pub fn install(
+        &self,
+        package: &Package,
+        namespace: Option<&str>,
+        policy: InstallPolicy,
+    ) -> Result<InstallReport> {
+        let namespace = namespace.unwrap_or(&package.namespace);
+        let mut report = InstallReport::default();
+        let mut pending = Vec::new();
+        for prompt in &package.prompts {
+            let key = full_key(namespace, &prompt.name);
+            match self.get_latest_version_number(&key)? {
+                None => pending.push((key, prompt, false)),
+                Some(_) if self.get(&key, VersionSelector::Latest)? == prompt.content => {
+                    report.unchanged.push(key)
+                }
+                Some(_) => match policy {
+                    InstallPolicy::Fail => {
+                        return Err(anyhow::anyhow!(
+                            "'{}' already exists with other content; install with \
+                             --on-conflict skip or update",
+                            key
+                        ))
+                    }
+                    InstallPolicy::Skip => report.skipped.push(key),
+                    InstallPolicy::Update => pending.push((key, prompt, true)),
+                },
+            }
+        }
+
+        let source = format!("package {}", package.namespace);
+        for (key, prompt, exists) in pending {
+            if exists {
+                let message = format!("Installed from {} v{}", source, prompt.version);
+                self.update(&key, &prompt.content, Some(message))?;
+                report.updated.push(key.clone());
+            } else {
+                self.add(&key, &prompt.content)?;
+                if !prompt.meta.is_empty() {
+                    self.set_prompt_meta(&key, &prompt.meta)?;
+                }
+                report.installed.push(key.clone());
+            }
+            let version = self
+                .get_latest_version_number(&key)?
+                .ok_or_else(|| self.unknown_key(&key))?;
+            for tag in &prompt.tags {
+                self.tag(&key, tag, version)?;
+            }
+            let provenance = Provenance {
+                source: source.clone(),
+                source_version: prompt.version,
+                with_history: false,
+                adopted_at: Utc::now(),
+            };
+            self.set_provenance(&key, &provenance)?;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_pack_and_install() -> Result<()> {
+        let dir = tempdir()?;
+        let source = PromptVault::open(dir.path().join("source"))?;
+        source.add("team/writer", "Write.")?;
+        source.update("team/writer", "Write well.", None)?;
+        source.tag("team/writer", "stable", 1)?;
+        source.add("team/agents/support", "Help.")?;
+        source.tag("team/agents/support", "stable", 1)?;
+        source.add("team/draft", "Untagged.")?;
+        source.add("other", "Not packed.")?;
+
+        let (package, skipped) = source.pack("team/", &VersionSelector::tag("stable"))?;
+        assert_eq!(skipped, ["team/draft"]);
+        let names: Vec<(&str, u64)> =
+            package.prompts.iter().map(|p| (p.name.as_str(), p.version)).collect();
+        assert_eq!(names, [("agents/support", 1), ("writer", 1)]);
+        let path = dir.path().join("team.ppk");
+        package.write(&path)?;
+        let package = Package::read(&path)?;
+
+        // Installing into another namespace keeps the pinned content and the tags
+        let target = PromptVault::open(dir.path().join("target"))?;
+        target.add("shared/writer", "Write badly.")?;
+        assert!(target.install(&package, Some("shared"), InstallPolicy::Fail).is_err());
+        assert!(target.get("shared/agents/support", VersionSelector::Latest).is_err());
+        let report = target.install(&package, Some("shared"), InstallPolicy::Skip)?;
+        assert_eq!((report.installed.len(), report.skipped.len()), (1, 1));
+        assert_eq!(target.get("shared/agents/support", VersionSelector::tag("stable"))?, "Help.");
+        let report = target.install(&package, Some("shared"), InstallPolicy::Update)?;
+        assert_eq!(report.updated, ["shared/writer"]);
+        assert_eq!(report.unchanged, ["shared/agents/support"]);
+        assert_eq!(target.get("shared/writer", VersionSelector::tag("stable"))?, "Write.");
+        let provenance = target.provenance("shared/writer")?.unwrap();
+        assert_eq!((provenance.source.as_str(), provenance.source_version), ("package team", 1));
+
+        // Tampered packages are refused
+        std::fs::write(&path, std::fs::read_to_string(&path)?.replace("Help.", "Harm."))?;
+        assert!(Package::read(&path).is_err());
+
+        Ok(())
+    }
+}
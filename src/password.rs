@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+
+/// Environment variable consulted for dump passwords in automation
+pub const PASSWORD_ENV: &str = "PROMPTPRO_PASSWORD";
+
+/// Keyring service name under which promptpro stores its passwords
+const KEYRING_SERVICE: &str = "promptpro";
+
+/// Keyring entry used when no explicit entry name is given
+pub const DEFAULT_KEYRING_ENTRY: &str = "default";
+
+/// Places a password can come from, checked in order:
+/// explicit value, password file, `PROMPTPRO_PASSWORD`, OS keyring, interactive prompt.
+#[derive(Debug, Clone)]
+pub struct PasswordOptions {
+    /// Password given directly on the command line (visible in shell history and `ps`)
+    pub password: Option<String>,
+    /// File whose contents are the password
+    pub password_file: Option<String>,
+    /// Keyring entry to look up
+    pub keyring_entry: String,
+}
+
+impl Default for PasswordOptions {
+    fn default() -> Self {
+        PasswordOptions {
+            password: None,
+            password_file: None,
+            keyring_entry: DEFAULT_KEYRING_ENTRY.to_string(),
+        }
+    }
+}
+
+impl PasswordOptions {
+    pub fn new(password: Option<String>, password_file: Option<String>) -> Self {
+        PasswordOptions {
+            password,
+            password_file,
+            ..Default::default()
+        }
+    }
+
+    /// Look up a different keyring entry than the default one
+    pub fn with_keyring_entry(mut self, entry: impl Into<String>) -> Self {
+        self.keyring_entry = entry.into();
+        self
+    }
+
+    /// Whether the caller explicitly supplied a password or password file
+    pub fn is_explicit(&self) -> bool {
+        self.password.is_some() || self.password_file.is_some()
+    }
+
+    /// Resolve a password without prompting; `None` if no source provides one
+    pub fn resolve(&self) -> Result<Option<String>> {
+        if let Some(password) = &self.password {
+            return Ok(Some(password.clone()));
+        }
+
+        if let Some(path) = &self.password_file {
+            let contents = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read password file '{}'", path))?;
+            return Ok(Some(contents.trim_end_matches(['\r', '\n']).to_string()));
+        }
+
+        if let Ok(password) = std::env::var(PASSWORD_ENV) {
+            if !password.is_empty() {
+                return Ok(Some(password));
+            }
+        }
+
+        Ok(keyring_password(&self.keyring_entry))
+    }
+
+    /// Resolve a password, falling back to a hidden interactive prompt
+    pub fn resolve_or_prompt(&self, prompt: &str) -> Result<String> {
+        match self.resolve()? {
+            Some(password) => Ok(password),
+            None => rpassword::prompt_password(prompt).context("Failed to read password"),
+        }
+    }
+}
+
+/// Look up a password in the OS keyring, treating any keyring failure as "not stored"
+fn keyring_password(entry: &str) -> Option<String> {
+    keyring::Entry::new(KEYRING_SERVICE, entry)
+        .and_then(|entry| entry.get_password())
+        .ok()
+}
+
+/// Keyring entry holding the passphrase of a protected prompt
+pub fn prompt_keyring_entry(key: &str) -> String {
+    format!("prompt:{}", key)
+}
+
+/// Keyring entry holding the password of an encrypted vault
+pub fn vault_keyring_entry(path: &std::path::Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    format!("vault:{}", path.display())
+}
+
+/// Store a password in the OS keyring
+pub fn store_in_keyring(entry: &str, password: &str) -> Result<()> {
+    keyring::Entry::new(KEYRING_SERVICE, entry)?
+        .set_password(password)
+        .context("Failed to store password in the OS keyring")
+}
+
+/// Remove a password from the OS keyring
+pub fn delete_from_keyring(entry: &str) -> Result<()> {
+    keyring::Entry::new(KEYRING_SERVICE, entry)?
+        .delete_credential()
+        .context("Failed to remove password from the OS keyring")
+}
+
+/// Ask for a new password twice on a hidden prompt
+pub fn prompt_new_password() -> Result<String> {
+    let password = rpassword::prompt_password("New password: ")?;
+    let confirm = rpassword::prompt_password("Confirm password: ")?;
+    if password != confirm {
+        return Err(anyhow::anyhow!("Passwords do not match"));
+    }
+    if password.is_empty() {
+        return Err(anyhow::anyhow!("Password cannot be empty"));
+    }
+    Ok(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_password_sources() -> Result<()> {
+        let dir = tempdir()?;
+        let file = dir.path().join("pw.txt");
+        std::fs::write(&file, "from-file\n")?;
+
+        // An explicit password wins over the password file
+        let explicit = PasswordOptions::new(
+            Some("explicit".to_string()),
+            Some(file.to_string_lossy().to_string()),
+        );
+        assert_eq!(explicit.resolve()?, Some("explicit".to_string()));
+
+        // Trailing newlines are stripped from password files
+        let from_file = PasswordOptions::new(None, Some(file.to_string_lossy().to_string()));
+        assert_eq!(from_file.resolve()?, Some("from-file".to_string()));
+
+        // A missing password file is an error rather than a silent fallback
+        let missing = PasswordOptions::new(None, Some("/nonexistent/pw".to_string()));
+        assert!(missing.resolve().is_err());
+
+        Ok(())
+    }
+}
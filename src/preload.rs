@@ -0,0 +1,130 @@
+use crate::storage::PromptVault;
+use crate::types::VersionMeta;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Latest version and tags of one key
+#[derive(Debug, Default, Clone)]
+struct KeyEntry {
+    latest: u64,
+    tags: HashMap<String, u64>,
+}
+
+/// In-memory copy of the latest versions and tags, filled by [`PromptVault::preload`].
+///
+/// Shared by the clones of a vault and kept current by their writes; empty (and
+/// ignored) until the vault is preloaded.
+#[derive(Debug, Default)]
+pub(crate) struct IndexCache {
+    keys: RwLock<Option<HashMap<String, KeyEntry>>>,
+}
+
+impl IndexCache {
+    /// Latest version of a key, or `None` when the vault is not preloaded
+    pub(crate) fn latest(&self, key: &str) -> Option<Option<u64>> {
+        let keys = self.keys.read().unwrap();
+        let keys = keys.as_ref()?;
+        Some(keys.get(key).map(|entry| entry.latest))
+    }
+
+    /// Version a tag points at, or `None` when the vault is not preloaded
+    pub(crate) fn tag(&self, key: &str, tag: &str) -> Option<Option<u64>> {
+        let keys = self.keys.read().unwrap();
+        let keys = keys.as_ref()?;
+        Some(keys.get(key).and_then(|entry| entry.tags.get(tag).copied()))
+    }
+
+    pub(crate) fn record_version(&self, key: &str, version: u64) {
+        if let Some(keys) = self.keys.write().unwrap().as_mut() {
+            let entry = keys.entry(key.to_string()).or_default();
+            entry.latest = entry.latest.max(version);
+        }
+    }
+
+    pub(crate) fn record_tag(&self, key: &str, tag: &str, version: u64) {
+        if let Some(keys) = self.keys.write().unwrap().as_mut() {
+            let entry = keys.entry(key.to_string()).or_default();
+            entry.tags.insert(tag.to_string(), version);
+        }
+    }
+
+    pub(crate) fn forget_tag(&self, key: &str, tag: &str) {
+        if let Some(entry) = self.keys.write().unwrap().as_mut().and_then(|k| k.get_mut(key)) {
+            entry.tags.remove(tag);
+        }
+    }
+
+    pub(crate) fn forget(&self, key: &str) {
+        if let Some(keys) = self.keys.write().unwrap().as_mut() {
+            keys.remove(key);
+        }
+    }
+}
+
+impl PromptVault {
+    /// Load the latest version and tags of every prompt into memory, so resolving
+    /// `Latest` or a tag no longer scans the vault.
+    ///
+    /// Meant for read-heavy services such as `promptpro serve`; writes made through
+    /// this vault or its clones keep the copy current.
+    pub fn preload(&self) -> Result<()> {
+        // Hold the lock while scanning so concurrent writes are recorded afterwards
+        let mut cache = self.index_cache().keys.write().unwrap();
+        let mut keys: HashMap<String, KeyEntry> = HashMap::new();
+
+        for result in self.db().scan_prefix(b"version:") {
+            let (_key, value) = result?;
+            let meta = VersionMeta::decode(&value)?;
+            let entry = keys.entry(meta.key).or_default();
+            entry.latest = entry.latest.max(meta.version);
+            for tag in meta.tags {
+                entry.tags.insert(tag, meta.version);
+            }
+        }
+
+        *cache = Some(keys);
+        Ok(())
+    }
+
+    /// Whether [`PromptVault::preload`] has been called
+    pub fn is_preloaded(&self) -> bool {
+        self.index_cache().keys.read().unwrap().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_preload() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open_with_cache_capacity(dir.path(), 64 * 1024 * 1024)?;
+        vault.add("writer", "v1")?;
+        vault.update("writer", "v2", None)?;
+        vault.tag("writer", "stable", 1)?;
+
+        vault.preload()?;
+        assert!(vault.is_preloaded());
+        assert_eq!(vault.get("writer", VersionSelector::Latest)?, "v2");
+        assert_eq!(vault.get("writer", VersionSelector::tag("stable"))?, "v1");
+
+        // Writes through a clone keep the preloaded index current
+        let clone = vault.clone();
+        clone.update("writer", "v3", None)?;
+        clone.promote("writer", "stable")?;
+        clone.add("reviewer", "r1")?;
+        assert_eq!(vault.get("writer", VersionSelector::Latest)?, "v3");
+        assert_eq!(vault.get("writer", VersionSelector::tag("stable"))?, "v3");
+        assert_eq!(vault.get("reviewer", VersionSelector::Latest)?, "r1");
+
+        vault.delete_prompt_key("writer")?;
+        assert_eq!(vault.get_latest_version_number("writer")?, None);
+        assert!(vault.get("writer", VersionSelector::tag("stable")).is_err());
+
+        Ok(())
+    }
+}
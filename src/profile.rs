@@ -0,0 +1,218 @@
+//! Named CLI profiles, read from `~/.promptpro/config.toml`.
+//!
+//! A profile bundles the vault, the default remote of `push`/`pull` and the author
+//! recorded by `add`, so that one `--profile work` (or `PROMPTPRO_PROFILE=work`)
+//! switches between clients or teams:
+//!
+//! ```toml
+//! [profile.work]
+//! vault = "~/clients/acme/vault"
+//! remote = "http://prompts.acme.internal:8080"
+//! author = "alice"
+//! ```
+//!
+//! Versions record who stored them: the author given with `--author`, else
+//! `PROMPTPRO_AUTHOR`, else the profile's author, else `name` of the `[user]` table
+//! (set with `promptpro config set user.name alice`).
+
+use crate::eval::LlmConfig;
+use crate::tokens::TokenConfig;
+use crate::validate::ValidatorConfig;
+use crate::webhook::Webhook;
+use anyhow::{Context as _, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Profile used when `--profile` is not given
+pub const PROFILE_ENV: &str = "PROMPTPRO_PROFILE";
+
+/// Author of new versions when `--author` is not given
+pub const AUTHOR_ENV: &str = "PROMPTPRO_AUTHOR";
+
+/// One `[profile.NAME]` table; every setting is optional
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    #[serde(skip)]
+    pub name: String,
+    /// Vault directory; `~/` is the home directory
+    pub vault: Option<PathBuf>,
+    /// Remote of `push` and `pull` when none is given
+    pub remote: Option<String>,
+    /// Author recorded by `add` and on new versions when `--author` is not given
+    pub author: Option<String>,
+}
+
+/// The `[user]` table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+    /// Author of new versions when neither `--author`, `PROMPTPRO_AUTHOR` nor the
+    /// profile names one
+    pub name: Option<String>,
+}
+
+impl Profile {
+    /// The vault directory with `~/` expanded
+    pub fn vault_path(&self) -> Result<Option<PathBuf>> {
+        let Some(vault) = &self.vault else {
+            return Ok(None);
+        };
+        match vault.strip_prefix("~") {
+            Ok(relative) => Ok(Some(PathBuf::from(std::env::var("HOME")?).join(relative))),
+            Err(_) => Ok(Some(vault.clone())),
+        }
+    }
+}
+
+/// The profiles of the config file, and its settings for every profile
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    /// Disable network access, as `--offline` does
+    #[serde(default)]
+    pub offline: bool,
+    #[serde(default)]
+    pub user: UserConfig,
+    /// Token counting and the budget `add`, `update` and the TUI warn about
+    #[serde(default)]
+    pub tokens: TokenConfig,
+    /// The API `promptpro test` sends test cases to, see [`crate::eval`]
+    #[serde(default)]
+    pub llm: LlmConfig,
+    #[serde(default)]
+    pub profile: BTreeMap<String, Profile>,
+    /// Receivers of vault changes, see [`crate::webhook`]
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    /// Checks of new versions, see [`crate::validate`]
+    #[serde(default)]
+    pub validators: Vec<ValidatorConfig>,
+}
+
+impl ProfileConfig {
+    /// `~/.promptpro/config.toml`
+    pub fn default_path() -> Result<PathBuf> {
+        let home_dir = std::env::var("HOME")?;
+        Ok(PathBuf::from(home_dir).join(".promptpro").join("config.toml"))
+    }
+
+    /// Parse a config file; a missing file has no profiles
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut config: ProfileConfig = toml::from_str(text)?;
+        for (name, profile) in &mut config.profile {
+            profile.name = name.clone();
+        }
+        for webhook in &config.webhooks {
+            webhook.check()?;
+        }
+        Ok(config)
+    }
+
+    /// The profile called `name`, or an error listing the existing ones
+    pub fn get(&self, name: &str) -> Result<&Profile> {
+        self.profile.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.profile.keys().map(String::as_str).collect();
+            if names.is_empty() {
+                anyhow::anyhow!("Unknown profile '{}': no profiles are configured", name)
+            } else {
+                anyhow::anyhow!(
+                    "Unknown profile '{}'; configured profiles: {}",
+                    name,
+                    names.join(", ")
+                )
+            }
+        })
+    }
+}
+
+/// Profile of this process, once selected
+static ACTIVE: OnceCell<Option<Profile>> = OnceCell::new();
+
+/// Load the profile named `name`, else the one `PROMPTPRO_PROFILE` names
+fn resolve(name: Option<&str>) -> Result<Option<Profile>> {
+    let from_env = std::env::var(PROFILE_ENV).ok().filter(|name| !name.is_empty());
+    let Some(name) = name.map(str::to_string).or(from_env) else {
+        return Ok(None);
+    };
+    let config = ProfileConfig::load(&ProfileConfig::default_path()?)?;
+    Ok(Some(config.get(&name)?.clone()))
+}
+
+/// Select the profile of this process: `name` (from `--profile`), else the one
+/// `PROMPTPRO_PROFILE` names. Fails once a profile is in use.
+pub fn select_profile(name: Option<&str>) -> Result<()> {
+    let profile = resolve(name)?;
+    ACTIVE
+        .set(profile)
+        .map_err(|_| anyhow::anyhow!("A profile is already in use"))
+}
+
+/// The selected profile, selecting the one `PROMPTPRO_PROFILE` names on first use
+pub fn active_profile() -> Result<Option<&'static Profile>> {
+    Ok(ACTIVE.get_or_try_init(|| resolve(None))?.as_ref())
+}
+
+/// Author of the versions this process stores: `explicit` (from `--author`), else
+/// `PROMPTPRO_AUTHOR`, the active profile's author or `user.name` of the config file
+pub fn resolve_author(explicit: Option<&str>) -> Result<Option<String>> {
+    if let Some(author) = explicit.filter(|author| !author.is_empty()) {
+        return Ok(Some(author.to_string()));
+    }
+    if let Some(author) = std::env::var(AUTHOR_ENV).ok().filter(|author| !author.is_empty()) {
+        return Ok(Some(author));
+    }
+    if let Some(author) = active_profile()?.and_then(|profile| profile.author.clone()) {
+        return Ok(Some(author));
+    }
+    Ok(ProfileConfig::load(&ProfileConfig::default_path()?)?.user.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_config() -> Result<()> {
+        let config = ProfileConfig::parse(
+            r#"
+            [profile.work]
+            vault = "~/clients/acme/vault"
+            remote = "http://prompts.acme.internal:8080"
+            author = "alice"
+
+            [profile.personal]
+            vault = "/srv/prompts"
+
+            [user]
+            name = "Alice Example"
+            "#,
+        )?;
+        assert_eq!(config.user.name.as_deref(), Some("Alice Example"));
+
+        let work = config.get("work")?;
+        assert_eq!(work.name, "work");
+        assert_eq!(work.author.as_deref(), Some("alice"));
+        let home = PathBuf::from(std::env::var("HOME")?);
+        assert_eq!(work.vault_path()?, Some(home.join("clients/acme/vault")));
+        let personal = config.get("personal")?;
+        assert_eq!(personal.vault_path()?, Some(PathBuf::from("/srv/prompts")));
+        assert_eq!(personal.remote, None);
+
+        let error = config.get("client").unwrap_err().to_string();
+        assert_eq!(error, "Unknown profile 'client'; configured profiles: personal, work");
+        assert!(ProfileConfig::parse("[profile.work]\nvalut = \"typo\"").is_err());
+        assert!(ProfileConfig::load(Path::new("/nonexistent/config.toml"))?.profile.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,350 @@
+use crate::encryption::KdfParams;
+use crate::storage::PromptVault;
+use crate::types::{VersionMeta, VersionSelector};
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::Result;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Placeholder shown in place of a locked sensitive prompt's content
+pub const REDACTED: &str = "•••";
+
+/// Plaintext encrypted into the `sensitive:` marker to verify passphrases
+const PASSPHRASE_CHECK: &[u8] = b"promptpro-sensitive";
+
+const NONCE_LEN: usize = 12;
+
+/// Key derivation of a protected prompt, stored bincode-encoded as its `sensitive:`
+/// marker
+#[derive(Serialize, Deserialize)]
+struct Protection {
+    salt: [u8; 16],
+    params: KdfParams,
+    /// [`PASSPHRASE_CHECK`] sealed with the derived key
+    check: Vec<u8>,
+}
+
+impl Protection {
+    fn derive(&self, passphrase: &str) -> Result<PromptCipher> {
+        let mut key = [0u8; 32];
+        self.params.derive(passphrase, &self.salt, &mut key)?;
+        Ok(PromptCipher(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))))
+    }
+}
+
+/// Key the versions of a protected prompt are encrypted with, derived with Argon2id;
+/// each blob is bound to the entry it is stored under
+struct PromptCipher(Aes256Gcm);
+
+impl PromptCipher {
+    /// Nonce and ciphertext of `data`, authenticated together with `entry`
+    fn seal(&self, entry: &str, data: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let payload = Payload {
+            msg: data,
+            aad: entry.as_bytes(),
+        };
+        let ciphertext = self
+            .0
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn open(&self, entry: &str, blob: &[u8]) -> Result<Vec<u8>> {
+        if blob.len() < NONCE_LEN {
+            return Err(anyhow::anyhow!("Encrypted data is too short"));
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: entry.as_bytes(),
+        };
+        self.0
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| anyhow::anyhow!("Decryption failed"))
+    }
+}
+
+/// A new `sensitive:` marker for `key` and the cipher its versions are encrypted with
+fn new_protection(key: &str, passphrase: &str) -> Result<(Vec<u8>, PromptCipher)> {
+    let mut protection = Protection {
+        salt: [0u8; 16],
+        params: KdfParams::default(),
+        check: Vec::new(),
+    };
+    rand::thread_rng().fill_bytes(&mut protection.salt);
+    let cipher = protection.derive(passphrase)?;
+    protection.check = cipher.seal(&marker_key(key), PASSPHRASE_CHECK)?;
+    Ok((bincode::serialize(&protection)?, cipher))
+}
+
+fn marker_key(key: &str) -> String {
+    format!("sensitive:{}", key)
+}
+
+fn content_key(key: &str, version: u64) -> String {
+    format!("content:{}:{}", key, version)
+}
+
+/// Sensitive prompts explicitly unlocked for display, with their passphrases.
+///
+/// History previews, the TUI and other listings show locked sensitive prompts as
+/// [`REDACTED`]; unlocking a key lets them show its real content.
+#[derive(Debug, Clone, Default)]
+pub struct UnlockedKeys {
+    passphrases: HashMap<String, String>,
+}
+
+impl UnlockedKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Unlock a key after checking its passphrase
+    pub fn unlock(&mut self, vault: &PromptVault, key: &str, passphrase: &str) -> Result<()> {
+        vault.verify_passphrase(key, passphrase)?;
+        self.passphrases
+            .insert(key.to_string(), passphrase.to_string());
+        Ok(())
+    }
+
+    /// Passphrase of an unlocked key
+    pub fn passphrase(&self, key: &str) -> Option<&str> {
+        self.passphrases.get(key).map(String::as_str)
+    }
+
+    pub fn is_unlocked(&self, key: &str) -> bool {
+        self.passphrases.contains_key(key)
+    }
+}
+
+impl PromptVault {
+    /// Whether a key is password-protected
+    pub fn is_sensitive(&self, key: &str) -> Result<bool> {
+        Ok(self.read_entry(marker_key(key).as_bytes())?.is_some())
+    }
+
+    /// Mark a key as sensitive and encrypt all of its versions with `passphrase`.
+    ///
+    /// Protected content stays encrypted in the vault and in dumps, whether or not
+    /// the dump itself is encrypted. The versions and the marker are rewritten in one
+    /// transaction, so an interruption leaves the prompt as it was.
+    pub fn protect(&self, key: &str, passphrase: &str) -> Result<()> {
+        if self.is_sensitive(key)? {
+            return Err(anyhow::anyhow!("Prompt '{}' is already password-protected", key));
+        }
+
+        let (marker, cipher) = new_protection(key, passphrase)?;
+        self.atomically(|vault| {
+            if vault.is_sensitive(key)? {
+                return Err(anyhow::anyhow!("Prompt '{}' is already password-protected", key));
+            }
+            let versions = vault.history(key)?;
+            if versions.is_empty() {
+                return Err(anyhow::anyhow!("Prompt with key '{}' does not exist", key));
+            }
+
+            // Reconstruct every version before rewriting any, since diffs read their parent
+            let contents = versions
+                .iter()
+                .map(|meta| vault.get(key, VersionSelector::Version(meta.version)))
+                .collect::<Result<Vec<_>>>()?;
+
+            // Protected versions are stored as encrypted snapshots of their own rather
+            // than diffs or shared content
+            for (mut meta, content) in versions.into_iter().zip(contents) {
+                let shared = vault.references_object(&meta)?;
+                let entry = content_key(key, meta.version);
+                let encrypted = cipher.seal(&entry, content.as_bytes())?;
+                vault.write_entry(entry.as_bytes(), encrypted)?;
+                if shared {
+                    vault.release_object(&meta.object_hash)?;
+                }
+                if !meta.snapshot {
+                    meta.snapshot = true;
+                    vault.update_version_meta(&meta)?;
+                    vault.remove_entry(format!("diff:{}:{}", key, meta.version).as_bytes())?;
+                }
+            }
+
+            vault.write_entry(marker_key(key).as_bytes(), marker.as_slice())
+        })
+    }
+
+    /// Remove password protection from a key, storing its versions in plaintext again,
+    /// in one transaction with removing its marker
+    pub fn unprotect(&self, key: &str, passphrase: &str) -> Result<()> {
+        let cipher = self.prompt_cipher(key, passphrase)?;
+
+        self.atomically(|vault| {
+            for meta in vault.history(key)? {
+                let content = vault.decrypt_version(key, meta.version, &cipher)?;
+                vault.share_snapshot(&meta, &content)?;
+            }
+            vault.remove_entry(marker_key(key).as_bytes())
+        })
+    }
+
+    /// Get the content of a password-protected prompt
+    pub fn get_protected(
+        &self,
+        key: &str,
+        selector: VersionSelector,
+        passphrase: &str,
+    ) -> Result<String> {
+        let cipher = self.prompt_cipher(key, passphrase)?;
+        let version = self.resolve_version(key, &selector)?;
+        self.decrypt_version(key, version, &cipher)
+    }
+
+    /// Update a password-protected prompt, encrypting the new version with `passphrase`
+    pub fn update_protected(
+        &self,
+        key: &str,
+        content: &str,
+        message: Option<String>,
+        passphrase: &str,
+    ) -> Result<()> {
+        let cipher = self.prompt_cipher(key, passphrase)?;
+
+        let parent_version = self
+            .get_latest_version_number(key)?
+            .ok_or_else(|| anyhow::anyhow!("Prompt with key '{}' does not exist", key))?;
+        if self.decrypt_version(key, parent_version, &cipher)? == content {
+            return Err(anyhow::anyhow!("No changes detected in content"));
+        }
+
+        let new_version = parent_version + 1;
+        let mut version_meta = VersionMeta::new(
+            key.to_string(),
+            new_version,
+            content,
+            Some(parent_version),
+            message,
+        );
+        version_meta.author = self.author().map(str::to_string);
+        version_meta.validated = self.validate(key, content)?;
+        let entry = content_key(key, new_version);
+        let encrypted = cipher.seal(&entry, content.as_bytes())?;
+        self.atomically(|vault| {
+            vault.claim_version(&version_meta)?;
+            vault.write_entry(entry.as_bytes(), encrypted.as_slice())?;
+
+            // Keep 'dev' on the latest version, as update() does
+            let _ = vault.tag(key, "dev", new_version);
+            Ok(())
+        })
+    }
+
+    /// Content of a version for display, redacting sensitive prompts that are not unlocked
+    pub fn display_content(
+        &self,
+        key: &str,
+        version: u64,
+        unlocked: &UnlockedKeys,
+    ) -> Result<String> {
+        if !self.is_sensitive(key)? {
+            return self.get(key, VersionSelector::Version(version));
+        }
+
+        match unlocked.passphrase(key) {
+            Some(passphrase) => {
+                self.get_protected(key, VersionSelector::Version(version), passphrase)
+            }
+            None => Ok(REDACTED.to_string()),
+        }
+    }
+
+    /// Check a passphrase against the key's marker
+    fn verify_passphrase(&self, key: &str, passphrase: &str) -> Result<()> {
+        self.prompt_cipher(key, passphrase).map(|_| ())
+    }
+
+    /// The cipher of a protected key, once `passphrase` is checked against its marker
+    fn prompt_cipher(&self, key: &str, passphrase: &str) -> Result<PromptCipher> {
+        let marker_key = marker_key(key);
+        let marker = self
+            .db()
+            .get(marker_key.as_bytes())?
+            .ok_or_else(|| anyhow::anyhow!("Prompt '{}' is not password-protected", key))?;
+
+        let protection: Protection = bincode::deserialize(&marker)?;
+        let cipher = protection.derive(passphrase)?;
+        match cipher.open(&marker_key, &protection.check) {
+            Ok(plain) if plain == PASSPHRASE_CHECK => Ok(cipher),
+            _ => Err(anyhow::anyhow!("Wrong passphrase for prompt '{}'", key)),
+        }
+    }
+
+    fn decrypt_version(&self, key: &str, version: u64, cipher: &PromptCipher) -> Result<String> {
+        let entry = content_key(key, version);
+        let encrypted = self.read_entry(entry.as_bytes())?.ok_or_else(|| {
+            anyhow::anyhow!("Content not found for key '{}', version {}", key, version)
+        })?;
+        let plain = cipher.open(&entry, &encrypted)?;
+        Ok(String::from_utf8(plain)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_protected_prompt() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+
+        vault.add("secret", "system prompt v1")?;
+        vault.protect("secret", "hunter2")?;
+        assert!(vault.is_sensitive("secret")?);
+
+        // Plain reads are refused, protected reads need the right passphrase
+        assert!(vault.get("secret", VersionSelector::Latest).is_err());
+        assert!(vault
+            .get_protected("secret", VersionSelector::Latest, "wrong")
+            .is_err());
+        assert_eq!(
+            vault.get_protected("secret", VersionSelector::Latest, "hunter2")?,
+            "system prompt v1"
+        );
+
+        vault.update_protected("secret", "system prompt v2", None, "hunter2")?;
+        assert_eq!(
+            vault.get_protected("secret", VersionSelector::tag("dev"), "hunter2")?,
+            "system prompt v2"
+        );
+
+        // Unencrypted dumps keep the protected content encrypted
+        let dump = dir.path().join("plain.vault");
+        vault.dump(dump.to_str().unwrap(), None)?;
+        let raw = std::fs::read(&dump)?;
+        assert!(!raw.windows(16).any(|w| w == b"system prompt v2"));
+
+        // Listings redact the prompt until it is explicitly unlocked
+        let mut unlocked = UnlockedKeys::new();
+        assert_eq!(vault.display_content("secret", 2, &unlocked)?, REDACTED);
+        assert!(unlocked.unlock(&vault, "secret", "wrong").is_err());
+        unlocked.unlock(&vault, "secret", "hunter2")?;
+        assert_eq!(vault.display_content("secret", 2, &unlocked)?, "system prompt v2");
+
+        // Versions are bound to their entry and cannot be swapped
+        let (v1, v2) = (content_key("secret", 1), content_key("secret", 2));
+        let first = vault.db().get(&v1)?.unwrap();
+        vault.db().insert(&v1, vault.db().get(&v2)?.unwrap())?;
+        assert!(vault.get_protected("secret", VersionSelector::Version(1), "hunter2").is_err());
+        vault.db().insert(&v1, first)?;
+
+        vault.unprotect("secret", "hunter2")?;
+        assert_eq!(vault.get("secret", VersionSelector::Version(1))?, "system prompt v1");
+
+        Ok(())
+    }
+}
@@ -1,6 +1,17 @@
-use crate::{PromptVault, VersionMeta, VersionSelector};
+use crate::{
+    NamespaceStats, PromptVault, SearchHit, SearchOptions, VaultStats, VersionMeta,
+    VersionSelector,
+};
 use pyo3::prelude::*;
 use pyo3::types::PyList;
+use std::collections::BTreeMap;
+
+pyo3::create_exception!(
+    promptpro,
+    InitError,
+    pyo3::exceptions::PyException,
+    "Raised when the prompt manager singleton cannot open its vault."
+);
 
 /// Python wrapper for VersionMeta
 #[pyclass]
@@ -22,6 +33,10 @@ pub struct PyVersionMeta {
     pub snapshot: bool,
     #[pyo3(get)]
     pub tags: Vec<String>,
+    #[pyo3(get)]
+    pub author: Option<String>,
+    #[pyo3(get)]
+    pub validated: Option<bool>,
 }
 
 impl From<VersionMeta> for PyVersionMeta {
@@ -35,6 +50,91 @@ impl From<VersionMeta> for PyVersionMeta {
             object_hash: meta.object_hash,
             snapshot: meta.snapshot,
             tags: meta.tags,
+            author: meta.author,
+            validated: meta.validated,
+        }
+    }
+}
+
+/// Python wrapper for SearchHit
+#[pyclass]
+#[derive(Clone)]
+pub struct PySearchHit {
+    #[pyo3(get)]
+    pub key: String,
+    #[pyo3(get)]
+    pub version: u64,
+    /// "key", "content", "message" or "tag"
+    #[pyo3(get)]
+    pub field: String,
+    #[pyo3(get)]
+    pub snippet: String,
+}
+
+impl From<SearchHit> for PySearchHit {
+    fn from(hit: SearchHit) -> Self {
+        PySearchHit {
+            key: hit.key,
+            version: hit.version,
+            field: hit.field.to_string(),
+            snippet: hit.snippet,
+        }
+    }
+}
+
+/// Python wrapper for NamespaceStats
+#[pyclass]
+#[derive(Clone)]
+pub struct PyNamespaceStats {
+    #[pyo3(get)]
+    pub keys: u64,
+    #[pyo3(get)]
+    pub versions: u64,
+    #[pyo3(get)]
+    pub bytes: u64,
+    #[pyo3(get)]
+    pub last_write: Option<String>,
+}
+
+impl From<NamespaceStats> for PyNamespaceStats {
+    fn from(stats: NamespaceStats) -> Self {
+        PyNamespaceStats {
+            keys: stats.keys,
+            versions: stats.versions,
+            bytes: stats.bytes,
+            last_write: stats.last_write.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+/// Python wrapper for VaultStats
+#[pyclass]
+#[derive(Clone)]
+pub struct PyVaultStats {
+    #[pyo3(get)]
+    pub keys: u64,
+    #[pyo3(get)]
+    pub versions: u64,
+    #[pyo3(get)]
+    pub bytes: u64,
+    #[pyo3(get)]
+    pub last_write: Option<String>,
+    #[pyo3(get)]
+    pub namespaces: BTreeMap<String, PyNamespaceStats>,
+}
+
+impl From<VaultStats> for PyVaultStats {
+    fn from(stats: VaultStats) -> Self {
+        PyVaultStats {
+            keys: stats.keys,
+            versions: stats.versions,
+            bytes: stats.bytes,
+            last_write: stats.last_write.map(|t| t.to_rfc3339()),
+            namespaces: stats
+                .namespaces
+                .into_iter()
+                .map(|(name, stats)| (name, stats.into()))
+                .collect(),
         }
     }
 }
@@ -60,6 +160,14 @@ impl PyPromptVault {
         Ok(PyPromptVault { inner: vault })
     }
 
+    /// Create an empty PromptVault in memory, e.g. for tests; no vault directory is used
+    #[staticmethod]
+    fn in_memory() -> PyResult<Self> {
+        let vault = PromptVault::open_in_memory()
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))?;
+        Ok(PyPromptVault { inner: vault })
+    }
+
     /// Add a new prompt with the given key and content
     fn add(&self, key: &str, content: &str) -> PyResult<()> {
         self.inner
@@ -67,10 +175,17 @@ impl PyPromptVault {
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
     }
 
-    /// Update an existing prompt with new content
-    fn update(&self, key: &str, content: &str, message: Option<String>) -> PyResult<()> {
+    /// Update an existing prompt with new content; with `if_version`, only if that is
+    /// still its latest version. Returns the new version number.
+    fn update(
+        &self,
+        key: &str,
+        content: &str,
+        message: Option<String>,
+        if_version: Option<u64>,
+    ) -> PyResult<u64> {
         self.inner
-            .update(key, content, message)
+            .update_expecting(key, content, message, if_version)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
     }
 
@@ -82,6 +197,15 @@ impl PyPromptVault {
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
     }
 
+    /// Get prompt content by key and selector as `(version, content)`
+    fn get_versioned(&self, key: &str, selector: &PyAny) -> PyResult<(u64, String)> {
+        let version_selector = parse_version_selector(selector)?;
+        self.inner
+            .get_versioned(key, version_selector)
+            .map(|prompt| (prompt.version, prompt.content))
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
+    }
+
     /// Get the latest version of a prompt
     fn get_latest(&self, key: &str) -> PyResult<String> {
         self.inner
@@ -106,6 +230,13 @@ impl PyPromptVault {
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
     }
 
+    /// Remove a tag; returns the version it pointed to
+    fn untag(&self, key: &str, tag: &str) -> PyResult<u64> {
+        self.inner
+            .untag(key, tag)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
+    }
+
     /// Promote a tag to point to the latest version
     fn promote(&self, key: &str, tag: &str) -> PyResult<()> {
         self.inner
@@ -149,34 +280,64 @@ impl PyPromptVault {
             .delete_prompt_key(key)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
     }
+
+    /// Count keys, versions and stored bytes, in total and per top-level namespace
+    fn stats(&self) -> PyResult<PyVaultStats> {
+        self.inner
+            .stats()
+            .map(PyVaultStats::from)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
+    }
+
+    /// Create or update one prompt per .txt/.md file under `path`; returns the keys
+    /// by outcome (`created`, `updated`, `unchanged`, `protected`)
+    fn import_dir(&self, path: &str) -> PyResult<BTreeMap<String, Vec<String>>> {
+        let report = self
+            .inner
+            .import_dir(path)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))?;
+        Ok(BTreeMap::from([
+            ("created".to_string(), report.created),
+            ("updated".to_string(), report.updated),
+            ("unchanged".to_string(), report.unchanged),
+            ("protected".to_string(), report.protected),
+        ]))
+    }
+
+    /// Search with a query such as `tag:stable author:alice "json output" key:agents/*`
+    fn search(
+        &self,
+        query: &str,
+        regex: Option<bool>,
+        case_sensitive: Option<bool>,
+        all_versions: Option<bool>,
+    ) -> PyResult<Vec<PySearchHit>> {
+        let options = SearchOptions {
+            regex: regex.unwrap_or(false),
+            case_sensitive: case_sensitive.unwrap_or(false),
+            all_versions: all_versions.unwrap_or(false),
+        };
+        self.inner
+            .search(query, &options)
+            .map(|hits| hits.into_iter().map(PySearchHit::from).collect())
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
+    }
 }
 
-/// Parse Python object to VersionSelector
+/// Parse Python object to VersionSelector: `None` is the latest version, an integer a
+/// version number, and a string is parsed with [`VersionSelector::parse`]
 fn parse_version_selector(selector: &PyAny) -> PyResult<VersionSelector> {
-    use pyo3::types::PyString;
-
     if selector.is_none() {
         Ok(VersionSelector::Latest)
     } else if let Ok(version) = selector.extract::<u64>() {
         Ok(VersionSelector::Version(version))
-    } else if let Ok(tag) = selector.extract::<String>() {
-        if tag == "latest" {
-            Ok(VersionSelector::Latest)
-        } else {
-            Ok(VersionSelector::Tag(Box::leak(tag.into_boxed_str())))
-        }
-    } else if let Ok(tag) = selector.downcast::<PyString>() {
-        let tag_str = tag.to_str()?;
-        if tag_str == "latest" {
-            Ok(VersionSelector::Latest)
-        } else {
-            Ok(VersionSelector::Tag(Box::leak(
-                tag_str.to_string().into_boxed_str(),
-            )))
-        }
+    } else if let Ok(selector) = selector.extract::<&str>() {
+        VersionSelector::parse(selector)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
     } else {
         Err(pyo3::exceptions::PyValueError::new_err(
-            "Invalid version selector. Must be a string (tag) or integer (version).",
+            "Invalid version selector. Must be None, an integer (version) or a string \
+             (tag, latest, v3, @2024-05-01T12:00, @-7d).",
         ))
     }
 }
@@ -201,11 +362,25 @@ impl PySyncPromptManager {
         Ok(PySyncPromptManager { inner: manager })
     }
 
-    /// Get the singleton instance
+    /// Create a manager of an empty vault in memory, e.g. for tests
+    #[staticmethod]
+    fn in_memory() -> PyResult<Self> {
+        let options = crate::options::ManagerOptions {
+            in_memory: true,
+            ..Default::default()
+        };
+        let manager = crate::sync_api::SyncPromptManager::with_options(options)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))?;
+        Ok(PySyncPromptManager { inner: manager })
+    }
+
+    /// Get the singleton instance; raises InitError if the vault cannot be opened
     #[staticmethod]
     fn get() -> PyResult<PySyncPromptManager> {
+        let manager = crate::sync_api::SyncPromptManager::try_get()
+            .map_err(|e| InitError::new_err(e.to_string()))?;
         Ok(PySyncPromptManager {
-            inner: crate::sync_api::SyncPromptManager::get().clone(),
+            inner: manager.clone(),
         })
     }
 
@@ -216,10 +391,17 @@ impl PySyncPromptManager {
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
     }
 
-    /// Update a prompt
-    fn update(&self, key: &str, content: &str, message: Option<&str>) -> PyResult<()> {
+    /// Update a prompt; with `if_version`, only if that is still its latest version.
+    /// Returns the new version number.
+    fn update(
+        &self,
+        key: &str,
+        content: &str,
+        message: Option<&str>,
+        if_version: Option<u64>,
+    ) -> PyResult<u64> {
         self.inner
-            .update(key, content, message)
+            .update_if(key, content, message, if_version)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
     }
 
@@ -230,6 +412,13 @@ impl PySyncPromptManager {
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
     }
 
+    /// Remove a tag; returns the version it pointed to
+    fn untag(&self, key: &str, tag: &str) -> PyResult<u64> {
+        self.inner
+            .untag(key, tag)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
+    }
+
     /// Get a prompt by selector
     fn get_prompt(&self, key: &str, selector: &PyAny) -> PyResult<String> {
         let version_selector = parse_version_selector(selector)?;
@@ -238,6 +427,15 @@ impl PySyncPromptManager {
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
     }
 
+    /// Get a prompt by selector as `(version, content)`
+    fn get_versioned(&self, key: &str, selector: &PyAny) -> PyResult<(u64, String)> {
+        let version_selector = parse_version_selector(selector)?;
+        self.inner
+            .get_versioned(key, version_selector)
+            .map(|prompt| (prompt.version, prompt.content))
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
+    }
+
     /// Get latest version of a prompt
     fn latest(&self, key: &str) -> PyResult<String> {
         self.inner
@@ -245,6 +443,13 @@ impl PySyncPromptManager {
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
     }
 
+    /// Get a prompt at PROMPTPRO_DEFAULT_TAG (latest when unset)
+    fn get_default(&self, key: &str) -> PyResult<String> {
+        self.inner
+            .get_default(key)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyException, _>(e.to_string()))
+    }
+
     /// Get history of a prompt
     fn history(&self, key: &str) -> PyResult<Vec<PyVersionMeta>> {
         let versions = self
@@ -303,9 +508,13 @@ fn run_cli(args: &PyList) -> PyResult<()> {
 
 /// Python module initialization
 #[pymodule]
-fn promptpro(_py: Python, m: &PyModule) -> PyResult<()> {
+fn promptpro(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("InitError", py.get_type::<InitError>())?;
     m.add_class::<PyPromptVault>()?;
     m.add_class::<PyVersionMeta>()?;
+    m.add_class::<PySearchHit>()?;
+    m.add_class::<PyVaultStats>()?;
+    m.add_class::<PyNamespaceStats>()?;
     m.add_class::<PySyncPromptManager>()?;
     m.add_function(wrap_pyfunction!(run_cli, m)?)?;
     Ok(())
@@ -0,0 +1,441 @@
+//! Push and pull prompts between vaults, e.g. between the machines of a team.
+//!
+//! Versions are reconciled per key: a side whose history is a prefix of the other's is
+//! fast-forwarded, and keys where both sides added different versions since they last
+//! agreed are reported as conflicts and left untouched. Tags follow the side being
+//! copied from. Deleted keys and password-protected prompts are never synced.
+
+use crate::export::{VaultExport, VersionExport};
+use crate::network;
+use crate::storage::PromptVault;
+use crate::types::{VersionMeta, VersionSelector};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+/// Where `push` and `pull` send and fetch prompts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Remote {
+    /// A vault directory on this machine or a mounted share
+    Vault(PathBuf),
+    /// A `promptpro serve` instance, e.g. `http://prompts.internal:8080`
+    Http(String),
+    /// A vault on another machine reached with `ssh`, e.g. `ssh://alice@build/srv/vault`.
+    /// `promptpro` must be on the remote `PATH`.
+    Ssh { host: String, path: String },
+    /// A vault in S3-compatible object storage, e.g. `s3://team-prompts/prod`
+    #[cfg(feature = "s3")]
+    S3(crate::s3::S3Remote),
+}
+
+impl std::str::FromStr for Remote {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(Remote::Http(s.trim_end_matches('/').to_string()))
+        } else if let Some(rest) = s.strip_prefix("ssh://") {
+            match rest.find('/') {
+                Some(slash) if slash > 0 && slash + 1 < rest.len() => {
+                    let (host, path) = rest.split_at(slash);
+                    // The host is passed to ssh, which would take `-oProxyCommand=...` as
+                    // an option
+                    if host.starts_with('-') || host.contains(char::is_whitespace) {
+                        return Err(anyhow::anyhow!(
+                            "Invalid remote '{}': '{}' is not a host name",
+                            s,
+                            host
+                        ));
+                    }
+                    Ok(Remote::Ssh {
+                        host: host.to_string(),
+                        // `ssh://host/~/vault` is relative to the remote home directory
+                        path: path.strip_prefix("/~/").unwrap_or(path).to_string(),
+                    })
+                }
+                _ => Err(anyhow::anyhow!(
+                    "Invalid remote '{}', expected ssh://host/path/to/vault",
+                    s
+                )),
+            }
+        } else if s.starts_with("s3://") {
+            s3_remote(s)
+        } else {
+            Ok(Remote::Vault(PathBuf::from(s)))
+        }
+    }
+}
+
+/// Versions and tags to copy into one side of a sync
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SyncPatch {
+    pub(crate) prompts: Vec<PromptPatch>,
+}
+
+impl SyncPatch {
+    /// Append `versions` to `key` after its version `base`, then point `tags` at versions
+    pub(crate) fn push(
+        &mut self,
+        key: &str,
+        base: u64,
+        versions: Vec<VersionExport>,
+        tags: BTreeMap<String, u64>,
+    ) {
+        self.prompts.push(PromptPatch {
+            key: key.to_string(),
+            base,
+            versions,
+            tags,
+        });
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PromptPatch {
+    pub(crate) key: String,
+    /// Latest version of the receiving side when the patch was planned, 0 for a new key
+    pub(crate) base: u64,
+    /// Versions to append after `base`
+    pub(crate) versions: Vec<VersionExport>,
+    /// Tags to point at a version
+    pub(crate) tags: BTreeMap<String, u64>,
+}
+
+/// Outcome of a push or pull
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Keys that received new versions, with the number of versions copied
+    pub updated: Vec<(String, usize)>,
+    /// Keys whose tags moved without new versions
+    pub retagged: Vec<String>,
+    /// Keys where both sides added different versions since they last agreed
+    pub conflicts: Vec<String>,
+}
+
+impl SyncReport {
+    /// Whether nothing needed to be copied
+    pub fn is_up_to_date(&self) -> bool {
+        self.updated.is_empty() && self.retagged.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+/// Work out what `target` is missing from `source`
+fn plan(source: &VaultExport, target: &VaultExport) -> (SyncPatch, SyncReport) {
+    let mut patch = SyncPatch::default();
+    let mut report = SyncReport::default();
+
+    for prompt in &source.prompts {
+        let theirs: &[VersionExport] = target
+            .prompts
+            .iter()
+            .find(|p| p.key == prompt.key)
+            .map_or(&[][..], |p| p.versions.as_slice());
+        let common = prompt
+            .versions
+            .iter()
+            .zip(theirs)
+            .take_while(|(ours, theirs)| ours.content == theirs.content)
+            .count();
+        if common < prompt.versions.len() && common < theirs.len() {
+            report.conflicts.push(prompt.key.clone());
+            continue;
+        }
+
+        let versions: Vec<VersionExport> = prompt.versions[common..]
+            .iter()
+            .map(|version| VersionExport {
+                tags: Vec::new(),
+                ..version.clone()
+            })
+            .collect();
+        let latest = prompt.versions.len().max(theirs.len()) as u64;
+
+        let current_tags: BTreeMap<&str, u64> = theirs
+            .iter()
+            .flat_map(|version| {
+                version
+                    .tags
+                    .iter()
+                    .map(move |tag| (tag.as_str(), version.version))
+            })
+            .collect();
+        let mut tags = BTreeMap::new();
+        for version in &prompt.versions {
+            for tag in &version.tags {
+                // 'dev' always points at the latest version
+                if tag == "dev" && version.version != latest {
+                    continue;
+                }
+                if current_tags.get(tag.as_str()) != Some(&version.version) {
+                    tags.insert(tag.clone(), version.version);
+                }
+            }
+        }
+
+        if versions.is_empty() && tags.is_empty() {
+            continue;
+        }
+        if versions.is_empty() {
+            report.retagged.push(prompt.key.clone());
+        } else {
+            report.updated.push((prompt.key.clone(), versions.len()));
+        }
+        patch.prompts.push(PromptPatch {
+            key: prompt.key.clone(),
+            base: theirs.len() as u64,
+            versions,
+            tags,
+        });
+    }
+
+    (patch, report)
+}
+
+impl PromptVault {
+    /// Copy the versions and tags the remote is missing to the remote
+    pub async fn push(&self, remote: &Remote) -> Result<SyncReport> {
+        let (patch, report) = plan(&self.export_prompts()?, &remote.fetch().await?);
+        if !patch.prompts.is_empty() {
+            remote.apply(&patch).await?;
+        }
+        Ok(report)
+    }
+
+    /// Copy the versions and tags this vault is missing from the remote
+    pub async fn pull(&self, remote: &Remote) -> Result<SyncReport> {
+        let (patch, report) = plan(&remote.fetch().await?, &self.export_prompts()?);
+        self.apply_sync_patch(&patch)?;
+        Ok(report)
+    }
+
+    /// Apply a patch planned against this vault.
+    ///
+    /// Fails without changing anything if one of the keys got new versions since the
+    /// patch was planned.
+    pub(crate) fn apply_sync_patch(&self, patch: &SyncPatch) -> Result<()> {
+        for prompt in &patch.prompts {
+            if self.is_sensitive(&prompt.key)? {
+                return Err(anyhow::anyhow!(
+                    "Prompt '{}' is password-protected and cannot be synced",
+                    prompt.key
+                ));
+            }
+            let latest = self.get_latest_version_number(&prompt.key)?.unwrap_or(0);
+            if latest != prompt.base {
+                return Err(anyhow::anyhow!(
+                    "Prompt '{}' changed during the sync (v{} instead of v{}); try again",
+                    prompt.key,
+                    latest,
+                    prompt.base
+                ));
+            }
+            for (expected, version) in (prompt.base + 1..).zip(&prompt.versions) {
+                if version.version != expected {
+                    return Err(anyhow::anyhow!(
+                        "Prompt '{}' has version {} where version {} was expected",
+                        prompt.key,
+                        version.version,
+                        expected
+                    ));
+                }
+            }
+        }
+
+        for prompt in &patch.prompts {
+            let mut parent_content = match prompt.base {
+                0 => None,
+                base => Some(self.get(&prompt.key, VersionSelector::Version(base))?),
+            };
+            for version in &prompt.versions {
+                let parent = version.version.checked_sub(1).filter(|v| *v > 0);
+                let mut meta = VersionMeta::new(
+                    prompt.key.clone(),
+                    version.version,
+                    &version.content,
+                    parent,
+                    version.message.clone(),
+                );
+                meta.timestamp = version.timestamp;
+                meta.author = version.author.clone();
+                meta.validated = version.validated;
+                self.store_version_after(&mut meta, &version.content, parent_content.as_deref())?;
+                parent_content = Some(version.content.clone());
+            }
+            for (tag, version) in &prompt.tags {
+                self.tag(&prompt.key, tag, *version)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Remote {
+    /// Every syncable prompt of the remote vault
+    async fn fetch(&self) -> Result<VaultExport> {
+        match self {
+            Remote::Vault(path) => PromptVault::open(path)?.export_prompts(),
+            Remote::Http(url) => {
+                let request = reqwest::Client::new().get(format!("{}/sync", url));
+                let response = network::send(&format!("Syncing with {}", url), request).await?;
+                Ok(http_result(response).await?.json().await?)
+            }
+            Remote::Ssh { host, path } => {
+                let output = ssh(host, "fetch", path)?.output().await?;
+                check_ssh(host, &output)?;
+                Ok(serde_json::from_slice(&output.stdout)?)
+            }
+            #[cfg(feature = "s3")]
+            Remote::S3(s3) => s3.fetch().await,
+        }
+    }
+
+    /// Apply a patch to the remote vault
+    async fn apply(&self, patch: &SyncPatch) -> Result<()> {
+        match self {
+            Remote::Vault(path) => PromptVault::open(path)?.apply_sync_patch(patch),
+            Remote::Http(url) => {
+                let request = reqwest::Client::new()
+                    .post(format!("{}/sync", url))
+                    .json(patch);
+                let response = network::send(&format!("Syncing with {}", url), request).await?;
+                http_result(response).await?;
+                Ok(())
+            }
+            Remote::Ssh { host, path } => {
+                let mut child = ssh(host, "apply", path)?
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::piped())
+                    .spawn()?;
+                let mut stdin = child.stdin.take().expect("stdin is piped");
+                stdin.write_all(&serde_json::to_vec(patch)?).await?;
+                drop(stdin);
+                check_ssh(host, &child.wait_with_output().await?)
+            }
+            #[cfg(feature = "s3")]
+            Remote::S3(s3) => s3.apply(patch).await,
+        }
+    }
+}
+
+#[cfg(feature = "s3")]
+fn s3_remote(s: &str) -> Result<Remote> {
+    Ok(Remote::S3(s.parse()?))
+}
+
+#[cfg(not(feature = "s3"))]
+fn s3_remote(_s: &str) -> Result<Remote> {
+    Err(anyhow::anyhow!(
+        "s3:// remotes require promptpro to be built with the `s3` feature"
+    ))
+}
+
+/// Turn an error response of `promptpro serve` into an error
+async fn http_result(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response
+        .json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|body| body["error"].as_str().map(str::to_string))
+        .unwrap_or_default();
+    Err(anyhow::anyhow!("Remote answered {}: {}", status, message))
+}
+
+/// `ssh host promptpro sync-remote <action> <path>`
+fn ssh(host: &str, action: &str, path: &str) -> Result<tokio::process::Command> {
+    // The remote command goes through the remote shell, so quote the path
+    let quoted = format!("'{}'", path.replace('\'', "'\\''"));
+    let command = format!("promptpro sync-remote {} {}", action, quoted);
+    network::ssh(&format!("Syncing with ssh://{}", host), host, command)
+}
+
+fn check_ssh(host: &str, output: &std::process::Output) -> Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "Sync over ssh with {} failed: {}",
+        host,
+        String::from_utf8_lossy(&output.stderr).trim()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_remote() -> Result<()> {
+        assert_eq!(
+            "https://prompts.internal/".parse::<Remote>()?,
+            Remote::Http("https://prompts.internal".to_string())
+        );
+        assert_eq!(
+            "ssh://alice@build/~/vault".parse::<Remote>()?,
+            Remote::Ssh {
+                host: "alice@build".to_string(),
+                path: "vault".to_string(),
+            }
+        );
+        assert_eq!(
+            "../shared".parse::<Remote>()?,
+            Remote::Vault(PathBuf::from("../shared"))
+        );
+        assert!("ssh://build".parse::<Remote>().is_err());
+        assert!("ssh://-oProxyCommand=touch${IFS}pwned/vault".parse::<Remote>().is_err());
+        assert!("ssh://build -oProxyCommand=sh/vault".parse::<Remote>().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_push_pull() -> Result<()> {
+        let dir = tempdir()?;
+        let remote = Remote::Vault(dir.path().join("remote"));
+        let alice = PromptVault::open(dir.path().join("alice"))?;
+        let bob = PromptVault::open(dir.path().join("bob"))?;
+
+        alice.add("writer", "v1")?;
+        alice.update("writer", "v2", Some("second".to_string()))?;
+        alice.tag("writer", "stable", 1)?;
+        let report = alice.push(&remote).await?;
+        assert_eq!(report.updated, [("writer".to_string(), 2)]);
+
+        let report = bob.pull(&remote).await?;
+        assert_eq!(report.updated, [("writer".to_string(), 2)]);
+        assert_eq!(bob.get("writer", VersionSelector::tag("stable"))?, "v1");
+        assert_eq!(bob.get("writer", VersionSelector::tag("dev"))?, "v2");
+        assert_eq!(bob.history("writer")?[1].message.as_deref(), Some("second"));
+
+        // Fast-forward and tag moves
+        bob.update("writer", "v3", None)?;
+        bob.tag("writer", "stable", 2)?;
+        bob.push(&remote).await?;
+        let report = alice.pull(&remote).await?;
+        assert_eq!(report.updated, [("writer".to_string(), 1)]);
+        assert_eq!(alice.get("writer", VersionSelector::tag("stable"))?, "v2");
+        assert!(alice.pull(&remote).await?.is_up_to_date());
+
+        // Concurrent edits are reported, not merged
+        alice.update("writer", "alice's v4", None)?;
+        bob.update("writer", "bob's v4", None)?;
+        bob.push(&remote).await?;
+        let report = alice.push(&remote).await?;
+        assert_eq!(report.conflicts, ["writer"]);
+        assert_eq!(
+            PromptVault::open(dir.path().join("remote"))?
+                .get("writer", VersionSelector::Latest)?,
+            "bob's v4"
+        );
+
+        Ok(())
+    }
+}
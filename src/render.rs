@@ -0,0 +1,116 @@
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+/// A `{{name}}` placeholder; whitespace inside the braces is ignored
+static PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_.-]*)\s*\}\}").unwrap());
+
+/// Names of the `{{name}}` placeholders in a template, in order of first appearance
+pub fn template_variables(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for captures in PLACEHOLDER.captures_iter(template) {
+        let name = &captures[1];
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Replace `{{name}}` placeholders with values from `vars`.
+///
+/// Placeholders without a value are left as they are, or reported as an error in
+/// `strict` mode.
+pub fn render_template(
+    template: &str,
+    vars: &HashMap<String, String>,
+    strict: bool,
+) -> Result<String> {
+    if strict {
+        let missing: Vec<String> = template_variables(template)
+            .into_iter()
+            .filter(|name| !vars.contains_key(name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Missing template variables: {}",
+                missing.join(", ")
+            ));
+        }
+    }
+
+    let rendered = PLACEHOLDER.replace_all(template, |captures: &Captures| {
+        match vars.get(&captures[1]) {
+            Some(value) => value.clone(),
+            None => captures[0].to_string(),
+        }
+    });
+    Ok(rendered.into_owned())
+}
+
+impl PromptVault {
+    /// Get a prompt version with its includes resolved and its `{{name}}` placeholders
+    /// filled in from `vars`.
+    ///
+    /// In `strict` mode, placeholders without a value are an error instead of being
+    /// left in the output.
+    pub fn render(
+        &self,
+        key: &str,
+        selector: VersionSelector,
+        vars: &HashMap<String, String>,
+        strict: bool,
+    ) -> Result<String> {
+        render_template(&self.resolve(key, selector)?, vars, strict)
+    }
+
+    /// Names of the placeholders found in a prompt version and the prompts it includes
+    pub fn variables(&self, key: &str, selector: VersionSelector) -> Result<Vec<String>> {
+        Ok(template_variables(&self.resolve(key, selector)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_render() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add(
+            "greeting",
+            "Hello {{customer_name}}, welcome to {{ product }}. Bye {{customer_name}}! {{}}",
+        )?;
+
+        assert_eq!(
+            vault.variables("greeting", VersionSelector::Latest)?,
+            ["customer_name", "product"]
+        );
+
+        let mut vars = HashMap::new();
+        vars.insert("customer_name".to_string(), "Ada".to_string());
+        assert_eq!(
+            vault.render("greeting", VersionSelector::Latest, &vars, false)?,
+            "Hello Ada, welcome to {{ product }}. Bye Ada! {{}}"
+        );
+
+        let err = vault
+            .render("greeting", VersionSelector::Latest, &vars, true)
+            .unwrap_err();
+        assert!(err.to_string().contains("product"));
+
+        vars.insert("product".to_string(), "PromptPro".to_string());
+        assert_eq!(
+            vault.render("greeting", VersionSelector::Latest, &vars, true)?,
+            "Hello Ada, welcome to PromptPro. Bye Ada! {{}}"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! Named numeric scores of versions, e.g. the accuracy an evaluation pipeline measured,
+//! so results stay next to the versions they are about.
+//!
+//! `best:METRIC` ([`VersionSelector::BestBy`](crate::VersionSelector)) selects the
+//! version with the highest score for a metric.
+
+use crate::storage::PromptVault;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A score of one version for one metric
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Score {
+    pub key: String,
+    pub version: u64,
+    pub metric: String,
+    pub value: f64,
+    /// What recorded the score, e.g. `eval-ci`
+    pub source: String,
+    pub recorded_at: DateTime<Utc>,
+}
+
+fn score_key(key: &str, version: u64, metric: &str) -> String {
+    format!("score:{}:{}:{}", key, version, metric)
+}
+
+impl PromptVault {
+    /// Record `value` as the `metric` score of version `version` of `key`, replacing
+    /// the score recorded for that metric before
+    pub fn record_score(
+        &self,
+        key: &str,
+        version: u64,
+        metric: &str,
+        value: f64,
+        source: &str,
+    ) -> Result<Score> {
+        if metric.is_empty()
+            || !metric.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid metric '{}'; use letters, digits, '-', '_' and '.'",
+                metric
+            ));
+        }
+        if !value.is_finite() {
+            return Err(anyhow::anyhow!("Score of '{}' must be a finite number", metric));
+        }
+        if self.get_version_meta(key, version)?.is_none() {
+            if self.get_latest_version_number(key)?.is_none() {
+                return Err(self.unknown_key(key));
+            }
+            return Err(anyhow::anyhow!("Version {} of '{}' not found", version, key));
+        }
+
+        let score = Score {
+            key: key.to_string(),
+            version,
+            metric: metric.to_string(),
+            value,
+            source: source.to_string(),
+            recorded_at: Utc::now(),
+        };
+        self.db().insert(
+            score_key(key, version, metric).as_bytes(),
+            bincode::serialize(&score)?,
+        )?;
+        Ok(score)
+    }
+
+    /// The scores of `key`, or of one of its versions, ordered by version and metric
+    pub fn scores(&self, key: &str, version: Option<u64>) -> Result<Vec<Score>> {
+        let prefix = match version {
+            Some(version) => format!("score:{}:{}:", key, version),
+            None => format!("score:{}:", key),
+        };
+        let mut scores = Vec::new();
+        for (_, value) in self.scan_entries(prefix.as_bytes())? {
+            let score: Score = bincode::deserialize(&value)?;
+            // The prefix also matches the scores of keys starting with `key:`
+            if score.key == key && version.is_none_or(|version| score.version == version) {
+                scores.push(score);
+            }
+        }
+        scores.sort_by(|a, b| (a.version, &a.metric).cmp(&(b.version, &b.metric)));
+        Ok(scores)
+    }
+
+    /// The version of `key` with the highest `metric` score; the newer one on a tie
+    pub fn best_version(&self, key: &str, metric: &str) -> Result<u64> {
+        let mut best: Option<&Score> = None;
+        let scores = self.scores(key, None)?;
+        for score in scores.iter().filter(|score| score.metric == metric) {
+            // Versions removed since keep their scores until `gc`
+            if self.get_version_meta(key, score.version)?.is_none() {
+                continue;
+            }
+            if best.is_none_or(|best| score.value >= best.value) {
+                best = Some(score);
+            }
+        }
+        best.map(|score| score.version)
+            .ok_or_else(|| anyhow::anyhow!("No version of '{}' has a '{}' score", key, metric))
+    }
+
+    /// Remove the scores of a prompt that is being deleted
+    pub(crate) fn remove_scores(&self, key: &str) -> Result<()> {
+        for score in self.scores(key, None)? {
+            self.remove_entry(score_key(key, score.version, &score.metric).as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_scores() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("support", "Answer.")?;
+        vault.update("support", "Answer briefly.", None)?;
+        vault.update("support", "Answer briefly and cite sources.", None)?;
+
+        vault.record_score("support", 1, "accuracy", 0.72, "eval-ci")?;
+        vault.record_score("support", 2, "accuracy", 0.91, "eval-ci")?;
+        vault.record_score("support", 3, "accuracy", 0.85, "eval-ci")?;
+        vault.record_score("support", 3, "latency_ms", 820.0, "eval-ci")?;
+        assert!(vault.record_score("support", 9, "accuracy", 0.5, "eval-ci").is_err());
+        assert!(vault.record_score("support", 1, "bad metric", 0.5, "eval-ci").is_err());
+        assert!(vault.record_score("support", 1, "accuracy", f64::NAN, "eval-ci").is_err());
+
+        let best = VersionSelector::parse("best:accuracy")?;
+        assert_eq!(best, VersionSelector::BestBy("accuracy".to_string()));
+        assert_eq!(vault.get_versioned("support", best.clone())?.version, 2);
+        // Recording again replaces the score
+        vault.record_score("support", 3, "accuracy", 0.95, "manual")?;
+        assert_eq!(vault.get_versioned("support", best)?.version, 3);
+        assert!(vault.get("support", VersionSelector::parse("best:recall")?).is_err());
+        assert!(vault.tag("support", "best:accuracy", 1).is_err());
+
+        let scores = vault.scores("support", Some(3))?;
+        let metrics: Vec<(&str, f64, &str)> = scores
+            .iter()
+            .map(|s| (s.metric.as_str(), s.value, s.source.as_str()))
+            .collect();
+        assert_eq!(metrics, [("accuracy", 0.95, "manual"), ("latency_ms", 820.0, "eval-ci")]);
+        assert_eq!(vault.scores("support", None)?.len(), 4);
+
+        vault.delete_prompt_key("support")?;
+        assert!(vault.scores("support", None)?.is_empty());
+
+        Ok(())
+    }
+}
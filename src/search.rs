@@ -0,0 +1,589 @@
+use crate::metadata::PromptMeta;
+use crate::storage::PromptVault;
+use crate::types::{VersionMeta, VersionSelector};
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Longest snippet returned for a content match, in characters
+const SNIPPET_CHARS: usize = 80;
+
+/// How `PromptVault::search` matches the query
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Treat the query terms as regular expressions instead of plain substrings
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Search every version instead of only the latest one
+    pub all_versions: bool,
+}
+
+/// A parsed search query such as `tag:stable author:alice "json output" key:agents/*`.
+///
+/// Bare words and quoted phrases are terms, each of which must match the key, a
+/// content line, the message or a tag of a version. `tag:`, `author:`, `model:`,
+/// `key:` and `label:` select prompts and versions; every selector must hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub terms: Vec<String>,
+    /// Tags the version must carry
+    pub tags: Vec<String>,
+    /// Prompt authors, compared case-insensitively
+    pub authors: Vec<String>,
+    /// Model hints, compared case-insensitively
+    pub models: Vec<String>,
+    /// Key patterns, where `*` matches any run of characters
+    pub keys: Vec<String>,
+    /// Label names, or `name=value` pairs
+    pub labels: Vec<String>,
+}
+
+impl FromStr for SearchQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut query = SearchQuery::default();
+
+        for (token, quoted) in tokenize(s)? {
+            let selector = if quoted {
+                None
+            } else {
+                token.split_once(':')
+            };
+            let (list, value) = match selector {
+                Some(("tag", value)) => (&mut query.tags, value),
+                Some(("author", value)) => (&mut query.authors, value),
+                Some(("model", value)) => (&mut query.models, value),
+                Some(("key", value)) => (&mut query.keys, value),
+                Some(("label", value)) => (&mut query.labels, value),
+                _ => {
+                    query.terms.push(token);
+                    continue;
+                }
+            };
+            if value.is_empty() {
+                return Err(anyhow::anyhow!("Missing selector value in search query '{}'", s));
+            }
+            list.push(value.to_string());
+        }
+
+        Ok(query)
+    }
+}
+
+/// Split a query on whitespace, keeping `"quoted phrases"` (also as selector values,
+/// `tag:"release candidate"`) together. Tokens that start with a quote are flagged.
+fn tokenize(s: &str) -> Result<Vec<(String, bool)>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let quoted = c == '"';
+        let mut token = String::new();
+        let mut in_quotes = false;
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() && !in_quotes {
+                break;
+            }
+            chars.next();
+            if c == '"' {
+                in_quotes = !in_quotes;
+            } else {
+                token.push(c);
+            }
+        }
+        if in_quotes {
+            return Err(anyhow::anyhow!("Unterminated quote in search query '{}'", s));
+        }
+        if !token.is_empty() {
+            tokens.push((token, quoted));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Whether `text` matches `pattern`, where `*` matches any run of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+impl SearchQuery {
+    fn matches_key(&self, key: &str) -> bool {
+        self.keys.iter().all(|pattern| glob_match(pattern, key))
+    }
+
+    fn has_meta_selectors(&self) -> bool {
+        !self.authors.is_empty() || !self.models.is_empty() || !self.labels.is_empty()
+    }
+
+    fn matches_meta(&self, meta: &PromptMeta) -> bool {
+        let equals = |value: &Option<String>, wanted: &String| {
+            value.as_deref().is_some_and(|v| v.eq_ignore_ascii_case(wanted))
+        };
+        self.authors.iter().all(|author| equals(&meta.author, author))
+            && self.models.iter().all(|model| equals(&meta.model, model))
+            && self.labels.iter().all(|label| match label.split_once('=') {
+                Some((name, value)) => meta.labels.get(name).is_some_and(|v| v == value),
+                None => meta.labels.contains_key(label),
+            })
+    }
+
+    fn matches_version(&self, meta: &VersionMeta) -> bool {
+        self.tags.iter().all(|tag| meta.tags.contains(tag))
+    }
+}
+
+/// Part of a prompt a search hit was found in
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchField {
+    Key,
+    Content,
+    Message,
+    Tag,
+}
+impl std::fmt::Display for SearchField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SearchField::Key => "key",
+            SearchField::Content => "content",
+            SearchField::Message => "message",
+            SearchField::Tag => "tag",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A single match of a search query
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub key: String,
+    pub version: u64,
+    pub field: SearchField,
+    /// The matching key, tag, message or content line
+    pub snippet: String,
+}
+
+/// A run of content lines with at least one match, plus its context lines
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    pub key: String,
+    pub version: u64,
+    pub lines: Vec<MatchLine>,
+}
+
+/// A content line of a [`LineMatch`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MatchLine {
+    /// 1-based line number
+    pub number: usize,
+    pub text: String,
+    /// Whether the line matched, as opposed to being context
+    pub matched: bool,
+}
+
+fn build_matcher(query: &str, options: &SearchOptions) -> Result<Regex> {
+    let pattern = if options.regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Invalid search pattern '{}': {}", query, e))
+}
+
+/// One matcher per term; with `--regex` the free text is a single pattern, so
+/// `respond in json` is not split into three
+fn build_matchers(query: &SearchQuery, options: &SearchOptions) -> Result<Vec<Regex>> {
+    if options.regex && !query.terms.is_empty() {
+        return Ok(vec![build_matcher(&query.terms.join(" "), options)?]);
+    }
+    query
+        .terms
+        .iter()
+        .map(|term| build_matcher(term, options))
+        .collect()
+}
+
+fn snippet(line: &str) -> String {
+    let line = line.trim();
+    if line.chars().count() <= SNIPPET_CHARS {
+        line.to_string()
+    } else {
+        let truncated: String = line.chars().take(SNIPPET_CHARS).collect();
+        format!("{}...", truncated)
+    }
+}
+
+
+impl PromptVault {
+    /// Search prompts with a query such as `tag:stable "json output"` (see
+    /// [`SearchQuery`]).
+    ///
+    /// Content hits report the first matching line of each version, and a key hit is
+    /// reported once per prompt. The contents of password-protected prompts are never
+    /// searched.
+    pub fn search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchHit>> {
+        self.search_query(&query.parse()?, options)
+    }
+
+    /// Search prompts with an already parsed query
+    pub fn search_query(
+        &self,
+        query: &SearchQuery,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchHit>> {
+        let matchers = build_matchers(query, options)?;
+        let mut hits = Vec::new();
+
+        for key in self.list_keys()? {
+            let versions = self.selected_versions(&key, query, options)?;
+            let sensitive = !matchers.is_empty() && self.is_sensitive(&key)?;
+
+            // Newest first, so a key hit is reported with the newest matching version
+            let mut key_hit = false;
+            let mut key_hits = Vec::new();
+            for meta in versions.iter().rev() {
+                let content = if matchers.is_empty() || sensitive {
+                    String::new()
+                } else {
+                    self.get(&key, VersionSelector::Version(meta.version))?
+                };
+                let version = VersionMatch {
+                    key: &key,
+                    meta,
+                    content: &content,
+                    report_key: !key_hit,
+                };
+                if let Some(version_hits) = version.hits(&matchers, &query.tags) {
+                    key_hit |= version_hits.iter().any(|h| h.field == SearchField::Key);
+                    key_hits.push(version_hits);
+                }
+            }
+            hits.extend(key_hits.into_iter().rev().flatten());
+        }
+
+        Ok(hits)
+    }
+
+    /// Stream the content lines matching `query`, with `context` lines around them,
+    /// as `search --regex` prints them.
+    ///
+    /// `on_match` is called once per run of lines, in key and version order, as soon
+    /// as the run is found. Keys, messages and tags are not searched.
+    pub fn search_lines<F>(
+        &self,
+        query: &SearchQuery,
+        options: &SearchOptions,
+        context: usize,
+        mut on_match: F,
+    ) -> Result<()>
+    where
+        F: FnMut(LineMatch) -> Result<()>,
+    {
+        let matchers = build_matchers(query, options)?;
+        if matchers.is_empty() {
+            return Err(anyhow::anyhow!("Nothing to search for; the query has only selectors"));
+        }
+
+        for key in self.list_keys()? {
+            let versions = self.selected_versions(&key, query, options)?;
+            if versions.is_empty() || self.is_sensitive(&key)? {
+                continue;
+            }
+            for meta in versions {
+                let content = self.get(&key, VersionSelector::Version(meta.version))?;
+                let lines: Vec<&str> = content.lines().collect();
+                let matched: Vec<usize> = (0..lines.len())
+                    .filter(|&i| matchers.iter().all(|m| m.is_match(lines[i])))
+                    .collect();
+
+                for (start, end) in line_runs(&matched, context, lines.len()) {
+                    on_match(LineMatch {
+                        key: key.clone(),
+                        version: meta.version,
+                        lines: (start..end)
+                            .map(|i| MatchLine {
+                                number: i + 1,
+                                text: lines[i].to_string(),
+                                matched: matched.binary_search(&i).is_ok(),
+                            })
+                            .collect(),
+                    })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Versions of `key` the selectors of `query` pick: versions with the selected
+    /// tags (however old), otherwise the latest one or, with `all_versions`, all
+    fn selected_versions(
+        &self,
+        key: &str,
+        query: &SearchQuery,
+        options: &SearchOptions,
+    ) -> Result<Vec<VersionMeta>> {
+        if !query.matches_key(key)
+            || (query.has_meta_selectors() && !query.matches_meta(&self.prompt_meta(key)?))
+        {
+            return Ok(Vec::new());
+        }
+
+        let mut history = self.history(key)?;
+        if !query.tags.is_empty() {
+            history.retain(|meta| query.matches_version(meta));
+        } else if !options.all_versions {
+            history = history.pop().into_iter().collect();
+        }
+        Ok(history)
+    }
+}
+
+/// Line ranges `[start, end)` covering the matched lines and `context` lines around
+/// them, with overlapping or touching ranges merged
+fn line_runs(matched: &[usize], context: usize, line_count: usize) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for &line in matched {
+        let start = line.saturating_sub(context);
+        let end = (line + context + 1).min(line_count);
+        match runs.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => runs.push((start, end)),
+        }
+    }
+    runs
+}
+
+/// One version of a prompt being matched against the query terms
+struct VersionMatch<'a> {
+    key: &'a str,
+    meta: &'a VersionMeta,
+    content: &'a str,
+    /// Whether a key match is still to be reported for this prompt
+    report_key: bool,
+}
+
+impl VersionMatch<'_> {
+    /// Hits of the version, or `None` when a term matches nowhere in it. Without
+    /// terms, the version itself (selected by the query) is the hit.
+    fn hits(&self, matchers: &[Regex], tags: &[String]) -> Option<Vec<SearchHit>> {
+        let (key, meta, content) = (self.key, self.meta, self.content);
+        let hit = |field, snippet| SearchHit {
+            key: key.to_string(),
+            version: meta.version,
+            field,
+            snippet,
+        };
+
+        if matchers.is_empty() {
+            return Some(vec![match tags.first() {
+                Some(tag) => hit(SearchField::Tag, tag.clone()),
+                None => hit(SearchField::Key, key.to_string()),
+            }]);
+        }
+
+        let mut hits = Vec::new();
+        for matcher in matchers {
+            let mut found = Vec::new();
+            if matcher.is_match(key) {
+                found.push(hit(SearchField::Key, key.to_string()));
+            }
+            if let Some(line) = content.lines().find(|line| matcher.is_match(line)) {
+                found.push(hit(SearchField::Content, snippet(line)));
+            }
+            if let Some(message) = meta.message.as_deref().filter(|m| matcher.is_match(m)) {
+                found.push(hit(SearchField::Message, snippet(message)));
+            }
+            for tag in meta.tags.iter().filter(|tag| matcher.is_match(tag)) {
+                found.push(hit(SearchField::Tag, tag.clone()));
+            }
+
+            if found.is_empty() {
+                return None;
+            }
+            for found_hit in found {
+                if !hits.contains(&found_hit) {
+                    hits.push(found_hit);
+                }
+            }
+        }
+
+        if !self.report_key {
+            hits.retain(|hit| hit.field != SearchField::Key);
+            if hits.is_empty() {
+                // Only the key matched, and it was already reported with a newer version
+                return None;
+            }
+        }
+        // Key hits first, as they describe the whole prompt
+        hits.sort_by_key(|hit| hit.field != SearchField::Key);
+        Some(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_search() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+
+        vault.add("writer", "You write Poems\nkeep it short")?;
+        vault.update("writer", "You write essays", Some("drop poems".to_string()))?;
+        vault.add("poem_critic", "Review the text")?;
+        vault.tag("poem_critic", "stable", 1)?;
+
+        // Latest versions only, case-insensitive substring
+        let hits = vault.search("poem", &SearchOptions::default())?;
+        let found: Vec<_> = hits.iter().map(|h| (h.key.as_str(), h.version, h.field)).collect();
+        assert_eq!(
+            found,
+            [
+                ("poem_critic", 1, SearchField::Key),
+                ("writer", 2, SearchField::Message)
+            ]
+        );
+
+        // Older versions and case sensitivity
+        let options = SearchOptions {
+            all_versions: true,
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let hits = vault.search("Poem", &options)?;
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].version, hits[0].field), (1, SearchField::Content));
+        assert_eq!(hits[0].snippet, "You write Poems");
+
+        // Regular expressions
+        let options = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        let hits = vault.search("^stab", &options)?;
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].field, SearchField::Tag);
+        assert!(vault.search("(", &options).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_query() -> Result<()> {
+        let query: SearchQuery =
+            r#"tag:stable author:alice "json output" key:agents/* plain"#.parse()?;
+        assert_eq!(query.terms, ["json output", "plain"]);
+        assert_eq!(query.tags, ["stable"]);
+        assert_eq!(query.authors, ["alice"]);
+        assert_eq!(query.keys, ["agents/*"]);
+        assert_eq!("\"key:x\"".parse::<SearchQuery>()?.terms, ["key:x"]);
+        assert!("tag:".parse::<SearchQuery>().is_err());
+        assert!("\"open".parse::<SearchQuery>().is_err());
+
+        assert!(glob_match("agents/*", "agents/planner/v2"));
+        assert!(glob_match("*/planner*", "agents/planner"));
+        assert!(!glob_match("agents/*", "tools/agents/x"));
+        assert!(!glob_match("a*b", "ab/c"));
+
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("agents/planner", "Reply with JSON output")?;
+        vault.update("agents/planner", "Reply in prose", None)?;
+        vault.tag("agents/planner", "stable", 1)?;
+        vault.add("agents/coder", "Write JSON output only")?;
+        vault.add("tools/json", "JSON output helper")?;
+        let alice = PromptMeta {
+            author: Some("Alice".to_string()),
+            ..Default::default()
+        };
+        vault.set_prompt_meta("agents/planner", &alice)?;
+        vault.set_prompt_meta("tools/json", &alice)?;
+
+        let options = SearchOptions::default();
+        let found = |query: &str| -> Result<Vec<(String, u64)>> {
+            let hits = vault.search(query, &options)?;
+            let mut found: Vec<_> = hits.into_iter().map(|h| (h.key, h.version)).collect();
+            found.dedup();
+            Ok(found)
+        };
+
+        // The tag selects v1 of the planner, even though v2 is the latest
+        assert_eq!(
+            found(r#"tag:stable author:alice "json output" key:agents/*"#)?,
+            [("agents/planner".to_string(), 1)]
+        );
+        // Terms are combined: both must match
+        assert_eq!(found("json helper")?, [("tools/json".to_string(), 1)]);
+        // Selectors alone list the selected prompts
+        assert_eq!(
+            found("author:ALICE")?,
+            [("agents/planner".to_string(), 2), ("tools/json".to_string(), 1)]
+        );
+        assert!(found("\"json output\" key:agents/* model:gpt-4o")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_lines() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("planner", "Plan the work\nRespond in JSON\nNo prose\n\nKeep it short")?;
+        vault.update("planner", "Plan\nrespond in json\nthen stop", None)?;
+        vault.add("writer", "Respond in prose")?;
+
+        let options = SearchOptions {
+            regex: true,
+            all_versions: true,
+            ..Default::default()
+        };
+        let query: SearchQuery = "(?i)respond in json".parse()?;
+        let mut found = Vec::new();
+        vault.search_lines(&query, &options, 1, |m| {
+            found.push(m);
+            Ok(())
+        })?;
+
+        assert_eq!(found.len(), 2);
+        let numbers: Vec<_> = found[0].lines.iter().map(|l| (l.number, l.matched)).collect();
+        assert_eq!((found[0].key.as_str(), found[0].version), ("planner", 1));
+        assert_eq!(numbers, [(1, false), (2, true), (3, false)]);
+        assert_eq!(found[1].version, 2);
+        assert_eq!(found[1].lines[1].text, "respond in json");
+
+        assert_eq!(line_runs(&[0, 2, 6], 1, 8), [(0, 4), (5, 8)]);
+        assert!(vault
+            .search_lines(&"key:planner".parse()?, &options, 0, |_| Ok(()))
+            .is_err());
+
+        Ok(())
+    }
+}
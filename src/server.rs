@@ -0,0 +1,651 @@
+//! HTTP API over a vault, started by `promptpro serve`.
+//!
+//! Keys are path segments, so namespaced keys must be URL-encoded
+//! (`/prompts/team%2Fagent`). `/sync` is used by `promptpro push` and `pull`. `/docs`
+//! answers browsers with the vault README and prompt notes as a catalog page. `/health`
+//! reports the last background maintenance sweep. Reads resolve aliases (`promptpro
+//! alias`) before looking a key up.
+
+use crate::docs::VaultDocs;
+use crate::export::VaultExport;
+use crate::history::HistoryQuery;
+use crate::lock::check_content_hash;
+use crate::remote::SyncPatch;
+use crate::search::{SearchHit, SearchOptions, SearchQuery};
+use crate::stats::VaultStats;
+use crate::storage::{PromptVault, VersionConflict};
+use crate::suggest::did_you_mean as did_you_mean_text;
+use crate::sweep::SweepReport;
+use crate::types::{VersionMeta, VersionSelector};
+use axum::extract::{Path, Query, State};
+use axum::http::header::{ACCEPT, ETAG, IF_MATCH, IF_NONE_MATCH};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// An error answered as `{"error": "..."}` with a status code, and for unknown keys
+/// the existing keys they may be a typo of as `"did_you_mean": [...]`
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+    did_you_mean: Vec<String>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: String) -> Self {
+        ApiError {
+            status,
+            message,
+            did_you_mean: Vec::new(),
+        }
+    }
+
+    fn not_found(message: String) -> Self {
+        ApiError::new(StatusCode::NOT_FOUND, message)
+    }
+
+    fn bad_request(message: String) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    /// 404 for a key without versions, suggesting similar keys
+    fn unknown_key(vault: &PromptVault, key: &str) -> Self {
+        let did_you_mean = vault.suggest_keys(key).unwrap_or_default();
+        ApiError {
+            status: StatusCode::NOT_FOUND,
+            message: format!("Prompt '{}' does not exist{}", key, did_you_mean_text(&did_you_mean)),
+            did_you_mean,
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut body = serde_json::json!({ "error": self.message });
+        if !self.did_you_mean.is_empty() {
+            body["did_you_mean"] = serde_json::json!(self.did_you_mean);
+        }
+        (self.status, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[derive(Deserialize)]
+struct ListQuery {
+    prefix: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct KeysResponse {
+    keys: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GetQuery {
+    /// Version number, `latest` or a tag name
+    selector: Option<String>,
+    /// Content hash the prompt must have, as pinned by `promptpro lock`
+    expect: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct PromptResponse {
+    key: String,
+    version: u64,
+    content: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct HealthResponse {
+    status: String,
+    last_sweep: Option<SweepReport>,
+}
+
+#[derive(Deserialize)]
+struct SearchQueryParams {
+    /// Query such as `tag:stable "json output"`
+    q: String,
+    #[serde(default)]
+    regex: bool,
+    #[serde(default)]
+    case_sensitive: bool,
+    #[serde(default)]
+    all_versions: bool,
+}
+
+/// Maximum number of prompts in one `/prompts:batchGet` request
+const BATCH_GET_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+struct BatchGetRequestItem {
+    key: String,
+    /// Version number, `latest` or a tag name
+    selector: Option<String>,
+}
+
+/// One prompt of a `/prompts:batchGet` answer: its content, or why it could not be read
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct BatchGetResponseItem {
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Existing keys an unknown key may be a typo of
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    did_you_mean: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct NewVersionRequest {
+    content: String,
+    message: Option<String>,
+    /// Answer 409 instead of updating when this is no longer the latest version
+    #[serde(default)]
+    expected_parent: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct VersionResponse {
+    key: String,
+    version: u64,
+}
+
+#[derive(Deserialize)]
+struct TagRequest {
+    /// Version to tag; the latest version when omitted
+    version: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+struct TagResponse {
+    key: String,
+    tag: String,
+    version: u64,
+}
+
+/// Build the HTTP routes for a vault
+pub fn router(vault: PromptVault) -> Router {
+    Router::new()
+        .route("/prompts", get(list_prompts))
+        .route("/prompts/:key", get(get_prompt).post(new_version))
+        .route("/prompts/:key/history", get(history))
+        .route("/prompts/:key/tags/:tag", put(tag_version))
+        .route("/sync", get(sync_state).post(apply_sync))
+        .route("/stats", get(stats))
+        .route("/search", get(search))
+        .route("/docs", get(docs))
+        .route("/health", get(health))
+        // Custom methods such as `/prompts:batchGet`
+        .route("/:method", post(custom_method))
+        .with_state(vault)
+}
+
+/// Serve a vault over HTTP until Ctrl-C is pressed
+pub async fn serve(vault: PromptVault, addr: SocketAddr) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router(vault))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+/// Latest version of a key, or 404 when it does not exist
+fn latest_version(vault: &PromptVault, key: &str) -> Result<u64, ApiError> {
+    vault
+        .get_latest_version_number(key)?
+        .ok_or_else(|| ApiError::unknown_key(vault, key))
+}
+
+/// Refuse to serve the ciphertext of password-protected prompts
+fn check_not_sensitive(vault: &PromptVault, key: &str) -> Result<(), ApiError> {
+    if vault.is_sensitive(key)? {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("Prompt '{}' is password-protected", key),
+        ));
+    }
+    Ok(())
+}
+
+async fn list_prompts(
+    State(vault): State<PromptVault>,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<KeysResponse> {
+    let keys = vault.list_keys_with_prefix(query.prefix.as_deref().unwrap_or_default())?;
+    Ok(Json(KeysResponse { keys }))
+}
+
+/// Read a prompt version; the latest one when no selector is given. An alias reads the
+/// prompt it names, answered under that prompt's key.
+fn read_prompt(
+    vault: &PromptVault,
+    key: String,
+    selector: Option<&str>,
+) -> Result<PromptResponse, ApiError> {
+    let key = vault.resolve_alias(&key)?;
+    latest_version(vault, &key)?;
+    check_not_sensitive(vault, &key)?;
+
+    let selector = match selector {
+        Some(selector) => VersionSelector::parse(selector)
+            .map_err(|e| ApiError::bad_request(e.to_string()))?,
+        None => VersionSelector::Latest,
+    };
+    let version = vault
+        .resolve_version(&key, &selector)
+        .map_err(|e| ApiError::not_found(e.to_string()))?;
+    let content = vault.get(&key, VersionSelector::Version(version))?;
+
+    Ok(PromptResponse {
+        key,
+        version,
+        content,
+    })
+}
+
+/// Answer with the resolved version as ETag, or 304 when the client already has it;
+/// 409 when `expect` is given and the content has another hash
+async fn get_prompt(
+    State(vault): State<PromptVault>,
+    Path(key): Path<String>,
+    Query(query): Query<GetQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let prompt = read_prompt(&vault, key, query.selector.as_deref())?;
+    if let Some(expected) = &query.expect {
+        check_content_hash(&prompt.key, prompt.version, &prompt.content, expected)
+            .map_err(|e| ApiError::new(StatusCode::CONFLICT, e.to_string()))?;
+    }
+
+    // Versions never change once stored, so the version number identifies the content
+    let etag = format!("\"{}\"", prompt.version);
+    let cached = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value == etag);
+    if cached {
+        return Ok((StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response());
+    }
+    Ok(([(ETAG, etag)], Json(prompt)).into_response())
+}
+
+async fn custom_method(
+    State(vault): State<PromptVault>,
+    Path(method): Path<String>,
+    Json(request): Json<Vec<BatchGetRequestItem>>,
+) -> ApiResult<Vec<BatchGetResponseItem>> {
+    match method.as_str() {
+        "prompts:batchGet" => batch_get(&vault, request).map(Json),
+        _ => Err(ApiError::not_found(format!("Unknown method '{}'", method))),
+    }
+}
+
+/// Read several prompts in one round trip, answering in request order.
+///
+/// A prompt that cannot be read gets an `error` instead of failing the whole batch.
+fn batch_get(
+    vault: &PromptVault,
+    request: Vec<BatchGetRequestItem>,
+) -> Result<Vec<BatchGetResponseItem>, ApiError> {
+    if request.len() > BATCH_GET_LIMIT {
+        return Err(ApiError::bad_request(format!(
+            "At most {} prompts can be read in one batch",
+            BATCH_GET_LIMIT
+        )));
+    }
+
+    Ok(request
+        .into_iter()
+        .map(|item| {
+            match read_prompt(vault, item.key.clone(), item.selector.as_deref()) {
+                Ok(prompt) => BatchGetResponseItem {
+                    key: prompt.key,
+                    version: Some(prompt.version),
+                    content: Some(prompt.content),
+                    error: None,
+                    did_you_mean: Vec::new(),
+                },
+                Err(error) => BatchGetResponseItem {
+                    key: item.key,
+                    version: None,
+                    content: None,
+                    error: Some(error.message),
+                    did_you_mean: error.did_you_mean,
+                },
+            }
+        })
+        .collect())
+}
+
+/// The version an `If-Match` header names, as the ETag `get_prompt` answers with
+fn if_match_version(headers: &HeaderMap) -> Result<Option<u64>, ApiError> {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().strip_prefix('"')?.strip_suffix('"')?.parse().ok())
+        .map(Some)
+        .ok_or_else(|| {
+            ApiError::bad_request("If-Match must be an ETag of this API, such as \"3\"".into())
+        })
+}
+
+/// Store a new version, creating the prompt if it does not exist yet. An `If-Match`
+/// ETag or `expected_parent` that is no longer the latest version answers 412 or 409.
+async fn new_version(
+    State(vault): State<PromptVault>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<NewVersionRequest>,
+) -> Result<Response, ApiError> {
+    let if_match = if_match_version(&headers)?;
+    let (expected, status) = match (if_match, request.expected_parent) {
+        (Some(version), _) => (Some(version), StatusCode::PRECONDITION_FAILED),
+        (None, expected) => (expected, StatusCode::CONFLICT),
+    };
+
+    let version = if vault.get_latest_version_number(&key)?.is_none() {
+        if expected.is_some() {
+            return Err(ApiError::new(status, format!("Prompt '{}' does not exist", key)));
+        }
+        vault.add(&key, &request.content)?;
+        1
+    } else {
+        check_not_sensitive(&vault, &key)?;
+        vault
+            .update_expecting(&key, &request.content, request.message, expected)
+            .map_err(|e| match e.downcast_ref::<VersionConflict>() {
+                Some(conflict) => ApiError::new(status, conflict.to_string()),
+                None => ApiError::bad_request(e.to_string()),
+            })?
+    };
+
+    let etag = format!("\"{}\"", version);
+    let response = (StatusCode::CREATED, [(ETAG, etag)], Json(VersionResponse { key, version }));
+    Ok(response.into_response())
+}
+
+async fn history(
+    State(vault): State<PromptVault>,
+    Path(key): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> ApiResult<Vec<VersionMeta>> {
+    let key = vault.resolve_alias(&key)?;
+    latest_version(&vault, &key)?;
+    Ok(Json(vault.history_filtered(&key, &query)?))
+}
+
+async fn tag_version(
+    State(vault): State<PromptVault>,
+    Path((key, tag)): Path<(String, String)>,
+    request: Option<Json<TagRequest>>,
+) -> ApiResult<TagResponse> {
+    let latest = latest_version(&vault, &key)?;
+    let version = request
+        .and_then(|Json(request)| request.version)
+        .unwrap_or(latest);
+
+    vault
+        .tag(&key, &tag, version)
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok(Json(TagResponse { key, tag, version }))
+}
+
+async fn stats(State(vault): State<PromptVault>) -> ApiResult<VaultStats> {
+    Ok(Json(vault.stats()?))
+}
+
+/// Answer a search query; 400 when it does not parse
+async fn search(
+    State(vault): State<PromptVault>,
+    Query(params): Query<SearchQueryParams>,
+) -> ApiResult<Vec<SearchHit>> {
+    let query: SearchQuery = params
+        .q
+        .parse()
+        .map_err(|e: anyhow::Error| ApiError::bad_request(e.to_string()))?;
+    let options = SearchOptions {
+        regex: params.regex,
+        case_sensitive: params.case_sensitive,
+        all_versions: params.all_versions,
+    };
+    let hits = vault
+        .search_query(&query, &options)
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok(Json(hits))
+}
+
+/// The prompt catalog: an HTML page for browsers, JSON otherwise
+async fn docs(State(vault): State<PromptVault>, headers: HeaderMap) -> Result<Response, ApiError> {
+    let docs: VaultDocs = vault.docs()?;
+    let wants_html = headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("text/html"));
+    if wants_html {
+        return Ok(Html(docs.to_html()).into_response());
+    }
+    Ok(Json(docs).into_response())
+}
+
+/// `degraded` when the last sweep found problems it could not repair
+async fn health(State(vault): State<PromptVault>) -> ApiResult<HealthResponse> {
+    let last_sweep = vault.last_sweep()?;
+    let degraded = last_sweep
+        .as_ref()
+        .is_some_and(|report| !report.problems.is_empty());
+    Ok(Json(HealthResponse {
+        status: if degraded { "degraded" } else { "ok" }.to_string(),
+        last_sweep,
+    }))
+}
+
+async fn sync_state(State(vault): State<PromptVault>) -> ApiResult<VaultExport> {
+    Ok(Json(vault.export_prompts()?))
+}
+
+/// Apply a patch sent by `promptpro push`; 409 when the vault moved on meanwhile
+async fn apply_sync(
+    State(vault): State<PromptVault>,
+    Json(patch): Json<SyncPatch>,
+) -> Result<StatusCode, ApiError> {
+    vault
+        .apply_sync_patch(&patch)
+        .map_err(|e| ApiError::new(StatusCode::CONFLICT, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use serde::de::DeserializeOwned;
+    use tempfile::tempdir;
+    use tower::ServiceExt;
+
+    async fn call<T: DeserializeOwned>(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, T) {
+        let request = Request::builder().method(method).uri(uri);
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        };
+        let response = app.clone().oneshot(request.unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_server_routes() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        let app = router(vault.clone());
+
+        let body = serde_json::json!({ "content": "v1" });
+        let (status, created): (_, VersionResponse) =
+            call(&app, "POST", "/prompts/team%2Fwriter", Some(body)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created.version, 1);
+
+        let body = serde_json::json!({ "content": "v2", "message": "second" });
+        call::<VersionResponse>(&app, "POST", "/prompts/team%2Fwriter", Some(body)).await;
+        let stale = serde_json::json!({ "content": "v2'", "expected_parent": 1 });
+        let (status, _): (_, serde_json::Value) =
+            call(&app, "POST", "/prompts/team%2Fwriter", Some(stale)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, tagged): (_, TagResponse) = call(
+            &app,
+            "PUT",
+            "/prompts/team%2Fwriter/tags/stable",
+            Some(serde_json::json!({ "version": 1 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(tagged.version, 1);
+
+        let (_, prompt): (_, PromptResponse) =
+            call(&app, "GET", "/prompts/team%2Fwriter?selector=stable", None).await;
+        assert_eq!(prompt.content, "v1");
+        let (_, prompt): (_, PromptResponse) =
+            call(&app, "GET", "/prompts/team%2Fwriter", None).await;
+        assert_eq!((prompt.version, prompt.content.as_str()), (2, "v2"));
+
+        let request = Request::builder()
+            .uri("/prompts/team%2Fwriter")
+            .header("if-none-match", "\"2\"")
+            .body(Body::empty())?;
+        let response = app.clone().oneshot(request).await?;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let (_, history): (_, Vec<VersionMeta>) =
+            call(&app, "GET", "/prompts/team%2Fwriter/history", None).await;
+        assert_eq!(history.len(), 2);
+        let (_, history): (_, Vec<VersionMeta>) =
+            call(&app, "GET", "/prompts/team%2Fwriter/history?limit=1", None).await;
+        assert_eq!(history[0].version, 2);
+
+        let (_, keys): (_, KeysResponse) = call(&app, "GET", "/prompts?prefix=team/", None).await;
+        assert_eq!(keys.keys, ["team/writer"]);
+
+        let (_, docs): (_, VaultDocs) = call(&app, "GET", "/docs", None).await;
+        assert_eq!((docs.readme, docs.prompts[0].latest_version), (None, 2));
+        let request = Request::builder()
+            .uri("/docs")
+            .header("accept", "text/html,application/xhtml+xml")
+            .body(Body::empty())?;
+        let page = to_bytes(app.clone().oneshot(request).await?.into_body(), usize::MAX).await?;
+        assert!(String::from_utf8(page.to_vec())?.contains("<h1>Prompt catalog</h1>"));
+
+        let (_, stats): (_, VaultStats) = call(&app, "GET", "/stats", None).await;
+        assert_eq!((stats.keys, stats.versions), (1, 2));
+        assert_eq!(stats.namespaces["team"].keys, 1);
+
+        let (_, health): (_, HealthResponse) = call(&app, "GET", "/health", None).await;
+        assert_eq!((health.status.as_str(), health.last_sweep), ("ok", None));
+        let sweep = std::time::Duration::from_secs(60);
+        let report = vault.sweep(&crate::sweep::SweepOptions::new(sweep))?;
+        let (_, health): (_, HealthResponse) = call(&app, "GET", "/health", None).await;
+        assert_eq!(health.last_sweep, Some(report));
+
+        let (_, hits): (_, Vec<SearchHit>) =
+            call(&app, "GET", "/search?q=tag%3Astable%20key%3Ateam%2F*", None).await;
+        assert_eq!((hits.len(), hits[0].version), (1, 1));
+        let (status, _): (_, serde_json::Value) =
+            call(&app, "GET", "/search?q=%22open", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, error): (_, serde_json::Value) =
+            call(&app, "GET", "/prompts/missing", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(error.get("did_you_mean").is_none());
+        let (status, error): (_, serde_json::Value) =
+            call(&app, "GET", "/prompts/team%2Fwritr/history", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error["did_you_mean"], serde_json::json!(["team/writer"]));
+        assert_eq!(
+            error["error"],
+            "Prompt 'team/writr' does not exist; did you mean 'team/writer'?"
+        );
+
+        let body = serde_json::json!([
+            { "key": "team/writer", "selector": "stable" },
+            { "key": "team/writer" },
+            { "key": "missing" },
+            { "key": "team/writr" },
+        ]);
+        let (status, batch): (_, Vec<BatchGetResponseItem>) =
+            call(&app, "POST", "/prompts:batchGet", Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(batch[0].content.as_deref(), Some("v1"));
+        assert_eq!((batch[1].version, batch[1].content.as_deref()), (Some(2), Some("v2")));
+        assert!(batch[2].content.is_none() && batch[2].error.is_some());
+        assert_eq!(batch[3].did_you_mean, ["team/writer"]);
+
+        // The ETag of a read is the precondition of the next write
+        let write = |etag: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/prompts/team%2Fwriter")
+                .header("content-type", "application/json")
+                .header("if-match", etag)
+                .body(Body::from(r#"{"content": "v3"}"#))
+        };
+        let response = app.clone().oneshot(write("\"1\"")?).await?;
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        let response = app.clone().oneshot(write("\"2\"")?).await?;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()[ETAG], "\"3\"");
+
+        // Reads pinned to a content hash fail once the content differs
+        let hash = crate::import_dir::content_hash("v3");
+        let uri = format!("/prompts/team%2Fwriter?expect={}", hash);
+        let (status, pinned): (_, PromptResponse) = call(&app, "GET", &uri, None).await;
+        assert_eq!((status, pinned.content.as_str()), (StatusCode::OK, "v3"));
+        let uri = format!("/prompts/team%2Fwriter?selector=1&expect={}", hash);
+        let (status, _): (_, serde_json::Value) = call(&app, "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        // Aliases read the prompt they name, which stays forbidden when protected
+        vault.set_alias("writer", "team/writer")?;
+        let (status, aliased): (_, PromptResponse) =
+            call(&app, "GET", "/prompts/writer?selector=stable", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((aliased.key.as_str(), aliased.content.as_str()), ("team/writer", "v1"));
+        let (_, history): (_, Vec<VersionMeta>) =
+            call(&app, "GET", "/prompts/writer/history", None).await;
+        assert_eq!(history.len(), 3);
+        vault.protect("team/writer", "secret")?;
+        let (status, _): (_, serde_json::Value) =
+            call(&app, "GET", "/prompts/writer", None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        Ok(())
+    }
+}
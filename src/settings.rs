@@ -0,0 +1,167 @@
+//! Reading and changing single settings of `~/.promptpro/config.toml` for
+//! `promptpro config`, keeping the comments and layout of the rest of the file.
+
+use crate::profile::ProfileConfig;
+use anyhow::Result;
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+/// Settings `promptpro config` reads and changes; `NAME` stands for a profile name
+pub const SETTINGS: &[&str] = &[
+    "user.name",
+    "offline",
+    "tokens.model",
+    "tokens.budget",
+    "llm.endpoint",
+    "llm.model",
+    "llm.api_key_env",
+    "profile.NAME.vault",
+    "profile.NAME.remote",
+    "profile.NAME.author",
+];
+
+/// The tables and key of a setting, e.g. `["user", "name"]`
+fn setting_path(name: &str) -> Result<Vec<&str>> {
+    let path: Vec<&str> = name.split('.').collect();
+    let known = match path.as_slice() {
+        ["user", "name"] | ["offline"] | ["tokens", "model" | "budget"] => true,
+        ["llm", "endpoint" | "model" | "api_key_env"] => true,
+        ["profile", profile, "vault" | "remote" | "author"] => !profile.is_empty(),
+        _ => false,
+    };
+    if !known {
+        return Err(anyhow::anyhow!(
+            "Unknown setting '{}'; expected one of {}",
+            name,
+            SETTINGS.join(", ")
+        ));
+    }
+    Ok(path)
+}
+
+fn read_document(path: &Path) -> Result<DocumentMut> {
+    if !path.exists() {
+        return Ok(DocumentMut::new());
+    }
+    let text = std::fs::read_to_string(path)?;
+    text.parse()
+        .map_err(|e| anyhow::anyhow!("Invalid config file {}: {}", path.display(), e))
+}
+
+/// Write `document` to `path`, unless it is no valid config
+fn write_document(path: &Path, document: &DocumentMut) -> Result<()> {
+    let text = document.to_string();
+    ProfileConfig::parse(&text)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+/// The value of setting `name` in the config file at `path`, if it is set
+pub fn get_setting(path: &Path, name: &str) -> Result<Option<String>> {
+    let document = read_document(path)?;
+    let mut item = document.as_item();
+    for part in setting_path(name)? {
+        match item.get(part) {
+            Some(inner) => item = inner,
+            None => return Ok(None),
+        }
+    }
+    Ok(match item.as_value() {
+        Some(Value::String(value)) => Some(value.value().clone()),
+        Some(value) => Some(value.to_string().trim().to_string()),
+        None => None,
+    })
+}
+
+/// Set setting `name` in the config file at `path`, creating the file if needed
+pub fn set_setting(path: &Path, name: &str, value: &str) -> Result<()> {
+    let parts = setting_path(name)?;
+    let value: Value = match name {
+        "offline" => value
+            .parse::<bool>()
+            .map_err(|_| {
+                anyhow::anyhow!("Invalid value '{}' for offline; expected true or false", value)
+            })?
+            .into(),
+        "tokens.budget" => i64::from(value.parse::<u32>().map_err(|_| {
+            anyhow::anyhow!("Invalid value '{}' for tokens.budget; expected a number", value)
+        })?)
+        .into(),
+        _ => value.into(),
+    };
+    let mut document = read_document(path)?;
+    let (key, tables) = parts.split_last().expect("settings have a key");
+    let mut table = document.as_table_mut();
+    for part in tables {
+        table = table
+            .entry(part)
+            .or_insert_with(|| {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            })
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("'{}' in {} is no table", part, path.display()))?;
+    }
+    table[key] = Item::Value(value);
+    write_document(path, &document)
+}
+
+/// Remove setting `name` from the config file at `path`; returns whether it was set
+pub fn unset_setting(path: &Path, name: &str) -> Result<bool> {
+    let parts = setting_path(name)?;
+    let mut document = read_document(path)?;
+    let (key, tables) = parts.split_last().expect("settings have a key");
+    let mut table = document.as_table_mut();
+    for part in tables {
+        match table.get_mut(part).and_then(Item::as_table_mut) {
+            Some(inner) => table = inner,
+            None => return Ok(false),
+        }
+    }
+    if table.remove(key).is_none() {
+        return Ok(false);
+    }
+    write_document(path, &document)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_settings() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "# shared with the team\n[profile.work]\nremote = \"http://x\"\n")?;
+
+        set_setting(&path, "user.name", "Alice Example")?;
+        set_setting(&path, "profile.work.author", "alice")?;
+        set_setting(&path, "offline", "true")?;
+        set_setting(&path, "tokens.budget", "4000")?;
+        assert!(set_setting(&path, "tokens.budget", "lots").is_err());
+        assert!(set_setting(&path, "offline", "yes").is_err());
+        assert!(set_setting(&path, "user.email", "alice@example.com").is_err());
+
+        assert_eq!(get_setting(&path, "user.name")?.as_deref(), Some("Alice Example"));
+        assert_eq!(get_setting(&path, "offline")?.as_deref(), Some("true"));
+        assert_eq!(get_setting(&path, "profile.home.vault")?, None);
+        let text = std::fs::read_to_string(&path)?;
+        assert!(text.starts_with("offline = true\n# shared with the team\n"));
+        let config = ProfileConfig::load(&path)?;
+        assert_eq!(config.user.name.as_deref(), Some("Alice Example"));
+        assert_eq!(config.get("work")?.author.as_deref(), Some("alice"));
+        assert_eq!(config.tokens.budget, Some(4000));
+
+        assert!(unset_setting(&path, "user.name")?);
+        assert!(!unset_setting(&path, "user.name")?);
+        assert_eq!(ProfileConfig::load(&path)?.user.name, None);
+
+        Ok(())
+    }
+}
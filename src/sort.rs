@@ -0,0 +1,163 @@
+//! Orderings of prompt keys other than alphabetical, for `promptpro list --sort` and
+//! the TUI Keys panel.
+
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+/// How to order prompt keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Alphabetical, so that the keys of a namespace stay together
+    #[default]
+    Name,
+    /// Most recently written first
+    Modified,
+    /// Most served first, counting the requests routed by canary rollouts
+    Usage,
+    /// Largest latest version first, in estimated tokens
+    Tokens,
+}
+
+impl KeyOrder {
+    pub const ALL: [KeyOrder; 4] = [
+        KeyOrder::Name,
+        KeyOrder::Modified,
+        KeyOrder::Usage,
+        KeyOrder::Tokens,
+    ];
+
+    /// The ordering after this one, wrapping around
+    pub fn next(self) -> KeyOrder {
+        let index = Self::ALL
+            .iter()
+            .position(|order| *order == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl fmt::Display for KeyOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            KeyOrder::Name => "name",
+            KeyOrder::Modified => "modified",
+            KeyOrder::Usage => "usage",
+            KeyOrder::Tokens => "tokens",
+        })
+    }
+}
+
+impl std::str::FromStr for KeyOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "name" => Ok(KeyOrder::Name),
+            "modified" | "recent" => Ok(KeyOrder::Modified),
+            "usage" => Ok(KeyOrder::Usage),
+            "tokens" | "size" => Ok(KeyOrder::Tokens),
+            other => Err(anyhow::anyhow!(
+                "Unknown sort order '{}', expected 'name', 'modified', 'usage' or 'tokens'",
+                other
+            )),
+        }
+    }
+}
+
+/// Rough token count of a prompt, at about four characters per token
+pub fn estimate_tokens(content: &str) -> u64 {
+    content.chars().count().div_ceil(4) as u64
+}
+
+/// Value a key is ranked by; higher values come first
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Rank {
+    Time(Option<DateTime<Utc>>),
+    Count(u64),
+}
+
+impl PromptVault {
+    /// Sort keys in the given order, breaking ties alphabetically
+    pub fn sort_keys(&self, keys: &mut [String], order: KeyOrder) -> Result<()> {
+        if order == KeyOrder::Name {
+            keys.sort();
+            return Ok(());
+        }
+
+        let mut ranked = Vec::with_capacity(keys.len());
+        for key in keys.iter() {
+            ranked.push((self.rank(key, order)?, key.clone()));
+        }
+        ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        for (slot, (_, key)) in keys.iter_mut().zip(ranked) {
+            *slot = key;
+        }
+        Ok(())
+    }
+
+    /// Keys starting with `prefix`, in the given order
+    pub fn list_keys_sorted(&self, prefix: &str, order: KeyOrder) -> Result<Vec<String>> {
+        let mut keys = self.list_keys_with_prefix(prefix)?;
+        self.sort_keys(&mut keys, order)?;
+        Ok(keys)
+    }
+
+    fn rank(&self, key: &str, order: KeyOrder) -> Result<Rank> {
+        Ok(match order {
+            KeyOrder::Name => Rank::Count(0),
+            KeyOrder::Modified => Rank::Time(self.history(key)?.last().map(|meta| meta.timestamp)),
+            KeyOrder::Usage => Rank::Count(
+                self.canary_counts(key)?
+                    .iter()
+                    .map(|(_, count)| count)
+                    .sum(),
+            ),
+            // Protected content is encrypted, so its size is unknown and ranks last
+            KeyOrder::Tokens if self.is_sensitive(key)? => Rank::Count(0),
+            KeyOrder::Tokens => match self.history(key)?.last() {
+                Some(meta) => Rank::Count(estimate_tokens(
+                    &self.get(key, VersionSelector::Version(meta.version))?,
+                )),
+                None => Rank::Count(0),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_key_order() -> Result<()> {
+        assert_eq!("usage".parse::<KeyOrder>()?, KeyOrder::Usage);
+        assert!("score".parse::<KeyOrder>().is_err());
+        assert_eq!(KeyOrder::Tokens.next(), KeyOrder::Name);
+        assert_eq!(estimate_tokens("abcde"), 2);
+
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("b/long", &"Think step by step. ".repeat(20))?;
+        vault.add("a/served", "Hi.")?;
+        vault.add("c/recent", "Hello there.")?;
+        vault.tag("a/served", "prod", 1)?;
+        vault.tag("a/served", "next", 1)?;
+        vault.resolve_canary("a/served", "prod", "next", 50, "user-1")?;
+
+        let order = |order| vault.list_keys_sorted("", order);
+        assert_eq!(order(KeyOrder::Name)?, ["a/served", "b/long", "c/recent"]);
+        assert_eq!(order(KeyOrder::Modified)?[0], "c/recent");
+        assert_eq!(order(KeyOrder::Usage)?, ["a/served", "b/long", "c/recent"]);
+        assert_eq!(order(KeyOrder::Tokens)?, ["b/long", "c/recent", "a/served"]);
+        assert_eq!(
+            vault.list_keys_sorted("c/", KeyOrder::Tokens)?,
+            ["c/recent"]
+        );
+
+        Ok(())
+    }
+}
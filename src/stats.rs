@@ -0,0 +1,483 @@
+use crate::namespace::NAMESPACE_SEPARATOR;
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Size and activity of a vault, see [`PromptVault::stats`]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultStats {
+    pub keys: u64,
+    pub versions: u64,
+    /// Stored bytes of contents and diffs
+    pub bytes: u64,
+    /// Timestamp of the most recent version
+    pub last_write: Option<DateTime<Utc>>,
+    /// Breakdown by top-level namespace; keys outside any namespace are under `""`
+    pub namespaces: BTreeMap<String, NamespaceStats>,
+    /// One entry per key, alphabetically
+    #[serde(default)]
+    pub prompts: Vec<KeyStats>,
+    /// Number of keys carrying each tag
+    #[serde(default)]
+    pub tags: BTreeMap<String, u64>,
+    /// Versions and new keys per month, oldest first
+    #[serde(default)]
+    pub growth: Vec<GrowthStats>,
+}
+
+impl VaultStats {
+    /// The `limit` keys with the most versions, most recently written first on ties
+    pub fn most_edited(&self, limit: usize) -> Vec<&KeyStats> {
+        let mut prompts: Vec<&KeyStats> = self.prompts.iter().collect();
+        prompts.sort_by(|a, b| {
+            b.versions
+                .cmp(&a.versions)
+                .then_with(|| b.last_write.cmp(&a.last_write))
+        });
+        prompts.truncate(limit);
+        prompts
+    }
+}
+
+/// Size and activity of one key
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyStats {
+    pub key: String,
+    pub versions: u64,
+    /// Stored bytes of contents and diffs
+    pub bytes: u64,
+    /// Timestamp of the first version
+    pub created: Option<DateTime<Utc>>,
+    pub last_write: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+}
+
+/// Activity of one calendar month
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct GrowthStats {
+    /// `YYYY-MM`, in UTC
+    pub month: String,
+    /// Versions written during the month
+    pub versions: u64,
+    /// Keys whose first version was written during the month
+    pub new_keys: u64,
+}
+
+/// Size and activity of the keys of one namespace
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct NamespaceStats {
+    pub keys: u64,
+    pub versions: u64,
+    pub bytes: u64,
+    pub last_write: Option<DateTime<Utc>>,
+}
+
+impl NamespaceStats {
+    fn add_key(&mut self, versions: u64, bytes: u64, last_write: Option<DateTime<Utc>>) {
+        self.keys += 1;
+        self.versions += versions;
+        self.bytes += bytes;
+        self.last_write = self.last_write.max(last_write);
+    }
+}
+
+/// Output format for `stats export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+    Csv,
+    Parquet,
+}
+
+impl std::str::FromStr for StatsFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(StatsFormat::Csv),
+            "parquet" => Ok(StatsFormat::Parquet),
+            other => Err(anyhow::anyhow!(
+                "Unknown stats format '{}', expected 'csv' or 'parquet'",
+                other
+            )),
+        }
+    }
+}
+
+/// A single column of an exported table
+enum Column {
+    Str(Vec<Option<String>>),
+    U64(Vec<Option<u64>>),
+}
+
+/// A named table of columns, written as one file per table
+struct Table {
+    name: &'static str,
+    columns: Vec<(&'static str, Column)>,
+}
+
+impl Table {
+    fn row_count(&self) -> usize {
+        match self.columns.first() {
+            Some((_, Column::Str(values))) => values.len(),
+            Some((_, Column::U64(values))) => values.len(),
+            None => 0,
+        }
+    }
+}
+
+impl PromptVault {
+    /// Count keys, versions and stored bytes, in total, per top-level namespace and per
+    /// key, with the tags in use and the growth of the vault month by month
+    pub fn stats(&self) -> Result<VaultStats> {
+        self.stats_of(&self.list_keys()?)
+    }
+
+    /// [`PromptVault::stats`] of the keys of a namespace, or of a single key
+    pub fn key_stats(&self, key_or_namespace: &str) -> Result<VaultStats> {
+        if self.get_latest_version_number(key_or_namespace)?.is_some() {
+            return self.stats_of(&[key_or_namespace.to_string()]);
+        }
+        let keys = self.list_namespace(key_or_namespace)?;
+        if keys.is_empty() {
+            return Err(self.unknown_key(key_or_namespace));
+        }
+        self.stats_of(&keys)
+    }
+
+    fn stats_of(&self, keys: &[String]) -> Result<VaultStats> {
+        let mut total = NamespaceStats::default();
+        let mut namespaces: BTreeMap<String, NamespaceStats> = BTreeMap::new();
+        let mut prompts = Vec::new();
+        let mut tags: BTreeMap<String, u64> = BTreeMap::new();
+        let mut months: BTreeMap<String, GrowthStats> = BTreeMap::new();
+        // Shared content is counted for the first key referencing it
+        let mut counted_objects = HashSet::new();
+
+        for key in keys {
+            let history = self.history(key)?;
+            let mut bytes = 0;
+            let mut key_tags = Vec::new();
+            for meta in &history {
+                // Each version is stored either as a full content or as a diff
+                for kind in ["content", "diff"] {
+                    let entry = format!("{}:{}:{}", kind, key, meta.version);
+                    if let Some(value) = self.db().get(entry.as_bytes())? {
+                        bytes += value.len() as u64;
+                    }
+                }
+                // ...or as a reference to content shared with other versions
+                if self.references_object(meta)?
+                    && counted_objects.insert(meta.object_hash.clone())
+                {
+                    if let Some((_, value)) = self.stored_snapshot(meta)? {
+                        bytes += value.len() as u64;
+                    }
+                }
+                key_tags.extend(meta.tags.iter().cloned());
+
+                let month = meta.timestamp.format("%Y-%m").to_string();
+                let growth = months.entry(month.clone()).or_insert_with(|| GrowthStats {
+                    month,
+                    ..GrowthStats::default()
+                });
+                growth.versions += 1;
+                if meta.version == history[0].version {
+                    growth.new_keys += 1;
+                }
+            }
+            let versions = history.len() as u64;
+            let last_write = history.iter().map(|meta| meta.timestamp).max();
+
+            let namespace = match key.split_once(NAMESPACE_SEPARATOR) {
+                Some((namespace, _)) => namespace.to_string(),
+                None => String::new(),
+            };
+            total.add_key(versions, bytes, last_write);
+            namespaces
+                .entry(namespace)
+                .or_default()
+                .add_key(versions, bytes, last_write);
+            key_tags.sort();
+            for tag in &key_tags {
+                *tags.entry(tag.clone()).or_default() += 1;
+            }
+            prompts.push(KeyStats {
+                key: key.clone(),
+                versions,
+                bytes,
+                created: history.first().map(|meta| meta.timestamp),
+                last_write,
+                tags: key_tags,
+            });
+        }
+
+        Ok(VaultStats {
+            keys: total.keys,
+            versions: total.versions,
+            bytes: total.bytes,
+            last_write: total.last_write,
+            namespaces,
+            prompts,
+            tags,
+            growth: months.into_values().collect(),
+        })
+    }
+
+    /// Export usage counters, per-key metrics and the version audit trail to `out_dir`.
+    ///
+    /// Writes `usage`, `metrics` and `audit` tables (one file each) and returns the
+    /// paths of the written files.
+    pub fn export_stats<P: AsRef<Path>>(
+        &self,
+        out_dir: P,
+        format: StatsFormat,
+    ) -> Result<Vec<PathBuf>> {
+        let out_dir = out_dir.as_ref();
+        std::fs::create_dir_all(out_dir)?;
+
+        let tables = [
+            self.usage_table()?,
+            self.metrics_table()?,
+            self.audit_table()?,
+        ];
+        let mut written = Vec::new();
+
+        for table in &tables {
+            let path = match format {
+                StatsFormat::Csv => {
+                    let path = out_dir.join(format!("{}.csv", table.name));
+                    write_csv(table, &path)?;
+                    path
+                }
+                StatsFormat::Parquet => {
+                    let path = out_dir.join(format!("{}.parquet", table.name));
+                    write_parquet(table, &path)?;
+                    path
+                }
+            };
+            written.push(path);
+        }
+
+        Ok(written)
+    }
+
+    /// Served-variant counters recorded by canary resolution
+    fn usage_table(&self) -> Result<Table> {
+        let mut keys = Vec::new();
+        let mut tags = Vec::new();
+        let mut counts = Vec::new();
+
+        for key in self.list_keys()? {
+            for (tag, count) in self.canary_counts(&key)? {
+                keys.push(Some(key.clone()));
+                tags.push(Some(tag));
+                counts.push(Some(count));
+            }
+        }
+
+        Ok(Table {
+            name: "usage",
+            columns: vec![
+                ("key", Column::Str(keys)),
+                ("tag", Column::Str(tags)),
+                ("served", Column::U64(counts)),
+            ],
+        })
+    }
+
+    /// One row per key: version count, latest version, content size, last write
+    fn metrics_table(&self) -> Result<Table> {
+        let mut keys = Vec::new();
+        let mut version_counts = Vec::new();
+        let mut latest_versions = Vec::new();
+        let mut latest_bytes = Vec::new();
+        let mut last_modified = Vec::new();
+
+        for key in self.list_keys()? {
+            let history = self.history(&key)?;
+            let latest = history.last();
+            // Protected content is encrypted, so its size is not reported
+            let bytes = match latest {
+                Some(_) if self.is_sensitive(&key)? => None,
+                Some(meta) => Some(
+                    self.get(&key, VersionSelector::Version(meta.version))?
+                        .len() as u64,
+                ),
+                None => None,
+            };
+
+            keys.push(Some(key.clone()));
+            version_counts.push(Some(history.len() as u64));
+            latest_versions.push(latest.map(|meta| meta.version));
+            latest_bytes.push(bytes);
+            last_modified.push(latest.map(|meta| meta.timestamp.to_rfc3339()));
+        }
+
+        Ok(Table {
+            name: "metrics",
+            columns: vec![
+                ("key", Column::Str(keys)),
+                ("versions", Column::U64(version_counts)),
+                ("latest_version", Column::U64(latest_versions)),
+                ("latest_bytes", Column::U64(latest_bytes)),
+                ("last_modified", Column::Str(last_modified)),
+            ],
+        })
+    }
+
+    /// Every version ever written, as an audit trail of vault changes
+    fn audit_table(&self) -> Result<Table> {
+        let mut keys = Vec::new();
+        let mut versions = Vec::new();
+        let mut timestamps = Vec::new();
+        let mut parents = Vec::new();
+        let mut messages = Vec::new();
+        let mut hashes = Vec::new();
+        let mut tags = Vec::new();
+
+        for key in self.list_keys()? {
+            for meta in self.history(&key)? {
+                keys.push(Some(meta.key));
+                versions.push(Some(meta.version));
+                timestamps.push(Some(meta.timestamp.to_rfc3339()));
+                parents.push(meta.parent);
+                messages.push(meta.message);
+                hashes.push(Some(meta.object_hash));
+                tags.push(Some(meta.tags.join(",")));
+            }
+        }
+
+        Ok(Table {
+            name: "audit",
+            columns: vec![
+                ("key", Column::Str(keys)),
+                ("version", Column::U64(versions)),
+                ("timestamp", Column::Str(timestamps)),
+                ("parent", Column::U64(parents)),
+                ("message", Column::Str(messages)),
+                ("object_hash", Column::Str(hashes)),
+                ("tags", Column::Str(tags)),
+            ],
+        })
+    }
+}
+
+fn write_csv(table: &Table, path: &Path) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(table.columns.iter().map(|(name, _)| *name))?;
+
+    for row in 0..table.row_count() {
+        let record = table.columns.iter().map(|(_, column)| match column {
+            Column::Str(values) => values[row].clone().unwrap_or_default(),
+            Column::U64(values) => values[row].map(|v| v.to_string()).unwrap_or_default(),
+        });
+        writer.write_record(record)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(feature = "arrow")]
+fn write_parquet(table: &Table, path: &Path) -> Result<()> {
+    use arrow::array::{ArrayRef, StringArray, UInt64Array};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    let arrays: Vec<(&str, ArrayRef)> = table
+        .columns
+        .iter()
+        .map(|(name, column)| {
+            let array: ArrayRef = match column {
+                Column::Str(values) => Arc::new(StringArray::from(values.clone())),
+                Column::U64(values) => Arc::new(UInt64Array::from(values.clone())),
+            };
+            (*name, array)
+        })
+        .collect();
+
+    let batch = RecordBatch::try_from_iter(arrays)?;
+    let file = std::fs::File::create(path)?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "arrow"))]
+fn write_parquet(_table: &Table, _path: &Path) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "Parquet export requires promptpro to be built with the `arrow` feature"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_export_stats_csv() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+
+        vault.add("greet", "hello")?;
+        vault.update("greet", "hi", Some("shorter".to_string()))?;
+        vault.tag("greet", "prod", 1)?;
+        vault.resolve_canary("greet", "prod", "dev", 0, "user-1")?;
+
+        let out = dir.path().join("usage");
+        let written = vault.export_stats(&out, StatsFormat::Csv)?;
+        assert_eq!(written.len(), 3);
+
+        let usage = std::fs::read_to_string(out.join("usage.csv"))?;
+        assert_eq!(usage, "key,tag,served\ngreet,prod,1\n");
+
+        let audit = std::fs::read_to_string(out.join("audit.csv"))?;
+        assert_eq!(audit.lines().count(), 3);
+        assert!(audit.contains("shorter"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vault_stats() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        assert_eq!(vault.stats()?, VaultStats::default());
+
+        vault.add("team/agent/system", "system")?;
+        vault.update("team/agent/system", "system v2", None)?;
+        vault.add("team/writer", "writer")?;
+        vault.add("greet", "hello")?;
+
+        let stats = vault.stats()?;
+        assert_eq!((stats.keys, stats.versions), (3, 4));
+        assert!(stats.bytes >= "systemwriterhello".len() as u64);
+        assert_eq!(stats.last_write, Some(vault.history("greet")?[0].timestamp));
+        assert_eq!(stats.namespaces["team"].keys, 2);
+        assert_eq!(stats.namespaces["team"].versions, 3);
+        assert_eq!(stats.namespaces[""].keys, 1);
+
+        vault.tag("team/writer", "prod", 1)?;
+        let stats = vault.stats()?;
+        assert_eq!(stats.most_edited(1)[0].key, "team/agent/system");
+        assert_eq!(stats.prompts[2].tags, ["prod"]);
+        assert_eq!((stats.tags["dev"], stats.tags["prod"]), (1, 1));
+        assert_eq!(stats.growth.len(), 1);
+        assert_eq!((stats.growth[0].versions, stats.growth[0].new_keys), (4, 3));
+
+        let team = vault.key_stats("team")?;
+        assert_eq!((team.keys, team.versions), (2, 3));
+        let writer = vault.key_stats("team/writer")?;
+        assert_eq!((writer.keys, writer.prompts[0].versions), (1, 1));
+        assert!(vault.key_stats("missing").is_err());
+
+        Ok(())
+    }
+}
@@ -1,19 +1,55 @@
-use crate::types::{VersionMeta, VersionSelector};
+use crate::canary::{HashBucketDecider, VariantDecider};
+use crate::compress::Compression;
+use crate::dump::RestorePolicy;
+use crate::encryption::KdfParams;
+use crate::events::EventBus;
+use crate::preload::IndexCache;
+use crate::transaction::{Staged, MAX_ATTEMPTS};
+use crate::types::{VersionMeta, VersionSelector, VersionedPrompt};
+use crate::validate::Validators;
+use crate::vault_lock::open_db;
 use aes_gcm::{
     aead::{Aead, KeyInit},
     Aes256Gcm, Key, Nonce,
 };
 use anyhow::{Context, Result};
-use rand::RngCore;
-use serde::{Deserialize, Serialize};
-use similar::{Algorithm, DiffOp, TextDiff};
-use std::{collections::HashMap, fs, path::PathBuf};
-use std::{io::Read, path::Path};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::{fs, path::PathBuf};
+use std::path::Path;
+
+/// Every Nth version is stored as a full snapshot; versions in between are stored
+/// as unified diffs against their parent, so reconstruction applies at most N - 1 diffs.
+pub const SNAPSHOT_INTERVAL: u64 = 10;
+
+/// A write based on a version that is no longer the latest, because another writer
+/// stored a version first. Returned inside `anyhow::Error`; check with `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "Version conflict on '{key}': the update was based on v{parent}, but the latest version \
+     is v{latest}"
+)]
+pub struct VersionConflict {
+    pub key: String,
+    /// Version the rejected write was based on
+    pub parent: u64,
+    /// Latest version when the write was rejected
+    pub latest: u64,
+}
 
 /// The main storage backend for prompt versions
 #[derive(Clone)]
 pub struct PromptVault {
     db: sled::Db,
+    decider: Arc<dyn VariantDecider>,
+    index: Arc<IndexCache>,
+    /// Key of an encrypted vault, once unlocked
+    cipher: Arc<OnceLock<Aes256Gcm>>,
+    /// Writes of the transaction this vault is part of, not applied yet
+    staged: Option<Arc<Mutex<Staged>>>,
+    events: Arc<EventBus>,
+    /// Recorded on the versions this vault stores
+    author: Option<String>,
+    validators: Arc<Validators>,
 }
 
 impl PromptVault {
@@ -56,8 +92,64 @@ impl PromptVault {
 
     /// Open a prompt vault at the specified path
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let db = sled::open(path)?;
-        Ok(PromptVault { db })
+        let path = path.as_ref();
+        Self::from_db(open_db(path, || sled::open(path))?)
+    }
+
+    /// Open a prompt vault with a page cache of `cache_capacity` bytes instead of
+    /// sled's default, e.g. for servers that read the same prompts over and over
+    pub fn open_with_cache_capacity<P: AsRef<Path>>(path: P, cache_capacity: u64) -> Result<Self> {
+        let path = path.as_ref();
+        let config = sled::Config::new().path(path).cache_capacity(cache_capacity);
+        Self::from_db(open_db(path, || config.open())?)
+    }
+
+    /// An empty vault in memory, e.g. for tests: it uses no vault directory or HOME
+    /// and is gone once the last clone of it is dropped. (sled backs it with a file
+    /// in `/dev/shm` on Linux and in the temp directory elsewhere.)
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_db(sled::Config::new().temporary(true).open()?)
+    }
+
+    pub(crate) fn from_db(db: sled::Db) -> Result<Self> {
+        let vault = PromptVault {
+            db,
+            decider: Arc::new(HashBucketDecider),
+            index: Arc::new(IndexCache::default()),
+            cipher: Arc::new(OnceLock::new()),
+            staged: None,
+            events: Arc::new(EventBus::default()),
+            author: None,
+            validators: Arc::new(Validators::default()),
+        };
+        if vault.db.is_empty() {
+            vault.mark_layout_current()?;
+            vault.set_compression(Compression::default())?;
+        }
+        vault.ensure_key_index()?;
+        Ok(vault)
+    }
+
+    /// Delegate canary variant decisions to a custom decider (e.g. a feature-flag service)
+    pub fn with_variant_decider<D: VariantDecider + 'static>(mut self, decider: D) -> Self {
+        self.decider = Arc::new(decider);
+        self
+    }
+
+    /// Get the decider used for canary variant decisions
+    pub fn variant_decider(&self) -> &dyn VariantDecider {
+        self.decider.as_ref()
+    }
+
+    /// Record `author` on the versions stored through this vault from now on
+    pub fn with_author(mut self, author: Option<String>) -> Self {
+        self.author = author;
+        self
+    }
+
+    /// Author recorded on the versions stored through this vault
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
     }
 
     /// Open the default prompt vault
@@ -70,6 +162,14 @@ impl PromptVault {
         Self::open(path)
     }
 
+    /// Open the vault the CLI works on: the nearest `.promptpro/` project vault above
+    /// the current directory, falling back to the default vault
+    pub fn open_current() -> Result<Self> {
+        let path = crate::utils::current_vault_path()?;
+        std::fs::create_dir_all(&path)?;
+        Self::open(path)
+    }
+
     /// Add a new prompt with the given key and content
     pub fn add(&self, key: &str, content: &str) -> Result<()> {
         // Check if the key already exists
@@ -78,34 +178,89 @@ impl PromptVault {
         }
 
         // Create initial version (version 1) - always a snapshot
-        let version_meta = VersionMeta::new(key.to_string(), 1, content, None, None);
-
-        self.store_version(&version_meta, content, None)?;
-        Ok(())
+        let mut version_meta = VersionMeta::new(key.to_string(), 1, content, None, None);
+        version_meta.author = self.author.clone();
+        version_meta.validated = self.validate(key, content)?;
+
+        match self.atomically(|vault| vault.store_version(&version_meta, content, None)) {
+            // Another writer added the key in the meantime
+            Err(e) if e.is::<VersionConflict>() => {
+                Err(anyhow::anyhow!("Prompt with key '{}' already exists", key))
+            }
+            result => result,
+        }
     }
 
     /// Update an existing prompt with new content
     pub fn update(&self, key: &str, content: &str, message: Option<String>) -> Result<()> {
+        self.update_expecting(key, content, message, None).map(|_| ())
+    }
+
+    /// Update a prompt only if `expected_parent`, when given, is still its latest version,
+    /// failing with [`VersionConflict`] otherwise. Returns the new version number.
+    ///
+    /// Concurrent writers never overwrite each other's versions: version numbers are
+    /// claimed atomically, so of two updates racing for the same number one stores it
+    /// and the other is stored on top of it, or fails with a conflict when it expected
+    /// a parent.
+    pub fn update_expecting(
+        &self,
+        key: &str,
+        content: &str,
+        message: Option<String>,
+        expected_parent: Option<u64>,
+    ) -> Result<u64> {
+        let validated = self.validate(key, content)?;
+        let mut attempts = 1;
+        loop {
+            let result = self.atomically(|vault| {
+                vault.store_update(key, content, message.clone(), expected_parent, validated)
+            });
+            match result {
+                Err(e)
+                    if expected_parent.is_none()
+                        && e.is::<VersionConflict>()
+                        && attempts < MAX_ATTEMPTS =>
+                {
+                    attempts += 1
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Store the next version of `key`, reading its latest version afresh
+    fn store_update(
+        &self,
+        key: &str,
+        content: &str,
+        message: Option<String>,
+        expected_parent: Option<u64>,
+        validated: Option<bool>,
+    ) -> Result<u64> {
         // Get the latest version to use as parent
         let latest_version = self.get_latest_version_number(key)?;
         let parent_version = match latest_version {
             Some(v) => v,
-            None => return Err(anyhow::anyhow!("Prompt with key '{}' does not exist", key)),
+            None => return Err(self.unknown_key(key)),
         };
+        self.check_parent(key, expected_parent, parent_version)?;
+
+        if self.is_sensitive(key)? {
+            return Err(anyhow::anyhow!(
+                "Prompt '{}' is password-protected; update it with a passphrase",
+                key
+            ));
+        }
 
         // Get the current content to check if there are changes
-        let current_content = self.get_content(&key, &VersionSelector::Version(parent_version))?;
+        let current_content = self.get_content(key, &VersionSelector::Version(parent_version))?;
         if current_content == content {
             return Err(anyhow::anyhow!("No changes detected in content"));
         }
 
-        // Always create a new version (snapshot) for now
-        // In a more complex implementation, we might decide to use diffs
-        let new_version = parent_version + 1;
-        let snapshot = true; // Always store as snapshot for simplicity and reliability
-        let diff_content = None; // We're using snapshots
-
         // Create new version metadata
+        let new_version = parent_version + 1;
         let mut version_meta = VersionMeta::new(
             key.to_string(),
             new_version,
@@ -113,35 +268,71 @@ impl PromptVault {
             Some(parent_version),
             message,
         );
-        version_meta.snapshot = snapshot;
+        version_meta.author = self.author.clone();
+        version_meta.validated = validated;
 
-        self.store_version(&version_meta, content, diff_content)?;
+        self.store_version_after(&mut version_meta, content, Some(&current_content))?;
 
         // Always promote the 'dev' tag to the new latest version
         // This ensures dev always points to the most recent version
         let _ = self.tag(key, "dev", new_version); // Ignore errors
 
-        Ok(())
+        Ok(new_version)
     }
 
     /// Get prompt content by key and selector
     pub fn get(&self, key: &str, selector: VersionSelector) -> Result<String> {
-        let version_number = match selector {
+        self.get_versioned(key, selector).map(|prompt| prompt.content)
+    }
+
+    /// Get prompt content with the version the selector resolved to, e.g. to pass that
+    /// version to [`update_expecting`](Self::update_expecting) after editing the content
+    pub fn get_versioned(&self, key: &str, selector: VersionSelector) -> Result<VersionedPrompt> {
+        // Canary reads go through resolve_canary so the served variant is recorded
+        if let VersionSelector::Canary {
+            stable,
+            candidate,
+            percent,
+            request_id,
+        } = selector
+        {
+            let resolution = self.resolve_canary(key, &stable, &candidate, percent, &request_id)?;
+            return Ok(VersionedPrompt {
+                version: resolution.version,
+                content: resolution.content,
+            });
+        }
+
+        let version = self.resolve_version(key, &selector)?;
+        let content = self.get_content(key, &VersionSelector::Version(version))?;
+        Ok(VersionedPrompt { version, content })
+    }
+
+    /// Resolve a selector to a concrete version number
+    pub(crate) fn resolve_version(&self, key: &str, selector: &VersionSelector) -> Result<u64> {
+        match selector {
             VersionSelector::Latest => self
                 .get_latest_version_number(key)?
-                .ok_or_else(|| anyhow::anyhow!("No versions found for key '{}'", key))?,
-            VersionSelector::Version(v) => v,
+                .ok_or_else(|| self.unknown_key(key)),
+            VersionSelector::Version(v) => Ok(*v),
             VersionSelector::Tag(tag) => self
                 .get_version_by_tag(key, tag)?
-                .ok_or_else(|| anyhow::anyhow!("Tag '{}' not found for key '{}'", tag, key))?,
-            VersionSelector::Time(time) => {
-                self.get_version_by_time(key, time)?.ok_or_else(|| {
-                    anyhow::anyhow!("No version found for key '{}' at time {}", key, time)
-                })?
+                .ok_or_else(|| anyhow::anyhow!("Tag '{}' not found for key '{}'", tag, key)),
+            VersionSelector::Time(time) => self.get_version_by_time(key, *time)?.ok_or_else(|| {
+                anyhow::anyhow!("No version found for key '{}' at time {}", key, time)
+            }),
+            VersionSelector::Canary {
+                stable,
+                candidate,
+                percent,
+                request_id,
+            } => {
+                let (tag, _) = self.canary_tag(key, stable, candidate, *percent, request_id)?;
+                self.get_version_by_tag(key, tag)?
+                    .ok_or_else(|| anyhow::anyhow!("Tag '{}' not found for key '{}'", tag, key))
             }
-        };
-
-        self.get_content(key, &VersionSelector::Version(version_number))
+            VersionSelector::BestBy(metric) => self.best_version(key, metric),
+        }
     }
 
     /// Get history of all versions for a key
@@ -150,9 +341,8 @@ impl PromptVault {
         let mut versions = Vec::new();
         let prefix = format!("version:{}:", key);
 
-        for result in self.db.scan_prefix(prefix.as_bytes()) {
-            let (_key, value) = result?;
-            let version_meta: VersionMeta = bincode::deserialize(&value)?;
+        for (_key, value) in self.scan_entries(prefix.as_bytes())? {
+            let version_meta = VersionMeta::decode(&value)?;
             versions.push(version_meta);
         }
 
@@ -163,9 +353,25 @@ impl PromptVault {
 
     /// Tag a specific version
     pub fn tag(&self, key: &str, tag: &str, version: u64) -> Result<()> {
+        // A tag named like a version or a time could never be selected
+        if !matches!(VersionSelector::parse(tag), Ok(VersionSelector::Tag(_))) {
+            return Err(anyhow::anyhow!(
+                "Invalid tag '{}': tags cannot be 'latest', a version (3, v3) or start with '@' \
+                 or 'best:'",
+                tag
+            ));
+        }
+        self.atomically(|vault| vault.store_tag(key, tag, version))
+    }
+
+    /// Point a tag at a version, moving it from the version it pointed to before
+    fn store_tag(&self, key: &str, tag: &str, version: u64) -> Result<()> {
         // Check if the version exists
         let version_key = format!("version:{}:{}", key, version);
-        if self.db.get(version_key.as_bytes())?.is_none() {
+        if self.read_entry(version_key.as_bytes())?.is_none() {
+            if self.get_latest_version_number(key)?.is_none() {
+                return Err(self.unknown_key(key));
+            }
             return Err(anyhow::anyhow!(
                 "Version {} does not exist for key '{}'",
                 version,
@@ -205,7 +411,7 @@ impl PromptVault {
 
         // Create/update the tag entry to point to the new version
         let tag_key = format!("tag:{}:{}", key, tag);
-        self.db.insert(tag_key.as_bytes(), &version.to_le_bytes())?;
+        self.write_entry(tag_key.as_bytes(), &version.to_le_bytes())?;
 
         // Update the new version's metadata to include the tag
         let mut version_meta = self
@@ -220,6 +426,35 @@ impl PromptVault {
         Ok(())
     }
 
+    /// Remove a tag from a prompt; returns the version it pointed to.
+    ///
+    /// `dev` always follows the latest version and cannot be removed.
+    pub fn untag(&self, key: &str, tag: &str) -> Result<u64> {
+        if tag == "dev" {
+            return Err(anyhow::anyhow!(
+                "'dev' always points to the latest version and cannot be removed"
+            ));
+        }
+        self.atomically(|vault| {
+            let version = vault
+                .get_version_by_tag(key, tag)?
+                .ok_or_else(|| anyhow::anyhow!("Tag '{}' not found for key '{}'", tag, key))?;
+
+            let tag_key = format!("tag:{}:{}", key, tag);
+            vault.remove_entry(tag_key.as_bytes())?;
+
+            // Clean every version, not just the tagged one, in case older metadata is stale
+            for mut version_meta in vault.history(key)? {
+                if version_meta.tags.iter().any(|t| t == tag) {
+                    version_meta.tags.retain(|t| t != tag);
+                    vault.update_version_meta(&version_meta)?;
+                }
+            }
+
+            Ok(version)
+        })
+    }
+
     /// Promote a tag to point to the latest version
     pub fn promote(&self, key: &str, tag: &str) -> Result<()> {
         // For 'dev' tag, we always promote to latest, but it's already handled in update()
@@ -231,14 +466,49 @@ impl PromptVault {
         self.tag(key, tag, latest_version)
     }
 
+    /// Restore an earlier version by storing its content as a new latest version.
+    ///
+    /// History is kept intact; returns the number of the new version. Fails with
+    /// [`VersionConflict`] when another version is stored after the one compared with.
+    pub fn rollback(&self, key: &str, selector: VersionSelector) -> Result<u64> {
+        let target = self.resolve_version(key, &selector)?;
+        let latest = self
+            .get_latest_version_number(key)?
+            .ok_or_else(|| anyhow::anyhow!("No versions found for key '{}'", key))?;
+
+        let content = self.get(key, VersionSelector::Version(target))?;
+        if content == self.get(key, VersionSelector::Version(latest))? {
+            return Err(anyhow::anyhow!(
+                "Version {} of '{}' already matches the latest version",
+                target,
+                key
+            ));
+        }
+
+        let message = Some(format!("reverted to v{}", target));
+        self.update_expecting(key, &content, message, Some(latest))
+    }
+
+    /// List all prompt keys in the vault, sorted alphabetically
+    pub fn list_keys(&self) -> Result<Vec<String>> {
+        self.list_keys_with_prefix("")
+    }
+
     /// Get the latest version number for a key
     pub fn get_latest_version_number(&self, key: &str) -> Result<Option<u64>> {
+        if let Some(latest) = self.index.latest(key) {
+            return Ok(latest);
+        }
+        if let Some(latest) = self.indexed_latest(key)? {
+            return Ok(Some(latest));
+        }
+
+        // Not in the latest-version index: no versions, or versions written without it
         let mut versions = Vec::new();
         let prefix = format!("version:{}:", key);
 
-        for result in self.db.scan_prefix(prefix.as_bytes()) {
-            let (_key, value) = result?;
-            let version_meta: VersionMeta = bincode::deserialize(&value)?;
+        for (_key, value) in self.scan_entries(prefix.as_bytes())? {
+            let version_meta = VersionMeta::decode(&value)?;
             versions.push(version_meta.version);
         }
 
@@ -250,9 +520,13 @@ impl PromptVault {
     }
 
     /// Get version number by tag
-    fn get_version_by_tag(&self, key: &str, tag: &str) -> Result<Option<u64>> {
+    pub(crate) fn get_version_by_tag(&self, key: &str, tag: &str) -> Result<Option<u64>> {
+        if let Some(version) = self.index.tag(key, tag) {
+            return Ok(version);
+        }
+
         let tag_key = format!("tag:{}:{}", key, tag);
-        if let Some(value) = self.db.get(tag_key.as_bytes())? {
+        if let Some(value) = self.read_entry(tag_key.as_bytes())? {
             let version_bytes: [u8; 8] = value
                 .as_ref()
                 .try_into()
@@ -273,9 +547,8 @@ impl PromptVault {
         let mut versions = Vec::new();
         let prefix = format!("version:{}:", key);
 
-        for result in self.db.scan_prefix(prefix.as_bytes()) {
-            let (_key, value) = result?;
-            let version_meta: VersionMeta = bincode::deserialize(&value)?;
+        for (_key, value) in self.scan_entries(prefix.as_bytes())? {
+            let version_meta = VersionMeta::decode(&value)?;
             versions.push(version_meta);
         }
 
@@ -293,16 +566,29 @@ impl PromptVault {
             _ => return Err(anyhow::anyhow!("Invalid selector for content retrieval")),
         };
 
+        // Protected content is encrypted; it can only be read through get_protected
+        if self.is_sensitive(key)? {
+            return Err(anyhow::anyhow!(
+                "Prompt '{}' is password-protected; a passphrase is required to read it",
+                key
+            ));
+        }
+
         // Get the version metadata to check if it's a snapshot or diff
-        let version_meta = self
-            .get_version_meta(key, version)?
-            .ok_or_else(|| anyhow::anyhow!("Version {} not found for key '{}'", version, key))?;
+        let version_meta = match self.get_version_meta(key, version)? {
+            Some(meta) => meta,
+            None if self.get_latest_version_number(key)?.is_none() => {
+                return Err(self.unknown_key(key))
+            }
+            None => {
+                return Err(anyhow::anyhow!("Version {} not found for key '{}'", version, key))
+            }
+        };
 
         if version_meta.snapshot {
-            // For snapshots, content is stored directly
-            let content_key = format!("content:{}:{}", key, version);
-            if let Some(content_bytes) = self.db.get(content_key.as_bytes())? {
-                Ok(String::from_utf8(content_bytes.to_vec())?)
+            // For snapshots, content is stored in full
+            if let Some((entry, content_bytes)) = self.stored_snapshot(&version_meta)? {
+                Ok(String::from_utf8(self.decode_blob(entry.as_bytes(), &content_bytes)?)?)
             } else {
                 Err(anyhow::anyhow!(
                     "Content not found for key '{}', version {}, make sure key were added.",
@@ -313,8 +599,9 @@ impl PromptVault {
         } else {
             // For diffs, we need to reconstruct from parent
             let diff_key = format!("diff:{}:{}", key, version);
-            if let Some(diff_bytes) = self.db.get(diff_key.as_bytes())? {
-                let diff_str = String::from_utf8(diff_bytes.to_vec())?;
+            if let Some(diff_bytes) = self.read_entry(diff_key.as_bytes())? {
+                let diff_str =
+                    String::from_utf8(self.decode_blob(diff_key.as_bytes(), &diff_bytes)?)?;
 
                 // Get parent content
                 let parent_version = version_meta.parent.ok_or_else(|| {
@@ -337,31 +624,96 @@ impl PromptVault {
         }
     }
 
-    /// Store a version with its content
+    /// Store a version following `parent_content`: as a diff against the parent, with a
+    /// full snapshot every SNAPSHOT_INTERVAL versions (and for the first version).
+    /// Content already stored for another version is referenced instead of diffed.
+    pub(crate) fn store_version_after(
+        &self,
+        version_meta: &mut VersionMeta,
+        content: &str,
+        parent_content: Option<&str>,
+    ) -> Result<()> {
+        let diff_content = match parent_content {
+            Some(parent)
+                if !(version_meta.version - 1).is_multiple_of(SNAPSHOT_INTERVAL)
+                    && !self.has_object(&version_meta.object_hash)? =>
+            {
+                Some(make_diff(parent, content))
+            }
+            _ => None,
+        };
+        version_meta.snapshot = diff_content.is_none();
+        self.store_version(version_meta, content, diff_content)
+    }
+
+    /// Store a version with its diff against the parent, or as a snapshot referencing
+    /// its content in the object store
     fn store_version(
         &self,
         version_meta: &VersionMeta,
         content: &str,
-        _diff_content: Option<String>,
+        diff_content: Option<String>,
     ) -> Result<()> {
-        // Store the version metadata
-        let version_key = format!("version:{}:{}", version_meta.key, version_meta.version);
-        let meta_bytes = bincode::serialize(version_meta)?;
-        self.db.insert(version_key.as_bytes(), meta_bytes)?;
+        // A locked vault fails before anything is written
+        self.ensure_unlocked()?;
+
+        self.atomically(|vault| {
+            vault.claim_version(version_meta)?;
+            vault.index_key(&version_meta.key)?;
+
+            match &diff_content {
+                Some(diff) => {
+                    let diff_key = format!("diff:{}:{}", version_meta.key, version_meta.version);
+                    let blob = vault.encode_blob(diff_key.as_bytes(), diff.as_bytes())?;
+                    vault.write_entry(diff_key.as_bytes(), blob)?;
+                }
+                None => vault.put_object(&version_meta.object_hash, content)?,
+            }
+            Ok(())
+        })
+    }
 
-        // Always store full content for snapshots (now all versions are snapshots)
-        let content_key = format!("content:{}:{}", version_meta.key, version_meta.version);
-        self.db.insert(content_key.as_bytes(), content.as_bytes())?;
+    /// Fail with [`VersionConflict`] when `expected` is given and `latest` is not it
+    pub(crate) fn check_parent(&self, key: &str, expected: Option<u64>, latest: u64) -> Result<()> {
+        match expected {
+            Some(parent) if parent != latest => Err(VersionConflict {
+                key: key.to_string(),
+                parent,
+                latest,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
 
-        Ok(())
+    /// Store the metadata of a new version, unless another writer already stored that
+    /// version number: the record is only created if absent, and the transaction
+    /// storing it only commits if it still is, so the losing writer gets a
+    /// [`VersionConflict`] without writing any content
+    pub(crate) fn claim_version(&self, version_meta: &VersionMeta) -> Result<()> {
+        let version_key = format!("version:{}:{}", version_meta.key, version_meta.version);
+        let meta_bytes = bincode::serialize(version_meta)?;
+        self.atomically(|vault| {
+            if vault.read_entry(version_key.as_bytes())?.is_some() {
+                let latest = vault.history(&version_meta.key)?.last().map(|meta| meta.version);
+                return Err(VersionConflict {
+                    key: version_meta.key.clone(),
+                    parent: version_meta.version.saturating_sub(1),
+                    latest: latest.unwrap_or(version_meta.version),
+                }
+                .into());
+            }
+            vault.write_entry(version_key.as_bytes(), meta_bytes.as_slice())?;
+            vault.index_version(&version_meta.key, version_meta.version)
+        })
     }
 
     /// Get version metadata
-    fn get_version_meta(&self, key: &str, version: u64) -> Result<Option<VersionMeta>> {
+    pub(crate) fn get_version_meta(&self, key: &str, version: u64) -> Result<Option<VersionMeta>> {
         let version_key = format!("version:{}:{}", key, version);
 
-        if let Some(value) = self.db.get(version_key.as_bytes())? {
-            let version_meta: VersionMeta = bincode::deserialize(&value)?;
+        if let Some(value) = self.read_entry(version_key.as_bytes())? {
+            let version_meta = VersionMeta::decode(&value)?;
             Ok(Some(version_meta))
         } else {
             Ok(None)
@@ -369,11 +721,46 @@ impl PromptVault {
     }
 
     /// Update version metadata (used when adding tags)
-    fn update_version_meta(&self, version_meta: &VersionMeta) -> Result<()> {
+    pub(crate) fn update_version_meta(&self, version_meta: &VersionMeta) -> Result<()> {
         let version_key = format!("version:{}:{}", version_meta.key, version_meta.version);
         let meta_bytes = bincode::serialize(version_meta)?;
-        self.db.insert(version_key.as_bytes(), meta_bytes)?;
-        Ok(())
+        self.write_entry(version_key.as_bytes(), meta_bytes)
+    }
+
+    pub(crate) fn index_cache(&self) -> &IndexCache {
+        &self.index
+    }
+
+    /// Writes of the transaction this vault is part of, if any
+    pub(crate) fn staged(&self) -> Option<&Mutex<Staged>> {
+        self.staged.as_deref()
+    }
+
+    /// A vault staging its writes for a new transaction. Its index is never preloaded,
+    /// so reads see the staged writes.
+    pub(crate) fn staging(&self) -> PromptVault {
+        PromptVault {
+            db: self.db.clone(),
+            decider: self.decider.clone(),
+            index: Arc::new(IndexCache::default()),
+            cipher: self.cipher.clone(),
+            staged: Some(Arc::default()),
+            events: self.events.clone(),
+            author: self.author.clone(),
+            validators: self.validators.clone(),
+        }
+    }
+
+    pub(crate) fn validators(&self) -> &Validators {
+        &self.validators
+    }
+
+    pub(crate) fn event_bus(&self) -> &EventBus {
+        &self.events
+    }
+
+    pub(crate) fn vault_cipher(&self) -> &OnceLock<Aes256Gcm> {
+        &self.cipher
     }
 
     /// Get access to the underlying database (for TUI usage)
@@ -383,81 +770,88 @@ impl PromptVault {
 
     /// Delete a prompt key and all its versions
     pub fn delete_prompt_key(&self, key: &str) -> Result<()> {
+        self.atomically(|vault| vault.remove_prompt_entries(key))?;
+        self.index.forget(key);
+        Ok(())
+    }
+
+    /// Remove the versions of a key and every record belonging to it
+    fn remove_prompt_entries(&self, key: &str) -> Result<()> {
         // Get all versions for this key to clean up related data
         let versions = self.history(key)?;
-        
+
         // Delete all version entries and related content/diff data
         for version in &versions {
             let version_key = format!("version:{}:{}", key, version.version);
-            self.db.remove(version_key.as_bytes())?;
-            
-            // Delete content for this version
+            self.remove_entry(version_key.as_bytes())?;
+
+            // Delete content for this version, or its reference to shared content
+            if self.references_object(version)? {
+                self.release_object(&version.object_hash)?;
+            }
             let content_key = format!("content:{}:{}", key, version.version);
-            self.db.remove(content_key.as_bytes())?;
-            
+            self.remove_entry(content_key.as_bytes())?;
+
             // Delete diff if it exists (for future compatibility)
             let diff_key = format!("diff:{}:{}", key, version.version);
-            self.db.remove(diff_key.as_bytes())?;
+            self.remove_entry(diff_key.as_bytes())?;
         }
-        
-        // Delete all tag entries for this key
-        let tag_prefix = format!("tag:{}:", key);
-        for result in self.db.scan_prefix(tag_prefix.as_bytes()) {
-            let (tag_key, _) = result?;
-            self.db.remove(tag_key)?;
+
+        // Delete all tag entries and canary counters for this key
+        for prefix in [format!("tag:{}:", key), format!("canary:{}:", key)] {
+            for (entry, _) in self.scan_entries(prefix.as_bytes())? {
+                self.remove_entry(&entry)?;
+            }
         }
-        
-        Ok(())
+
+        // Delete the password-protection marker
+        let sensitive_key = format!("sensitive:{}", key);
+        self.remove_entry(sensitive_key.as_bytes())?;
+
+        // Delete the adoption record
+        let provenance_key = format!("provenance:{}", key);
+        self.remove_entry(provenance_key.as_bytes())?;
+
+        self.remove_prompt_meta(key)?;
+        self.set_notes(key, None)?;
+        self.remove_comments(key)?;
+        self.remove_experiments(key)?;
+        self.remove_test_cases(key)?;
+        self.remove_scores(key)?;
+        self.remove_aliases_to(key)?;
+
+        self.unindex_versions(key)?;
+        self.unindex_key(key)
     }
 
     /// Export the entire vault to a binary file
     pub fn dump(&self, output_path: &str, password: Option<&str>) -> Result<()> {
-        use std::fs::File;
-        use std::io::Write;
-
-        // Collect all data from sled database
-        let mut data = Vec::new();
-        for result in self.db.iter() {
-            let (key, value) = result?;
-            data.push((key.to_vec(), value.to_vec()));
-        }
-
-        // Serialize the data
-        let serialized_data = bincode::serialize(&data)?;
-
-        let output_data = if let Some(password) = password {
-            // Encrypt the data
-            let encrypted = self.encrypt_data(&serialized_data, password)?;
-            // Add a header to indicate this is encrypted
-            let mut output = b"VAULT_ENC".to_vec(); // 9-byte header
-            output.extend_from_slice(&encrypted);
-            output
-        } else {
-            // Not encrypted - add header to indicate unencrypted
-            let mut output = b"VAULT_RAW".to_vec(); // 9-byte header
-            output.extend_from_slice(&serialized_data);
-            output
-        };
+        self.dump_with_kdf(output_path, password, KdfParams::default())
+    }
 
-        // Write to file
-        let mut file = File::create(output_path)?;
-        file.write_all(&output_data)?;
+    /// Export the entire vault to a binary file, deriving the key of an encrypted dump
+    /// with custom Argon2id parameters, e.g. more memory for long-term backups
+    pub fn dump_with_kdf(
+        &self,
+        output_path: &str,
+        password: Option<&str>,
+        kdf: KdfParams,
+    ) -> Result<()> {
+        kdf.validate()?;
+        let file = fs::File::create(output_path)?;
+        self.write_dump(std::io::BufWriter::new(file), password, kdf)
+    }
 
-        Ok(())
+    /// Check whether a dump file is password-encrypted
+    pub fn is_encrypted_dump(input_path: &str) -> Result<bool> {
+        Ok(Self::inspect_dump(input_path)?.encrypted)
     }
 
-    /// Import data from a binary vault file
+    /// Import data from a binary vault file into `~/.promptpro/<file name>`, keeping
+    /// a vault already there as it is; see [`PromptVault::restore_to`]
     pub fn restore(input_path: &str, password: Option<&str>) -> Result<Self> {
-        let input_path = Path::new(input_path);
-        if !input_path.exists() {
-            return Err(anyhow::anyhow!(
-                "Vault file not found: {}",
-                input_path.display()
-            ));
-        }
-
         // vault_name = filename without extension
-        let vault_name = input_path
+        let vault_name = Path::new(input_path)
             .file_stem()
             .and_then(|s| s.to_str())
             .ok_or_else(|| anyhow::anyhow!("Invalid vault filename"))?;
@@ -466,101 +860,12 @@ impl PromptVault {
         let home = std::env::var("HOME").map_err(|_| anyhow::anyhow!("HOME env not found"))?;
         let target_path = PathBuf::from(home).join(".promptpro").join(vault_name);
 
-        // if already exists, skip restore
-        if target_path.exists() {
-            println!(
-                "✅ Vault '{}' already exists — skipping restore.",
-                vault_name
-            );
-            return Self::open(&target_path);
-        }
-
-        // read full file
-        let mut data = Vec::new();
-
-        std::fs::File::open(input_path)?.read_to_end(&mut data)?;
-        if data.len() < 9 {
-            return Err(anyhow::anyhow!("Invalid vault file: too short"));
-        }
-
-        let header = &data[..9];
-        let payload = &data[9..];
-
-        // decrypt or raw load
-        let raw = if header == b"VAULT_ENC" {
-            if let Some(pwd) = password {
-                Self::decrypt_data(payload, pwd)?
-            } else {
-                return Err(anyhow::anyhow!("Vault encrypted but no password provided"));
-            }
-        } else if header == b"VAULT_RAW" {
-            payload.to_vec()
-        } else {
-            return Err(anyhow::anyhow!("Invalid vault file header"));
-        };
-
-        // deserialize data
-        let entries: Vec<(Vec<u8>, Vec<u8>)> = bincode::deserialize(&raw)
-            .map_err(|_| anyhow::anyhow!("Failed to deserialize vault"))?;
-
-        // create target dir and insert
-        fs::create_dir_all(&target_path)?;
-        let vault = Self::open(&target_path)?;
-
-        for (k, v) in entries {
-            vault.db.insert(k, v)?;
-        }
-        vault.db.flush()?;
-
-        println!(
-            "✅ Restored vault '{}' → {}",
-            vault_name,
-            target_path.display()
-        );
-
-        Ok(vault)
-    }
-
-    /// Encrypt data with the given password
-    fn encrypt_data(&self, data: &[u8], password: &str) -> Result<Vec<u8>> {
-        use blake3;
-
-        // Derive a key from the password using blake3
-        let mut salt = [0u8; 32];
-        rand::thread_rng().fill_bytes(&mut salt);
-
-        // Derive key using blake3
-        let mut key_bytes = [0u8; 32];
-        let mut hasher = blake3::Hasher::new();
-        hasher.update(password.as_bytes());
-        hasher.update(&salt);
-        let hash = hasher.finalize();
-        (&mut key_bytes).copy_from_slice(&hash.as_bytes()[..32]);
-
-        let key = Key::<Aes256Gcm>::from_slice(&key_bytes);
-        let cipher = Aes256Gcm::new(key);
-
-        // Generate random nonce
-        let mut nonce_bytes = [0u8; 12];
-        rand::thread_rng().fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
-
-        // Encrypt the data
-        let ciphertext = cipher
-            .encrypt(nonce, data)
-            .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
-
-        // Combine salt + nonce + ciphertext
-        let mut result = Vec::new();
-        result.extend_from_slice(&salt);
-        result.extend_from_slice(&nonce_bytes);
-        result.extend_from_slice(&ciphertext);
-
-        Ok(result)
+        Self::restore_to(input_path, target_path, password, RestorePolicy::Skip)
     }
 
-    /// Decrypt data with the given password
-    fn decrypt_data(data: &[u8], password: &str) -> Result<Vec<u8>> {
+    /// Decrypt data encrypted with a key hashed from `password` with blake3, as the
+    /// format 1 dumps of older releases were
+    pub(crate) fn decrypt_data(data: &[u8], password: &str) -> Result<Vec<u8>> {
         use blake3;
 
         if data.len() < 44 {
@@ -579,7 +884,7 @@ impl PromptVault {
         hasher.update(password.as_bytes());
         hasher.update(salt);
         let hash = hasher.finalize();
-        (&mut key_bytes).copy_from_slice(&hash.as_bytes()[..32]);
+        key_bytes.copy_from_slice(&hash.as_bytes()[..32]);
 
         let key = Key::<Aes256Gcm>::from_slice(&key_bytes);
         let cipher = Aes256Gcm::new(key);
@@ -594,10 +899,106 @@ impl PromptVault {
     }
 }
 
-/// Apply a diff to old content to get new content (placeholder - not used when using snapshots)
-fn apply_diff(_old_content: &str, _diff_str: &str) -> Result<String> {
-    // This function is not used when using snapshots only
-    Ok("".to_string())
+/// Create a unified diff turning `old_content` into `new_content`
+pub(crate) fn make_diff(old_content: &str, new_content: &str) -> String {
+    // Force newline-terminated output so a missing final newline is always marked
+    similar::TextDiff::configure()
+        .newline_terminated(true)
+        .diff_lines(old_content, new_content)
+        .unified_diff()
+        .to_string()
+}
+
+/// A line of a unified diff hunk, including its trailing newline if it has one
+enum DiffLine<'a> {
+    Context(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// Apply a unified diff created by `make_diff` to old content to get new content
+pub(crate) fn apply_diff(old_content: &str, diff_str: &str) -> Result<String> {
+    let old_lines: Vec<&str> = old_content.split_inclusive('\n').collect();
+    let mut hunks: Vec<(usize, Vec<DiffLine>)> = Vec::new();
+
+    for line in diff_str.split_inclusive('\n') {
+        if let Some(header) = line.strip_prefix("@@ -") {
+            // "@@ -start[,len] +start[,len] @@"; an empty old range starts at the line before it
+            let range = header.split_whitespace().next().unwrap_or_default();
+            let (start, len) = match range.split_once(',') {
+                Some((start, len)) => (start.parse::<usize>()?, len.parse::<usize>()?),
+                None => (range.parse::<usize>()?, 1),
+            };
+            let start = if len == 0 { start } else { start.saturating_sub(1) };
+            hunks.push((start, Vec::new()));
+            continue;
+        }
+
+        let (_, lines) = hunks
+            .last_mut()
+            .ok_or_else(|| anyhow::anyhow!("Malformed diff: content before first hunk"))?;
+
+        if line.starts_with('\\') {
+            // "\ No newline at end of file" applies to the previous line
+            let previous = match lines.last_mut() {
+                Some(DiffLine::Context(text))
+                | Some(DiffLine::Delete(text))
+                | Some(DiffLine::Insert(text)) => text,
+                None => return Err(anyhow::anyhow!("Malformed diff: stray newline marker")),
+            };
+            *previous = previous.strip_suffix('\n').unwrap_or(previous);
+            continue;
+        }
+
+        let text = line.get(1..).unwrap_or_default();
+        match line.chars().next() {
+            Some(' ') => lines.push(DiffLine::Context(text)),
+            Some('-') => lines.push(DiffLine::Delete(text)),
+            Some('+') => lines.push(DiffLine::Insert(text)),
+            _ => return Err(anyhow::anyhow!("Malformed diff line: {:?}", line)),
+        }
+    }
+
+    let mut result = String::with_capacity(old_content.len());
+    let mut position = 0;
+
+    for (start, lines) in hunks {
+        if start < position || start > old_lines.len() {
+            return Err(anyhow::anyhow!("Diff hunk out of range at line {}", start + 1));
+        }
+        old_lines[position..start]
+            .iter()
+            .for_each(|line| result.push_str(line));
+        position = start;
+
+        for line in lines {
+            match line {
+                DiffLine::Insert(text) => result.push_str(text),
+                DiffLine::Context(text) => {
+                    expect_line(&old_lines, position, text)?;
+                    result.push_str(text);
+                    position += 1;
+                }
+                DiffLine::Delete(text) => {
+                    expect_line(&old_lines, position, text)?;
+                    position += 1;
+                }
+            }
+        }
+    }
+
+    old_lines[position..]
+        .iter()
+        .for_each(|line| result.push_str(line));
+    Ok(result)
+}
+
+/// Check that a context or deleted diff line matches the old content
+fn expect_line(old_lines: &[&str], position: usize, text: &str) -> Result<()> {
+    if old_lines.get(position) != Some(&text) {
+        return Err(anyhow::anyhow!("Diff does not apply at line {}", position + 1));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -638,6 +1039,50 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_concurrent_updates() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "v1")?;
+
+        // A write based on a stale version is rejected with the versions involved
+        let read = vault.get_versioned("writer", VersionSelector::Latest)?;
+        assert_eq!((read.version, read.etag().as_str()), (1, "\"1\""));
+        assert_eq!(vault.update_expecting("writer", "v2", None, Some(read.version))?, 2);
+        let stale = vault.update_expecting("writer", "v2'", None, Some(1)).unwrap_err();
+        let conflict = stale.downcast_ref::<VersionConflict>().unwrap();
+        assert_eq!((conflict.parent, conflict.latest), (1, 2));
+
+        // Racing writers each get a version number of their own, never overwriting
+        // another writer's version
+        vault.preload()?;
+        let writers: Vec<_> = (0..8)
+            .map(|writer| {
+                let vault = vault.clone();
+                std::thread::spawn(move || {
+                    vault.update_expecting("writer", &format!("w{}", writer), None, None)
+                })
+            })
+            .collect();
+        let mut versions = Vec::new();
+        for writer in writers {
+            versions.push(writer.join().unwrap()?);
+        }
+        versions.sort();
+        assert_eq!(versions, (3..=10).collect::<Vec<u64>>());
+        let mut contents = Vec::new();
+        for version in 3..=10 {
+            contents.push(vault.get("writer", VersionSelector::Version(version))?);
+        }
+        contents.sort();
+        assert_eq!(contents, (0..8).map(|w| format!("w{}", w)).collect::<Vec<_>>());
+        assert_eq!(vault.get_latest_version_number("writer")?, Some(10));
+        assert_eq!(vault.get_version_by_tag("writer", "dev")?, Some(10));
+        assert!(vault.verify(false)?.is_clean());
+
+        Ok(())
+    }
+
     #[test]
     fn test_tagging() -> Result<()> {
         let dir = tempdir()?;
@@ -650,14 +1095,45 @@ mod tests {
         vault.tag("test_key", "stable", 1)?;
 
         // Get content by tag
-        let content = vault.get("test_key", VersionSelector::Tag("stable"))?;
+        let content = vault.get("test_key", VersionSelector::tag("stable"))?;
         assert_eq!(content, "content v1");
 
         // Promote tag to latest
         vault.promote("test_key", "stable")?;
-        let content = vault.get("test_key", VersionSelector::Tag("stable"))?;
+        let content = vault.get("test_key", VersionSelector::tag("stable"))?;
         assert_eq!(content, "content v2");
 
+        // Remove the tag again
+        assert_eq!(vault.untag("test_key", "stable")?, 2);
+        assert!(vault.get("test_key", VersionSelector::tag("stable")).is_err());
+        let history = vault.history("test_key")?;
+        assert!(history.iter().all(|m| !m.tags.iter().any(|t| t == "stable")));
+        assert!(vault.untag("test_key", "stable").is_err());
+        assert!(vault.untag("test_key", "dev").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollback() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+
+        vault.add("greet", "hello")?;
+        vault.update("greet", "hi", None)?;
+        vault.update("greet", "broken", None)?;
+        vault.tag("greet", "stable", 2)?;
+
+        assert_eq!(vault.rollback("greet", VersionSelector::tag("stable"))?, 4);
+        assert_eq!(vault.get("greet", VersionSelector::Latest)?, "hi");
+        assert_eq!(vault.get("greet", VersionSelector::tag("dev"))?, "hi");
+        let history = vault.history("greet")?;
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[3].message.as_deref(), Some("reverted to v2"));
+
+        // Rolling back to content identical to the latest version is refused
+        assert!(vault.rollback("greet", VersionSelector::Version(2)).is_err());
+
         Ok(())
     }
 
@@ -713,11 +1189,66 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_delta_storage() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+
+        let mut expected = vec!["line one\nline two\nline three".to_string()];
+        vault.add("delta", &expected[0])?;
+        for i in 1..25 {
+            let previous = expected.last().unwrap();
+            let next = match i % 4 {
+                0 => format!("{}\nappended {}", previous, i),
+                1 => format!("row {}\n{}", i, previous),
+                2 => format!("{}\n", previous),
+                _ => previous.lines().skip(1).collect::<Vec<_>>().join("\n") + "\nnew tail",
+            };
+            vault.update("delta", &next, None)?;
+            expected.push(next);
+        }
+
+        for (i, content) in expected.iter().enumerate() {
+            let version = i as u64 + 1;
+            assert_eq!(&vault.get("delta", VersionSelector::Version(version))?, content);
+
+            // Checkpoints are full snapshots, everything in between is a diff
+            let snapshot = (version - 1).is_multiple_of(SNAPSHOT_INTERVAL);
+            let diff_key = format!("diff:delta:{}", version);
+            let meta = vault.get_version_meta("delta", version)?.unwrap();
+            assert_eq!(vault.db().contains_key(diff_key)?, !snapshot);
+            assert_eq!(meta.snapshot, snapshot);
+            assert_eq!(vault.has_object(&meta.object_hash)?, snapshot);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_diff() -> Result<()> {
+        let cases = [
+            ("", "hello"),
+            ("hello", ""),
+            ("a\nb\nc\n", "a\nc\n"),
+            ("a\nb\nc", "a\nb\nc\n"),
+            ("a\nb\nc\n", "a\nb\nc"),
+            ("x\n", "y\nx\n"),
+        ];
+        for (old, new) in cases {
+            assert_eq!(apply_diff(old, &make_diff(old, new))?, new);
+        }
+
+        // Diffs refuse to apply to content they were not made from
+        assert!(apply_diff("other\n", &make_diff("a\nb\n", "a\nc\n")).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_dump_restore_unencrypted() -> Result<()> {
         use tempfile::tempdir;
         let source_dir = tempdir()?;
-        let _target_dir = tempdir()?;
+        let target_dir = tempdir()?;
 
         let source_vault = PromptVault::open(source_dir.path())?;
 
@@ -739,7 +1270,12 @@ mod tests {
         source_vault.dump(dump_file.to_str().unwrap(), None)?;
 
         // Restore to a new vault location
-        let restored_vault = PromptVault::restore(dump_file.to_str().unwrap(), None)?;
+        let restored_vault = PromptVault::restore_to(
+            dump_file.to_str().unwrap(),
+            target_dir.path().join("restored"),
+            None,
+            RestorePolicy::Refuse,
+        )?;
 
         // Check that the restored data is the same
         let content = restored_vault.get("test_key", VersionSelector::Latest)?;
@@ -761,7 +1297,7 @@ mod tests {
     fn test_dump_restore_encrypted() -> Result<()> {
         use tempfile::tempdir;
         let source_dir = tempdir()?;
-        let _target_dir = tempdir()?;
+        let target_dir = tempdir()?;
 
         let source_vault = PromptVault::open(source_dir.path())?;
 
@@ -779,7 +1315,12 @@ mod tests {
         source_vault.dump(dump_file.to_str().unwrap(), Some("mypassword"))?;
 
         // Restore the vault from the file with correct password
-        let restored_vault = PromptVault::restore(dump_file.to_str().unwrap(), Some("mypassword"))?;
+        let restored_vault = PromptVault::restore_to(
+            dump_file.to_str().unwrap(),
+            target_dir.path().join("restored"),
+            Some("mypassword"),
+            RestorePolicy::Refuse,
+        )?;
 
         // Check that the restored data is the same
         let content = restored_vault.get("encrypted_key", VersionSelector::Latest)?;
@@ -791,8 +1332,14 @@ mod tests {
         assert!(history[0].tags.contains(&"secret".to_string()));
 
         // Try to restore with wrong password - should fail
-        let result = PromptVault::restore(dump_file.to_str().unwrap(), Some("wrongpassword"));
+        let result = PromptVault::restore_to(
+            dump_file.to_str().unwrap(),
+            target_dir.path().join("wrong"),
+            Some("wrongpassword"),
+            RestorePolicy::Refuse,
+        );
         assert!(result.is_err());
+        assert!(!target_dir.path().join("wrong").exists());
 
         Ok(())
     }
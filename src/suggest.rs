@@ -0,0 +1,82 @@
+//! Suggesting existing keys in place of mistyped ones.
+
+use crate::storage::PromptVault;
+use anyhow::Result;
+
+/// Most keys offered for one unknown key
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Jaro-Winkler similarity from which a key counts as a likely typo
+const MIN_SIMILARITY: f64 = 0.8;
+
+impl PromptVault {
+    /// Existing keys that `key` is likely a typo of, most similar first
+    pub fn suggest_keys(&self, key: &str) -> Result<Vec<String>> {
+        let mut scored: Vec<(f64, String)> = self
+            .list_keys()?
+            .into_iter()
+            .filter(|candidate| candidate != key)
+            .map(|candidate| (strsim::jaro_winkler(key, &candidate), candidate))
+            // Edit distance catches typos in short keys that similarity scores low
+            .filter(|(score, candidate)| {
+                *score >= MIN_SIMILARITY || strsim::levenshtein(key, candidate) <= 2
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+        Ok(scored
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, candidate)| candidate)
+            .collect())
+    }
+
+    /// The error for a key without versions, naming the keys it may be a typo of
+    pub(crate) fn unknown_key(&self, key: &str) -> anyhow::Error {
+        let suggestions = self.suggest_keys(key).unwrap_or_default();
+        anyhow::anyhow!("No versions found for key '{}'{}", key, did_you_mean(&suggestions))
+    }
+}
+
+/// `; did you mean 'a', 'b' or 'c'?`, or nothing without suggestions
+pub(crate) fn did_you_mean(suggestions: &[String]) -> String {
+    let quoted: Vec<String> = suggestions.iter().map(|key| format!("'{}'", key)).collect();
+    match quoted.as_slice() {
+        [] => String::new(),
+        [only] => format!("; did you mean {}?", only),
+        [rest @ .., last] => format!("; did you mean {} or {}?", rest.join(", "), last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_suggest_keys() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        for key in ["assistant", "agents/planner", "agents/plannr-v2", "db"] {
+            vault.add(key, "content")?;
+        }
+
+        assert_eq!(vault.suggest_keys("asistant")?, ["assistant"]);
+        let suggestions = vault.suggest_keys("agents/plannr")?;
+        assert!(suggestions.contains(&"agents/planner".to_string()));
+        assert_eq!(vault.suggest_keys("dc")?, ["db"]);
+        assert!(vault.suggest_keys("summarizer")?.is_empty());
+
+        let error = vault.get("asistant", VersionSelector::Latest).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No versions found for key 'asistant'; did you mean 'assistant'?"
+        );
+        assert!(vault.tag("asistant", "stable", 1).is_err());
+        let error = vault.update("asistant", "new", None).unwrap_err();
+        assert!(error.to_string().ends_with("did you mean 'assistant'?"));
+
+        Ok(())
+    }
+}
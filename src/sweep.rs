@@ -0,0 +1,179 @@
+//! Background maintenance for long-running `promptpro serve` deployments.
+//!
+//! A sweep runs [`verify`](PromptVault::verify), repairing what it can, then
+//! [`gc`](PromptVault::gc) with the configured retention. Sweeps run at a jittered
+//! interval, so replicas started together do not all sweep at once. The last report is
+//! stored in the vault and served at `/health`.
+
+use crate::gc::{GcReport, RetentionPolicy};
+use crate::storage::PromptVault;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Where the report of the last sweep is kept
+const LAST_SWEEP_KEY: &[u8] = b"meta:last_sweep";
+
+/// Share of the interval a sweep may run early or late
+const JITTER: f64 = 0.1;
+
+/// When sweeps run and which versions they keep
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SweepOptions {
+    /// Time between sweeps, before jitter
+    pub every: Duration,
+    /// Keep this many of the latest versions of each key
+    pub keep_last: Option<usize>,
+    /// Keep versions written within this window of each sweep
+    pub keep_within: Option<chrono::Duration>,
+    /// Keep versions that have a tag
+    pub keep_tagged: bool,
+}
+
+impl SweepOptions {
+    /// Sweeps every `every` that only remove orphaned entries
+    pub fn new(every: Duration) -> Self {
+        SweepOptions {
+            every,
+            keep_last: None,
+            keep_within: None,
+            keep_tagged: true,
+        }
+    }
+
+    /// The retention of a sweep starting at `now`
+    pub fn retention(&self, now: DateTime<Utc>) -> RetentionPolicy {
+        RetentionPolicy {
+            keep_last: self.keep_last,
+            keep_tagged: self.keep_tagged,
+            keep_newer_than: self.keep_within.map(|window| now - window),
+        }
+    }
+
+    /// Delay before the next sweep: the interval, give or take 10%
+    pub fn next_delay(&self) -> Duration {
+        self.every
+            .mul_f64(rand::thread_rng().gen_range(1.0 - JITTER..=1.0 + JITTER))
+    }
+}
+
+/// What a sweep found and fixed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SweepReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Issues a sweep cannot repair, such as corrupted versions
+    pub problems: Vec<String>,
+    /// Issues repaired, such as dangling tags
+    pub repaired: usize,
+    pub gc: GcReport,
+}
+
+impl SweepReport {
+    /// One-line summary for the server log
+    pub fn summary(&self) -> String {
+        format!(
+            "{} problem(s), {} issue(s) repaired, {} version(s) pruned, {} orphan(s) removed \
+             in {} ms",
+            self.problems.len(),
+            self.repaired,
+            self.gc.pruned_versions(),
+            self.gc.orphans,
+            (self.finished_at - self.started_at).num_milliseconds()
+        )
+    }
+}
+
+impl PromptVault {
+    /// Run one sweep and store its report (see the [module docs](self))
+    pub fn sweep(&self, options: &SweepOptions) -> Result<SweepReport> {
+        let started_at = Utc::now();
+        let verified = self.verify(true)?;
+        let problems = verified.outstanding().map(|issue| issue.to_string()).collect();
+        let repaired = verified.issues.iter().filter(|issue| issue.repairable()).count();
+        let report = SweepReport {
+            started_at,
+            problems,
+            repaired,
+            gc: self.gc(&options.retention(started_at))?,
+            finished_at: Utc::now(),
+        };
+        self.db()
+            .insert(LAST_SWEEP_KEY, serde_json::to_vec(&report)?)?;
+        self.db().flush()?;
+        Ok(report)
+    }
+
+    /// Report of the last sweep, if any ran
+    pub fn last_sweep(&self) -> Result<Option<SweepReport>> {
+        match self.db().get(LAST_SWEEP_KEY)? {
+            Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Sweep `vault` in the background until the runtime shuts down, logging each report
+pub fn spawn_sweeps(vault: PromptVault, options: SweepOptions) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(options.next_delay()).await;
+            let (vault, sweep) = (vault.clone(), options.clone());
+            match tokio::task::spawn_blocking(move || vault.sweep(&sweep)).await {
+                Ok(Ok(report)) => {
+                    println!("Sweep: {}", report.summary());
+                    for problem in &report.problems {
+                        eprintln!("Sweep problem: {}", problem);
+                    }
+                }
+                Ok(Err(e)) => eprintln!("Sweep failed: {}", e),
+                Err(e) => eprintln!("Sweep panicked: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sweep() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        assert_eq!(vault.last_sweep()?, None);
+        vault.add("writer", "v1")?;
+        for step in 2..=4 {
+            vault.update("writer", &format!("v{}", step), None)?;
+        }
+        vault.add("planner", "plan")?;
+
+        // A stale index entry, a dangling tag, an orphaned diff and a corrupted version
+        vault.index_key("ghost")?;
+        vault.db().insert("tag:planner:prod", &7u64.to_le_bytes())?;
+        vault.db().insert("diff:ghost:1", b"stale".as_slice())?;
+        vault.db().remove("diff:writer:4")?;
+
+        let options = SweepOptions {
+            keep_last: Some(2),
+            ..SweepOptions::new(Duration::from_secs(3600))
+        };
+        let report = vault.sweep(&options)?;
+        assert_eq!(report.repaired, 3);
+        assert_eq!(report.gc.pruned_versions(), 2);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].starts_with("writer v4: cannot be read"));
+        assert_eq!(vault.list_keys()?, ["planner", "writer"]);
+        assert_eq!(vault.get("writer", VersionSelector::Version(3))?, "v3");
+        assert_eq!(vault.last_sweep()?, Some(report));
+
+        let delay = options.next_delay();
+        assert!(delay >= Duration::from_secs(3240) && delay <= Duration::from_secs(3960));
+
+        Ok(())
+    }
+}
@@ -2,21 +2,20 @@ use anyhow::Result;
 use std::sync::Arc;
 use std::sync::RwLock;
 
-use crate::{PromptVault, VersionSelector};
+use crate::options::{InitError, ManagerOptions};
+use crate::{PromptVault, VersionSelector, VersionedPrompt};
 
 /// Synchronous default prompt manager (singleton)
 #[derive(Clone)]
 pub struct SyncPromptManager {
     vault: Arc<RwLock<PromptVault>>,
+    options: ManagerOptions,
 }
 
 impl SyncPromptManager {
-    /// Create a new sync prompt manager with the default vault
+    /// Create a new sync prompt manager configured from the environment
     pub fn new() -> Result<Self> {
-        let vault = PromptVault::open_default()?;
-        Ok(SyncPromptManager {
-            vault: Arc::new(RwLock::new(vault)),
-        })
+        Self::with_options(ManagerOptions::from_env())
     }
 
     /// Create a new sync prompt manager with a specific vault path
@@ -24,11 +23,22 @@ impl SyncPromptManager {
         let vault = PromptVault::open(path)?;
         Ok(SyncPromptManager {
             vault: Arc::new(RwLock::new(vault)),
+            options: ManagerOptions::default(),
+        })
+    }
+
+    /// Create a new sync prompt manager with explicit options
+    pub fn with_options(options: ManagerOptions) -> Result<Self> {
+        let vault = options.open_vault()?;
+        Ok(SyncPromptManager {
+            vault: Arc::new(RwLock::new(vault)),
+            options,
         })
     }
 
     /// Add a prompt (creates if missing)
     pub fn add(&self, key: &str, content: &str) -> Result<()> {
+        self.options.check_writable()?;
         let vault = self.vault.write().unwrap();
         vault.add(key, content)?;
         Ok(())
@@ -36,29 +46,64 @@ impl SyncPromptManager {
 
     /// Update a prompt version
     pub fn update(&self, key: &str, content: &str, message: Option<&str>) -> Result<()> {
+        self.options.check_writable()?;
         let vault = self.vault.write().unwrap();
         vault.update(key, content, message.map(|s| s.to_string()))?;
         Ok(())
     }
 
+    /// Update a prompt only if `if_version`, when given, is still its latest version,
+    /// e.g. the version [`get_versioned`](Self::get_versioned) returned; fails with a
+    /// [`VersionConflict`](crate::VersionConflict) otherwise. Returns the new version.
+    pub fn update_if(
+        &self,
+        key: &str,
+        content: &str,
+        message: Option<&str>,
+        if_version: Option<u64>,
+    ) -> Result<u64> {
+        self.options.check_writable()?;
+        let vault = self.vault.write().unwrap();
+        vault.update_expecting(key, content, message.map(|s| s.to_string()), if_version)
+    }
+
     /// Tag a version (e.g. stable/release/dev)
     pub fn tag(&self, key: &str, tag: &str, version: u64) -> Result<()> {
+        self.options.check_writable()?;
         let vault = self.vault.write().unwrap();
         vault.tag(key, tag, version)?;
         Ok(())
     }
 
+    /// Remove a tag; returns the version it pointed to
+    pub fn untag(&self, key: &str, tag: &str) -> Result<u64> {
+        self.options.check_writable()?;
+        let vault = self.vault.write().unwrap();
+        vault.untag(key, tag)
+    }
+
     /// Retrieve a prompt by version/tag
     pub fn get_prompt(&self, key: &str, selector: VersionSelector) -> Result<String> {
         let vault = self.vault.read().unwrap();
         Ok(vault.get(key, selector)?)
     }
 
+    /// Retrieve a prompt with the version it resolved to
+    pub fn get_versioned(&self, key: &str, selector: VersionSelector) -> Result<VersionedPrompt> {
+        let vault = self.vault.read().unwrap();
+        vault.get_versioned(key, selector)
+    }
+
     /// Retrieve latest prompt
     pub fn latest(&self, key: &str) -> Result<String> {
         self.get_prompt(key, VersionSelector::Latest)
     }
 
+    /// Retrieve the prompt at the configured default tag (latest when none is set)
+    pub fn get_default(&self, key: &str) -> Result<String> {
+        self.get_prompt(key, self.options.default_selector())
+    }
+
     /// List history of versions
     pub fn history(&self, key: &str) -> Result<Vec<crate::types::VersionMeta>> {
         let vault = self.vault.read().unwrap();
@@ -74,40 +119,55 @@ impl SyncPromptManager {
 
     /// Delete a prompt key and all its versions
     pub fn delete_prompt(&self, key: &str) -> Result<()> {
+        self.options.check_writable()?;
         let vault = self.vault.write().unwrap();
         vault.delete_prompt_key(key)?;
         Ok(())
     }
 
-    /// Restore from backup
+    /// Restore from backup, merging the dump into the current vault key by key; keys
+    /// changed on both sides keep their local versions
     pub fn restore(&self, path: &str, password: Option<&str>) -> Result<()> {
-        // This is a bit more complex as we need to restore to the current vault
-        // For now, we'll just delegate to the static restore method and replace our vault
-        let restored_vault = PromptVault::restore(path, password)?;
-        
-        // Replace the current vault contents with the restored vault
-        {
-            let current_vault = self.vault.write().unwrap();
-            // Unfortunately we can't directly replace the contents of an existing vault,
-            // so we'd need to copy data between them. For now, this is a placeholder.
-            // In a real implementation, we might want to restructure this differently.
-        }
+        self.options.check_writable()?;
+        let vault = self.vault.write().unwrap();
+        vault.merge_dump(path, password)?;
         Ok(())
     }
 }
 
-/// Global static instance of the sync manager
-static mut GLOBAL_MANAGER: Option<SyncPromptManager> = None;
-static INIT: std::sync::Once = std::sync::Once::new();
+/// Global static instance of the sync manager, set on first successful access
+static GLOBAL_MANAGER: once_cell::sync::OnceCell<SyncPromptManager> =
+    once_cell::sync::OnceCell::new();
 
 impl SyncPromptManager {
-    /// Get a reference to the global singleton
+    /// Get a reference to the global singleton, configured like `DefaultPromptManager::try_get`.
+    ///
+    /// A failed initialization is returned as an error and retried on the next call.
+    pub fn try_get() -> Result<&'static Self, InitError> {
+        GLOBAL_MANAGER.get_or_try_init(|| {
+            Ok(SyncPromptManager::with_options(
+                ManagerOptions::for_singleton(),
+            )?)
+        })
+    }
+
+    /// Get a reference to the global singleton.
+    ///
+    /// Panics if the vault cannot be opened; use `try_get()` to handle that instead.
     pub fn get() -> &'static Self {
-        unsafe {
-            INIT.call_once(|| {
-                GLOBAL_MANAGER = Some(SyncPromptManager::new().expect("Failed to create PromptPro sync manager"));
-            });
-            GLOBAL_MANAGER.as_ref().unwrap()
+        match Self::try_get() {
+            Ok(manager) => manager,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Configure the singleton; must be called before the first `get()`
+    pub fn configure(options: ManagerOptions) -> Result<()> {
+        if GLOBAL_MANAGER.get().is_some() {
+            return Err(anyhow::anyhow!(
+                "SyncPromptManager is already initialized; call configure() before get()"
+            ));
         }
+        ManagerOptions::configure(options)
     }
 }
\ No newline at end of file
@@ -0,0 +1,193 @@
+//! Prompts written as Jinja2 or Handlebars template files, for `promptpro
+//! export-templates`, so apps in other languages can load them from a directory.
+//!
+//! Each file starts with a YAML frontmatter block naming the key, version, tags and
+//! variables of the prompt, followed by the template. Includes are resolved, as the
+//! template engines have no notion of pinned versions, and text the engine would take
+//! for its own syntax is escaped.
+
+use crate::render::template_variables;
+use crate::storage::PromptVault;
+use crate::types::{key_file_path, VersionSelector};
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// A `{{name}}` placeholder, or the start of any other tag of a template engine
+static TEMPLATE_TOKEN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_.-]*)\s*\}\}|\{\{|\{%|\{#").unwrap()
+});
+
+/// Template engine whose syntax exported templates use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateSyntax {
+    /// `{{ name }}`, in `.j2` files
+    Jinja2,
+    /// `{{{name}}}`, which Handlebars does not HTML-escape, in `.hbs` files
+    Handlebars,
+}
+
+impl TemplateSyntax {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TemplateSyntax::Jinja2 => "j2",
+            TemplateSyntax::Handlebars => "hbs",
+        }
+    }
+
+    /// `content` with its placeholders in this syntax and other tags escaped
+    pub fn convert(self, content: &str) -> String {
+        TEMPLATE_TOKEN
+            .replace_all(content, |captures: &Captures| {
+                match (self, captures.get(1)) {
+                    (TemplateSyntax::Jinja2, Some(name)) => format!("{{{{ {} }}}}", name.as_str()),
+                    (TemplateSyntax::Handlebars, Some(name)) => {
+                        format!("{{{{{{{}}}}}}}", name.as_str())
+                    }
+                    // Jinja prints a string literal; Handlebars only has `{{` tags
+                    (TemplateSyntax::Jinja2, None) => format!("{{{{ '{}' }}}}", &captures[0]),
+                    (TemplateSyntax::Handlebars, None) => match &captures[0] {
+                        "{{" => "\\{{".to_string(),
+                        other => other.to_string(),
+                    },
+                }
+            })
+            .into_owned()
+    }
+}
+
+impl std::str::FromStr for TemplateSyntax {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "jinja2" | "jinja" => Ok(TemplateSyntax::Jinja2),
+            "handlebars" | "hbs" => Ok(TemplateSyntax::Handlebars),
+            other => Err(anyhow::anyhow!(
+                "Unknown template syntax '{}', expected 'jinja2' or 'handlebars'",
+                other
+            )),
+        }
+    }
+}
+
+/// Frontmatter of a template file
+#[derive(Serialize)]
+struct Frontmatter<'a> {
+    key: &'a str,
+    version: u64,
+    tags: &'a [String],
+    variables: Vec<String>,
+}
+
+/// A template file: frontmatter between `---` lines, then the template
+fn template_file(
+    key: &str,
+    version: u64,
+    tags: &[String],
+    content: &str,
+    syntax: TemplateSyntax,
+) -> Result<String> {
+    let frontmatter = Frontmatter {
+        key,
+        version,
+        tags,
+        variables: template_variables(content),
+    };
+    Ok(format!(
+        "---\n{}---\n{}",
+        serde_yaml::to_string(&frontmatter)?,
+        syntax.convert(content)
+    ))
+}
+
+impl PromptVault {
+    /// Write the selected version of each prompt starting with `prefix` to
+    /// `dir/<key>.<ext>` as a template; returns the files written.
+    ///
+    /// Prompts the selector does not resolve for, and password-protected ones, are
+    /// skipped. Fails before writing anything when a key is no relative path, such as
+    /// `../writer`.
+    pub fn export_templates<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        syntax: TemplateSyntax,
+        selector: &VersionSelector,
+        prefix: &str,
+    ) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        let keys = self.list_keys_with_prefix(prefix)?;
+        let files = keys
+            .iter()
+            .map(|key| key_file_path(key, syntax.extension()))
+            .collect::<Result<Vec<_>>>()?;
+        let mut written = Vec::new();
+        for (key, file) in keys.into_iter().zip(files) {
+            if self.is_sensitive(&key)? {
+                continue;
+            }
+            let Ok(version) = self.resolve_version(&key, selector) else {
+                continue;
+            };
+            let tags = self
+                .get_version_meta(&key, version)?
+                .map(|meta| meta.tags)
+                .unwrap_or_default();
+            let content = self.get(&key, VersionSelector::Version(version))?;
+            let content = self.resolve_content(&key, version, &content)?;
+
+            let path = dir.join(file);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, template_file(&key, version, &tags, &content, syntax)?)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_export_templates() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        vault.add("preamble", "Be kind.")?;
+        vault.add("agents/writer", "{{> preamble}}\nWrite about {{ topic }} in {% raw %}.")?;
+        vault.tag("agents/writer", "stable", 1)?;
+        vault.add("draft", "Untagged {{idea}}.")?;
+
+        let out = dir.path().join("out");
+        let stable = VersionSelector::tag("stable");
+        let written = vault.export_templates(&out, TemplateSyntax::Jinja2, &stable, "")?;
+        assert_eq!(written, [out.join("agents/writer.j2")]);
+        assert_eq!(
+            std::fs::read_to_string(&written[0])?,
+            "---\nkey: agents/writer\nversion: 1\ntags:\n- stable\nvariables:\n- topic\n---\n\
+             Be kind.\nWrite about {{ topic }} in {{ '{%' }} raw %}."
+        );
+
+        let syntax = "handlebars".parse()?;
+        let written = vault.export_templates(&out, syntax, &VersionSelector::Latest, "d")?;
+        let template = std::fs::read_to_string(&written[0])?;
+        assert!(template.ends_with("---\nUntagged {{{idea}}}."));
+        assert_eq!(TemplateSyntax::Handlebars.convert("{{ 1 }}"), "\\{{ 1 }}");
+
+        // Keys are never written outside the directory
+        for key in ["../escape", "/tmp/escape", "agents/../../escape"] {
+            vault.add(key, "Out of reach.")?;
+            let error = vault.export_templates(&out, syntax, &VersionSelector::Latest, key);
+            assert!(error.unwrap_err().to_string().contains("cannot be written as a file"));
+            vault.delete_prompt_key(key)?;
+        }
+        assert!(!dir.path().join("escape.hbs").exists());
+
+        Ok(())
+    }
+}
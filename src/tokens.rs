@@ -0,0 +1,133 @@
+//! Counting the tokens of prompts, so versions outgrowing a model's context are noticed.
+//!
+//! Built with the `tiktoken` feature, counts are exact for the OpenAI models tiktoken
+//! knows; otherwise they are estimated at about four characters per token. `add`,
+//! `update` and the TUI warn about versions over the budget of the `[tokens]` table of
+//! `~/.promptpro/config.toml`:
+//!
+//! ```toml
+//! [tokens]
+//! model = "gpt-4o"   # the default
+//! budget = 4000
+//! ```
+
+use crate::profile::ProfileConfig;
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Model whose tokenizer counts when none is configured
+pub const DEFAULT_MODEL: &str = "gpt-4o";
+
+/// The `[tokens]` table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenConfig {
+    /// Model whose tokenizer counts, [`DEFAULT_MODEL`] when unset
+    pub model: Option<String>,
+    /// Warn about versions with more tokens than this
+    pub budget: Option<u64>,
+}
+
+impl TokenConfig {
+    /// The `[tokens]` table of the config file
+    pub fn load() -> Result<Self> {
+        Ok(ProfileConfig::load(&ProfileConfig::default_path()?)?.tokens)
+    }
+
+    pub fn model(&self) -> &str {
+        self.model.as_deref().unwrap_or(DEFAULT_MODEL)
+    }
+
+    /// A warning when `content` has more tokens than the budget
+    pub fn budget_warning(&self, content: &str) -> Result<Option<String>> {
+        let Some(budget) = self.budget else {
+            return Ok(None);
+        };
+        let count = count_tokens(content, self.model())?;
+        Ok((count.tokens > budget).then(|| format!("{}, over the budget of {}", count, budget)))
+    }
+}
+
+/// The number of tokens of a text for a model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenCount {
+    pub tokens: u64,
+    pub model: String,
+    /// Whether the model's tokenizer counted, rather than an estimate
+    pub exact: bool,
+}
+
+impl fmt::Display for TokenCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.exact {
+            f.write_str("about ")?;
+        }
+        write!(f, "{} tokens for {}", self.tokens, self.model)
+    }
+}
+
+/// Count the tokens of `content` with the tokenizer of `model`
+#[cfg(feature = "tiktoken")]
+pub fn count_tokens(content: &str, model: &str) -> Result<TokenCount> {
+    let bpe = tiktoken_rs::get_bpe_from_model(model)?;
+    Ok(TokenCount {
+        tokens: bpe.encode_ordinary(content).len() as u64,
+        model: model.to_string(),
+        exact: true,
+    })
+}
+
+/// Estimate the tokens of `content`; exact counts need the `tiktoken` feature
+#[cfg(not(feature = "tiktoken"))]
+pub fn count_tokens(content: &str, model: &str) -> Result<TokenCount> {
+    Ok(TokenCount {
+        tokens: crate::sort::estimate_tokens(content),
+        model: model.to_string(),
+        exact: false,
+    })
+}
+
+impl PromptVault {
+    /// Count the tokens of the selected version of `key` for `model`
+    pub fn token_count(
+        &self,
+        key: &str,
+        selector: VersionSelector,
+        model: &str,
+    ) -> Result<TokenCount> {
+        count_tokens(&self.get(key, selector)?, model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_token_count() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "You write short posts.")?;
+        vault.update("writer", &"You write long posts. ".repeat(50), None)?;
+
+        let short = vault.token_count("writer", VersionSelector::Version(1), "gpt-4o")?;
+        let long = vault.token_count("writer", VersionSelector::Latest, "gpt-4o")?;
+        assert!((4..=8).contains(&short.tokens), "{}", short);
+        assert!(long.tokens > 100, "{}", long);
+        assert_eq!(short.exact, cfg!(feature = "tiktoken"));
+
+        let config = TokenConfig {
+            model: None,
+            budget: Some(100),
+        };
+        assert_eq!(config.budget_warning("You write short posts.")?, None);
+        let warning = config.budget_warning(&"You write long posts. ".repeat(50))?;
+        assert!(warning.unwrap().ends_with("for gpt-4o, over the budget of 100"));
+
+        Ok(())
+    }
+}
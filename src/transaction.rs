@@ -0,0 +1,374 @@
+//! Atomic groups of writes, for [`PromptVault::transaction`].
+//!
+//! Writes made in a transaction are staged in memory, where the reads of the same
+//! transaction see them, and applied in one sled transaction when it commits, so a
+//! crash never leaves half of them behind. Entries read before being overwritten are
+//! checked again at commit: when another writer changed them in the meantime the
+//! transaction runs again, or fails with a [`VersionConflict`] when both stored the
+//! same version number. `add`, `update`, `tag`, `untag` and `delete_prompt_key` each
+//! run in a transaction of their own unless they are part of a larger one.
+
+use crate::events::VaultEvent;
+use crate::storage::{PromptVault, VersionConflict};
+use crate::types::{VersionMeta, VersionSelector};
+use anyhow::Result;
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::IVec;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Number of times a transaction runs while concurrent writes keep changing what it read
+pub(crate) const MAX_ATTEMPTS: usize = 16;
+
+/// Writes of a transaction not applied yet, and what it read before writing
+#[derive(Debug, Default)]
+pub(crate) struct Staged {
+    /// Value of each entry when the transaction first read it
+    reads: HashMap<Vec<u8>, Option<IVec>>,
+    /// New value of each written entry; `None` removes it
+    writes: BTreeMap<Vec<u8>, Option<IVec>>,
+    /// Entries read but not written that must still hold what was read at commit
+    checked: HashSet<Vec<u8>>,
+}
+
+/// Why a commit failed
+enum CommitError {
+    /// A version this transaction stored was stored by another writer first
+    Version { key: String, version: u64 },
+    /// Another entry this transaction read and wrote was changed by another writer
+    Changed,
+    Storage(sled::Error),
+}
+
+/// `version:{key}:{version}` as its key and version
+fn parse_version_entry(entry: &[u8]) -> Option<(String, u64)> {
+    let entry = std::str::from_utf8(entry.strip_prefix(b"version:")?).ok()?;
+    let (key, version) = entry.rsplit_once(':')?;
+    Some((key.to_string(), version.parse().ok()?))
+}
+
+impl Staged {
+    /// The changes these writes make once committed: new keys and versions first, then
+    /// tags, then deleted keys (whose tags are not reported as removed)
+    fn events(&self) -> Vec<VaultEvent> {
+        let mut versions = Vec::new();
+        let mut tags = Vec::new();
+        let mut deleted = Vec::new();
+        for (entry, value) in &self.writes {
+            let previous = self.reads.get(entry);
+            if let Some((key, version)) = parse_version_entry(entry) {
+                // Rewritten metadata of an existing version is no new version
+                if value.is_some() && matches!(previous, Some(None)) {
+                    versions.push(match version {
+                        1 => VaultEvent::PromptAdded { key },
+                        _ => VaultEvent::VersionCreated { key, version },
+                    });
+                }
+            } else if let Some(name) = entry.strip_prefix(b"tag:") {
+                let name = String::from_utf8_lossy(name);
+                let Some((key, tag)) = name.rsplit_once(':') else {
+                    continue;
+                };
+                let (key, tag) = (key.to_string(), tag.to_string());
+                match value.as_deref().and_then(|bytes| bytes.try_into().ok()) {
+                    Some(bytes) if previous != Some(value) => tags.push(VaultEvent::Tagged {
+                        key,
+                        tag,
+                        version: u64::from_le_bytes(bytes),
+                    }),
+                    Some(_) => {}
+                    None => tags.push(VaultEvent::Untagged { key, tag }),
+                }
+            } else if let (Some(key), None) = (entry.strip_prefix(b"keys:"), value) {
+                deleted.push(String::from_utf8_lossy(key).into_owned());
+            }
+        }
+        tags.retain(|event| !deleted.iter().any(|key| key == event.key()));
+        versions.extend(tags);
+        versions.extend(deleted.into_iter().map(|key| VaultEvent::Deleted { key }));
+        versions
+    }
+}
+
+/// The writes of one [`PromptVault::transaction`]; reads see the writes made so far
+pub struct Transaction<'a> {
+    vault: &'a PromptVault,
+}
+
+impl Transaction<'_> {
+    /// Add a new prompt, as [`PromptVault::add`]
+    pub fn add(&self, key: &str, content: &str) -> Result<()> {
+        self.vault.add(key, content)
+    }
+
+    /// Store a new version of a prompt, as [`PromptVault::update`]
+    pub fn update(&self, key: &str, content: &str, message: Option<String>) -> Result<()> {
+        self.vault.update(key, content, message)
+    }
+
+    /// Store a new version if `expected_parent` is still the latest, as
+    /// [`PromptVault::update_expecting`]; returns the new version number
+    pub fn update_expecting(
+        &self,
+        key: &str,
+        content: &str,
+        message: Option<String>,
+        expected_parent: Option<u64>,
+    ) -> Result<u64> {
+        self.vault
+            .update_expecting(key, content, message, expected_parent)
+    }
+
+    /// Tag a version, as [`PromptVault::tag`]
+    pub fn tag(&self, key: &str, tag: &str, version: u64) -> Result<()> {
+        self.vault.tag(key, tag, version)
+    }
+
+    /// Remove a tag, as [`PromptVault::untag`]; returns the version it pointed to
+    pub fn untag(&self, key: &str, tag: &str) -> Result<u64> {
+        self.vault.untag(key, tag)
+    }
+
+    /// Read a prompt, including versions stored earlier in the transaction
+    pub fn get(&self, key: &str, selector: VersionSelector) -> Result<String> {
+        self.vault.get(key, selector)
+    }
+
+    pub fn get_latest_version_number(&self, key: &str) -> Result<Option<u64>> {
+        self.vault.get_latest_version_number(key)
+    }
+
+    pub fn history(&self, key: &str) -> Result<Vec<VersionMeta>> {
+        self.vault.history(key)
+    }
+}
+
+impl PromptVault {
+    /// Run `f` and apply all of its writes at once, or none of them when it fails,
+    /// e.g. to add a prompt and tag it for production in one step. Returns what `f`
+    /// returns.
+    ///
+    /// `f` may run more than once when concurrent writers change the entries it read,
+    /// and fails with a [`VersionConflict`] when another writer stored a version it
+    /// stored. See the [module docs](crate::transaction).
+    pub fn transaction<T>(&self, f: impl Fn(&Transaction) -> Result<T>) -> Result<T> {
+        self.atomically(|vault| f(&Transaction { vault }))
+    }
+
+    /// Run `op` on a vault staging its writes and commit them together; runs `op`
+    /// directly when this vault is already part of a transaction
+    pub(crate) fn atomically<T>(&self, op: impl Fn(&PromptVault) -> Result<T>) -> Result<T> {
+        if self.staged().is_some() {
+            return op(self);
+        }
+        for _ in 0..MAX_ATTEMPTS {
+            let staging = self.staging();
+            let value = op(&staging)?;
+            let staged = std::mem::take(&mut *staging.staged().unwrap().lock().unwrap());
+            match self.commit(&staged) {
+                Ok(()) => {
+                    self.record_committed(&staged);
+                    self.event_bus().emit(&staged.events());
+                    return Ok(value);
+                }
+                Err(CommitError::Changed) => continue,
+                Err(CommitError::Version { key, version }) => {
+                    let latest = self.get_latest_version_number(&key)?;
+                    return Err(VersionConflict {
+                        key,
+                        parent: version.saturating_sub(1),
+                        latest: latest.unwrap_or(version),
+                    }
+                    .into());
+                }
+                Err(CommitError::Storage(e)) => return Err(e.into()),
+            }
+        }
+        Err(anyhow::anyhow!(
+            "Gave up after {} attempts: concurrent writes kept changing the same entries",
+            MAX_ATTEMPTS
+        ))
+    }
+
+    /// Apply the staged writes in one sled transaction, unless what they were based on
+    /// changed since it was read
+    fn commit(&self, staged: &Staged) -> Result<(), CommitError> {
+        if staged.writes.is_empty() {
+            return Ok(());
+        }
+        let mut checks: Vec<(&Vec<u8>, &Option<IVec>)> = staged
+            .reads
+            .iter()
+            .filter(|(entry, _)| {
+                staged.writes.contains_key(*entry) || staged.checked.contains(*entry)
+            })
+            .collect();
+        // Versions first, so losing a race for a version number is reported as such
+        checks.sort_by_key(|(entry, _)| !entry.starts_with(b"version:"));
+
+        let result = self.db().transaction(|tx| {
+            for (entry, expected) in &checks {
+                if tx.get(entry)? == **expected {
+                    continue;
+                }
+                let error = match (expected, parse_version_entry(entry)) {
+                    (None, Some((key, version))) => CommitError::Version { key, version },
+                    _ => CommitError::Changed,
+                };
+                return Err(ConflictableTransactionError::Abort(error));
+            }
+            for (entry, value) in &staged.writes {
+                match value {
+                    Some(value) => tx.insert(entry.as_slice(), value.clone())?,
+                    None => tx.remove(entry.as_slice())?,
+                };
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => Ok(()),
+            Err(TransactionError::Abort(error)) => Err(error),
+            Err(TransactionError::Storage(e)) => Err(CommitError::Storage(e)),
+        }
+    }
+
+    /// Bring the preloaded index up to date with committed versions and tags
+    fn record_committed(&self, staged: &Staged) {
+        for (entry, value) in &staged.writes {
+            if let (Some((key, version)), Some(_)) = (parse_version_entry(entry), value) {
+                self.index_cache().record_version(&key, version);
+                continue;
+            }
+            let Some(name) = entry.strip_prefix(b"tag:") else {
+                continue;
+            };
+            let name = String::from_utf8_lossy(name);
+            let Some((key, tag)) = name.rsplit_once(':') else {
+                continue;
+            };
+            match value.as_deref().and_then(|bytes| bytes.try_into().ok()) {
+                Some(bytes) => self.index_cache().record_tag(key, tag, u64::from_le_bytes(bytes)),
+                None => self.index_cache().forget_tag(key, tag),
+            }
+        }
+    }
+
+    /// Read an entry, as staged by the transaction when it wrote it
+    pub(crate) fn read_entry(&self, entry: &[u8]) -> Result<Option<IVec>> {
+        let Some(staged) = self.staged() else {
+            return Ok(self.db().get(entry)?);
+        };
+        let mut staged = staged.lock().unwrap();
+        if let Some(value) = staged.writes.get(entry) {
+            return Ok(value.clone());
+        }
+        let value = self.db().get(entry)?;
+        staged
+            .reads
+            .entry(entry.to_vec())
+            .or_insert_with(|| value.clone());
+        Ok(value)
+    }
+
+    /// Read an entry the transaction depends on without writing it: the transaction runs
+    /// again when another writer changes it before the commit
+    pub(crate) fn read_checked_entry(&self, entry: &[u8]) -> Result<Option<IVec>> {
+        let value = self.read_entry(entry)?;
+        if let Some(staged) = self.staged() {
+            staged.lock().unwrap().checked.insert(entry.to_vec());
+        }
+        Ok(value)
+    }
+
+    /// Write an entry, or stage it when part of a transaction
+    pub(crate) fn write_entry(&self, entry: &[u8], value: impl Into<IVec>) -> Result<()> {
+        match self.staged() {
+            Some(staged) => {
+                let mut staged = staged.lock().unwrap();
+                staged.writes.insert(entry.to_vec(), Some(value.into()));
+            }
+            None => {
+                self.db().insert(entry, value.into())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove an entry, or stage its removal when part of a transaction
+    pub(crate) fn remove_entry(&self, entry: &[u8]) -> Result<()> {
+        match self.staged() {
+            Some(staged) => {
+                staged.lock().unwrap().writes.insert(entry.to_vec(), None);
+            }
+            None => {
+                self.db().remove(entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Entries starting with `prefix` in order, including staged writes
+    pub(crate) fn scan_entries(&self, prefix: &[u8]) -> Result<Vec<(IVec, IVec)>> {
+        let mut entries = BTreeMap::new();
+        for result in self.db().scan_prefix(prefix) {
+            let (entry, value) = result?;
+            entries.insert(entry.to_vec(), value);
+        }
+        if let Some(staged) = self.staged() {
+            let staged = staged.lock().unwrap();
+            let writes = staged.writes.range(prefix.to_vec()..);
+            for (entry, value) in writes.take_while(|(entry, _)| entry.starts_with(prefix)) {
+                match value {
+                    Some(value) => entries.insert(entry.clone(), value.clone()),
+                    None => entries.remove(entry),
+                };
+            }
+        }
+        Ok(entries
+            .into_iter()
+            .map(|(entry, value)| (IVec::from(entry), value))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_transaction() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "v1")?;
+
+        // Reads inside see earlier writes; nothing is visible outside until commit
+        let version = vault.transaction(|tx| {
+            tx.add("planner", "Plan.")?;
+            tx.tag("planner", "prod", 1)?;
+            assert!(vault.get_latest_version_number("planner")?.is_none());
+            let version = tx.update_expecting("writer", "v2", None, Some(1))?;
+            tx.tag("writer", "prod", version)?;
+            assert_eq!(tx.get("writer", VersionSelector::tag("prod"))?, "v2");
+            Ok(version)
+        })?;
+        assert_eq!(version, 2);
+        assert_eq!(vault.get("planner", VersionSelector::tag("prod"))?, "Plan.");
+        assert_eq!(vault.history("writer")?[1].tags, ["dev", "prod"]);
+        assert_eq!(vault.list_keys()?, ["planner", "writer"]);
+
+        // A failure anywhere leaves the vault as it was
+        let failed = vault.transaction(|tx| {
+            tx.update("writer", "v3", None)?;
+            tx.untag("writer", "prod")?;
+            tx.add("reviewer", "Review.")?;
+            tx.tag("writer", "stable", 7)
+        });
+        assert!(failed.is_err());
+        assert_eq!(vault.get("writer", VersionSelector::Latest)?, "v2");
+        assert_eq!(vault.get("writer", VersionSelector::tag("prod"))?, "v2");
+        assert_eq!(vault.list_keys()?, ["planner", "writer"]);
+        assert!(vault.verify(false)?.is_clean());
+
+        Ok(())
+    }
+}
@@ -1,4 +1,12 @@
-use crate::storage::PromptVault;
+use crate::chat::{ChatPrompt, Role};
+use crate::comments::{comment_threads, Comment};
+use crate::diff::{line_hunks, ChangeKind, DiffHunk};
+use crate::namespace::{namespaces_of, NAMESPACE_SEPARATOR};
+use crate::protect::UnlockedKeys;
+use crate::search::{SearchHit, SearchOptions, SearchQuery};
+use crate::sort::KeyOrder;
+use crate::storage::{PromptVault, VersionConflict};
+use crate::tokens::TokenConfig;
 use crate::types::{VersionMeta, VersionSelector};
 use anyhow::Result;
 use crossterm::{
@@ -11,14 +19,38 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Terminal,
 };
+use std::collections::BTreeSet;
 use std::io;
 use std::thread;
 use std::time::Duration;
 use unicode_width::UnicodeWidthStr;
 
+/// Environment variable that opens the TUI read-only when set to `1` or `true`
+pub const READ_ONLY_ENV: &str = "PROMPTPRO_TUI_READ_ONLY";
+
+/// Options for launching the TUI
+#[derive(Debug, Clone, Default)]
+pub struct TuiOptions {
+    /// Sensitive prompts shown instead of redacted
+    pub unlocked: UnlockedKeys,
+    /// Disable every keybinding that modifies the vault
+    pub read_only: bool,
+    /// Token budget saved versions are checked against
+    pub tokens: TokenConfig,
+}
+
+impl TuiOptions {
+    /// Whether `READ_ONLY_ENV` asks for a read-only TUI
+    pub fn read_only_from_env() -> bool {
+        std::env::var(READ_ONLY_ENV)
+            .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+            .unwrap_or(false)
+    }
+}
+
 #[derive(Clone)]
 pub struct App {
     vault: PromptVault,
@@ -31,12 +63,67 @@ pub struct App {
     mode: Mode,
     message: String,
     active_panel: Panel,
-    show_tag_popup: bool,
     selected_tag: Option<String>,
     show_delete_confirmation: bool,
     show_add_prompt_dialog: bool,
     new_prompt_key_input: String,
+    show_new_tag_dialog: bool,
+    new_tag_input: String,
     input_cursor_pos: usize,
+    unlocked: UnlockedKeys,
+    read_only: bool,
+    tokens: TokenConfig,
+    show_search_input: bool,
+    search_query: String,
+    collapsed_namespaces: BTreeSet<String>,
+    show_global_search: bool,
+    global_query: String,
+    global_hits: Vec<SearchHit>,
+    selected_hit_index: usize,
+    /// Annotate each content line with the version that introduced it
+    show_blame: bool,
+    /// Order of the Keys panel; only the name order shows namespaces as a tree
+    key_order: KeyOrder,
+    /// Latest version of the key when the content being edited was loaded
+    edit_base: Option<u64>,
+    /// An edit waiting for the user to decide how to save it
+    save_conflict: Option<SaveConflict>,
+    /// Comments on the selected version, while their popup is open
+    comments_popup: Option<CommentsPopup>,
+}
+
+/// The comments on a version, shown in a popup
+#[derive(Clone)]
+struct CommentsPopup {
+    key: String,
+    version: u64,
+    comments: Vec<Comment>,
+    /// Index of the selected comment in thread order
+    selected: usize,
+    /// Comment being written: the comment it replies to, and its text so far
+    draft: Option<(Option<u64>, String)>,
+}
+
+impl CommentsPopup {
+    /// The selected comment
+    fn selected_comment(&self) -> Option<&Comment> {
+        comment_threads(&self.comments)
+            .get(self.selected)
+            .map(|(_, comment)| *comment)
+    }
+}
+
+/// An edit that was not saved because another version was stored since its content was
+/// loaded
+#[derive(Clone)]
+struct SaveConflict {
+    key: String,
+    content: String,
+    message: &'static str,
+    /// Latest version when the edit was saved
+    latest: u64,
+    /// Changes from the latest version to the edit, once the user asked to see them
+    diff: Option<Vec<DiffHunk>>,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -47,6 +134,122 @@ enum Panel {
     Tags,
 }
 
+/// A row of the Keys panel, which shows namespaced keys as a tree
+struct KeyRow {
+    depth: usize,
+    label: String,
+    /// Index in `App::keys` of the key, or of the first key inside a namespace
+    key_index: usize,
+    kind: KeyRowKind,
+}
+
+enum KeyRowKind {
+    Key,
+    Namespace {
+        path: String,
+        collapsed: bool,
+        /// Number of keys inside the namespace
+        count: usize,
+    },
+}
+
+impl KeyRow {
+    /// Keys and collapsed namespaces can be selected; expanded namespaces are headers
+    fn is_selectable(&self) -> bool {
+        !matches!(self.kind, KeyRowKind::Namespace { collapsed: false, .. })
+    }
+
+    /// Whether the row shows the key at `index`
+    fn contains(&self, index: usize) -> bool {
+        match self.kind {
+            KeyRowKind::Key => self.key_index == index,
+            KeyRowKind::Namespace { count, .. } => {
+                (self.key_index..self.key_index + count).contains(&index)
+            }
+        }
+    }
+}
+
+/// Lay out sorted keys as a tree of namespaces, hiding the keys of collapsed ones.
+/// Keys in another order than by name are listed flat with their full path.
+fn key_rows(keys: &[String], collapsed: &BTreeSet<String>, tree: bool) -> Vec<KeyRow> {
+    if !tree {
+        return keys
+            .iter()
+            .enumerate()
+            .map(|(index, key)| KeyRow {
+                depth: 0,
+                label: key.clone(),
+                key_index: index,
+                kind: KeyRowKind::Key,
+            })
+            .collect();
+    }
+
+    let last_segment = |path: &str| {
+        path.rsplit(NAMESPACE_SEPARATOR)
+            .next()
+            .unwrap_or(path)
+            .to_string()
+    };
+
+    let mut rows = Vec::new();
+    // Namespaces whose headers are shown above the current key, outermost first
+    let mut open: Vec<&str> = Vec::new();
+    let mut index = 0;
+
+    while index < keys.len() {
+        let key = &keys[index];
+        let namespaces: Vec<&str> = namespaces_of(key).collect();
+        let common = open
+            .iter()
+            .zip(&namespaces)
+            .take_while(|(a, b)| a == b)
+            .count();
+        open.truncate(common);
+
+        let mut hidden = None;
+        for &namespace in &namespaces[common..] {
+            // Sorted keys of a namespace are contiguous
+            let count = keys[index..]
+                .iter()
+                .take_while(|k| namespaces_of(k).any(|n| n == namespace))
+                .count();
+            let is_collapsed = collapsed.contains(namespace);
+            rows.push(KeyRow {
+                depth: open.len(),
+                label: last_segment(namespace),
+                key_index: index,
+                kind: KeyRowKind::Namespace {
+                    path: namespace.to_string(),
+                    collapsed: is_collapsed,
+                    count,
+                },
+            });
+            if is_collapsed {
+                hidden = Some(count);
+                break;
+            }
+            open.push(namespace);
+        }
+
+        match hidden {
+            Some(count) => index += count,
+            None => {
+                rows.push(KeyRow {
+                    depth: open.len(),
+                    label: last_segment(key),
+                    key_index: index,
+                    kind: KeyRowKind::Key,
+                });
+                index += 1;
+            }
+        }
+    }
+
+    rows
+}
+
 #[derive(Clone)]
 enum Mode {
     Normal,
@@ -54,8 +257,7 @@ enum Mode {
 }
 
 impl App {
-    fn new() -> Result<Self> {
-        let vault = PromptVault::open_default()?;
+    fn new(vault: PromptVault, options: TuiOptions) -> Result<Self> {
         let keys = get_all_keys(&vault)?;
         let mut versions = Vec::new();
         let mut content = String::new();
@@ -63,7 +265,8 @@ impl App {
         if let Some(first_key) = keys.first() {
             versions = vault.history(first_key)?;
             if let Some(latest_version) = versions.last() {
-                content = vault.get(first_key, VersionSelector::Version(latest_version.version))?;
+                content =
+                    vault.display_content(first_key, latest_version.version, &options.unlocked)?;
             }
         }
 
@@ -78,27 +281,41 @@ impl App {
             mode: Mode::Normal,
             message: String::new(),
             active_panel: Panel::Keys,
-            show_tag_popup: false,
             selected_tag: None,
             show_delete_confirmation: false,
             show_add_prompt_dialog: false,
             new_prompt_key_input: String::new(),
+            show_new_tag_dialog: false,
+            new_tag_input: String::new(),
             input_cursor_pos: 0,
+            unlocked: options.unlocked,
+            read_only: options.read_only,
+            tokens: options.tokens,
+            show_search_input: false,
+            search_query: String::new(),
+            collapsed_namespaces: BTreeSet::new(),
+            show_global_search: false,
+            global_query: String::new(),
+            global_hits: Vec::new(),
+            selected_hit_index: 0,
+            show_blame: false,
+            key_order: KeyOrder::Name,
+            edit_base: None,
+            save_conflict: None,
+            comments_popup: None,
         })
     }
 
-    fn new_with_key(key: String) -> Result<Self> {
-        let vault = PromptVault::open_default()?;
+    fn new_with_key(vault: PromptVault, key: String, options: TuiOptions) -> Result<Self> {
         let keys = get_all_keys(&vault)?;
-        let mut versions = Vec::new();
         let mut content = String::new();
 
         // Set the selected key to the provided key
         let selected_key_index = keys.iter().position(|k| k == &key).unwrap_or(0);
 
-        versions = vault.history(&key)?;
+        let versions = vault.history(&key)?;
         if let Some(latest_version) = versions.last() {
-            content = vault.get(&key, VersionSelector::Version(latest_version.version))?;
+            content = vault.display_content(&key, latest_version.version, &options.unlocked)?;
         }
 
         Ok(App {
@@ -112,17 +329,429 @@ impl App {
             mode: Mode::Normal,
             message: String::new(),
             active_panel: Panel::Keys,
-            show_tag_popup: false,
             selected_tag: None,
             show_delete_confirmation: false,
             show_add_prompt_dialog: false,
             new_prompt_key_input: String::new(),
+            show_new_tag_dialog: false,
+            new_tag_input: String::new(),
             input_cursor_pos: 0,
+            unlocked: options.unlocked,
+            read_only: options.read_only,
+            tokens: options.tokens,
+            show_search_input: false,
+            search_query: String::new(),
+            collapsed_namespaces: BTreeSet::new(),
+            show_global_search: false,
+            global_query: String::new(),
+            global_hits: Vec::new(),
+            selected_hit_index: 0,
+            show_blame: false,
+            key_order: KeyOrder::Name,
+            edit_base: None,
+            save_conflict: None,
+            comments_popup: None,
         })
     }
 
+    /// Whether the selected key is sensitive and has not been unlocked
+    fn selected_key_is_locked(&self) -> bool {
+        self.keys
+            .get(self.selected_key_index)
+            .map(|key| {
+                !self.unlocked.is_unlocked(key) && self.vault.is_sensitive(key).unwrap_or(false)
+            })
+            .unwrap_or(false)
+    }
+
+    /// Store a new version, encrypting it again if the key is an unlocked sensitive prompt.
+    /// Fails with a [`VersionConflict`] when `expected_parent` is no longer the latest.
+    fn update_prompt(
+        &self,
+        key: &str,
+        content: &str,
+        message: &str,
+        expected_parent: Option<u64>,
+    ) -> Result<()> {
+        let message = Some(message.to_string());
+        match self.unlocked.passphrase(key) {
+            Some(passphrase) => {
+                if let Some(latest) = self.vault.get_latest_version_number(key)? {
+                    self.vault.check_parent(key, expected_parent, latest)?;
+                }
+                self.vault
+                    .update_protected(key, content, message, passphrase)
+            }
+            None => self
+                .vault
+                .update_expecting(key, content, message, expected_parent)
+                .map(|_| ()),
+        }
+    }
+
+    /// Save an edit of `key` made against version `base`, asking the user what to do
+    /// when another version was stored since
+    fn save_edit(
+        &mut self,
+        key: String,
+        content: String,
+        message: &'static str,
+        base: Option<u64>,
+    ) -> Result<()> {
+        match self.update_prompt(&key, &content, message, base) {
+            Ok(()) => {
+                self.message = match self.tokens.budget_warning(&content) {
+                    Ok(Some(warning)) => {
+                        format!("Saved changes to '{}', which has {}", key, warning)
+                    }
+                    _ => format!("Saved changes to '{}'", key),
+                };
+                self.refresh_versions()?;
+            }
+            Err(e) => match e.downcast_ref::<VersionConflict>() {
+                Some(conflict) => {
+                    self.message = format!(
+                        "'{}' changed since it was loaded: v{} is the latest version",
+                        key, conflict.latest
+                    );
+                    self.save_conflict = Some(SaveConflict {
+                        latest: conflict.latest,
+                        key,
+                        content,
+                        message,
+                        diff: None,
+                    });
+                }
+                None => self.message = format!("Error saving: {}", e),
+            },
+        }
+        Ok(())
+    }
+
+    /// Store the conflicting edit as a new version on top of the latest one
+    fn overwrite_conflict(&mut self) -> Result<()> {
+        if let Some(conflict) = self.save_conflict.take() {
+            let latest = Some(conflict.latest);
+            self.save_edit(conflict.key, conflict.content, conflict.message, latest)?;
+        }
+        Ok(())
+    }
+
+    /// Show or hide the changes from the latest version to the conflicting edit
+    fn toggle_conflict_diff(&mut self) -> Result<()> {
+        if let Some(conflict) = &mut self.save_conflict {
+            conflict.diff = match conflict.diff {
+                Some(_) => None,
+                None => {
+                    let latest =
+                        self.vault
+                            .display_content(&conflict.key, conflict.latest, &self.unlocked)?;
+                    Some(line_hunks(&latest, &conflict.content))
+                }
+            };
+        }
+        Ok(())
+    }
+
+    /// Keep the conflicting edit in the editor without saving it
+    fn cancel_conflict(&mut self) {
+        if let Some(conflict) = self.save_conflict.take() {
+            self.edit_content = conflict.content;
+            self.mode = Mode::Editing;
+            self.message = "Save cancelled; Esc discards the edit".to_string();
+        }
+    }
+
+    /// Whether a comment is being written in the comments popup
+    fn is_writing_comment(&self) -> bool {
+        self.comments_popup.as_ref().is_some_and(|popup| popup.draft.is_some())
+    }
+
+    /// Open the comments popup of the selected version
+    fn open_comments(&mut self) -> Result<()> {
+        let (Some(key), Some(version)) = (
+            self.keys.get(self.selected_key_index).cloned(),
+            self.versions.get(self.selected_version_index).map(|v| v.version),
+        ) else {
+            return Ok(());
+        };
+        let comments = self.vault.comments(&key, version)?;
+        self.message = format!("{} comment(s) on '{}' v{}", comments.len(), key, version);
+        self.comments_popup = Some(CommentsPopup {
+            key,
+            version,
+            comments,
+            selected: 0,
+            draft: None,
+        });
+        Ok(())
+    }
+
+    fn move_comment_selection(&mut self, forward: bool) {
+        if let Some(popup) = &mut self.comments_popup {
+            if popup.comments.is_empty() {
+                return;
+            }
+            popup.selected = if forward {
+                (popup.selected + 1) % popup.comments.len()
+            } else {
+                popup.selected.saturating_sub(1)
+            };
+        }
+    }
+
+    /// Start writing a comment on the version, or a reply to the selected comment
+    fn start_comment(&mut self, reply: bool) {
+        if self.read_only {
+            self.message = "Read-only mode: changes are disabled".to_string();
+            return;
+        }
+        let Some(popup) = &mut self.comments_popup else {
+            return;
+        };
+        let reply_to = match popup.selected_comment() {
+            Some(comment) if reply => Some(comment.id),
+            None if reply => {
+                self.message = "No comment to reply to".to_string();
+                return;
+            }
+            _ => None,
+        };
+        popup.draft = Some((reply_to, String::new()));
+        self.message = match reply_to {
+            Some(id) => format!("Replying to comment #{}", id),
+            None => format!("Commenting on v{}", popup.version),
+        };
+    }
+
+    /// Store the comment being written, as the author the TUI was started with
+    fn post_comment(&mut self) -> Result<()> {
+        let Some(popup) = &mut self.comments_popup else {
+            return Ok(());
+        };
+        let Some((reply_to, text)) = popup.draft.clone() else {
+            return Ok(());
+        };
+        let Some(author) = self.vault.author().map(str::to_string) else {
+            self.message =
+                "Set your name with `promptpro config set user.name NAME` to comment".to_string();
+            return Ok(());
+        };
+        let result = match reply_to {
+            Some(id) => self.vault.reply(&popup.key, id, &author, &text),
+            None => self.vault.comment(&popup.key, popup.version, &author, &text),
+        };
+        match result {
+            Ok(comment) => {
+                popup.comments = self.vault.comments(&popup.key, popup.version)?;
+                popup.selected = comment_threads(&popup.comments)
+                    .iter()
+                    .position(|(_, c)| c.id == comment.id)
+                    .unwrap_or(0);
+                popup.draft = None;
+                self.message = format!("Posted comment #{}", comment.id);
+            }
+            Err(e) => self.message = format!("Error: {}", e),
+        }
+        Ok(())
+    }
+
     fn refresh_keys(&mut self) -> Result<()> {
-        self.keys = get_all_keys(&self.vault)?;
+        self.keys = if self.search_query.is_empty() {
+            get_all_keys(&self.vault)?
+        } else {
+            search_keys(&self.vault, &self.search_query)?
+        };
+        // Search results stay ranked by relevance unless another order was picked
+        if self.key_order != KeyOrder::Name || self.search_query.is_empty() {
+            self.vault.sort_keys(&mut self.keys, self.key_order)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the Keys panel shows namespaces as a tree
+    fn shows_key_tree(&self) -> bool {
+        self.key_order == KeyOrder::Name
+    }
+
+    /// Switch the Keys panel to the next order, keeping the selected key
+    fn cycle_key_order(&mut self) -> Result<()> {
+        let selected = self.keys.get(self.selected_key_index).cloned();
+        self.key_order = self.key_order.next();
+        self.refresh_keys()?;
+        self.selected_key_index = selected
+            .and_then(|key| self.keys.iter().position(|k| *k == key))
+            .unwrap_or(0);
+        if let Some(key) = self.keys.get(self.selected_key_index) {
+            for namespace in namespaces_of(key) {
+                self.collapsed_namespaces.remove(namespace);
+            }
+        }
+        self.message = format!("Keys sorted by {}", self.key_order);
+        Ok(())
+    }
+
+    /// Move to the next (or previous) key or collapsed namespace in the Keys panel
+    fn move_key_selection(&mut self, forward: bool) -> Result<()> {
+        let tree = self.shows_key_tree();
+        let rows: Vec<KeyRow> = key_rows(&self.keys, &self.collapsed_namespaces, tree)
+            .into_iter()
+            .filter(KeyRow::is_selectable)
+            .collect();
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let current = rows
+            .iter()
+            .position(|row| row.contains(self.selected_key_index))
+            .unwrap_or(0);
+        let next = if forward {
+            (current + 1) % rows.len()
+        } else {
+            current.saturating_sub(1)
+        };
+        self.selected_key_index = rows[next].key_index;
+        self.refresh_versions()
+    }
+
+    /// Expand the selected collapsed namespace, or collapse the selected key's namespace
+    fn toggle_namespace(&mut self) {
+        if !self.shows_key_tree() {
+            self.message = "Namespaces fold when keys are sorted by name".to_string();
+            return;
+        }
+        let rows = key_rows(&self.keys, &self.collapsed_namespaces, true);
+        let selected = rows
+            .iter()
+            .find(|row| row.is_selectable() && row.contains(self.selected_key_index));
+
+        match selected.map(|row| &row.kind) {
+            Some(KeyRowKind::Namespace { path, .. }) => {
+                self.collapsed_namespaces.remove(path);
+            }
+            Some(KeyRowKind::Key) => {
+                let key = &self.keys[self.selected_key_index];
+                match namespaces_of(key).last() {
+                    Some(namespace) => {
+                        self.collapsed_namespaces.insert(namespace.to_string());
+                    }
+                    None => self.message = format!("'{}' is not in a namespace", key),
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn start_search(&mut self) {
+        self.show_search_input = true;
+        self.message = "Type to filter keys, Enter to keep the filter, Esc to clear".to_string();
+    }
+
+    /// Re-filter the Keys panel after the search query changed
+    fn apply_search(&mut self) -> Result<()> {
+        self.refresh_keys()?;
+        self.selected_key_index = 0;
+        if self.keys.is_empty() {
+            self.versions.clear();
+            self.selected_version_index = 0;
+            self.content.clear();
+        } else {
+            self.refresh_versions()?;
+        }
+        Ok(())
+    }
+
+    fn finish_search(&mut self) {
+        self.show_search_input = false;
+        self.message = if self.search_query.is_empty() {
+            String::new()
+        } else {
+            format!("{} key(s) match '{}'", self.keys.len(), self.search_query)
+        };
+    }
+
+    fn clear_search(&mut self) -> Result<()> {
+        self.show_search_input = false;
+        self.search_query.clear();
+        self.message = "Search cleared".to_string();
+        self.apply_search()
+    }
+
+    fn start_global_search(&mut self) {
+        self.show_global_search = true;
+        self.global_query.clear();
+        self.global_hits.clear();
+        self.selected_hit_index = 0;
+        self.message = "Search all prompts and versions".to_string();
+    }
+
+    /// Re-run the global search after its query changed
+    fn update_global_search(&mut self) -> Result<()> {
+        self.selected_hit_index = 0;
+        // Queries are incomplete while typed, e.g. `tag:` or an open quote
+        let query = match self.global_query.parse::<SearchQuery>() {
+            Ok(query) if !self.global_query.trim().is_empty() => query,
+            _ => {
+                self.global_hits.clear();
+                return Ok(());
+            }
+        };
+        let options = SearchOptions {
+            all_versions: true,
+            ..Default::default()
+        };
+        self.global_hits = self.vault.search_query(&query, &options)?;
+        Ok(())
+    }
+
+    fn move_hit_selection(&mut self, forward: bool) {
+        if self.global_hits.is_empty() {
+            return;
+        }
+        self.selected_hit_index = if forward {
+            (self.selected_hit_index + 1) % self.global_hits.len()
+        } else {
+            self.selected_hit_index.saturating_sub(1)
+        };
+    }
+
+    fn close_global_search(&mut self) {
+        self.show_global_search = false;
+        self.global_query.clear();
+        self.global_hits.clear();
+    }
+
+    /// Select the key and version of the chosen global search result
+    fn jump_to_hit(&mut self) -> Result<()> {
+        let Some(hit) = self.global_hits.get(self.selected_hit_index).cloned() else {
+            return Ok(());
+        };
+        self.close_global_search();
+
+        // The Keys panel filter may hide the key
+        if !self.keys.contains(&hit.key) {
+            self.search_query.clear();
+            self.refresh_keys()?;
+        }
+        let Some(index) = self.keys.iter().position(|k| k == &hit.key) else {
+            self.message = format!("'{}' no longer exists", hit.key);
+            return Ok(());
+        };
+        for namespace in namespaces_of(&hit.key) {
+            self.collapsed_namespaces.remove(namespace);
+        }
+
+        self.selected_key_index = index;
+        self.refresh_versions()?;
+        if let Some(version_index) = self.versions.iter().position(|v| v.version == hit.version) {
+            self.selected_version_index = version_index;
+            self.content = self
+                .vault
+                .display_content(&hit.key, hit.version, &self.unlocked)?;
+        }
+        self.active_panel = Panel::Versions;
+        self.message = format!("Jumped to '{}' v{}", hit.key, hit.version);
         Ok(())
     }
 
@@ -134,9 +763,9 @@ impl App {
                 self.selected_version_index = self.versions.len().saturating_sub(1);
 
                 if let Some(version) = self.versions.get(self.selected_version_index) {
-                    self.content = self
-                        .vault
-                        .get(key, VersionSelector::Version(version.version))?;
+                    self.content =
+                        self.vault
+                            .display_content(key, version.version, &self.unlocked)?;
                 }
             } else {
                 self.selected_version_index = 0;
@@ -146,41 +775,135 @@ impl App {
         Ok(())
     }
 
+    /// Edit the shown content, based on the latest version loaded
+    fn start_editing(&mut self) {
+        self.edit_content = self.content.clone();
+        self.edit_base = self.versions.last().map(|version| version.version);
+        self.mode = Mode::Editing;
+    }
+
     fn save_content(&mut self) -> Result<()> {
-        if let Some(key) = self.keys.get(self.selected_key_index) {
-            match self
-                .vault
-                .update(key, &self.edit_content, Some("Updated via TUI".to_string()))
-            {
-                Ok(_) => {
-                    self.message = format!("Saved changes to '{}'", key);
-                    self.refresh_versions()?;
-                }
-                Err(e) => {
-                    self.message = format!("Error saving: {}", e);
-                }
+        if let Some(key) = self.keys.get(self.selected_key_index).cloned() {
+            let content = self.edit_content.clone();
+            self.save_edit(key, content, "Updated via TUI", self.edit_base)?;
+        }
+        self.mode = Mode::Normal;
+        Ok(())
+    }
+
+    /// Apply the selected tag to the selected version, or remove it when the version
+    /// already carries it
+    fn toggle_selected_tag(&mut self) -> Result<()> {
+        let (Some(tag), Some(key), Some(meta)) = (
+            self.selected_tag.clone(),
+            self.keys.get(self.selected_key_index).cloned(),
+            self.versions.get(self.selected_version_index).cloned(),
+        ) else {
+            return Ok(());
+        };
+        if !self.tag_names().contains(&tag) {
+            return Ok(());
+        }
+        let version = meta.version;
+
+        let result = if meta.tags.contains(&tag) {
+            self.vault
+                .untag(&key, &tag)
+                .map(|_| format!("Removed tag '{}' from version {}", tag, version))
+        } else {
+            self.vault
+                .tag(&key, &tag, version)
+                .map(|_| format!("Applied tag '{}' to version {}", tag, version))
+        };
+        match result {
+            Ok(message) => {
+                self.message = message;
+                self.refresh_versions()?;
             }
+            Err(e) => self.message = format!("Error: {}", e),
         }
         Ok(())
     }
 
-    fn add_tag(&mut self, tag: &str) -> Result<()> {
-        if let Some(key) = self.keys.get(self.selected_key_index) {
-            if let Some(version) = self.versions.get(self.selected_version_index) {
-                match self.vault.tag(key, tag, version.version) {
-                    Ok(_) => {
-                        self.message = format!("Tagged version {} as '{}'", version.version, tag);
-                        self.refresh_versions()?;
-                    }
-                    Err(e) => {
-                        self.message = format!("Error tagging: {}", e);
-                    }
-                }
+    /// Every tag on some version of the selected key, for the Tags panel
+    fn tag_names(&self) -> Vec<String> {
+        let tags: BTreeSet<&String> = self.versions.iter().flat_map(|v| &v.tags).collect();
+        tags.into_iter().cloned().collect()
+    }
+
+    /// Select the next (or previous) tag in the Tags panel
+    fn move_tag_selection(&mut self, forward: bool) {
+        let tags = self.tag_names();
+        if tags.is_empty() {
+            return;
+        }
+        let current = self
+            .selected_tag
+            .as_ref()
+            .and_then(|tag| tags.iter().position(|t| t == tag));
+        let index = match (current, forward) {
+            (None, true) => 0,
+            (None, false) => tags.len() - 1,
+            (Some(i), true) => (i + 1) % tags.len(),
+            (Some(i), false) => (i + tags.len() - 1) % tags.len(),
+        };
+        self.selected_tag = Some(tags[index].clone());
+    }
+
+    fn start_new_tag(&mut self) {
+        self.show_new_tag_dialog = true;
+        self.new_tag_input.clear();
+        self.input_cursor_pos = 0;
+        self.message = "Enter a tag name, then press Enter to apply it".to_string();
+    }
+
+    /// Apply the tag typed in the new-tag popup to the selected version
+    fn apply_new_tag(&mut self) -> Result<()> {
+        let tag = self.new_tag_input.trim().to_string();
+        if tag.is_empty() || tag.contains(char::is_whitespace) {
+            self.message = "Tag names cannot be empty or contain spaces".to_string();
+            return Ok(());
+        }
+
+        let (Some(key), Some(version)) = (
+            self.keys.get(self.selected_key_index).cloned(),
+            self.versions.get(self.selected_version_index).map(|v| v.version),
+        ) else {
+            return Ok(());
+        };
+        match self.vault.tag(&key, &tag, version) {
+            Ok(()) => {
+                self.message = format!("Applied tag '{}' to version {}", tag, version);
+                self.refresh_versions()?;
+                self.selected_tag = Some(tag);
             }
+            Err(e) => self.message = format!("Error: {}", e),
         }
+
+        self.show_new_tag_dialog = false;
+        self.new_tag_input.clear();
+        self.input_cursor_pos = 0;
         Ok(())
     }
 
+    fn cancel_new_tag(&mut self) {
+        self.show_new_tag_dialog = false;
+        self.new_tag_input.clear();
+        self.input_cursor_pos = 0;
+        self.message = "New tag cancelled".to_string();
+    }
+
+    /// Text field of the open popup, if any
+    fn active_input(&mut self) -> Option<&mut String> {
+        if self.show_add_prompt_dialog {
+            Some(&mut self.new_prompt_key_input)
+        } else if self.show_new_tag_dialog {
+            Some(&mut self.new_tag_input)
+        } else {
+            None
+        }
+    }
+
     fn switch_panel(&mut self, panel: Panel) {
         self.active_panel = panel;
     }
@@ -270,28 +993,31 @@ impl App {
     }
 
     fn handle_input_char(&mut self, c: char) {
-        if self.show_add_prompt_dialog {
+        let pos = self.input_cursor_pos;
+        if let Some(input) = self.active_input() {
             // Insert character at cursor position
-            self.new_prompt_key_input.insert(self.input_cursor_pos, c);
+            input.insert(pos, c);
             self.input_cursor_pos += 1;
         }
     }
 
     fn handle_backspace(&mut self) {
-        if self.show_add_prompt_dialog && self.input_cursor_pos > 0 {
-            self.new_prompt_key_input.remove(self.input_cursor_pos - 1);
+        let pos = self.input_cursor_pos;
+        if let Some(input) = self.active_input().filter(|_| pos > 0) {
+            input.remove(pos - 1);
             self.input_cursor_pos -= 1;
         }
     }
 
     fn handle_left_arrow(&mut self) {
-        if self.show_add_prompt_dialog && self.input_cursor_pos > 0 {
+        if self.active_input().is_some() && self.input_cursor_pos > 0 {
             self.input_cursor_pos -= 1;
         }
     }
 
     fn handle_right_arrow(&mut self) {
-        if self.show_add_prompt_dialog && self.input_cursor_pos < self.new_prompt_key_input.len() {
+        let pos = self.input_cursor_pos;
+        if self.active_input().is_some_and(|input| pos < input.len()) {
             self.input_cursor_pos += 1;
         }
     }
@@ -325,28 +1051,245 @@ impl App {
 }
 
 fn get_all_keys(vault: &PromptVault) -> Result<Vec<String>> {
-    let mut keys = std::collections::HashSet::new();
-
-    // Scan through all version entries to extract unique keys
-    for result in vault.db().scan_prefix(b"version:") {
-        let (key, _) = result?;
-        let key_str = String::from_utf8(key.to_vec())?;
+    vault.list_keys()
+}
 
-        // Extract the key from the format "version:{key}:{version}"
-        if let Some(stripped) = key_str.strip_prefix("version:") {
-            if let Some(key_part) = stripped.split(':').next() {
-                keys.insert(key_part.to_string());
+/// Keys with at least one hit for `query` in their latest version
+/// Popup with the global search query and its results as a jump list
+/// The choices for an edit made against an outdated version, with its diff on request
+fn render_save_conflict(f: &mut ratatui::Frame, conflict: &SaveConflict) {
+    let area = f.size();
+    let popup_area = match conflict.diff {
+        Some(_) => ratatui::layout::Rect {
+            x: area.width / 8,
+            y: area.height / 8,
+            width: area.width * 3 / 4,
+            height: area.height * 3 / 4,
+        },
+        None => {
+            let (width, height) = (64.min(area.width), 9.min(area.height));
+            ratatui::layout::Rect {
+                x: area.width.saturating_sub(width) / 2,
+                y: area.height.saturating_sub(height) / 2,
+                width,
+                height,
+            }
+        }
+    };
+    f.render_widget(Clear, popup_area);
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(vec![Span::styled(
+            format!("'{}' changed since you loaded it", conflict.key),
+            bold,
+        )]),
+        Line::from(format!(
+            "v{} is now the latest version; saving would layer your edit on it.",
+            conflict.latest
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("O", bold),
+            Span::raw(" overwrite as a new version, "),
+            Span::styled("D", bold),
+            Span::raw(" show diff, "),
+            Span::styled("C", bold),
+            Span::raw(" cancel"),
+        ]),
+    ];
+    if let Some(hunks) = &conflict.diff {
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!("Changes from v{} to your edit:", conflict.latest)));
+        for hunk in hunks {
+            lines.push(Line::styled(hunk.header(), Style::default().fg(Color::Cyan)));
+            for change in &hunk.changes {
+                let (sign, style) = match change.kind {
+                    ChangeKind::Equal => (' ', Style::default()),
+                    ChangeKind::Delete => ('-', Style::default().fg(Color::Red)),
+                    ChangeKind::Insert => ('+', Style::default().fg(Color::Green)),
+                };
+                lines.push(Line::styled(format!("{}{}", sign, change.text), style));
             }
         }
+        if hunks.is_empty() {
+            lines.push(Line::from("(no differences)"));
+        }
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(" Save Conflict ")
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::Yellow).fg(Color::Black)),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(paragraph, popup_area);
+}
+
+fn render_comments(f: &mut ratatui::Frame, popup: &CommentsPopup) {
+    let area = f.size();
+    let popup_area = ratatui::layout::Rect {
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
+    };
+    f.render_widget(Clear, popup_area);
+
+    let constraints = match popup.draft {
+        Some(_) => vec![Constraint::Min(1), Constraint::Length(3)],
+        None => vec![Constraint::Min(1)],
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(popup_area);
+
+    let threads = comment_threads(&popup.comments);
+    let items: Vec<ListItem> = threads
+        .iter()
+        .map(|(depth, comment)| {
+            let marker = if comment.reply_to.is_some() { "↳ " } else { "" };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{}{}", "  ".repeat(*depth), marker)),
+                Span::styled(
+                    format!(
+                        "#{} {} {} ",
+                        comment.id,
+                        comment.author,
+                        comment.timestamp.format("%m-%d %H:%M")
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(comment.text.replace('\n', " ")),
+            ]))
+        })
+        .collect();
+    let title = format!(" Comments on {} v{} ({}) ", popup.key, popup.version, threads.len());
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::Black)),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+    let mut state = ListState::default();
+    if !threads.is_empty() {
+        state.select(Some(popup.selected));
+    }
+    f.render_stateful_widget(list, chunks[0], &mut state);
+
+    if let Some((reply_to, text)) = &popup.draft {
+        let title = match reply_to {
+            Some(id) => format!(" Reply to #{} ", id),
+            None => " New comment ".to_string(),
+        };
+        let input = Paragraph::new(text.as_str()).block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::Blue).fg(Color::White)),
+        );
+        f.render_widget(input, chunks[1]);
+        let cursor_x = chunks[1].x + 1 + text.width() as u16;
+        if cursor_x < chunks[1].right() {
+            f.set_cursor(cursor_x, chunks[1].y + 1);
+        }
     }
+}
+
+fn render_global_search(f: &mut ratatui::Frame, app: &App) {
+    let area = f.size();
+    let popup_area = ratatui::layout::Rect {
+        x: area.width / 8,
+        y: area.height / 8,
+        width: area.width * 3 / 4,
+        height: area.height * 3 / 4,
+    };
+    f.render_widget(Clear, popup_area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(popup_area);
 
-    let mut keys_vec: Vec<String> = keys.into_iter().collect();
-    keys_vec.sort();
-    Ok(keys_vec)
+    let input = Paragraph::new(app.global_query.as_str()).block(
+        Block::default()
+            .title(" Search all prompts (tag:, author:, key: selectors) ")
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::Blue).fg(Color::White)),
+    );
+    f.render_widget(input, chunks[0]);
+    let cursor_x = chunks[0].x + 1 + app.global_query.width() as u16;
+    if cursor_x < chunks[0].right() {
+        f.set_cursor(cursor_x, chunks[0].y + 1);
+    }
+
+    let hit_items: Vec<ListItem> = app
+        .global_hits
+        .iter()
+        .map(|hit| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{} v{} ", hit.key, hit.version)),
+                Span::styled(
+                    format!("[{}] ", hit.field),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(hit.snippet.as_str()),
+            ]))
+        })
+        .collect();
+
+    let title = if app.global_query.trim().is_empty() {
+        " Results ".to_string()
+    } else {
+        format!(" {} result(s) ", app.global_hits.len())
+    };
+    let results = List::new(hit_items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .style(Style::default().bg(Color::Black)),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+    // The list state scrolls the selected result into view
+    let mut state = ListState::default();
+    if !app.global_hits.is_empty() {
+        state.select(Some(app.selected_hit_index));
+    }
+    f.render_stateful_widget(results, chunks[1], &mut state);
+}
+
+fn search_keys(vault: &PromptVault, query: &str) -> Result<Vec<String>> {
+    // Queries are incomplete while typed, e.g. `tag:` or an open quote
+    let Ok(query) = query.parse::<SearchQuery>() else {
+        return Ok(Vec::new());
+    };
+    let mut keys: Vec<String> = vault
+        .search_query(&query, &SearchOptions::default())?
+        .into_iter()
+        .map(|hit| hit.key)
+        .collect();
+    keys.dedup();
+    Ok(keys)
 }
 
 async fn show_splash_screen<B: Backend>(terminal: &mut Terminal<B>) -> Result<()> {
-    let ascii_art = vec![
+    let ascii_art = [
         " ██████╗  ██████╗  ██████╗   ██████╗ ",
         " ██╔══██╗ ██╔══██╗ ██╔══██╗ ██╔═══██╗",
         " ██████╔╝ ██████╔╝ ██████╔╝ ██║   ██║",
@@ -430,14 +1373,14 @@ async fn show_splash_screen<B: Backend>(terminal: &mut Terminal<B>) -> Result<()
     Ok(())
 }
 
-pub async fn run() -> Result<()> {
+pub async fn run(vault: PromptVault, options: TuiOptions) -> Result<()> {
     // For now, skip splash screen to ensure TUI works properly
-    run_with_app(App::new()?).await
+    run_with_app(App::new(vault, options)?).await
 }
 
-pub async fn run_with_key(key: String) -> Result<()> {
+pub async fn run_with_key(vault: PromptVault, key: String, options: TuiOptions) -> Result<()> {
     // For the specific key case, we'll skip the splash screen for better UX
-    run_with_app(App::new_with_key(key)?).await
+    run_with_app(App::new_with_key(vault, key, options)?).await
 }
 
 async fn run_with_app(mut app: App) -> Result<()> {
@@ -476,14 +1419,144 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()>
             if key.kind == KeyEventKind::Press {
                 match app.mode.clone() {
                     Mode::Normal => match key.code {
-                        KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Char('e') => {
-                            // Enter edit mode
-                            if app.active_panel == Panel::Content {
-                                app.edit_content = app.content.clone();
-                                app.mode = Mode::Editing;
+                        KeyCode::Char('o') if app.save_conflict.is_some() => {
+                            app.overwrite_conflict()?
+                        }
+                        KeyCode::Char('d') if app.save_conflict.is_some() => {
+                            app.toggle_conflict_diff()?
+                        }
+                        KeyCode::Char('c') | KeyCode::Esc if app.save_conflict.is_some() => {
+                            app.cancel_conflict()
+                        }
+                        _ if app.save_conflict.is_some() => {}
+                        KeyCode::Esc if app.is_writing_comment() => {
+                            if let Some(popup) = &mut app.comments_popup {
+                                popup.draft = None;
+                            }
+                            app.message = "Comment discarded".to_string();
+                        }
+                        KeyCode::Enter if app.is_writing_comment() => app.post_comment()?,
+                        KeyCode::Backspace | KeyCode::Char(_) if app.is_writing_comment() => {
+                            if let Some((_, text)) =
+                                app.comments_popup.as_mut().and_then(|p| p.draft.as_mut())
+                            {
+                                match key.code {
+                                    KeyCode::Char(c) => text.push(c),
+                                    _ => {
+                                        text.pop();
+                                    }
+                                }
                             }
                         }
+                        _ if app.is_writing_comment() => {}
+                        KeyCode::Esc | KeyCode::Char('c') if app.comments_popup.is_some() => {
+                            app.comments_popup = None;
+                        }
+                        KeyCode::Char('j') | KeyCode::Down if app.comments_popup.is_some() => {
+                            app.move_comment_selection(true)
+                        }
+                        KeyCode::Char('k') | KeyCode::Up if app.comments_popup.is_some() => {
+                            app.move_comment_selection(false)
+                        }
+                        KeyCode::Char('a') if app.comments_popup.is_some() => {
+                            app.start_comment(false)
+                        }
+                        KeyCode::Char('r') if app.comments_popup.is_some() => {
+                            app.start_comment(true)
+                        }
+                        _ if app.comments_popup.is_some() => {}
+                        KeyCode::Esc if app.show_global_search => app.close_global_search(),
+                        KeyCode::Enter if app.show_global_search => app.jump_to_hit()?,
+                        KeyCode::Down if app.show_global_search => app.move_hit_selection(true),
+                        KeyCode::Up if app.show_global_search => app.move_hit_selection(false),
+                        KeyCode::Backspace if app.show_global_search => {
+                            app.global_query.pop();
+                            app.update_global_search()?;
+                        }
+                        KeyCode::Char(c) if app.show_global_search => {
+                            app.global_query.push(c);
+                            app.update_global_search()?;
+                        }
+                        KeyCode::Char('f')
+                            if key.modifiers.contains(event::KeyModifiers::CONTROL)
+                                && !app.show_search_input
+                                && !app.show_add_prompt_dialog
+                                && !app.show_new_tag_dialog
+                                && !app.show_delete_confirmation =>
+                        {
+                            app.start_global_search();
+                        }
+                        KeyCode::Esc if app.show_search_input => app.clear_search()?,
+                        KeyCode::Enter if app.show_search_input => app.finish_search(),
+                        KeyCode::Backspace if app.show_search_input => {
+                            app.search_query.pop();
+                            app.apply_search()?;
+                        }
+                        KeyCode::Char(c) if app.show_search_input => {
+                            app.search_query.push(c);
+                            app.apply_search()?;
+                        }
+                        KeyCode::Esc if app.show_add_prompt_dialog => app.cancel_add_prompt(),
+                        KeyCode::Backspace if app.show_add_prompt_dialog => app.handle_backspace(),
+                        KeyCode::Char(c) if app.show_add_prompt_dialog => app.handle_input_char(c),
+                        KeyCode::Esc if app.show_new_tag_dialog => app.cancel_new_tag(),
+                        KeyCode::Enter if app.show_new_tag_dialog => app.apply_new_tag()?,
+                        KeyCode::Backspace if app.show_new_tag_dialog => app.handle_backspace(),
+                        KeyCode::Char(c) if app.show_new_tag_dialog => app.handle_input_char(c),
+                        KeyCode::Left if app.show_new_tag_dialog => app.handle_left_arrow(),
+                        KeyCode::Right if app.show_new_tag_dialog => app.handle_right_arrow(),
+                        KeyCode::Char('q') => return Ok(()),
+                        KeyCode::Char('/')
+                            if app.active_panel == Panel::Keys
+                                && !app.show_add_prompt_dialog
+                                && !app.show_delete_confirmation =>
+                        {
+                            app.start_search();
+                        }
+                        KeyCode::Char(' ')
+                            if app.active_panel == Panel::Keys
+                                && !app.show_add_prompt_dialog
+                                && !app.show_delete_confirmation =>
+                        {
+                            app.toggle_namespace();
+                        }
+                        KeyCode::Char('s')
+                            if app.active_panel == Panel::Keys
+                                && !app.show_add_prompt_dialog
+                                && !app.show_delete_confirmation =>
+                        {
+                            app.cycle_key_order()?;
+                            app.refresh_versions()?;
+                        }
+                        KeyCode::Esc
+                            if !app.search_query.is_empty() && !app.show_delete_confirmation =>
+                        {
+                            app.clear_search()?
+                        }
+                        KeyCode::Char('e' | 'o' | 'x' | 'a' | 'd' | 't') | KeyCode::Enter
+                            if app.read_only =>
+                        {
+                            app.message = "Read-only mode: changes are disabled".to_string();
+                        }
+                        KeyCode::Char('e') | KeyCode::Char('o')
+                            if app.active_panel == Panel::Content
+                                && app.selected_key_is_locked() =>
+                        {
+                            app.message =
+                                "Prompt is password-protected; restart with --unlock to edit it"
+                                    .to_string();
+                        }
+                        KeyCode::Char('b') if app.active_panel == Panel::Content => {
+                            app.show_blame = !app.show_blame;
+                        }
+                        KeyCode::Char('c')
+                            if app.active_panel == Panel::Versions && !app.versions.is_empty() =>
+                        {
+                            app.open_comments()?;
+                        }
+                        KeyCode::Char('e') if app.active_panel == Panel::Content => {
+                            app.start_editing();
+                        }
                         KeyCode::Right => {
                             if app.show_add_prompt_dialog {
                                 // Move cursor right in input field when in add prompt dialog
@@ -516,369 +1589,124 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()>
                             if app.show_add_prompt_dialog {
                                 // Add the prompt with the entered key name
                                 app.add_prompt()?;
-                            } else {
+                            } else if app.active_panel == Panel::Tags && !app.versions.is_empty() {
                                 // Apply or remove tag for the currently selected version
-                                if app.active_panel == Panel::Tags && !app.versions.is_empty() {
-                                    if let Some(tag) = app.selected_tag.clone() {
-                                        if let Some(version) =
-                                            app.versions.get(app.selected_version_index)
-                                        {
-                                            if let Some(key) = app.keys.get(app.selected_key_index)
-                                            {
-                                                // Check if the tag is currently on this version
-                                                let is_currently_tagged =
-                                                    version.tags.contains(&tag);
-
-                                                if is_currently_tagged {
-                                                    // Tag is currently on this version
-                                                    // For dev tag, we don't allow removing from latest version
-                                                    if tag == "dev"
-                                                        && app.selected_version_index
-                                                            == app.versions.len().saturating_sub(1)
-                                                    {
-                                                        // This is the latest version with dev tag - we can't remove it since dev should stay on latest
-                                                        app.message = "Cannot remove 'dev' tag. It always points to the latest version.".to_string();
-                                                    } else if tag == "dev" {
-                                                        // This is not the latest version, but dev tag is on it somehow - user can't remove it
-                                                        app.message = "Cannot modify 'dev' tag manually. It always points to the latest version.".to_string();
-                                                    } else {
-                                                        // For other tags, allow removal by tagging version 1 if available, otherwise find another version
-                                                        let target_version = if app.versions.len()
-                                                            > 1
-                                                            && version.version != 1
-                                                        {
-                                                            1 // Move to version 1
-                                                        } else if app.versions.len() > 1 {
-                                                            // We're on version 1, move to version 2
-                                                            2
-                                                        } else {
-                                                            // Only one version - clear the tag by applying it back to same version to force storage update
-                                                            // Actually, let's just not allow removal if it's the only version
-                                                            app.message = format!("Cannot remove tag '{}' from the only available version", tag);
-                                                            return Ok(());
-                                                        };
-
-                                                        match app.vault.tag(
-                                                            key,
-                                                            &tag,
-                                                            target_version,
-                                                        ) {
-                                                            Ok(_) => {
-                                                                app.message = format!(
-                                                                    "Moved tag '{}' to version {}",
-                                                                    tag, target_version
-                                                                );
-                                                                app.refresh_versions()?;
-                                                            }
-                                                            Err(e) => {
-                                                                app.message = format!(
-                                                                    "Error moving tag: {}",
-                                                                    e
-                                                                );
-                                                            }
-                                                        }
-                                                    }
-                                                } else {
-                                                    // Tag is not on this version - apply it here
-                                                    // First check if this version already has any tags applied
-                                                    let version_already_has_tags =
-                                                        !version.tags.is_empty();
-
-                                                    if version_already_has_tags {
-                                                        // This version already has tags, so first remove all tags from this version
-                                                        // We'll apply the selected tag after removing existing ones
-                                                        // For now, we'll just apply the tag - the backend will handle moving tags from other versions
-                                                        match app.vault.tag(
-                                                            key,
-                                                            &tag,
-                                                            version.version,
-                                                        ) {
-                                                            Ok(_) => {
-                                                                app.message = format!("Applied tag '{}' to version {} (replacing previous tags)", tag, version.version);
-                                                                app.refresh_versions()?;
-                                                            }
-                                                            Err(e) => {
-                                                                app.message = format!(
-                                                                    "Error applying tag: {}",
-                                                                    e
-                                                                );
-                                                            }
-                                                        }
-                                                    } else {
-                                                        // No existing tags on this version, just apply the new tag
-                                                        match app.vault.tag(
-                                                            key,
-                                                            &tag,
-                                                            version.version,
-                                                        ) {
-                                                            Ok(_) => {
-                                                                app.message = format!("Applied tag '{}' to version {}", tag, version.version);
-                                                                app.refresh_versions()?;
-                                                            }
-                                                            Err(e) => {
-                                                                app.message = format!(
-                                                                    "Error applying tag: {}",
-                                                                    e
-                                                                );
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
+                                app.toggle_selected_tag()?;
                             }
                         }
-                        KeyCode::Char('x') => {
-                            // Apply or remove tag for the currently selected version (same as Enter for convenience)
-                            if app.active_panel == Panel::Tags && !app.versions.is_empty() {
-                                if let Some(tag) = app.selected_tag.clone() {
-                                    if let Some(version) =
-                                        app.versions.get(app.selected_version_index)
-                                    {
-                                        if let Some(key) = app.keys.get(app.selected_key_index) {
-                                            // Check if the tag is currently on this version
-                                            let is_currently_tagged = version.tags.contains(&tag);
-
-                                            if is_currently_tagged {
-                                                // Tag is currently on this version
-                                                // For dev tag, we don't allow removing from latest version
-                                                if tag == "dev"
-                                                    && app.selected_version_index
-                                                        == app.versions.len().saturating_sub(1)
-                                                {
-                                                    // This is the latest version with dev tag - we can't remove it since dev should stay on latest
-                                                    app.message = "Cannot remove 'dev' tag. It always points to the latest version.".to_string();
-                                                } else if tag == "dev" {
-                                                    // This is not the latest version, but dev tag is on it somehow - user can't remove it
-                                                    app.message = "Cannot modify 'dev' tag manually. It always points to the latest version.".to_string();
-                                                } else {
-                                                    // For other tags, allow removal by tagging version 1 if available, otherwise find another version
-                                                    let target_version = if app.versions.len() > 1
-                                                        && version.version != 1
-                                                    {
-                                                        1 // Move to version 1
-                                                    } else if app.versions.len() > 1 {
-                                                        // We're on version 1, move to version 2
-                                                        2
-                                                    } else {
-                                                        // Only one version - clear the tag by applying it back to same version to force storage update
-                                                        // Actually, let's just not allow removal if it's the only version
-                                                        app.message = format!("Cannot remove tag '{}' from the only available version", tag);
-                                                        return Ok(());
-                                                    };
-
-                                                    match app.vault.tag(key, &tag, target_version) {
-                                                        Ok(_) => {
-                                                            app.message = format!(
-                                                                "Moved tag '{}' to version {}",
-                                                                tag, target_version
-                                                            );
-                                                            app.refresh_versions()?;
-                                                        }
-                                                        Err(e) => {
-                                                            app.message =
-                                                                format!("Error moving tag: {}", e);
-                                                        }
-                                                    }
-                                                }
-                                            } else {
-                                                // Tag is not on this version - apply it here
-                                                // First check if this version already has any tags applied
-                                                let version_already_has_tags =
-                                                    !version.tags.is_empty();
-
-                                                if version_already_has_tags {
-                                                    // This version already has tags, so first remove all tags from this version
-                                                    // We'll apply the selected tag after removing existing ones
-                                                    // For now, we'll just apply the tag - the backend will handle moving tags from other versions
-                                                    match app.vault.tag(key, &tag, version.version)
-                                                    {
-                                                        Ok(_) => {
-                                                            app.message = format!("Applied tag '{}' to version {} (replacing previous tags)", tag, version.version);
-                                                            app.refresh_versions()?;
-                                                        }
-                                                        Err(e) => {
-                                                            app.message = format!(
-                                                                "Error applying tag: {}",
-                                                                e
-                                                            );
-                                                        }
-                                                    }
-                                                } else {
-                                                    // No existing tags on this version, just apply the new tag
-                                                    match app.vault.tag(key, &tag, version.version)
-                                                    {
-                                                        Ok(_) => {
-                                                            app.message = format!(
-                                                                "Applied tag '{}' to version {}",
-                                                                tag, version.version
-                                                            );
-                                                            app.refresh_versions()?;
-                                                        }
-                                                        Err(e) => {
-                                                            app.message = format!(
-                                                                "Error applying tag: {}",
-                                                                e
-                                                            );
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+                        KeyCode::Char('x')
+                            if app.active_panel == Panel::Tags && !app.versions.is_empty() =>
+                        {
+                            // Same as Enter, for convenience
+                            app.toggle_selected_tag()?;
+                        }
+                        KeyCode::Char('t')
+                            if app.active_panel == Panel::Tags && !app.versions.is_empty() =>
+                        {
+                            app.start_new_tag();
                         }
                         KeyCode::Char('j') | KeyCode::Down => {
                             match app.active_panel {
-                                Panel::Keys => {
-                                    // Move down in key list
-                                    if !app.keys.is_empty() {
-                                        app.selected_key_index =
-                                            (app.selected_key_index + 1) % app.keys.len();
-                                        app.refresh_versions()?;
-                                    }
+                                Panel::Keys if !app.keys.is_empty() => {
+                                    // Move down in key tree
+                                    app.move_key_selection(true)?;
                                 }
-                                Panel::Versions => {
+                                Panel::Versions if !app.versions.is_empty() => {
                                     // Move down in version list
-                                    if !app.versions.is_empty() {
-                                        app.selected_version_index =
-                                            (app.selected_version_index + 1) % app.versions.len();
-
-                                        if let Some(version) =
-                                            app.versions.get(app.selected_version_index)
-                                        {
-                                            if let Some(key) = app.keys.get(app.selected_key_index)
-                                            {
-                                                app.content = app.vault.get(
-                                                    key,
-                                                    VersionSelector::Version(version.version),
-                                                )?;
-                                            }
-                                        }
-                                    }
-                                }
-                                Panel::Tags => {
-                                    // Move down in tag selection
-                                    let tags = ["stable", "dev", "release"];
-                                    if app.selected_tag.is_none() {
-                                        app.selected_tag = Some(tags[0].to_string());
-                                    } else {
-                                        let current = app.selected_tag.as_ref().unwrap();
-                                        for (i, tag) in tags.iter().enumerate() {
-                                            if tag == current {
-                                                let next_idx = (i + 1) % tags.len();
-                                                app.selected_tag = Some(tags[next_idx].to_string());
-                                                break;
-                                            }
+                                    app.selected_version_index =
+                                        (app.selected_version_index + 1) % app.versions.len();
+
+                                    if let Some(version) =
+                                        app.versions.get(app.selected_version_index)
+                                    {
+                                        if let Some(key) = app.keys.get(app.selected_key_index) {
+                                            app.content = app.vault.display_content(
+                                                key,
+                                                version.version,
+                                                &app.unlocked,
+                                            )?;
                                         }
                                     }
                                 }
+                                Panel::Tags => app.move_tag_selection(true),
                                 _ => {}
                             }
                         }
                         KeyCode::Char('k') | KeyCode::Up => {
                             match app.active_panel {
-                                Panel::Keys => {
-                                    // Move up in key list
-                                    if !app.keys.is_empty() {
-                                        app.selected_key_index = app
-                                            .selected_key_index
-                                            .saturating_sub(1)
-                                            .min(app.keys.len().saturating_sub(1));
-                                        app.refresh_versions()?;
-                                    }
+                                Panel::Keys if !app.keys.is_empty() => {
+                                    // Move up in key tree
+                                    app.move_key_selection(false)?;
                                 }
-                                Panel::Versions => {
+                                Panel::Versions if !app.versions.is_empty() => {
                                     // Move up in version list
-                                    if !app.versions.is_empty() {
-                                        app.selected_version_index = app
-                                            .selected_version_index
-                                            .saturating_sub(1)
-                                            .min(app.versions.len().saturating_sub(1));
-
-                                        if let Some(version) =
-                                            app.versions.get(app.selected_version_index)
-                                        {
-                                            if let Some(key) = app.keys.get(app.selected_key_index)
-                                            {
-                                                app.content = app.vault.get(
-                                                    key,
-                                                    VersionSelector::Version(version.version),
-                                                )?;
-                                            }
-                                        }
-                                    }
-                                }
-                                Panel::Tags => {
-                                    // Move up in tag selection
-                                    let tags = ["stable", "dev", "release"];
-                                    if app.selected_tag.is_none() {
-                                        app.selected_tag = Some(tags[tags.len() - 1].to_string());
-                                    } else {
-                                        let current = app.selected_tag.as_ref().unwrap();
-                                        for (i, tag) in tags.iter().enumerate() {
-                                            if tag == current {
-                                                let prev_idx = (i + tags.len() - 1) % tags.len();
-                                                app.selected_tag = Some(tags[prev_idx].to_string());
-                                                break;
-                                            }
+                                    app.selected_version_index = app
+                                        .selected_version_index
+                                        .saturating_sub(1)
+                                        .min(app.versions.len().saturating_sub(1));
+
+                                    if let Some(version) =
+                                        app.versions.get(app.selected_version_index)
+                                    {
+                                        if let Some(key) = app.keys.get(app.selected_key_index) {
+                                            app.content = app.vault.display_content(
+                                                key,
+                                                version.version,
+                                                &app.unlocked,
+                                            )?;
                                         }
                                     }
                                 }
+                                Panel::Tags => app.move_tag_selection(false),
                                 _ => {}
                             }
                         }
-                        KeyCode::Char('o') => {
+                        KeyCode::Char('o')
+                            if app.active_panel == Panel::Content && !app.versions.is_empty() =>
+                        {
                             // Open external editor when on content panel
-                            if app.active_panel == Panel::Content && !app.versions.is_empty() {
-                                if let Some(version) = app.versions.get(app.selected_version_index)
-                                {
-                                    if let Some(key) = app.keys.get(app.selected_key_index) {
-                                        // Get content to edit
-                                        let content_to_edit = app
-                                            .vault
-                                            .get(key, VersionSelector::Version(version.version))?;
-
-                                        // Write content to a temporary file
-                                        use std::fs;
-                                        use std::process::Command;
-
-                                        let temp_file =
-                                            std::env::temp_dir().join("promptpro_edit.txt");
-                                        fs::write(&temp_file, &content_to_edit)?;
-
-                                        // Get editor from environment or default to vim
-                                        let editor = std::env::var("EDITOR")
-                                            .unwrap_or_else(|_| "vim".to_string());
-
-                                        // Open external editor
-                                        let status =
-                                            Command::new(&editor).arg(&temp_file).status()?;
-
-                                        // Read the updated content if the editor exited successfully
-                                        if status.success() {
-                                            let updated_content = fs::read_to_string(&temp_file)?;
-                                            if updated_content != content_to_edit {
-                                                // Update the vault with the new content
-                                                app.vault.update(
-                                                    key,
-                                                    &updated_content,
-                                                    Some("Updated via external editor".to_string()),
-                                                )?;
-                                                app.message =
-                                                    format!("Updated content for '{}'", key);
-                                                app.refresh_versions()?; // Refresh to get the new version
-                                            } else {
-                                                app.message = "No changes detected".to_string();
-                                            }
+                            if let Some(version) = app.versions.get(app.selected_version_index) {
+                                let key = app.keys.get(app.selected_key_index).cloned();
+                                if let Some(key) = key {
+                                    let base = app.versions.last().map(|latest| latest.version);
+                                    // Get content to edit
+                                    let content_to_edit = app.vault.display_content(
+                                        &key,
+                                        version.version,
+                                        &app.unlocked,
+                                    )?;
+
+                                    // Write content to a temporary file
+                                    use std::fs;
+                                    use std::process::Command;
+
+                                    let temp_file = std::env::temp_dir().join("promptpro_edit.txt");
+                                    fs::write(&temp_file, &content_to_edit)?;
+
+                                    // Get editor from environment or default to vim
+                                    let editor = std::env::var("EDITOR")
+                                        .unwrap_or_else(|_| "vim".to_string());
+
+                                    // Open external editor
+                                    let status = Command::new(&editor).arg(&temp_file).status()?;
+
+                                    // Read the updated content if the editor exited successfully
+                                    if status.success() {
+                                        let updated_content = fs::read_to_string(&temp_file)?;
+                                        if updated_content != content_to_edit {
+                                            app.save_edit(
+                                                key,
+                                                updated_content,
+                                                "Updated via external editor",
+                                                base,
+                                            )?;
+                                        } else {
+                                            app.message = "No changes detected".to_string();
                                         }
-
-                                        // Clean up temp file
-                                        let _ = fs::remove_file(&temp_file);
                                     }
+
+                                    // Clean up temp file
+                                    let _ = fs::remove_file(&temp_file);
                                 }
                             }
                         }
@@ -890,26 +1718,24 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()>
                             // Start adding a new prompt (when on Keys panel)
                             app.start_add_prompt();
                         }
-                        KeyCode::Char('d') => {
+                        KeyCode::Char('d')
+                            if app.active_panel == Panel::Keys && !app.keys.is_empty() =>
+                        {
                             // Delete current key (when on Keys panel)
-                            if app.active_panel == Panel::Keys {
-                                // Confirm deletion with user before proceeding
-                                if !app.keys.is_empty() {
-                                    if let Some(_key) = app.keys.get(app.selected_key_index) {
-                                        // Show confirmation dialog
-                                        app.show_delete_confirmation = true;
-                                    }
-                                }
+                            // Confirm deletion with user before proceeding
+                            if let Some(_key) = app.keys.get(app.selected_key_index) {
+                                // Show confirmation dialog
+                                app.show_delete_confirmation = true;
                             }
                         }
-                        KeyCode::Char('y') if app.show_delete_confirmation => {
+                        KeyCode::Char('y')
+                            if app.show_delete_confirmation && !app.keys.is_empty() =>
+                        {
                             // Confirm deletion
-                            if !app.keys.is_empty() {
-                                if let Some(key) = app.keys.get(app.selected_key_index).cloned() {
-                                    app.delete_current_key()?;
-                                    app.show_delete_confirmation = false;
-                                    app.message = format!("Deleted prompt key: '{}'", key);
-                                }
+                            if let Some(key) = app.keys.get(app.selected_key_index).cloned() {
+                                app.delete_current_key()?;
+                                app.show_delete_confirmation = false;
+                                app.message = format!("Deleted prompt key: '{}'", key);
                             }
                         }
                         KeyCode::Char('n') => {
@@ -938,9 +1764,7 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()>
                         KeyCode::Char('s')
                             if key.modifiers.contains(event::KeyModifiers::CONTROL) =>
                         {
-                            // Save content
                             app.save_content()?;
-                            app.mode = Mode::Normal;
                         }
                         _ => {}
                     },
@@ -950,6 +1774,68 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()>
     }
 }
 
+/// The messages of a chat prompt, each under a header naming its role
+fn chat_lines(chat: &ChatPrompt) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for (index, part) in chat.messages.iter().enumerate() {
+        if index > 0 {
+            lines.push(Line::from(""));
+        }
+        let color = match part.role {
+            Role::System => Color::Magenta,
+            Role::User => Color::Cyan,
+            Role::Assistant => Color::Green,
+        };
+        lines.push(Line::from(Span::styled(
+            format!("── [{}] {} ──", index + 1, part.role),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        )));
+        for line in part.content.lines() {
+            lines.push(Line::from(Span::styled(
+                line.to_string(),
+                Style::default().fg(Color::White),
+            )));
+        }
+    }
+    lines
+}
+
+/// The selected version's lines, each prefixed with the version that introduced it
+fn blame_lines(app: &App) -> Vec<Line<'static>> {
+    let (Some(key), Some(version)) = (
+        app.keys.get(app.selected_key_index),
+        app.versions.get(app.selected_version_index),
+    ) else {
+        return Vec::new();
+    };
+    let blame = match app.vault.blame_at(key, VersionSelector::Version(version.version)) {
+        Ok(blame) => blame,
+        Err(e) => {
+            let style = Style::default().fg(Color::Red);
+            return vec![Line::from(Span::styled(e.to_string(), style))];
+        }
+    };
+
+    let width = blame.iter().map(|line| line.version.to_string().len()).max().unwrap_or(1);
+    blame
+        .into_iter()
+        .map(|line| {
+            let color = if line.version == version.version {
+                Color::Green
+            } else {
+                Color::DarkGray
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!("v{:<width$} │ ", line.version, width = width),
+                    Style::default().fg(color),
+                ),
+                Span::styled(line.text, Style::default().fg(Color::White)),
+            ])
+        })
+        .collect()
+}
+
 fn ui(f: &mut ratatui::Frame, app: &App) {
     // Main layout: split between content area and footer
     let main_chunks = Layout::default()
@@ -1004,31 +1890,59 @@ fn ui(f: &mut ratatui::Frame, app: &App) {
         Style::default().fg(Color::DarkGray)
     };
 
-    // Keys List Panel
-    let key_items: Vec<ListItem> = app
-        .keys
+    // Keys Tree Panel
+    let tree = app.shows_key_tree();
+    let mut key_items: Vec<ListItem> = key_rows(&app.keys, &app.collapsed_namespaces, tree)
         .iter()
-        .enumerate()
-        .map(|(i, key)| {
-            let is_selected = i == app.selected_key_index;
+        .map(|row| {
+            let is_selected = row.is_selectable() && row.contains(app.selected_key_index);
+            let indent = "  ".repeat(row.depth);
+            let label = match &row.kind {
+                KeyRowKind::Key => row.label.clone(),
+                KeyRowKind::Namespace {
+                    collapsed: true,
+                    count,
+                    ..
+                } => format!("▸ {}/ ({})", row.label, count),
+                KeyRowKind::Namespace { .. } => format!("▾ {}/", row.label),
+            };
             let (text, style) = if is_selected {
                 (
-                    format!("> {}", key),
+                    format!("> {}{}", indent, label),
                     Style::default()
                         .fg(Color::Yellow)
                         .add_modifier(Modifier::BOLD),
                 )
+            } else if matches!(row.kind, KeyRowKind::Namespace { .. }) {
+                (format!("  {}{}", indent, label), Style::default().fg(Color::Cyan))
             } else {
-                (format!("  {}", key), Style::default().fg(Color::White))
+                (format!("  {}{}", indent, label), Style::default().fg(Color::White))
             };
             ListItem::new(vec![Line::from(Span::styled(text, style))])
         })
         .collect();
+    if key_items.is_empty() && app.search_query.is_empty() {
+        // A new vault; `ppro tui` offers to import prompts before getting here
+        let hint = if app.read_only {
+            "  No prompts yet"
+        } else {
+            "  No prompts yet; press 'a' to add one"
+        };
+        key_items.push(ListItem::new(Line::from(Span::styled(
+            hint,
+            Style::default().fg(Color::DarkGray),
+        ))));
+    }
 
     let key_list = List::new(key_items)
         .block(
             Block::default()
-                .title(" Keys ")
+                .title(match (app.read_only, app.key_order) {
+                    (true, KeyOrder::Name) => " Keys (read-only) ".to_string(),
+                    (false, KeyOrder::Name) => " Keys ".to_string(),
+                    (true, order) => format!(" Keys by {} (read-only) ", order),
+                    (false, order) => format!(" Keys by {} ", order),
+                })
                 .borders(Borders::ALL)
                 .style(keys_border_style),
         )
@@ -1038,7 +1952,38 @@ fn ui(f: &mut ratatui::Frame, app: &App) {
                 .add_modifier(Modifier::BOLD),
         );
 
-    f.render_widget(key_list, chunks[0]);
+    // Search box above the key list while a query is typed or applied
+    let keys_area = if app.show_search_input || !app.search_query.is_empty() {
+        let keys_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)])
+            .split(chunks[0]);
+
+        let search_style = if app.show_search_input {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        let search_box = Paragraph::new(format!("/{}", app.search_query)).block(
+            Block::default()
+                .title(" Search ")
+                .borders(Borders::ALL)
+                .style(search_style),
+        );
+        f.render_widget(search_box, keys_chunks[0]);
+
+        if app.show_search_input {
+            let cursor_x = keys_chunks[0].x + 2 + app.search_query.width() as u16;
+            if cursor_x < keys_chunks[0].right().saturating_sub(1) {
+                f.set_cursor(cursor_x, keys_chunks[0].y + 1);
+            }
+        }
+        keys_chunks[1]
+    } else {
+        chunks[0]
+    };
+
+    f.render_widget(key_list, keys_area);
 
     // Versions List Panel
     let version_items: Vec<ListItem> = app
@@ -1052,12 +1997,18 @@ fn ui(f: &mut ratatui::Frame, app: &App) {
             } else {
                 format!(" [{}]", version.tags.join(","))
             };
+            let author = version
+                .author
+                .as_ref()
+                .map(|author| format!(" {}", author))
+                .unwrap_or_default();
             let text = format!(
-                "{} v{}{} ({})",
+                "{} v{}{} ({}{})",
                 if is_selected { ">" } else { " " },
                 version.version,
                 tags_str,
-                version.timestamp.format("%m-%d %H:%M")
+                version.timestamp.format("%m-%d %H:%M"),
+                author
             );
             let style = if is_selected {
                 Style::default()
@@ -1112,7 +2063,18 @@ fn ui(f: &mut ratatui::Frame, app: &App) {
             .style(Style::default().fg(Color::White))
             .wrap(Wrap { trim: false })
             .scroll((0, 0)),
+        _ if app.show_blame => Paragraph::new(blame_lines(app))
+            .block(
+                Block::default()
+                    .title(" Content (blame) ")
+                    .borders(Borders::ALL)
+                    .style(content_border_style),
+            )
+            .wrap(Wrap { trim: false })
+            .scroll((0, 0)),
         _ => {
+            // Chat prompts show their messages one by one instead of their YAML
+            let chat = ChatPrompt::from_content(&app.content).ok();
             // Simple markdown-like styling for content display
             let styled_content = app
                 .content
@@ -1152,10 +2114,33 @@ fn ui(f: &mut ratatui::Frame, app: &App) {
                 })
                 .collect::<Vec<Line>>();
 
-            Paragraph::new(styled_content)
+            // Metadata of the selected prompt above its content
+            let header = app
+                .keys
+                .get(app.selected_key_index)
+                .and_then(|key| app.vault.prompt_meta(key).ok())
+                .map(|meta| meta.summary_lines())
+                .unwrap_or_default();
+            let mut lines: Vec<Line> = header
+                .into_iter()
+                .map(|line| Line::from(Span::styled(line, Style::default().fg(Color::DarkGray))))
+                .collect();
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+            match &chat {
+                Some(chat) => lines.extend(chat_lines(chat)),
+                None => lines.extend(styled_content),
+            }
+
+            let title = match &chat {
+                Some(chat) => format!(" Content (chat, {} messages) ", chat.messages.len()),
+                None => " Content ".to_string(),
+            };
+            Paragraph::new(lines)
                 .block(
                     Block::default()
-                        .title(" Content ")
+                        .title(title)
                         .borders(Borders::ALL)
                         .style(content_border_style),
                 )
@@ -1167,17 +2152,16 @@ fn ui(f: &mut ratatui::Frame, app: &App) {
     f.render_widget(content_paragraph, chunks[2]);
 
     // Tags Panel
-    let tags = ["stable", "dev", "release"];
-    let tag_items: Vec<ListItem> = tags
-        .iter()
-        .map(|tag_str| {
-            let tag = tag_str.to_string();
+    let tag_items: Vec<ListItem> = app
+        .tag_names()
+        .into_iter()
+        .map(|tag| {
             let is_selected = app.selected_tag.as_ref() == Some(&tag);
             // Check specifically if this tag is applied to the current version
             let is_currently_on_this_version = app
                 .versions
                 .get(app.selected_version_index)
-                .map_or(false, |v| v.tags.contains(&tag));
+                .is_some_and(|v| v.tags.contains(&tag));
 
             let (text, style) = if is_currently_on_this_version {
                 // This specific tag is applied to the currently selected version
@@ -1281,6 +2265,54 @@ fn ui(f: &mut ratatui::Frame, app: &App) {
             }
         }
     }
+    // Check if we need to show the new tag popup
+    else if app.show_new_tag_dialog {
+        let version = app
+            .versions
+            .get(app.selected_version_index)
+            .map_or(0, |v| v.version);
+        let popup_width = 50;
+        let popup_height = 7;
+        let area = f.size();
+        let popup_x = area.width.saturating_sub(popup_width) / 2;
+        let popup_y = area.height.saturating_sub(popup_height) / 2;
+        let popup_area = ratatui::layout::Rect {
+            x: popup_x,
+            y: popup_y,
+            width: popup_width.min(area.width),
+            height: popup_height.min(area.height),
+        };
+
+        let new_tag_block = Block::default()
+            .title(" New Tag ")
+            .borders(Borders::ALL)
+            .style(Style::default().bg(Color::Blue).fg(Color::White));
+
+        let text_lines = vec![
+            Line::from(format!("Tag name for version {}:", version)),
+            Line::from(""),
+            Line::from(vec![Span::raw(&app.new_tag_input)]),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to apply, "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" to cancel"),
+            ]),
+        ];
+
+        let paragraph = Paragraph::new(text_lines)
+            .block(new_tag_block)
+            .wrap(Wrap { trim: false });
+        f.render_widget(paragraph, popup_area);
+
+        // Inside the border, on the input line
+        let cursor_x = popup_x + 1 + app.input_cursor_pos as u16;
+        let cursor_y = popup_y + 3;
+        if cursor_x < area.width && cursor_y < area.height {
+            f.set_cursor(cursor_x, cursor_y);
+        }
+    }
     // Check if we need to show delete confirmation popup
     else if app.show_delete_confirmation {
         if let Some(key) = app.keys.get(app.selected_key_index) {
@@ -1329,23 +2361,61 @@ fn ui(f: &mut ratatui::Frame, app: &App) {
         }
     }
 
+    if app.show_global_search {
+        render_global_search(f, app);
+    }
+    if let Some(popup) = &app.comments_popup {
+        render_comments(f, popup);
+    }
+    if let Some(conflict) = &app.save_conflict {
+        render_save_conflict(f, conflict);
+    }
+
     // Footer with instructions
     let footer_text = match app.mode {
         Mode::Normal => {
-            let panel_desc = if app.show_delete_confirmation {
+            let panel_desc = if app.save_conflict.is_some() {
+                "Save conflict: o to save as a new version anyway, d for the diff, c to cancel"
+            } else if app.is_writing_comment() {
+                "Comment: type the text, Enter to post, Esc to discard"
+            } else if app.comments_popup.is_some() && app.read_only {
+                "Comments: j/k to select, Esc to close"
+            } else if app.comments_popup.is_some() {
+                "Comments: j/k to select, a to comment, r to reply, Esc to close"
+            } else if app.show_global_search {
+                "Global search: type a query, ↑↓ to pick, Enter to jump, Esc to close"
+            } else if app.show_search_input {
+                "Search: type to filter, Enter to keep, Esc to clear"
+            } else if app.show_delete_confirmation {
                 "Confirm deletion: Y(es) / N(o) or Esc"
             } else if app.show_add_prompt_dialog {
                 "Enter key name, then press Enter to edit in external editor"
+            } else if app.show_new_tag_dialog {
+                "Enter a tag name, then press Enter to apply it to the selected version"
+            } else if app.read_only {
+                match app.active_panel {
+                    Panel::Keys => {
+                        "Keys: j/k to navigate, Space to fold, s to sort, / to filter, \
+                         Ctrl+F to search"
+                    }
+                    Panel::Versions => "Versions: j/k to navigate, c for comments",
+                    Panel::Content => "Content: b to toggle blame",
+                    Panel::Tags => "Tags: view only",
+                }
             } else {
                 match app.active_panel {
-                    Panel::Keys => "Keys: j/k to navigate, d to delete, a to add",
-                    Panel::Versions => "Versions: j/k to navigate",
-                    Panel::Content => "Content: e to edit, o for external editor",
-                    Panel::Tags => "Tags: j/k to select, Enter to apply",
+                    Panel::Keys => {
+                        "Keys: j/k to navigate, Space to fold, s to sort, / to filter, \
+                         Ctrl+F to search, d to delete, a to add"
+                    }
+                    Panel::Versions => "Versions: j/k to navigate, c for comments",
+                    Panel::Content => "Content: e to edit, o for external editor, b for blame",
+                    Panel::Tags => "Tags: j/k to select, Enter to apply or remove, t for a new tag",
                 }
             };
 
-            format!("←→: switch panels | {} | q: quit", panel_desc)
+            let mode_desc = if app.read_only { "READ-ONLY | " } else { "" };
+            format!("{}←→: switch panels | {} | q: quit", mode_desc, panel_desc)
         }
         Mode::Editing => "Ctrl+S: save | Esc: cancel".to_string(),
     };
@@ -1360,3 +2430,81 @@ fn ui(f: &mut ratatui::Frame, app: &App) {
 
     f.render_widget(footer, main_chunks[1]); // Render footer in the bottom chunk
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_save_conflict() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "Write.\n")?;
+        let mut app = App::new(vault.clone(), TuiOptions::default())?;
+
+        // Someone else stores v2 while the loaded v1 is being edited
+        app.start_editing();
+        vault.update("writer", "Write well.\n", None)?;
+        app.edit_content = "Write briefly.\n".to_string();
+        app.save_content()?;
+        assert_eq!(app.save_conflict.as_ref().map(|conflict| conflict.latest), Some(2));
+        assert_eq!(vault.history("writer")?.len(), 2);
+
+        app.toggle_conflict_diff()?;
+        let diff = app.save_conflict.as_ref().and_then(|conflict| conflict.diff.clone());
+        let changes: Vec<String> = diff.unwrap()[0]
+            .changes
+            .iter()
+            .map(|change| change.text.clone())
+            .collect();
+        assert_eq!(changes, ["Write well.", "Write briefly."]);
+
+        // Cancelling keeps the edit, and saving it again asks again
+        app.cancel_conflict();
+        assert!(matches!(app.mode, Mode::Editing));
+        assert_eq!(app.edit_content, "Write briefly.\n");
+        app.save_content()?;
+        assert!(app.save_conflict.is_some());
+
+        app.overwrite_conflict()?;
+        assert!(app.save_conflict.is_none());
+        assert_eq!(vault.get("writer", VersionSelector::Latest)?, "Write briefly.\n");
+        assert_eq!(vault.history("writer")?.len(), 3);
+
+        Ok(())
+    }
+    #[test]
+    fn test_comments_popup() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?.with_author(Some("alice".to_string()));
+        vault.add("writer", "Write.\n")?;
+        vault.comment("writer", 1, "bob", "Too short")?;
+        let mut app = App::new(vault.clone(), TuiOptions::default())?;
+
+        app.open_comments()?;
+        app.start_comment(true);
+        if let Some((_, text)) = app.comments_popup.as_mut().and_then(|p| p.draft.as_mut()) {
+            text.push_str("Agreed");
+        }
+        app.post_comment()?;
+        assert!(!app.is_writing_comment());
+        let popup = app.comments_popup.as_ref().unwrap();
+        assert_eq!(popup.selected_comment().map(|c| c.reply_to), Some(Some(1)));
+
+        let comments = vault.comments("writer", 1)?;
+        assert_eq!(comments.len(), 2);
+        assert_eq!((comments[1].author.as_str(), comments[1].text.as_str()), ("alice", "Agreed"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chat_lines() -> Result<()> {
+        let yaml = "- {role: system, content: Be kind.}\n- {role: user, content: Hi}";
+        let chat = ChatPrompt::parse(yaml)?;
+        let lines: Vec<String> = chat_lines(&chat).iter().map(|line| line.to_string()).collect();
+        assert_eq!(lines, ["── [1] system ──", "Be kind.", "", "── [2] user ──", "Hi"]);
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
+use crate::history::parse_time;
+use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::path::{Component, PathBuf};
 
 /// Metadata for a prompt version
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -12,6 +15,27 @@ pub struct VersionMeta {
     pub object_hash: String,
     pub snapshot: bool,
     pub tags: Vec<String>,
+    /// Who stored the version, when known; versions of older releases have none
+    #[serde(default)]
+    pub author: Option<String>,
+    /// Whether the version passed the validators that ran before it was stored; none
+    /// when no validator ran
+    #[serde(default)]
+    pub validated: Option<bool>,
+}
+
+/// [`VersionMeta`] as older releases stored it, before versions had an author or a
+/// validation result
+#[derive(Deserialize)]
+struct VersionMetaV1 {
+    key: String,
+    version: u64,
+    timestamp: DateTime<Utc>,
+    parent: Option<u64>,
+    message: Option<String>,
+    object_hash: String,
+    snapshot: bool,
+    tags: Vec<String>,
 }
 
 impl VersionMeta {
@@ -29,23 +53,121 @@ impl VersionMeta {
             object_hash,
             snapshot: true, // Initially all versions are snapshots
             tags,
+            author: None,
+            validated: None,
+        }
+    }
+
+    /// Decode a stored version record, including those of older releases
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self> {
+        if let Ok(meta) = bincode::deserialize(bytes) {
+            return Ok(meta);
         }
+        let old: VersionMetaV1 = bincode::deserialize(bytes)?;
+        Ok(VersionMeta {
+            key: old.key,
+            version: old.version,
+            timestamp: old.timestamp,
+            parent: old.parent,
+            message: old.message,
+            object_hash: old.object_hash,
+            snapshot: old.snapshot,
+            tags: old.tags,
+            author: None,
+            validated: None,
+        })
+    }
+}
+
+/// Content of a prompt version together with the version it resolved to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VersionedPrompt {
+    pub version: u64,
+    pub content: String,
+}
+
+impl VersionedPrompt {
+    /// The version as an HTTP entity tag (`"3"`); stored versions never change, so it
+    /// identifies the content
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
     }
 }
 
 /// Calculate a hash for the content to detect changes
-fn calculate_hash(content: &str) -> String {
+pub(crate) fn calculate_hash(content: &str) -> String {
     let hash = blake3::hash(content.as_bytes());
     format!("{}", hash)
 }
 
+/// Relative path of the file of `key` when prompts are written out as files, e.g.
+/// `agents/writer.txt`; fails for keys that would point outside the directory, such as
+/// `../writer` or `/etc/writer`
+pub(crate) fn key_file_path(key: &str, extension: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(format!("{}.{}", key, extension));
+    let normal = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if key.is_empty() || !normal {
+        return Err(anyhow::anyhow!(
+            "Key '{}' cannot be written as a file: it is not a relative path without '..'",
+            key
+        ));
+    }
+    Ok(path)
+}
+
 /// Selector for getting specific versions of prompts
-#[derive(Debug, Clone)]
-pub enum VersionSelector<'a> {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionSelector {
     Latest,
     Version(u64),
-    Tag(&'a str),
+    Tag(String),
     Time(DateTime<Utc>),
+    /// Route `percent`% of callers (bucketed by `request_id`) to the `candidate` tag
+    /// and everyone else to the `stable` tag
+    Canary {
+        stable: String,
+        candidate: String,
+        percent: u8,
+        request_id: String,
+    },
+    /// The version with the highest score for a metric, see [`crate::score`]
+    BestBy(String),
+}
+
+impl VersionSelector {
+    /// Select the version carrying `tag`
+    pub fn tag(tag: impl Into<String>) -> Self {
+        VersionSelector::Tag(tag.into())
+    }
+
+    /// Parse a textual selector: `latest`, a version as `3` or `v3`, a point in time as
+    /// `@2024-05-01T12:00` (UTC), `@2024-05-01` or `@-7d` (seven days ago), the best
+    /// scored version as `best:accuracy`, or a tag name
+    pub fn parse(selector: &str) -> Result<Self> {
+        if selector == "latest" {
+            return Ok(VersionSelector::Latest);
+        }
+        if let Some(metric) = selector.strip_prefix("best:") {
+            if metric.is_empty() {
+                return Err(anyhow::anyhow!("Missing metric in '{}'", selector));
+            }
+            return Ok(VersionSelector::BestBy(metric.to_string()));
+        }
+        let number = selector.strip_prefix('v').unwrap_or(selector);
+        if let Ok(version) = number.parse::<u64>() {
+            return Ok(VersionSelector::Version(version));
+        }
+        if let Some(time) = selector.strip_prefix('@') {
+            let time = time.strip_prefix('-').unwrap_or(time);
+            return Ok(VersionSelector::Time(parse_time(time, Utc::now(), false)?));
+        }
+        if selector.is_empty() {
+            return Err(anyhow::anyhow!("Empty version selector"));
+        }
+        Ok(VersionSelector::tag(selector))
+    }
 }
 
 #[cfg(test)]
@@ -67,6 +189,27 @@ mod tests {
         assert_eq!(meta.message, Some("initial version".to_string()));
         assert!(!meta.object_hash.is_empty());
         assert_eq!(meta.tags.len(), 0);
+
+        // Records written before versions had an author or were validated still decode
+        let mut authored = meta.clone();
+        authored.author = Some("alice".to_string());
+        authored.validated = Some(false);
+        let decoded = VersionMeta::decode(&bincode::serialize(&authored).unwrap()).unwrap();
+        assert_eq!(decoded.author.as_deref(), Some("alice"));
+        assert_eq!(decoded.validated, Some(false));
+        let old = bincode::serialize(&(
+            &meta.key,
+            meta.version,
+            meta.timestamp,
+            meta.parent,
+            &meta.message,
+            &meta.object_hash,
+            meta.snapshot,
+            &meta.tags,
+        ))
+        .unwrap();
+        let decoded = VersionMeta::decode(&old).unwrap();
+        assert_eq!((decoded.version, decoded.author, decoded.validated), (1, None, None));
     }
 
     #[test]
@@ -78,4 +221,26 @@ mod tests {
         assert_eq!(calculate_hash(content1), calculate_hash(content2));
         assert_ne!(calculate_hash(content1), calculate_hash(content3));
     }
+
+    #[test]
+    fn test_selector_parse() -> Result<()> {
+        assert_eq!(VersionSelector::parse("latest")?, VersionSelector::Latest);
+        assert_eq!(VersionSelector::parse("3")?, VersionSelector::Version(3));
+        assert_eq!(VersionSelector::parse("v12")?, VersionSelector::Version(12));
+        assert_eq!(VersionSelector::parse("stable")?, VersionSelector::tag("stable"));
+        assert_eq!(VersionSelector::parse("v2-beta")?, VersionSelector::tag("v2-beta"));
+
+        let noon = VersionSelector::parse("@2024-05-01T12:00")?;
+        let expected = "2024-05-01T12:00:00Z".parse::<DateTime<Utc>>()?;
+        assert_eq!(noon, VersionSelector::Time(expected));
+        let VersionSelector::Time(week_ago) = VersionSelector::parse("@-7d")? else {
+            panic!("not a time selector");
+        };
+        let elapsed = Utc::now() - week_ago;
+        assert!(elapsed >= chrono::Duration::days(7) && elapsed < chrono::Duration::days(8));
+
+        assert!(VersionSelector::parse("@last tuesday").is_err());
+        assert!(VersionSelector::parse("").is_err());
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -1,8 +1,78 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Get the default vault path: ~/.promptpro/default_vault
 pub fn default_vault_path() -> Result<PathBuf> {
     let home_dir = std::env::var("HOME")?;
     Ok(PathBuf::from(home_dir).join(".promptpro").join("default_vault"))
-}
\ No newline at end of file
+}
+
+/// Vault directory that overrides project discovery and the default vault
+pub const VAULT_ENV: &str = "PROMPTPRO_VAULT";
+
+/// Vault directory set through `PROMPTPRO_VAULT`, if any
+pub fn vault_path_from_env() -> Option<PathBuf> {
+    std::env::var_os(VAULT_ENV)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Vault directory of the selected profile, if it sets one
+pub fn profile_vault_path() -> Result<Option<PathBuf>> {
+    match crate::profile::active_profile()? {
+        Some(profile) => profile.vault_path(),
+        None => Ok(None),
+    }
+}
+
+/// Directory marking a project-local vault, discovered like `.git`
+pub const PROJECT_DIR: &str = ".promptpro";
+
+/// Vault database inside a project's `.promptpro/` directory
+pub fn project_vault_path(root: &Path) -> PathBuf {
+    root.join(PROJECT_DIR).join("vault")
+}
+
+/// Walk up from `start` looking for a project-local vault
+pub fn find_project_vault(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(project_vault_path)
+        .find(|path| path.is_dir())
+}
+
+/// Vault the CLI and TUI work on: `PROMPTPRO_VAULT` when set, else the vault of the
+/// selected profile, else the nearest project vault, else the default vault
+pub fn current_vault_path() -> Result<PathBuf> {
+    if let Some(path) = vault_path_from_env() {
+        return Ok(path);
+    }
+    if let Some(path) = profile_vault_path()? {
+        return Ok(path);
+    }
+    let cwd = std::env::current_dir()?;
+    match find_project_vault(&cwd) {
+        Some(path) => Ok(path),
+        None => default_vault_path(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_project_vault() -> Result<()> {
+        let dir = tempdir()?;
+        let nested = dir.path().join("services").join("api");
+        std::fs::create_dir_all(&nested)?;
+        assert_eq!(find_project_vault(&nested), None);
+
+        let vault = project_vault_path(dir.path());
+        std::fs::create_dir_all(&vault)?;
+        assert_eq!(find_project_vault(&nested), Some(vault));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+//! Checks run on the content of a new version before `add` and `update` store it, e.g.
+//! to reject prompts containing secrets or banned phrases.
+//!
+//! Programs register a [`Validator`] with [`PromptVault::add_validator`]; the CLI, the
+//! TUI and `serve` run the `[[validators]]` of `~/.promptpro/config.toml`:
+//!
+//! ```toml
+//! [[validators]]
+//! name = "no-secrets"
+//! command = "gitleaks stdin --no-banner"   # content on stdin, fails to reject
+//!
+//! [[validators]]
+//! name = "style"
+//! command = "./scripts/lint-prompt.sh"
+//! warn = true                              # store anyway, recording the failure
+//! ```
+//!
+//! Each stored version records in [`VersionMeta::validated`](crate::VersionMeta)
+//! whether it passed the validators that ran on it.
+
+use crate::profile::ProfileConfig;
+use crate::storage::PromptVault;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, RwLock};
+
+/// The new version a validator checks
+#[derive(Debug, Clone, Copy)]
+pub struct ValidationRequest<'a> {
+    pub key: &'a str,
+    pub content: &'a str,
+}
+
+/// A version was rejected by a validator
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Validator '{validator}' rejected '{key}': {reason}")]
+pub struct ValidationError {
+    pub key: String,
+    pub validator: String,
+    pub reason: String,
+}
+
+type Check = Arc<dyn Fn(&ValidationRequest) -> Result<()> + Send + Sync>;
+
+/// A check of new versions
+#[derive(Clone)]
+pub struct Validator {
+    name: String,
+    check: Check,
+    warn_only: bool,
+}
+
+impl Validator {
+    /// A validator rejecting the versions for which `check` fails, with its error as
+    /// the reason
+    pub fn new<F>(name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn(&ValidationRequest) -> Result<()> + Send + Sync + 'static,
+    {
+        Validator {
+            name: name.into(),
+            check: Arc::new(check),
+            warn_only: false,
+        }
+    }
+
+    /// A validator running `command` with the shell, the content on stdin and the key
+    /// in `PROMPTPRO_KEY`; a failing exit rejects the version with the command's output
+    pub fn command(name: impl Into<String>, command: impl Into<String>) -> Self {
+        let command = command.into();
+        Self::new(name, move |request| run_command(&command, request))
+    }
+
+    /// Store versions this validator fails anyway, warning and recording the failure
+    pub fn warn_only(mut self) -> Self {
+        self.warn_only = true;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+fn run_command(command: &str, request: &ValidationRequest) -> Result<()> {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .env("PROMPTPRO_KEY", request.key)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("cannot run `{}`: {}", command, e))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // Commands may exit without reading all of the content
+    let _ = stdin.write_all(request.content.as_bytes());
+    drop(stdin);
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let reason = [stderr.trim(), stdout.trim()]
+        .into_iter()
+        .find(|text| !text.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("`{}` failed with {}", command, output.status));
+    Err(anyhow::anyhow!(reason))
+}
+
+/// One `[[validators]]` table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidatorConfig {
+    pub name: String,
+    /// Shell command getting the content on stdin
+    pub command: String,
+    /// Store versions failing the command anyway
+    #[serde(default)]
+    pub warn: bool,
+}
+
+impl ValidatorConfig {
+    pub fn validator(&self) -> Validator {
+        let validator = Validator::command(&self.name, &self.command);
+        if self.warn {
+            validator.warn_only()
+        } else {
+            validator
+        }
+    }
+}
+
+/// The validators of the config file
+pub fn configured_validators() -> Result<Vec<Validator>> {
+    let config = ProfileConfig::load(&ProfileConfig::default_path()?)?;
+    Ok(config.validators.iter().map(ValidatorConfig::validator).collect())
+}
+
+/// Validators of a vault, shared by its clones
+#[derive(Default)]
+pub(crate) struct Validators {
+    validators: RwLock<Vec<Validator>>,
+}
+
+impl PromptVault {
+    /// Run `validator` on the content of every version added or updated through this
+    /// vault or one of its clones from now on, before it is stored
+    pub fn add_validator(&self, validator: Validator) {
+        self.validators().validators.write().unwrap().push(validator);
+    }
+
+    /// Run the validators on a new version of `key`: `None` when there are none,
+    /// otherwise whether every one passed. Fails with a [`ValidationError`] when a
+    /// validator that is not warn-only rejects the version.
+    pub(crate) fn validate(&self, key: &str, content: &str) -> Result<Option<bool>> {
+        let validators = self.validators().validators.read().unwrap().clone();
+        if validators.is_empty() {
+            return Ok(None);
+        }
+        let request = ValidationRequest { key, content };
+        let mut passed = true;
+        for validator in validators {
+            let Err(e) = (validator.check)(&request) else {
+                continue;
+            };
+            let error = ValidationError {
+                key: key.to_string(),
+                validator: validator.name.clone(),
+                reason: format!("{:#}", e),
+            };
+            if !validator.warn_only {
+                return Err(error.into());
+            }
+            eprintln!("⚠️ {}; storing it anyway", error);
+            passed = false;
+        }
+        Ok(Some(passed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_validators() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "Before validators.")?;
+        assert_eq!(vault.history("writer")?[0].validated, None);
+
+        vault.clone().add_validator(Validator::new("no-secrets", |request| {
+            match request.content.contains("sk-") {
+                true => Err(anyhow::anyhow!("contains an API key")),
+                false => Ok(()),
+            }
+        }));
+        let error = vault.update("writer", "Use sk-123.", None).unwrap_err();
+        let rejected = error.downcast_ref::<ValidationError>().unwrap();
+        assert_eq!(rejected.reason, "contains an API key");
+        assert!(vault.add("leaky", "sk-456").is_err());
+        assert_eq!(vault.list_keys()?, ["writer"]);
+
+        vault.update("writer", "You write.", None)?;
+        assert_eq!(vault.history("writer")?[1].validated, Some(true));
+
+        // Warn-only validators record the failure; commands get the content on stdin
+        let config = ProfileConfig::parse(
+            "[[validators]]\nname = \"short\"\ncommand = \"test $(wc -c) -lt 20\"\nwarn = true",
+        )?;
+        vault.add_validator(config.validators[0].validator());
+        vault.update("writer", "You write long and winding posts.", None)?;
+        vault.add("reader", "You read.")?;
+        assert_eq!(vault.history("writer")?[2].validated, Some(false));
+        assert_eq!(vault.history("reader")?[0].validated, Some(true));
+        assert_eq!(
+            vault.get("writer", VersionSelector::Latest)?,
+            "You write long and winding posts."
+        );
+
+        Ok(())
+    }
+}
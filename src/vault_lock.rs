@@ -0,0 +1,237 @@
+//! Coordinating the processes that open the same vault.
+//!
+//! sled lets one process at a time hold a vault. The process holding it records its
+//! PID in `promptpro.pid` inside the vault directory, so another process fails with a
+//! [`VaultInUse`] naming it while it runs, waits for it ([`LockPolicy::Wait`]), or reads from a copy
+//! of the vault instead ([`LockPolicy::Snapshot`]).
+
+use crate::storage::PromptVault;
+use anyhow::Result;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// File in the vault directory holding the PID of the process that has it open
+pub(crate) const HOLDER_FILE: &str = "promptpro.pid";
+
+/// How often a waiting process tries to open the vault again
+const RETRY_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long sled may take to let go of a vault this process dropped; its background
+/// flusher keeps the lock for a moment after the last handle is gone
+const RELEASE_GRACE: Duration = Duration::from_secs(1);
+
+/// The vault is held by another process, such as an open TUI
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub struct VaultInUse {
+    pub path: PathBuf,
+    /// Process holding the vault, when it recorded itself and is still running
+    pub pid: Option<u32>,
+}
+
+impl fmt::Display for VaultInUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pid {
+            Some(pid) => write!(f, "Vault {} is in use by PID {}", self.path.display(), pid)?,
+            None => write!(f, "Vault {} is in use by another process", self.path.display())?,
+        }
+        f.write_str(" (such as an open TUI); wait for it with --wait or read a copy with ")?;
+        f.write_str("--snapshot")
+    }
+}
+
+/// What [`PromptVault::open_with_policy`] does when another process holds the vault
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LockPolicy {
+    /// Fail with [`VaultInUse`]
+    #[default]
+    Fail,
+    /// Wait until the vault is released, failing after the timeout when one is given
+    Wait(Option<Duration>),
+    /// Open a copy of the vault as it is on disk; changes to the copy are discarded
+    Snapshot,
+}
+
+/// Open the sled database at `path` with `open` and record this process as its
+/// holder, failing with [`VaultInUse`] when another holder has it
+pub(crate) fn open_db(
+    path: &Path,
+    open: impl Fn() -> sled::Result<sled::Db>,
+) -> Result<sled::Db> {
+    let started = Instant::now();
+    loop {
+        let error = match open() {
+            Ok(db) => {
+                // Only used to name the holder in errors, so a read-only directory is
+                // no failure
+                let _ = std::fs::write(path.join(HOLDER_FILE), std::process::id().to_string());
+                return Ok(db);
+            }
+            Err(error) => error,
+        };
+        if !error.to_string().contains("could not acquire lock") {
+            return Err(error.into());
+        }
+        let pid = std::fs::read_to_string(path.join(HOLDER_FILE))
+            .ok()
+            .and_then(|pid| pid.trim().parse().ok())
+            // The file outlives its process; an exited holder is not named
+            .filter(|&pid| is_running(pid));
+        // A vault this process just dropped is released shortly
+        if pid == Some(std::process::id()) && started.elapsed() < RELEASE_GRACE {
+            std::thread::sleep(Duration::from_millis(10));
+            continue;
+        }
+        return Err(VaultInUse {
+            path: path.to_path_buf(),
+            pid,
+        }
+        .into());
+    }
+}
+
+/// Whether the process `pid` still exists
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 sends nothing, it only checks the process exists; EPERM means it
+    // exists but belongs to another user
+    pid > 0
+        && (unsafe { libc::kill(pid, 0) } == 0
+            || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM))
+}
+
+/// Whether the process `pid` still exists; assumed so where it cannot be checked
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    true
+}
+
+impl PromptVault {
+    /// Open a vault, dealing with another process holding it as `policy` says
+    pub fn open_with_policy<P: AsRef<Path>>(path: P, policy: LockPolicy) -> Result<Self> {
+        Self::open_with_policy_and_cache(path, policy, None)
+    }
+
+    /// [`PromptVault::open_with_policy`] with a page cache of `cache_capacity` bytes
+    /// instead of sled's default, as [`PromptVault::open_with_cache_capacity`] opens
+    /// vaults
+    pub fn open_with_policy_and_cache<P: AsRef<Path>>(
+        path: P,
+        policy: LockPolicy,
+        cache_capacity: Option<u64>,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let started = Instant::now();
+        let mut announced = false;
+        loop {
+            let opened = match cache_capacity {
+                Some(capacity) => Self::open_with_cache_capacity(path, capacity),
+                None => Self::open(path),
+            };
+            let error = match opened {
+                Ok(vault) => return Ok(vault),
+                Err(error) => error,
+            };
+            let Some(in_use) = error.downcast_ref::<VaultInUse>() else {
+                return Err(error);
+            };
+            match policy {
+                LockPolicy::Fail => return Err(error),
+                LockPolicy::Snapshot => return Self::open_snapshot(path),
+                LockPolicy::Wait(timeout) => {
+                    if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+                        return Err(error);
+                    }
+                    if !announced {
+                        let holder = in_use.pid.map(|pid| format!(" by PID {}", pid));
+                        eprintln!(
+                            "⏳ Waiting for the vault to be released{}...",
+                            holder.unwrap_or_default()
+                        );
+                        announced = true;
+                    }
+                    std::thread::sleep(RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+
+    /// Open a copy of the vault at `path` as it is on disk, e.g. to read while another
+    /// process holds it. The copy is removed when the vault is dropped, so changes to
+    /// it are lost; versions the holder has not flushed yet are not included.
+    pub fn open_snapshot<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let copy = tempfile::Builder::new()
+            .prefix("promptpro-snapshot")
+            .tempdir()?
+            .keep();
+        copy_dir(path.as_ref(), &copy)?;
+        let db = sled::Config::new().path(&copy).temporary(true).open()?;
+        Self::from_db(db)
+    }
+}
+
+/// Copy the files of a vault directory, except the holder's PID
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            copy_dir(&entry.path(), &target)?;
+        } else if entry.file_name() != HOLDER_FILE {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_vault_in_use() -> Result<()> {
+        let dir = tempdir()?;
+        let holder = PromptVault::open(dir.path())?;
+        holder.add("writer", "v1")?;
+        holder.db().flush()?;
+
+        let error = PromptVault::open(dir.path()).err().unwrap();
+        let in_use = error.downcast_ref::<VaultInUse>().unwrap();
+        assert_eq!(in_use.pid, Some(std::process::id()));
+        assert!(error.to_string().contains("is in use by PID"));
+
+        // A PID left behind by a process that exited is not named
+        #[cfg(unix)]
+        {
+            let mut exited = std::process::Command::new("true").spawn()?;
+            exited.wait()?;
+            std::fs::write(dir.path().join(HOLDER_FILE), exited.id().to_string())?;
+            let error = PromptVault::open(dir.path()).err().unwrap();
+            assert_eq!(error.downcast_ref::<VaultInUse>().unwrap().pid, None);
+            assert!(error.to_string().contains("in use by another process"));
+        }
+
+        // Readers can use a snapshot; waiting gives up after the timeout
+        let snapshot = PromptVault::open_with_policy(dir.path(), LockPolicy::Snapshot)?;
+        assert_eq!(snapshot.get("writer", VersionSelector::Latest)?, "v1");
+        let wait = LockPolicy::Wait(Some(Duration::from_millis(300)));
+        assert!(PromptVault::open_with_policy(dir.path(), wait).is_err());
+
+        // Waiting succeeds once the holder is gone
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            drop(holder);
+        });
+        let vault = PromptVault::open_with_policy(dir.path(), LockPolicy::Wait(None))?;
+        release.join().unwrap();
+        assert_eq!(vault.get("writer", VersionSelector::Latest)?, "v1");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+//! Integrity checks of a vault, for `promptpro fsck`.
+//!
+//! Every version is read back and its content re-hashed against its `object_hash`;
+//! parents of diff versions, tags, the key index and shared content are checked
+//! against the versions stored, and content, diffs and per-key records (metadata, notes,
+//! provenance, protection markers, canary counters) against the versions and keys they
+//! belong to. Dangling tags, index entries and unused entries can be repaired; corrupted
+//! versions and missing parents are only reported, since the vault no longer holds
+//! their content.
+
+use crate::storage::PromptVault;
+use crate::types::{calculate_hash, VersionSelector};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A problem found by [`PromptVault::verify`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Issue {
+    /// The version reads back with content that does not match its hash
+    HashMismatch { key: String, version: u64 },
+    /// The version cannot be read back at all
+    Unreadable {
+        key: String,
+        version: u64,
+        error: String,
+    },
+    /// A diff version whose parent version is missing
+    MissingParent {
+        key: String,
+        version: u64,
+        parent: u64,
+    },
+    /// A tag pointing at a missing version
+    DanglingTag { key: String, tag: String, version: u64 },
+    /// A key with versions that the key index does not list
+    UnindexedKey { key: String },
+    /// A key index entry without versions
+    StaleIndexEntry { key: String },
+    /// Content, a diff, shared content or a per-key record no version uses, such as
+    /// what an interrupted delete leaves behind
+    OrphanedEntry { entry: String },
+    /// Shared content whose reference count disagrees with the versions using it
+    WrongRefCount { hash: String, references: u64 },
+}
+
+impl Issue {
+    /// Whether `verify` with `repair` fixes this issue
+    pub fn repairable(&self) -> bool {
+        !matches!(
+            self,
+            Issue::HashMismatch { .. } | Issue::Unreadable { .. } | Issue::MissingParent { .. }
+        )
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::HashMismatch { key, version } => {
+                write!(f, "{} v{}: content does not match its hash", key, version)
+            }
+            Issue::Unreadable {
+                key,
+                version,
+                error,
+            } => write!(f, "{} v{}: cannot be read: {}", key, version, error),
+            Issue::MissingParent {
+                key,
+                version,
+                parent,
+            } => write!(f, "{} v{}: parent v{} is missing", key, version, parent),
+            Issue::DanglingTag { key, tag, version } => {
+                write!(f, "{}: tag '{}' points at missing v{}", key, tag, version)
+            }
+            Issue::UnindexedKey { key } => write!(f, "{}: missing from the key index", key),
+            Issue::StaleIndexEntry { key } => {
+                write!(f, "{}: listed in the key index without versions", key)
+            }
+            Issue::OrphanedEntry { entry } => write!(f, "{}: used by no version", entry),
+            Issue::WrongRefCount { hash, references } => write!(
+                f,
+                "object {}: reference count differs from its {} reference(s)",
+                hash, references
+            ),
+        }
+    }
+}
+
+/// What [`PromptVault::verify`] found
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    /// Versions read back and re-hashed
+    pub versions: usize,
+    pub issues: Vec<Issue>,
+    /// Whether the repairable issues were repaired
+    pub repaired: bool,
+}
+
+impl VerifyReport {
+    /// Issues left after the check, i.e. all of them unless they were repaired
+    pub fn outstanding(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(move |issue| !(self.repaired && issue.repairable()))
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.outstanding().next().is_none()
+    }
+}
+
+impl PromptVault {
+    /// Check the integrity of the vault (see the [module docs](self)), repairing what
+    /// can be repaired when `repair` is set. The content of password-protected prompts
+    /// is encrypted and only its presence is checked.
+    pub fn verify(&self, repair: bool) -> Result<VerifyReport> {
+        let mut report = VerifyReport {
+            repaired: repair,
+            ..VerifyReport::default()
+        };
+
+        let mut stored = BTreeSet::new();
+        for entry in self.db().scan_prefix(b"version:").keys() {
+            let entry = entry?;
+            let entry = String::from_utf8_lossy(&entry["version:".len()..]).into_owned();
+            if let Some((key, _)) = entry.rsplit_once(':') {
+                stored.insert(key.to_string());
+            }
+        }
+
+        for key in &stored {
+            let sensitive = self.is_sensitive(key)?;
+            for meta in self.history(key)? {
+                if let Some(parent) = meta.parent {
+                    if self.get_version_meta(key, parent)?.is_none() {
+                        report.issues.push(Issue::MissingParent {
+                            key: key.clone(),
+                            version: meta.version,
+                            parent,
+                        });
+                        continue;
+                    }
+                }
+                if sensitive {
+                    let present = self.stored_snapshot(&meta)?.is_some();
+                    if !present {
+                        report.issues.push(Issue::Unreadable {
+                            key: key.clone(),
+                            version: meta.version,
+                            error: "content is missing".to_string(),
+                        });
+                    }
+                    continue;
+                }
+                report.versions += 1;
+                match self.get(key, VersionSelector::Version(meta.version)) {
+                    Ok(content) if calculate_hash(&content) == meta.object_hash => {}
+                    Ok(_) => report.issues.push(Issue::HashMismatch {
+                        key: key.clone(),
+                        version: meta.version,
+                    }),
+                    Err(e) => report.issues.push(Issue::Unreadable {
+                        key: key.clone(),
+                        version: meta.version,
+                        error: e.to_string(),
+                    }),
+                }
+            }
+        }
+
+        let mut dangling = Vec::new();
+        for entry in self.db().scan_prefix(b"tag:") {
+            let (entry, value) = entry?;
+            let name = String::from_utf8_lossy(&entry["tag:".len()..]).into_owned();
+            let (Some((key, tag)), Ok(bytes)) = (name.rsplit_once(':'), value.as_ref().try_into())
+            else {
+                report.issues.push(Issue::OrphanedEntry {
+                    entry: format!("tag:{}", name),
+                });
+                dangling.push(entry);
+                continue;
+            };
+            let version = u64::from_le_bytes(bytes);
+            if self.get_version_meta(key, version)?.is_none() {
+                report.issues.push(Issue::DanglingTag {
+                    key: key.to_string(),
+                    tag: tag.to_string(),
+                    version,
+                });
+                dangling.push(entry);
+            }
+        }
+
+        let indexed: BTreeSet<String> = self.list_keys()?.into_iter().collect();
+        for key in stored.difference(&indexed) {
+            report.issues.push(Issue::UnindexedKey { key: key.clone() });
+        }
+        for key in indexed.difference(&stored) {
+            report.issues.push(Issue::StaleIndexEntry { key: key.clone() });
+        }
+
+        let orphans = self.find_orphans()?;
+        for entry in &orphans.entries {
+            report.issues.push(Issue::OrphanedEntry {
+                entry: entry.clone(),
+            });
+        }
+        for (hash, references) in &orphans.refcounts {
+            report.issues.push(Issue::WrongRefCount {
+                hash: hash.clone(),
+                references: *references,
+            });
+        }
+
+        if repair {
+            for entry in dangling {
+                self.db().remove(entry)?;
+            }
+            for issue in &report.issues {
+                match issue {
+                    Issue::DanglingTag { key, tag, .. } => {
+                        self.index_cache().forget(key);
+                        // 'dev' always follows the latest version
+                        if let (true, Some(latest)) =
+                            (tag == "dev", self.get_latest_version_number(key)?)
+                        {
+                            self.tag(key, tag, latest)?;
+                        }
+                    }
+                    Issue::UnindexedKey { key } => self.index_key(key)?,
+                    Issue::StaleIndexEntry { key } => self.unindex_key(key)?,
+                    _ => {}
+                }
+            }
+            self.remove_orphans(&orphans)?;
+            self.db().flush()?;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_verify() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("writer", "v1\n")?;
+        vault.update("writer", "v2\n", None)?;
+        vault.tag("writer", "prod", 2)?;
+        vault.add("planner", "plan\n")?;
+        vault.update("planner", "plan more\n", None)?;
+        assert!(vault.verify(false)?.is_clean());
+
+        // A lost parent, a dangling tag, a stale index entry, and an orphaned diff and
+        // protection marker as left by an interrupted delete
+        let mut meta = vault.get_version_meta("planner", 2)?.unwrap();
+        meta.parent = Some(5);
+        vault.update_version_meta(&meta)?;
+        vault.db().insert("tag:writer:stable", &9u64.to_le_bytes())?;
+        vault.index_key("ghost")?;
+        vault.db().insert("diff:ghost:2", b"stale".as_slice())?;
+        vault.db().insert("sensitive:ghost", b"stale".as_slice())?;
+
+        let report = vault.verify(false)?;
+        assert_eq!(report.versions, 3);
+        let issues: Vec<String> = report.issues.iter().map(|issue| issue.to_string()).collect();
+        assert_eq!(
+            issues,
+            [
+                "planner v2: parent v5 is missing",
+                "writer: tag 'stable' points at missing v9",
+                "ghost: listed in the key index without versions",
+                "diff:ghost:2: used by no version",
+                "sensitive:ghost: used by no version",
+            ]
+        );
+        assert!(!report.is_clean());
+
+        let report = vault.verify(true)?;
+        let outstanding: Vec<&Issue> = report.outstanding().collect();
+        assert_eq!(outstanding.len(), 1);
+        assert!(matches!(outstanding[0], Issue::MissingParent { .. }));
+        assert_eq!(vault.verify(false)?.issues.len(), 1);
+        assert_eq!(vault.list_keys()?, ["planner", "writer"]);
+        assert_eq!(vault.get("writer", VersionSelector::tag("prod"))?, "v2\n");
+        assert!(!vault.is_sensitive("ghost")?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+//! Keeping a directory of prompt files and the vault in sync while the files are being
+//! edited, for `promptpro watch`.
+
+use crate::import_dir::{is_prompt_file, FileChange};
+use crate::storage::PromptVault;
+use anyhow::Result;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use similar::{ChangeTag, TextDiff};
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// How long a burst of events (editors often write a file several times per save)
+/// must go quiet before the files are synced
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+/// A prompt file that was synced into the vault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedChange {
+    pub key: String,
+    pub change: FileChange,
+    /// Path of the file relative to the watched directory
+    pub file: String,
+}
+
+/// Watches a directory for saved `.txt`/`.md` files (see [`PromptVault::watch_dir`])
+pub struct DirWatcher {
+    dir: PathBuf,
+    events: Receiver<notify::Result<notify::Event>>,
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+}
+
+impl PromptVault {
+    /// Start watching `dir` recursively; [`DirWatcher::wait`] syncs the files saved
+    /// since into this vault
+    pub fn watch_dir<P: AsRef<Path>>(&self, dir: P) -> Result<DirWatcher> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Err(anyhow::anyhow!("{:?} is not a directory", dir));
+        }
+        // Events carry absolute paths
+        let dir = dir.canonicalize()?;
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(&dir, RecursiveMode::Recursive)?;
+
+        Ok(DirWatcher {
+            dir,
+            events,
+            _watcher: watcher,
+        })
+    }
+}
+
+impl DirWatcher {
+    /// Wait up to `timeout` for prompt files to be saved, then sync them into `vault`.
+    ///
+    /// Each changed file becomes a new version with a commit-style message such as
+    /// `Update agents/planner.md (+2 -1 lines)`. Deleted files are ignored, so removing
+    /// a file never removes its prompt. Returns no changes when the timeout expires.
+    pub fn wait(&self, vault: &PromptVault, timeout: Duration) -> Result<Vec<WatchedChange>> {
+        let mut paths = BTreeSet::new();
+        let mut next = self.events.recv_timeout(timeout);
+        loop {
+            match next {
+                Ok(event) => {
+                    let event = event?;
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        paths.extend(event.paths);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(anyhow::anyhow!("Stopped watching {:?}", self.dir))
+                }
+            }
+            next = self.events.recv_timeout(SETTLE_DELAY);
+        }
+
+        let mut changes = Vec::new();
+        for path in paths {
+            if !self.is_watched_file(&path) {
+                continue;
+            }
+            let file = path.strip_prefix(&self.dir)?.display().to_string();
+            let message = |old: &str, new: &str| change_message(&file, old, new);
+            if let Some((key, change)) = vault.sync_file(&self.dir, &path, true, message)? {
+                if change != FileChange::Unchanged {
+                    changes.push(WatchedChange { key, change, file });
+                }
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// An existing prompt file under the directory, outside hidden directories
+    fn is_watched_file(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.dir) else {
+            return false;
+        };
+        let hidden = relative.components().any(|component| match component {
+            Component::Normal(name) => name.to_str().is_some_and(|name| name.starts_with('.')),
+            _ => false,
+        });
+        !hidden && path.is_file() && is_prompt_file(path)
+    }
+}
+
+/// `Update <file> (+N -M lines)`
+fn change_message(file: &str, old: &str, new: &str) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let (mut inserted, mut deleted) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => inserted += 1,
+            ChangeTag::Delete => deleted += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    format!("Update {} (+{} -{} lines)", file, inserted, deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::VersionSelector;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_watch_dir() -> Result<()> {
+        let dir = tempdir()?;
+        let prompts = dir.path().join("prompts");
+        std::fs::create_dir_all(prompts.join("agents"))?;
+        std::fs::write(prompts.join("agents/planner.md"), "Plan\n")?;
+
+        let vault = PromptVault::open(dir.path().join("vault"))?;
+        vault.import_dir(&prompts)?;
+        let watcher = vault.watch_dir(&prompts)?;
+
+        std::fs::write(prompts.join("agents/planner.md"), "Plan\nthe work\n")?;
+        std::fs::write(prompts.join("agents/.planner.md.swp"), "swap")?;
+        let mut changes = Vec::new();
+        for _ in 0..50 {
+            changes = watcher.wait(&vault, Duration::from_millis(100))?;
+            if !changes.is_empty() {
+                break;
+            }
+        }
+        assert_eq!(
+            changes,
+            [WatchedChange {
+                key: "agents/planner".to_string(),
+                change: FileChange::Updated,
+                file: "agents/planner.md".to_string(),
+            }]
+        );
+        assert_eq!(vault.get("agents/planner", VersionSelector::Latest)?, "Plan\nthe work\n");
+        let history = vault.history("agents/planner")?;
+        assert_eq!(
+            history[1].message.as_deref(),
+            Some("Update agents/planner.md (+1 -0 lines)")
+        );
+        assert_eq!(history.len(), 2);
+
+        assert!(vault.watch_dir(prompts.join("missing")).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+//! Static JSON bundles of prompts for web apps, for `promptpro export --format web-bundle`.
+//!
+//! A bundle is one minified JSON object, `{"prompts":{key:content,...},"integrity":..}`,
+//! that a frontend can host on a CDN and fetch at startup. `integrity` is
+//! `blake3-<hex>` of the `prompts` object exactly as written, so clients can check
+//! they received the bundle that was exported.
+
+use crate::render::render_template;
+use crate::storage::PromptVault;
+use crate::types::VersionSelector;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+/// Which versions [`PromptVault::web_bundle`] bundles
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebBundleOptions {
+    /// Bundle the version of the first of these tags a prompt has, skipping prompts with
+    /// none of them; the latest version of every prompt when empty
+    pub tags: Vec<String>,
+    /// Fill in `{{name}}` placeholders with these values; others are left as they are
+    pub vars: HashMap<String, String>,
+}
+
+/// Prompt contents by key, with a hash to check them against
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebBundle {
+    pub prompts: BTreeMap<String, String>,
+    pub integrity: String,
+}
+
+impl WebBundle {
+    pub fn new(prompts: BTreeMap<String, String>) -> Result<Self> {
+        let integrity = integrity_of(&prompts)?;
+        Ok(WebBundle { prompts, integrity })
+    }
+
+    /// Whether `integrity` matches the prompts
+    pub fn verify(&self) -> Result<bool> {
+        Ok(integrity_of(&self.prompts)? == self.integrity)
+    }
+}
+
+/// `blake3-<hex>` of the minified JSON of `prompts`
+fn integrity_of(prompts: &BTreeMap<String, String>) -> Result<String> {
+    let json = serde_json::to_vec(prompts)?;
+    Ok(format!("blake3-{}", blake3::hash(&json)))
+}
+
+impl PromptVault {
+    /// Bundle the prompts `options` selects, e.g. the `stable` version of each.
+    /// Password-protected prompts are left out.
+    pub fn web_bundle(&self, options: &WebBundleOptions) -> Result<WebBundle> {
+        let mut prompts = BTreeMap::new();
+        for key in self.list_keys()? {
+            if self.is_sensitive(&key)? {
+                continue;
+            }
+            let Some(selector) = self.bundled_version(&key, &options.tags)? else {
+                continue;
+            };
+            let content = self.get(&key, selector)?;
+            prompts.insert(key, render_template(&content, &options.vars, false)?);
+        }
+        WebBundle::new(prompts)
+    }
+
+    /// Version of the first of `tags` that `key` has, or its latest version without tags
+    fn bundled_version(&self, key: &str, tags: &[String]) -> Result<Option<VersionSelector>> {
+        if tags.is_empty() {
+            return Ok(Some(VersionSelector::Latest));
+        }
+        for tag in tags {
+            if let Some(version) = self.get_version_by_tag(key, tag)? {
+                return Ok(Some(VersionSelector::Version(version)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Write a bundle as minified JSON; returns the number of prompts bundled
+    pub fn export_web_bundle<W: Write>(
+        &self,
+        mut writer: W,
+        options: &WebBundleOptions,
+    ) -> Result<usize> {
+        let bundle = self.web_bundle(options)?;
+        serde_json::to_writer(&mut writer, &bundle)?;
+        writer.flush()?;
+        Ok(bundle.prompts.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_web_bundle() -> Result<()> {
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        vault.add("greeting", "Hello {{name}}, welcome to {{product}}!")?;
+        vault.update("greeting", "Hi {{name}}!", None)?;
+        vault.tag("greeting", "stable", 1)?;
+        vault.add("farewell", "Bye.")?;
+        vault.tag("farewell", "beta", 1)?;
+        vault.add("draft", "Not released.")?;
+
+        let options = WebBundleOptions {
+            tags: vec!["stable".to_string(), "beta".to_string()],
+            vars: HashMap::from([("product".to_string(), "Acme".to_string())]),
+        };
+        let mut output = Vec::new();
+        assert_eq!(vault.export_web_bundle(&mut output, &options)?, 2);
+        let json = String::from_utf8(output)?;
+        assert!(json.starts_with(
+            r#"{"prompts":{"farewell":"Bye.","greeting":"Hello {{name}}, welcome to Acme!"},"#
+        ));
+        assert!(!json.contains('\n'));
+
+        let mut bundle: WebBundle = serde_json::from_str(&json)?;
+        assert!(bundle.verify()?);
+        bundle.prompts.insert("draft".to_string(), "Not released.".to_string());
+        assert!(!bundle.verify()?);
+
+        let latest = vault.web_bundle(&WebBundleOptions::default())?;
+        assert_eq!(latest.prompts.len(), 3);
+        assert_eq!(latest.prompts["greeting"], "Hi {{name}}!");
+
+        Ok(())
+    }
+}
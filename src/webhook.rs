@@ -0,0 +1,218 @@
+//! Webhooks receiving vault events as JSON, e.g. to notify Slack when a prompt is
+//! tagged `release`. They are configured in `~/.promptpro/config.toml`:
+//!
+//! ```toml
+//! [[webhooks]]
+//! url = "https://hooks.slack.com/services/T000/B000/XXXX"
+//! events = ["tagged"]   # every event when left out
+//! tags = ["release"]    # only these tags, for tagged and untagged events
+//! ```
+//!
+//! or for one command with `--webhook URL`. Every matching event is posted as
+//! `{"event":"tagged","key":..,"tag":..,"version":..,"text":..,"timestamp":..}`;
+//! `text` describes the change in a sentence, which Slack shows as the message.
+
+use crate::events::VaultEvent;
+use crate::network;
+use crate::profile::ProfileConfig;
+use crate::storage::PromptVault;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Names of the events a webhook can subscribe to
+pub const EVENT_NAMES: &[&str] =
+    &["prompt_added", "version_created", "tagged", "untagged", "deleted"];
+
+/// How long a webhook may take to answer
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One `[[webhooks]]` table
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: String,
+    /// Events to post, by name; every event when empty
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Only post tag events for these tags; every tag when empty
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// What a webhook receives
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a VaultEvent,
+    text: String,
+    timestamp: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// A webhook receiving every event
+    pub fn new(url: impl Into<String>) -> Self {
+        Webhook {
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether `event` is posted to this webhook
+    pub fn matches(&self, event: &VaultEvent) -> bool {
+        if !self.events.is_empty() && !self.events.iter().any(|name| name == event.name()) {
+            return false;
+        }
+        match event {
+            VaultEvent::Tagged { tag, .. } | VaultEvent::Untagged { tag, .. } => {
+                self.tags.is_empty() || self.tags.contains(tag)
+            }
+            _ => true,
+        }
+    }
+
+    /// Fail on event names that never match
+    pub(crate) fn check(&self) -> Result<()> {
+        match self.events.iter().find(|name| !EVENT_NAMES.contains(&name.as_str())) {
+            Some(name) => Err(anyhow::anyhow!(
+                "Unknown event '{}' for webhook {}; expected one of {}",
+                name,
+                self.url,
+                EVENT_NAMES.join(", ")
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The webhooks of the config file
+pub fn configured_webhooks() -> Result<Vec<Webhook>> {
+    Ok(ProfileConfig::load(&ProfileConfig::default_path()?)?.webhooks)
+}
+
+/// Posts the events of the vaults it is attached to, in the background
+#[derive(Clone, Default)]
+pub struct WebhookSender {
+    hooks: Arc<Vec<Webhook>>,
+    /// Posts not known to be finished
+    pending: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl WebhookSender {
+    pub fn new(hooks: Vec<Webhook>) -> Self {
+        WebhookSender {
+            hooks: Arc::new(hooks),
+            pending: Arc::default(),
+        }
+    }
+
+    /// Post the events of `vault` to the matching webhooks.
+    ///
+    /// Posts are spawned on the Tokio runtime of the thread making the change; changes
+    /// made outside of one are not posted. A failing webhook is reported on stderr and
+    /// does not fail the change.
+    pub fn attach(&self, vault: &PromptVault) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        let sender = self.clone();
+        vault.on_event(move |event| sender.send(event));
+    }
+
+    fn send(&self, event: &VaultEvent) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            eprintln!("⚠️ Webhooks not notified, no async runtime: {}", event);
+            return;
+        };
+        let payload = Payload {
+            event,
+            text: event.to_string(),
+            timestamp: Utc::now(),
+        };
+        let Ok(body) = serde_json::to_vec(&payload) else {
+            return;
+        };
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|post| !post.is_finished());
+        for hook in self.hooks.iter().filter(|hook| hook.matches(event)) {
+            let (url, body) = (hook.url.clone(), body.clone());
+            pending.push(runtime.spawn(async move {
+                if let Err(e) = post(&url, body).await {
+                    eprintln!("⚠️ Webhook {} failed: {:#}", url, e);
+                }
+            }));
+        }
+    }
+
+    /// Wait for the posts in flight, e.g. before the process exits
+    pub async fn flush(&self) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        for post in pending {
+            let _ = post.await;
+        }
+    }
+}
+
+async fn post(url: &str, body: Vec<u8>) -> Result<()> {
+    let request = reqwest::Client::new()
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .timeout(TIMEOUT)
+        .body(body);
+    let response = network::send(&format!("Notifying webhook {}", url), request).await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("answered {}", response.status()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post as route_post;
+    use axum::Json;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_webhooks() -> Result<()> {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let router = axum::Router::new().route(
+            "/hook",
+            route_post(move |Json(body): Json<serde_json::Value>| async move {
+                sink.lock().unwrap().push(body);
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/hook", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let config = ProfileConfig::parse(&format!(
+            "[[webhooks]]\nurl = \"{}\"\nevents = [\"tagged\"]\ntags = [\"release\"]",
+            url
+        ))?;
+        assert!(ProfileConfig::parse("[[webhooks]]\nurl = \"x\"\nevents = [\"tag\"]").is_err());
+
+        let dir = tempdir()?;
+        let vault = PromptVault::open(dir.path())?;
+        let sender = WebhookSender::new(config.webhooks);
+        sender.attach(&vault);
+        vault.add("writer", "v1")?;
+        vault.update("writer", "v2", None)?;
+        vault.tag("writer", "staging", 1)?;
+        vault.tag("writer", "release", 2)?;
+        sender.flush().await;
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0]["event"], "tagged");
+        assert_eq!(received[0]["key"], "writer");
+        assert_eq!(received[0]["version"], 2);
+        assert_eq!(received[0]["text"], "Prompt 'writer' v2 was tagged 'release'");
+
+        Ok(())
+    }
+}